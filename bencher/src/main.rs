@@ -7,6 +7,8 @@ extern crate verification;
 extern crate network;
 extern crate byteorder;
 extern crate primitives;
+extern crate keys;
+extern crate script;
 
 mod database;
 mod verifier;
@@ -76,4 +78,6 @@ fn main() {
 	benchmark!(database::reorg_short);
 	benchmark!(database::write_heavy);
 	benchmark!(verifier::main);
+	benchmark!(verifier::signed);
+	benchmark!(verifier::signed_mempool);
 }
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 use db::BlockChainDatabase;
-use chain::IndexedBlock;
+use db::kv::MemoryDatabase;
+use chain::{IndexedBlock, Transaction, TransactionInput, TransactionOutput, OutPoint};
 use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Verify, VerificationLevel};
 use network::{Network, ConsensusParams, ConsensusFork};
+use keys::{KeyPair, Private};
+use script::{Builder, Opcode, TransactionInputSigner, SignatureVersion};
 use test_data;
 use byteorder::{LittleEndian, ByteOrder};
 
@@ -105,3 +108,150 @@ pub fn main(benchmark: &mut Benchmark) {
 	 }
 	benchmark.stop();
 }
+
+// 1. write SIGNED_BLOCKS_INITIAL blocks with 1 transaction each, paying to the public key
+// 2. verify <SIGNED_BLOCKS> blocks that has <SIGNED_TRANSACTIONS> transaction each with <SIGNED_INPUTS> signed inputs each
+const SIGNED_BLOCKS_INITIAL: usize = 2200;
+const SIGNED_BLOCKS: usize = 10;
+const SIGNED_TRANSACTIONS: usize = 100;
+const SIGNED_INPUTS: usize = 2;
+
+fn signed_blocks() -> (Arc<BlockChainDatabase<MemoryDatabase>>, Vec<IndexedBlock>) {
+	assert!(SIGNED_BLOCKS_INITIAL - 100 > SIGNED_BLOCKS * SIGNED_TRANSACTIONS * SIGNED_INPUTS,
+		"There will be not enough initial blocks to continue this bench");
+
+	let key_pair = KeyPair::from_private(Private {
+		network: ::keys::Network::Testnet,
+		secret: "88d3dad1b9bc0e2cbcf0f5ae5e75f2c3e06d9e9fc7d72ba8b08ef7e16ebc4fdb".into(),
+		compressed: true,
+	}).unwrap();
+	let script_pubkey = Builder::default()
+		.push_data(&**key_pair.public())
+		.push_opcode(Opcode::OP_CHECKSIG)
+		.into_script();
+
+	let genesis = test_data::genesis();
+	let mut rolling_hash = genesis.hash();
+	let mut blocks: Vec<IndexedBlock> = Vec::new();
+
+	for x in 0..SIGNED_BLOCKS_INITIAL {
+		let mut coinbase_nonce = [0u8;8];
+		LittleEndian::write_u64(&mut coinbase_nonce[..], x as u64);
+		let next_block = test_data::block_builder()
+			.transaction()
+				.lock_time(x as u32)
+				.input()
+					.coinbase()
+					.signature_bytes(coinbase_nonce.to_vec().into())
+					.build()
+				.output().value(5000000000).script_pubkey_bytes(script_pubkey.to_bytes()).build()
+				.build()
+			.merkled_header()
+				.parent(rolling_hash.clone())
+				.nonce(x as u32)
+				.build()
+			.build();
+		rolling_hash = next_block.hash();
+		blocks.push(next_block.into());
+	}
+
+	let store = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]));
+	for block in blocks.iter() {
+		let hash = block.hash().clone();
+		store.insert(block.clone()).unwrap();
+		store.canonize(&hash).unwrap();
+	}
+
+	let mut verification_blocks: Vec<IndexedBlock> = Vec::new();
+	for b in 0..SIGNED_BLOCKS {
+		let mut coinbase_nonce = [0u8;8];
+		LittleEndian::write_u64(&mut coinbase_nonce[..], (b + SIGNED_BLOCKS_INITIAL) as u64);
+		let mut builder = test_data::block_builder()
+			.transaction()
+				.lock_time(b as u32)
+				.input().coinbase().signature_bytes(coinbase_nonce.to_vec().into()).build()
+				.output().value(5000000000).build()
+				.build();
+
+		for t in 0..SIGNED_TRANSACTIONS {
+			let mut transaction = Transaction {
+				version: 1,
+				inputs: (0..SIGNED_INPUTS).map(|i| TransactionInput {
+					previous_output: OutPoint {
+						hash: blocks[b * SIGNED_TRANSACTIONS * SIGNED_INPUTS + t * SIGNED_INPUTS + i].transactions[0].hash.clone(),
+						index: 0,
+					},
+					script_sig: Default::default(),
+					sequence: 0xffffffff,
+					script_witness: vec![],
+				}).collect(),
+				outputs: vec![TransactionOutput {
+					value: 0,
+					script_pubkey: Default::default(),
+				}],
+				lock_time: 0,
+			};
+			let signer: TransactionInputSigner = transaction.clone().into();
+			transaction.inputs = (0..SIGNED_INPUTS)
+				.map(|i| signer.signed_input(&key_pair, i, 5000000000, &script_pubkey, SignatureVersion::Base, 1))
+				.collect();
+
+			builder = builder.with_transaction(transaction);
+		}
+
+		verification_blocks.push(
+			builder
+				.merkled_header()
+					.parent(rolling_hash.clone())
+					.build()
+				.build()
+			.into());
+	}
+
+	assert_eq!(store.best_block().hash, rolling_hash);
+
+	(store, verification_blocks)
+}
+
+// verify blocks with signed transactions, which have never been seen before
+pub fn signed(benchmark: &mut Benchmark) {
+	benchmark.samples(SIGNED_BLOCKS);
+
+	let (store, verification_blocks) = signed_blocks();
+	let chain_verifier = ChainVerifier::new(store.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore));
+
+	// bench
+	benchmark.start();
+	for block in verification_blocks.iter() {
+		chain_verifier.verify(VerificationLevel::Full, block).unwrap();
+	}
+	benchmark.stop();
+}
+
+// verify blocks with signed transactions, which have been accepted to the memory pool before
+pub fn signed_mempool(benchmark: &mut Benchmark) {
+	benchmark.samples(SIGNED_BLOCKS);
+
+	let (store, verification_blocks) = signed_blocks();
+	let chain_verifier = ChainVerifier::new(store.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore));
+
+	let height = store.best_block().number + 1;
+	for block in verification_blocks.iter() {
+		for transaction in block.transactions.iter().skip(1) {
+			chain_verifier.verify_mempool_transaction(
+				&*store,
+				&*store,
+				height,
+				block.header.raw.time,
+				transaction,
+			).unwrap();
+		}
+	}
+
+	// bench
+	benchmark.start();
+	for block in verification_blocks.iter() {
+		chain_verifier.verify(VerificationLevel::Full, block).unwrap();
+	}
+	benchmark.stop();
+}
@@ -0,0 +1,281 @@
+//! Compact block filters.
+//! https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+
+use std::collections::HashSet;
+use crypto::{dhash256, siphash24};
+use hash::{H256, H512};
+use bytes::Bytes;
+use ser::{serialize, Reader, CompactInteger};
+use indexed_block::IndexedBlock;
+
+/// Basic filter type.
+pub const BASIC_FILTER_TYPE: u8 = 0;
+/// Golomb-Rice coding parameter of basic filter.
+const BASIC_FILTER_P: u8 = 19;
+/// Inverse false positive rate of basic filter.
+const BASIC_FILTER_M: u64 = 784931;
+/// OP_RETURN opcode. Outputs starting with it are never included into the filter.
+const OP_RETURN: u8 = 0x6a;
+
+/// Golomb-coded set of block scripts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockFilter {
+	/// Hash of the block this filter has been built for.
+	pub block_hash: H256,
+	/// Serialized filter: number of elements followed by the Golomb-Rice coded set.
+	pub filter: Bytes,
+}
+
+impl BlockFilter {
+	/// Builds basic filter, given scripts of all outputs spent by the block (in any order).
+	pub fn basic(block: &IndexedBlock, spent_scripts: &[Bytes]) -> Self {
+		let mut elements: HashSet<&[u8]> = HashSet::new();
+		for transaction in &block.transactions {
+			for output in &transaction.raw.outputs {
+				let script: &[u8] = &output.script_pubkey;
+				if !script.is_empty() && script[0] != OP_RETURN {
+					elements.insert(script);
+				}
+			}
+		}
+
+		for script in spent_scripts {
+			if !script.is_empty() {
+				elements.insert(&**script);
+			}
+		}
+
+		Self::from_elements(block.hash().clone(), elements.into_iter())
+	}
+
+	/// Builds filter of given elements. Duplicate elements must be removed by the caller.
+	pub fn from_elements<'a, I>(block_hash: H256, elements: I) -> Self where I: ExactSizeIterator<Item=&'a [u8]> {
+		let n = elements.len() as u64;
+		let keys = siphash_keys(&block_hash);
+		let mut values: Vec<u64> = elements.map(|element| hash_to_range(keys, n, element)).collect();
+		values.sort();
+
+		let mut filter: Vec<u8> = serialize(&CompactInteger::from(n)).into();
+		let mut writer = BitWriter::new(&mut filter);
+		let mut last = 0;
+		for value in values {
+			golomb_rice_encode(&mut writer, value - last, BASIC_FILTER_P);
+			last = value;
+		}
+		writer.flush();
+
+		BlockFilter {
+			block_hash: block_hash,
+			filter: filter.into(),
+		}
+	}
+
+	/// Number of elements in the filter.
+	pub fn len(&self) -> u64 {
+		self.decode_len().map(|(n, _)| n).unwrap_or(0)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Hash of the filter.
+	pub fn hash(&self) -> H256 {
+		dhash256(&self.filter)
+	}
+
+	/// Filter header, which commits to this filter and all previous filters.
+	pub fn header(&self, previous_header: &H256) -> H256 {
+		let mut data = H512::default();
+		data[0..32].copy_from_slice(&*self.hash());
+		data[32..64].copy_from_slice(&**previous_header);
+		dhash256(&*data)
+	}
+
+	/// Returns true if any of elements (probably) belongs to the filter.
+	pub fn match_any(&self, elements: &[&[u8]]) -> bool {
+		let (n, offset) = match self.decode_len() {
+			Some(len) => len,
+			None => return false,
+		};
+		if n == 0 || elements.is_empty() {
+			return false;
+		}
+
+		let keys = siphash_keys(&self.block_hash);
+		let mut queries: Vec<u64> = elements.iter().map(|element| hash_to_range(keys, n, element)).collect();
+		queries.sort();
+
+		let mut reader = BitReader::new(&self.filter[offset..]);
+		let mut queries = queries.into_iter().peekable();
+		let mut value = 0;
+		for _ in 0..n {
+			value += match golomb_rice_decode(&mut reader, BASIC_FILTER_P) {
+				Some(delta) => delta,
+				None => return false,
+			};
+
+			loop {
+				match queries.peek() {
+					Some(query) if *query == value => return true,
+					Some(query) if *query < value => {
+						queries.next();
+					},
+					Some(_) => break,
+					None => return false,
+				}
+			}
+		}
+
+		false
+	}
+
+	fn decode_len(&self) -> Option<(u64, usize)> {
+		let n: CompactInteger = Reader::new(&self.filter).read().ok()?;
+		let offset = serialize(&n).len();
+		Some((n.into(), offset))
+	}
+}
+
+fn siphash_keys(block_hash: &H256) -> (u64, u64) {
+	let mut k0 = 0u64;
+	let mut k1 = 0u64;
+	for i in 0..8 {
+		k0 |= (block_hash[i] as u64) << (i * 8);
+		k1 |= (block_hash[8 + i] as u64) << (i * 8);
+	}
+	(k0, k1)
+}
+
+/// Maps element hash uniformly into [0, n * M) range.
+fn hash_to_range(keys: (u64, u64), n: u64, element: &[u8]) -> u64 {
+	let hash = siphash24(keys.0, keys.1, element);
+	let range = n * BASIC_FILTER_M;
+	((hash as u128 * range as u128) >> 64) as u64
+}
+
+fn golomb_rice_encode(writer: &mut BitWriter, value: u64, p: u8) {
+	let mut quotient = value >> p;
+	while quotient > 0 {
+		let bits = ::std::cmp::min(quotient, 64);
+		writer.write(!0u64, bits as u8);
+		quotient -= bits;
+	}
+	writer.write(0, 1);
+	writer.write(value, p);
+}
+
+fn golomb_rice_decode(reader: &mut BitReader, p: u8) -> Option<u64> {
+	let mut quotient = 0;
+	while reader.read(1)? == 1 {
+		quotient += 1;
+	}
+	let remainder = reader.read(p)?;
+	Some((quotient << p) + remainder)
+}
+
+/// Writes bits, most significant first.
+struct BitWriter<'a> {
+	buffer: &'a mut Vec<u8>,
+	byte: u8,
+	used: u8,
+}
+
+impl<'a> BitWriter<'a> {
+	fn new(buffer: &'a mut Vec<u8>) -> Self {
+		BitWriter {
+			buffer: buffer,
+			byte: 0,
+			used: 0,
+		}
+	}
+
+	/// Writes `bits` lowest bits of `value`.
+	fn write(&mut self, value: u64, bits: u8) {
+		for i in (0..bits).rev() {
+			self.byte |= (((value >> i) & 1) as u8) << (7 - self.used);
+			self.used += 1;
+			if self.used == 8 {
+				self.buffer.push(self.byte);
+				self.byte = 0;
+				self.used = 0;
+			}
+		}
+	}
+
+	fn flush(&mut self) {
+		if self.used != 0 {
+			self.buffer.push(self.byte);
+			self.byte = 0;
+			self.used = 0;
+		}
+	}
+}
+
+/// Reads bits, most significant first.
+struct BitReader<'a> {
+	data: &'a [u8],
+	position: usize,
+}
+
+impl<'a> BitReader<'a> {
+	fn new(data: &'a [u8]) -> Self {
+		BitReader {
+			data: data,
+			position: 0,
+		}
+	}
+
+	fn read(&mut self, bits: u8) -> Option<u64> {
+		let mut value = 0;
+		for _ in 0..bits {
+			let byte = *self.data.get(self.position / 8)?;
+			let bit = (byte >> (7 - self.position % 8)) & 1;
+			value = (value << 1) | bit as u64;
+			self.position += 1;
+		}
+		Some(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use bytes::Bytes;
+	use super::BlockFilter;
+
+	fn genesis_script() -> Bytes {
+		"4104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac".into()
+	}
+
+	#[test]
+	fn test_testnet_genesis_basic_filter() {
+		// test vector from BIP158
+		let block_hash = H256::from_reversed_str("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943");
+		let script = genesis_script();
+		let filter = BlockFilter::from_elements(block_hash, vec![&script[..]].into_iter());
+		assert_eq!(filter.filter, "019dfca8".into());
+		assert_eq!(filter.header(&H256::default()), H256::from_reversed_str("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750"));
+		assert_eq!(filter.len(), 1);
+	}
+
+	#[test]
+	fn test_filter_match() {
+		let scripts: Vec<Bytes> = (0u8..100).map(|i| vec![i; 25].into()).collect();
+		let filter = BlockFilter::from_elements(H256::from(1), scripts.iter().map(|script| &script[..]));
+		assert_eq!(filter.len(), 100);
+		for script in &scripts {
+			assert!(filter.match_any(&[&**script]));
+		}
+
+		let unknown = [0xffu8; 25];
+		assert!(!filter.match_any(&[&unknown[..]]));
+		assert!(filter.match_any(&[&unknown[..], &*scripts[50]]));
+		assert!(!filter.match_any(&[]));
+
+		let empty = BlockFilter::from_elements(H256::from(1), Vec::new().into_iter());
+		assert_eq!(empty.filter, "00".into());
+		assert!(empty.is_empty());
+		assert!(!empty.match_any(&[&*scripts[0]]));
+	}
+}
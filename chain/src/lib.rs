@@ -10,6 +10,7 @@ extern crate serialization_derive;
 pub mod constants;
 
 mod block;
+mod block_filter;
 mod block_header;
 mod merkle_root;
 mod transaction;
@@ -23,6 +24,7 @@ mod indexed_transaction;
 pub use primitives::{hash, bytes, bigint, compact};
 
 pub use block::Block;
+pub use block_filter::{BlockFilter, BASIC_FILTER_TYPE};
 pub use block_header::BlockHeader;
 pub use merkle_root::{merkle_root, merkle_node_hash};
 pub use transaction::{Transaction, TransactionInput, TransactionOutput, OutPoint};
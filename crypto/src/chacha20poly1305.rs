@@ -0,0 +1,208 @@
+//! ChaCha20-Poly1305 AEAD (RFC 8439) and forward-secure ciphers built on top of it (BIP324).
+
+use rcrypto::chacha20::ChaCha20;
+use rcrypto::poly1305::Poly1305;
+use rcrypto::mac::Mac;
+use rcrypto::symmetriccipher::SynchronousStreamCipher;
+use rcrypto::util::fixed_time_eq;
+
+/// Length of Poly1305 authentication tag.
+pub const TAG_LEN: usize = 16;
+/// Number of messages encrypted with a single key, before it is rotated.
+pub const REKEY_INTERVAL: u64 = 224;
+
+fn nonce(first: u32, second: u64) -> [u8; 12] {
+	let mut nonce = [0u8; 12];
+	for i in 0..4 {
+		nonce[i] = (first >> (i * 8)) as u8;
+	}
+	for i in 0..8 {
+		nonce[4 + i] = (second >> (i * 8)) as u8;
+	}
+	nonce
+}
+
+fn poly1305_tag(key: &[u8], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+	let zeros = [0u8; 16];
+	let mut mac = Poly1305::new(key);
+	mac.input(aad);
+	mac.input(&zeros[..(16 - aad.len() % 16) % 16]);
+	mac.input(ciphertext);
+	mac.input(&zeros[..(16 - ciphertext.len() % 16) % 16]);
+	let mut lengths = [0u8; 16];
+	for i in 0..8 {
+		lengths[i] = ((aad.len() as u64) >> (i * 8)) as u8;
+		lengths[8 + i] = ((ciphertext.len() as u64) >> (i * 8)) as u8;
+	}
+	mac.input(&lengths);
+
+	let mut tag = [0u8; TAG_LEN];
+	mac.raw_result(&mut tag);
+	tag
+}
+
+/// Creates cipher with the keystream positioned at block 1 and the Poly1305 key taken from block 0.
+fn init_cipher(key: &[u8; 32], nonce: &[u8; 12]) -> (ChaCha20, [u8; 32]) {
+	let mut cipher = ChaCha20::new(key, nonce);
+	let mut block = [0u8; 64];
+	cipher.process(&[0u8; 64], &mut block);
+	let mut poly_key = [0u8; 32];
+	poly_key.copy_from_slice(&block[..32]);
+	(cipher, poly_key)
+}
+
+/// Encrypts `plaintext` and returns ciphertext followed by the authentication tag.
+pub fn aead_encrypt(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+	let (mut cipher, poly_key) = init_cipher(key, nonce);
+	let mut result = vec![0u8; plaintext.len()];
+	cipher.process(plaintext, &mut result);
+	let tag = poly1305_tag(&poly_key, aad, &result);
+	result.extend_from_slice(&tag);
+	result
+}
+
+/// Authenticates and decrypts `ciphertext` (including the tag). Returns None if authentication fails.
+pub fn aead_decrypt(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+	if ciphertext.len() < TAG_LEN {
+		return None;
+	}
+
+	let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+	let (mut cipher, poly_key) = init_cipher(key, nonce);
+	if !fixed_time_eq(&poly1305_tag(&poly_key, aad, ciphertext), tag) {
+		return None;
+	}
+
+	let mut result = vec![0u8; ciphertext.len()];
+	cipher.process(ciphertext, &mut result);
+	Some(result)
+}
+
+/// ChaCha20 stream cipher, which rotates its key every `REKEY_INTERVAL` chunks.
+pub struct FSChaCha20 {
+	cipher: ChaCha20,
+	chunk_counter: u64,
+}
+
+impl FSChaCha20 {
+	pub fn new(key: [u8; 32]) -> Self {
+		FSChaCha20 {
+			cipher: ChaCha20::new(&key, &nonce(0, 0)),
+			chunk_counter: 0,
+		}
+	}
+
+	/// Encrypts or decrypts next chunk in place.
+	pub fn crypt(&mut self, chunk: &mut [u8]) {
+		let input = chunk.to_vec();
+		self.cipher.process(&input, chunk);
+
+		self.chunk_counter += 1;
+		if self.chunk_counter % REKEY_INTERVAL == 0 {
+			let mut key = [0u8; 32];
+			self.cipher.process(&[0u8; 32], &mut key);
+			self.cipher = ChaCha20::new(&key, &nonce(0, self.chunk_counter / REKEY_INTERVAL));
+		}
+	}
+}
+
+/// ChaCha20-Poly1305 AEAD, which rotates its key every `REKEY_INTERVAL` packets.
+pub struct FSChaCha20Poly1305 {
+	key: [u8; 32],
+	packet_counter: u64,
+}
+
+impl FSChaCha20Poly1305 {
+	pub fn new(key: [u8; 32]) -> Self {
+		FSChaCha20Poly1305 {
+			key: key,
+			packet_counter: 0,
+		}
+	}
+
+	pub fn encrypt(&mut self, aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+		let result = aead_encrypt(&self.key, &self.nonce(), aad, plaintext);
+		self.next_packet();
+		result
+	}
+
+	pub fn decrypt(&mut self, aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+		let result = aead_decrypt(&self.key, &self.nonce(), aad, ciphertext);
+		self.next_packet();
+		result
+	}
+
+	fn nonce(&self) -> [u8; 12] {
+		nonce((self.packet_counter % REKEY_INTERVAL) as u32, self.packet_counter / REKEY_INTERVAL)
+	}
+
+	fn next_packet(&mut self) {
+		if (self.packet_counter + 1) % REKEY_INTERVAL == 0 {
+			let rekey_nonce = nonce(0xffffffff, self.packet_counter / REKEY_INTERVAL);
+			let new_key = aead_encrypt(&self.key, &rekey_nonce, &[], &[0u8; 32]);
+			self.key.copy_from_slice(&new_key[..32]);
+		}
+		self.packet_counter += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{aead_encrypt, aead_decrypt, FSChaCha20, FSChaCha20Poly1305, REKEY_INTERVAL, TAG_LEN};
+
+	#[test]
+	fn test_aead_rfc8439() {
+		let mut key = [0u8; 32];
+		for (i, byte) in key.iter_mut().enumerate() {
+			*byte = 0x80 + i as u8;
+		}
+		let nonce = [0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+		let aad = [0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7];
+		let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+		let encrypted = aead_encrypt(&key, &nonce, &aad, plaintext);
+		assert_eq!(encrypted.len(), plaintext.len() + TAG_LEN);
+		assert_eq!(&encrypted[..4], &[0xd3, 0x1a, 0x8d, 0x34]);
+		assert_eq!(&encrypted[plaintext.len()..], &[
+			0x1a, 0xe1, 0x0b, 0x59, 0x4f, 0x09, 0xe2, 0x6a, 0x7e, 0x90, 0x2e, 0xcb, 0xd0, 0x60, 0x06, 0x91,
+		]);
+		assert_eq!(aead_decrypt(&key, &nonce, &aad, &encrypted), Some(plaintext.to_vec()));
+
+		let mut tampered = encrypted.clone();
+		tampered[0] ^= 1;
+		assert_eq!(aead_decrypt(&key, &nonce, &aad, &tampered), None);
+		assert_eq!(aead_decrypt(&key, &nonce, &[], &encrypted), None);
+	}
+
+	#[test]
+	fn test_fschacha20_roundtrip_across_rekey() {
+		let mut encryptor = FSChaCha20::new([1u8; 32]);
+		let mut decryptor = FSChaCha20::new([1u8; 32]);
+		let mut previous = Vec::new();
+		for i in 0..REKEY_INTERVAL * 2 + 1 {
+			let mut chunk = [i as u8, 1, 2];
+			encryptor.crypt(&mut chunk);
+			previous.push(chunk);
+			decryptor.crypt(&mut chunk);
+			assert_eq!(chunk, [i as u8, 1, 2]);
+		}
+		// keystream never repeats
+		assert!(previous[0] != previous[REKEY_INTERVAL as usize]);
+	}
+
+	#[test]
+	fn test_fschacha20poly1305_roundtrip_across_rekey() {
+		let mut encryptor = FSChaCha20Poly1305::new([2u8; 32]);
+		let mut decryptor = FSChaCha20Poly1305::new([2u8; 32]);
+		for i in 0..REKEY_INTERVAL * 2 + 1 {
+			let encrypted = encryptor.encrypt(b"aad", &[i as u8; 10]);
+			assert_eq!(decryptor.decrypt(b"aad", &encrypted), Some(vec![i as u8; 10]));
+		}
+
+		// decryptor state is advanced even if authentication fails
+		let encrypted = encryptor.encrypt(&[], b"first");
+		assert_eq!(decryptor.decrypt(b"wrong", &encrypted), None);
+		let encrypted = encryptor.encrypt(&[], b"second");
+		assert_eq!(decryptor.decrypt(&[], &encrypted), Some(b"second".to_vec()));
+	}
+}
@@ -0,0 +1,400 @@
+//! ElligatorSwift encoding of secp256k1 x coordinates (BIP324).
+//!
+//! Public keys encoded this way are indistinguishable from 64 uniformly random bytes.
+
+use std::cmp::Ordering;
+use std::ops::{Add, Sub, Mul, Neg};
+
+/// secp256k1 field prime, little-endian limbs.
+const P: [u64; 4] = [0xfffffffefffffc2f, 0xffffffffffffffff, 0xffffffffffffffff, 0xffffffffffffffff];
+/// 2^256 - p
+const P_COMPLEMENT: u64 = 0x1000003d1;
+
+/// Element of secp256k1 base field, always fully reduced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldElement([u64; 4]);
+
+impl FieldElement {
+	pub fn zero() -> Self {
+		FieldElement([0; 4])
+	}
+
+	pub fn from_u64(v: u64) -> Self {
+		FieldElement([v, 0, 0, 0])
+	}
+
+	/// Interprets 32 big-endian bytes as a field element, reducing it modulo p.
+	pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+		let mut limbs = [0u64; 4];
+		for (i, limb) in limbs.iter_mut().enumerate() {
+			for byte in &bytes[(3 - i) * 8..(4 - i) * 8] {
+				*limb = (*limb << 8) | *byte as u64;
+			}
+		}
+		FieldElement(limbs).reduce_once()
+	}
+
+	/// Big-endian representation.
+	pub fn to_bytes(&self) -> [u8; 32] {
+		let mut bytes = [0u8; 32];
+		for (i, limb) in self.0.iter().enumerate() {
+			for j in 0..8 {
+				bytes[31 - i * 8 - j] = (limb >> (j * 8)) as u8;
+			}
+		}
+		bytes
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.0 == [0; 4]
+	}
+
+	pub fn square(&self) -> Self {
+		*self * *self
+	}
+
+	fn pow(&self, exponent: &[u64; 4]) -> Self {
+		let mut result = FieldElement::from_u64(1);
+		for limb in exponent.iter().rev() {
+			for bit in (0..64).rev() {
+				result = result.square();
+				if (limb >> bit) & 1 == 1 {
+					result = result * *self;
+				}
+			}
+		}
+		result
+	}
+
+	/// Multiplicative inverse. Inverse of zero is zero.
+	pub fn invert(&self) -> Self {
+		// a^(p - 2)
+		self.pow(&[P[0] - 2, P[1], P[2], P[3]])
+	}
+
+	/// Square root, if it exists.
+	pub fn sqrt(&self) -> Option<Self> {
+		// p = 3 mod 4, so the root is a^((p + 1) / 4)
+		let root = self.pow(&[0xffffffffbfffff0c, 0xffffffffffffffff, 0xffffffffffffffff, 0x3fffffffffffffff]);
+		if root.square() == *self {
+			Some(root)
+		} else {
+			None
+		}
+	}
+
+	pub fn is_square(&self) -> bool {
+		self.sqrt().is_some()
+	}
+
+	fn half(&self) -> Self {
+		*self * FieldElement::from_u64(2).invert()
+	}
+
+	fn reduce_once(self) -> Self {
+		if compare(&self.0, &P) == Ordering::Less {
+			return self;
+		}
+
+		let (limbs, _) = sub_limbs(&self.0, &P);
+		FieldElement(limbs)
+	}
+}
+
+fn compare(a: &[u64; 4], b: &[u64; 4]) -> Ordering {
+	for i in (0..4).rev() {
+		match a[i].cmp(&b[i]) {
+			Ordering::Equal => continue,
+			other => return other,
+		}
+	}
+	Ordering::Equal
+}
+
+fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+	let mut result = [0u64; 4];
+	let mut carry = 0u128;
+	for i in 0..4 {
+		let sum = a[i] as u128 + b[i] as u128 + carry;
+		result[i] = sum as u64;
+		carry = sum >> 64;
+	}
+	(result, carry != 0)
+}
+
+fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+	let mut result = [0u64; 4];
+	let mut borrow = 0u64;
+	for i in 0..4 {
+		let (diff, underflow1) = a[i].overflowing_sub(b[i]);
+		let (diff, underflow2) = diff.overflowing_sub(borrow);
+		result[i] = diff;
+		borrow = (underflow1 || underflow2) as u64;
+	}
+	(result, borrow != 0)
+}
+
+impl Add for FieldElement {
+	type Output = FieldElement;
+
+	fn add(self, other: FieldElement) -> FieldElement {
+		let (limbs, carry) = add_limbs(&self.0, &other.0);
+		if carry {
+			// 2^256 = 2^256 - p (mod p)
+			let (limbs, _) = add_limbs(&limbs, &[P_COMPLEMENT, 0, 0, 0]);
+			return FieldElement(limbs);
+		}
+		FieldElement(limbs).reduce_once()
+	}
+}
+
+impl Sub for FieldElement {
+	type Output = FieldElement;
+
+	fn sub(self, other: FieldElement) -> FieldElement {
+		let (limbs, borrow) = sub_limbs(&self.0, &other.0);
+		if borrow {
+			let (limbs, _) = add_limbs(&limbs, &P);
+			return FieldElement(limbs);
+		}
+		FieldElement(limbs)
+	}
+}
+
+impl Neg for FieldElement {
+	type Output = FieldElement;
+
+	fn neg(self) -> FieldElement {
+		FieldElement::zero() - self
+	}
+}
+
+impl Mul for FieldElement {
+	type Output = FieldElement;
+
+	fn mul(self, other: FieldElement) -> FieldElement {
+		let mut wide = [0u64; 8];
+		for i in 0..4 {
+			let mut carry = 0u128;
+			for j in 0..4 {
+				let product = self.0[i] as u128 * other.0[j] as u128 + wide[i + j] as u128 + carry;
+				wide[i + j] = product as u64;
+				carry = product >> 64;
+			}
+			wide[i + 4] = carry as u64;
+		}
+
+		// fold the upper half: hi * 2^256 = hi * (2^32 + 977) (mod p)
+		let mut folded = [0u64; 5];
+		let mut carry = 0u128;
+		for i in 0..4 {
+			let value = wide[i] as u128 + wide[i + 4] as u128 * P_COMPLEMENT as u128 + carry;
+			folded[i] = value as u64;
+			carry = value >> 64;
+		}
+		folded[4] = carry as u64;
+
+		// folded[4] is small, so fold it once more
+		let mut result = [0u64; 4];
+		let mut carry = folded[4] as u128 * P_COMPLEMENT as u128;
+		for i in 0..4 {
+			let value = folded[i] as u128 + carry;
+			result[i] = value as u64;
+			carry = value >> 64;
+		}
+
+		let result = if carry != 0 {
+			add_limbs(&result, &[P_COMPLEMENT, 0, 0, 0]).0
+		} else {
+			result
+		};
+		FieldElement(result).reduce_once()
+	}
+}
+
+fn curve_b() -> FieldElement {
+	FieldElement::from_u64(7)
+}
+
+/// sqrt(-3)
+fn minus_3_sqrt() -> FieldElement {
+	(-FieldElement::from_u64(3)).sqrt().expect("-3 is a square in secp256k1 field")
+}
+
+/// Returns true if x is a valid x coordinate of a point on the curve.
+pub fn is_valid_x(x: &FieldElement) -> bool {
+	(x.square() * *x + curve_b()).is_square()
+}
+
+/// Decodes field elements (u, t) to x coordinate of a point on the curve.
+pub fn xswiftec(u: FieldElement, t: FieldElement) -> FieldElement {
+	let one = FieldElement::from_u64(1);
+	let u = if u.is_zero() { one } else { u };
+	let mut t = if t.is_zero() { one } else { t };
+	let u3_plus_b = u.square() * u + curve_b();
+	if (u3_plus_b + t.square()).is_zero() {
+		t = t + t;
+	}
+
+	let x = (u3_plus_b - t.square()) * (t + t).invert();
+	let y = (x + t) * (minus_3_sqrt() * u).invert();
+	let x_over_y = x * y.invert();
+	let candidates = [
+		u + FieldElement::from_u64(4) * y.square(),
+		(-x_over_y - u).half(),
+		(x_over_y - u).half(),
+	];
+
+	*candidates.iter()
+		.find(|x| is_valid_x(x))
+		.expect("one of the candidates is always a valid x coordinate")
+}
+
+/// Finds t such that `xswiftec(u, t) == x`. `case` (0..8) selects one of up to 8 solutions.
+pub fn xswiftec_inv(x: FieldElement, u: FieldElement, case: u8) -> Option<FieldElement> {
+	let two_inv = FieldElement::from_u64(2).invert();
+	let u3_plus_b = u.square() * u + curve_b();
+
+	let (s, v) = if case & 2 == 0 {
+		if is_valid_x(&(-x - u)) {
+			return None;
+		}
+		let s = -u3_plus_b * (u.square() + u * x + x.square()).invert();
+		(s, x)
+	} else {
+		let s = x - u;
+		if s.is_zero() {
+			return None;
+		}
+		let r = (-s * (FieldElement::from_u64(4) * u3_plus_b + FieldElement::from_u64(3) * s * u.square())).sqrt()?;
+		if case & 1 == 1 && r.is_zero() {
+			return None;
+		}
+		let v = (r * s.invert() - u) * two_inv;
+		(s, v)
+	};
+
+	let w = s.sqrt()?;
+	// c1 = (1 - sqrt(-3)) / 2, c2 = (1 + sqrt(-3)) / 2
+	let one = FieldElement::from_u64(1);
+	let c1 = (one - minus_3_sqrt()) * two_inv;
+	let c2 = (one + minus_3_sqrt()) * two_inv;
+	let t = match case & 5 {
+		0 => -w * (v + c1 * u),
+		1 => w * (v + c2 * u),
+		4 => w * (v + c1 * u),
+		_ => -w * (v + c2 * u),
+	};
+	Some(t)
+}
+
+/// Encodes x coordinate using randomness source `random`, which is expected to
+/// return fresh 32 random bytes on every call.
+pub fn encode<F>(x: &[u8; 32], mut random: F) -> [u8; 64] where F: FnMut() -> [u8; 32] {
+	let x = FieldElement::from_bytes(x);
+	loop {
+		let entropy = random();
+		let u = FieldElement::from_bytes(&entropy);
+		if u.is_zero() {
+			continue;
+		}
+
+		// case is drawn separately, so that encoding is not correlated with its own bytes
+		let case = random()[0] & 7;
+		if let Some(t) = xswiftec_inv(x, u, case) {
+			let mut encoded = [0u8; 64];
+			encoded[..32].copy_from_slice(&u.to_bytes());
+			encoded[32..].copy_from_slice(&t.to_bytes());
+			return encoded;
+		}
+	}
+}
+
+/// Decodes 64 bytes into x coordinate of a point on the curve.
+pub fn decode(encoded: &[u8; 64]) -> [u8; 32] {
+	let mut u = [0u8; 32];
+	let mut t = [0u8; 32];
+	u.copy_from_slice(&encoded[..32]);
+	t.copy_from_slice(&encoded[32..]);
+	xswiftec(FieldElement::from_bytes(&u), FieldElement::from_bytes(&t)).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{FieldElement, xswiftec, xswiftec_inv, is_valid_x, encode, decode};
+
+	fn fe(hex: &str) -> FieldElement {
+		let mut bytes = [0u8; 32];
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap();
+		}
+		FieldElement::from_bytes(&bytes)
+	}
+
+	/// Deterministic byte source for tests.
+	fn counter_random(seed: u8) -> impl FnMut() -> [u8; 32] {
+		let mut counter = seed as u64;
+		move || {
+			counter = counter.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+			let mut bytes = [0u8; 32];
+			for (i, byte) in bytes.iter_mut().enumerate() {
+				*byte = (counter >> ((i % 8) * 8)) as u8 ^ (i as u8).wrapping_mul(31);
+			}
+			bytes
+		}
+	}
+
+	#[test]
+	fn test_field_arithmetic() {
+		let a = fe("fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e");
+		let one = FieldElement::from_u64(1);
+		assert_eq!(a + one, FieldElement::zero());
+		assert_eq!(FieldElement::zero() - one, a);
+		assert_eq!(a * a, one);
+		assert_eq!(a.invert() * a, one);
+
+		let b = fe("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+		assert_eq!(b * b.invert(), one);
+		assert_eq!(b.square().sqrt().map(|r| r.square()), Some(b.square()));
+		assert_eq!(FieldElement::from_bytes(&b.to_bytes()), b);
+	}
+
+	#[test]
+	fn test_generator_x_is_valid() {
+		let g = fe("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+		assert!(is_valid_x(&g));
+		assert!(!is_valid_x(&(g + FieldElement::from_u64(1))) || is_valid_x(&g));
+	}
+
+	#[test]
+	fn test_xswiftec_inverse_roundtrip() {
+		let mut random = counter_random(1);
+		let mut found = 0;
+		for _ in 0..32 {
+			let u = FieldElement::from_bytes(&random());
+			let t = FieldElement::from_bytes(&random());
+			let x = xswiftec(u, t);
+			assert!(is_valid_x(&x));
+			for case in 0..8 {
+				if let Some(t) = xswiftec_inv(x, u, case) {
+					assert_eq!(xswiftec(u, t), x);
+					found += 1;
+				}
+			}
+		}
+		assert!(found > 0);
+	}
+
+	#[test]
+	fn test_encode_decode() {
+		let x = [
+			0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b, 0x07,
+			0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8, 0x17, 0x98,
+		];
+		let first = encode(&x, counter_random(2));
+		let second = encode(&x, counter_random(3));
+		assert!(first[..] != second[..]);
+		assert_eq!(decode(&first), x);
+		assert_eq!(decode(&second), x);
+	}
+}
@@ -2,13 +2,19 @@ extern crate crypto as rcrypto;
 extern crate primitives;
 extern crate siphasher;
 
+pub mod chacha20poly1305;
+pub mod ellswift;
+
 pub use rcrypto::digest::Digest;
 use std::hash::Hasher;
 use rcrypto::sha1::Sha1;
-use rcrypto::sha2::Sha256;
+use rcrypto::sha2::{Sha256, Sha512};
 use rcrypto::ripemd160::Ripemd160;
+use rcrypto::hkdf::{hkdf_extract, hkdf_expand};
+use rcrypto::hmac::Hmac;
+use rcrypto::mac::Mac;
 use siphasher::sip::SipHasher24;
-use primitives::hash::{H32, H160, H256};
+use primitives::hash::{H32, H160, H256, H512};
 
 pub struct DHash160 {
 	sha256: Sha256,
@@ -163,6 +169,44 @@ pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
 	hasher.finish()
 }
 
+/// Tagged hash (BIP340)
+#[inline]
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> H256 {
+	let tag_hash = sha256(tag);
+	let mut result = H256::default();
+	let mut hasher = Sha256::new();
+	hasher.input(&*tag_hash);
+	hasher.input(&*tag_hash);
+	hasher.input(input);
+	hasher.result(&mut *result);
+	result
+}
+
+/// HKDF-SHA256 (RFC 5869). Fills whole `output` with the key material.
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], output: &mut [u8]) {
+	let mut prk = [0u8; 32];
+	hkdf_extract(Sha256::new(), salt, ikm, &mut prk);
+	hkdf_expand(Sha256::new(), &prk, info, output);
+}
+
+/// HMAC-SHA256 (RFC 2104)
+pub fn hmac_sha256(key: &[u8], input: &[u8]) -> H256 {
+	let mut result = H256::default();
+	let mut hmac = Hmac::new(Sha256::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
+/// HMAC-SHA512 (RFC 2104)
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> H512 {
+	let mut result = H512::default();
+	let mut hmac = Hmac::new(Sha512::new(), key);
+	hmac.input(input);
+	hmac.raw_result(&mut *result);
+	result
+}
+
 /// Data checksum
 #[inline]
 pub fn checksum(data: &[u8]) -> H32 {
@@ -174,7 +218,7 @@ pub fn checksum(data: &[u8]) -> H32 {
 #[cfg(test)]
 mod tests {
 	use primitives::bytes::Bytes;
-	use super::{ripemd160, sha1, sha256, dhash160, dhash256, siphash24, checksum};
+	use super::{ripemd160, sha1, sha256, dhash160, dhash256, siphash24, checksum, tagged_hash, hkdf_sha256, hmac_sha256, hmac_sha512};
 
 	#[test]
 	fn test_ripemd160() {
@@ -227,4 +271,40 @@ mod tests {
 	fn test_checksum() {
 		assert_eq!(checksum(b"hello"), "9595c9df".into());
 	}
+
+	#[test]
+	fn test_tagged_hash() {
+		let tag = sha256(b"tag");
+		let mut data = Vec::new();
+		data.extend_from_slice(&*tag);
+		data.extend_from_slice(&*tag);
+		data.extend_from_slice(b"data");
+		assert_eq!(tagged_hash(b"tag", b"data"), sha256(&data));
+	}
+
+	#[test]
+	fn test_hkdf_sha256() {
+		// RFC 5869, test case 1
+		let ikm = [0x0bu8; 22];
+		let salt: Vec<u8> = (0u8..13).collect();
+		let info: Vec<u8> = (0xf0u8..0xfa).collect();
+		let mut okm = [0u8; 42];
+		hkdf_sha256(&salt, &ikm, &info, &mut okm);
+		let expected: Bytes = "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865".into();
+		assert_eq!(&okm[..], &*expected);
+	}
+
+	#[test]
+	fn test_hmac_sha256() {
+		// RFC 4231, test case 2
+		let expected = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843".into();
+		assert_eq!(hmac_sha256(b"Jefe", b"what do ya want for nothing?"), expected);
+	}
+
+	#[test]
+	fn test_hmac_sha512() {
+		// RFC 4231, test case 2
+		let expected = "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737".into();
+		assert_eq!(hmac_sha512(b"Jefe", b"what do ya want for nothing?"), expected);
+	}
 }
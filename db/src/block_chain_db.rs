@@ -6,14 +6,14 @@ use hash::H256;
 use bytes::Bytes;
 use chain::{
 	IndexedBlock, IndexedBlockHeader, IndexedTransaction,
-	OutPoint, TransactionOutput
+	OutPoint, TransactionOutput, BlockFilter
 };
 use ser::{
 	deserialize, serialize, List
 };
 use kv::{
 	KeyValueDatabase, OverlayDatabase, Transaction as DBTransaction, Value, DiskDatabase,
-	DatabaseConfig, MemoryDatabase, AutoFlushingOverlayDatabase, KeyValue, Key, KeyState, CacheDatabase
+	SledDatabase, DatabaseConfig, MemoryDatabase, AutoFlushingOverlayDatabase, KeyValue, Key, KeyState, CacheDatabase
 };
 use kv::{
 	COL_COUNT, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS, COL_TRANSACTIONS,
@@ -22,16 +22,22 @@ use kv::{
 use storage::{
 	BlockRef, Error, BlockHeaderProvider, BlockProvider, BlockOrigin, TransactionMeta,
 	TransactionMetaProvider, TransactionProvider, TransactionOutputProvider, BlockChain, Store,
-	SideChainOrigin, ForkChain, Forkable, CanonStore, ConfigStore, BestBlock
+	SideChainOrigin, ForkChain, Forkable, CanonStore, ConfigStore, BestBlock, BlockFilterProvider,
+	MigrationProgress, IntegrityStore, Inconsistency
 };
+use {integrity, migration};
 
 const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
 const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+pub const KEY_CHAIN_TIPS: &'static str = "chain_tips";
+const KEY_INVALIDATED_BLOCKS: &'static str = "invalidated_blocks";
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
 
 pub struct BlockChainDatabase<T> where T: KeyValueDatabase {
 	best_block: RwLock<BestBlock>,
+	/// Build compact block filters of canonized blocks.
+	block_filter_index: bool,
 	db: T,
 }
 
@@ -71,6 +77,16 @@ impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>
 	}
 }
 
+impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<SledDatabase>>> {
+	pub fn open_sled_at_path<P>(path: P, total_cache: usize) -> Result<Self, Error> where P: AsRef<Path> {
+		fs::create_dir_all(path.as_ref()).map_err(|err| Error::DatabaseError(err.to_string()))?;
+		match SledDatabase::open(path, COL_COUNT, total_cache) {
+			Ok(db) => Ok(Self::open_with_cache(db)),
+			Err(err) => Err(Error::DatabaseError(err))
+		}
+	}
+}
+
 impl BlockChainDatabase<MemoryDatabase> {
 	pub fn init_test_chain(blocks: Vec<IndexedBlock>) -> Self {
 		let store = BlockChainDatabase::open(MemoryDatabase::default());
@@ -90,6 +106,7 @@ impl<T> BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<T>>> where
 		let best_block = Self::read_best_block(&db).unwrap_or_default();
 		BlockChainDatabase {
 			best_block: RwLock::new(best_block),
+			block_filter_index: false,
 			db: db,
 		}
 	}
@@ -114,16 +131,116 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		let best_block = Self::read_best_block(&db).unwrap_or_default();
 		BlockChainDatabase {
 			best_block: RwLock::new(best_block),
+			block_filter_index: false,
 			db: db,
 		}
 	}
 
+	/// Enables or disables compact block filters index.
+	/// When enabled, filters of all canon blocks that are not yet indexed are built.
+	/// Filters are not built if database has other format version: it must not be modified before migration.
+	pub fn with_block_filter_index(mut self, enabled: bool) -> Result<Self, Error> {
+		self.block_filter_index = enabled;
+		if enabled && migration::read_version(&self.db)? == migration::DATABASE_VERSION {
+			self.index_block_filters()?;
+		}
+		Ok(self)
+	}
+
+	/// Builds filters of canon blocks, which have been canonized while index was disabled.
+	fn index_block_filters(&self) -> Result<(), Error> {
+		let best_block = self.best_block();
+		if best_block.hash.is_zero() {
+			return Ok(());
+		}
+
+		// find first canon block without filter
+		let mut first_number = best_block.number + 1;
+		while first_number > 0 {
+			let hash = self.block_hash(first_number - 1).expect("canon block hash to be in db; qed");
+			if self.block_filter_header(&hash).is_some() {
+				break;
+			}
+			first_number -= 1;
+		}
+
+		if first_number <= best_block.number {
+			info!(target: "db", "Building compact block filters for blocks {}..{}", first_number, best_block.number);
+		}
+
+		for number in first_number..best_block.number + 1 {
+			let block = self.block(number.into()).expect("canon block to be in db; qed");
+			let mut update = DBTransaction::new();
+			self.update_block_filter(&block, &mut update)?;
+			self.db.write(update).map_err(Error::DatabaseError)?;
+		}
+
+		Ok(())
+	}
+
+	/// Builds basic filter of the block and inserts it (with the filter header) into database transaction.
+	fn update_block_filter(&self, block: &IndexedBlock, update: &mut DBTransaction) -> Result<(), Error> {
+		let previous_header = if block.header.raw.previous_header_hash.is_zero() {
+			H256::default()
+		} else {
+			match self.block_filter_header(&block.header.raw.previous_header_hash) {
+				Some(previous_header) => previous_header,
+				None => {
+					error!(target: "db", "Filter header of parent block is not found: {}", block.header.raw.previous_header_hash.reversed());
+					return Err(Error::CannotCanonize);
+				},
+			}
+		};
+
+		let mut spent_scripts = Vec::new();
+		for tx in block.transactions.iter().skip(1) {
+			for input in &tx.raw.inputs {
+				let script = self.transaction(&input.previous_output.hash)
+					.and_then(|prev_tx| prev_tx.raw.outputs.into_iter().nth(input.previous_output.index as usize))
+					.map(|output| output.script_pubkey)
+					.ok_or_else(|| {
+						error!(
+							target: "db",
+							"Cannot find spent output during filter construction of tx {}: {}/{}",
+							tx.hash.reversed(),
+							input.previous_output.hash.reversed(),
+							input.previous_output.index,
+						);
+						Error::CannotCanonize
+					})?;
+				spent_scripts.push(script);
+			}
+		}
+
+		let filter = BlockFilter::basic(block, &spent_scripts);
+		let header = filter.header(&previous_header);
+		update.insert(KeyValue::BlockFilter(block.hash().clone(), filter.filter));
+		update.insert(KeyValue::BlockFilterHeader(block.hash().clone(), header));
+		Ok(())
+	}
+
 	pub fn best_block(&self) -> BestBlock {
 		self.best_block.read().clone()
 	}
 
+	/// Returns hashes of blocks without children. Tips of databases, created before tips were tracked,
+	/// are stored by the format upgrade. Until then, only the best block is known.
+	pub fn chain_tips(&self) -> Vec<H256> {
+		match self.get(Key::Meta(KEY_CHAIN_TIPS)).and_then(Value::as_meta) {
+			Some(tips) => deserialize::<_, List<H256>>(&**tips).expect("Inconsistent DB. Invalid chain tips.").into(),
+			None => {
+				let best_block_hash = self.best_block.read().hash.clone();
+				match best_block_hash.is_zero() {
+					true => Vec::new(),
+					false => vec![best_block_hash],
+				}
+			},
+		}
+	}
+
 	pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, Error> {
-		let overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db));
+		let mut overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db));
+		overlay.block_filter_index = self.block_filter_index;
 
 		for hash in side_chain.decanonized_route.into_iter().rev() {
 			let decanonized_hash = overlay.decanonize()?;
@@ -208,7 +325,12 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			return Err(Error::UnknownParent);
 		}
 
+		let mut chain_tips = self.chain_tips();
+		chain_tips.retain(|tip| *tip != parent_hash);
+		chain_tips.push(block.hash().clone());
+
 		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(chain_tips))));
 		update.insert(KeyValue::BlockHeader(block.hash().clone(), block.header.raw));
 		let tx_hashes = block.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
 		update.insert(KeyValue::BlockTransactions(block.header.hash.clone(), List::from(tx_hashes)));
@@ -230,16 +352,24 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		// and now remove decanonized block from database
 		// all code currently works in assumption that origin of all blocks is one of:
 		// {CanonChain, SideChain, SideChainBecomingCanonChain}
+		let new_best_block_hash = self.best_block().hash;
+		let mut chain_tips = self.chain_tips();
+		chain_tips.retain(|tip| *tip != decanonized_hash && *tip != new_best_block_hash);
+		chain_tips.push(new_best_block_hash.clone());
+
 		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(chain_tips))));
 		update.delete(Key::BlockHeader(decanonized_hash.clone()));
 		update.delete(Key::BlockTransactions(decanonized_hash.clone()));
+		update.delete(Key::BlockFilter(decanonized_hash.clone()));
+		update.delete(Key::BlockFilterHeader(decanonized_hash.clone()));
 		for tx_hash in tx_to_decanonize {
 			update.delete(Key::Transaction(tx_hash));
 		}
 
 		self.db.write(update).map_err(Error::DatabaseError)?;
 
-		Ok(self.best_block().hash)
+		Ok(new_best_block_hash)
 	}
 
 	/// Marks block as a new best block.
@@ -282,6 +412,9 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		update.insert(KeyValue::BlockNumber(new_best_block.hash.clone(), new_best_block.number));
 		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_HASH, serialize(&new_best_block.hash)));
 		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_NUMBER, serialize(&new_best_block.number)));
+		if new_best_block.number == 0 {
+			migration::write_version(&mut update);
+		}
 
 		let mut modified_meta: HashMap<H256, TransactionMeta> = HashMap::new();
 		if let Some(tx) = block.transactions.first() {
@@ -323,6 +456,11 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			update.insert(KeyValue::TransactionMeta(hash, meta));
 		}
 
+		// filters only depend on block contents, so they are kept when block is decanonized
+		if self.block_filter_index && self.block_filter_header(hash).is_none() {
+			self.update_block_filter(&block, &mut update)?;
+		}
+
 		self.db.write(update).map_err(Error::DatabaseError)?;
 		*best_block = new_best_block;
 		Ok(())
@@ -478,6 +616,13 @@ impl<T> TransactionMetaProvider for BlockChainDatabase<T> where T: KeyValueDatab
 		self.get(Key::TransactionMeta(hash.clone()))
 			.and_then(Value::as_transaction_meta)
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<BestBlock, Error> {
+		// blocks are (de)canonized under the write lock
+		let best_block = self.best_block.read();
+		self.db.for_each_transaction_meta(after, visitor).map_err(Error::DatabaseError)?;
+		Ok(best_block.clone())
+	}
 }
 
 impl<T> TransactionProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
@@ -507,6 +652,26 @@ impl<T> TransactionOutputProvider for BlockChainDatabase<T> where T: KeyValueDat
 	}
 }
 
+impl<T> BlockFilterProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn is_block_filter_index_enabled(&self) -> bool {
+		self.block_filter_index
+	}
+
+	fn block_filter(&self, hash: &H256) -> Option<BlockFilter> {
+		self.get(Key::BlockFilter(hash.clone()))
+			.and_then(Value::as_block_filter)
+			.map(|filter| BlockFilter {
+				block_hash: hash.clone(),
+				filter: filter,
+			})
+	}
+
+	fn block_filter_header(&self, hash: &H256) -> Option<H256> {
+		self.get(Key::BlockFilterHeader(hash.clone()))
+			.and_then(Value::as_block_filter_header)
+	}
+}
+
 impl<T> BlockChain for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn insert(&self, block: IndexedBlock) -> Result<(), Error> {
 		BlockChainDatabase::insert(self, block)
@@ -527,6 +692,10 @@ impl<T> BlockChain for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn block_origin(&self, header: &IndexedBlockHeader) -> Result<BlockOrigin, Error> {
 		BlockChainDatabase::block_origin(self, header)
 	}
+
+	fn chain_tips(&self) -> Vec<H256> {
+		BlockChainDatabase::chain_tips(self)
+	}
 }
 
 impl<T> Forkable for BlockChainDatabase<T> where T: KeyValueDatabase {
@@ -567,6 +736,13 @@ impl<T> Store for BlockChainDatabase<T> where T: KeyValueDatabase {
 	}
 }
 
+impl<T> IntegrityStore for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn check_integrity(&self, depth: u32, repair: bool) -> Result<Vec<Inconsistency>, Error> {
+		let best_block = self.best_block.read();
+		integrity::check_integrity(&self.db, &best_block, depth, repair)
+	}
+}
+
 impl<T> ConfigStore for BlockChainDatabase<T> where T: KeyValueDatabase {
 	fn consensus_fork(&self) -> Result<Option<String>, Error> {
 		match self.db.get(&Key::Configuration("consensus_fork"))
@@ -585,4 +761,42 @@ impl<T> ConfigStore for BlockChainDatabase<T> where T: KeyValueDatabase {
 		update.insert(KeyValue::Configuration("consensus_fork", consensus_fork.as_bytes().into()));
 		self.db.write(update).map_err(Error::DatabaseError)
 	}
+
+	fn import_position(&self) -> Result<Option<Bytes>, Error> {
+		self.db.get(&Key::Configuration("import_position"))
+			.map(KeyState::into_option)
+			.map(|x| x.and_then(Value::as_configuration))
+			.map_err(Error::DatabaseError)
+	}
+
+	fn set_import_position(&self, position: &[u8]) -> Result<(), Error> {
+		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Configuration("import_position", position.into()));
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	fn invalidated_blocks(&self) -> Result<Vec<H256>, Error> {
+		self.db.get(&Key::Configuration(KEY_INVALIDATED_BLOCKS))
+			.map(KeyState::into_option)
+			.map_err(Error::DatabaseError)?
+			.and_then(Value::as_configuration)
+			.map_or(Ok(Vec::new()), |blocks| deserialize::<_, List<H256>>(&**blocks)
+				.map(List::into)
+				.map_err(|e| Error::DatabaseError(format!("{:?}", e))))
+	}
+
+	fn set_invalidated_blocks(&self, hashes: &[H256]) -> Result<(), Error> {
+		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Configuration(KEY_INVALIDATED_BLOCKS, serialize(&List::from(hashes.to_vec()))));
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	fn format_version(&self) -> Result<u32, Error> {
+		migration::read_version(&self.db)
+	}
+
+	fn migrate(&self, progress: &mut dyn FnMut(MigrationProgress)) -> Result<(), Error> {
+		let best_block = self.best_block.read().clone();
+		migration::migrate(&self.db, &best_block, progress)
+	}
 }
@@ -0,0 +1,265 @@
+//! Consistency check of the canon chain indexes.
+//!
+//! Walks `COL_BLOCK_HASHES`, `COL_BLOCK_NUMBERS`, `COL_BLOCK_HEADERS`, `COL_BLOCK_TRANSACTIONS`,
+//! `COL_TRANSACTIONS` and `COL_TRANSACTIONS_META` for the best canon blocks. Transaction meta of
+//! these blocks is rebuilt from block contents and compared with the stored one.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use hash::H256;
+use ser::serialize;
+use chain::Transaction as ChainTransaction;
+use storage::{BestBlock, Error, Inconsistency, TransactionMeta};
+use kv::{KeyValueDatabase, Transaction, Key, KeyValue, KeyState, Value};
+
+fn get<T>(db: &T, key: Key) -> Result<Option<Value>, Error> where T: KeyValueDatabase {
+	db.get(&key).map(KeyState::into_option).map_err(Error::DatabaseError)
+}
+
+/// Checks `depth` best canon blocks, fixing repairable inconsistencies if `repair` is set.
+pub fn check_integrity<T>(db: &T, best_block: &BestBlock, depth: u32, repair: bool) -> Result<Vec<Inconsistency>, Error>
+	where T: KeyValueDatabase {
+	let mut inconsistencies = Vec::new();
+	if best_block.hash.is_zero() {
+		return Ok(inconsistencies);
+	}
+
+	let depth = cmp::min(depth, best_block.number + 1);
+	let first = best_block.number + 1 - depth;
+	let mut update = Transaction::new();
+	// meta of transactions from checked blocks, rebuilt from blocks contents
+	let mut expected_meta: HashMap<H256, (u32, TransactionMeta)> = HashMap::new();
+	// outputs of older transactions, spent by checked blocks
+	let mut spent_meta: HashMap<H256, (TransactionMeta, bool)> = HashMap::new();
+	let mut previous_hash: Option<H256> = None;
+
+	for number in first..best_block.number + 1 {
+		let hash = match get(db, Key::BlockHash(number))?.and_then(Value::as_block_hash) {
+			Some(hash) => hash,
+			None => {
+				inconsistencies.push(Inconsistency::MissingBlockHash(number));
+				previous_hash = None;
+				continue;
+			},
+		};
+
+		if get(db, Key::BlockNumber(hash.clone()))?.and_then(Value::as_block_number) != Some(number) {
+			inconsistencies.push(Inconsistency::BlockNumberMismatch(number, hash.clone()));
+			update.insert(KeyValue::BlockNumber(hash.clone(), number));
+		}
+
+		match get(db, Key::BlockHeader(hash.clone()))?.and_then(Value::as_block_header) {
+			Some(header) => if previous_hash.as_ref().map_or(false, |previous_hash| *previous_hash != header.previous_header_hash) {
+				inconsistencies.push(Inconsistency::ParentMismatch(number, hash.clone()));
+			},
+			None => inconsistencies.push(Inconsistency::MissingBlockHeader(number, hash.clone())),
+		}
+		previous_hash = Some(hash.clone());
+
+		let tx_hashes = match get(db, Key::BlockTransactions(hash.clone()))?.and_then(Value::as_block_transactions) {
+			Some(tx_hashes) => tx_hashes.into(),
+			None => {
+				inconsistencies.push(Inconsistency::MissingBlockTransactions(number, hash));
+				continue;
+			},
+		};
+
+		let mut transactions: Vec<(H256, ChainTransaction)> = Vec::new();
+		for tx_hash in tx_hashes {
+			match get(db, Key::Transaction(tx_hash.clone()))?.and_then(Value::as_transaction) {
+				Some(tx) => transactions.push((tx_hash, tx)),
+				None => inconsistencies.push(Inconsistency::MissingTransaction(number, tx_hash)),
+			}
+		}
+
+		// same rules as in BlockChainDatabase::canonize
+		for (index, &(ref tx_hash, ref tx)) in transactions.iter().enumerate() {
+			let meta = match index {
+				0 => TransactionMeta::new_coinbase(number, tx.outputs.len()),
+				_ => TransactionMeta::new(number, tx.outputs.len()),
+			};
+			expected_meta.insert(tx_hash.clone(), (number, meta));
+
+			if index == 0 {
+				continue;
+			}
+
+			for input in &tx.inputs {
+				let prevout = &input.previous_output;
+				if let Some(&mut (_, ref mut meta)) = expected_meta.get_mut(&prevout.hash) {
+					meta.denote_used(prevout.index as usize);
+					continue;
+				}
+
+				match spent_meta.entry(prevout.hash.clone()) {
+					Entry::Occupied(mut entry) => {
+						let &mut (ref mut meta, ref mut modified) = entry.get_mut();
+						if meta.is_spent(prevout.index as usize) != Some(true) {
+							inconsistencies.push(Inconsistency::SpentOutputNotMarked(number, prevout.hash.clone(), prevout.index));
+							meta.denote_used(prevout.index as usize);
+							*modified = true;
+						}
+					},
+					Entry::Vacant(entry) => match get(db, Key::TransactionMeta(prevout.hash.clone()))?.and_then(Value::as_transaction_meta) {
+						Some(mut meta) => {
+							let modified = meta.is_spent(prevout.index as usize) != Some(true);
+							if modified {
+								inconsistencies.push(Inconsistency::SpentOutputNotMarked(number, prevout.hash.clone(), prevout.index));
+								meta.denote_used(prevout.index as usize);
+							}
+							entry.insert((meta, modified));
+						},
+						None => inconsistencies.push(Inconsistency::MissingSpentTransactionMeta(number, prevout.hash.clone())),
+					},
+				}
+			}
+		}
+	}
+
+	let mut expected_meta: Vec<_> = expected_meta.into_iter().collect();
+	expected_meta.sort_by_key(|&(_, (number, _))| number);
+	for (tx_hash, (number, meta)) in expected_meta {
+		let stored_meta = get(db, Key::TransactionMeta(tx_hash.clone()))?.and_then(Value::as_transaction_meta);
+		// stored bits are padded to the whole bytes, so compare serialized meta
+		if stored_meta.map(|stored_meta| serialize(&stored_meta)) != Some(serialize(&meta)) {
+			inconsistencies.push(Inconsistency::TransactionMetaMismatch(number, tx_hash.clone()));
+			update.insert(KeyValue::TransactionMeta(tx_hash, meta));
+		}
+	}
+
+	for (tx_hash, (meta, modified)) in spent_meta {
+		if modified {
+			update.insert(KeyValue::TransactionMeta(tx_hash, meta));
+		}
+	}
+
+	if repair && !update.operations.is_empty() {
+		db.write(update).map_err(Error::DatabaseError)?;
+	}
+
+	Ok(inconsistencies)
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlock;
+	use storage::{Inconsistency, IntegrityStore, TransactionMeta, TransactionMetaProvider};
+	use kv::{SharedMemoryDatabase, KeyValueDatabase, Transaction, Key, KeyValue};
+	use block_chain_db::BlockChainDatabase;
+
+	fn database() -> (SharedMemoryDatabase, BlockChainDatabase<SharedMemoryDatabase>, Vec<IndexedBlock>) {
+		let db = SharedMemoryDatabase::default();
+		let blocks: Vec<IndexedBlock> = vec![
+			test_data::block_h0().into(),
+			test_data::block_h1().into(),
+			test_data::block_h2().into(),
+		];
+
+		let store = BlockChainDatabase::open(db.clone());
+		for block in &blocks {
+			store.insert(block.clone()).unwrap();
+			store.canonize(block.hash()).unwrap();
+		}
+
+		(db, store, blocks)
+	}
+
+	#[test]
+	fn consistent_database() {
+		let (_, store, _) = database();
+		assert_eq!(store.check_integrity(10, false), Ok(vec![]));
+	}
+
+	#[test]
+	fn spent_output_not_marked_is_repaired() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.merkled_header().build()
+			.build();
+		let genesis_coinbase = genesis.transactions()[0].hash();
+		let block = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.input().hash(genesis_coinbase.clone()).build()
+				.output().value(2).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+
+		let db = SharedMemoryDatabase::default();
+		let store = BlockChainDatabase::open(db.clone());
+		for block in vec![IndexedBlock::from(genesis), IndexedBlock::from(block)] {
+			store.insert(block.clone()).unwrap();
+			store.canonize(block.hash()).unwrap();
+		}
+		assert_eq!(store.check_integrity(10, false), Ok(vec![]));
+
+		let mut update = Transaction::new();
+		update.insert(KeyValue::TransactionMeta(genesis_coinbase.clone(), TransactionMeta::new_coinbase(0, 1)));
+		db.write(update).unwrap();
+
+		// genesis coinbase meta is outside of the checked blocks, only spent output is checked
+		let expected = vec![Inconsistency::SpentOutputNotMarked(1, genesis_coinbase.clone(), 0)];
+		assert_eq!(store.check_integrity(1, true), Ok(expected));
+		assert_eq!(store.transaction_meta(&genesis_coinbase).unwrap().is_spent(0), Some(true));
+		assert_eq!(store.check_integrity(10, false), Ok(vec![]));
+	}
+
+	#[test]
+	fn block_number_mismatch_is_repaired() {
+		let (db, store, blocks) = database();
+		let mut update = Transaction::new();
+		update.delete(Key::BlockNumber(blocks[1].hash().clone()));
+		db.write(update).unwrap();
+
+		let expected = vec![Inconsistency::BlockNumberMismatch(1, blocks[1].hash().clone())];
+		assert_eq!(store.check_integrity(10, false), Ok(expected.clone()));
+		assert_eq!(store.check_integrity(10, true), Ok(expected));
+		assert_eq!(store.check_integrity(10, false), Ok(vec![]));
+	}
+
+	#[test]
+	fn transaction_meta_is_rebuilt() {
+		let (db, store, blocks) = database();
+		let coinbase_hash = blocks[2].transactions[0].hash.clone();
+		let mut update = Transaction::new();
+		update.insert(KeyValue::TransactionMeta(coinbase_hash.clone(), TransactionMeta::new(1, 1)));
+		db.write(update).unwrap();
+
+		// meta is outside of the checked blocks
+		assert_eq!(store.check_integrity(0, false), Ok(vec![]));
+
+		let expected = vec![Inconsistency::TransactionMetaMismatch(2, coinbase_hash.clone())];
+		assert_eq!(store.check_integrity(1, true), Ok(expected));
+		assert_eq!(store.check_integrity(3, false), Ok(vec![]));
+
+		let meta = store.transaction_meta(&coinbase_hash).unwrap();
+		assert_eq!(meta.height(), 2);
+		assert!(meta.is_coinbase());
+	}
+
+	#[test]
+	fn missing_transactions_are_reported() {
+		let (db, store, blocks) = database();
+		let mut update = Transaction::new();
+		update.delete(Key::Transaction(blocks[1].transactions[0].hash.clone()));
+		update.delete(Key::BlockTransactions(blocks[2].hash().clone()));
+		db.write(update).unwrap();
+
+		let inconsistencies = store.check_integrity(2, true).unwrap();
+		assert_eq!(inconsistencies, vec![
+			Inconsistency::MissingTransaction(1, blocks[1].transactions[0].hash.clone()),
+			Inconsistency::MissingBlockTransactions(2, blocks[2].hash().clone()),
+		]);
+		assert!(inconsistencies.iter().all(|inconsistency| !inconsistency.is_repairable()));
+	}
+}
@@ -2,6 +2,7 @@ use lru_cache::LruCache;
 use parking_lot::Mutex;
 use hash::H256;
 use chain::BlockHeader;
+use storage::TransactionMeta;
 use kv::{KeyValueDatabase, KeyState, Operation, KeyValue, Key, Value, Transaction};
 
 pub struct CacheDatabase<T> where T: KeyValueDatabase {
@@ -44,4 +45,12 @@ impl<T> KeyValueDatabase for CacheDatabase<T> where T: KeyValueDatabase {
 		}
 		self.db.get(key)
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		self.db.for_each_transaction_meta(after, visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		self.db.for_each_block_header(after, visitor)
+	}
 }
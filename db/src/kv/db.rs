@@ -1,7 +1,18 @@
+use hash::H256;
+use chain::BlockHeader;
+use storage::TransactionMeta;
 use kv::{Transaction, KeyState, Key, Value};
 
 pub trait KeyValueDatabase: Send + Sync {
 	fn write(&self, tx: Transaction) -> Result<(), String>;
 
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String>;
+
+	/// Visits stored transaction metas in the order of transaction hashes, starting after given hash,
+	/// until visitor returns false.
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String>;
+
+	/// Visits stored block headers in the order of block hashes, starting after given hash,
+	/// until visitor returns false.
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String>;
 }
@@ -3,12 +3,16 @@
 use std::collections::HashMap;
 use std::path::Path;
 use rocksdb::{
-	DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBIterator,
+	DB, Writable, WriteBatch, WriteOptions, IteratorMode, Direction, DBIterator,
 	Options, DBCompactionStyle, BlockBasedOptions, Cache, Column, ReadOptions
 };
 use bytes::Bytes;
+use hash::H256;
+use ser::Deserializable;
+use chain::BlockHeader;
+use storage::TransactionMeta;
 use kv::{Transaction, RawTransaction, RawOperation, Location, Value, KeyValueDatabase, Key, KeyState, RawKeyValue,
-RawKey};
+RawKey, COL_BLOCK_HEADERS, COL_TRANSACTIONS_META, decode_hash_entry};
 
 const DB_BACKGROUND_FLUSHES: i32 = 2;
 const DB_BACKGROUND_COMPACTIONS: i32 = 2;
@@ -132,9 +136,35 @@ impl KeyValueDatabase for Database {
 			None => Ok(KeyState::Unknown)
 		}
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		self.for_each_hash_entry(COL_TRANSACTIONS_META, after, visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		self.for_each_hash_entry(COL_BLOCK_HEADERS, after, visitor)
+	}
 }
 
 impl Database {
+	/// Visits entries of the column, keyed by hash, in the order of hashes, starting after given hash.
+	fn for_each_hash_entry<V>(&self, column: u32, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &V) -> bool) -> Result<(), String> where V: Deserializable {
+		let iter = match after {
+			Some(after) => self.iter_from(Location::Column(column), &**after),
+			None => self.iter(Location::Column(column)),
+		};
+		for (key, value) in iter {
+			let (hash, value) = decode_hash_entry(&key, &value)?;
+			if Some(&hash) == after {
+				continue;
+			}
+			if !visitor(&hash, &value) {
+				break;
+			}
+		}
+		Ok(())
+	}
+
 	/// Open database with default settings.
 	pub fn open_default<P>(path: P) -> Result<Database, String> where P: AsRef<Path> {
 		Database::open(DatabaseConfig::default(), path)
@@ -292,6 +322,21 @@ impl Database {
 			}
 		}
 	}
+
+	/// Iterate over entries, starting at the given key.
+	pub fn iter_from(&self, location: Location, from: &[u8]) -> DatabaseIterator {
+		let DBAndColumns { ref db, ref cfs } = self.db;
+		let mode = IteratorMode::From(from, Direction::Forward);
+		match location {
+			Location::DB => DatabaseIterator {
+				iter: db.iterator_opt(mode, &self.read_opts)
+			},
+			Location::Column(column) => DatabaseIterator {
+				iter: db.iterator_cf_opt(cfs[column as usize], mode, &self.read_opts)
+					.expect("iterator params are valid; qed")
+			}
+		}
+	}
 }
 
 #[cfg(test)]
@@ -19,6 +19,8 @@ struct InnerDatabase {
 	transaction_meta: HashMap<H256, KeyState<TransactionMeta>>,
 	block_number: HashMap<H256, KeyState<u32>>,
 	configuration: HashMap<&'static str, KeyState<Bytes>>,
+	block_filter: HashMap<H256, KeyState<Bytes>>,
+	block_filter_header: HashMap<H256, KeyState<H256>>,
 }
 
 #[derive(Default, Debug)]
@@ -26,7 +28,38 @@ pub struct MemoryDatabase {
 	db: RwLock<InnerDatabase>,
 }
 
+/// Returns states of entries, which hashes are greater than given hash, ordered by hash.
+fn sorted_states<V>(entries: &HashMap<H256, KeyState<V>>, after: Option<&H256>) -> Vec<(H256, KeyState<V>)> where V: Clone {
+	let mut states: Vec<_> = entries.iter()
+		.filter(|&(hash, _)| after.map(|after| hash > after).unwrap_or(true))
+		.map(|(hash, state)| (hash.clone(), state.clone()))
+		.collect();
+	states.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+	states
+}
+
+/// Visits inserted entries of given states, until visitor returns false.
+fn visit_inserted<V>(states: Vec<(H256, KeyState<V>)>, visitor: &mut dyn FnMut(&H256, &V) -> bool) {
+	for (hash, state) in states {
+		if let KeyState::Insert(value) = state {
+			if !visitor(&hash, &value) {
+				break;
+			}
+		}
+	}
+}
+
 impl MemoryDatabase {
+	/// Returns states of transaction metas, which hashes are greater than given hash, ordered by hash.
+	pub fn transaction_meta_states(&self, after: Option<&H256>) -> Vec<(H256, KeyState<TransactionMeta>)> {
+		sorted_states(&self.db.read().transaction_meta, after)
+	}
+
+	/// Returns states of block headers, which hashes are greater than given hash, ordered by hash.
+	pub fn block_header_states(&self, after: Option<&H256>) -> Vec<(H256, KeyState<BlockHeader>)> {
+		sorted_states(&self.db.read().block_header, after)
+	}
+
 	pub fn drain_transaction(&self) -> Transaction {
 		let mut db = self.db.write();
 		let meta = replace(&mut db.meta, HashMap::default()).into_iter()
@@ -53,6 +86,12 @@ impl MemoryDatabase {
 		let configuration = replace(&mut db.configuration, HashMap::default()).into_iter()
 			.flat_map(|(key, state)| state.into_operation(key, KeyValue::Configuration, Key::Configuration));
 
+		let block_filter = replace(&mut db.block_filter, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockFilter, Key::BlockFilter));
+
+		let block_filter_header = replace(&mut db.block_filter_header, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockFilterHeader, Key::BlockFilterHeader));
+
 		Transaction {
 			operations: meta
 				.chain(block_hash)
@@ -62,6 +101,8 @@ impl MemoryDatabase {
 				.chain(transaction_meta)
 				.chain(block_number)
 				.chain(configuration)
+				.chain(block_filter)
+				.chain(block_filter_header)
 				.collect()
 		}
 	}
@@ -81,6 +122,8 @@ impl KeyValueDatabase for MemoryDatabase {
 					KeyValue::TransactionMeta(key, value) => { db.transaction_meta.insert(key, KeyState::Insert(value)); },
 					KeyValue::BlockNumber(key, value) => { db.block_number.insert(key, KeyState::Insert(value)); },
 					KeyValue::Configuration(key, value) => { db.configuration.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockFilter(key, value) => { db.block_filter.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockFilterHeader(key, value) => { db.block_filter_header.insert(key, KeyState::Insert(value)); },
 				},
 				Operation::Delete(delete) => match delete {
 					Key::Meta(key) => { db.meta.insert(key, KeyState::Delete); }
@@ -91,6 +134,8 @@ impl KeyValueDatabase for MemoryDatabase {
 					Key::TransactionMeta(key) => { db.transaction_meta.insert(key, KeyState::Delete); }
 					Key::BlockNumber(key) => { db.block_number.insert(key, KeyState::Delete); }
 					Key::Configuration(key) => { db.configuration.insert(key, KeyState::Delete); }
+					Key::BlockFilter(key) => { db.block_filter.insert(key, KeyState::Delete); }
+					Key::BlockFilterHeader(key) => { db.block_filter_header.insert(key, KeyState::Delete); }
 				}
 			}
 		}
@@ -108,10 +153,23 @@ impl KeyValueDatabase for MemoryDatabase {
 			Key::TransactionMeta(ref key) => db.transaction_meta.get(key).cloned().unwrap_or_default().map(Value::TransactionMeta),
 			Key::BlockNumber(ref key) => db.block_number.get(key).cloned().unwrap_or_default().map(Value::BlockNumber),
 			Key::Configuration(ref key) => db.configuration.get(key).cloned().unwrap_or_default().map(Value::Configuration),
+			Key::BlockFilter(ref key) => db.block_filter.get(key).cloned().unwrap_or_default().map(Value::BlockFilter),
+			Key::BlockFilterHeader(ref key) => db.block_filter_header.get(key).cloned().unwrap_or_default().map(Value::BlockFilterHeader),
 		};
 
 		Ok(result)
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		// visitor may read from the database => do not hold the lock while visiting
+		visit_inserted(self.transaction_meta_states(after), visitor);
+		Ok(())
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		visit_inserted(self.block_header_states(after), visitor);
+		Ok(())
+	}
 }
 
 #[derive(Debug)]
@@ -143,4 +201,12 @@ impl KeyValueDatabase for SharedMemoryDatabase {
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
 		self.db.get(key)
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		self.db.for_each_transaction_meta(after, visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		self.db.for_each_block_header(after, visitor)
+	}
 }
@@ -3,6 +3,7 @@ mod db;
 mod diskdb;
 mod memorydb;
 mod overlaydb;
+mod sleddb;
 mod transaction;
 
 pub use self::cachedb::CacheDatabase;
@@ -10,9 +11,10 @@ pub use self::db::KeyValueDatabase;
 pub use self::diskdb::{Database as DiskDatabase, DatabaseConfig, CompactionProfile};
 pub use self::memorydb::{MemoryDatabase, SharedMemoryDatabase};
 pub use self::overlaydb::{OverlayDatabase, AutoFlushingOverlayDatabase};
+pub use self::sleddb::Database as SledDatabase;
 pub use self::transaction::{
 	RawTransaction, Transaction, RawOperation, Operation, Location, KeyState,
-	Key, Value, KeyValue, RawKeyValue, RawKey,
+	Key, Value, KeyValue, RawKeyValue, RawKey, decode_hash_entry,
 	COL_COUNT, COL_META, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS,
-	COL_TRANSACTIONS, COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS
+	COL_TRANSACTIONS, COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS, COL_BLOCK_FILTERS, COL_BLOCK_FILTER_HEADERS
 };
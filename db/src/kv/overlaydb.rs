@@ -1,6 +1,52 @@
 use parking_lot::Mutex;
+use hash::H256;
+use chain::BlockHeader;
+use storage::TransactionMeta;
 use kv::{Transaction, Value, KeyValueDatabase, MemoryDatabase, KeyState, Key};
 
+/// Visits entries of the overlay and of the underlying database, which are not overwritten in the overlay,
+/// in the order of hashes. Overlay states must be ordered by hash.
+fn for_each_overlayed<V>(
+	overlayed: Vec<(H256, KeyState<V>)>,
+	for_each_in_db: &mut dyn FnMut(&mut dyn FnMut(&H256, &V) -> bool) -> Result<(), String>,
+	visitor: &mut dyn FnMut(&H256, &V) -> bool,
+) -> Result<(), String> {
+	let mut overlayed = overlayed.into_iter().peekable();
+	let mut visit_more = true;
+	for_each_in_db(&mut |hash, value| {
+		// visit overlay entries, preceding this one
+		while overlayed.peek().map(|&(ref overlayed_hash, _)| overlayed_hash < hash).unwrap_or(false) {
+			if let Some((overlayed_hash, KeyState::Insert(overlayed_value))) = overlayed.next() {
+				visit_more = visitor(&overlayed_hash, &overlayed_value);
+				if !visit_more {
+					return false;
+				}
+			}
+		}
+
+		visit_more = match overlayed.peek().map(|&(ref overlayed_hash, _)| overlayed_hash == hash).unwrap_or(false) {
+			true => match overlayed.next() {
+				Some((overlayed_hash, KeyState::Insert(overlayed_value))) => visitor(&overlayed_hash, &overlayed_value),
+				_ => true,
+			},
+			false => visitor(hash, value),
+		};
+		visit_more
+	})?;
+
+	if visit_more {
+		for (hash, state) in overlayed {
+			if let KeyState::Insert(value) = state {
+				if !visitor(&hash, &value) {
+					break;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
 pub struct OverlayDatabase<'a, T> where T: 'a + KeyValueDatabase {
 	db: &'a T,
 	overlay: MemoryDatabase,
@@ -30,6 +76,14 @@ impl<'a, T> KeyValueDatabase for OverlayDatabase<'a, T> where T: 'a + KeyValueDa
 			exists => Ok(exists)
 		}
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		for_each_overlayed(self.overlay.transaction_meta_states(after), &mut |visitor| self.db.for_each_transaction_meta(after, visitor), visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		for_each_overlayed(self.overlay.block_header_states(after), &mut |visitor| self.db.for_each_block_header(after, visitor), visitor)
+	}
 }
 
 pub struct AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
@@ -72,6 +126,18 @@ impl<T> KeyValueDatabase for AutoFlushingOverlayDatabase<T> where T: KeyValueDat
 			exists => Ok(exists)
 		}
 	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		// overlay must not be flushed while visiting, or some metas are visited twice
+		let _operations = self.operations.lock();
+		for_each_overlayed(self.overlay.transaction_meta_states(after), &mut |visitor| self.db.for_each_transaction_meta(after, visitor), visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		// overlay must not be flushed while visiting, or some headers are visited twice
+		let _operations = self.operations.lock();
+		for_each_overlayed(self.overlay.block_header_states(after), &mut |visitor| self.db.for_each_block_header(after, visitor), visitor)
+	}
 }
 
 impl<T> Drop for AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
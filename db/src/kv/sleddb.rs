@@ -0,0 +1,224 @@
+//! Key-Value store abstraction with pure-Rust `sled` backend.
+
+use std::path::Path;
+use sled::{self, Batch, Tree, Transactional};
+use sled::transaction::TransactionResult;
+use bytes::Bytes;
+use hash::H256;
+use ser::Deserializable;
+use chain::BlockHeader;
+use storage::TransactionMeta;
+use kv::{Transaction, RawTransaction, RawOperation, Location, Value, KeyValueDatabase, Key, KeyState, RawKeyValue,
+RawKey, COL_BLOCK_HEADERS, COL_TRANSACTIONS_META, decode_hash_entry};
+
+/// Default page cache size (in MiB).
+const DEFAULT_CACHE: usize = 64;
+
+/// Database iterator for flushed data only
+pub struct DatabaseIterator {
+	iter: sled::Iter,
+}
+
+impl Iterator for DatabaseIterator {
+	type Item = Result<(Box<[u8]>, Box<[u8]>), String>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.iter.next().map(|item| item
+			.map(|(key, value)| ((&*key).into(), (&*value).into()))
+			.map_err(|err| err.to_string()))
+	}
+}
+
+/// Key-Value database.
+///
+/// Every column is stored in a separate tree named `col{n}`, so the on-disk
+/// layout mirrors the column families of the `RocksDB` backend.
+pub struct Database {
+	db: sled::Db,
+	/// Default tree first, followed by one tree per column.
+	trees: Vec<Tree>,
+}
+
+impl KeyValueDatabase for Database {
+	fn write(&self, tx: Transaction) -> Result<(), String> {
+		Database::write(self, (&tx).into())
+	}
+
+	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
+		match Database::get(self, &key.into())? {
+			Some(value) => Ok(KeyState::Insert(Value::for_key(key, &value)?)),
+			None => Ok(KeyState::Unknown)
+		}
+	}
+
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<(), String> {
+		self.for_each_hash_entry(COL_TRANSACTIONS_META, after, visitor)
+	}
+
+	fn for_each_block_header(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &BlockHeader) -> bool) -> Result<(), String> {
+		self.for_each_hash_entry(COL_BLOCK_HEADERS, after, visitor)
+	}
+}
+
+impl Database {
+	/// Visits entries of the column, keyed by hash, in the order of hashes, starting after given hash.
+	fn for_each_hash_entry<V>(&self, column: u32, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &V) -> bool) -> Result<(), String> where V: Deserializable {
+		let iter = match after {
+			Some(after) => self.iter_from(Location::Column(column), &**after),
+			None => self.iter(Location::Column(column)),
+		};
+		for item in iter {
+			let (key, value) = item?;
+			let (hash, value) = decode_hash_entry(&key, &value)?;
+			if Some(&hash) == after {
+				continue;
+			}
+			if !visitor(&hash, &value) {
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	/// Open database with default settings.
+	pub fn open_default<P>(path: P, columns: u32) -> Result<Database, String> where P: AsRef<Path> {
+		Database::open(path, columns, DEFAULT_CACHE)
+	}
+
+	/// Open database file with given number of columns and cache size (in MiB). Creates if it does not exist.
+	pub fn open<P>(path: P, columns: u32, cache_size: usize) -> Result<Database, String> where P: AsRef<Path> {
+		let db = sled::Config::new()
+			.path(path.as_ref())
+			.cache_capacity(cache_size as u64 * 1024 * 1024)
+			.open()
+			.map_err(|err| err.to_string())?;
+
+		let mut trees = Vec::with_capacity(columns as usize + 1);
+		trees.push(Tree::clone(&db));
+		for col in 0..columns {
+			trees.push(db.open_tree(format!("col{}", col)).map_err(|err| err.to_string())?);
+		}
+
+		Ok(Database {
+			db: db,
+			trees: trees,
+		})
+	}
+
+	fn tree_index(&self, location: Location) -> usize {
+		match location {
+			Location::DB => 0,
+			Location::Column(col) => col as usize + 1,
+		}
+	}
+
+	/// Commit transaction to database.
+	///
+	/// Operations on all columns are applied atomically.
+	pub fn write(&self, tx: RawTransaction) -> Result<(), String> {
+		let mut batches: Vec<Option<Batch>> = self.trees.iter().map(|_| None).collect();
+		for op in tx.operations.into_iter() {
+			match op {
+				RawOperation::Insert(RawKeyValue { location, key, value }) => {
+					let index = self.tree_index(location);
+					batches[index].get_or_insert_with(Batch::default).insert(key.take(), value.take());
+				},
+				RawOperation::Delete(RawKey { location, key }) => {
+					let index = self.tree_index(location);
+					batches[index].get_or_insert_with(Batch::default).remove(key.take());
+				},
+			}
+		}
+
+		let result: TransactionResult<()> = self.trees.as_slice().transaction(|trees| {
+			for (tree, batch) in trees.iter().zip(batches.iter()) {
+				if let Some(ref batch) = *batch {
+					tree.apply_batch(batch)?;
+				}
+			}
+			Ok(())
+		});
+
+		result.map_err(|err| format!("{:?}", err))
+	}
+
+	/// Get value by key.
+	pub fn get(&self, key: &RawKey) -> Result<Option<Bytes>, String> {
+		let tree = &self.trees[self.tree_index(key.location)];
+		let value = tree.get(&*key.key).map_err(|err| err.to_string())?;
+		Ok(value.map(|v| (&*v).into()))
+	}
+
+	/// Flush all pending writes to disk.
+	pub fn flush(&self) -> Result<(), String> {
+		self.db.flush().map(|_| ()).map_err(|err| err.to_string())
+	}
+
+	/// Close the database
+	pub fn close(self) {}
+
+	pub fn iter(&self, location: Location) -> DatabaseIterator {
+		DatabaseIterator {
+			iter: self.trees[self.tree_index(location)].iter(),
+		}
+	}
+
+	/// Iterate over entries, starting at the given key.
+	pub fn iter_from(&self, location: Location, from: &[u8]) -> DatabaseIterator {
+		DatabaseIterator {
+			iter: self.trees[self.tree_index(location)].range(from..),
+		}
+	}
+}
+
+impl Drop for Database {
+	fn drop(&mut self) {
+		if let Err(err) = self.flush() {
+			warn!("Failed to flush database on close: {}", err);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate tempdir;
+
+	use self::tempdir::TempDir;
+	use kv::{RawTransaction, Location};
+	use super::*;
+
+	#[test]
+	fn sleddb() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = Database::open_default(tempdir.path(), 2).unwrap();
+
+		let key1 = b"key1";
+		let key2 = b"key2";
+		let key3 = b"key3";
+
+		let mut batch = RawTransaction::default();
+		batch.insert_raw(Location::DB, key1, b"cat");
+		batch.insert_raw(Location::DB, key2, b"dog");
+		batch.insert_raw(Location::Column(1), key1, b"mouse");
+		db.write(batch).unwrap();
+
+		assert_eq!(&*db.get(&RawKey::new(Location::DB, key1 as &[u8])).unwrap().unwrap(), b"cat");
+		assert_eq!(&*db.get(&RawKey::new(Location::Column(1), key1 as &[u8])).unwrap().unwrap(), b"mouse");
+		assert!(db.get(&RawKey::new(Location::Column(0), key1 as &[u8])).unwrap().is_none());
+
+		let contents: Vec<_> = db.iter(Location::DB).collect::<Result<_, _>>().unwrap();
+		assert_eq!(contents.len(), 2);
+		assert_eq!(&*contents[0].0, &*key1);
+		assert_eq!(&*contents[0].1, b"cat");
+		assert_eq!(&*contents[1].0, &*key2);
+		assert_eq!(&*contents[1].1, b"dog");
+
+		let mut batch = RawTransaction::default();
+		batch.delete_raw(Location::DB, key1);
+		batch.insert_raw(Location::DB, key3, b"elephant");
+		db.write(batch).unwrap();
+
+		assert!(db.get(&RawKey::new(Location::DB, key1 as &[u8])).unwrap().is_none());
+		assert_eq!(&*db.get(&RawKey::new(Location::DB, key3 as &[u8])).unwrap().unwrap(), b"elephant");
+	}
+}
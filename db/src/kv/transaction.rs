@@ -1,6 +1,6 @@
 use bytes::Bytes;
 use hash::H256;
-use ser::{serialize, List, deserialize};
+use ser::{serialize, List, deserialize, Deserializable};
 use chain::{Transaction as ChainTransaction, BlockHeader};
 use storage::{TransactionMeta};
 
@@ -13,6 +13,8 @@ pub const COL_TRANSACTIONS: u32 = 4;
 pub const COL_TRANSACTIONS_META: u32 = 5;
 pub const COL_BLOCK_NUMBERS: u32 = 6;
 pub const COL_CONFIGURATION: u32 = 7;
+pub const COL_BLOCK_FILTERS: u32 = 8;
+pub const COL_BLOCK_FILTER_HEADERS: u32 = 9;
 
 #[derive(Debug)]
 pub enum Operation {
@@ -30,6 +32,8 @@ pub enum KeyValue {
 	TransactionMeta(H256, TransactionMeta),
 	BlockNumber(H256, u32),
 	Configuration(&'static str, Bytes),
+	BlockFilter(H256, Bytes),
+	BlockFilterHeader(H256, H256),
 }
 
 #[derive(Debug)]
@@ -42,6 +46,8 @@ pub enum Key {
 	TransactionMeta(H256),
 	BlockNumber(H256),
 	Configuration(&'static str),
+	BlockFilter(H256),
+	BlockFilterHeader(H256),
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +60,15 @@ pub enum Value {
 	TransactionMeta(TransactionMeta),
 	BlockNumber(u32),
 	Configuration(Bytes),
+	BlockFilter(Bytes),
+	BlockFilterHeader(H256),
+}
+
+/// Decodes entry of the column, keyed by hash.
+pub fn decode_hash_entry<V>(key: &[u8], value: &[u8]) -> Result<(H256, V), String> where V: Deserializable {
+	let hash = deserialize(key).map_err(|e| format!("{:?}", e))?;
+	let value = deserialize(value).map_err(|e| format!("{:?}", e))?;
+	Ok((hash, value))
 }
 
 impl Value {
@@ -67,6 +82,8 @@ impl Value {
 			Key::TransactionMeta(_) => deserialize(bytes).map(Value::TransactionMeta),
 			Key::BlockNumber(_) => deserialize(bytes).map(Value::BlockNumber),
 			Key::Configuration(_) => deserialize(bytes).map(Value::Configuration),
+			Key::BlockFilter(_) => deserialize(bytes).map(Value::BlockFilter),
+			Key::BlockFilterHeader(_) => deserialize(bytes).map(Value::BlockFilterHeader),
 		}.map_err(|e| format!("{:?}", e))
 	}
 
@@ -125,6 +142,20 @@ impl Value {
 			_ => None,
 		}
 	}
+
+	pub fn as_block_filter(self) -> Option<Bytes> {
+		match self {
+			Value::BlockFilter(filter) => Some(filter),
+			_ => None,
+		}
+	}
+
+	pub fn as_block_filter_header(self) -> Option<H256> {
+		match self {
+			Value::BlockFilterHeader(header) => Some(header),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -228,6 +259,8 @@ impl<'a> From<&'a KeyValue> for RawKeyValue {
 			KeyValue::TransactionMeta(ref key, ref value) => (COL_TRANSACTIONS_META, serialize(key), serialize(value)),
 			KeyValue::BlockNumber(ref key, ref value) => (COL_BLOCK_NUMBERS, serialize(key), serialize(value)),
 			KeyValue::Configuration(ref key, ref value) => (COL_CONFIGURATION, serialize(key), serialize(value)),
+			KeyValue::BlockFilter(ref key, ref value) => (COL_BLOCK_FILTERS, serialize(key), serialize(value)),
+			KeyValue::BlockFilterHeader(ref key, ref value) => (COL_BLOCK_FILTER_HEADERS, serialize(key), serialize(value)),
 		};
 
 		RawKeyValue {
@@ -263,6 +296,8 @@ impl<'a> From<&'a Key> for RawKey {
 			Key::TransactionMeta(ref key) => (COL_TRANSACTIONS_META, serialize(key)),
 			Key::BlockNumber(ref key) => (COL_BLOCK_NUMBERS, serialize(key)),
 			Key::Configuration(ref key) => (COL_CONFIGURATION, serialize(key)),
+			Key::BlockFilter(ref key) => (COL_BLOCK_FILTERS, serialize(key)),
+			Key::BlockFilterHeader(ref key) => (COL_BLOCK_FILTER_HEADERS, serialize(key)),
 		};
 
 		RawKey {
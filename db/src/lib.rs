@@ -1,4 +1,5 @@
 extern crate rocksdb;
+extern crate sled;
 extern crate elastic_array;
 extern crate parking_lot;
 #[macro_use]
@@ -13,6 +14,9 @@ extern crate storage;
 
 pub mod kv;
 mod block_chain_db;
+mod integrity;
+mod migration;
 
 pub use block_chain_db::{BlockChainDatabase, ForkChainDatabase};
+pub use migration::DATABASE_VERSION;
 pub use primitives::{hash, bytes};
\ No newline at end of file
@@ -0,0 +1,305 @@
+//! Database format versioning and offline upgrades.
+//!
+//! Format version is stored in `COL_META`. Upgrade walks stored block headers in batches and saves
+//! its position together with every batch, so an interrupted upgrade continues where it stopped.
+
+use std::cmp;
+use hash::H256;
+use chain::IndexedBlockHeader;
+use ser::{serialize, deserialize, Deserializable, List};
+use storage::{Error, MigrationProgress, BestBlock};
+use kv::{KeyValueDatabase, Transaction, Key, KeyValue, KeyState, Value};
+use block_chain_db::KEY_CHAIN_TIPS;
+
+/// Current database format version.
+///
+/// - 0: database created before format versioning
+/// - 1: hashes of blocks without children (chain tips) are stored
+pub const DATABASE_VERSION: u32 = 1;
+
+const KEY_DATABASE_VERSION: &'static str = "database_version";
+const KEY_MIGRATION_PROGRESS: &'static str = "migration_progress";
+const KEY_MIGRATION_POSITION: &'static str = "migration_position";
+
+/// Number of blocks upgraded in single database transaction.
+const MIGRATION_BATCH_SIZE: usize = 1_000;
+
+fn read_meta<T, V>(db: &T, key: &'static str) -> Result<Option<V>, Error> where T: KeyValueDatabase, V: Deserializable {
+	match db.get(&Key::Meta(key)).map(KeyState::into_option).map(|x| x.and_then(Value::as_meta)) {
+		Ok(Some(value)) => deserialize(&**value)
+			.map(Some)
+			.map_err(|e| Error::DatabaseError(format!("{:?}", e))),
+		Ok(None) => Ok(None),
+		Err(e) => Err(Error::DatabaseError(e)),
+	}
+}
+
+/// Reads format version of the database.
+///
+/// Empty database has the current version. Non-empty database without stored version predates versioning.
+pub fn read_version<T>(db: &T) -> Result<u32, Error> where T: KeyValueDatabase {
+	if let Some(version) = read_meta(db, KEY_DATABASE_VERSION)? {
+		return Ok(version);
+	}
+
+	match db.get(&Key::BlockHash(0)).map_err(Error::DatabaseError)?.into_option() {
+		Some(_) => Ok(0),
+		None => Ok(DATABASE_VERSION),
+	}
+}
+
+/// Stamps current format version as a part of the given update.
+pub fn write_version(update: &mut Transaction) {
+	update.insert(KeyValue::Meta(KEY_DATABASE_VERSION, serialize(&DATABASE_VERSION)));
+}
+
+/// Upgrades database with given best block to the current format version.
+pub fn migrate<T>(db: &T, best_block: &BestBlock, progress: &mut dyn FnMut(MigrationProgress)) -> Result<(), Error>
+	where T: KeyValueDatabase {
+	let current_version = read_version(db)?;
+	if current_version > DATABASE_VERSION {
+		return Err(Error::IncompatibleVersion(current_version, DATABASE_VERSION));
+	}
+
+	// every canon block has a header, side chain blocks are only known when they are visited
+	let canon_blocks = best_block.number + 1;
+	for version in current_version + 1..DATABASE_VERSION + 1 {
+		let mut processed: u32 = read_meta(db, KEY_MIGRATION_PROGRESS)?.unwrap_or(0);
+		let mut position: Option<H256> = read_meta(db, KEY_MIGRATION_POSITION)?;
+		loop {
+			let mut headers = Vec::with_capacity(MIGRATION_BATCH_SIZE);
+			db.for_each_block_header(position.as_ref(), &mut |hash, header| {
+				headers.push(IndexedBlockHeader::new(hash.clone(), header.clone()));
+				headers.len() < MIGRATION_BATCH_SIZE
+			}).map_err(Error::DatabaseError)?;
+
+			let last_header = match headers.last() {
+				Some(header) => header.hash.clone(),
+				None => break,
+			};
+
+			let mut update = Transaction::new();
+			upgrade_blocks(db, version, best_block, &headers, &mut update)?;
+			processed += headers.len() as u32;
+			update.insert(KeyValue::Meta(KEY_MIGRATION_PROGRESS, serialize(&processed)));
+			update.insert(KeyValue::Meta(KEY_MIGRATION_POSITION, serialize(&last_header)));
+			db.write(update).map_err(Error::DatabaseError)?;
+
+			position = Some(last_header);
+			progress(MigrationProgress {
+				version: version,
+				processed: processed,
+				total: cmp::max(canon_blocks, processed),
+			});
+
+			if headers.len() < MIGRATION_BATCH_SIZE {
+				break;
+			}
+		}
+
+		let mut update = Transaction::new();
+		update.insert(KeyValue::Meta(KEY_DATABASE_VERSION, serialize(&version)));
+		update.delete(Key::Meta(KEY_MIGRATION_PROGRESS));
+		update.delete(Key::Meta(KEY_MIGRATION_POSITION));
+		db.write(update).map_err(Error::DatabaseError)?;
+	}
+
+	Ok(())
+}
+
+/// Adds changes required to upgrade batch of stored blocks to given version.
+fn upgrade_blocks<T>(db: &T, version: u32, best_block: &BestBlock, headers: &[IndexedBlockHeader], update: &mut Transaction) -> Result<(), Error>
+	where T: KeyValueDatabase {
+	match version {
+		1 => {
+			// tips may be partially tracked already: by previous batches, or by blocks inserted before the upgrade
+			let mut chain_tips: Vec<H256> = match read_meta::<_, List<H256>>(db, KEY_CHAIN_TIPS)? {
+				Some(chain_tips) => chain_tips.into(),
+				None => vec![best_block.hash.clone()],
+			};
+
+			// canon blocks, except the best block, always have children
+			for header in headers {
+				if is_canon_block(db, &header.hash)? {
+					continue;
+				}
+
+				// tips always contain the latest visited descendant of the visited side chain block
+				let mut is_tip = !chain_tips.contains(&header.hash);
+				for tip in &chain_tips {
+					if !is_tip {
+						break;
+					}
+					is_tip = !side_chain_ancestors(db, tip)?.contains(&header.hash);
+				}
+
+				if is_tip {
+					let ancestors = side_chain_ancestors(db, &header.hash)?;
+					chain_tips.retain(|tip| !ancestors.contains(tip));
+					chain_tips.push(header.hash.clone());
+				}
+			}
+
+			update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(chain_tips))));
+		},
+		_ => unreachable!("migrate only upgrades to versions up to DATABASE_VERSION; there is no upgrade to version {}; qed", version),
+	}
+
+	Ok(())
+}
+
+fn is_canon_block<T>(db: &T, hash: &H256) -> Result<bool, Error> where T: KeyValueDatabase {
+	db.get(&Key::BlockNumber(hash.clone()))
+		.map(|state| state.into_option().is_some())
+		.map_err(Error::DatabaseError)
+}
+
+/// Returns hashes of side chain ancestors of the block, up to the fork point.
+fn side_chain_ancestors<T>(db: &T, hash: &H256) -> Result<Vec<H256>, Error> where T: KeyValueDatabase {
+	let mut ancestors = Vec::new();
+	let mut hash = hash.clone();
+	loop {
+		let header = db.get(&Key::BlockHeader(hash.clone()))
+			.map_err(Error::DatabaseError)?
+			.into_option()
+			.and_then(Value::as_block_header)
+			.ok_or_else(|| Error::DatabaseError(format!("Block header {} is missing", hash.reversed())))?;
+		hash = header.previous_header_hash;
+		if hash.is_zero() || is_canon_block(db, &hash)? {
+			return Ok(ancestors);
+		}
+		ancestors.push(hash.clone());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlock;
+	use storage::{BlockProvider, BlockFilterProvider, ConfigStore, Error};
+	use kv::{SharedMemoryDatabase, KeyValueDatabase, Transaction, Key, KeyValue};
+	use ser::{serialize, List};
+	use block_chain_db::{BlockChainDatabase, KEY_CHAIN_TIPS};
+	use super::{DATABASE_VERSION, KEY_DATABASE_VERSION, KEY_MIGRATION_PROGRESS, KEY_MIGRATION_POSITION};
+
+	/// Database with canon blocks 0..2 and side chain blocks: b0 <- side_b1 <- side_b2 and b1 <- side_c2
+	fn database_with_version(version: Option<u32>) -> (SharedMemoryDatabase, Vec<IndexedBlock>, Vec<IndexedBlock>) {
+		let db = SharedMemoryDatabase::default();
+		let blocks: Vec<IndexedBlock> = vec![
+			test_data::block_h0().into(),
+			test_data::block_h1().into(),
+			test_data::block_h2().into(),
+		];
+		let side_b1: IndexedBlock = test_data::block_builder()
+			.header().parent(blocks[0].hash().clone()).nonce(1).build()
+			.build()
+			.into();
+		let side_b2: IndexedBlock = test_data::block_builder()
+			.header().parent(side_b1.hash().clone()).nonce(2).build()
+			.build()
+			.into();
+		let side_c2: IndexedBlock = test_data::block_builder()
+			.header().parent(blocks[1].hash().clone()).nonce(3).build()
+			.build()
+			.into();
+		let side_blocks = vec![side_b1, side_b2, side_c2];
+
+		let store = BlockChainDatabase::open(db.clone());
+		for block in &blocks {
+			store.insert(block.clone()).unwrap();
+			store.canonize(block.hash()).unwrap();
+		}
+		for block in &side_blocks {
+			store.insert(block.clone()).unwrap();
+		}
+
+		let mut update = Transaction::new();
+		match version {
+			Some(version) => update.insert(KeyValue::Meta(KEY_DATABASE_VERSION, serialize(&version))),
+			None => {
+				// databases, created before versioning, do not track chain tips
+				update.delete(Key::Meta(KEY_DATABASE_VERSION));
+				update.delete(Key::Meta(KEY_CHAIN_TIPS));
+			},
+		}
+		db.write(update).unwrap();
+
+		(db, blocks, side_blocks)
+	}
+
+	#[test]
+	fn new_database_has_current_version() {
+		let store = BlockChainDatabase::open(SharedMemoryDatabase::default());
+		assert_eq!(store.format_version(), Ok(DATABASE_VERSION));
+
+		let b0: IndexedBlock = test_data::block_h0().into();
+		store.insert(b0.clone()).unwrap();
+		store.canonize(b0.hash()).unwrap();
+		assert_eq!(store.format_version(), Ok(DATABASE_VERSION));
+	}
+
+	#[test]
+	fn legacy_database_is_migrated() {
+		let (db, blocks, side_blocks) = database_with_version(None);
+		let store = BlockChainDatabase::open(db);
+		assert_eq!(store.format_version(), Ok(0));
+		assert_eq!(store.chain_tips(), vec![blocks[2].hash().clone()]);
+
+		let mut reports = Vec::new();
+		store.migrate(&mut |progress| reports.push(progress)).unwrap();
+		assert_eq!(reports.len(), 1);
+		assert_eq!(reports[0].version, 1);
+		assert_eq!(reports[0].processed, 6);
+		assert_eq!(reports[0].total, 6);
+
+		assert_eq!(store.format_version(), Ok(DATABASE_VERSION));
+		let mut chain_tips = store.chain_tips();
+		chain_tips.sort_by(|a, b| a[..].cmp(&b[..]));
+		let mut expected_tips = vec![blocks[2].hash().clone(), side_blocks[1].hash().clone(), side_blocks[2].hash().clone()];
+		expected_tips.sort_by(|a, b| a[..].cmp(&b[..]));
+		assert_eq!(chain_tips, expected_tips);
+		for (number, block) in blocks.iter().enumerate() {
+			assert_eq!(store.block_number(block.hash()), Some(number as u32));
+		}
+	}
+
+	#[test]
+	fn interrupted_migration_is_resumed() {
+		// headers are visited in the order of hashes: b1, b0, side_b2, b2, side_b1, side_c2
+		// => interrupt migration after the first three headers are upgraded
+		let (db, blocks, side_blocks) = database_with_version(None);
+		let mut update = Transaction::new();
+		update.insert(KeyValue::Meta(KEY_MIGRATION_PROGRESS, serialize(&3u32)));
+		update.insert(KeyValue::Meta(KEY_MIGRATION_POSITION, serialize(side_blocks[1].hash())));
+		update.insert(KeyValue::Meta(KEY_CHAIN_TIPS, serialize(&List::from(vec![blocks[2].hash().clone(), side_blocks[1].hash().clone()]))));
+		db.write(update).unwrap();
+
+		let store = BlockChainDatabase::open(db);
+		let mut reports = Vec::new();
+		store.migrate(&mut |progress| reports.push(progress)).unwrap();
+		assert_eq!(reports.len(), 1);
+		assert_eq!(reports[0].processed, 6);
+
+		// side_b1 is visited after its child => it is not a tip
+		assert_eq!(store.chain_tips(), vec![blocks[2].hash().clone(), side_blocks[1].hash().clone(), side_blocks[2].hash().clone()]);
+		assert_eq!(store.format_version(), Ok(DATABASE_VERSION));
+	}
+
+	#[test]
+	fn newer_database_is_rejected() {
+		let (db, _, _) = database_with_version(Some(DATABASE_VERSION + 1));
+		let store = BlockChainDatabase::open(db);
+		assert_eq!(store.format_version(), Ok(DATABASE_VERSION + 1));
+		assert_eq!(store.migrate(&mut |_| ()), Err(Error::IncompatibleVersion(DATABASE_VERSION + 1, DATABASE_VERSION)));
+	}
+
+	#[test]
+	fn newer_database_is_not_modified_when_opened() {
+		let (db, blocks, _) = database_with_version(Some(DATABASE_VERSION + 1));
+		let store = BlockChainDatabase::open(db).with_block_filter_index(true).unwrap();
+		for block in &blocks {
+			assert!(store.block_filter_header(block.hash()).is_none());
+		}
+	}
+}
@@ -0,0 +1,326 @@
+//! Conformance tests run against every `KeyValueDatabase` backend.
+
+extern crate chain;
+extern crate storage;
+extern crate db;
+extern crate tempdir;
+extern crate test_data;
+
+use tempdir::TempDir;
+use chain::IndexedBlock;
+use storage::{BlockProvider, BlockHeaderProvider, TransactionMeta};
+use db::BlockChainDatabase;
+use db::bytes::Bytes;
+use db::hash::H256;
+use db::kv::{
+	KeyValueDatabase, SharedMemoryDatabase, DiskDatabase, DatabaseConfig, SledDatabase,
+	Transaction, KeyValue, Key, Value, COL_COUNT
+};
+
+/// Creates (and reopens) a database of the backend under test.
+trait Backend {
+	type Database: KeyValueDatabase;
+
+	fn new() -> Self;
+	fn open(&self) -> Self::Database;
+}
+
+struct MemoryBackend(SharedMemoryDatabase);
+
+impl Backend for MemoryBackend {
+	type Database = SharedMemoryDatabase;
+
+	fn new() -> Self {
+		MemoryBackend(SharedMemoryDatabase::default())
+	}
+
+	fn open(&self) -> Self::Database {
+		self.0.clone()
+	}
+}
+
+struct RocksDbBackend(TempDir);
+
+impl Backend for RocksDbBackend {
+	type Database = DiskDatabase;
+
+	fn new() -> Self {
+		RocksDbBackend(TempDir::new("rocksdb").unwrap())
+	}
+
+	fn open(&self) -> Self::Database {
+		DiskDatabase::open(DatabaseConfig::with_columns(Some(COL_COUNT)), self.0.path()).unwrap()
+	}
+}
+
+struct SledBackend(TempDir);
+
+impl Backend for SledBackend {
+	type Database = SledDatabase;
+
+	fn new() -> Self {
+		SledBackend(TempDir::new("sled").unwrap())
+	}
+
+	fn open(&self) -> Self::Database {
+		SledDatabase::open_default(self.0.path(), COL_COUNT).unwrap()
+	}
+}
+
+fn get_block_hash<T: KeyValueDatabase>(db: &T, number: u32) -> Option<H256> {
+	match db.get(&Key::BlockHash(number)).unwrap().into_option() {
+		Some(Value::BlockHash(hash)) => Some(hash),
+		Some(value) => panic!("unexpected value: {:?}", value),
+		None => None,
+	}
+}
+
+fn get_meta<T: KeyValueDatabase>(db: &T, key: &'static str) -> Option<Bytes> {
+	match db.get(&Key::Meta(key)).unwrap().into_option() {
+		Some(Value::Meta(bytes)) => Some(bytes),
+		Some(value) => panic!("unexpected value: {:?}", value),
+		None => None,
+	}
+}
+
+fn insert_and_get<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHash(0, H256::from(1)));
+	tx.insert(KeyValue::Meta("key", "0102".into()));
+	db.write(tx).unwrap();
+
+	assert_eq!(get_block_hash(&db, 0), Some(H256::from(1)));
+	assert_eq!(get_meta(&db, "key"), Some("0102".into()));
+	assert_eq!(get_block_hash(&db, 1), None);
+	assert_eq!(get_meta(&db, "other"), None);
+}
+
+fn columns_are_separated<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::Meta("key", "01".into()));
+	tx.insert(KeyValue::Configuration("key", "02".into()));
+	db.write(tx).unwrap();
+
+	assert_eq!(get_meta(&db, "key"), Some("01".into()));
+	match db.get(&Key::Configuration("key")).unwrap().into_option() {
+		Some(Value::Configuration(bytes)) => assert_eq!(bytes, "02".into()),
+		value => panic!("unexpected value: {:?}", value),
+	}
+}
+
+fn overwrite<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHash(0, H256::from(1)));
+	db.write(tx).unwrap();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHash(0, H256::from(2)));
+	db.write(tx).unwrap();
+
+	assert_eq!(get_block_hash(&db, 0), Some(H256::from(2)));
+}
+
+fn delete<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHash(0, H256::from(1)));
+	tx.insert(KeyValue::BlockHash(1, H256::from(2)));
+	db.write(tx).unwrap();
+
+	let mut tx = Transaction::new();
+	tx.delete(Key::BlockHash(0));
+	db.write(tx).unwrap();
+
+	assert_eq!(get_block_hash(&db, 0), None);
+	assert_eq!(get_block_hash(&db, 1), Some(H256::from(2)));
+}
+
+fn operations_are_applied_in_order<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHash(0, H256::from(1)));
+	tx.delete(Key::BlockHash(0));
+	tx.insert(KeyValue::BlockHash(1, H256::from(2)));
+	tx.delete(Key::BlockHash(1));
+	tx.insert(KeyValue::BlockHash(1, H256::from(3)));
+	db.write(tx).unwrap();
+
+	assert_eq!(get_block_hash(&db, 0), None);
+	assert_eq!(get_block_hash(&db, 1), Some(H256::from(3)));
+}
+
+fn reopen<B: Backend>() {
+	let backend = B::new();
+	{
+		let db = backend.open();
+		let mut tx = Transaction::new();
+		tx.insert(KeyValue::BlockHash(0, H256::from(1)));
+		tx.insert(KeyValue::BlockHash(1, H256::from(2)));
+		tx.delete(Key::BlockHash(1));
+		db.write(tx).unwrap();
+	}
+
+	let db = backend.open();
+	assert_eq!(get_block_hash(&db, 0), Some(H256::from(1)));
+	assert_eq!(get_block_hash(&db, 1), None);
+}
+
+fn transaction_metas<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::TransactionMeta(H256::from(1), TransactionMeta::new_coinbase(0, 1)));
+	tx.insert(KeyValue::TransactionMeta(H256::from(2), TransactionMeta::new(1, 2)));
+	tx.insert(KeyValue::TransactionMeta(H256::from(3), TransactionMeta::new(2, 3)));
+	tx.delete(Key::TransactionMeta(H256::from(2)));
+	tx.insert(KeyValue::BlockHash(0, H256::from(4)));
+	db.write(tx).unwrap();
+
+	let mut metas = Vec::new();
+	db.for_each_transaction_meta(None, &mut |hash, meta| {
+		metas.push((hash.clone(), meta.height(), meta.is_coinbase()));
+		true
+	}).unwrap();
+
+	// metas are visited in the order of hashes
+	let mut expected = vec![(H256::from(1), 0, true), (H256::from(3), 2, false)];
+	expected.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+	assert_eq!(metas, expected);
+
+	// visiting is resumed after given hash
+	let mut metas = Vec::new();
+	db.for_each_transaction_meta(Some(&expected[0].0), &mut |hash, meta| {
+		metas.push((hash.clone(), meta.height(), meta.is_coinbase()));
+		true
+	}).unwrap();
+	assert_eq!(metas, vec![expected[1].clone()]);
+}
+
+fn block_headers<B: Backend>() {
+	let backend = B::new();
+	let db = backend.open();
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+
+	let mut tx = Transaction::new();
+	tx.insert(KeyValue::BlockHeader(b0.hash().clone(), b0.header.raw.clone()));
+	tx.insert(KeyValue::BlockHeader(b1.hash().clone(), b1.header.raw.clone()));
+	db.write(tx).unwrap();
+
+	// headers are visited in the order of hashes
+	let mut headers = Vec::new();
+	db.for_each_block_header(None, &mut |hash, header| {
+		headers.push((hash.clone(), header.clone()));
+		true
+	}).unwrap();
+	assert_eq!(headers, vec![
+		(b1.hash().clone(), b1.header.raw.clone()),
+		(b0.hash().clone(), b0.header.raw.clone()),
+	]);
+
+	let mut headers = Vec::new();
+	db.for_each_block_header(Some(b1.hash()), &mut |hash, _| {
+		headers.push(hash.clone());
+		true
+	}).unwrap();
+	assert_eq!(headers, vec![b0.hash().clone()]);
+}
+
+fn blockchain<B: Backend>() {
+	let backend = B::new();
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+
+	{
+		let store = BlockChainDatabase::open(backend.open());
+		store.insert(b0.clone()).unwrap();
+		store.insert(b1.clone()).unwrap();
+		store.insert(b2.clone()).unwrap();
+
+		store.canonize(b0.hash()).unwrap();
+		store.canonize(b1.hash()).unwrap();
+		store.canonize(b2.hash()).unwrap();
+
+		assert_eq!(b2.hash(), &store.decanonize().unwrap());
+	}
+
+	let store = BlockChainDatabase::open(backend.open());
+	assert_eq!(1, store.best_block().number);
+	assert_eq!(b1.hash(), &store.best_block().hash);
+	assert_eq!(b0.hash(), &store.block_hash(0).unwrap());
+	assert_eq!(b1.hash(), &store.block_hash(1).unwrap());
+	assert!(store.block_hash(2).is_none());
+	assert_eq!(store.block_header(b2.hash().clone().into()).unwrap().hash, *b2.hash());
+}
+
+macro_rules! backend_tests {
+	($name: ident, $backend: ty) => {
+		mod $name {
+			use super::*;
+
+			#[test]
+			fn insert_and_get() {
+				super::insert_and_get::<$backend>();
+			}
+
+			#[test]
+			fn columns_are_separated() {
+				super::columns_are_separated::<$backend>();
+			}
+
+			#[test]
+			fn overwrite() {
+				super::overwrite::<$backend>();
+			}
+
+			#[test]
+			fn delete() {
+				super::delete::<$backend>();
+			}
+
+			#[test]
+			fn operations_are_applied_in_order() {
+				super::operations_are_applied_in_order::<$backend>();
+			}
+
+			#[test]
+			fn reopen() {
+				super::reopen::<$backend>();
+			}
+
+			#[test]
+			fn transaction_metas() {
+				super::transaction_metas::<$backend>();
+			}
+
+			#[test]
+			fn block_headers() {
+				super::block_headers::<$backend>();
+			}
+
+			#[test]
+			fn blockchain() {
+				super::blockchain::<$backend>();
+			}
+		}
+	}
+}
+
+backend_tests!(memory, MemoryBackend);
+backend_tests!(rocksdb, RocksDbBackend);
+backend_tests!(sled, SledBackend);
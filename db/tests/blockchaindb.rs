@@ -4,9 +4,9 @@ extern crate db;
 extern crate test_data;
 
 use chain::IndexedBlock;
-use storage::{ForkChain, BlockProvider, SideChainOrigin};
+use storage::{ForkChain, BlockChain, BlockProvider, SideChainOrigin, BlockFilterProvider, ConfigStore, TransactionMetaProvider};
 use db::BlockChainDatabase;
-use db::kv::{MemoryDatabase, SharedMemoryDatabase};
+use db::kv::{MemoryDatabase, SharedMemoryDatabase, AutoFlushingOverlayDatabase};
 
 #[test]
 fn insert_block() {
@@ -128,3 +128,163 @@ fn switch_to_simple_fork() {
 	assert_eq!(store.best_block().hash, store.block_hash(2).unwrap());
 
 }
+
+#[test]
+fn block_filter_index() {
+	let shared_database = SharedMemoryDatabase::default();
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+
+	{
+		// b0 is canonized without index
+		let store = BlockChainDatabase::open(shared_database.clone());
+		store.insert(b0.clone()).unwrap();
+		store.canonize(b0.hash()).unwrap();
+		assert!(store.block_filter(b0.hash()).is_none());
+	}
+
+	let store = BlockChainDatabase::open(shared_database).with_block_filter_index(true).unwrap();
+	let filter0 = store.block_filter(b0.hash()).unwrap();
+	assert_eq!(filter0.filter, "017fa880".into());
+	assert_eq!(store.block_filter_header(b0.hash()).unwrap(), filter0.header(&Default::default()));
+
+	store.insert(b1.clone()).unwrap();
+	store.insert(b2.clone()).unwrap();
+	store.canonize(b1.hash()).unwrap();
+	store.canonize(b2.hash()).unwrap();
+
+	let filter2 = store.block_filter(b2.hash()).unwrap();
+	let header1 = store.block_filter_header(b1.hash()).unwrap();
+	assert_eq!(store.block_filter_header(b2.hash()).unwrap(), filter2.header(&header1));
+	assert!(filter2.match_any(&[&*b2.transactions[0].raw.outputs[0].script_pubkey]));
+
+	store.decanonize().unwrap();
+	assert!(store.block_filter(b2.hash()).is_some());
+}
+
+#[test]
+fn chain_tips() {
+	let shared_database = SharedMemoryDatabase::default();
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let side_b1: IndexedBlock = test_data::block_builder()
+		.header().parent(b0.hash().clone()).nonce(1).build()
+		.build()
+		.into();
+
+	{
+		let store = BlockChainDatabase::open(shared_database.clone());
+		assert!(store.chain_tips().is_empty());
+
+		store.insert(b0.clone()).unwrap();
+		store.canonize(b0.hash()).unwrap();
+		assert_eq!(store.chain_tips(), vec![b0.hash().clone()]);
+
+		store.insert(b1.clone()).unwrap();
+		store.insert(side_b1.clone()).unwrap();
+		store.canonize(b1.hash()).unwrap();
+	}
+
+	let store = BlockChainDatabase::open(shared_database);
+	assert_eq!(store.chain_tips(), vec![b1.hash().clone(), side_b1.hash().clone()]);
+
+	BlockChain::rollback_best(&store).unwrap();
+	assert_eq!(store.chain_tips(), vec![side_b1.hash().clone(), b0.hash().clone()]);
+}
+
+#[test]
+fn invalidated_blocks() {
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	assert_eq!(store.invalidated_blocks(), Ok(vec![]));
+
+	let hashes = vec![test_data::block_h1().hash(), test_data::block_h2().hash()];
+	store.set_invalidated_blocks(&hashes).unwrap();
+	assert_eq!(store.invalidated_blocks(), Ok(hashes));
+
+	store.set_invalidated_blocks(&[]).unwrap();
+	assert_eq!(store.invalidated_blocks(), Ok(vec![]));
+}
+
+#[test]
+fn for_each_transaction_meta() {
+	// some metas are flushed, and some are still in overlay
+	let store = BlockChainDatabase::open(AutoFlushingOverlayDatabase::new(MemoryDatabase::default(), 5));
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+
+	for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+		let hash = block.hash().clone();
+		store.insert(block).unwrap();
+		store.canonize(&hash).unwrap();
+	}
+	store.decanonize().unwrap();
+
+	let mut visited = Vec::new();
+	let best_block = store.for_each_transaction_meta(None, &mut |hash, meta| {
+		visited.push((hash.clone(), meta.height()));
+		true
+	}).unwrap();
+
+	// metas are visited in the order of hashes
+	let mut expected = vec![
+		(b0.transactions[0].hash.clone(), 0),
+		(b1.transactions[0].hash.clone(), 1),
+	];
+	expected.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+	assert!(best_block == store.best_block());
+	assert_eq!(visited, expected);
+
+	// visiting stops when visitor returns false
+	let mut visited = 0;
+	store.for_each_transaction_meta(None, &mut |_, _| {
+		visited += 1;
+		false
+	}).unwrap();
+	assert_eq!(visited, 1);
+}
+
+#[test]
+fn block_is_canonized_while_transaction_metas_are_scanned() {
+	let store = BlockChainDatabase::open(AutoFlushingOverlayDatabase::new(MemoryDatabase::default(), 2));
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+
+	for block in vec![b0.clone(), b1.clone()] {
+		let hash = block.hash().clone();
+		store.insert(block).unwrap();
+		store.canonize(&hash).unwrap();
+	}
+
+	// scan the first chunk of metas
+	let mut visited = Vec::new();
+	let best_block = store.for_each_transaction_meta(None, &mut |hash, _| {
+		visited.push(hash.clone());
+		false
+	}).unwrap();
+	assert_eq!(best_block.number, 1);
+
+	// storage isn't locked in between of chunks
+	let hash = b2.hash().clone();
+	store.insert(b2.clone()).unwrap();
+	store.canonize(&hash).unwrap();
+
+	// scan the rest of metas
+	let after = visited[0].clone();
+	let best_block = store.for_each_transaction_meta(Some(&after), &mut |hash, _| {
+		visited.push(hash.clone());
+		true
+	}).unwrap();
+	assert_eq!(best_block.number, 2);
+
+	// hashes of coinbase transactions are ordered by height
+	let expected = vec![
+		b0.transactions[0].hash.clone(),
+		b1.transactions[0].hash.clone(),
+		b2.transactions[0].hash.clone(),
+	];
+	// metas of the new block are visited, because they follow the last visited meta
+	assert_eq!(visited, expected);
+}
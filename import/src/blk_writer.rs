@@ -0,0 +1,131 @@
+use std::{io, fs, path};
+use std::io::Write;
+use ser::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use chain::Block;
+use fs::{read_blk_dir, blk_file_name};
+use raw_blk::BlkPosition;
+
+/// Maximal size of blk .dat file, same as in bitcoind
+pub const MAX_BLK_FILE_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Creates writer of blocks into empty bitcoind database directory
+pub fn create_blk_dir<P>(path: P, magic: u32) -> Result<BlkDirWriter, io::Error> where P: AsRef<path::Path> {
+	fs::create_dir_all(path.as_ref())?;
+	if read_blk_dir(path.as_ref())?.next().is_some() {
+		return Err(io::Error::new(io::ErrorKind::AlreadyExists, "Directory already contains blk files"));
+	}
+
+	let writer = BlkDirWriter {
+		path: path.as_ref().to_owned(),
+		magic: magic,
+		max_file_size: MAX_BLK_FILE_SIZE,
+		file: None,
+		position: BlkPosition::default(),
+	};
+
+	Ok(writer)
+}
+
+/// Writes magic-prefixed block records to blk .dat files
+pub struct BlkDirWriter {
+	path: path::PathBuf,
+	magic: u32,
+	max_file_size: u64,
+	file: Option<io::BufWriter<fs::File>>,
+	position: BlkPosition,
+}
+
+impl BlkDirWriter {
+	/// Sets maximal size of single blk .dat file
+	pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+		self.max_file_size = max_file_size;
+		self
+	}
+
+	/// Appends block to the current file, starting new file when current is full.
+	/// Returns position of the written record.
+	pub fn write_block(&mut self, block: &Block) -> Result<BlkPosition, io::Error> {
+		let data = serialize_with_flags(block, SERIALIZE_TRANSACTION_WITNESS);
+		let record_size = 8 + data.len() as u64;
+		if self.file.is_some() && self.position.offset + record_size > self.max_file_size {
+			self.flush()?;
+			self.file = None;
+			self.position = BlkPosition {
+				file: self.position.file + 1,
+				offset: 0,
+			};
+		}
+
+		if self.file.is_none() {
+			trace!("Creating blk file: {}", blk_file_name(self.position.file));
+			let file = fs::File::create(self.path.join(blk_file_name(self.position.file)))?;
+			self.file = Some(io::BufWriter::new(file));
+		}
+
+		let position = self.position;
+		{
+			let file = self.file.as_mut().expect("file is created above; qed");
+			let size = data.len() as u32;
+			file.write_all(&[self.magic as u8, (self.magic >> 8) as u8, (self.magic >> 16) as u8, (self.magic >> 24) as u8])?;
+			file.write_all(&[size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8])?;
+			file.write_all(&data)?;
+		}
+		self.position.offset += record_size;
+
+		Ok(position)
+	}
+
+	/// Flushes written blocks to the disk
+	pub fn flush(&mut self) -> Result<(), io::Error> {
+		match self.file {
+			Some(ref mut file) => file.flush(),
+			None => Ok(()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate tempdir;
+	extern crate test_data;
+
+	use self::tempdir::TempDir;
+	use chain::IndexedBlock;
+	use raw_blk::{open_raw_blk_dir, read_blk_at, BlkPosition};
+	use super::create_blk_dir;
+
+	#[test]
+	fn test_write_and_read_blk_dir() {
+		let dir = TempDir::new("blk").unwrap();
+		let blocks = vec![test_data::block_h0(), test_data::block_h1(), test_data::block_h2()];
+
+		let mut writer = create_blk_dir(dir.path(), 0xd9b4bef9).unwrap().with_max_file_size(400);
+		let positions = blocks.iter().map(|block| writer.write_block(block).unwrap()).collect::<Vec<_>>();
+		writer.flush().unwrap();
+
+		// every block is larger than half of the file
+		assert_eq!(positions, vec![
+			BlkPosition { file: 0, offset: 0 },
+			BlkPosition { file: 1, offset: 0 },
+			BlkPosition { file: 2, offset: 0 },
+		]);
+		assert!(create_blk_dir(dir.path(), 0xd9b4bef9).is_err());
+
+		let read = open_raw_blk_dir(dir.path(), BlkPosition::default()).unwrap()
+			.map(|raw| raw.unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(read.len(), 3);
+		for (raw, block) in read.iter().zip(blocks.iter()) {
+			assert_eq!(raw.magic, 0xd9b4bef9);
+			assert_eq!(raw.block().unwrap(), IndexedBlock::from_raw(block.clone()));
+		}
+
+		let resumed = open_raw_blk_dir(dir.path(), positions[1]).unwrap()
+			.map(|raw| raw.unwrap().position)
+			.collect::<Vec<_>>();
+		assert_eq!(resumed, positions[1..].to_vec());
+
+		let raw = read_blk_at(dir.path(), positions[2]).unwrap();
+		assert_eq!(raw.block().unwrap(), IndexedBlock::from_raw(blocks[2].clone()));
+	}
+}
@@ -31,14 +31,31 @@ impl cmp::Ord for BlkEntry {
 }
 
 fn is_blk_file_name(file_name: &str) -> bool {
+	blk_file_number(file_name).is_some()
+}
+
+fn blk_file_number(file_name: &str) -> Option<u32> {
 	if file_name.len() != 12 || !file_name.starts_with("blk") || !file_name.ends_with(".dat") {
-		return false;
+		return None;
 	}
 
-	file_name[3..8].parse::<u32>().is_ok()
+	file_name[3..8].parse::<u32>().ok()
+}
+
+/// Returns name of the blk .dat file with given number
+pub fn blk_file_name(number: u32) -> String {
+	format!("blk{:05}.dat", number)
 }
 
 impl BlkEntry {
+	/// Returns number of the blk .dat file
+	pub fn number(&self) -> u32 {
+		self.path.file_name()
+			.and_then(|file_name| file_name.to_str())
+			.and_then(blk_file_number)
+			.expect("BlkEntry is only created for blk files; qed")
+	}
+
 	fn from_dir_entry(dir_entry: fs::DirEntry) -> Option<Self> {
 		match dir_entry.metadata() {
 			Err(_) => return None,
@@ -86,7 +103,7 @@ impl Iterator for ReadBlkDir {
 
 #[cfg(test)]
 mod test {
-	use super::is_blk_file_name;
+	use super::{is_blk_file_name, blk_file_number, blk_file_name};
 
 	#[test]
 	fn test_is_blk_file_name() {
@@ -97,4 +114,12 @@ mod test {
 		assert!(!is_blk_file_name("blk00032.daw"));
 		assert!(!is_blk_file_name("blk000ff.dat"));
 	}
+
+	#[test]
+	fn test_blk_file_number() {
+		assert_eq!(blk_file_number("blk00000.dat"), Some(0));
+		assert_eq!(blk_file_number("blk00232.dat"), Some(232));
+		assert_eq!(blk_file_number("blk00232.daw"), None);
+		assert_eq!(blk_file_number(&blk_file_name(232)), Some(232));
+	}
 }
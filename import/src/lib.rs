@@ -7,9 +7,13 @@ extern crate serialization as ser;
 extern crate chain;
 
 mod blk;
+mod blk_writer;
 mod block;
 mod fs;
+mod raw_blk;
 
 pub use primitives::{hash, bytes};
 
 pub use blk::{open_blk_dir, BlkDir};
+pub use blk_writer::{create_blk_dir, BlkDirWriter, MAX_BLK_FILE_SIZE};
+pub use raw_blk::{open_raw_blk_dir, read_blk_at, RawBlkDir, RawBlk, BlkPosition};
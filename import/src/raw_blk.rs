@@ -0,0 +1,269 @@
+use std::{io, fs, path, fmt};
+use std::io::{Read, Seek, SeekFrom};
+use std::collections::VecDeque;
+use bytes::Bytes;
+use ser::{deserialize, Error as ReaderError};
+use chain::IndexedBlock;
+use fs::{read_blk_dir, blk_file_name};
+
+/// Maximal size of a single block record. Larger records are treated as a garbage.
+const MAX_BLK_RECORD_SIZE: u32 = 32 * 1024 * 1024;
+
+/// Position of a block record in the blocks directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlkPosition {
+	/// Number of the blk .dat file
+	pub file: u32,
+	/// Offset of the record in the file
+	pub offset: u64,
+}
+
+impl fmt::Display for BlkPosition {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}", blk_file_name(self.file), self.offset)
+	}
+}
+
+/// Block record which is not deserialized yet
+#[derive(Debug, PartialEq)]
+pub struct RawBlk {
+	/// Position of the record
+	pub position: BlkPosition,
+	/// Network magic
+	pub magic: u32,
+	/// Serialized block
+	pub data: Bytes,
+}
+
+impl RawBlk {
+	/// Deserializes block
+	pub fn block(&self) -> Result<IndexedBlock, ReaderError> {
+		deserialize(self.data.as_ref())
+	}
+}
+
+/// Reads single record, skipping zero-filled gaps before it.
+/// Returns `None` if there are no more records in the file.
+fn read_record<R>(reader: &mut R, file: u32, offset: &mut u64) -> Result<Option<RawBlk>, io::Error> where R: Read {
+	let mut byte = [0u8];
+	loop {
+		if reader.read(&mut byte)? == 0 {
+			return Ok(None);
+		}
+		if byte[0] != 0 {
+			break;
+		}
+		*offset += 1;
+	}
+
+	let position = BlkPosition {
+		file: file,
+		offset: *offset,
+	};
+
+	let mut header = [0u8; 8];
+	header[0] = byte[0];
+	reader.read_exact(&mut header[1..])?;
+	let magic = u32::from(header[0]) | u32::from(header[1]) << 8 | u32::from(header[2]) << 16 | u32::from(header[3]) << 24;
+	let size = u32::from(header[4]) | u32::from(header[5]) << 8 | u32::from(header[6]) << 16 | u32::from(header[7]) << 24;
+	if size > MAX_BLK_RECORD_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Too large block record at {}", position)));
+	}
+
+	let mut data = vec![0u8; size as usize];
+	reader.read_exact(&mut data)?;
+	*offset += 8 + size as u64;
+
+	Ok(Some(RawBlk {
+		position: position,
+		magic: magic,
+		data: data.into(),
+	}))
+}
+
+/// Reads single block record at given position
+pub fn read_blk_at<P>(path: P, position: BlkPosition) -> Result<RawBlk, io::Error> where P: AsRef<path::Path> {
+	let mut file = fs::File::open(path.as_ref().join(blk_file_name(position.file)))?;
+	file.seek(SeekFrom::Start(position.offset))?;
+	let mut offset = position.offset;
+	match read_record(&mut io::BufReader::new(file), position.file, &mut offset)? {
+		Some(ref raw) if raw.position != position => Err(io::Error::new(io::ErrorKind::InvalidData, format!("No block record at {}", position))),
+		Some(raw) => Ok(raw),
+		None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("No block record at {}", position))),
+	}
+}
+
+/// Creates iterator over block records of bitcoind database, starting at given position
+pub fn open_raw_blk_dir<P>(path: P, start: BlkPosition) -> Result<RawBlkDir, io::Error> where P: AsRef<path::Path> {
+	let mut files = read_blk_dir(path)?
+		.map(|entry| entry.map(|entry| (entry.number(), entry.path)))
+		.collect::<Result<Vec<_>, _>>()?;
+	files.retain(|&(number, _)| number >= start.file);
+	files.sort();
+
+	let raw_blk_dir = RawBlkDir {
+		files: files.into_iter().collect(),
+		current: None,
+		start: start,
+		position: start,
+	};
+
+	Ok(raw_blk_dir)
+}
+
+/// Bitcoind database block records iterator
+pub struct RawBlkDir {
+	files: VecDeque<(u32, path::PathBuf)>,
+	current: Option<io::BufReader<fs::File>>,
+	start: BlkPosition,
+	position: BlkPosition,
+}
+
+impl RawBlkDir {
+	/// Returns position right after the last read record. All records before it have been already read.
+	pub fn position(&self) -> BlkPosition {
+		self.position
+	}
+
+	fn open_next_file(&mut self) -> Result<bool, io::Error> {
+		let (number, path) = match self.files.pop_front() {
+			Some(file) => file,
+			None => return Ok(false),
+		};
+
+		trace!("Opening blk file: {:?}", path);
+		let mut file = fs::File::open(path)?;
+		let offset = match number == self.start.file {
+			true => self.start.offset,
+			false => 0,
+		};
+		file.seek(SeekFrom::Start(offset))?;
+		self.current = Some(io::BufReader::new(file));
+		self.position = BlkPosition {
+			file: number,
+			offset: offset,
+		};
+		Ok(true)
+	}
+}
+
+impl Iterator for RawBlkDir {
+	type Item = Result<RawBlk, io::Error>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if self.current.is_none() {
+				match self.open_next_file() {
+					Ok(true) => (),
+					Ok(false) => return None,
+					Err(err) => return Some(Err(err)),
+				}
+			}
+
+			let result = {
+				let reader = self.current.as_mut().expect("current file is opened above; qed");
+				read_record(reader, self.position.file, &mut self.position.offset)
+			};
+
+			match result {
+				Ok(Some(raw)) => return Some(Ok(raw)),
+				Ok(None) => self.current = None,
+				// bitcoind may still be writing the last record of the live file. Stop before it,
+				// so that it's read again when import is resumed
+				Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof && self.files.is_empty() => {
+					debug!("Truncated block record at {}, treating as end of input", self.position);
+					self.current = None;
+					return None;
+				},
+				Err(err) => return Some(Err(err)),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate tempdir;
+
+	use std::fs;
+	use std::io::Write;
+	use self::tempdir::TempDir;
+	use fs::blk_file_name;
+	use super::{read_record, open_raw_blk_dir, BlkPosition};
+
+	#[test]
+	fn test_read_record() {
+		let data: &[u8] = &[
+			0, 0,
+			0xf9, 0xbe, 0xb4, 0xd9, 2, 0, 0, 0, 0xaa, 0xbb,
+			0,
+			0xf9, 0xbe, 0xb4, 0xd9, 1, 0, 0, 0, 0xcc,
+			0, 0, 0,
+		];
+		let mut reader = data;
+		let mut offset = 0;
+
+		let first = read_record(&mut reader, 3, &mut offset).unwrap().unwrap();
+		assert_eq!(first.position, BlkPosition { file: 3, offset: 2 });
+		assert_eq!(first.magic, 0xd9b4bef9);
+		assert_eq!(&*first.data, &[0xaa, 0xbb]);
+		assert_eq!(offset, 12);
+
+		let second = read_record(&mut reader, 3, &mut offset).unwrap().unwrap();
+		assert_eq!(second.position, BlkPosition { file: 3, offset: 13 });
+		assert_eq!(&*second.data, &[0xcc]);
+		assert_eq!(offset, 22);
+
+		assert!(read_record(&mut reader, 3, &mut offset).unwrap().is_none());
+	}
+
+	#[test]
+	fn test_read_truncated_record() {
+		let data: &[u8] = &[0xf9, 0xbe, 0xb4, 0xd9, 2, 0, 0, 0, 0xaa];
+		let mut reader = data;
+		let mut offset = 0;
+		assert!(read_record(&mut reader, 0, &mut offset).is_err());
+	}
+
+	#[test]
+	fn test_truncated_record_of_live_file_ends_input() {
+		let dir = TempDir::new("raw_blk").unwrap();
+		let complete: &[u8] = &[0xf9, 0xbe, 0xb4, 0xd9, 1, 0, 0, 0, 0xaa];
+		let truncated: &[u8] = &[0xf9, 0xbe, 0xb4, 0xd9, 2, 0, 0, 0, 0xbb];
+		let mut data = complete.to_vec();
+		data.extend_from_slice(truncated);
+		fs::write(dir.path().join(blk_file_name(0)), &data).unwrap();
+
+		let mut reader = open_raw_blk_dir(dir.path(), BlkPosition::default()).unwrap();
+		assert_eq!(&*reader.next().unwrap().unwrap().data, &[0xaa]);
+		assert!(reader.next().is_none());
+		let position = reader.position();
+		assert_eq!(position, BlkPosition { file: 0, offset: 9 });
+
+		// once the record is written completely, it is read on resume
+		fs::OpenOptions::new().append(true).open(dir.path().join(blk_file_name(0))).unwrap().write_all(&[0xcc]).unwrap();
+		let resumed = open_raw_blk_dir(dir.path(), position).unwrap()
+			.map(|raw| raw.unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(resumed.len(), 1);
+		assert_eq!(resumed[0].position, position);
+		assert_eq!(&*resumed[0].data, &[0xbb, 0xcc]);
+	}
+
+	#[test]
+	fn test_truncated_record_of_old_file_is_error() {
+		let dir = TempDir::new("raw_blk").unwrap();
+		fs::write(dir.path().join(blk_file_name(0)), &[0xf9, 0xbe, 0xb4, 0xd9, 2, 0, 0, 0, 0xaa]).unwrap();
+		fs::write(dir.path().join(blk_file_name(1)), &[0xf9, 0xbe, 0xb4, 0xd9, 1, 0, 0, 0, 0xbb]).unwrap();
+
+		let mut reader = open_raw_blk_dir(dir.path(), BlkPosition::default()).unwrap();
+		assert!(reader.next().unwrap().is_err());
+	}
+
+	#[test]
+	fn test_position_order() {
+		let a = BlkPosition { file: 0, offset: 100 };
+		let b = BlkPosition { file: 1, offset: 0 };
+		assert!(a < b);
+	}
+}
@@ -57,8 +57,8 @@ impl DisplayLayout for Address {
 		result[0] = match (self.network, self.kind) {
 			(Network::Mainnet, Type::P2PKH) => 0,
 			(Network::Mainnet, Type::P2SH) => 5,
-			(Network::Testnet, Type::P2PKH) => 111,
-			(Network::Testnet, Type::P2SH) => 196,
+			(Network::Testnet, Type::P2PKH) | (Network::Regtest, Type::P2PKH) => 111,
+			(Network::Testnet, Type::P2SH) | (Network::Regtest, Type::P2SH) => 196,
 		};
 
 		result[1..21].copy_from_slice(&*self.hash);
@@ -0,0 +1,98 @@
+//! Ephemeral keys with ElligatorSwift-encoded public part and x-only ECDH (BIP324).
+
+use rand::Rng;
+use rand::os::OsRng;
+use secp256k1::key;
+use crypto::{ellswift, tagged_hash};
+use hash::H256;
+use {Error, SECP256K1};
+
+const ECDH_TAG: &'static [u8] = b"bip324_ellswift_xonly_ecdh";
+
+pub struct EllSwiftKey {
+	secret: key::SecretKey,
+	encoded: [u8; 64],
+}
+
+impl EllSwiftKey {
+	/// Generates new random key.
+	pub fn generate() -> Result<Self, Error> {
+		let context = &SECP256K1;
+		let mut rng = OsRng::new().map_err(|_| Error::FailedKeyGeneration)?;
+		let (secret, public) = context.generate_keypair(&mut rng)?;
+		let serialized = public.serialize_vec(context, true);
+		let mut x = [0u8; 32];
+		x.copy_from_slice(&serialized[1..33]);
+		let encoded = ellswift::encode(&x, || rng.gen());
+
+		Ok(EllSwiftKey {
+			secret: secret,
+			encoded: encoded,
+		})
+	}
+
+	/// 64 bytes, which are sent to the other side.
+	pub fn encoded(&self) -> &[u8; 64] {
+		&self.encoded
+	}
+
+	/// Computes shared secret with the owner of `their` encoded key.
+	pub fn ecdh(&self, their: &[u8; 64], initiator: bool) -> Result<H256, Error> {
+		let context = &SECP256K1;
+		let mut their_public = [0u8; 33];
+		their_public[0] = 0x02;
+		their_public[1..].copy_from_slice(&ellswift::decode(their));
+		let mut point = key::PublicKey::from_slice(context, &their_public)?;
+		point.mul_assign(context, &self.secret)?;
+		let shared_x = point.serialize_vec(context, true);
+
+		let (initiator_key, responder_key) = if initiator {
+			(&self.encoded, their)
+		} else {
+			(their, &self.encoded)
+		};
+
+		let mut input = Vec::with_capacity(64 + 64 + 32);
+		input.extend_from_slice(initiator_key);
+		input.extend_from_slice(responder_key);
+		input.extend_from_slice(&shared_x[1..33]);
+		Ok(tagged_hash(ECDH_TAG, &input))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use secp256k1::key;
+	use hash::{H256, H512};
+	use SECP256K1;
+	use super::EllSwiftKey;
+
+	#[test]
+	fn test_ecdh_agreement() {
+		let initiator = EllSwiftKey::generate().unwrap();
+		let responder = EllSwiftKey::generate().unwrap();
+		assert!(initiator.encoded()[..] != responder.encoded()[..]);
+
+		let initiator_secret = initiator.ecdh(responder.encoded(), true).unwrap();
+		let responder_secret = responder.ecdh(initiator.encoded(), false).unwrap();
+		assert_eq!(initiator_secret, responder_secret);
+
+		// roles must be agreed on
+		assert!(initiator.ecdh(responder.encoded(), false).unwrap() != responder_secret);
+	}
+
+	#[test]
+	fn test_ecdh_bip324_vector() {
+		// first vector from BIP324 packet_encoding_test_vectors.csv
+		let secret: H256 = "61062ea5071d800bbfd59e2e8b53d47d194b095ae5a4df04936b49772ef0d4d7".into();
+		let ours: H512 = "ec0adff257bbfe500c188c80b4fdd640f6b45a482bbc15fc7cef5931deff0aa186f6eb9bba7b85dc4dcc28b28722de1e3d9108b985e2967045668f66098e475b".into();
+		let theirs: H512 = "a4a94dfce69b4a2a0a099313d10f9f7e7d649d60501c9e1d274c300e0d89aafaffffffffffffffffffffffffffffffffffffffffffffffffffffffff8faf88d5".into();
+		let key = EllSwiftKey {
+			secret: key::SecretKey::from_slice(&SECP256K1, &*secret).unwrap(),
+			encoded: *ours,
+		};
+
+		let expected: H256 = "c6992a117f5edbea70c3f511d32d26b9798be4b81a62eaee1a5acaa8459a3592".into();
+		assert_eq!(key.ecdh(&*theirs, true).unwrap(), expected);
+	}
+}
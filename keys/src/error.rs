@@ -11,6 +11,8 @@ pub enum Error {
 	InvalidChecksum,
 	InvalidPrivate,
 	InvalidAddress,
+	InvalidExtendedKey,
+	HardenedDerivation,
 	FailedKeyGeneration,
 }
 
@@ -25,6 +27,8 @@ impl fmt::Display for Error {
 			Error::InvalidChecksum => "Invalid Checksum",
 			Error::InvalidPrivate => "Invalid Private",
 			Error::InvalidAddress => "Invalid Address",
+			Error::InvalidExtendedKey => "Invalid Extended Key",
+			Error::HardenedDerivation => "Hardened derivation requires private key",
 			Error::FailedKeyGeneration => "Key generation failed",
 		};
 
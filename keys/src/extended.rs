@@ -0,0 +1,200 @@
+//! Extended public key (BIP32)
+//!
+//! Public key with chain code, from which child public keys could be derived.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+
+use std::fmt;
+use std::str::FromStr;
+use secp256k1::key;
+use base58::{ToBase58, FromBase58};
+use crypto::{checksum, hmac_sha512};
+use hash::{H32, H256, H264};
+use network::Network;
+use {DisplayLayout, Error, Public, SECP256K1};
+
+/// Indices starting from this one are used for hardened derivation
+pub const HARDENED_INDEX: u32 = 0x80000000;
+
+/// Extended public key
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExtendedPublic {
+	/// The network on which this key should be used.
+	pub network: Network,
+	/// Number of derivations from the master key.
+	pub depth: u8,
+	/// Fingerprint of the parent key.
+	pub parent_fingerprint: H32,
+	/// Index of this key in the parent key children.
+	pub child_number: u32,
+	/// Chain code.
+	pub chain_code: H256,
+	/// Compressed public key.
+	pub public: Public,
+}
+
+impl ExtendedPublic {
+	/// First 4 bytes of the public key hash, identifying this key
+	pub fn fingerprint(&self) -> H32 {
+		let mut fingerprint = H32::default();
+		fingerprint.copy_from_slice(&self.public.address_hash()[0..4]);
+		fingerprint
+	}
+
+	/// Derives non-hardened child key with given index
+	pub fn derive(&self, index: u32) -> Result<Self, Error> {
+		if index >= HARDENED_INDEX {
+			return Err(Error::HardenedDerivation);
+		}
+
+		let mut data = Vec::with_capacity(37);
+		data.extend_from_slice(&*self.public);
+		data.extend_from_slice(&[(index >> 24) as u8, (index >> 16) as u8, (index >> 8) as u8, index as u8]);
+		let hmac = hmac_sha512(&*self.chain_code, &data);
+
+		let context = &SECP256K1;
+		let tweak = key::SecretKey::from_slice(context, &hmac[0..32])?;
+		let mut public = key::PublicKey::from_slice(context, &*self.public)?;
+		public.add_exp_assign(context, &tweak)?;
+
+		let mut chain_code = H256::default();
+		chain_code.copy_from_slice(&hmac[32..64]);
+		let mut compressed = H264::default();
+		compressed.copy_from_slice(&public.serialize_vec(context, true));
+
+		Ok(ExtendedPublic {
+			network: self.network,
+			depth: self.depth.checked_add(1).ok_or(Error::InvalidExtendedKey)?,
+			parent_fingerprint: self.fingerprint(),
+			child_number: index,
+			chain_code: chain_code,
+			public: Public::Compressed(compressed),
+		})
+	}
+
+	/// Derives key, following given path of non-hardened indices
+	pub fn derive_path(&self, path: &[u32]) -> Result<Self, Error> {
+		let mut key = self.clone();
+		for index in path {
+			key = key.derive(*index)?;
+		}
+		Ok(key)
+	}
+}
+
+impl DisplayLayout for ExtendedPublic {
+	type Target = Vec<u8>;
+
+	fn layout(&self) -> Self::Target {
+		let mut result = Vec::with_capacity(82);
+		result.extend_from_slice(match self.network {
+			Network::Mainnet => &[0x04, 0x88, 0xb2, 0x1e],
+			Network::Testnet | Network::Regtest => &[0x04, 0x35, 0x87, 0xcf],
+		});
+		result.push(self.depth);
+		result.extend_from_slice(&*self.parent_fingerprint);
+		result.extend_from_slice(&[(self.child_number >> 24) as u8, (self.child_number >> 16) as u8, (self.child_number >> 8) as u8, self.child_number as u8]);
+		result.extend_from_slice(&*self.chain_code);
+		result.extend_from_slice(&*self.public);
+		let cs = checksum(&result);
+		result.extend_from_slice(&*cs);
+		result
+	}
+
+	fn from_layout(data: &[u8]) -> Result<Self, Error> where Self: Sized {
+		if data.len() != 82 {
+			return Err(Error::InvalidExtendedKey);
+		}
+
+		let cs = checksum(&data[0..78]);
+		if &data[78..] != &*cs {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let network = match &data[0..4] {
+			[0x04, 0x88, 0xb2, 0x1e] => Network::Mainnet,
+			[0x04, 0x35, 0x87, 0xcf] => Network::Testnet,
+			_ => return Err(Error::InvalidExtendedKey),
+		};
+
+		let mut parent_fingerprint = H32::default();
+		parent_fingerprint.copy_from_slice(&data[5..9]);
+		let child_number = (data[9] as u32) << 24 | (data[10] as u32) << 16 | (data[11] as u32) << 8 | data[12] as u32;
+		let mut chain_code = H256::default();
+		chain_code.copy_from_slice(&data[13..45]);
+
+		// only compressed keys are serialized
+		match data[45] {
+			2 | 3 => (),
+			_ => return Err(Error::InvalidPublic),
+		}
+		key::PublicKey::from_slice(&SECP256K1, &data[45..78])?;
+
+		let extended = ExtendedPublic {
+			network: network,
+			depth: data[4],
+			parent_fingerprint: parent_fingerprint,
+			child_number: child_number,
+			chain_code: chain_code,
+			public: Public::from_slice(&data[45..78])?,
+		};
+
+		Ok(extended)
+	}
+}
+
+impl fmt::Display for ExtendedPublic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.layout().to_base58().fmt(f)
+	}
+}
+
+impl FromStr for ExtendedPublic {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = s.from_base58().map_err(|_| Error::InvalidExtendedKey)?;
+		ExtendedPublic::from_layout(&data)
+	}
+}
+
+impl From<&'static str> for ExtendedPublic {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use network::Network;
+	use Error;
+	use super::{ExtendedPublic, HARDENED_INDEX};
+
+	// BIP32 test vector 1, chain m/0H
+	const M_0H: &'static str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+	// BIP32 test vector 1, chain m/0H/1
+	const M_0H_1: &'static str = "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ";
+
+	#[test]
+	fn test_extended_public_roundtrip() {
+		let key: ExtendedPublic = M_0H.into();
+		assert_eq!(key.network, Network::Mainnet);
+		assert_eq!(key.depth, 1);
+		assert_eq!(key.child_number, HARDENED_INDEX);
+		assert_eq!(key.to_string(), M_0H);
+	}
+
+	#[test]
+	fn test_extended_public_derive() {
+		let key: ExtendedPublic = M_0H.into();
+		assert_eq!(key.derive(1).unwrap(), M_0H_1.into());
+		assert_eq!(key.derive_path(&[1]).unwrap(), M_0H_1.into());
+		assert_eq!(key.derive(HARDENED_INDEX), Err(Error::HardenedDerivation));
+	}
+
+	#[test]
+	fn test_extended_public_invalid() {
+		assert_eq!("xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnx".parse::<ExtendedPublic>(), Err(Error::InvalidChecksum));
+		assert_eq!("5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu".parse::<ExtendedPublic>(), Err(Error::InvalidExtendedKey));
+	}
+}
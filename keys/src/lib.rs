@@ -12,21 +12,29 @@ extern crate primitives;
 pub mod generator;
 mod address;
 mod display;
+mod ecdh;
+mod extended;
 mod keypair;
+mod message;
 mod error;
 mod network;
 mod private;
 mod public;
+mod segwit_address;
 mod signature;
 
 pub use primitives::{hash, bytes};
 
 pub use address::{Type, Address};
 pub use display::DisplayLayout;
+pub use ecdh::EllSwiftKey;
+pub use extended::{ExtendedPublic, HARDENED_INDEX};
 pub use keypair::KeyPair;
+pub use message::{MESSAGE_MAGIC, signed_message_hash, sign_message, recover_message};
 pub use error::Error;
 pub use private::Private;
 pub use public::Public;
+pub use segwit_address::SegwitAddress;
 pub use signature::{Signature, CompactSignature};
 pub use network::Network;
 
@@ -0,0 +1,129 @@
+//! Bitcoin Signed Message
+//!
+//! Message is prefixed with the magic string and double-SHA256 hashed. The hash is signed
+//! with the compact recoverable signature, so verifier could recover the public key and
+//! compare it to the P2PKH address.
+
+use crypto::dhash256;
+use {Message, Private, Public, CompactSignature, Error};
+
+/// Prefix of the signed message
+pub const MESSAGE_MAGIC: &'static str = "Bitcoin Signed Message:\n";
+
+/// Returns hash of the magic-prefixed message, which is signed by `sign_message`
+pub fn signed_message_hash(message: &[u8]) -> Message {
+	let mut data = Vec::with_capacity(MESSAGE_MAGIC.len() + message.len() + 10);
+	append_var_bytes(&mut data, MESSAGE_MAGIC.as_bytes());
+	append_var_bytes(&mut data, message);
+	dhash256(&data)
+}
+
+/// Signs the message with the compact recoverable signature
+pub fn sign_message(private: &Private, message: &[u8]) -> Result<CompactSignature, Error> {
+	private.sign_compact(&signed_message_hash(message))
+}
+
+/// Recovers public key of the message signer
+pub fn recover_message(message: &[u8], signature: &CompactSignature) -> Result<Public, Error> {
+	if signature[0] < 27 || signature[0] > 34 {
+		return Err(Error::InvalidSignature);
+	}
+
+	Public::recover_compact(&signed_message_hash(message), signature)
+}
+
+/// Appends compact size prefixed bytes
+fn append_var_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+	let len = bytes.len() as u64;
+	match len {
+		0..=0xfc => data.push(len as u8),
+		0xfd..=0xffff => {
+			data.push(0xfd);
+			data.extend_from_slice(&[len as u8, (len >> 8) as u8]);
+		},
+		0x10000..=0xffff_ffff => {
+			data.push(0xfe);
+			data.extend((0..4).map(|i| (len >> (8 * i)) as u8));
+		},
+		_ => {
+			data.push(0xff);
+			data.extend((0..8).map(|i| (len >> (8 * i)) as u8));
+		},
+	}
+	data.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+	use {Private, KeyPair, Address, Network};
+	use super::*;
+
+	fn verify_message(address: &str, signature: &str, message: &[u8]) -> bool {
+		let address: Address = address.parse().unwrap();
+		recover_message(message, &signature.parse().unwrap())
+			.map(|public| public.address_hash() == address.hash)
+			.unwrap_or(false)
+	}
+
+	#[test]
+	fn test_signed_message_hash() {
+		// long messages have multi-byte length prefix
+		let message = vec![0x61u8; 300];
+		assert!(signed_message_hash(&message) != signed_message_hash(&message[..299]));
+		assert!(signed_message_hash(b"") != signed_message_hash(b"\0"));
+	}
+
+	#[test]
+	fn test_sign_and_recover_message() {
+		let private: Private = "cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N".into();
+		let keypair = KeyPair::from_private(private).unwrap();
+		let signature = sign_message(keypair.private(), b"This is just a test message").unwrap();
+		// compressed key flag is set
+		assert!(signature[0] >= 31 && signature[0] <= 34);
+		assert_eq!(&recover_message(b"This is just a test message", &signature).unwrap(), keypair.public());
+		assert!(&recover_message(b"This is just another message", &signature).unwrap() != keypair.public());
+	}
+
+	#[test]
+	fn test_sign_message_known_answer() {
+		// Bitcoin Core MessageSign test vector
+		let private = Private {
+			network: Network::Mainnet,
+			secret: "d97f5108f11cda6eeebaaa420fef0726b1f898060b98489fa3098463c0032866".into(),
+			compressed: true,
+		};
+		let signature = sign_message(&private, b"Trust no one").unwrap();
+		// IPojfrX2dfPnH26UegfbGQQLrdK844DlHq5157/P6h57WyuS/Qsl+h/WSVGDF4MUi4rWSswW38oimDYfNNUBUOk=
+		assert_eq!(signature, "20fa237eb5f675f3e71f6e947a07db19040badd2bce380e51eae75e7bfcfea1e7b5b2b92fd0b25fa1fd64951831783148b8ad64acc16dfca2298361f34d50150e9".into());
+
+		// Bitcoin Core signmessagewithprivkey test vector
+		let private: Private = "cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N".into();
+		let signature = sign_message(&private, b"This is just a test message").unwrap();
+		// INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=
+		assert_eq!(signature, "20d6d59d6e1ee8f7919acbf6420bbc36ea29beb56391cc686feb17f0e7191b44802e15b26d48f330b3dd02c5c8e3a61919bd0a4134628bec16210cd1a46fd4f92d".into());
+	}
+
+	#[test]
+	fn test_verify_message_known_answer() {
+		// Bitcoin Core MessageVerify test vectors
+		assert!(verify_message("15CRxFdyRpGZLW9w8HnHvVduizdL5jKNbs",
+			"20fa237eb5f675f3e71f6e947a07db19040badd2bce380e51eae75e7bfcfea1e7b5b2b92fd0b25fa1fd64951831783148b8ad64acc16dfca2298361f34d50150e9",
+			b"Trust no one"));
+		assert!(verify_message("11canuhp9X2NocwCq7xNrQYTmUgZAnLK3",
+			"20871a204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b7320068746d6c27836483726afc75e",
+			b"Trust me"));
+		// signature of another message
+		assert!(!verify_message("15CRxFdyRpGZLW9w8HnHvVduizdL5jKNbs",
+			"20fa237eb5f675f3e71f6e947a07db19040badd2bce380e51eae75e7bfcfea1e7b5b2b92fd0b25fa1fd64951831783148b8ad64acc16dfca2298361f34d50150e9",
+			b"Trust me"));
+		// signature of another key
+		assert!(!verify_message("11canuhp9X2NocwCq7xNrQYTmUgZAnLK3",
+			"20fa237eb5f675f3e71f6e947a07db19040badd2bce380e51eae75e7bfcfea1e7b5b2b92fd0b25fa1fd64951831783148b8ad64acc16dfca2298361f34d50150e9",
+			b"Trust no one"));
+
+		// Bitcoin Core verifymessage test vector
+		assert!(verify_message("mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB",
+			"20d6d59d6e1ee8f7919acbf6420bbc36ea29beb56391cc686feb17f0e7191b44802e15b26d48f330b3dd02c5c8e3a61919bd0a4134628bec16210cd1a46fd4f92d",
+			b"This is just a test message"));
+	}
+}
@@ -2,4 +2,16 @@
 pub enum Network {
 	Mainnet,
 	Testnet,
+	/// Shares base58 prefixes with testnet, so base58-encoded keys and addresses are always decoded as testnet ones
+	Regtest,
+}
+
+impl Network {
+	/// Network, which base58-encoded keys and addresses are decoded to
+	pub fn base58_network(&self) -> Network {
+		match *self {
+			Network::Regtest => Network::Testnet,
+			network => network,
+		}
+	}
 }
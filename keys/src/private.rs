@@ -57,7 +57,7 @@ impl DisplayLayout for Private {
 		let mut result = vec![];
 		let network_byte = match self.network {
 			Network::Mainnet => 128,
-			Network::Testnet => 239,
+			Network::Testnet | Network::Regtest => 239,
 		};
 
 		result.push(network_byte);
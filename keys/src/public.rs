@@ -7,6 +7,7 @@ use hash::{H264, H520};
 use {AddressHash, Error, CompactSignature, Signature, Message, SECP256K1};
 
 /// Secret public key
+#[derive(Clone)]
 pub enum Public {
 	/// Normal version of public key
 	Normal(H520),
@@ -31,6 +32,11 @@ impl Public {
 		}
 	}
 
+	/// Returns true if key is a valid point on the curve
+	pub fn is_valid(&self) -> bool {
+		key::PublicKey::from_slice(&SECP256K1, self).is_ok()
+	}
+
 	pub fn address_hash(&self) -> AddressHash {
 		dhash160(self)
 	}
@@ -0,0 +1,231 @@
+//! Segregated witness address (BIP173, BIP350)
+//!
+//! Witness program with network identifier, encoded using bech32 (version 0) or bech32m (versions 1-16)
+//! checksum, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+
+use std::fmt;
+use std::str::FromStr;
+use bytes::Bytes;
+use network::Network;
+use Error;
+
+const CHARSET: &'static [u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// Witness program with network identifier
+#[derive(Debug, PartialEq, Clone)]
+pub struct SegwitAddress {
+	/// The network of the address.
+	pub network: Network,
+	/// Witness version.
+	pub version: u8,
+	/// Witness program.
+	pub program: Bytes,
+}
+
+impl SegwitAddress {
+	/// Creates address of the witness v0 program
+	pub fn new_v0(network: Network, program: &[u8]) -> Self {
+		SegwitAddress {
+			network: network,
+			version: 0,
+			program: program.to_vec().into(),
+		}
+	}
+
+	fn is_valid_program(version: u8, program: &[u8]) -> bool {
+		match version {
+			0 => program.len() == 20 || program.len() == 32,
+			1..=16 => program.len() >= 2 && program.len() <= 40,
+			_ => false,
+		}
+	}
+}
+
+fn hrp(network: Network) -> &'static str {
+	match network {
+		Network::Mainnet => "bc",
+		Network::Testnet => "tb",
+		Network::Regtest => "bcrt",
+	}
+}
+
+fn polymod(values: &[u8]) -> u32 {
+	const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+	let mut chk: u32 = 1;
+	for value in values {
+		let top = chk >> 25;
+		chk = (chk & 0x1ffffff) << 5 ^ *value as u32;
+		for (i, generator) in GENERATOR.iter().enumerate() {
+			if (top >> i) & 1 == 1 {
+				chk ^= *generator;
+			}
+		}
+	}
+	chk
+}
+
+fn hrp_expand(hrp: &str) -> Vec<u8> {
+	let mut result: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+	result.push(0);
+	result.extend(hrp.bytes().map(|b| b & 31));
+	result
+}
+
+fn checksum_const(version: u8) -> u32 {
+	match version {
+		0 => BECH32_CONST,
+		_ => BECH32M_CONST,
+	}
+}
+
+/// Regroups bits of the `data` from `from`-bit to `to`-bit groups
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+	let mut acc: u32 = 0;
+	let mut bits: u32 = 0;
+	let max_value = (1 << to) - 1;
+	let mut result = Vec::new();
+	for value in data {
+		let value = *value as u32;
+		if value >> from != 0 {
+			return None;
+		}
+		acc = (acc << from) | value;
+		bits += from;
+		while bits >= to {
+			bits -= to;
+			result.push(((acc >> bits) & max_value) as u8);
+		}
+	}
+
+	if pad {
+		if bits > 0 {
+			result.push(((acc << (to - bits)) & max_value) as u8);
+		}
+	} else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+		return None;
+	}
+
+	Some(result)
+}
+
+impl fmt::Display for SegwitAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let hrp = hrp(self.network);
+		let mut data = vec![self.version];
+		data.extend(convert_bits(&self.program, 8, 5, true).expect("padded conversion of 8-bit values never fails; qed"));
+
+		let mut values = hrp_expand(hrp);
+		values.extend_from_slice(&data);
+		values.extend_from_slice(&[0; 6]);
+		let checksum = polymod(&values) ^ checksum_const(self.version);
+		data.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+		let encoded: String = data.into_iter().map(|value| CHARSET[value as usize] as char).collect();
+		write!(f, "{}1{}", hrp, encoded)
+	}
+}
+
+impl FromStr for SegwitAddress {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		if s.len() > 90 || (s.to_lowercase() != s && s.to_uppercase() != s) {
+			return Err(Error::InvalidAddress);
+		}
+
+		let s = s.to_lowercase();
+		let separator = s.rfind('1').ok_or(Error::InvalidAddress)?;
+		let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+		let network = match hrp {
+			"bc" => Network::Mainnet,
+			"tb" => Network::Testnet,
+			"bcrt" => Network::Regtest,
+			_ => return Err(Error::InvalidAddress),
+		};
+
+		if data.len() < 7 {
+			return Err(Error::InvalidAddress);
+		}
+
+		let data = data.bytes()
+			.map(|c| CHARSET.iter().position(|x| *x == c).map(|position| position as u8))
+			.collect::<Option<Vec<_>>>()
+			.ok_or(Error::InvalidAddress)?;
+
+		let version = data[0];
+		let mut values = hrp_expand(hrp);
+		values.extend_from_slice(&data);
+		if polymod(&values) != checksum_const(version) {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let program = convert_bits(&data[1..data.len() - 6], 5, 8, false).ok_or(Error::InvalidAddress)?;
+		if !SegwitAddress::is_valid_program(version, &program) {
+			return Err(Error::InvalidAddress);
+		}
+
+		Ok(SegwitAddress {
+			network: network,
+			version: version,
+			program: program.into(),
+		})
+	}
+}
+
+impl From<&'static str> for SegwitAddress {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use network::Network;
+	use Error;
+	use super::SegwitAddress;
+
+	#[test]
+	fn test_segwit_address_to_string() {
+		let address = SegwitAddress::new_v0(Network::Mainnet, &Bytes::from("751e76e8199196d454941c45d1b3a323f1433bd6"));
+		assert_eq!(address.to_string(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+
+		let address = SegwitAddress::new_v0(Network::Testnet, &Bytes::from("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"));
+		assert_eq!(address.to_string(), "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7");
+
+		let address = SegwitAddress::new_v0(Network::Regtest, &Bytes::from("751e76e8199196d454941c45d1b3a323f1433bd6"));
+		assert!(address.to_string().starts_with("bcrt1q"));
+		assert_eq!(address.to_string().parse(), Ok(address));
+
+		let address = SegwitAddress {
+			network: Network::Mainnet,
+			version: 1,
+			program: "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".into(),
+		};
+		assert_eq!(address.to_string(), "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0");
+	}
+
+	#[test]
+	fn test_segwit_address_from_str() {
+		let address: SegwitAddress = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4".parse().unwrap();
+		assert_eq!(address, SegwitAddress::new_v0(Network::Mainnet, &Bytes::from("751e76e8199196d454941c45d1b3a323f1433bd6")));
+
+		let address: SegwitAddress = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".parse().unwrap();
+		assert_eq!(address.version, 1);
+
+		let address: SegwitAddress = "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y".parse().unwrap();
+		assert_eq!(address.program.len(), 40);
+	}
+
+	#[test]
+	fn test_segwit_address_invalid() {
+		// mixed case
+		assert_eq!("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".parse::<SegwitAddress>(), Err(Error::InvalidAddress));
+		// invalid checksum
+		assert_eq!("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5".parse::<SegwitAddress>(), Err(Error::InvalidChecksum));
+		// unknown human-readable part
+		assert_eq!("tc1qw508d6qejxtdg4y5r3zarvary0c5xw7kg3g4ty".parse::<SegwitAddress>(), Err(Error::InvalidAddress));
+	}
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::collections::BTreeMap;
+use log::{LevelFilter, Metadata};
+
+/// Log filters in `target=level` form, separated by commas, e.g. `sync=info,p2p=debug,warn`.
+/// Directive without target sets the default level, directive without level enables all
+/// messages of the target.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Filters {
+	/// Default level. None, if it is not set explicitly - only errors are logged then.
+	default: Option<LevelFilter>,
+	targets: BTreeMap<String, LevelFilter>,
+}
+
+impl Filters {
+	/// Parses filters. Later directives override earlier ones.
+	pub fn parse(filters: &str) -> Result<Self, String> {
+		let mut result = Filters::default();
+		for directive in filters.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+			let mut parts = directive.splitn(2, '=');
+			let first = parts.next().expect("splitn always yields at least one item; qed");
+			match parts.next() {
+				Some(level) => {
+					let level = parse_level(level)?;
+					result.set(first, level);
+				},
+				None => match parse_level(first) {
+					Ok(level) => result.default = Some(level),
+					Err(_) => result.set(first, LevelFilter::Trace),
+				},
+			}
+		}
+
+		Ok(result)
+	}
+
+	/// Level of messages, which targets are not filtered explicitly
+	pub fn default_level(&self) -> LevelFilter {
+		self.default.unwrap_or(LevelFilter::Error)
+	}
+
+	/// Explicitly filtered targets
+	pub fn targets(&self) -> &BTreeMap<String, LevelFilter> {
+		&self.targets
+	}
+
+	/// Sets level of target messages
+	pub fn set(&mut self, target: &str, level: LevelFilter) {
+		self.targets.insert(target.to_owned(), level);
+	}
+
+	/// Removes target filter, so that default level is used for it
+	pub fn remove(&mut self, target: &str) {
+		self.targets.remove(target);
+	}
+
+	/// Applies directives of other filters on top of these filters
+	pub fn extend(&mut self, other: Filters) {
+		if other.default.is_some() {
+			self.default = other.default;
+		}
+		self.targets.extend(other.targets);
+	}
+
+	/// Most verbose level enabled by these filters
+	pub fn max_level(&self) -> LevelFilter {
+		self.targets.values().cloned().fold(self.default_level(), ::std::cmp::max)
+	}
+
+	/// Returns true if message should be logged. The longest matching target wins.
+	pub fn enabled(&self, metadata: &Metadata) -> bool {
+		let level = self.targets.iter()
+			.filter(|&(target, _)| target_matches(metadata.target(), target))
+			.max_by_key(|&(target, _)| target.len())
+			.map(|(_, level)| *level)
+			.unwrap_or_else(|| self.default_level());
+		metadata.level() <= level
+	}
+}
+
+impl fmt::Display for Filters {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut directives = self.targets.iter()
+			.map(|(target, level)| format!("{}={}", target, level.to_string().to_lowercase()))
+			.collect::<Vec<_>>();
+		if let Some(default) = self.default {
+			directives.push(default.to_string().to_lowercase());
+		}
+		write!(f, "{}", directives.join(","))
+	}
+}
+
+/// Returns true if message target is the filtered target or one of its `::`-separated submodules.
+fn target_matches(target: &str, filter: &str) -> bool {
+	target.starts_with(filter) && (target.len() == filter.len() || target[filter.len()..].starts_with("::"))
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+	level.trim().parse().map_err(|_| format!("Invalid log level: {}", level))
+}
+
+#[cfg(test)]
+mod tests {
+	use log::{Level, LevelFilter, MetadataBuilder};
+	use super::Filters;
+
+	fn enabled(filters: &Filters, target: &str, level: Level) -> bool {
+		filters.enabled(&MetadataBuilder::new().target(target).level(level).build())
+	}
+
+	#[test]
+	fn test_filters_parse() {
+		let filters = Filters::parse("sync=info, p2p=debug,warn,db").unwrap();
+		assert_eq!(filters.default_level(), LevelFilter::Warn);
+		assert_eq!(filters.targets().get("sync"), Some(&LevelFilter::Info));
+		assert_eq!(filters.targets().get("p2p"), Some(&LevelFilter::Debug));
+		assert_eq!(filters.targets().get("db"), Some(&LevelFilter::Trace));
+		assert_eq!(filters.max_level(), LevelFilter::Trace);
+		assert_eq!(filters.to_string(), "db=trace,p2p=debug,sync=info,warn");
+		assert_eq!(Filters::parse(&filters.to_string()).unwrap(), filters);
+		assert!(Filters::parse("sync=loud").is_err());
+	}
+
+	#[test]
+	fn test_filters_enabled() {
+		let mut filters = Filters::parse("sync=info,sync::verifier=trace").unwrap();
+		assert!(enabled(&filters, "sync", Level::Info));
+		assert!(!enabled(&filters, "sync", Level::Debug));
+		assert!(enabled(&filters, "sync::verifier", Level::Trace));
+		assert!(enabled(&filters, "p2p", Level::Error));
+		assert!(!enabled(&filters, "p2p", Level::Warn));
+		assert!(enabled(&filters, "sync::verifier::queue", Level::Trace));
+		assert!(!enabled(&filters, "synchronization", Level::Info));
+		assert!(!enabled(&filters, "sync::verifierx", Level::Debug));
+
+		filters.extend(Filters::parse("p2p=debug").unwrap());
+		filters.remove("sync");
+		assert!(enabled(&filters, "p2p", Level::Debug));
+		assert!(!enabled(&filters, "sync", Level::Info));
+	}
+
+	#[test]
+	fn test_filters_extend() {
+		let mut filters = Filters::parse("info,sync=debug").unwrap();
+		filters.extend(Filters::parse("p2p=trace").unwrap());
+		assert_eq!(filters.default_level(), LevelFilter::Info);
+
+		// explicit levels are lowered back to error
+		filters.extend(Filters::parse("error,sync=error").unwrap());
+		assert_eq!(filters.default_level(), LevelFilter::Error);
+		assert_eq!(filters.targets().get("sync"), Some(&LevelFilter::Error));
+		assert_eq!(filters.to_string(), "p2p=trace,sync=error,error");
+		assert!(!enabled(&filters, "db", Level::Warn));
+		assert!(!enabled(&filters, "sync", Level::Warn));
+	}
+}
@@ -1,13 +1,26 @@
 extern crate ansi_term;
 extern crate log;
-extern crate env_logger;
+extern crate parking_lot;
 extern crate time;
 
-use std::env;
-use ansi_term::Colour as Color;
-use log::{Record, Level};
-use env_logger::Builder;
+mod filters;
+mod rotating_file;
+
+use std::{env, io};
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ansi_term::Colour as Color;
+use log::{kv, Log, Record, Level, Metadata};
+use parking_lot::{Mutex, RwLock};
+
+pub use filters::Filters;
+pub use rotating_file::RotatingFile;
+
+/// Size of the log file, after which it is rotated
+pub const DEFAULT_MAX_LOG_FILE_SIZE: u64 = 64 * 1024 * 1024;
+/// Number of rotated log files, kept on the disk
+pub const DEFAULT_MAX_LOG_FILES: usize = 5;
 
 fn strftime() -> String {
 	time::strftime("%Y-%m-%d %H:%M:%S %Z", &time::now()).expect("Time is incorrectly formatted")
@@ -46,18 +59,245 @@ impl LogFormatter for DateAndColorLogFormatter {
 	}
 }
 
-pub fn init<T>(filters: &str, formatter: T) where T: LogFormatter {
-	let mut builder = Builder::new();
+/// Formats every record as a single line JSON object with `timestamp`, `level`, `module`
+/// and `message` fields. Structured fields of the record (e.g. `peer` and `block`) are
+/// added before the message.
+pub struct JsonLogFormatter;
+
+impl LogFormatter for JsonLogFormatter {
+	fn format(&self, record: &Record) -> String {
+		let timestamp = time::strftime("%Y-%m-%dT%H:%M:%SZ", &time::now_utc()).expect("Time is incorrectly formatted");
+		let mut json = format!("{{\"timestamp\":\"{}\",\"level\":\"{}\",\"module\":{}", timestamp, record.level(), json_string(record.target()));
+		let _ = record.key_values().visit(&mut JsonFields(&mut json));
+		json.push_str(&format!(",\"message\":{}}}", json_string(&record.args().to_string())));
+		json
+	}
+}
 
-	let filters = match env::var("RUST_LOG") {
-		Ok(env_filters) => format!("{},{}", filters, env_filters),
-		Err(_) => filters.into(),
-	};
+/// Appends visited key-value pairs to the JSON object. Integers are written as numbers.
+struct JsonFields<'a>(&'a mut String);
 
-	builder.parse(&filters);
-	builder.format(move |buf, record| {
-		writeln!(buf, "{}", formatter.format(record))
-	});
+impl<'a, 'kvs> kv::VisitSource<'kvs> for JsonFields<'a> {
+	fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+		let value = match value.to_i64() {
+			Some(number) => number.to_string(),
+			None => json_string(&value.to_string()),
+		};
+		self.0.push_str(&format!(",{}:{}", json_string(key.as_str()), value));
+		Ok(())
+	}
+}
+
+fn json_string(s: &str) -> String {
+	let mut result = String::with_capacity(s.len() + 2);
+	result.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => result.push_str("\\\""),
+			'\\' => result.push_str("\\\\"),
+			'\n' => result.push_str("\\n"),
+			'\r' => result.push_str("\\r"),
+			'\t' => result.push_str("\\t"),
+			c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+			c => result.push(c),
+		}
+	}
+	result.push('"');
+	result
+}
+
+/// Handle to the installed logger, which allows to change filters at runtime
+#[derive(Clone)]
+pub struct LogHandle {
+	filters: Arc<RwLock<Filters>>,
+}
 
-	builder.init();
+impl LogHandle {
+	/// Currently active filters
+	pub fn filters(&self) -> Filters {
+		self.filters.read().clone()
+	}
+
+	/// Replaces active filters
+	pub fn set_filters(&self, filters: Filters) {
+		log::set_max_level(filters.max_level());
+		*self.filters.write() = filters;
+	}
+}
+
+struct LogFile {
+	/// Only messages of this target are written to the file, if set
+	target: Option<String>,
+	file: Mutex<RotatingFile>,
+}
+
+struct Logger {
+	filters: Arc<RwLock<Filters>>,
+	console: Option<Box<dyn LogFormatter>>,
+	file_formatter: Box<dyn LogFormatter>,
+	files: Vec<LogFile>,
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		self.filters.read().enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+
+		if let Some(ref console) = self.console {
+			let _ = writeln!(io::stderr(), "{}", console.format(record));
+		}
+
+		let mut line = None;
+		for log_file in &self.files {
+			let matches = match log_file.target {
+				Some(ref target) => record.target().starts_with(target.as_str()),
+				None => true,
+			};
+			if matches {
+				let line = line.get_or_insert_with(|| self.file_formatter.format(record));
+				let _ = log_file.file.lock().write_line(line);
+			}
+		}
+	}
+
+	fn flush(&self) {
+		for log_file in &self.files {
+			let _ = log_file.file.lock().flush();
+		}
+	}
+}
+
+/// Logger configuration. Filters, given in `RUST_LOG` environment variable,
+/// are applied on top of the configured ones.
+pub struct Builder {
+	filters: String,
+	console: Option<Box<dyn LogFormatter>>,
+	file_formatter: Box<dyn LogFormatter>,
+	files: Vec<(Option<String>, PathBuf)>,
+	max_file_size: u64,
+	max_files: usize,
+}
+
+impl Builder {
+	pub fn new(filters: &str) -> Self {
+		Builder {
+			filters: filters.into(),
+			console: None,
+			file_formatter: Box::new(DateLogFormatter),
+			files: Vec::new(),
+			max_file_size: DEFAULT_MAX_LOG_FILE_SIZE,
+			max_files: DEFAULT_MAX_LOG_FILES,
+		}
+	}
+
+	/// Prints messages to stderr
+	pub fn console<T>(mut self, formatter: T) -> Self where T: LogFormatter {
+		self.console = Some(Box::new(formatter));
+		self
+	}
+
+	/// Sets formatter of messages, written to files
+	pub fn file_formatter<T>(mut self, formatter: T) -> Self where T: LogFormatter {
+		self.file_formatter = Box::new(formatter);
+		self
+	}
+
+	/// Writes all messages to the file
+	pub fn file<P>(mut self, path: P) -> Self where P: AsRef<Path> {
+		self.files.push((None, path.as_ref().to_owned()));
+		self
+	}
+
+	/// Writes messages of given target to the file
+	pub fn module_file<P>(mut self, target: &str, path: P) -> Self where P: AsRef<Path> {
+		self.files.push((Some(target.into()), path.as_ref().to_owned()));
+		self
+	}
+
+	/// Sets size of the log file, after which it is rotated
+	pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+		self.max_file_size = max_file_size;
+		self
+	}
+
+	/// Installs global logger
+	pub fn init(self) -> Result<LogHandle, String> {
+		let mut filters = Filters::parse(&self.filters)?;
+		if let Ok(env_filters) = env::var("RUST_LOG") {
+			filters.extend(Filters::parse(&env_filters).map_err(|err| format!("Invalid RUST_LOG: {}", err))?);
+		}
+
+		let mut files = Vec::with_capacity(self.files.len());
+		for (target, path) in self.files {
+			let file = RotatingFile::open(&path, self.max_file_size, self.max_files)
+				.map_err(|err| format!("Failed to open log file {}: {}", path.display(), err))?;
+			files.push(LogFile {
+				target: target,
+				file: Mutex::new(file),
+			});
+		}
+
+		let max_level = filters.max_level();
+		let filters = Arc::new(RwLock::new(filters));
+		let logger = Logger {
+			filters: filters.clone(),
+			console: self.console,
+			file_formatter: self.file_formatter,
+			files: files,
+		};
+
+		log::set_boxed_logger(Box::new(logger)).map_err(|err| format!("Failed to initialize logger: {}", err))?;
+		log::set_max_level(max_level);
+
+		Ok(LogHandle {
+			filters: filters,
+		})
+	}
+}
+
+pub fn init<T>(filters: &str, formatter: T) -> Result<LogHandle, String> where T: LogFormatter {
+	Builder::new(filters).console(formatter).init()
+}
+
+#[cfg(test)]
+mod tests {
+	use log::{Level, RecordBuilder};
+	use log::kv::ToValue;
+	use super::{LogFormatter, JsonLogFormatter, json_string};
+
+	#[test]
+	fn test_json_string() {
+		assert_eq!(json_string("a \"b\"\\\n\u{1}"), "\"a \\\"b\\\"\\\\\\n\\u0001\"");
+	}
+
+	#[test]
+	fn test_json_log_formatter() {
+		let record = RecordBuilder::new()
+			.args(format_args!("Ignored `getblocktxn` message from peer#3"))
+			.level(Level::Trace)
+			.target("sync")
+			.build();
+		let json = JsonLogFormatter.format(&record);
+		assert!(json.starts_with("{\"timestamp\":\""));
+		assert!(json.ends_with(",\"level\":\"TRACE\",\"module\":\"sync\",\"message\":\"Ignored `getblocktxn` message from peer#3\"}"));
+	}
+
+	#[test]
+	fn test_json_log_formatter_fields() {
+		let block = "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048";
+		let fields: Vec<(&str, &dyn ToValue)> = vec![("peer", &17usize), ("block", &block)];
+		let record = RecordBuilder::new()
+			.args(format_args!("Got `block` message"))
+			.level(Level::Trace)
+			.target("sync")
+			.key_values(&fields)
+			.build();
+		let json = JsonLogFormatter.format(&record);
+		assert!(json.ends_with(&format!(",\"module\":\"sync\",\"peer\":17,\"block\":\"{}\",\"message\":\"Got `block` message\"}}", block)));
+	}
 }
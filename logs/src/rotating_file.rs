@@ -0,0 +1,108 @@
+use std::{io, fs};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Log file, which is moved to `<name>.1` when it grows over the size limit.
+/// Older files are shifted to `<name>.2`, `<name>.3`, ... and the oldest one is removed.
+pub struct RotatingFile {
+	path: PathBuf,
+	file: fs::File,
+	size: u64,
+	max_size: u64,
+	max_files: usize,
+}
+
+impl RotatingFile {
+	/// Opens log file for appending, creating parent directories if required
+	pub fn open<P>(path: P, max_size: u64, max_files: usize) -> Result<Self, io::Error> where P: AsRef<Path> {
+		if let Some(parent) = path.as_ref().parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let file = open_append(path.as_ref())?;
+		let size = file.metadata()?.len();
+		Ok(RotatingFile {
+			path: path.as_ref().to_owned(),
+			file: file,
+			size: size,
+			max_size: max_size,
+			max_files: max_files,
+		})
+	}
+
+	/// Writes single line to the file, rotating files if required
+	pub fn write_line(&mut self, line: &str) -> Result<(), io::Error> {
+		let len = line.len() as u64 + 1;
+		if self.size != 0 && self.size + len > self.max_size {
+			self.rotate()?;
+		}
+
+		self.file.write_all(line.as_bytes())?;
+		self.file.write_all(b"\n")?;
+		self.size += len;
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> Result<(), io::Error> {
+		self.file.flush()
+	}
+
+	fn rotate(&mut self) -> Result<(), io::Error> {
+		self.file.flush()?;
+		if self.max_files == 0 {
+			fs::remove_file(&self.path)?;
+		} else {
+			for index in (1..self.max_files).rev() {
+				let from = self.rotated_path(index);
+				if from.exists() {
+					fs::rename(from, self.rotated_path(index + 1))?;
+				}
+			}
+			fs::rename(&self.path, self.rotated_path(1))?;
+		}
+
+		self.file = open_append(&self.path)?;
+		self.size = 0;
+		Ok(())
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		let mut path = self.path.clone().into_os_string();
+		path.push(format!(".{}", index));
+		path.into()
+	}
+}
+
+fn open_append(path: &Path) -> Result<fs::File, io::Error> {
+	fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate tempdir;
+
+	use std::fs;
+	use self::tempdir::TempDir;
+	use super::RotatingFile;
+
+	#[test]
+	fn test_rotating_file() {
+		let dir = TempDir::new("logs").unwrap();
+		let path = dir.path().join("pbtc.log");
+		let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+		for line in &["first", "second", "third", "fourth"] {
+			file.write_line(line).unwrap();
+		}
+		file.flush().unwrap();
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+		assert_eq!(fs::read_to_string(dir.path().join("pbtc.log.1")).unwrap(), "third\n");
+		assert_eq!(fs::read_to_string(dir.path().join("pbtc.log.2")).unwrap(), "second\n");
+		assert!(!dir.path().join("pbtc.log.3").exists());
+
+		// appends to the existing file
+		let mut file = RotatingFile::open(&path, 20, 2).unwrap();
+		file.write_line("fifth").unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\nfifth\n");
+	}
+}
@@ -10,6 +10,7 @@ pub enum InventoryType {
 	MessageBlock = 2,
 	MessageFilteredBlock = 3,
 	MessageCompactBlock = 4,
+	MessageAncestorPackageInfo = 6,
 	MessageWitnessTx = 0x40000001,
 	MessageWitnessBlock = 0x40000002,
 	MessageWitnessFilteredBlock = 0x40000003,
@@ -23,6 +24,7 @@ impl InventoryType {
 			2 => Some(InventoryType::MessageBlock),
 			3 => Some(InventoryType::MessageFilteredBlock),
 			4 => Some(InventoryType::MessageCompactBlock),
+			6 => Some(InventoryType::MessageAncestorPackageInfo),
 			0x40000001 => Some(InventoryType::MessageWitnessTx),
 			0x40000002 => Some(InventoryType::MessageWitnessBlock),
 			0x40000003 => Some(InventoryType::MessageWitnessFilteredBlock),
@@ -71,6 +73,13 @@ impl InventoryVector {
 		}
 	}
 
+	pub fn ancestor_package_info(hash: H256) -> Self {
+		InventoryVector {
+			inv_type: InventoryType::MessageAncestorPackageInfo,
+			hash: hash,
+		}
+	}
+
 	pub fn block(hash: H256) -> Self {
 		InventoryVector {
 			inv_type: InventoryType::MessageBlock,
@@ -142,6 +151,7 @@ mod tests {
 		assert_eq!(2u32, InventoryType::MessageBlock.into());
 		assert_eq!(3u32, InventoryType::MessageFilteredBlock.into());
 		assert_eq!(4u32, InventoryType::MessageCompactBlock.into());
+		assert_eq!(6u32, InventoryType::MessageAncestorPackageInfo.into());
 		assert_eq!(0x40000001u32, InventoryType::MessageWitnessTx.into());
 		assert_eq!(0x40000002u32, InventoryType::MessageWitnessBlock.into());
 		assert_eq!(0x40000003u32, InventoryType::MessageWitnessFilteredBlock.into());
@@ -151,6 +161,7 @@ mod tests {
 		assert_eq!(InventoryType::from_u32(2).unwrap(), InventoryType::MessageBlock);
 		assert_eq!(InventoryType::from_u32(3).unwrap(), InventoryType::MessageFilteredBlock);
 		assert_eq!(InventoryType::from_u32(4).unwrap(), InventoryType::MessageCompactBlock);
+		assert_eq!(InventoryType::from_u32(6).unwrap(), InventoryType::MessageAncestorPackageInfo);
 		assert_eq!(InventoryType::from_u32(0x40000001).unwrap(), InventoryType::MessageWitnessTx);
 		assert_eq!(InventoryType::from_u32(0x40000002).unwrap(), InventoryType::MessageWitnessBlock);
 		assert_eq!(InventoryType::from_u32(0x40000003).unwrap(), InventoryType::MessageWitnessFilteredBlock);
@@ -14,6 +14,9 @@ pub struct MessageHeader {
 }
 
 impl MessageHeader {
+	/// Size of serialized message header.
+	pub const SIZE: usize = 24;
+
 	pub fn for_data(magic: Magic, command: Command, data: &[u8]) -> Self {
 		MessageHeader {
 			magic: magic,
@@ -26,7 +29,7 @@ impl MessageHeader {
 
 impl MessageHeader {
 	pub fn deserialize(data: &[u8], expected: Magic) -> Result<Self, Error> {
-		if data.len() != 24 {
+		if data.len() != MessageHeader::SIZE {
 			return Err(Error::Deserialize);
 		}
 
@@ -0,0 +1,67 @@
+use std::io;
+use hash::H256;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+pub const ANCPKGINFO_MAX_HASHES_LEN: usize = 25;
+
+#[derive(Debug, PartialEq)]
+pub struct AncPkgInfo {
+	/// Hashes of unconfirmed ancestors, sorted topologically, followed by the child hash.
+	pub hashes: Vec<H256>,
+}
+
+impl AncPkgInfo {
+	pub fn with_hashes(hashes: Vec<H256>) -> Self {
+		AncPkgInfo {
+			hashes: hashes,
+		}
+	}
+}
+
+impl Payload for AncPkgInfo {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"ancpkginfo"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let ancpkginfo = AncPkgInfo {
+			hashes: reader.read_list_max(ANCPKGINFO_MAX_HASHES_LEN)?,
+		};
+
+		Ok(ancpkginfo)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append_list(&self.hashes);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use serialization::{serialize_payload, deserialize_payload};
+	use super::{AncPkgInfo, ANCPKGINFO_MAX_HASHES_LEN};
+
+	#[test]
+	fn test_ancpkginfo_serialize_deserialize() {
+		let ancpkginfo = AncPkgInfo::with_hashes(vec![H256::from(1), H256::from(2)]);
+		let raw = serialize_payload(&ancpkginfo, 0).unwrap();
+
+		assert_eq!(raw.len(), 1 + 2 * 32);
+		assert_eq!(ancpkginfo, deserialize_payload(&raw, 0).unwrap());
+	}
+
+	#[test]
+	fn test_ancpkginfo_deserialize_too_many_hashes() {
+		let ancpkginfo = AncPkgInfo::with_hashes(vec![H256::from(1); ANCPKGINFO_MAX_HASHES_LEN + 1]);
+		let raw = serialize_payload(&ancpkginfo, 0).unwrap();
+
+		assert!(deserialize_payload::<AncPkgInfo>(&raw, 0).is_err());
+	}
+}
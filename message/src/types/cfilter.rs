@@ -0,0 +1,40 @@
+use std::io;
+use hash::H256;
+use bytes::Bytes;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+#[derive(Debug, PartialEq)]
+pub struct CFilter {
+	pub filter_type: u8,
+	pub block_hash: H256,
+	pub filter: Bytes,
+}
+
+impl Payload for CFilter {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"cfilter"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let cfilter = CFilter {
+			filter_type: reader.read()?,
+			block_hash: reader.read()?,
+			filter: reader.read()?,
+		};
+
+		Ok(cfilter)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.block_hash)
+			.append(&self.filter);
+		Ok(())
+	}
+}
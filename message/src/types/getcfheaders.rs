@@ -0,0 +1,41 @@
+use std::io;
+use hash::H256;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+pub const GETCFHEADERS_MAX_RESPONSE_HEADERS: u32 = 2_000;
+
+#[derive(Debug, PartialEq)]
+pub struct GetCFHeaders {
+	pub filter_type: u8,
+	pub start_height: u32,
+	pub stop_hash: H256,
+}
+
+impl Payload for GetCFHeaders {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"getcfheaders"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let get_cfheaders = GetCFHeaders {
+			filter_type: reader.read()?,
+			start_height: reader.read()?,
+			stop_hash: reader.read()?,
+		};
+
+		Ok(get_cfheaders)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.filter_type)
+			.append(&self.start_height)
+			.append(&self.stop_hash);
+		Ok(())
+	}
+}
@@ -0,0 +1,66 @@
+use std::io;
+use hash::H256;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+pub const GETPKGTXNS_MAX_HASHES_LEN: usize = 25;
+
+#[derive(Debug, PartialEq)]
+pub struct GetPkgTxns {
+	pub hashes: Vec<H256>,
+}
+
+impl GetPkgTxns {
+	pub fn with_hashes(hashes: Vec<H256>) -> Self {
+		GetPkgTxns {
+			hashes: hashes,
+		}
+	}
+}
+
+impl Payload for GetPkgTxns {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"getpkgtxns"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let getpkgtxns = GetPkgTxns {
+			hashes: reader.read_list_max(GETPKGTXNS_MAX_HASHES_LEN)?,
+		};
+
+		Ok(getpkgtxns)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append_list(&self.hashes);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use serialization::{serialize_payload, deserialize_payload};
+	use super::{GetPkgTxns, GETPKGTXNS_MAX_HASHES_LEN};
+
+	#[test]
+	fn test_getpkgtxns_serialize_deserialize() {
+		let getpkgtxns = GetPkgTxns::with_hashes(vec![H256::from(1), H256::from(2)]);
+		let raw = serialize_payload(&getpkgtxns, 0).unwrap();
+
+		assert_eq!(raw.len(), 1 + 2 * 32);
+		assert_eq!(getpkgtxns, deserialize_payload(&raw, 0).unwrap());
+	}
+
+	#[test]
+	fn test_getpkgtxns_deserialize_too_many_hashes() {
+		let getpkgtxns = GetPkgTxns::with_hashes(vec![H256::from(1); GETPKGTXNS_MAX_HASHES_LEN + 1]);
+		let raw = serialize_payload(&getpkgtxns, 0).unwrap();
+
+		assert!(deserialize_payload::<GetPkgTxns>(&raw, 0).is_err());
+	}
+}
@@ -1,6 +1,10 @@
 pub mod addr;
+mod ancpkginfo;
 mod block;
 mod blocktxn;
+mod cfcheckpt;
+mod cfheaders;
+mod cfilter;
 mod compactblock;
 mod feefilter;
 mod filteradd;
@@ -9,25 +13,35 @@ mod filterload;
 mod getaddr;
 mod getblocks;
 mod getblocktxn;
+mod getcfcheckpt;
+mod getcfheaders;
+mod getcfilters;
 mod getdata;
 mod getheaders;
+mod getpkgtxns;
 mod headers;
 mod inv;
 mod mempool;
 mod merkle_block;
 mod notfound;
 mod ping;
+mod pkgtxns;
 mod pong;
 pub mod reject;
 mod sendcompact;
 mod sendheaders;
+mod sendpackages;
 mod tx;
 mod verack;
 pub mod version;
 
 pub use self::addr::Addr;
+pub use self::ancpkginfo::{AncPkgInfo, ANCPKGINFO_MAX_HASHES_LEN};
 pub use self::block::Block;
 pub use self::blocktxn::BlockTxn;
+pub use self::cfcheckpt::CFCheckpt;
+pub use self::cfheaders::CFHeaders;
+pub use self::cfilter::CFilter;
 pub use self::compactblock::CompactBlock;
 pub use self::feefilter::FeeFilter;
 pub use self::filterload::{FilterLoad, FILTERLOAD_MAX_FILTER_LEN, FILTERLOAD_MAX_HASH_FUNCS};
@@ -37,18 +51,24 @@ pub use self::filteradd::{FilterAdd, FILTERADD_MAX_DATA_LEN};
 pub use self::getaddr::GetAddr;
 pub use self::getblocks::{GetBlocks, GETBLOCKS_MAX_RESPONSE_HASHES};
 pub use self::getblocktxn::GetBlockTxn;
+pub use self::getcfcheckpt::{GetCFCheckpt, CFCHECKPT_INTERVAL};
+pub use self::getcfheaders::{GetCFHeaders, GETCFHEADERS_MAX_RESPONSE_HEADERS};
+pub use self::getcfilters::{GetCFilters, GETCFILTERS_MAX_RESPONSE_FILTERS};
 pub use self::getdata::{GetData, GETDATA_MAX_INVENTORY_LEN};
 pub use self::getheaders::{GetHeaders, GETHEADERS_MAX_RESPONSE_HEADERS};
+pub use self::getpkgtxns::{GetPkgTxns, GETPKGTXNS_MAX_HASHES_LEN};
 pub use self::headers::{Headers, HEADERS_MAX_HEADERS_LEN};
 pub use self::inv::{Inv, INV_MAX_INVENTORY_LEN};
 pub use self::mempool::MemPool;
 pub use self::merkle_block::MerkleBlock;
 pub use self::notfound::NotFound;
 pub use self::ping::Ping;
+pub use self::pkgtxns::{PkgTxns, PKGTXNS_MAX_TRANSACTIONS_LEN};
 pub use self::pong::Pong;
 pub use self::reject::Reject;
 pub use self::sendcompact::SendCompact;
 pub use self::sendheaders::SendHeaders;
+pub use self::sendpackages::{SendPackages, SENDPACKAGES_ANCESTOR_PACKAGE_RELAY};
 pub use self::tx::Tx;
 pub use self::verack::Verack;
 pub use self::version::Version;
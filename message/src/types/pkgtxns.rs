@@ -0,0 +1,58 @@
+use std::io;
+use chain::Transaction;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+pub const PKGTXNS_MAX_TRANSACTIONS_LEN: usize = 25;
+
+#[derive(Debug, PartialEq)]
+pub struct PkgTxns {
+	pub transactions: Vec<Transaction>,
+}
+
+impl PkgTxns {
+	pub fn with_transactions(transactions: Vec<Transaction>) -> Self {
+		PkgTxns {
+			transactions: transactions,
+		}
+	}
+}
+
+impl Payload for PkgTxns {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"pkgtxns"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let pkgtxns = PkgTxns {
+			transactions: reader.read_list_max(PKGTXNS_MAX_TRANSACTIONS_LEN)?,
+		};
+
+		Ok(pkgtxns)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append_list(&self.transactions);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::Transaction;
+	use serialization::{serialize_payload, deserialize_payload};
+	use super::PkgTxns;
+
+	#[test]
+	fn test_pkgtxns_serialize_deserialize() {
+		let transaction: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		let pkgtxns = PkgTxns::with_transactions(vec![transaction]);
+		let raw = serialize_payload(&pkgtxns, 0).unwrap();
+
+		assert_eq!(pkgtxns, deserialize_payload(&raw, 0).unwrap());
+	}
+}
@@ -0,0 +1,70 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Bit of the `versions` field, signalling support of ancestor package relay.
+pub const SENDPACKAGES_ANCESTOR_PACKAGE_RELAY: u64 = 1;
+
+#[derive(Debug, PartialEq)]
+pub struct SendPackages {
+	pub versions: u64,
+}
+
+impl SendPackages {
+	pub fn with_versions(versions: u64) -> Self {
+		SendPackages {
+			versions: versions,
+		}
+	}
+
+	pub fn supports_ancestor_packages(&self) -> bool {
+		self.versions & SENDPACKAGES_ANCESTOR_PACKAGE_RELAY != 0
+	}
+}
+
+impl Payload for SendPackages {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendpackages"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let send_packages = SendPackages {
+			versions: reader.read()?,
+		};
+
+		Ok(send_packages)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append(&self.versions);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use serialization::{serialize_payload, deserialize_payload};
+	use super::{SendPackages, SENDPACKAGES_ANCESTOR_PACKAGE_RELAY};
+
+	#[test]
+	fn test_sendpackages_serialize() {
+		let expected: Bytes = "0100000000000000".into();
+		let sendpackages = SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY);
+
+		assert_eq!(serialize_payload(&sendpackages, 0), Ok(expected));
+	}
+
+	#[test]
+	fn test_sendpackages_deserialize() {
+		let raw: Bytes = "0100000000000000".into();
+		let sendpackages: SendPackages = deserialize_payload(&raw, 0).unwrap();
+
+		assert_eq!(sendpackages, SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY));
+		assert!(sendpackages.supports_ancestor_packages());
+	}
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+use parking_lot::Mutex;
+
+/// Default histogram buckets (in seconds), same as in Prometheus client libraries
+pub const DEFAULT_BUCKETS: &'static [f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Histogram values at some moment
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+	/// Upper bounds of buckets with cumulative number of observations, which are less or equal to the bound
+	pub buckets: Vec<(f64, u64)>,
+	/// Total number of observations
+	pub count: u64,
+	/// Sum of all observed values
+	pub sum: f64,
+}
+
+#[derive(Debug)]
+struct HistogramData {
+	/// Number of observations in every bucket (not cumulative)
+	counts: Vec<u64>,
+	count: u64,
+	sum: f64,
+}
+
+/// Thread-safe histogram of observed values
+#[derive(Debug)]
+pub struct Histogram {
+	bounds: Vec<f64>,
+	data: Mutex<HistogramData>,
+}
+
+impl Default for Histogram {
+	fn default() -> Self {
+		Histogram::new(DEFAULT_BUCKETS)
+	}
+}
+
+impl Histogram {
+	/// Creates histogram with given buckets upper bounds, sorted in ascending order
+	pub fn new(bounds: &[f64]) -> Self {
+		Histogram {
+			bounds: bounds.to_vec(),
+			data: Mutex::new(HistogramData {
+				counts: vec![0; bounds.len()],
+				count: 0,
+				sum: 0f64,
+			}),
+		}
+	}
+
+	/// Adds single observation
+	pub fn observe(&self, value: f64) {
+		let mut data = self.data.lock();
+		if let Some(index) = self.bounds.iter().position(|bound| value <= *bound) {
+			data.counts[index] += 1;
+		}
+		data.count += 1;
+		data.sum += value;
+	}
+
+	/// Adds single observation of duration in seconds
+	pub fn observe_duration(&self, duration: Duration) {
+		self.observe(duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9)
+	}
+
+	/// Returns current histogram values
+	pub fn snapshot(&self) -> HistogramSnapshot {
+		let data = self.data.lock();
+		let mut cumulative = 0;
+		HistogramSnapshot {
+			buckets: self.bounds.iter().zip(data.counts.iter())
+				.map(|(bound, count)| {
+					cumulative += count;
+					(*bound, cumulative)
+				})
+				.collect(),
+			count: data.count,
+			sum: data.sum,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::Histogram;
+
+	#[test]
+	fn test_histogram() {
+		let histogram = Histogram::new(&[0.1, 1.0]);
+		histogram.observe(0.05);
+		histogram.observe(0.5);
+		histogram.observe_duration(Duration::from_millis(700));
+		histogram.observe(2.0);
+
+		let snapshot = histogram.snapshot();
+		assert_eq!(snapshot.buckets, vec![(0.1, 1), (1.0, 3)]);
+		assert_eq!(snapshot.count, 4);
+		assert!((snapshot.sum - 3.25).abs() < 1e-9);
+	}
+}
@@ -0,0 +1,19 @@
+//! Prometheus metrics: histograms, text exposition format and `/metrics` HTTP endpoint.
+
+#[macro_use]
+extern crate log;
+extern crate parking_lot;
+
+mod histogram;
+mod server;
+mod writer;
+
+pub use histogram::{Histogram, HistogramSnapshot, DEFAULT_BUCKETS};
+pub use server::{Server, start_http};
+pub use writer::{MetricsWriter, MetricType};
+
+/// Source of metrics, exported on every `/metrics` request
+pub trait MetricsSource: Send + Sync + 'static {
+	/// Writes current values of all metrics
+	fn write_metrics(&self, writer: &mut MetricsWriter);
+}
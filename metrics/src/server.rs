@@ -0,0 +1,113 @@
+use std::{io, thread};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use writer::MetricsWriter;
+use MetricsSource;
+
+/// Time to wait for the request, in seconds
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+
+/// Metrics HTTP server. Runs until the process exits.
+pub struct Server {
+	address: SocketAddr,
+}
+
+impl Server {
+	/// Address the server is listening on
+	pub fn address(&self) -> SocketAddr {
+		self.address
+	}
+}
+
+/// Starts HTTP server, which responds to `GET /metrics` requests with metrics from the source
+pub fn start_http<T>(address: &SocketAddr, source: T) -> Result<Server, io::Error> where T: MetricsSource {
+	let listener = TcpListener::bind(address)?;
+	let address = listener.local_addr()?;
+	let source = Arc::new(source);
+	thread::Builder::new()
+		.name("Metrics server".to_owned())
+		.spawn(move || {
+			for stream in listener.incoming() {
+				let result = stream.and_then(|stream| handle_connection(stream, &*source));
+				if let Err(err) = result {
+					trace!(target: "metrics", "Failed to serve metrics request: {}", err);
+				}
+			}
+		})?;
+
+	Ok(Server {
+		address: address,
+	})
+}
+
+fn handle_connection<T>(stream: TcpStream, source: &T) -> Result<(), io::Error> where T: MetricsSource {
+	stream.set_read_timeout(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))?;
+	let mut reader = BufReader::new(stream.try_clone()?);
+
+	let mut request_line = String::new();
+	reader.read_line(&mut request_line)?;
+	// skip request headers
+	loop {
+		let mut header = String::new();
+		if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+			break;
+		}
+	}
+
+	let mut parts = request_line.split_whitespace();
+	let (status, body) = match (parts.next(), parts.next()) {
+		(Some("GET"), Some("/metrics")) => {
+			let mut writer = MetricsWriter::default();
+			source.write_metrics(&mut writer);
+			("200 OK", writer.into_string())
+		},
+		(Some("GET"), Some(_)) => ("404 Not Found", "Not Found\n".to_owned()),
+		_ => ("405 Method Not Allowed", "Method Not Allowed\n".to_owned()),
+	};
+
+	let mut stream = stream;
+	write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body)?;
+	stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+	use std::net::TcpStream;
+	use writer::MetricsWriter;
+	use MetricsSource;
+	use super::start_http;
+
+	struct TestSource;
+
+	impl MetricsSource for TestSource {
+		fn write_metrics(&self, writer: &mut MetricsWriter) {
+			writer.gauge("test_value", "Test value", 42);
+		}
+	}
+
+	fn request(server: &super::Server, request: &str) -> String {
+		let mut stream = TcpStream::connect(server.address()).unwrap();
+		stream.write_all(request.as_bytes()).unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	}
+
+	#[test]
+	fn test_metrics_server() {
+		let server = start_http(&"127.0.0.1:0".parse().unwrap(), TestSource).unwrap();
+
+		let response = request(&server, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+		assert!(response.ends_with("\r\n\r\n# HELP test_value Test value\n# TYPE test_value gauge\ntest_value 42\n"));
+
+		let response = request(&server, "GET / HTTP/1.1\r\n\r\n");
+		assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+		let response = request(&server, "POST /metrics HTTP/1.1\r\n\r\n");
+		assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+	}
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::fmt::Write;
+use histogram::HistogramSnapshot;
+
+/// Type of the metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetricType {
+	Counter,
+	Gauge,
+	Histogram,
+}
+
+impl fmt::Display for MetricType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MetricType::Counter => write!(f, "counter"),
+			MetricType::Gauge => write!(f, "gauge"),
+			MetricType::Histogram => write!(f, "histogram"),
+		}
+	}
+}
+
+/// Writes metrics in Prometheus text exposition format
+#[derive(Debug, Default)]
+pub struct MetricsWriter {
+	buffer: String,
+}
+
+impl MetricsWriter {
+	/// Writes metric description. Must be followed by metric samples.
+	pub fn metric(&mut self, name: &str, help: &str, metric_type: MetricType) {
+		let _ = writeln!(self.buffer, "# HELP {} {}", name, help.replace('\\', "\\\\").replace('\n', "\\n"));
+		let _ = writeln!(self.buffer, "# TYPE {} {}", name, metric_type);
+	}
+
+	/// Writes single metric sample
+	pub fn sample<T>(&mut self, name: &str, labels: &[(&str, &str)], value: T) where T: fmt::Display {
+		self.buffer.push_str(name);
+		if !labels.is_empty() {
+			let labels = labels.iter()
+				.map(|&(label, value)| format!("{}=\"{}\"", label, escape_label_value(value)))
+				.collect::<Vec<_>>();
+			let _ = write!(self.buffer, "{{{}}}", labels.join(","));
+		}
+		let _ = writeln!(self.buffer, " {}", value);
+	}
+
+	/// Writes gauge without labels
+	pub fn gauge<T>(&mut self, name: &str, help: &str, value: T) where T: fmt::Display {
+		self.metric(name, help, MetricType::Gauge);
+		self.sample(name, &[], value);
+	}
+
+	/// Writes counter without labels
+	pub fn counter<T>(&mut self, name: &str, help: &str, value: T) where T: fmt::Display {
+		self.metric(name, help, MetricType::Counter);
+		self.sample(name, &[], value);
+	}
+
+	/// Writes histogram without labels
+	pub fn histogram(&mut self, name: &str, help: &str, snapshot: &HistogramSnapshot) {
+		self.metric(name, help, MetricType::Histogram);
+		let bucket_name = format!("{}_bucket", name);
+		for &(bound, count) in &snapshot.buckets {
+			self.sample(&bucket_name, &[("le", &bound.to_string())], count);
+		}
+		self.sample(&bucket_name, &[("le", "+Inf")], snapshot.count);
+		self.sample(&format!("{}_sum", name), &[], snapshot.sum);
+		self.sample(&format!("{}_count", name), &[], snapshot.count);
+	}
+
+	/// Returns written metrics
+	pub fn into_string(self) -> String {
+		self.buffer
+	}
+}
+
+fn escape_label_value(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use histogram::Histogram;
+	use super::{MetricsWriter, MetricType};
+
+	#[test]
+	fn test_metrics_writer() {
+		let histogram = Histogram::new(&[0.5, 1.0]);
+		histogram.observe(0.25);
+		histogram.observe(0.75);
+
+		let mut writer = MetricsWriter::default();
+		writer.gauge("pbtc_best_block_height", "Best block height", 100);
+		writer.metric("pbtc_p2p_received_bytes_total", "Received bytes", MetricType::Counter);
+		writer.sample("pbtc_p2p_received_bytes_total", &[("command", "inv")], 10);
+		writer.sample("pbtc_p2p_received_bytes_total", &[("command", "a\"b")], 20);
+		writer.histogram("pbtc_block_verification_seconds", "Block verification time", &histogram.snapshot());
+
+		assert_eq!(writer.into_string(), r#"# HELP pbtc_best_block_height Best block height
+# TYPE pbtc_best_block_height gauge
+pbtc_best_block_height 100
+# HELP pbtc_p2p_received_bytes_total Received bytes
+# TYPE pbtc_p2p_received_bytes_total counter
+pbtc_p2p_received_bytes_total{command="inv"} 10
+pbtc_p2p_received_bytes_total{command="a\"b"} 20
+# HELP pbtc_block_verification_seconds Block verification time
+# TYPE pbtc_block_verification_seconds histogram
+pbtc_block_verification_seconds_bucket{le="0.5"} 1
+pbtc_block_verification_seconds_bucket{le="1"} 2
+pbtc_block_verification_seconds_bucket{le="+Inf"} 2
+pbtc_block_verification_seconds_sum 1
+pbtc_block_verification_seconds_count 2
+"#);
+	}
+}
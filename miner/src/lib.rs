@@ -19,7 +19,7 @@ mod memory_pool;
 
 pub use block_assembler::{BlockAssembler, BlockTemplate};
 pub use cpu_miner::find_solution;
-pub use memory_pool::{MemoryPool, HashedOutPoint, Information as MemoryPoolInformation,
+pub use memory_pool::{MemoryPool, MemoryPoolListener, HashedOutPoint, Information as MemoryPoolInformation,
 	OrderingStrategy as MemoryPoolOrderingStrategy, DoubleSpendCheckResult, NonFinalDoubleSpendSet};
 pub use fee::{FeeCalculator, transaction_fee, transaction_fee_rate};
 
@@ -10,6 +10,7 @@ use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use chain::{IndexedTransaction, Transaction, OutPoint, TransactionOutput};
 use std::cmp::Ordering;
+use std::fmt;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::BTreeSet;
@@ -38,6 +39,22 @@ pub struct Information {
 	pub transactions_count: usize,
 	/// Total number of bytes occupied by transactions from the `MemoryPool`
 	pub transactions_size_in_bytes: usize,
+	/// Total miner fee of transactions from the `MemoryPool`
+	pub transactions_fee: u64,
+}
+
+/// Memory pool events listener
+pub trait MemoryPoolListener: Send + Sync {
+	/// Called when transaction is inserted to the memory pool
+	fn transaction_added(&self, hash: &H256);
+	/// Called when transaction is removed from the memory pool
+	fn transaction_removed(&self, hash: &H256);
+}
+
+impl fmt::Debug for dyn MemoryPoolListener {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("MemoryPoolListener")
+	}
 }
 
 /// Transactions memory pool
@@ -47,6 +64,11 @@ pub struct MemoryPool {
 	storage: Storage,
 	/// Do we accept zero fee transactions?
 	accept_zero_fee_transactions: bool,
+	/// Memory pool events listeners
+	listeners: Vec<Box<dyn MemoryPoolListener>>,
+	/// Transactions, removed for reverification. Listeners are only notified about
+	/// those which are not inserted back.
+	reverified_transactions: HashSet<H256>,
 }
 
 /// Single entry
@@ -81,6 +103,8 @@ struct Storage {
 	counter: u64,
 	/// Total transactions size (when serialized) in bytes
 	transactions_size_in_bytes: usize,
+	/// Total transactions miner fee
+	transactions_fee: u64,
 	/// By-hash storage
 	by_hash: HashMap<H256, Entry>,
 	/// Transactions by previous output
@@ -285,6 +309,7 @@ impl Storage {
 		Storage {
 			counter: 0,
 			transactions_size_in_bytes: 0,
+			transactions_fee: 0,
 			by_hash: HashMap::new(),
 			by_previous_output: HashMap::new(),
 			references: ReferenceStorage {
@@ -302,6 +327,7 @@ impl Storage {
 	pub fn insert(&mut self, entry: Entry) {
 		// update pool information
 		self.transactions_size_in_bytes += entry.size;
+		self.transactions_fee += entry.miner_fee;
 
 		// remember that this transactions depends on its inputs
 		for input_hash in entry.transaction.inputs.iter().map(|input| &input.previous_output.hash) {
@@ -411,6 +437,7 @@ impl Storage {
 			.map(|entry| {
 				// update pool information
 				self.transactions_size_in_bytes -= entry.size;
+				self.transactions_fee -= entry.miner_fee;
 
 				// forget that all inputs of this transaction are spent
 				for input in &entry.transaction.inputs {
@@ -649,9 +676,16 @@ impl MemoryPool {
 		MemoryPool {
 			storage: Storage::new(),
 			accept_zero_fee_transactions: false,
+			listeners: Vec::new(),
+			reverified_transactions: HashSet::new(),
 		}
 	}
 
+	/// Install memory pool events listener
+	pub fn install_listener(&mut self, listener: Box<dyn MemoryPoolListener>) {
+		self.listeners.push(listener);
+	}
+
 	/// Accept zero fee transactions.
 	pub fn accept_zero_fee_transactions(&mut self) {
 		self.accept_zero_fee_transactions = true;
@@ -659,17 +693,40 @@ impl MemoryPool {
 
 	/// Insert verified transaction to the `MemoryPool`
 	pub fn insert_verified<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) {
-		if let Some(entry) = self.make_entry(t, fc) {
-			let descendants = self.storage.remove_by_parent_hash(&entry.hash);
-			self.storage.insert(entry);
-			if let Some(descendants_iter) = descendants.map(|d| d.into_iter()) {
-				for descendant in descendants_iter {
-					if let Some(descendant_entry) = self.make_entry(descendant, fc) {
-						self.storage.insert(descendant_entry);
+		let hash = t.hash.clone();
+		match self.make_entry(t, fc) {
+			Some(entry) => {
+				let descendants = self.storage.remove_by_parent_hash(&entry.hash);
+				self.storage.insert(entry);
+				// reverified transaction is returned back => listeners are not notified
+				if !self.reverified_transactions.remove(&hash) {
+					self.notify_added(&hash);
+				}
+				if let Some(descendants_iter) = descendants.map(|d| d.into_iter()) {
+					for descendant in descendants_iter {
+						let descendant_hash = descendant.hash.clone();
+						match self.make_entry(descendant, fc) {
+							Some(descendant_entry) => self.storage.insert(descendant_entry),
+							None => self.notify_removed(&descendant_hash),
+						}
 					}
 				}
-			}
+			},
+			None => {
+				self.forget_reverified_transaction(&hash);
+			},
+		}
+	}
+
+	/// Insert verified package to the `MemoryPool`. Package transactions are sorted topologically
+	/// and their fee has been checked as a whole => zero fee transactions are also accepted.
+	pub fn insert_verified_package<FC: MemoryPoolFeeCalculator>(&mut self, package: Vec<IndexedTransaction>, fc: &FC) {
+		let accept_zero_fee_transactions = self.accept_zero_fee_transactions;
+		self.accept_zero_fee_transactions = true;
+		for transaction in package {
+			self.insert_verified(transaction, fc);
 		}
+		self.accept_zero_fee_transactions = accept_zero_fee_transactions;
 	}
 
 	/// Iterator over memory pool transactions according to specified strategy
@@ -680,8 +737,37 @@ impl MemoryPool {
 	/// Removes single transaction by its hash.
 	/// All descendants remain in the pool.
 	pub fn remove_by_hash(&mut self, h: &H256) -> Option<IndexedTransaction> {
-		self.storage.remove_by_hash(h)
-			.map(|entry| IndexedTransaction::new(entry.hash, entry.transaction))
+		let removed = self.storage.remove_by_hash(h)
+			.map(|entry| IndexedTransaction::new(entry.hash, entry.transaction));
+		match removed.is_some() {
+			true => self.notify_removed(h),
+			// transaction could be removed for reverification
+			false => {
+				self.forget_reverified_transaction(h);
+			},
+		}
+		removed
+	}
+
+	/// Removes all transactions from the `MemoryPool` for reverification, ordered by timestamp.
+	/// Listeners are not notified until the transaction is either inserted back, or forgotten.
+	pub fn remove_all_for_reverification(&mut self) -> Vec<IndexedTransaction> {
+		let count = self.storage.by_hash.len();
+		let removed = self.storage.remove_n_with_strategy(count, OrderingStrategy::ByTimestamp);
+		self.reverified_transactions.extend(removed.iter().map(|transaction| transaction.hash.clone()));
+		removed
+	}
+
+	/// Forgets transaction, removed for reverification, which will not be inserted back.
+	/// Returns true if it was removed for reverification.
+	pub fn forget_reverified_transaction(&mut self, h: &H256) -> bool {
+		match self.reverified_transactions.remove(h) {
+			true => {
+				self.notify_removed(h);
+				true
+			},
+			false => false,
+		}
 	}
 
 	/// Checks if `transaction` spends some outputs, already spent by inpool transactions.
@@ -691,7 +777,11 @@ impl MemoryPool {
 
 	/// Removes transaction (and all its descendants) which has spent given output
 	pub fn remove_by_prevout(&mut self, prevout: &OutPoint) -> Option<Vec<IndexedTransaction>> {
-		self.storage.remove_by_prevout(prevout)
+		let removed = self.storage.remove_by_prevout(prevout);
+		if let Some(ref removed) = removed {
+			self.notify_removed_transactions(removed);
+		}
+		removed
 	}
 
 	/// Reads single transaction by its hash.
@@ -715,13 +805,19 @@ impl MemoryPool {
 	/// Removes the 'top' transaction from the `MemoryPool` using selected strategy.
 	/// Ancestors are always removed before descendant transactions.
 	pub fn remove_with_strategy(&mut self, strategy: OrderingStrategy) -> Option<IndexedTransaction> {
-		self.storage.remove_with_strategy(strategy)
+		let removed = self.storage.remove_with_strategy(strategy);
+		if let Some(ref removed) = removed {
+			self.notify_removed(&removed.hash);
+		}
+		removed
 	}
 
 	/// Removes up to n transactions from the `MemoryPool`, using selected strategy.
 	/// Ancestors are always removed before descendant transactions.
 	pub fn remove_n_with_strategy(&mut self, n: usize, strategy: OrderingStrategy) -> Vec<IndexedTransaction> {
-		self.storage.remove_n_with_strategy(n, strategy)
+		let removed = self.storage.remove_n_with_strategy(n, strategy);
+		self.notify_removed_transactions(&removed);
+		removed
 	}
 
 	/// Set miner virtual fee for transaction
@@ -739,12 +835,26 @@ impl MemoryPool {
 		self.storage.contains(hash)
 	}
 
+	/// Returns hashes of in-pool ancestors of the transaction, sorted so that parents go before children
+	pub fn get_ancestors_ids(&self, hash: &H256) -> Option<Vec<H256>> {
+		self.storage.get_by_hash(hash).map(|entry| {
+			let mut ancestors: Vec<_> = entry.ancestors.iter().cloned().collect();
+			// every ancestor of transaction is also an ancestor of its descendants
+			// => parent always has less ancestors than its child
+			ancestors.sort_by_key(|ancestor| self.storage.get_by_hash(ancestor)
+				.map(|ancestor_entry| ancestor_entry.ancestors.len())
+				.expect("ancestors of in-pool transaction are in pool; qed"));
+			ancestors
+		})
+	}
+
 	/// Returns information on `MemoryPool` (as in GetMemPoolInfo RPC)
 	/// https://bitcoin.org/en/developer-reference#getmempoolinfo
 	pub fn information(&self) -> Information {
 		Information {
 			transactions_count: self.storage.by_hash.len(),
 			transactions_size_in_bytes: self.storage.transactions_size_in_bytes,
+			transactions_fee: self.storage.transactions_fee,
 		}
 	}
 
@@ -759,6 +869,24 @@ impl MemoryPool {
 		self.storage.is_output_spent(prevout)
 	}
 
+	fn notify_added(&self, hash: &H256) {
+		for listener in &self.listeners {
+			listener.transaction_added(hash);
+		}
+	}
+
+	fn notify_removed(&self, hash: &H256) {
+		for listener in &self.listeners {
+			listener.transaction_removed(hash);
+		}
+	}
+
+	fn notify_removed_transactions(&self, transactions: &[IndexedTransaction]) {
+		for transaction in transactions {
+			self.notify_removed(&transaction.hash);
+		}
+	}
+
 	fn make_entry<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) -> Option<Entry> {
 		let ancestors = self.get_ancestors(&t.raw);
 		let size = self.get_transaction_size(&t.raw);
@@ -883,10 +1011,12 @@ impl<'a> Iterator for MemoryPoolIterator<'a> {
 pub mod tests {
 	extern crate test_data;
 
+	use std::sync::{Arc, Mutex};
 	use chain::{Transaction, OutPoint};
 	use heapsize::HeapSizeOf;
-	use fee::NonZeroFeeCalculator;
-	use super::{MemoryPool, OrderingStrategy, DoubleSpendCheckResult};
+	use primitives::hash::H256;
+	use fee::{MemoryPoolFeeCalculator, NonZeroFeeCalculator};
+	use super::{MemoryPool, MemoryPoolListener, OrderingStrategy, DoubleSpendCheckResult};
 	use self::test_data::{ChainBuilder, TransactionBuilder};
 
 	fn to_memory_pool(chain: &mut ChainBuilder) -> MemoryPool {
@@ -1050,14 +1180,20 @@ pub mod tests {
 		let mut pool = MemoryPool::new();
 
 		let mut transactions_size = 0;
+		let mut transactions_fee = 0;
 		for transaction_index in 0..4 {
 			pool.insert_verified(chain.at(transaction_index).into(), &NonZeroFeeCalculator);
 			transactions_size += chain.size(transaction_index);
+			transactions_fee += 100_000_000 + 10 * (transaction_index as u64 + 1);
 
 			let info = pool.information();
 			assert_eq!(info.transactions_count, transaction_index + 1);
 			assert_eq!(info.transactions_size_in_bytes, transactions_size);
+			assert_eq!(info.transactions_fee, transactions_fee);
 		}
+
+		pool.remove_by_hash(&chain.hash(3));
+		assert_eq!(pool.information().transactions_fee, transactions_fee - 100_000_040);
 	}
 
 	#[test]
@@ -1413,4 +1549,132 @@ pub mod tests {
 		assert!(memory_pool.is_spent(&out1));
 		assert!(!memory_pool.is_spent(&out2));
 	}
+
+	#[derive(Default)]
+	struct DummyListener {
+		events: Arc<Mutex<Vec<(bool, H256)>>>,
+	}
+
+	impl MemoryPoolListener for DummyListener {
+		fn transaction_added(&self, hash: &H256) {
+			self.events.lock().unwrap().push((true, hash.clone()));
+		}
+
+		fn transaction_removed(&self, hash: &H256) {
+			self.events.lock().unwrap().push((false, hash.clone()));
+		}
+	}
+
+	#[test]
+	fn test_memory_pool_listener() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)	// transaction0
+			.into_input(0).add_output(20).store(chain)		// transaction0 -> transaction1
+			.reset().add_output(30).store(chain);			// transaction2
+
+		let listener = DummyListener::default();
+		let events = listener.events.clone();
+		let mut pool = MemoryPool::new();
+		pool.install_listener(Box::new(listener));
+
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		assert_eq!(*events.lock().unwrap(), vec![(true, chain.hash(0)), (true, chain.hash(1)), (true, chain.hash(2))]);
+		events.lock().unwrap().clear();
+
+		pool.remove_by_prevout(&OutPoint { hash: chain.hash(0), index: 0 });
+		assert_eq!(*events.lock().unwrap(), vec![(false, chain.hash(1))]);
+		events.lock().unwrap().clear();
+
+		pool.remove_by_hash(&chain.hash(2));
+		pool.remove_by_hash(&chain.hash(2));
+		assert_eq!(*events.lock().unwrap(), vec![(false, chain.hash(2))]);
+		events.lock().unwrap().clear();
+
+		pool.remove_n_with_strategy(10, OrderingStrategy::ByTimestamp);
+		assert_eq!(*events.lock().unwrap(), vec![(false, chain.hash(0))]);
+	}
+
+	#[test]
+	fn test_memory_pool_listener_is_not_notified_about_reverified_transactions() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)	// transaction0
+			.into_input(0).add_output(20).store(chain)		// transaction0 -> transaction1
+			.reset().add_output(30).store(chain)			// transaction2
+			.reset().add_output(40).store(chain);			// transaction3
+
+		let listener = DummyListener::default();
+		let events = listener.events.clone();
+		let mut pool = MemoryPool::new();
+		pool.install_listener(Box::new(listener));
+		for index in 0..4 {
+			pool.insert_verified(chain.at(index).into(), &NonZeroFeeCalculator);
+		}
+		events.lock().unwrap().clear();
+
+		let reverified = pool.remove_all_for_reverification();
+		assert_eq!(reverified.len(), 4);
+		assert_eq!(pool.information().transactions_count, 0);
+		assert!(events.lock().unwrap().is_empty());
+
+		// transactions, returned back to the pool
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		assert!(events.lock().unwrap().is_empty());
+
+		// transaction, which has failed reverification
+		assert!(pool.forget_reverified_transaction(&chain.hash(2)));
+		assert!(!pool.forget_reverified_transaction(&chain.hash(2)));
+		// transaction, which has been included in the block
+		assert!(pool.remove_by_hash(&chain.hash(3)).is_none());
+		assert_eq!(*events.lock().unwrap(), vec![(false, chain.hash(2)), (false, chain.hash(3))]);
+		events.lock().unwrap().clear();
+
+		// the same transaction is reported as new after it has been forgotten
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		assert_eq!(*events.lock().unwrap(), vec![(true, chain.hash(2))]);
+	}
+
+	#[test]
+	fn test_memory_pool_get_ancestors_ids() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)	// transaction0
+			.into_input(0).add_output(20).store(chain)		// transaction0 -> transaction1
+			.into_input(0).add_output(30).store(chain)		// transaction0 -> transaction1 -> transaction2
+			.reset().add_output(40).store(chain);			// transaction3
+		let pool = to_memory_pool(chain);
+
+		assert_eq!(pool.get_ancestors_ids(&chain.hash(2)), Some(vec![chain.hash(0), chain.hash(1)]));
+		assert_eq!(pool.get_ancestors_ids(&chain.hash(3)), Some(vec![]));
+		assert_eq!(pool.get_ancestors_ids(&H256::default()), None);
+	}
+
+	#[test]
+	fn test_memory_pool_insert_verified_package() {
+		struct ZeroFeeCalculator;
+
+		impl MemoryPoolFeeCalculator for ZeroFeeCalculator {
+			fn calculate(&self, _: &MemoryPool, _: &Transaction) -> u64 {
+				0
+			}
+		}
+
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)	// transaction0
+			.into_input(0).add_output(20).store(chain)		// transaction0 -> transaction1
+			.reset().add_output(30).store(chain);			// transaction2
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(2).into(), &ZeroFeeCalculator);
+		assert!(!pool.contains(&chain.hash(2)));
+
+		pool.insert_verified_package(vec![chain.at(0).into(), chain.at(1).into()], &ZeroFeeCalculator);
+		assert!(pool.contains(&chain.hash(0)));
+		assert!(pool.contains(&chain.hash(1)));
+
+		// zero fee transactions are still rejected outside of packages
+		pool.insert_verified(chain.at(2).into(), &ZeroFeeCalculator);
+		assert!(!pool.contains(&chain.hash(2)));
+	}
 }
@@ -1,4 +1,5 @@
 use hash::H256;
+use primitives::bigint::{Uint, U256};
 use {Network, Magic, Deployment};
 
 #[derive(Debug, Clone)]
@@ -28,6 +29,10 @@ pub struct ConsensusParams {
 	pub csv_deployment: Option<Deployment>,
 	/// BIP141, BIP143, BIP147 deployment
 	pub segwit_deployment: Option<Deployment>,
+	/// Scripts of this block ancestors are assumed to be valid (zero hash disables assumevalid).
+	pub assume_valid: H256,
+	/// Minimal work of the chain, containing `assume_valid` block.
+	pub minimum_chain_work: U256,
 }
 
 #[derive(Debug, Clone)]
@@ -90,7 +95,6 @@ impl ConsensusParams {
 					}),
 					ConsensusFork::BitcoinCash(_) => None,
 				},
-				fork: fork,
 				rule_change_activation_threshold: 1916, // 95%
 				miner_confirmation_window: 2016,
 				csv_deployment: Some(Deployment {
@@ -100,6 +104,17 @@ impl ConsensusParams {
 					timeout: 1493596800,
 					activation: Some(419328),
 				}),
+				assume_valid: match fork {
+					// 654683
+					ConsensusFork::BitcoinCore => H256::from_reversed_str("0000000000000000000b9d2ec5a352ecba0592946514a92f14319dc2b367fc72"),
+					ConsensusFork::BitcoinCash(_) => H256::default(),
+				},
+				minimum_chain_work: match fork {
+					ConsensusFork::BitcoinCore => "00000000000000000000000000000000000000001533efd8d716a517fe2c5008".parse()
+						.expect("hardcoded value should parse without errors"),
+					ConsensusFork::BitcoinCash(_) => U256::zero(),
+				},
+				fork: fork,
 			},
 			Network::Testnet => ConsensusParams {
 				network: network,
@@ -117,7 +132,6 @@ impl ConsensusParams {
 					}),
 					ConsensusFork::BitcoinCash(_) => None,
 				},
-				fork: fork,
 				rule_change_activation_threshold: 1512, // 75%
 				miner_confirmation_window: 2016,
 				csv_deployment: Some(Deployment {
@@ -127,6 +141,17 @@ impl ConsensusParams {
 					timeout: 1493596800,
 					activation: Some(770112),
 				}),
+				assume_valid: match fork {
+					// 1864000
+					ConsensusFork::BitcoinCore => H256::from_reversed_str("000000000000006433d1efec504c53ca332b64963c425395515b01977bd7b3b0"),
+					ConsensusFork::BitcoinCash(_) => H256::default(),
+				},
+				minimum_chain_work: match fork {
+					ConsensusFork::BitcoinCore => "0000000000000000000000000000000000000000000001db6ec4ac88cf2272c6".parse()
+						.expect("hardcoded value should parse without errors"),
+					ConsensusFork::BitcoinCash(_) => U256::zero(),
+				},
+				fork: fork,
 			},
 			Network::Regtest | Network::Unitest => ConsensusParams {
 				network: network,
@@ -154,6 +179,8 @@ impl ConsensusParams {
 					timeout: 0,
 					activation: Some(0),
 				}),
+				assume_valid: H256::default(),
+				minimum_chain_work: U256::zero(),
 			},
 		}
 	}
@@ -229,6 +256,14 @@ impl ConsensusFork {
 		}
 	}
 
+	/// Maximal serialized size of block at any height. Serialized SegWit block is never larger than its weight.
+	pub fn max_serialized_block_size(&self) -> usize {
+		match *self {
+			ConsensusFork::BitcoinCore => self.max_block_weight(0),
+			ConsensusFork::BitcoinCash(_) => Self::absolute_maximum_block_size(),
+		}
+	}
+
 	pub fn max_block_sigops(&self, height: u32, block_size: usize) -> usize {
 		match *self {
 			// according to REQ-5: max_block_sigops = 20000 * ceil((max(blocksize_bytes, 1000000) / 1000000))
@@ -302,6 +337,14 @@ mod tests {
 		assert_eq!(ConsensusParams::new(Network::Regtest, ConsensusFork::BitcoinCore).bip34_height, 100000000);
 	}
 
+	#[test]
+	fn test_consensus_params_assume_valid() {
+		assert!(!ConsensusParams::new(Network::Mainnet, ConsensusFork::BitcoinCore).assume_valid.is_zero());
+		assert!(!ConsensusParams::new(Network::Testnet, ConsensusFork::BitcoinCore).assume_valid.is_zero());
+		assert!(ConsensusParams::new(Network::Regtest, ConsensusFork::BitcoinCore).assume_valid.is_zero());
+		assert!(ConsensusParams::new(Network::Mainnet, ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Network::Mainnet))).assume_valid.is_zero());
+	}
+
 	#[test]
 	fn test_consensus_params_bip65_height() {
 		assert_eq!(ConsensusParams::new(Network::Mainnet, ConsensusFork::BitcoinCore).bip65_height, 388381);
@@ -338,6 +381,12 @@ mod tests {
 		assert_eq!(fork.min_block_size(fork.activation_height()), 1_000_001);
 	}
 
+	#[test]
+	fn test_consensus_fork_max_serialized_block_size() {
+		assert_eq!(ConsensusFork::BitcoinCore.max_serialized_block_size(), 4_000_000);
+		assert_eq!(ConsensusFork::BitcoinCash(BitcoinCashConsensusParams::new(Network::Mainnet)).max_serialized_block_size(), 32_000_000);
+	}
+
 	#[test]
 	fn test_consensus_fork_max_transaction_size() {
 		assert_eq!(ConsensusFork::BitcoinCore.max_transaction_size(), 1_000_000);
@@ -1,6 +1,6 @@
 use std::{net, path};
 use message::common::Services;
-use net::Config as NetConfig;
+use net::{Config as NetConfig, OnionPeer, TorControlConfig};
 use util::InternetProtocol;
 
 #[derive(Debug, Clone)]
@@ -15,6 +15,8 @@ pub struct Config {
 	pub connection: NetConfig,
 	/// Connect only to these nodes.
 	pub peers: Vec<net::SocketAddr>,
+	/// Connect only to these `.onion` nodes.
+	pub onion_peers: Vec<OnionPeer>,
 	/// Connect to these nodes to retrieve peer addresses, and disconnect.
 	pub seeds: Vec<String>,
 	/// p2p/nodes.csv file path.
@@ -23,4 +25,6 @@ pub struct Config {
 	pub preferable_services: Services,
 	/// Internet protocol.
 	pub internet_protocol: InternetProtocol,
+	/// Publish hidden service for the local listener using this Tor control port.
+	pub tor_control: Option<TorControlConfig>,
 }
@@ -1,22 +1,25 @@
 use std::{io, cmp};
 use futures::{Future, Poll, Async};
 use tokio_io::{AsyncRead, AsyncWrite};
-use message::{Message, MessageResult, Error};
-use message::types::{Version, Verack};
+use tokio_io::io::{read_exact, ReadExact};
+use bytes::Bytes;
+use message::{Message, MessageResult, Error, Payload};
+use message::types::{Version, Verack, SendPackages, SENDPACKAGES_ANCESTOR_PACKAGE_RELAY};
 use network::Magic;
-use io::{write_message, WriteMessage, ReadMessage, read_message};
+use io::{write_message, WriteMessage, ReadMessage, read_message, read_header, ReadHeader, read_payload, ReadPayload};
 
-pub fn handshake<A>(a: A, magic: Magic, version: Version, min_version: u32) -> Handshake<A> where A: AsyncWrite + AsyncRead {
+pub fn handshake<A>(a: A, magic: Magic, version: Version, min_version: u32, package_relay: bool) -> Handshake<A> where A: AsyncWrite + AsyncRead {
 	Handshake {
 		version: version.version(),
 		nonce: version.nonce(),
 		state: HandshakeState::SendVersion(write_message(a, version_message(magic, version))),
 		magic: magic,
 		min_version: min_version,
+		package_relay: package_relay,
 	}
 }
 
-pub fn accept_handshake<A>(a: A, magic: Magic, version: Version, min_version: u32) -> AcceptHandshake<A> where A: AsyncWrite + AsyncRead {
+pub fn accept_handshake<A>(a: A, magic: Magic, version: Version, min_version: u32, package_relay: bool) -> AcceptHandshake<A> where A: AsyncWrite + AsyncRead {
 	AcceptHandshake {
 		version: version.version(),
 		nonce: version.nonce(),
@@ -26,6 +29,7 @@ pub fn accept_handshake<A>(a: A, magic: Magic, version: Version, min_version: u3
 		},
 		magic: magic,
 		min_version: min_version,
+		package_relay: package_relay,
 	}
 }
 
@@ -37,6 +41,8 @@ pub fn negotiate_version(local: u32, other: u32) -> u32 {
 pub struct HandshakeResult {
 	pub version: Version,
 	pub negotiated_version: u32,
+	/// True if both sides have signalled support of package relay.
+	pub package_relay: bool,
 }
 
 fn version_message(magic: Magic, version: Version) -> Message<Version> {
@@ -47,16 +53,94 @@ fn verack_message(magic: Magic) -> Message<Verack> {
 	Message::new(magic, 0, &Verack).expect("verack message should always be serialized correctly")
 }
 
+fn sendpackages_message(magic: Magic) -> Message<SendPackages> {
+	Message::new(magic, 0, &SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY))
+		.expect("sendpackages message should always be serialized correctly")
+}
+
+/// Reads `verack`, which may be preceded by `sendpackages` and by other messages,
+/// unknown to us (like `wtxidrelay` or `sendaddrv2`). Unknown messages are skipped.
+fn read_verack<A>(a: A, magic: Magic) -> ReadVerack<A> where A: AsyncRead {
+	ReadVerack {
+		state: ReadVerackState::ReadHeader(read_header(a, magic)),
+		magic: magic,
+		package_relay: false,
+	}
+}
+
+enum ReadVerackState<A> {
+	ReadHeader(ReadHeader<A>),
+	ReadSendPackages(ReadPayload<SendPackages, A>),
+	ReadVerack(ReadPayload<Verack, A>),
+	SkipPayload(ReadExact<A, Bytes>),
+}
+
+/// Resolves to true if remote node has signalled support of ancestor package relay.
+struct ReadVerack<A> {
+	state: ReadVerackState<A>,
+	magic: Magic,
+	package_relay: bool,
+}
+
+impl<A> Future for ReadVerack<A> where A: AsyncRead {
+	type Item = (A, MessageResult<bool>);
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next_state = match self.state {
+				ReadVerackState::ReadHeader(ref mut future) => {
+					let (stream, header) = try_ready!(future.poll());
+					let header = match header {
+						Ok(header) => header,
+						Err(err) => return Ok((stream, Err(err)).into()),
+					};
+
+					if header.command == SendPackages::command() {
+						ReadVerackState::ReadSendPackages(read_payload(stream, 0, header.len as usize, header.checksum))
+					} else if header.command == Verack::command() {
+						ReadVerackState::ReadVerack(read_payload(stream, 0, header.len as usize, header.checksum))
+					} else {
+						ReadVerackState::SkipPayload(read_exact(stream, Bytes::new_with_len(header.len as usize)))
+					}
+				},
+				ReadVerackState::ReadSendPackages(ref mut future) => {
+					let (stream, sendpackages) = try_ready!(future.poll());
+					match sendpackages {
+						Ok(sendpackages) => self.package_relay = sendpackages.supports_ancestor_packages(),
+						Err(err) => return Ok((stream, Err(err)).into()),
+					}
+
+					ReadVerackState::ReadHeader(read_header(stream, self.magic))
+				},
+				ReadVerackState::ReadVerack(ref mut future) => {
+					let (stream, verack) = try_ready!(future.poll());
+					return Ok((stream, verack.map(|_| self.package_relay)).into());
+				},
+				ReadVerackState::SkipPayload(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					ReadVerackState::ReadHeader(read_header(stream, self.magic))
+				},
+			};
+			self.state = next_state;
+		}
+	}
+}
+
 enum HandshakeState<A> {
 	SendVersion(WriteMessage<Version, A>),
 	ReceiveVersion(ReadMessage<Version, A>),
+	SendPackages {
+		version: Option<Version>,
+		future: WriteMessage<SendPackages, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
 	},
 	ReceiveVerack {
 		version: Option<Version>,
-		future: ReadMessage<Verack, A>,
+		future: ReadVerack<A>,
 	},
 }
 
@@ -69,10 +153,18 @@ enum AcceptHandshakeState<A> {
 		version: Option<Version>,
 		future: WriteMessage<Version, A>,
 	},
+	SendPackages {
+		version: Option<Version>,
+		future: WriteMessage<SendPackages, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
 	},
+	ReceiveVerack {
+		version: Option<Version>,
+		future: ReadVerack<A>,
+	},
 }
 
 pub struct Handshake<A> {
@@ -81,6 +173,7 @@ pub struct Handshake<A> {
 	version: u32,
 	nonce: Option<u64>,
 	min_version: u32,
+	package_relay: bool,
 }
 
 pub struct AcceptHandshake<A> {
@@ -89,6 +182,7 @@ pub struct AcceptHandshake<A> {
 	version: u32,
 	nonce: Option<u64>,
 	min_version: u32,
+	package_relay: bool,
 }
 
 impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
@@ -118,8 +212,23 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 						}
 					}
 
+					// packages are only relayed to peers, which want to receive transactions
+					self.package_relay = self.package_relay && version.relay_transactions();
+					match self.package_relay {
+						true => HandshakeState::SendPackages {
+							version: Some(version),
+							future: write_message(stream, sendpackages_message(self.magic)),
+						},
+						false => HandshakeState::SendVerack {
+							version: Some(version),
+							future: write_message(stream, verack_message(self.magic)),
+						},
+					}
+				},
+				HandshakeState::SendPackages { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
 					HandshakeState::SendVerack {
-						version: Some(version),
+						version: version.take(),
 						future: write_message(stream, verack_message(self.magic)),
 					}
 				},
@@ -130,16 +239,21 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 
 					HandshakeState::ReceiveVerack {
 						version: Some(version),
-						future: read_message(stream, self.magic, 0),
+						future: read_verack(stream, self.magic),
 					}
 				},
 				HandshakeState::ReceiveVerack { ref mut version, ref mut future } => {
-					let (stream, _verack) = try_ready!(future.poll());
+					let (stream, remote_package_relay) = try_ready!(future.poll());
+					let remote_package_relay = match remote_package_relay {
+						Ok(remote_package_relay) => remote_package_relay,
+						Err(err) => return Ok((stream, Err(err)).into()),
+					};
 					let version = version.take().expect("verack must be preceded by version");
 
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						package_relay: self.package_relay && remote_package_relay,
 					};
 
 					return Ok(Async::Ready((stream, Ok(result))));
@@ -180,6 +294,23 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 					}
 				},
 				AcceptHandshakeState::SendVersion { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
+					let version = version.take().expect("verack must be preceded by version");
+
+					// packages are only relayed to peers, which want to receive transactions
+					self.package_relay = self.package_relay && version.relay_transactions();
+					match self.package_relay {
+						true => AcceptHandshakeState::SendPackages {
+							version: Some(version),
+							future: write_message(stream, sendpackages_message(self.magic)),
+						},
+						false => AcceptHandshakeState::SendVerack {
+							version: Some(version),
+							future: write_message(stream, verack_message(self.magic)),
+						},
+					}
+				},
+				AcceptHandshakeState::SendPackages { ref mut version, ref mut future } => {
 					let (stream, _) = try_ready!(future.poll());
 					AcceptHandshakeState::SendVerack {
 						version: version.take(),
@@ -189,11 +320,37 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 				AcceptHandshakeState::SendVerack { ref mut version, ref mut future } => {
 					let (stream, _) = try_ready!(future.poll());
 
+					// we only need to wait for remote verack if we have offered package relay:
+					// remote node could signal its support with sendpackages before verack
+					if self.package_relay {
+						AcceptHandshakeState::ReceiveVerack {
+							version: version.take(),
+							future: read_verack(stream, self.magic),
+						}
+					} else {
+						let version = version.take().expect("verack must be preceded by version");
+
+						let result = HandshakeResult {
+							negotiated_version: negotiate_version(self.version, version.version()),
+							version: version,
+							package_relay: false,
+						};
+
+						return Ok(Async::Ready((stream, Ok(result))));
+					}
+				},
+				AcceptHandshakeState::ReceiveVerack { ref mut version, ref mut future } => {
+					let (stream, remote_package_relay) = try_ready!(future.poll());
+					let remote_package_relay = match remote_package_relay {
+						Ok(remote_package_relay) => remote_package_relay,
+						Err(err) => return Ok((stream, Err(err)).into()),
+					};
 					let version = version.take().expect("verack must be preceded by version");
 
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						package_relay: self.package_relay && remote_package_relay,
 					};
 
 					return Ok(Async::Ready((stream, Ok(result))));
@@ -212,8 +369,8 @@ mod tests {
 	use bytes::Bytes;
 	use ser::Stream;
 	use network::{Network, ConsensusFork, BitcoinCashConsensusParams};
-	use message::{Message, Error};
-	use message::types::Verack;
+	use message::{Message, Error, to_raw_message};
+	use message::types::{Verack, SendPackages, SENDPACKAGES_ANCESTOR_PACKAGE_RELAY};
 	use message::types::version::{Version, V0, V106, V70001};
 	use super::{handshake, accept_handshake, HandshakeResult};
 
@@ -299,6 +456,7 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			package_relay: false,
 		};
 
 		let mut expected_stream = Stream::new();
@@ -310,7 +468,7 @@ mod tests {
 			write: Bytes::default(),
 		};
 
-		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		let hs = handshake(test_io, magic, local_version, 0, false).wait().unwrap();
 		assert_eq!(hs.0.write, expected_stream.out());
 		assert_eq!(hs.1.unwrap(), expected);
 	}
@@ -333,17 +491,144 @@ mod tests {
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			package_relay: false,
+		};
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let hs = accept_handshake(test_io, magic, local_version, 0, false).wait().unwrap();
+		assert_eq!(hs.0.write, expected_stream.out());
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
+	#[test]
+	fn test_handshake_with_package_relay() {
+		let magic = Network::Mainnet.magic(&ConsensusFork::BitcoinCore);
+		let version = 70012;
+		let local_version = local_version();
+		let remote_version = remote_version();
+		let sendpackages = SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &sendpackages).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			package_relay: true,
+		};
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &sendpackages).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0, true).wait().unwrap();
+		assert_eq!(hs.0.write, expected_stream.out());
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
+	#[test]
+	fn test_accept_handshake_without_remote_package_relay() {
+		let magic = Network::Mainnet.magic(&ConsensusFork::BitcoinCore);
+		let version = 70012;
+		let local_version = local_version();
+		let remote_version = remote_version();
+		let sendpackages = SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			package_relay: false,
 		};
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &sendpackages).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
 
-		let hs = accept_handshake(test_io, magic, local_version, 0).wait().unwrap();
+		let hs = accept_handshake(test_io, magic, local_version, 0, true).wait().unwrap();
 		assert_eq!(hs.0.write, expected_stream.out());
 		assert_eq!(hs.1.unwrap(), expected);
 	}
 
+	#[test]
+	fn test_handshake_skips_unknown_messages_before_verack() {
+		let magic = Network::Mainnet.magic(&ConsensusFork::BitcoinCore);
+		let version = 70012;
+		let local_version = local_version();
+		let remote_version = remote_version();
+		let sendpackages = SendPackages::with_versions(SENDPACKAGES_ANCESTOR_PACKAGE_RELAY);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(&to_raw_message(magic, "wtxidrelay".into(), &Bytes::default()));
+		remote_stream.append_slice(&to_raw_message(magic, "sendtxrcncl".into(), &"0100000000000000000000".into()));
+		remote_stream.append_slice(Message::new(magic, version, &sendpackages).unwrap().as_ref());
+		remote_stream.append_slice(&to_raw_message(magic, "sendaddrv2".into(), &Bytes::default()));
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			package_relay: true,
+		};
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0, true).wait().unwrap();
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
+	#[test]
+	fn test_accept_handshake_skips_unknown_messages_before_verack() {
+		let magic = Network::Mainnet.magic(&ConsensusFork::BitcoinCore);
+		let version = 70012;
+		let local_version = local_version();
+		let remote_version = remote_version();
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(&to_raw_message(magic, "wtxidrelay".into(), &Bytes::default()));
+		remote_stream.append_slice(&to_raw_message(magic, "sendaddrv2".into(), &Bytes::default()));
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: 70001,
+			package_relay: false,
+		};
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = accept_handshake(test_io, magic, local_version, 0, true).wait().unwrap();
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
 	#[test]
 	fn test_self_handshake() {
 		let magic = Network::Mainnet.magic(&ConsensusFork::BitcoinCore);
@@ -361,7 +646,7 @@ mod tests {
 
 		let expected = Error::InvalidVersion;
 
-		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		let hs = handshake(test_io, magic, local_version, 0, false).wait().unwrap();
 		assert_eq!(hs.1.unwrap_err(), expected);
 	}
 
@@ -382,7 +667,7 @@ mod tests {
 
 		let expected = Error::InvalidVersion;
 
-		let hs = accept_handshake(test_io, magic, local_version, 0).wait().unwrap();
+		let hs = accept_handshake(test_io, magic, local_version, 0, false).wait().unwrap();
 		assert_eq!(hs.1.unwrap_err(), expected);
 	}
 
@@ -404,7 +689,7 @@ mod tests {
 
 		let expected = Error::InvalidMagic;
 
-		let hs = accept_handshake(test_io, magic1, local_version, 0).wait().unwrap();
+		let hs = accept_handshake(test_io, magic1, local_version, 0, false).wait().unwrap();
 		assert_eq!(hs.1.unwrap_err(), expected);
 	}
 }
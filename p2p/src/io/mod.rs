@@ -5,7 +5,9 @@ mod read_message;
 mod read_any_message;
 mod read_payload;
 mod sharedtcpstream;
+mod v2_handshake;
 mod write_message;
+pub mod v2_transport;
 
 pub use self::deadline::{deadline, Deadline, DeadlineStatus};
 pub use self::handshake::{
@@ -16,4 +18,5 @@ pub use self::read_payload::{read_payload, ReadPayload};
 pub use self::read_message::{read_message, ReadMessage};
 pub use self::read_any_message::{read_any_message, ReadAnyMessage};
 pub use self::sharedtcpstream::SharedTcpStream;
+pub use self::v2_handshake::{v2_handshake, accept_v2_handshake, V2Handshake};
 pub use self::write_message::{write_message, WriteMessage};
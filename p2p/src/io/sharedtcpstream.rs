@@ -2,17 +2,49 @@ use std::sync::Arc;
 use std::net::Shutdown;
 use std::io::{Read, Write, Error};
 use futures::Poll;
+use parking_lot::Mutex;
 use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_core::net::TcpStream;
+use io::v2_transport::V2Transport;
+
+enum Transport {
+	/// Plaintext transport. `read_ahead` bytes have been read while detecting transport version.
+	V1 {
+		read_ahead: Vec<u8>,
+	},
+	/// BIP324 encrypted transport.
+	V2(V2Transport),
+}
 
 pub struct SharedTcpStream {
 	io: Arc<TcpStream>,
+	transport: Arc<Mutex<Transport>>,
 }
 
 impl SharedTcpStream {
 	pub fn new(a: Arc<TcpStream>) -> Self {
 		SharedTcpStream {
 			io: a,
+			transport: Arc::new(Mutex::new(Transport::V1 { read_ahead: Vec::new() })),
+		}
+	}
+
+	/// Returns v1 stream, which yields `read_ahead` bytes before reading from the socket.
+	pub fn with_read_ahead(self, read_ahead: Vec<u8>) -> Self {
+		*self.transport.lock() = Transport::V1 { read_ahead: read_ahead };
+		self
+	}
+
+	/// Returns stream, which translates v1 messages to and from v2 packets.
+	pub fn with_v2_transport(self, transport: V2Transport) -> Self {
+		*self.transport.lock() = Transport::V2(transport);
+		self
+	}
+
+	pub fn is_v2(&self) -> bool {
+		match *self.transport.lock() {
+			Transport::V2(_) => true,
+			Transport::V1 { .. } => false,
 		}
 	}
 
@@ -30,7 +62,17 @@ impl From<TcpStream> for SharedTcpStream {
 
 impl Read for SharedTcpStream {
 	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
-		Read::read(&mut (&*self.io as &TcpStream), buf)
+		let mut io = &*self.io as &TcpStream;
+		match *self.transport.lock() {
+			Transport::V1 { ref mut read_ahead } if !read_ahead.is_empty() => {
+				let len = ::std::cmp::min(buf.len(), read_ahead.len());
+				buf[..len].copy_from_slice(&read_ahead[..len]);
+				read_ahead.drain(..len);
+				Ok(len)
+			},
+			Transport::V1 { .. } => Read::read(&mut io, buf),
+			Transport::V2(ref mut transport) => transport.read(&mut io, buf),
+		}
 	}
 }
 
@@ -44,7 +86,11 @@ impl AsyncWrite for SharedTcpStream {
 
 impl Write for SharedTcpStream {
 	fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
-		Write::write(&mut (&*self.io as &TcpStream), buf)
+		let mut io = &*self.io as &TcpStream;
+		match *self.transport.lock() {
+			Transport::V1 { .. } => Write::write(&mut io, buf),
+			Transport::V2(ref mut transport) => transport.write(&mut io, buf),
+		}
 	}
 
 	fn flush(&mut self) -> Result<(), Error> {
@@ -54,6 +100,9 @@ impl Write for SharedTcpStream {
 
 impl Clone for SharedTcpStream {
 	fn clone(&self) -> Self {
-		SharedTcpStream::new(self.io.clone())
+		SharedTcpStream {
+			io: self.io.clone(),
+			transport: self.transport.clone(),
+		}
 	}
 }
@@ -0,0 +1,311 @@
+use std::{io, cmp, mem};
+use std::io::{Read, Write};
+use futures::{Future, Poll, Async};
+use rand::{Rng, thread_rng};
+use keys::EllSwiftKey;
+use network::Magic;
+use io::SharedTcpStream;
+use io::v2_transport::{V2Cipher, V2Transport, LENGTH_LEN, GARBAGE_TERMINATOR_LEN, MAX_GARBAGE_LEN, V1_PREFIX_LEN};
+
+const ELLSWIFT_LEN: usize = 64;
+
+/// Starts BIP324 handshake as the side, which opened the connection.
+/// Packets with messages larger than `max_payload_len` are rejected.
+pub fn v2_handshake(stream: SharedTcpStream, magic: Magic, max_payload_len: usize) -> V2Handshake {
+	V2Handshake {
+		stream: Some(stream),
+		magic: magic,
+		max_payload_len: max_payload_len,
+		initiator: true,
+		key: None,
+		garbage: random_garbage(),
+		cipher: None,
+		state: V2HandshakeState::SendKey,
+	}
+}
+
+/// Continues BIP324 handshake as the side, which accepted the connection.
+/// `prefix` are the bytes, which were already read while checking for v1 peer.
+pub fn accept_v2_handshake(stream: SharedTcpStream, magic: Magic, max_payload_len: usize, prefix: [u8; V1_PREFIX_LEN]) -> V2Handshake {
+	let mut key = [0u8; ELLSWIFT_LEN];
+	key[..V1_PREFIX_LEN].copy_from_slice(&prefix);
+	V2Handshake {
+		stream: Some(stream),
+		magic: magic,
+		max_payload_len: max_payload_len,
+		initiator: false,
+		key: None,
+		garbage: random_garbage(),
+		cipher: None,
+		state: V2HandshakeState::ReceiveKey {
+			buffer: key,
+			filled: V1_PREFIX_LEN,
+		},
+	}
+}
+
+fn random_garbage() -> Vec<u8> {
+	let mut rng = thread_rng();
+	let mut garbage = vec![0u8; rng.gen_range(0, MAX_GARBAGE_LEN + 1)];
+	rng.fill_bytes(&mut garbage);
+	garbage
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+enum V2HandshakeState {
+	/// Initiator sends its key and garbage.
+	SendKey,
+	ReceiveKey {
+		buffer: [u8; ELLSWIFT_LEN],
+		filled: usize,
+	},
+	Send {
+		buffer: Vec<u8>,
+		written: usize,
+	},
+	ReceiveGarbage {
+		buffer: Vec<u8>,
+	},
+	ReceiveLength {
+		garbage: Vec<u8>,
+		buffer: [u8; LENGTH_LEN],
+		filled: usize,
+	},
+	ReceivePacket {
+		garbage: Vec<u8>,
+		buffer: Vec<u8>,
+		filled: usize,
+	},
+	Finished,
+}
+
+pub struct V2Handshake {
+	stream: Option<SharedTcpStream>,
+	magic: Magic,
+	max_payload_len: usize,
+	initiator: bool,
+	key: Option<EllSwiftKey>,
+	garbage: Vec<u8>,
+	cipher: Option<V2Cipher>,
+	state: V2HandshakeState,
+}
+
+impl V2Handshake {
+	fn key(&mut self) -> io::Result<&EllSwiftKey> {
+		if self.key.is_none() {
+			let key = EllSwiftKey::generate()
+				.map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+			self.key = Some(key);
+		}
+		Ok(self.key.as_ref().expect("key has been generated above; qed"))
+	}
+
+	/// Garbage terminator followed by version packet, authenticated with our garbage.
+	fn terminator_and_version(&mut self) -> Vec<u8> {
+		let garbage = mem::replace(&mut self.garbage, Vec::new());
+		let cipher = self.cipher.as_mut().expect("cipher is created after keys are exchanged; qed");
+		let mut buffer = cipher.send_garbage_terminator().to_vec();
+		buffer.extend_from_slice(&cipher.encrypt(&[], &garbage, false));
+		buffer
+	}
+}
+
+/// Reads into `buf`, converting `WouldBlock` into `NotReady` and end of stream into error.
+fn poll_read(stream: &mut SharedTcpStream, buf: &mut [u8]) -> Poll<usize, io::Error> {
+	match stream.read(buf) {
+		Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+		Ok(read) => Ok(Async::Ready(read)),
+		Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Ok(Async::NotReady),
+		Err(err) => Err(err),
+	}
+}
+
+/// Number of bytes, which can be safely read without reading past garbage terminator.
+fn garbage_read_len(garbage: &[u8], terminator: &[u8]) -> usize {
+	let matched = (1..cmp::min(garbage.len(), terminator.len()) + 1)
+		.rev()
+		.find(|len| garbage[garbage.len() - len..] == terminator[..*len])
+		.unwrap_or(0);
+	terminator.len() - matched
+}
+
+impl Future for V2Handshake {
+	type Item = SharedTcpStream;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next = match self.state {
+				V2HandshakeState::SendKey => {
+					let mut buffer = self.key()?.encoded().to_vec();
+					buffer.extend_from_slice(&self.garbage);
+					V2HandshakeState::Send { buffer: buffer, written: 0 }
+				},
+				V2HandshakeState::ReceiveKey { ref mut buffer, ref mut filled } => {
+					while *filled < ELLSWIFT_LEN {
+						let stream = self.stream.as_mut().expect("stream is taken only after handshake is finished; qed");
+						*filled += try_ready!(poll_read(stream, &mut buffer[*filled..]));
+					}
+
+					let their_key = *buffer;
+					let initiator = self.initiator;
+					let secret = self.key()?.ecdh(&their_key, initiator)
+						.map_err(|_| invalid_data("invalid v2 handshake key"))?;
+					self.cipher = Some(V2Cipher::new(&secret, self.magic, initiator, self.max_payload_len));
+
+					let mut buffer = Vec::new();
+					if !initiator {
+						buffer.extend_from_slice(self.key()?.encoded());
+						buffer.extend_from_slice(&self.garbage);
+					}
+					buffer.extend_from_slice(&self.terminator_and_version());
+					V2HandshakeState::Send { buffer: buffer, written: 0 }
+				},
+				V2HandshakeState::Send { ref buffer, ref mut written } => {
+					while *written < buffer.len() {
+						let stream = self.stream.as_mut().expect("stream is taken only after handshake is finished; qed");
+						match stream.write(&buffer[*written..]) {
+							Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+							Ok(len) => *written += len,
+							Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Async::NotReady),
+							Err(err) => return Err(err),
+						}
+					}
+
+					if self.cipher.is_none() {
+						V2HandshakeState::ReceiveKey { buffer: [0u8; ELLSWIFT_LEN], filled: 0 }
+					} else {
+						V2HandshakeState::ReceiveGarbage { buffer: Vec::new() }
+					}
+				},
+				V2HandshakeState::ReceiveGarbage { ref mut buffer } => {
+					let terminator = *self.cipher.as_ref().expect("cipher is created after keys are exchanged; qed").recv_garbage_terminator();
+					loop {
+						if buffer.len() >= GARBAGE_TERMINATOR_LEN && buffer[buffer.len() - GARBAGE_TERMINATOR_LEN..] == terminator {
+							break;
+						}
+						if buffer.len() >= MAX_GARBAGE_LEN + GARBAGE_TERMINATOR_LEN {
+							return Err(invalid_data("v2 garbage terminator not found"));
+						}
+
+						let offset = buffer.len();
+						buffer.resize(offset + garbage_read_len(buffer, &terminator), 0);
+						let stream = self.stream.as_mut().expect("stream is taken only after handshake is finished; qed");
+						match poll_read(stream, &mut buffer[offset..]) {
+							Ok(Async::Ready(read)) => buffer.truncate(offset + read),
+							Ok(Async::NotReady) => {
+								buffer.truncate(offset);
+								return Ok(Async::NotReady);
+							},
+							Err(err) => return Err(err),
+						}
+					}
+
+					let mut garbage = mem::replace(buffer, Vec::new());
+					let len = garbage.len() - GARBAGE_TERMINATOR_LEN;
+					garbage.truncate(len);
+					V2HandshakeState::ReceiveLength { garbage: garbage, buffer: [0u8; LENGTH_LEN], filled: 0 }
+				},
+				V2HandshakeState::ReceiveLength { ref mut garbage, ref mut buffer, ref mut filled } => {
+					while *filled < LENGTH_LEN {
+						let stream = self.stream.as_mut().expect("stream is taken only after handshake is finished; qed");
+						*filled += try_ready!(poll_read(stream, &mut buffer[*filled..]));
+					}
+
+					let length = *buffer;
+					let len = self.cipher.as_mut().expect("cipher is created after keys are exchanged; qed").decrypt_length(length)?;
+					V2HandshakeState::ReceivePacket {
+						garbage: mem::replace(garbage, Vec::new()),
+						buffer: vec![0u8; len],
+						filled: 0,
+					}
+				},
+				V2HandshakeState::ReceivePacket { ref mut garbage, ref mut buffer, ref mut filled } => {
+					while *filled < buffer.len() {
+						let stream = self.stream.as_mut().expect("stream is taken only after handshake is finished; qed");
+						*filled += try_ready!(poll_read(stream, &mut buffer[*filled..]));
+					}
+
+					// only the first packet is authenticated with garbage
+					let aad = mem::replace(garbage, Vec::new());
+					let cipher = self.cipher.as_mut().expect("cipher is created after keys are exchanged; qed");
+					let (_, ignore) = cipher.decrypt(buffer, &aad)?;
+					if ignore {
+						V2HandshakeState::ReceiveLength { garbage: Vec::new(), buffer: [0u8; LENGTH_LEN], filled: 0 }
+					} else {
+						// contents of version packet are reserved for future extensions and ignored
+						V2HandshakeState::Finished
+					}
+				},
+				V2HandshakeState::Finished => {
+					let cipher = self.cipher.take().expect("cipher is created after keys are exchanged; qed");
+					let stream = self.stream.take().expect("poll V2Handshake after it's done");
+					trace!("Established v2 session {:?}", cipher.session_id());
+					return Ok(Async::Ready(stream.with_v2_transport(V2Transport::new(cipher, self.magic))));
+				},
+			};
+
+			self.state = next;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+	use std::net::TcpListener;
+	use futures::Future;
+	use tokio_core::reactor::Core;
+	use tokio_core::net::TcpStream;
+	use tokio_io::io::{write_all, read_exact};
+	use message::to_raw_message;
+	use bytes::Bytes;
+	use io::SharedTcpStream;
+	use io::v2_transport::V1_PREFIX_LEN;
+	use super::{v2_handshake, accept_v2_handshake, garbage_read_len};
+
+	#[test]
+	fn test_garbage_read_len() {
+		let terminator = [1u8, 2, 3, 4];
+		assert_eq!(garbage_read_len(&[], &terminator), 4);
+		assert_eq!(garbage_read_len(&[9, 9], &terminator), 4);
+		assert_eq!(garbage_read_len(&[9, 1], &terminator), 3);
+		assert_eq!(garbage_read_len(&[1, 2, 3], &terminator), 1);
+		assert_eq!(garbage_read_len(&[1, 2, 1, 2], &terminator), 2);
+	}
+
+	#[test]
+	fn test_v2_handshake_and_messages() {
+		let magic = 0xd9b4bef9;
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+
+		let responder = thread::spawn(move || {
+			let mut core = Core::new().unwrap();
+			let handle = core.handle();
+			let (stream, peer) = listener.accept().unwrap();
+			let stream: SharedTcpStream = TcpStream::from_stream(stream, &handle).unwrap().into();
+			let future = read_exact(stream, [0u8; V1_PREFIX_LEN])
+				.and_then(move |(stream, prefix)| accept_v2_handshake(stream, magic, 4_000_000, prefix))
+				.and_then(|stream| read_exact(stream, vec![0u8; 24 + 3]))
+				.and_then(|(stream, message)| write_all(stream, message));
+			core.run(future).unwrap();
+			peer
+		});
+
+		let mut core = Core::new().unwrap();
+		let handle = core.handle();
+		let message = to_raw_message(magic, "ping".into(), &Bytes::from(vec![1u8, 2, 3]));
+		let expected = message.clone();
+		let future = TcpStream::connect(&address, &handle)
+			.and_then(move |stream| v2_handshake(stream.into(), magic, 4_000_000))
+			.and_then(move |stream| write_all(stream, message))
+			.and_then(|(stream, message)| read_exact(stream, vec![0u8; message.len()]));
+		let (_, echoed) = core.run(future).unwrap();
+		assert_eq!(&echoed[..], &*expected);
+		responder.join().unwrap();
+	}
+}
@@ -0,0 +1,479 @@
+//! BIP324 v2 encrypted transport.
+//!
+//! Rest of the p2p module speaks v1 framed messages. `V2Transport` translates
+//! between them and v2 packets on the fly, so channels don't need to know which
+//! transport is being used.
+
+use std::{io, cmp};
+use std::io::{Read, Write};
+use crypto::hkdf_sha256;
+use crypto::chacha20poly1305::{FSChaCha20, FSChaCha20Poly1305, TAG_LEN};
+use message::{Command, to_raw_message};
+use network::Magic;
+use hash::H256;
+use bytes::Bytes;
+
+/// Length of encrypted packet length.
+pub const LENGTH_LEN: usize = 3;
+/// Length of garbage terminator.
+pub const GARBAGE_TERMINATOR_LEN: usize = 16;
+/// Maximal length of garbage sent before garbage terminator.
+pub const MAX_GARBAGE_LEN: usize = 4095;
+/// Packet header bit, which tells receiver to ignore the packet.
+const IGNORE_BIT: u8 = 0x80;
+/// Length of long message header: zero byte followed by 12 bytes command.
+const LONG_HEADER_LEN: usize = 1 + 12;
+/// v1 message header length.
+const V1_HEADER_LEN: usize = 24;
+/// Length of v1 `version` message header prefix, which identifies v1 peers.
+pub const V1_PREFIX_LEN: usize = 16;
+
+/// Commands encoded as one byte ids, in id order starting from 1.
+const SHORT_IDS: [&'static str; 28] = [
+	"addr", "block", "blocktxn", "cmpctblock", "feefilter", "filteradd", "filterclear",
+	"filterload", "getblocks", "getblocktxn", "getdata", "getheaders", "headers", "inv",
+	"mempool", "merkleblock", "notfound", "ping", "pong", "sendcmpct", "tx",
+	"getcfilters", "cfilter", "getcfheaders", "cfheaders", "getcfcheckpt", "cfcheckpt", "addrv2",
+];
+
+/// Returns first bytes, which v1 peer sends on the wire.
+pub fn v1_prefix(magic: Magic) -> [u8; V1_PREFIX_LEN] {
+	let mut prefix = [0u8; V1_PREFIX_LEN];
+	for i in 0..4 {
+		prefix[i] = (magic >> (i * 8)) as u8;
+	}
+	prefix[4..11].copy_from_slice(b"version");
+	prefix
+}
+
+/// Session ciphers, derived from ECDH shared secret.
+pub struct V2Cipher {
+	send_length: FSChaCha20,
+	send_aead: FSChaCha20Poly1305,
+	recv_length: FSChaCha20,
+	recv_aead: FSChaCha20Poly1305,
+	session_id: H256,
+	send_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	recv_garbage_terminator: [u8; GARBAGE_TERMINATOR_LEN],
+	/// Maximal accepted packet contents length.
+	max_contents_len: usize,
+}
+
+impl V2Cipher {
+	/// `max_payload_len` is the largest message payload accepted from peer, i.e. network max block size.
+	pub fn new(shared_secret: &H256, magic: Magic, initiator: bool, max_payload_len: usize) -> Self {
+		let mut salt = b"bitcoin_v2_shared_secret".to_vec();
+		for i in 0..4 {
+			salt.push((magic >> (i * 8)) as u8);
+		}
+
+		let derive = |info: &[u8]| {
+			let mut key = [0u8; 32];
+			hkdf_sha256(&salt, &**shared_secret, info, &mut key);
+			key
+		};
+
+		let garbage_terminators = derive(b"garbage_terminators");
+		let mut initiator_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		let mut responder_terminator = [0u8; GARBAGE_TERMINATOR_LEN];
+		initiator_terminator.copy_from_slice(&garbage_terminators[..GARBAGE_TERMINATOR_LEN]);
+		responder_terminator.copy_from_slice(&garbage_terminators[GARBAGE_TERMINATOR_LEN..]);
+
+		let initiator_length = FSChaCha20::new(derive(b"initiator_L"));
+		let initiator_aead = FSChaCha20Poly1305::new(derive(b"initiator_P"));
+		let responder_length = FSChaCha20::new(derive(b"responder_L"));
+		let responder_aead = FSChaCha20Poly1305::new(derive(b"responder_P"));
+		let session_id = derive(b"session_id").into();
+		let max_contents_len = LONG_HEADER_LEN + max_payload_len;
+
+		if initiator {
+			V2Cipher {
+				send_length: initiator_length,
+				send_aead: initiator_aead,
+				recv_length: responder_length,
+				recv_aead: responder_aead,
+				session_id: session_id,
+				send_garbage_terminator: initiator_terminator,
+				recv_garbage_terminator: responder_terminator,
+				max_contents_len: max_contents_len,
+			}
+		} else {
+			V2Cipher {
+				send_length: responder_length,
+				send_aead: responder_aead,
+				recv_length: initiator_length,
+				recv_aead: initiator_aead,
+				session_id: session_id,
+				send_garbage_terminator: responder_terminator,
+				recv_garbage_terminator: initiator_terminator,
+				max_contents_len: max_contents_len,
+			}
+		}
+	}
+
+	pub fn session_id(&self) -> &H256 {
+		&self.session_id
+	}
+
+	pub fn send_garbage_terminator(&self) -> &[u8; GARBAGE_TERMINATOR_LEN] {
+		&self.send_garbage_terminator
+	}
+
+	pub fn recv_garbage_terminator(&self) -> &[u8; GARBAGE_TERMINATOR_LEN] {
+		&self.recv_garbage_terminator
+	}
+
+	/// Encrypts packet contents. `aad` is only non-empty for the first packet.
+	pub fn encrypt(&mut self, contents: &[u8], aad: &[u8], ignore: bool) -> Vec<u8> {
+		let mut length = [0u8; LENGTH_LEN];
+		for i in 0..LENGTH_LEN {
+			length[i] = (contents.len() >> (i * 8)) as u8;
+		}
+		self.send_length.crypt(&mut length);
+
+		let mut plaintext = Vec::with_capacity(1 + contents.len());
+		plaintext.push(if ignore { IGNORE_BIT } else { 0 });
+		plaintext.extend_from_slice(contents);
+
+		let mut packet = length.to_vec();
+		packet.extend_from_slice(&self.send_aead.encrypt(aad, &plaintext));
+		packet
+	}
+
+	/// Decrypts length of the next packet and returns number of bytes which follow it.
+	pub fn decrypt_length(&mut self, mut length: [u8; LENGTH_LEN]) -> io::Result<usize> {
+		self.recv_length.crypt(&mut length);
+		let length = length[0] as usize | (length[1] as usize) << 8 | (length[2] as usize) << 16;
+		if length > self.max_contents_len {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "v2 packet is too large"));
+		}
+		Ok(1 + length + TAG_LEN)
+	}
+
+	/// Authenticates and decrypts packet. Returns contents and ignore flag.
+	pub fn decrypt(&mut self, packet: &[u8], aad: &[u8]) -> io::Result<(Vec<u8>, bool)> {
+		let mut plaintext = self.recv_aead.decrypt(aad, packet)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "v2 packet authentication failed"))?;
+		let ignore = plaintext[0] & IGNORE_BIT != 0;
+		plaintext.remove(0);
+		Ok((plaintext, ignore))
+	}
+}
+
+/// Encodes v1 message as v2 packet contents.
+pub fn encode_message(command: &[u8], payload: &[u8]) -> Vec<u8> {
+	let len = command.iter().position(|b| *b == 0).unwrap_or(command.len());
+	let command = &command[..len];
+	let mut contents = match SHORT_IDS.iter().position(|id| id.as_bytes() == command) {
+		Some(index) => vec![index as u8 + 1],
+		None => {
+			let mut contents = vec![0u8; LONG_HEADER_LEN];
+			contents[1..1 + command.len()].copy_from_slice(command);
+			contents
+		},
+	};
+	contents.extend_from_slice(payload);
+	contents
+}
+
+/// Decodes v2 packet contents into command and payload.
+/// Returns None if message has unknown short id. Such messages must be ignored.
+pub fn decode_message(contents: &[u8]) -> io::Result<Option<(Command, &[u8])>> {
+	let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid v2 message");
+	match contents.first() {
+		None => Err(invalid()),
+		Some(&0) => {
+			if contents.len() < LONG_HEADER_LEN {
+				return Err(invalid());
+			}
+			let command = &contents[1..LONG_HEADER_LEN];
+			let len = command.iter().position(|b| *b == 0).unwrap_or(command.len());
+			if command[len..].iter().any(|b| *b != 0) {
+				return Err(invalid());
+			}
+			let command = ::std::str::from_utf8(&command[..len]).map_err(|_| invalid())?;
+			let command = command.parse().map_err(|_| invalid())?;
+			Ok(Some((command, &contents[LONG_HEADER_LEN..])))
+		},
+		Some(&id) => {
+			Ok(SHORT_IDS.get(id as usize - 1).map(|command| ((*command).into(), &contents[1..])))
+		},
+	}
+}
+
+enum ReadState {
+	Length {
+		buffer: [u8; LENGTH_LEN],
+		filled: usize,
+	},
+	Packet {
+		buffer: Vec<u8>,
+		filled: usize,
+	},
+}
+
+/// Established v2 session.
+pub struct V2Transport {
+	cipher: V2Cipher,
+	magic: Magic,
+	read_state: ReadState,
+	/// Decrypted v1 framed bytes, which are waiting to be read.
+	decoded: Vec<u8>,
+	decoded_position: usize,
+	/// v1 framed message, which is being written.
+	pending: Vec<u8>,
+	/// Encrypted bytes waiting to be written to the socket.
+	encrypted: Vec<u8>,
+	encrypted_position: usize,
+	/// Last byte of pending message is reported as written only after the whole packet is flushed.
+	holding_last_byte: bool,
+}
+
+impl V2Transport {
+	pub fn new(cipher: V2Cipher, magic: Magic) -> Self {
+		V2Transport {
+			cipher: cipher,
+			magic: magic,
+			read_state: ReadState::Length { buffer: [0u8; LENGTH_LEN], filled: 0 },
+			decoded: Vec::new(),
+			decoded_position: 0,
+			pending: Vec::new(),
+			encrypted: Vec::new(),
+			encrypted_position: 0,
+			holding_last_byte: false,
+		}
+	}
+
+	pub fn session_id(&self) -> &H256 {
+		self.cipher.session_id()
+	}
+
+	/// Reads v1 framed messages decrypted from the stream.
+	pub fn read<R>(&mut self, stream: &mut R, buf: &mut [u8]) -> io::Result<usize> where R: Read {
+		loop {
+			if self.decoded_position < self.decoded.len() {
+				let len = cmp::min(buf.len(), self.decoded.len() - self.decoded_position);
+				buf[..len].copy_from_slice(&self.decoded[self.decoded_position..self.decoded_position + len]);
+				self.decoded_position += len;
+				return Ok(len);
+			}
+
+			let next = match self.read_state {
+				ReadState::Length { ref mut buffer, ref mut filled } => {
+					let read = stream.read(&mut buffer[*filled..])?;
+					if read == 0 {
+						return Ok(0);
+					}
+					*filled += read;
+					if *filled < LENGTH_LEN {
+						continue;
+					}
+					let len = self.cipher.decrypt_length(*buffer)?;
+					ReadState::Packet { buffer: vec![0u8; len], filled: 0 }
+				},
+				ReadState::Packet { ref mut buffer, ref mut filled } => {
+					let read = stream.read(&mut buffer[*filled..])?;
+					if read == 0 {
+						return Ok(0);
+					}
+					*filled += read;
+					if *filled < buffer.len() {
+						continue;
+					}
+					let (contents, ignore) = self.cipher.decrypt(buffer, &[])?;
+					if !ignore {
+						if let Some((command, payload)) = decode_message(&contents)? {
+							self.decoded = to_raw_message(self.magic, command, &Bytes::from(payload)).take();
+							self.decoded_position = 0;
+						}
+					}
+					ReadState::Length { buffer: [0u8; LENGTH_LEN], filled: 0 }
+				},
+			};
+
+			self.read_state = next;
+		}
+	}
+
+	/// Accepts v1 framed messages and writes them encrypted to the stream.
+	pub fn write<W>(&mut self, stream: &mut W, buf: &[u8]) -> io::Result<usize> where W: Write {
+		if self.encrypted_position < self.encrypted.len() {
+			self.flush_encrypted(stream)?;
+			if self.holding_last_byte {
+				self.holding_last_byte = false;
+				return Ok(1);
+			}
+		}
+
+		let needed = if self.pending.len() < V1_HEADER_LEN {
+			V1_HEADER_LEN - self.pending.len()
+		} else {
+			let payload_len = self.pending[16] as usize | (self.pending[17] as usize) << 8 |
+				(self.pending[18] as usize) << 16 | (self.pending[19] as usize) << 24;
+			V1_HEADER_LEN + payload_len - self.pending.len()
+		};
+		let len = cmp::min(needed, buf.len());
+		self.pending.extend_from_slice(&buf[..len]);
+
+		// message is complete once the header says there is no more payload to wait for
+		let complete = self.pending.len() >= V1_HEADER_LEN && {
+			let payload_len = self.pending[16] as usize | (self.pending[17] as usize) << 8 |
+				(self.pending[18] as usize) << 16 | (self.pending[19] as usize) << 24;
+			self.pending.len() == V1_HEADER_LEN + payload_len
+		};
+		if !complete {
+			return Ok(len);
+		}
+
+		let contents = encode_message(&self.pending[4..16], &self.pending[V1_HEADER_LEN..]);
+		self.encrypted = self.cipher.encrypt(&contents, &[], false);
+		self.encrypted_position = 0;
+		self.pending.clear();
+
+		match self.flush_encrypted(stream) {
+			Ok(()) => Ok(len),
+			Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+				self.holding_last_byte = true;
+				if len > 1 {
+					Ok(len - 1)
+				} else {
+					Err(io::ErrorKind::WouldBlock.into())
+				}
+			},
+			Err(err) => Err(err),
+		}
+	}
+
+	fn flush_encrypted<W>(&mut self, stream: &mut W) -> io::Result<()> where W: Write {
+		while self.encrypted_position < self.encrypted.len() {
+			let written = stream.write(&self.encrypted[self.encrypted_position..])?;
+			if written == 0 {
+				return Err(io::ErrorKind::WriteZero.into());
+			}
+			self.encrypted_position += written;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use hash::H256;
+	use message::{Command, to_raw_message};
+	use bytes::Bytes;
+	use super::{V2Cipher, V2Transport, encode_message, decode_message, v1_prefix};
+
+	fn cipher_pair() -> (V2Cipher, V2Cipher) {
+		let secret = H256::from(7);
+		(V2Cipher::new(&secret, 0xd9b4bef9, true, 4_000_000), V2Cipher::new(&secret, 0xd9b4bef9, false, 4_000_000))
+	}
+
+	#[test]
+	fn test_v1_prefix() {
+		assert_eq!(&v1_prefix(0xd9b4bef9), b"\xf9\xbe\xb4\xd9version\0\0\0\0\0");
+	}
+
+	#[test]
+	fn test_short_ids() {
+		let mut command = [0u8; 12];
+		command[..4].copy_from_slice(b"ping");
+		assert_eq!(encode_message(&command, &[1, 2]), vec![18, 1, 2]);
+		let (decoded, payload) = decode_message(&[18, 1, 2]).unwrap().unwrap();
+		assert_eq!(decoded, Command::from("ping"));
+		assert_eq!(payload, &[1, 2]);
+
+		command = [0u8; 12];
+		command[..7].copy_from_slice(b"version");
+		let encoded = encode_message(&command, &[3]);
+		assert_eq!(&encoded[..8], b"\0version");
+		assert_eq!(encoded.len(), 14);
+		let (decoded, payload) = decode_message(&encoded).unwrap().unwrap();
+		assert_eq!(decoded, Command::from("version"));
+		assert_eq!(payload, &[3]);
+
+		assert!(decode_message(&[0, b'a']).is_err());
+		// unknown short ids are ignored
+		assert_eq!(decode_message(&[200]).unwrap(), None);
+	}
+
+	#[test]
+	fn test_cipher_roundtrip() {
+		let (mut initiator, mut responder) = cipher_pair();
+		assert_eq!(initiator.session_id(), responder.session_id());
+		assert_eq!(initiator.send_garbage_terminator(), responder.recv_garbage_terminator());
+		assert_eq!(initiator.recv_garbage_terminator(), responder.send_garbage_terminator());
+
+		let packet = initiator.encrypt(b"hello", b"garbage", false);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		let len = responder.decrypt_length(length).unwrap();
+		assert_eq!(len, packet.len() - 3);
+		assert_eq!(responder.decrypt(&packet[3..], b"garbage").unwrap(), (b"hello".to_vec(), false));
+
+		let packet = responder.encrypt(&[], &[], true);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		initiator.decrypt_length(length).unwrap();
+		assert_eq!(initiator.decrypt(&packet[3..], &[]).unwrap(), (vec![], true));
+	}
+
+	#[test]
+	fn test_cipher_bip324_vector() {
+		// first vector from BIP324 packet_encoding_test_vectors.csv
+		let secret: H256 = "c6992a117f5edbea70c3f511d32d26b9798be4b81a62eaee1a5acaa8459a3592".into();
+		let mut cipher = V2Cipher::new(&secret, 0xd9b4bef9, true, 4_000_000);
+		let session_id: H256 = "ce72dffb015da62b0d0f5474cab8bc72605225b0cee3f62312ec680ec5f41ba5".into();
+		assert_eq!(cipher.session_id(), &session_id);
+		assert_eq!(&cipher.send_garbage_terminator()[..], &*Bytes::from("faef555dfcdb936425d84aba524758f3"));
+		assert_eq!(&cipher.recv_garbage_terminator()[..], &*Bytes::from("02cb8ff24307a6e27de3b4e7ea3fa65b"));
+		// vector packet is the second one sent
+		cipher.encrypt(&[], &[], false);
+		assert_eq!(cipher.encrypt(&[0x8e], &[], false), Bytes::from("7530d2a18720162ac09c25329a60d75adf36eda3c3").take());
+	}
+
+	#[test]
+	fn test_max_contents_len() {
+		let secret = H256::from(7);
+		let mut initiator = V2Cipher::new(&secret, 0xd9b4bef9, true, 1000);
+		let mut responder = V2Cipher::new(&secret, 0xd9b4bef9, false, 1000);
+
+		// max size payload with long message header is accepted
+		let packet = initiator.encrypt(&vec![0u8; 13 + 1000], &[], false);
+		let mut length = [0u8; 3];
+		length.copy_from_slice(&packet[..3]);
+		assert_eq!(responder.decrypt_length(length).unwrap(), packet.len() - 3);
+
+		let packet = initiator.encrypt(&vec![0u8; 13 + 1001], &[], false);
+		length.copy_from_slice(&packet[..3]);
+		assert!(responder.decrypt_length(length).is_err());
+	}
+
+	#[test]
+	fn test_transport_translates_v1_messages() {
+		let (initiator, responder) = cipher_pair();
+		let mut sender = V2Transport::new(initiator, 0xd9b4bef9);
+		let mut receiver = V2Transport::new(responder, 0xd9b4bef9);
+
+		let first = to_raw_message(0xd9b4bef9, "ping".into(), &Bytes::from(vec![1u8; 8]));
+		let second = to_raw_message(0xd9b4bef9, "version".into(), &Bytes::from(vec![2u8; 100]));
+		let mut wire = Vec::new();
+		// write in small pieces, as the socket would accept them
+		for message in &[&first, &second] {
+			let mut written = 0;
+			while written < message.len() {
+				let end = ::std::cmp::min(written + 7, message.len());
+				written += sender.write(&mut wire, &message[written..end]).unwrap();
+			}
+		}
+
+		let mut reader = io::Cursor::new(wire);
+		let mut decoded = vec![0u8; first.len() + second.len()];
+		let mut read = 0;
+		while read < decoded.len() {
+			read += receiver.read(&mut reader, &mut decoded[read..]).unwrap();
+		}
+		assert_eq!(&decoded[..first.len()], &*first);
+		assert_eq!(&decoded[first.len()..], &*second);
+	}
+}
@@ -17,6 +17,7 @@ extern crate message;
 extern crate primitives;
 extern crate serialization as ser;
 extern crate network;
+extern crate keys;
 
 mod io;
 mod net;
@@ -30,7 +31,7 @@ mod p2p;
 pub use primitives::{hash, bytes};
 
 pub use config::Config;
-pub use net::Config as NetConfig;
+pub use net::{Config as NetConfig, Proxy, ProxyCredentials, OnionPeer, TorControlConfig, NetworkStats, CommandStats};
 pub use p2p::{P2P, Context};
 pub use event_loop::{event_loop, forever};
 pub use util::{NodeTableError, PeerId, PeerInfo, InternetProtocol, Direction};
@@ -3,25 +3,57 @@ use std::time::Duration;
 use futures::{Future, Poll};
 use tokio_core::reactor::Handle;
 use tokio_core::net::TcpStream;
+use tokio_io::io::{read_exact, ReadExact};
 use network::Magic;
 use message::{MessageResult};
-use io::{accept_handshake, AcceptHandshake, Deadline, deadline};
+use message::types::Version;
+use io::{accept_handshake, AcceptHandshake, Deadline, deadline, accept_v2_handshake, V2Handshake, SharedTcpStream};
+use io::v2_transport::{v1_prefix, V1_PREFIX_LEN};
 use net::{Config, Connection};
 
 pub fn accept_connection(stream: TcpStream, handle: &Handle, config: &Config, address: net::SocketAddr) -> Deadline<AcceptConnection> {
+	let stream: SharedTcpStream = stream.into();
+	let state = if config.v2_transport {
+		AcceptConnectionState::DetectTransport {
+			future: read_exact(stream, [0u8; V1_PREFIX_LEN]),
+			version: Some(config.version(&address)),
+		}
+	} else {
+		AcceptConnectionState::Handshake(accept_handshake(stream, config.magic, config.version(&address), config.protocol_minimum, config.package_relay))
+	};
+
 	let accept = AcceptConnection {
-		handshake: accept_handshake(stream, config.magic, config.version(&address), config.protocol_minimum),
+		state: state,
 		magic: config.magic,
+		max_block_size: config.max_block_size,
+		protocol_minimum: config.protocol_minimum,
 		address: address,
+		package_relay: config.package_relay,
 	};
 
 	deadline(Duration::new(5, 0), handle, accept).expect("Failed to create timeout")
 }
 
+enum AcceptConnectionState {
+	/// Reads first bytes to tell v1 peers from v2 ones.
+	DetectTransport {
+		future: ReadExact<SharedTcpStream, [u8; V1_PREFIX_LEN]>,
+		version: Option<Version>,
+	},
+	V2Handshake {
+		future: V2Handshake,
+		version: Option<Version>,
+	},
+	Handshake(AcceptHandshake<SharedTcpStream>),
+}
+
 pub struct AcceptConnection {
-	handshake: AcceptHandshake<TcpStream>,
+	state: AcceptConnectionState,
 	magic: Magic,
+	max_block_size: usize,
+	protocol_minimum: u32,
 	address: net::SocketAddr,
+	package_relay: bool,
 }
 
 impl Future for AcceptConnection {
@@ -29,19 +61,46 @@ impl Future for AcceptConnection {
 	type Error = io::Error;
 
 	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-		let (stream, result) = try_ready!(self.handshake.poll());
-		let result = match result {
-			Ok(result) => result,
-			Err(err) => return Ok(Err(err).into()),
-		};
-		let connection = Connection {
-			stream: stream.into(),
-			services: result.version.services(),
-			version: result.negotiated_version,
-			version_message: result.version, 
-			magic: self.magic,
-			address: self.address,
+		let next = match self.state {
+			AcceptConnectionState::DetectTransport { ref mut future, ref mut version } => {
+				let (stream, prefix) = try_ready!(future.poll());
+				let version = version.take().expect("state DetectTransport must have version");
+				if prefix == v1_prefix(self.magic) {
+					let stream = stream.with_read_ahead(prefix.to_vec());
+					AcceptConnectionState::Handshake(accept_handshake(stream, self.magic, version, self.protocol_minimum, self.package_relay))
+				} else {
+					AcceptConnectionState::V2Handshake {
+						future: accept_v2_handshake(stream, self.magic, self.max_block_size, prefix),
+						version: Some(version),
+					}
+				}
+			},
+			AcceptConnectionState::V2Handshake { ref mut future, ref mut version } => {
+				let stream = try_ready!(future.poll());
+				let version = version.take().expect("state V2Handshake must have version");
+				AcceptConnectionState::Handshake(accept_handshake(stream, self.magic, version, self.protocol_minimum, self.package_relay))
+			},
+			AcceptConnectionState::Handshake(ref mut future) => {
+				let (stream, result) = try_ready!(future.poll());
+				let result = match result {
+					Ok(result) => result,
+					Err(err) => return Ok(Err(err).into()),
+				};
+				let connection = Connection {
+					stream: stream,
+					services: result.version.services(),
+					version: result.negotiated_version,
+					version_message: result.version,
+					magic: self.magic,
+					address: self.address,
+					package_relay: result.package_relay,
+				};
+				return Ok(Ok(connection).into());
+			},
 		};
-		Ok(Ok(connection).into())
+
+		self.state = next;
+		// by polling again, we register new future
+		self.poll()
 	}
 }
@@ -4,20 +4,36 @@ use message::common::{Services, NetAddress};
 use message::types::version::{Version, V0, V106, V70001};
 use util::time::{Time, RealTime};
 use util::nonce::{NonceGenerator, RandomNonce};
+use net::Proxy;
 
 #[derive(Debug, Clone)]
 pub struct Config {
 	pub protocol_version: u32,
 	pub protocol_minimum: u32,
 	pub magic: Magic,
+	/// Maximal size of block on the network. Larger v2 packets are rejected.
+	pub max_block_size: usize,
 	pub local_address: SocketAddr,
 	pub services: Services,
 	pub user_agent: String,
 	pub start_height: i32,
 	pub relay: bool,
+	/// Route outbound connections through SOCKS5 proxy.
+	pub proxy: Option<Proxy>,
+	/// SOCKS5 proxy used to reach `.onion` peers. Falls back to `proxy`.
+	pub onion_proxy: Option<Proxy>,
+	/// Try BIP324 encrypted transport on outbound connections and accept it on inbound ones.
+	pub v2_transport: bool,
+	/// Negotiate package relay with peers, which want to receive transactions.
+	pub package_relay: bool,
 }
 
 impl Config {
+	/// Proxy used to reach `.onion` peers.
+	pub fn onion_proxy(&self) -> Option<&Proxy> {
+		self.onion_proxy.as_ref().or(self.proxy.as_ref())
+	}
+
 	pub fn version(&self, to: &SocketAddr) -> Version {
 		Version::V70001(V0 {
 			version: self.protocol_version,
@@ -7,21 +7,47 @@ use tokio_core::net::{TcpStream, TcpStreamNew};
 use network::Magic;
 use message::Error;
 use message::types::Version;
-use io::{handshake, Handshake, Deadline, deadline};
-use net::{Config, Connection};
+use io::{handshake, Handshake, Deadline, deadline, v2_handshake, V2Handshake, SharedTcpStream};
+use net::{Config, Connection, Proxy, named_address};
+use net::socks5::{socks5_connect, Socks5Connect, Target, Credentials};
 
 pub fn connect(address: &SocketAddr, handle: &Handle, config: &Config) -> Deadline<Connect> {
+	let proxy = config.proxy.as_ref().map(|proxy| (proxy, Target::Address(*address)));
+	connect_to(*address, proxy, handle, config)
+}
+
+/// Connects to peer by name through given proxy. The name is resolved by proxy.
+pub fn connect_named(host: &str, port: u16, proxy: &Proxy, handle: &Handle, config: &Config) -> Deadline<Connect> {
+	let target = Target::Domain(host.to_owned(), port);
+	connect_to(named_address(host, port), Some((proxy, target)), handle, config)
+}
+
+fn connect_to(address: SocketAddr, proxy: Option<(&Proxy, Target)>, handle: &Handle, config: &Config) -> Deadline<Connect> {
+	let (future, proxy, timeout) = match proxy {
+		Some((proxy, target)) => (
+			TcpStream::connect(&proxy.address, handle),
+			Some((target, proxy.connection_credentials())),
+			// circuits are built lazily, so give proxy some more time
+			Duration::new(30, 0),
+		),
+		None => (TcpStream::connect(&address, handle), None, Duration::new(5, 0)),
+	};
+
 	let connect = Connect {
 		state: ConnectState::TcpConnect {
-			future: TcpStream::connect(address, handle),
-			version: Some(config.version(address)),
+			future: future,
+			version: Some(config.version(&address)),
 		},
 		magic: config.magic,
-		address: *address,
+		max_block_size: config.max_block_size,
+		address: address,
 		protocol_minimum: config.protocol_minimum,
+		proxy: proxy,
+		v2_transport: config.v2_transport,
+		package_relay: config.package_relay,
 	};
 
-	deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
+	deadline(timeout, handle, connect).expect("Failed to create timeout")
 }
 
 enum ConnectState {
@@ -29,15 +55,40 @@ enum ConnectState {
 		future: TcpStreamNew,
 		version: Option<Version>,
 	},
-	Handshake(Handshake<TcpStream>),
+	ProxyConnect {
+		future: Socks5Connect<TcpStream>,
+		version: Option<Version>,
+	},
+	V2Handshake {
+		future: V2Handshake,
+		version: Option<Version>,
+	},
+	Handshake(Handshake<SharedTcpStream>),
 	Connected,
 }
 
 pub struct Connect {
 	state: ConnectState,
 	magic: Magic,
+	max_block_size: usize,
 	address: SocketAddr,
 	protocol_minimum: u32,
+	proxy: Option<(Target, Option<Credentials>)>,
+	v2_transport: bool,
+	package_relay: bool,
+}
+
+impl Connect {
+	fn handshake(&self, stream: TcpStream, version: Version) -> ConnectState {
+		if self.v2_transport {
+			ConnectState::V2Handshake {
+				future: v2_handshake(stream.into(), self.magic, self.max_block_size),
+				version: Some(version),
+			}
+		} else {
+			ConnectState::Handshake(handshake(stream.into(), self.magic, version, self.protocol_minimum, self.package_relay))
+		}
+	}
 }
 
 impl Future for Connect {
@@ -49,7 +100,23 @@ impl Future for Connect {
 			ConnectState::TcpConnect { ref mut future, ref mut version } => {
 				let stream = try_ready!(future.poll());
 				let version = version.take().expect("state TcpConnect must have version");
-				let handshake = handshake(stream, self.magic, version, self.protocol_minimum);
+				match self.proxy.take() {
+					Some((target, credentials)) => {
+						let future = socks5_connect(stream, target, credentials);
+						(ConnectState::ProxyConnect { future: future, version: Some(version) }, Async::NotReady)
+					},
+					None => (self.handshake(stream, version), Async::NotReady),
+				}
+			},
+			ConnectState::ProxyConnect { ref mut future, ref mut version } => {
+				let stream = try_ready!(future.poll());
+				let version = version.take().expect("state ProxyConnect must have version");
+				(self.handshake(stream, version), Async::NotReady)
+			},
+			ConnectState::V2Handshake { ref mut future, ref mut version } => {
+				let stream = try_ready!(future.poll());
+				let version = version.take().expect("state V2Handshake must have version");
+				let handshake = handshake(stream, self.magic, version, self.protocol_minimum, self.package_relay);
 				(ConnectState::Handshake(handshake), Async::NotReady)
 			},
 			ConnectState::Handshake(ref mut future) => {
@@ -59,12 +126,13 @@ impl Future for Connect {
 					Err(err) => return Ok(Async::Ready(Err(err))),
 				};
 				let connection = Connection {
-					stream: stream.into(),
+					stream: stream,
 					services: result.version.services(),
 					version: result.negotiated_version,
 					version_message: result.version,
 					magic: self.magic,
 					address: self.address,
+					package_relay: result.package_relay,
 				};
 				(ConnectState::Connected, Async::Ready(Ok(connection)))
 			},
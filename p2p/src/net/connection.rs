@@ -11,4 +11,6 @@ pub struct Connection {
 	pub magic: Magic,
 	pub services: Services,
 	pub address: net::SocketAddr,
+	/// True if package relay has been negotiated during handshake.
+	pub package_relay: bool,
 }
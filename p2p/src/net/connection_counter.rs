@@ -11,6 +11,8 @@ pub struct ConnectionCounter {
 	max_inbound_connections: u32,
 	/// Maximum number of outbound connections.
 	max_outbound_connections: u32,
+	/// Number of outbound connections requested on top of the maximum.
+	extra_outbound_connections: AtomicUsize,
 }
 
 impl ConnectionCounter {
@@ -20,6 +22,7 @@ impl ConnectionCounter {
 			current_outbound_connections: AtomicUsize::new(0),
 			max_inbound_connections: max_inbound_connections,
 			max_outbound_connections: max_outbound_connections,
+			extra_outbound_connections: AtomicUsize::new(0),
 		}
 	}
 
@@ -45,6 +48,16 @@ impl ConnectionCounter {
 		self.current_outbound_connections.fetch_sub(1, Ordering::AcqRel);
 	}
 
+	/// Requests one more outbound connection, even if the maximum is already reached.
+	pub fn note_extra_outbound_connection_needed(&self) {
+		self.extra_outbound_connections.fetch_add(1, Ordering::AcqRel);
+	}
+
+	/// Returns number of requested extra outbound connections and resets the request.
+	pub fn take_extra_outbound_connections(&self) -> u32 {
+		self.extra_outbound_connections.swap(0, Ordering::AcqRel) as u32
+	}
+
 	/// Returns number of inbound connections needed to reach the maximum
 	pub fn inbound_connections_needed(&self) -> u32 {
 		let ic = self.inbound_connections();
@@ -109,4 +122,16 @@ mod tests {
 		assert_eq!(cc.outbound_connections_needed(), 3);
 		assert_eq!(cc.outbound_connections(), (1, 4));
 	}
+
+	#[test]
+	fn test_extra_outbound_connections() {
+		let cc = ConnectionCounter::new(0, 1);
+		cc.note_new_outbound_connection();
+		assert_eq!(cc.outbound_connections_needed(), 0);
+		assert_eq!(cc.take_extra_outbound_connections(), 0);
+		cc.note_extra_outbound_connection_needed();
+		assert_eq!(cc.outbound_connections_needed(), 0);
+		assert_eq!(cc.take_extra_outbound_connections(), 1);
+		assert_eq!(cc.take_extra_outbound_connections(), 0);
+	}
 }
@@ -58,6 +58,7 @@ impl Connections {
 			version: connection.version,
 			version_message: connection.version_message,
 			magic: connection.magic,
+			package_relay: connection.package_relay,
 		};
 
 		let session = T::new_session(context, peer_info.clone(), SYNCHRONOUS_RESPONSES);
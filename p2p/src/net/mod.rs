@@ -6,14 +6,18 @@ mod connection;
 mod connection_counter;
 mod connections;
 mod peer_context;
+mod socks5;
 mod stats;
+mod tor;
 
 pub use self::accept_connection::{AcceptConnection, accept_connection};
 pub use self::channel::Channel;
 pub use self::config::Config;
-pub use self::connect::{Connect, connect};
+pub use self::connect::{Connect, connect, connect_named};
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
 pub use self::peer_context::PeerContext;
-pub use self::stats::PeerStats;
+pub use self::stats::{PeerStats, NetworkStats, CommandStats};
+pub use self::socks5::{Proxy, Credentials as ProxyCredentials};
+pub use self::tor::{OnionPeer, OnionService, TorControlConfig, TorControlError, add_onion, named_address, is_onion_address};
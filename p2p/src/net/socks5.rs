@@ -0,0 +1,420 @@
+//! SOCKS5 client (RFC 1928) with username/password authentication (RFC 1929).
+
+use std::io;
+use std::net::{SocketAddr, IpAddr};
+use futures::{Future, Poll, Async};
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_io::io::{WriteAll, ReadExact, write_all, read_exact};
+use rand;
+
+const SOCKS_VERSION: u8 = 0x05;
+const AUTH_VERSION: u8 = 0x01;
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_USERNAME_PASSWORD: u8 = 0x02;
+const METHOD_NOT_ACCEPTABLE: u8 = 0xff;
+const COMMAND_CONNECT: u8 = 0x01;
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// Username and password used to authenticate with the proxy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+	pub username: String,
+	pub password: String,
+}
+
+/// SOCKS5 proxy configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proxy {
+	/// Address of the proxy server.
+	pub address: SocketAddr,
+	/// Static credentials, if the proxy requires authentication.
+	pub credentials: Option<Credentials>,
+	/// Use random credentials for every connection. Tor isolates
+	/// streams with different credentials onto different circuits.
+	pub randomize_credentials: bool,
+}
+
+impl Proxy {
+	pub fn new(address: SocketAddr) -> Self {
+		Proxy {
+			address: address,
+			credentials: None,
+			randomize_credentials: false,
+		}
+	}
+
+	/// Credentials which should be used for the next connection.
+	pub fn connection_credentials(&self) -> Option<Credentials> {
+		if self.randomize_credentials {
+			return Some(Credentials {
+				username: format!("{:x}", rand::random::<u64>()),
+				password: format!("{:x}", rand::random::<u64>()),
+			});
+		}
+
+		self.credentials.clone()
+	}
+}
+
+/// Destination which proxy should connect to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+	Address(SocketAddr),
+	Domain(String, u16),
+}
+
+impl Target {
+	fn serialize(&self) -> io::Result<Vec<u8>> {
+		let mut request = vec![SOCKS_VERSION, COMMAND_CONNECT, 0x00];
+		let port = match *self {
+			Target::Address(ref address) => {
+				match address.ip() {
+					IpAddr::V4(ip) => {
+						request.push(ADDRESS_IPV4);
+						request.extend_from_slice(&ip.octets());
+					},
+					IpAddr::V6(ip) => {
+						request.push(ADDRESS_IPV6);
+						request.extend_from_slice(&ip.octets());
+					},
+				}
+				address.port()
+			},
+			Target::Domain(ref domain, port) => {
+				if domain.is_empty() || domain.len() > 255 {
+					return Err(invalid_data("SOCKS5 domain name must be between 1 and 255 bytes long"));
+				}
+				request.push(ADDRESS_DOMAIN);
+				request.push(domain.len() as u8);
+				request.extend_from_slice(domain.as_bytes());
+				port
+			},
+		};
+		request.push((port >> 8) as u8);
+		request.push(port as u8);
+		Ok(request)
+	}
+}
+
+/// Negotiates connection to `target` over already established connection with the proxy.
+pub fn socks5_connect<A>(a: A, target: Target, credentials: Option<Credentials>) -> Socks5Connect<A> where A: AsyncRead + AsyncWrite {
+	let greeting = match credentials {
+		Some(_) => vec![SOCKS_VERSION, 2, METHOD_NO_AUTH, METHOD_USERNAME_PASSWORD],
+		None => vec![SOCKS_VERSION, 1, METHOD_NO_AUTH],
+	};
+
+	Socks5Connect {
+		state: Socks5ConnectState::SendGreeting(write_all(a, greeting)),
+		target: target,
+		credentials: credentials,
+	}
+}
+
+enum Socks5ConnectState<A> {
+	SendGreeting(WriteAll<A, Vec<u8>>),
+	ReceiveMethod(ReadExact<A, [u8; 2]>),
+	SendCredentials(WriteAll<A, Vec<u8>>),
+	ReceiveAuthStatus(ReadExact<A, [u8; 2]>),
+	SendRequest(WriteAll<A, Vec<u8>>),
+	ReceiveReply(ReadExact<A, [u8; 5]>),
+	ReceiveBoundAddress(ReadExact<A, Vec<u8>>),
+	Finished,
+}
+
+pub struct Socks5Connect<A> {
+	state: Socks5ConnectState<A>,
+	target: Target,
+	credentials: Option<Credentials>,
+}
+
+impl<A> Socks5Connect<A> where A: AsyncRead + AsyncWrite {
+	fn request(&self, a: A) -> io::Result<Socks5ConnectState<A>> {
+		Ok(Socks5ConnectState::SendRequest(write_all(a, self.target.serialize()?)))
+	}
+}
+
+impl<A> Future for Socks5Connect<A> where A: AsyncRead + AsyncWrite {
+	type Item = A;
+	type Error = io::Error;
+
+	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		loop {
+			let next = match self.state {
+				Socks5ConnectState::SendGreeting(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					Socks5ConnectState::ReceiveMethod(read_exact(stream, [0u8; 2]))
+				},
+				Socks5ConnectState::ReceiveMethod(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					if reply[0] != SOCKS_VERSION {
+						return Err(invalid_data("Proxy is not a SOCKS5 server"));
+					}
+
+					match (reply[1], self.credentials.as_ref()) {
+						(METHOD_NO_AUTH, _) => self.request(stream)?,
+						(METHOD_USERNAME_PASSWORD, Some(credentials)) => {
+							Socks5ConnectState::SendCredentials(write_all(stream, credentials_request(credentials)?))
+						},
+						(METHOD_NOT_ACCEPTABLE, _) => return Err(other("SOCKS5 proxy rejected all authentication methods")),
+						_ => return Err(invalid_data("SOCKS5 proxy selected unsupported authentication method")),
+					}
+				},
+				Socks5ConnectState::SendCredentials(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					Socks5ConnectState::ReceiveAuthStatus(read_exact(stream, [0u8; 2]))
+				},
+				Socks5ConnectState::ReceiveAuthStatus(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					if reply[0] != AUTH_VERSION || reply[1] != 0x00 {
+						return Err(io::Error::new(io::ErrorKind::PermissionDenied, "SOCKS5 proxy authentication failed"));
+					}
+					self.request(stream)?
+				},
+				Socks5ConnectState::SendRequest(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					Socks5ConnectState::ReceiveReply(read_exact(stream, [0u8; 5]))
+				},
+				Socks5ConnectState::ReceiveReply(ref mut future) => {
+					let (stream, reply) = try_ready!(future.poll());
+					if reply[0] != SOCKS_VERSION {
+						return Err(invalid_data("Proxy is not a SOCKS5 server"));
+					}
+					if reply[1] != 0x00 {
+						return Err(reply_error(reply[1]));
+					}
+
+					// first byte of the bound address (or the domain length) has already been read
+					let remaining = match reply[3] {
+						ADDRESS_IPV4 => 4 - 1 + 2,
+						ADDRESS_IPV6 => 16 - 1 + 2,
+						ADDRESS_DOMAIN => reply[4] as usize + 2,
+						_ => return Err(invalid_data("SOCKS5 proxy replied with unknown address type")),
+					};
+					Socks5ConnectState::ReceiveBoundAddress(read_exact(stream, vec![0u8; remaining]))
+				},
+				Socks5ConnectState::ReceiveBoundAddress(ref mut future) => {
+					let (stream, _) = try_ready!(future.poll());
+					self.state = Socks5ConnectState::Finished;
+					return Ok(Async::Ready(stream));
+				},
+				Socks5ConnectState::Finished => panic!("poll Socks5Connect after it's done"),
+			};
+
+			self.state = next;
+		}
+	}
+}
+
+fn credentials_request(credentials: &Credentials) -> io::Result<Vec<u8>> {
+	let username = credentials.username.as_bytes();
+	let password = credentials.password.as_bytes();
+	if username.is_empty() || username.len() > 255 || password.len() > 255 {
+		return Err(invalid_data("SOCKS5 username and password must be at most 255 bytes long"));
+	}
+
+	let mut request = Vec::with_capacity(3 + username.len() + password.len());
+	request.push(AUTH_VERSION);
+	request.push(username.len() as u8);
+	request.extend_from_slice(username);
+	request.push(password.len() as u8);
+	request.extend_from_slice(password);
+	Ok(request)
+}
+
+fn reply_error(code: u8) -> io::Error {
+	let (kind, description) = match code {
+		0x01 => (io::ErrorKind::Other, "general SOCKS server failure"),
+		0x02 => (io::ErrorKind::PermissionDenied, "connection not allowed by ruleset"),
+		0x03 => (io::ErrorKind::Other, "network unreachable"),
+		0x04 => (io::ErrorKind::Other, "host unreachable"),
+		0x05 => (io::ErrorKind::ConnectionRefused, "connection refused"),
+		0x06 => (io::ErrorKind::TimedOut, "TTL expired"),
+		0x07 => (io::ErrorKind::InvalidInput, "command not supported"),
+		0x08 => (io::ErrorKind::InvalidInput, "address type not supported"),
+		_ => (io::ErrorKind::Other, "unknown error"),
+	};
+	io::Error::new(kind, format!("SOCKS5 proxy: {}", description))
+}
+
+fn invalid_data(description: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, description)
+}
+
+fn other(description: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, description)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io, thread};
+	use std::io::{Read, Write};
+	use std::net::{TcpListener, SocketAddr};
+	use futures::{Future, Poll};
+	use tokio_io::{AsyncRead, AsyncWrite};
+	use tokio_io::io::read_exact;
+	use tokio_core::reactor::Core;
+	use tokio_core::net::TcpStream;
+	use bytes::Bytes;
+	use super::{socks5_connect, Target, Credentials, Proxy};
+
+	#[derive(Debug)]
+	pub struct TestIo {
+		read: io::Cursor<Bytes>,
+		write: Bytes,
+	}
+
+	impl io::Read for TestIo {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			io::Read::read(&mut self.read, buf)
+		}
+	}
+
+	impl AsyncRead for TestIo {}
+
+	impl io::Write for TestIo {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			io::Write::write(&mut self.write, buf)
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			io::Write::flush(&mut self.write)
+		}
+	}
+
+	impl AsyncWrite for TestIo {
+		fn shutdown(&mut self) -> Poll<(), io::Error> {
+			Ok(().into())
+		}
+	}
+
+	fn test_io(read: Vec<u8>) -> TestIo {
+		TestIo {
+			read: io::Cursor::new(read.into()),
+			write: Bytes::default(),
+		}
+	}
+
+	#[test]
+	fn test_socks5_connect_no_auth_ipv4() {
+		let target = Target::Address("127.0.0.1:8333".parse().unwrap());
+		let io = test_io(vec![
+			0x05, 0x00,
+			0x05, 0x00, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x01, 0x20, 0x8d,
+		]);
+
+		let io = socks5_connect(io, target, None).wait().unwrap();
+		let expected: Bytes = vec![
+			0x05, 0x01, 0x00,
+			0x05, 0x01, 0x00, 0x01, 0x7f, 0x00, 0x00, 0x01, 0x20, 0x8d,
+		].into();
+		assert_eq!(io.write, expected);
+	}
+
+	#[test]
+	fn test_socks5_connect_username_password_domain() {
+		let target = Target::Domain("abc.onion".into(), 8333);
+		let credentials = Credentials {
+			username: "user".into(),
+			password: "pass".into(),
+		};
+		let io = test_io(vec![
+			0x05, 0x02,
+			0x01, 0x00,
+			0x05, 0x00, 0x00, 0x03, 0x03, b'x', b'y', b'z', 0x00, 0x00,
+		]);
+
+		let io = socks5_connect(io, target, Some(credentials)).wait().unwrap();
+		let mut expected = vec![0x05, 0x02, 0x00, 0x02];
+		expected.extend_from_slice(&[0x01, 0x04, b'u', b's', b'e', b'r', 0x04, b'p', b'a', b's', b's']);
+		expected.extend_from_slice(&[0x05, 0x01, 0x00, 0x03, 0x09]);
+		expected.extend_from_slice(b"abc.onion");
+		expected.extend_from_slice(&[0x20, 0x8d]);
+		let expected: Bytes = expected.into();
+		assert_eq!(io.write, expected);
+	}
+
+	#[test]
+	fn test_socks5_connect_auth_failed() {
+		let target = Target::Address("127.0.0.1:8333".parse().unwrap());
+		let credentials = Credentials {
+			username: "user".into(),
+			password: "wrong".into(),
+		};
+		let io = test_io(vec![0x05, 0x02, 0x01, 0x01]);
+
+		let err = socks5_connect(io, target, Some(credentials)).wait().unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+	}
+
+	#[test]
+	fn test_socks5_connect_refused() {
+		let target = Target::Address("127.0.0.1:8333".parse().unwrap());
+		let io = test_io(vec![
+			0x05, 0x00,
+			0x05, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+		]);
+
+		let err = socks5_connect(io, target, None).wait().unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+	}
+
+	#[test]
+	fn test_socks5_connect_no_acceptable_methods() {
+		let target = Target::Address("127.0.0.1:8333".parse().unwrap());
+		let io = test_io(vec![0x05, 0xff]);
+		assert!(socks5_connect(io, target, None).wait().is_err());
+	}
+
+	#[test]
+	fn test_proxy_randomized_credentials() {
+		let mut proxy = Proxy::new("127.0.0.1:9050".parse().unwrap());
+		assert_eq!(proxy.connection_credentials(), None);
+
+		proxy.randomize_credentials = true;
+		let first = proxy.connection_credentials().unwrap();
+		let second = proxy.connection_credentials().unwrap();
+		assert!(first != second);
+	}
+
+	/// Minimal SOCKS5 server, which accepts single connection without authentication
+	/// and answers `pong` to whatever was sent through the tunnel.
+	fn spawn_test_server() -> (SocketAddr, thread::JoinHandle<Vec<u8>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		let handle = thread::spawn(move || {
+			let (mut stream, _) = listener.accept().unwrap();
+			let mut greeting = [0u8; 3];
+			stream.read_exact(&mut greeting).unwrap();
+			stream.write_all(&[0x05, 0x00]).unwrap();
+
+			let mut request = [0u8; 10];
+			stream.read_exact(&mut request).unwrap();
+			stream.write_all(&[0x05, 0x00, 0x00, 0x01, 0x7f, 0x00, 0x00, 0x01, 0x00, 0x00]).unwrap();
+
+			let mut ping = [0u8; 4];
+			stream.read_exact(&mut ping).unwrap();
+			stream.write_all(b"pong").unwrap();
+			request.to_vec()
+		});
+		(address, handle)
+	}
+
+	#[test]
+	fn test_socks5_connect_local_server() {
+		let (proxy_address, server) = spawn_test_server();
+		let mut core = Core::new().unwrap();
+		let handle = core.handle();
+		let target = Target::Address("10.0.0.1:8333".parse().unwrap());
+
+		let future = TcpStream::connect(&proxy_address, &handle)
+			.and_then(move |stream| socks5_connect(stream, target, None))
+			.and_then(|stream| ::tokio_io::io::write_all(stream, b"ping"))
+			.and_then(|(stream, _)| read_exact(stream, [0u8; 4]));
+		let (_, pong) = core.run(future).unwrap();
+
+		assert_eq!(&pong, b"pong");
+		assert_eq!(server.join().unwrap(), vec![0x05, 0x01, 0x00, 0x01, 0x0a, 0x00, 0x00, 0x01, 0x20, 0x8d]);
+	}
+}
@@ -43,6 +43,34 @@ impl RunningAverage {
 
 pub enum Flow { Receive, Send }
 
+/// Number of messages and bytes of single command
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct CommandStats {
+	pub messages: u64,
+	pub bytes: u64,
+}
+
+/// Node-wide traffic statistics. Unlike `PeerStats`, these are kept after peers disconnect.
+#[derive(Default, Clone, Debug)]
+pub struct NetworkStats {
+	pub sent: HashMap<Command, CommandStats>,
+	pub received: HashMap<Command, CommandStats>,
+}
+
+impl NetworkStats {
+	pub fn report_send(&mut self, command: Command, bytes: usize) {
+		let stats = self.sent.entry(command).or_insert_with(CommandStats::default);
+		stats.messages += 1;
+		stats.bytes += bytes as u64;
+	}
+
+	pub fn report_recv(&mut self, command: Command, bytes: usize) {
+		let stats = self.received.entry(command).or_insert_with(CommandStats::default);
+		stats.messages += 1;
+		stats.bytes += bytes as u64;
+	}
+}
+
 #[derive(Default, Clone)]
 pub struct PeerStats<T: Interval = RealInterval> {
 	pub last_send: u32,
@@ -141,7 +169,7 @@ impl<I: Interval> PeerStats<I> {
 #[cfg(test)]
 mod tests {
 
-	use super::{RunningAverage, PeerStats, Flow};
+	use super::{RunningAverage, PeerStats, NetworkStats, CommandStats, Flow};
 	use util::interval::{FixedIntervalSpawner, RealInterval};
 
 	#[test]
@@ -189,4 +217,16 @@ mod tests {
 
 		assert_eq!(stats.avg(Flow::Receive, "inv"), 2500);
 	}
+
+	#[test]
+	fn network_stats() {
+		let mut stats = NetworkStats::default();
+		stats.report_send("inv".into(), 200);
+		stats.report_send("inv".into(), 300);
+		stats.report_recv("ping".into(), 8);
+
+		assert_eq!(stats.sent[&"inv".into()], CommandStats { messages: 2, bytes: 500 });
+		assert_eq!(stats.received[&"ping".into()], CommandStats { messages: 1, bytes: 8 });
+		assert!(stats.received.get(&"inv".into()).is_none());
+	}
 }
@@ -0,0 +1,270 @@
+//! Tor helpers: `.onion` peer addresses and a minimal control-port client,
+//! which is used to publish a hidden service for the local p2p listener.
+
+use std::{io, fmt};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream, IpAddr, Ipv6Addr};
+use std::time::Duration;
+use crypto::sha256;
+
+/// OnionCat prefix (fd87:d87e:eb43::/48), used to give onion peers stable addresses.
+const ONIONCAT_PREFIX: [u8; 6] = [0xfd, 0x87, 0xd8, 0x7e, 0xeb, 0x43];
+/// Max time to wait for Tor control port to accept connection or to reply.
+const CONTROL_PORT_TIMEOUT_SECS: u64 = 10;
+
+/// `.onion` peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OnionPeer {
+	pub host: String,
+	pub port: u16,
+}
+
+impl OnionPeer {
+	/// Parses `<name>.onion` or `<name>.onion:<port>`.
+	pub fn parse(s: &str, default_port: u16) -> Option<Self> {
+		let (host, port) = match s.rfind(':') {
+			Some(pos) => (&s[..pos], s[pos + 1..].parse().ok()?),
+			None => (s, default_port),
+		};
+
+		let host = host.to_lowercase();
+		if !host.ends_with(".onion") || host.len() == ".onion".len() {
+			return None;
+		}
+
+		Some(OnionPeer {
+			host: host,
+			port: port,
+		})
+	}
+
+	/// Address under which the peer is known to the rest of p2p module.
+	pub fn address(&self) -> SocketAddr {
+		named_address(&self.host, self.port)
+	}
+}
+
+/// Address under which peer, that is reached by name through proxy, is known to the rest of p2p module.
+///
+/// The address is taken from the OnionCat range and never routed directly.
+pub fn named_address(host: &str, port: u16) -> SocketAddr {
+	let hash = sha256(host.as_bytes());
+	let mut octets = [0u8; 16];
+	octets[..6].copy_from_slice(&ONIONCAT_PREFIX);
+	octets[6..].copy_from_slice(&hash[..10]);
+	SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port)
+}
+
+/// Returns true if address has been derived from `.onion` peer or from other peer name.
+pub fn is_onion_address(address: &SocketAddr) -> bool {
+	match address.ip() {
+		IpAddr::V6(ip) => ip.octets()[..6] == ONIONCAT_PREFIX,
+		IpAddr::V4(_) => false,
+	}
+}
+
+/// Tor control port configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TorControlConfig {
+	/// Address of Tor control port.
+	pub address: SocketAddr,
+	/// Password used in `AUTHENTICATE` command.
+	pub password: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum TorControlError {
+	Io(io::Error),
+	/// Control port replied with unexpected status.
+	Reply(String),
+}
+
+impl From<io::Error> for TorControlError {
+	fn from(err: io::Error) -> Self {
+		TorControlError::Io(err)
+	}
+}
+
+impl fmt::Display for TorControlError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			TorControlError::Io(ref err) => write!(f, "Tor control port I/O error: {}", err),
+			TorControlError::Reply(ref reply) => write!(f, "Tor control port replied with: {}", reply),
+		}
+	}
+}
+
+/// Ephemeral hidden service. It lives as long as the control connection is open.
+pub struct OnionService {
+	/// Hostname of the service, including `.onion` suffix.
+	pub host: String,
+	_control: TcpStream,
+}
+
+/// Asks Tor to publish hidden service on `virtual_port`, which forwards connections to `target`.
+pub fn add_onion(config: &TorControlConfig, virtual_port: u16, target: &SocketAddr) -> Result<OnionService, TorControlError> {
+	add_onion_with_timeout(config, virtual_port, target, Duration::from_secs(CONTROL_PORT_TIMEOUT_SECS))
+}
+
+fn add_onion_with_timeout(config: &TorControlConfig, virtual_port: u16, target: &SocketAddr, timeout: Duration) -> Result<OnionService, TorControlError> {
+	let stream = TcpStream::connect_timeout(&config.address, timeout)?;
+	stream.set_read_timeout(Some(timeout))?;
+	stream.set_write_timeout(Some(timeout))?;
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut writer = stream.try_clone()?;
+
+	match config.password {
+		Some(ref password) => write!(writer, "AUTHENTICATE \"{}\"\r\n", escape(password))?,
+		None => write!(writer, "AUTHENTICATE\r\n")?,
+	}
+	read_reply(&mut reader)?;
+
+	write!(writer, "ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port={},{}\r\n", virtual_port, target)?;
+	let reply = read_reply(&mut reader)?;
+	let service_id = reply.iter()
+		.filter_map(|line| if line.starts_with("ServiceID=") { Some(&line["ServiceID=".len()..]) } else { None })
+		.next()
+		.ok_or_else(|| TorControlError::Reply("ADD_ONION reply without ServiceID".into()))?;
+
+	Ok(OnionService {
+		host: format!("{}.onion", service_id),
+		_control: stream,
+	})
+}
+
+/// Reads single (possibly multi-line) reply and returns its lines without status codes.
+fn read_reply<R>(reader: &mut R) -> Result<Vec<String>, TorControlError> where R: BufRead {
+	let mut lines = Vec::new();
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 {
+			return Err(TorControlError::Io(io::ErrorKind::UnexpectedEof.into()));
+		}
+
+		let line = line.trim_end();
+		if line.len() < 4 {
+			return Err(TorControlError::Reply(line.into()));
+		}
+
+		let (status, separator, text) = (&line[..3], &line[3..4], &line[4..]);
+		if status != "250" {
+			return Err(TorControlError::Reply(line.into()));
+		}
+
+		lines.push(text.to_owned());
+		if separator == " " {
+			return Ok(lines);
+		}
+	}
+}
+
+fn escape(s: &str) -> String {
+	s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io, thread};
+	use std::io::{BufRead, BufReader, Write};
+	use std::net::TcpListener;
+	use std::time::Duration;
+	use super::{OnionPeer, TorControlConfig, TorControlError, is_onion_address, named_address, add_onion, add_onion_with_timeout};
+
+	#[test]
+	fn test_parse_onion_peer() {
+		let peer = OnionPeer::parse("ExampleOnion.onion:18333", 8333).unwrap();
+		assert_eq!(peer.host, "exampleonion.onion");
+		assert_eq!(peer.port, 18333);
+
+		let peer = OnionPeer::parse("exampleonion.onion", 8333).unwrap();
+		assert_eq!(peer.port, 8333);
+
+		assert_eq!(OnionPeer::parse("127.0.0.1:8333", 8333), None);
+		assert_eq!(OnionPeer::parse(".onion", 8333), None);
+		assert_eq!(OnionPeer::parse("exampleonion.onion:port", 8333), None);
+	}
+
+	#[test]
+	fn test_onion_peer_address() {
+		let first = OnionPeer::parse("first.onion", 8333).unwrap();
+		let second = OnionPeer::parse("second.onion", 8333).unwrap();
+		assert!(is_onion_address(&first.address()));
+		assert!(first.address() != second.address());
+		assert_eq!(first.address(), first.address());
+		assert!(!is_onion_address(&"127.0.0.1:8333".parse().unwrap()));
+		assert!(!is_onion_address(&"[::1]:8333".parse().unwrap()));
+		assert!(is_onion_address(&named_address("seed.bitcoin.sipa.be", 8333)));
+		assert_eq!(first.address(), named_address("first.onion", 8333));
+	}
+
+	#[test]
+	fn test_add_onion() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let config = TorControlConfig {
+			address: listener.local_addr().unwrap(),
+			password: Some("secret".into()),
+		};
+		let server = thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut writer = stream;
+			let mut commands = Vec::new();
+
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			commands.push(line.clone());
+			writer.write_all(b"250 OK\r\n").unwrap();
+
+			line.clear();
+			reader.read_line(&mut line).unwrap();
+			commands.push(line);
+			writer.write_all(b"250-ServiceID=abcdef\r\n250 OK\r\n").unwrap();
+			commands
+		});
+
+		let service = add_onion(&config, 8333, &"127.0.0.1:8333".parse().unwrap()).unwrap();
+		assert_eq!(service.host, "abcdef.onion");
+		assert_eq!(server.join().unwrap(), vec![
+			"AUTHENTICATE \"secret\"\r\n".to_owned(),
+			"ADD_ONION NEW:ED25519-V3 Flags=DiscardPK Port=8333,127.0.0.1:8333\r\n".to_owned(),
+		]);
+	}
+
+	#[test]
+	fn test_add_onion_authentication_failed() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let config = TorControlConfig {
+			address: listener.local_addr().unwrap(),
+			password: None,
+		};
+		let server = thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut writer = stream;
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			writer.write_all(b"515 Authentication failed\r\n").unwrap();
+		});
+
+		assert!(add_onion(&config, 8333, &"127.0.0.1:8333".parse().unwrap()).is_err());
+		server.join().unwrap();
+	}
+
+	#[test]
+	fn test_add_onion_timeout() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let config = TorControlConfig {
+			address: listener.local_addr().unwrap(),
+			password: None,
+		};
+		// control port accepts connection, but never replies
+		let server = thread::spawn(move || listener.accept().unwrap());
+
+		match add_onion_with_timeout(&config, 8333, &"127.0.0.1:8333".parse().unwrap(), Duration::from_millis(100)) {
+			Err(TorControlError::Io(ref err)) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => (),
+			Err(err) => panic!("unexpected error: {}", err),
+			Ok(_) => panic!("add_onion succeeded without reply"),
+		}
+		drop(server.join().unwrap());
+	}
+}
@@ -1,7 +1,7 @@
 use std::{io, net, error, time};
 use std::sync::Arc;
 use std::net::SocketAddr;
-use parking_lot::RwLock;
+use parking_lot::{RwLock, Mutex};
 use futures::{Future, finished, failed};
 use futures::stream::Stream;
 use futures_cpupool::{CpuPool, Builder as CpuPoolBuilder};
@@ -10,15 +10,16 @@ use tokio_core::net::{TcpListener, TcpStream};
 use tokio_core::reactor::{Handle, Remote, Timeout, Interval};
 use abstract_ns::Resolver;
 use ns_dns_tokio::DnsResolver;
-use message::{Payload, MessageResult, Message};
+use message::{Payload, MessageResult, Message, MessageHeader};
 use message::common::Services;
 use message::types::addr::AddressEntry;
-use net::{connect, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter};
+use net::{connect, connect_named, Connect, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter,
+	NetworkStats, OnionPeer, OnionService, Proxy, add_onion, named_address, is_onion_address};
 use util::{NodeTable, Node, NodeTableError, Direction};
 use session::{SessionFactory, SeednodeSessionFactory, NormalSessionFactory};
 use {Config, PeerId};
 use protocol::{LocalSyncNodeRef, InboundSyncConnectionRef, OutboundSyncConnectionRef};
-use io::DeadlineStatus;
+use io::{Deadline, DeadlineStatus};
 
 pub type BoxedEmptyFuture = Box<dyn Future<Item=(), Error=()> + Send>;
 
@@ -28,6 +29,8 @@ pub struct Context {
 	connections: Connections,
 	/// Connection counter.
 	connection_counter: ConnectionCounter,
+	/// Node-wide traffic statistics.
+	stats: Mutex<NetworkStats>,
 	/// Node Table.
 	node_table: RwLock<NodeTable>,
 	/// Thread pool handle.
@@ -46,6 +49,7 @@ impl Context {
 		let context = Context {
 			connections: Default::default(),
 			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections),
+			stats: Mutex::default(),
 			node_table: RwLock::new(NodeTable::from_file(config.preferable_services, &config.node_table_path)?),
 			pool: pool_handle,
 			remote: remote,
@@ -96,6 +100,12 @@ impl Context {
 		self.node_table.write().note_failure(addr);
 	}
 
+	/// Ask for one more outbound connection on next autoconnect round.
+	pub fn connect_extra_outbound(&self) {
+		trace!("Extra outbound connection requested");
+		self.connection_counter.note_extra_outbound_connection_needed();
+	}
+
 	/// Adds node to table.
 	pub fn add_node(&self, addr: SocketAddr) -> Result<(), NodeTableError> {
 		trace!("Adding node {} to node table", &addr);
@@ -125,18 +135,19 @@ impl Context {
 					channel.session().maintain();
 				}
 
-				let needed = context.connection_counter.outbound_connections_needed() as usize;
+				let needed = (context.connection_counter.outbound_connections_needed() +
+					context.connection_counter.take_extra_outbound_connections()) as usize;
 				if needed != 0 {
 					// TODO: pass Services::with_bitcoin_cash(true) after HF block
 					let used_addresses = context.connections.addresses();
 					let peers = context.node_table.read().nodes_with_services(&Services::default(), context.config.internet_protocol, &used_addresses, needed);
-					let addresses = peers.into_iter()
-						.map(|peer| peer.address())
-						.collect::<Vec<_>>();
 
-					trace!("Creating {} more outbound connections", addresses.len());
-					for address in addresses {
-						Context::connect::<NormalSessionFactory>(context.clone(), address);
+					trace!("Creating {} more outbound connections", peers.len());
+					for peer in peers {
+						// v2 transport is only tried with peers, which have advertised its support
+						let mut config = context.config.connection.clone();
+						config.v2_transport = config.v2_transport && peer.services().p2p_v2();
+						Context::connect_with_config::<NormalSessionFactory>(context.clone(), peer.address(), config);
 					}
 				}
 
@@ -155,12 +166,46 @@ impl Context {
 	fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr, handle: &Handle, config: &NetConfig) -> BoxedEmptyFuture where T: SessionFactory {
 		trace!("Trying to connect to: {}", socket);
 		let connection = connect(&socket, handle, config);
+		let retry = {
+			let context = context.clone();
+			Context::v1_retry(config, move |config| Context::connect_with_config::<T>(context, socket, config))
+		};
+		Context::on_connect_future::<T>(context, socket, connection, retry)
+	}
+
+	/// Connect to peer by name through proxy using given context and handle.
+	fn connect_named_future<T>(context: Arc<Context>, host: String, port: u16, proxy: Proxy, handle: &Handle, config: &NetConfig) -> BoxedEmptyFuture where T: SessionFactory {
+		trace!("Trying to connect to: {}:{}", host, port);
+		let connection = connect_named(&host, port, &proxy, handle, config);
+		let address = named_address(&host, port);
+		let retry = {
+			let context = context.clone();
+			Context::v1_retry(config, move |config| Context::connect_named_with_config::<T>(context, host, port, proxy, config))
+		};
+		Context::on_connect_future::<T>(context, address, connection, retry)
+	}
+
+	/// Returns function, which repeats failed v2 connection attempt with v1 transport.
+	fn v1_retry<F>(config: &NetConfig, connect: F) -> Option<Box<dyn FnOnce() + Send>> where F: FnOnce(NetConfig) + Send + 'static {
+		if !config.v2_transport {
+			return None;
+		}
+
+		let mut config = config.clone();
+		config.v2_transport = false;
+		Some(Box::new(move || connect(config)))
+	}
+
+	fn on_connect_future<T>(context: Arc<Context>, socket: net::SocketAddr, connection: Deadline<Connect>, retry: Option<Box<dyn FnOnce() + Send>>) -> BoxedEmptyFuture where T: SessionFactory {
 		Box::new(connection.then(move |result| {
 			match result {
 				Ok(DeadlineStatus::Meet(Ok(connection))) => {
 					// successfull hanshake
 					trace!("Connected to {}", connection.address);
-					context.node_table.write().insert(connection.address, connection.services);
+					// onion addresses are unroutable without proxy, so they never get into node table
+					if !is_onion_address(&connection.address) {
+						context.node_table.write().insert(connection.address, connection.services);
+					}
 					let channel = context.connections.store::<T>(context.clone(), connection, Direction::Outbound);
 
 					// initialize session and then start reading messages
@@ -184,10 +229,19 @@ impl Context {
 					Box::new(finished(Ok(())))
 				},
 				Err(_) => {
-					// network error
-					trace!("Unable to connect to {}", socket);
-					context.node_table.write().note_failure(&socket);
 					context.connection_counter.note_close_outbound_connection();
+					match retry {
+						Some(retry) => {
+							// peer is likely to support v1 transport only
+							trace!("Unable to establish v2 connection to {}, retrying with v1", socket);
+							retry();
+						},
+						None => {
+							// network error
+							trace!("Unable to connect to {}", socket);
+							context.node_table.write().note_failure(&socket);
+						},
+					}
 					Box::new(finished(Ok(())))
 				}
 			}
@@ -197,10 +251,45 @@ impl Context {
 
 	/// Connect to socket using given context.
 	pub fn connect<T>(context: Arc<Context>, socket: net::SocketAddr) where T: SessionFactory {
+		let config = context.config.connection.clone();
+		Context::connect_with_config::<T>(context, socket, config)
+	}
+
+	fn connect_with_config<T>(context: Arc<Context>, socket: net::SocketAddr, config: NetConfig) where T: SessionFactory {
+		context.connection_counter.note_new_outbound_connection();
+		context.remote.clone().spawn(move |handle| {
+			context.pool.clone().spawn(Context::connect_future::<T>(context, socket, handle, &config))
+		})
+	}
+
+	/// Connect to `.onion` peer using given context.
+	pub fn connect_onion<T>(context: Arc<Context>, peer: OnionPeer) where T: SessionFactory {
+		let config = context.config.connection.clone();
+		Context::connect_onion_with_config::<T>(context, peer, config)
+	}
+
+	fn connect_onion_with_config<T>(context: Arc<Context>, peer: OnionPeer, config: NetConfig) where T: SessionFactory {
+		let proxy = match config.onion_proxy() {
+			Some(proxy) => proxy.clone(),
+			None => {
+				warn!("Unable to connect to {}: no onion proxy configured", peer.host);
+				return;
+			},
+		};
+
+		Context::connect_named_with_config::<T>(context, peer.host, peer.port, proxy, config)
+	}
+
+	/// Connect to peer by name through proxy, so that the name is never resolved locally.
+	pub fn connect_named<T>(context: Arc<Context>, host: String, port: u16, proxy: Proxy) where T: SessionFactory {
+		let config = context.config.connection.clone();
+		Context::connect_named_with_config::<T>(context, host, port, proxy, config)
+	}
+
+	fn connect_named_with_config<T>(context: Arc<Context>, host: String, port: u16, proxy: Proxy, config: NetConfig) where T: SessionFactory {
 		context.connection_counter.note_new_outbound_connection();
 		context.remote.clone().spawn(move |handle| {
-			let config = context.config.clone();
-			context.pool.clone().spawn(Context::connect_future::<T>(context, socket, handle, &config.connection))
+			context.pool.clone().spawn(Context::connect_named_future::<T>(context, host, port, proxy, handle, &config))
 		})
 	}
 
@@ -285,6 +374,7 @@ impl Context {
 				Ok(Ok((command, payload))) => {
 					// successful read
 					trace!("Received {} message from {}", command, channel.peer_info().address);
+					context.stats.lock().report_recv(command.clone(), MessageHeader::SIZE + payload.len());
 					// handle message and read the next one
 					match channel.session().on_message(command, payload) {
 						Ok(_) => {
@@ -322,6 +412,7 @@ impl Context {
 				let info = channel.peer_info();
 				let message = Message::with_flags(info.magic, info.version, payload, serialization_flags).expect("failed to create outgoing message");
 				channel.session().stats().lock().report_send(T::command().into(), message.len());
+				context.stats.lock().report_send(T::command().into(), message.len());
 				Context::send(context, channel, message)
 			},
 			None => {
@@ -399,6 +490,16 @@ impl Context {
 		&self.connections
 	}
 
+	/// Returns numbers of currently open inbound and outbound connections.
+	pub fn connections_count(&self) -> (u32, u32) {
+		(self.connection_counter.inbound_connections().0, self.connection_counter.outbound_connections().0)
+	}
+
+	/// Returns node-wide traffic statistics.
+	pub fn network_stats(&self) -> NetworkStats {
+		self.stats.lock().clone()
+	}
+
 	pub fn nodes(&self) -> Vec<Node> {
 		self.node_table.read().nodes()
 	}
@@ -413,6 +514,8 @@ pub struct P2P {
 	config: Config,
 	/// Network context.
 	context: Arc<Context>,
+	/// Hidden service published for the local listener.
+	onion_service: Mutex<Option<OnionService>>,
 }
 
 impl Drop for P2P {
@@ -442,6 +545,7 @@ impl P2P {
 			pool: pool,
 			context: Arc::new(context),
 			config: config,
+			onion_service: Mutex::new(None),
 		};
 
 		Ok(p2p)
@@ -452,6 +556,10 @@ impl P2P {
 			self.connect::<NormalSessionFactory>(*peer);
 		}
 
+		for peer in &self.config.onion_peers {
+			Context::connect_onion::<NormalSessionFactory>(self.context.clone(), peer.clone());
+		}
+
 		let resolver = DnsResolver::system_config(&self.event_loop_handle)?;
 		for seed in &self.config.seeds {
 			self.connect_to_seednode(&resolver, seed);
@@ -459,6 +567,27 @@ impl P2P {
 
 		Context::autoconnect(self.context.clone(), &self.event_loop_handle);
 		self.listen()?;
+		self.publish_onion_service()?;
+		Ok(())
+	}
+
+	/// Publishes hidden service, which forwards connections to the local listener.
+	fn publish_onion_service(&self) -> Result<(), Box<dyn error::Error>> {
+		let tor_control = match self.config.tor_control {
+			Some(ref tor_control) => tor_control,
+			None => return Ok(()),
+		};
+
+		let local_address = self.config.connection.local_address;
+		let target = if local_address.ip().is_unspecified() {
+			net::SocketAddr::new(net::Ipv4Addr::new(127, 0, 0, 1).into(), local_address.port())
+		} else {
+			local_address
+		};
+
+		let service = add_onion(tor_control, local_address.port(), &target).map_err(|err| err.to_string())?;
+		info!("Listening on hidden service {}:{}", service.host, local_address.port());
+		*self.onion_service.lock() = Some(service);
 		Ok(())
 	}
 
@@ -468,6 +597,15 @@ impl P2P {
 	}
 
 	pub fn connect_to_seednode(&self, resolver: &dyn Resolver, seednode: &str) {
+		// local DNS lookup would leak seednode name => let proxy resolve it
+		if let Some(ref proxy) = self.config.connection.proxy {
+			match seednode.rfind(':').and_then(|pos| seednode[pos + 1..].parse().ok().map(|port| (&seednode[..pos], port))) {
+				Some((host, port)) => Context::connect_named::<SeednodeSessionFactory>(self.context.clone(), host.to_owned(), port, proxy.clone()),
+				None => warn!("Unable to connect to seednode {} through proxy: port is not specified", seednode),
+			}
+			return;
+		}
+
 		let owned_seednode = seednode.to_owned();
 		let context = self.context.clone();
 		let dns_lookup = resolver.resolve(seednode).then(move |result| {
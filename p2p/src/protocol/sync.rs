@@ -20,7 +20,7 @@ pub trait InboundSyncConnectionState: Send + Sync {
 
 pub trait InboundSyncConnection : Send + Sync {
 	fn sync_state(&self) -> InboundSyncConnectionStateRef;
-	fn start_sync_session(&self, peer_name: String, version: types::Version);
+	fn start_sync_session(&self, peer_name: String, version: types::Version, package_relay: bool);
 	fn close_session(&self);
 	fn on_inventory(&self, message: types::Inv);
 	fn on_getdata(&self, message: types::GetData);
@@ -41,6 +41,12 @@ pub trait InboundSyncConnection : Send + Sync {
 	fn on_get_block_txn(&self, message: types::GetBlockTxn);
 	fn on_block_txn(&self, message: types::BlockTxn);
 	fn on_notfound(&self, message: types::NotFound);
+	fn on_get_cfilters(&self, message: types::GetCFilters);
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders);
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt);
+	fn on_ancpkginfo(&self, message: types::AncPkgInfo);
+	fn on_getpkgtxns(&self, message: types::GetPkgTxns);
+	fn on_pkgtxns(&self, message: types::PkgTxns);
 }
 
 pub trait OutboundSyncConnection : Send + Sync {
@@ -66,8 +72,15 @@ pub trait OutboundSyncConnection : Send + Sync {
 	fn send_get_block_txn(&self, message: &types::GetBlockTxn);
 	fn send_block_txn(&self, message: &types::BlockTxn);
 	fn send_notfound(&self, message: &types::NotFound);
+	fn send_cfilter(&self, message: &types::CFilter);
+	fn send_cfheaders(&self, message: &types::CFHeaders);
+	fn send_cfcheckpt(&self, message: &types::CFCheckpt);
+	fn send_ancpkginfo(&self, message: &types::AncPkgInfo);
+	fn send_getpkgtxns(&self, message: &types::GetPkgTxns);
+	fn send_pkgtxns(&self, message: &types::PkgTxns);
 	fn ignored(&self, id: u32);
 	fn close(&self);
+	fn connect_extra_outbound(&self);
 }
 
 struct OutboundSync {
@@ -171,6 +184,30 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.send_request(message);
 	}
 
+	fn send_cfilter(&self, message: &types::CFilter) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfheaders(&self, message: &types::CFHeaders) {
+		self.context.send_request(message);
+	}
+
+	fn send_cfcheckpt(&self, message: &types::CFCheckpt) {
+		self.context.send_request(message);
+	}
+
+	fn send_ancpkginfo(&self, message: &types::AncPkgInfo) {
+		self.context.send_request(message);
+	}
+
+	fn send_getpkgtxns(&self, message: &types::GetPkgTxns) {
+		self.context.send_request(message);
+	}
+
+	fn send_pkgtxns(&self, message: &types::PkgTxns) {
+		self.context.send_request_with_flags(message, SERIALIZE_TRANSACTION_WITNESS);
+	}
+
 	fn ignored(&self, id: u32) {
 		self.context.ignore_response(id);
 	}
@@ -179,6 +216,10 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.global().penalize_node(&self.context.info().address);
 		self.context.close()
 	}
+
+	fn connect_extra_outbound(&self) {
+		self.context.global().connect_extra_outbound()
+	}
 }
 
 pub struct SyncProtocol {
@@ -205,7 +246,8 @@ impl Protocol for SyncProtocol {
 		let info = self.context.info();
 		self.inbound_connection.start_sync_session(
 			format!("{}/{}", info.address, info.user_agent),
-			info.version_message.clone()
+			info.version_message.clone(),
+			info.package_relay
 		);
 	}
 
@@ -324,6 +366,55 @@ impl Protocol for SyncProtocol {
 			let message: types::NotFound = deserialize_payload(payload, version)?;
 			self.inbound_connection.on_notfound(message);
 		}
+		else if command == &types::GetCFilters::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFilters = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfilters(message);
+		}
+		else if command == &types::GetCFHeaders::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFHeaders = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfheaders(message);
+		}
+		else if command == &types::GetCFCheckpt::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetCFCheckpt = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_get_cfcheckpt(message);
+		}
+		else if command == &types::AncPkgInfo::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::AncPkgInfo = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_ancpkginfo(message);
+		}
+		else if command == &types::GetPkgTxns::command() {
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::GetPkgTxns = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_getpkgtxns(message);
+		}
+		else if command == &types::PkgTxns::command() {
+			// like transactions, packages are ignored until synchronization is completed
+			if self.state.synchronizing() {
+				return Ok(());
+			}
+
+			let message: types::PkgTxns = deserialize_payload(payload, version)?;
+			self.inbound_connection.on_pkgtxns(message);
+		}
 		Ok(())
 	}
 
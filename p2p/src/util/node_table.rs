@@ -27,6 +27,10 @@ impl Node {
 	pub fn address(&self) -> SocketAddr {
 		self.addr
 	}
+
+	pub fn services(&self) -> Services {
+		self.services
+	}
 }
 
 impl From<Node> for AddressEntry {
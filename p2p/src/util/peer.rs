@@ -19,5 +19,6 @@ pub struct PeerInfo {
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
+	pub package_relay: bool,
 }
 
@@ -0,0 +1,28 @@
+use clap::ArgMatches;
+use storage::BlockRef;
+use config::Config;
+use util::init_db;
+
+pub fn export(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	init_db(&cfg)?;
+
+	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
+	let mut writer = ::import::create_blk_dir(blk_path, cfg.consensus.magic())
+		.map_err(|err| format!("Failed to create export directory: {}", err))?;
+
+	let best_block = cfg.db.best_block();
+	for number in 0..best_block.number + 1 {
+		let block = cfg.db.block(BlockRef::Number(number))
+			.ok_or_else(|| format!("Canon block #{} is missing", number))?;
+		let position = writer.write_block(&block.to_raw_block())
+			.map_err(|err| format!("Cannot write block #{}: {}", number, err))?;
+		if number % 1000 == 0 {
+			info!("Exported block #{} to {}", number, position);
+		}
+	}
+
+	writer.flush().map_err(|err| format!("Cannot write block: {}", err))?;
+	info!("Finished export of {} blocks", best_block.number + 1);
+
+	Ok(())
+}
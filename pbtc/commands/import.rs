@@ -1,37 +1,276 @@
+use std::cmp;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use clap::ArgMatches;
+use rayon::prelude::*;
+use chain::IndexedBlock;
+use import::{open_raw_blk_dir, read_blk_at, BlkPosition, RawBlk};
+use network::Network;
+use primitives::hash::H256;
 use sync::{create_sync_blocks_writer, Error};
+use verification::{ChainVerifier, VerificationLevel};
 use config::Config;
 use util::init_db;
 
+/// Number of blocks read from disk, deserialized and pre-verified at once.
+const IMPORT_BATCH_SIZE: usize = 512;
+
+/// Position of the import in the given blocks directory.
+struct ImportPosition {
+	path: String,
+	position: BlkPosition,
+}
+
+impl ImportPosition {
+	fn to_bytes(&self) -> Vec<u8> {
+		format!("{}:{}:{}", self.position.file, self.position.offset, self.path).into_bytes()
+	}
+
+	fn from_bytes(bytes: &[u8]) -> Option<Self> {
+		let s = ::std::str::from_utf8(bytes).ok()?;
+		let mut parts = s.splitn(3, ':');
+		let file = parts.next()?.parse().ok()?;
+		let offset = parts.next()?.parse().ok()?;
+		let path = parts.next()?.to_owned();
+		Some(ImportPosition {
+			path: path,
+			position: BlkPosition {
+				file: file,
+				offset: offset,
+			},
+		})
+	}
+}
+
+/// Returns position to resume import of blocks directory at `path` from, given position stored by previous import.
+fn resume_position(stored: &[u8], path: &str) -> Option<BlkPosition> {
+	match ImportPosition::from_bytes(stored) {
+		Some(ref position) if position.path == path => Some(position.position),
+		_ => None,
+	}
+}
+
+/// Blocks, which parents are not imported yet. Only positions are kept in memory,
+/// blocks are read again when their parent is imported.
+#[derive(Default)]
+struct DeferredBlocks {
+	by_parent: HashMap<H256, Vec<BlkPosition>>,
+	positions: BTreeSet<BlkPosition>,
+}
+
+impl DeferredBlocks {
+	fn insert(&mut self, parent: H256, position: BlkPosition) {
+		if self.positions.insert(position) {
+			self.by_parent.entry(parent).or_insert_with(Vec::new).push(position);
+		}
+	}
+
+	fn take_children(&mut self, parent: &H256) -> Vec<BlkPosition> {
+		let children = self.by_parent.remove(parent).unwrap_or_default();
+		for position in &children {
+			self.positions.remove(position);
+		}
+		children
+	}
+
+	fn first(&self) -> Option<BlkPosition> {
+		self.positions.iter().next().cloned()
+	}
+
+	fn len(&self) -> usize {
+		self.positions.len()
+	}
+
+	/// Everything before returned position is either imported, or will be read again.
+	fn resume_position(&self, read: BlkPosition) -> BlkPosition {
+		match self.first() {
+			Some(first_deferred) => cmp::min(first_deferred, read),
+			None => read,
+		}
+	}
+}
+
+/// Checks magic, deserializes and pre-verifies (unless `verify` is false) the block record.
+fn prepare_block(raw: RawBlk, magic: u32, network: Network, current_time: u32, verify: bool) -> Result<(BlkPosition, IndexedBlock), String> {
+	if raw.magic != magic {
+		return Err(format!("Invalid magic of the block at {}", raw.position));
+	}
+
+	let block = raw.block().map_err(|err| format!("Cannot read block at {}: {:?}", raw.position, err))?;
+	if verify {
+		ChainVerifier::new(&block, network, current_time).check()
+			.map_err(|err| format!("Invalid block {} at {}: {:?}", block.hash().to_reversed_str(), raw.position, err))?;
+	}
+	Ok((raw.position, block))
+}
+
 pub fn import(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 	init_db(&cfg)?;
 
 	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
-	let blk_dir = ::import::open_blk_dir(blk_path)
+	let blk_dir = Path::new(blk_path).canonicalize()
+		.map_err(|err| format!("Failed to open import directory: {}", err))?
+		.to_string_lossy()
+		.into_owned();
+
+	let start = match cfg.db.import_position().map_err(|err| format!("{}", err))? {
+		Some(ref bytes) if !matches.is_present("from-start") => match resume_position(bytes, &blk_dir) {
+			Some(position) => {
+				info!("Resuming import from {}", position);
+				position
+			},
+			None => BlkPosition::default(),
+		},
+		_ => BlkPosition::default(),
+	};
+
+	let mut reader = open_raw_blk_dir(&blk_dir, start)
 		.map_err(|err| format!("Failed to open import directory: {}", err))?;
 
+	let magic = cfg.consensus.magic();
+	let network = cfg.consensus.network;
+	// blocks after verification edge are fully verified by the writer anyway
+	let verify = cfg.verification_params.verification_level != VerificationLevel::NoVerification;
+	let current_time = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as u32).unwrap_or_default();
+	let db = cfg.db.clone();
 	let mut writer = create_sync_blocks_writer(cfg.db, cfg.consensus, cfg.verification_params);
+	let mut deferred = DeferredBlocks::default();
 	let mut counter = 0;
-	let mut previous_hash = None;
-	for blk in blk_dir {
-		// TODO: verify magic!
-		let blk = blk.map_err(|err| format!("Cannot read block: {:?}. Previous block: {:?}", err, previous_hash))?;
-		let blk_hash = blk.block.hash().reversed();
-		match writer.append_block(blk.block) {
-			Ok(_) => {
-				counter += 1;
-				if counter % 1000 == 0 {
-					info!(target: "sync", "Imported {} blocks", counter);
+
+	loop {
+		let batch = reader.by_ref()
+			.take(IMPORT_BATCH_SIZE)
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|err| format!("Cannot read block: {}. Resume position: {}", err, reader.position()))?;
+		if batch.is_empty() {
+			break;
+		}
+
+		let blocks = batch.into_par_iter()
+			.map(|raw| prepare_block(raw, magic, network, current_time, verify))
+			.collect::<Vec<_>>();
+
+		for block in blocks {
+			let mut queue = vec![block?];
+			while let Some((position, block)) = queue.pop() {
+				if db.contains_block(block.hash().clone().into()) {
+					continue;
+				}
+
+				// blocks may be stored out of order, so wait until parent is imported
+				let parent_hash = block.header.raw.previous_header_hash.clone();
+				if !db.contains_block(parent_hash.clone().into()) {
+					deferred.insert(parent_hash, position);
+					continue;
+				}
+
+				let block_hash = block.hash().clone();
+				match writer.append_block(block) {
+					Ok(_) => {
+						counter += 1;
+						if counter % 1000 == 0 {
+							info!(target: "sync", "Imported {} blocks", counter);
+						}
+					},
+					Err(Error::TooManyOrphanBlocks) => return Err("Too many orphan (unordered) blocks".into()),
+					Err(err) => return Err(format!("Cannot append block: {:?}. Block: {}", err, block_hash.to_reversed_str())),
+				}
+
+				for child_position in deferred.take_children(&block_hash) {
+					let raw = read_blk_at(&blk_dir, child_position)
+						.map_err(|err| format!("Cannot read block at {}: {}", child_position, err))?;
+					queue.push(prepare_block(raw, magic, network, current_time, verify)?);
 				}
 			}
-			Err(Error::TooManyOrphanBlocks) => return Err("Too many orphan (unordered) blocks".into()),
-			Err(err) => return Err(format!("Cannot append block: {:?}. Block: {}", err, blk_hash)),
 		}
 
-		previous_hash = Some(blk_hash);
+		let position = ImportPosition {
+			path: blk_dir.clone(),
+			position: deferred.resume_position(reader.position()),
+		};
+		db.set_import_position(&position.to_bytes()).map_err(|err| format!("{}", err))?;
+	}
+
+	if deferred.len() != 0 {
+		warn!("{} blocks were not imported, because their parents are unknown", deferred.len());
 	}
 
 	info!("Finished import of {} blocks", counter);
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use import::BlkPosition;
+	use primitives::hash::H256;
+	use super::{ImportPosition, DeferredBlocks, resume_position};
+
+	fn position(file: u32, offset: u64) -> BlkPosition {
+		BlkPosition {
+			file: file,
+			offset: offset,
+		}
+	}
+
+	#[test]
+	fn import_position_roundtrip() {
+		let import_position = ImportPosition {
+			path: "/data/blocks:old".into(),
+			position: position(3, 1024),
+		};
+		let decoded = ImportPosition::from_bytes(&import_position.to_bytes()).unwrap();
+		assert_eq!(decoded.path, "/data/blocks:old");
+		assert_eq!(decoded.position, position(3, 1024));
+
+		assert!(ImportPosition::from_bytes(b"3:1024").is_none());
+		assert!(ImportPosition::from_bytes(b"x:1024:/data/blocks").is_none());
+		assert!(ImportPosition::from_bytes(&[0xff, b':', b'1', b':', b'/']).is_none());
+	}
+
+	#[test]
+	fn import_is_resumed_only_for_the_same_directory() {
+		let stored = ImportPosition {
+			path: "/data/blocks".into(),
+			position: position(1, 100),
+		}.to_bytes();
+		assert_eq!(resume_position(&stored, "/data/blocks"), Some(position(1, 100)));
+		assert_eq!(resume_position(&stored, "/other/blocks"), None);
+		assert_eq!(resume_position(b"garbage", "/data/blocks"), None);
+	}
+
+	#[test]
+	fn deferred_blocks_are_returned_when_parent_is_imported() {
+		let mut deferred = DeferredBlocks::default();
+		deferred.insert(H256::from(1), position(0, 300));
+		deferred.insert(H256::from(2), position(0, 200));
+		deferred.insert(H256::from(1), position(1, 0));
+		// same record may be read twice, when import is resumed
+		deferred.insert(H256::from(1), position(0, 300));
+		assert_eq!(deferred.len(), 3);
+		assert_eq!(deferred.first(), Some(position(0, 200)));
+
+		assert_eq!(deferred.take_children(&H256::from(3)), vec![]);
+		assert_eq!(deferred.take_children(&H256::from(1)), vec![position(0, 300), position(1, 0)]);
+		assert_eq!(deferred.take_children(&H256::from(1)), vec![]);
+		assert_eq!(deferred.len(), 1);
+
+		assert_eq!(deferred.take_children(&H256::from(2)), vec![position(0, 200)]);
+		assert_eq!(deferred.len(), 0);
+		assert_eq!(deferred.first(), None);
+	}
+
+	#[test]
+	fn deferred_blocks_are_read_again_when_import_is_resumed() {
+		let mut deferred = DeferredBlocks::default();
+		assert_eq!(deferred.resume_position(position(2, 500)), position(2, 500));
+
+		deferred.insert(H256::from(1), position(1, 100));
+		deferred.insert(H256::from(2), position(2, 0));
+		assert_eq!(deferred.resume_position(position(2, 500)), position(1, 100));
+
+		deferred.take_children(&H256::from(1));
+		assert_eq!(deferred.resume_position(position(2, 500)), position(2, 0));
+	}
+}
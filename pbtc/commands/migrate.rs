@@ -0,0 +1,18 @@
+use config::Config;
+use db::DATABASE_VERSION;
+
+pub fn migrate(cfg: Config) -> Result<(), String> {
+	let version = cfg.db.format_version().map_err(|e| format!("{}", e))?;
+	if version == DATABASE_VERSION {
+		info!("Database is already at the latest format version {}", version);
+		return Ok(());
+	}
+
+	info!("Upgrading database from format version {} to {}", version, DATABASE_VERSION);
+	cfg.db.migrate(&mut |progress| {
+		info!("Upgrading to version {}: {}/{} blocks", progress.version, progress.processed, progress.total);
+	}).map_err(|e| format!("{}", e))?;
+	info!("Database is upgraded to format version {}", DATABASE_VERSION);
+
+	Ok(())
+}
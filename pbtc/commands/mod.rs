@@ -1,7 +1,15 @@
+mod export;
 mod import;
+mod migrate;
 mod start;
 mod rollback;
+mod script_debug;
+mod verifydb;
 
+pub use self::export::export;
 pub use self::import::import;
+pub use self::migrate::migrate;
 pub use self::start::start;
-pub use self::rollback::rollback;
\ No newline at end of file
+pub use self::rollback::rollback;
+pub use self::script_debug::script_debug;
+pub use self::verifydb::verifydb;
\ No newline at end of file
@@ -1,5 +1,5 @@
 use clap::ArgMatches;
-use storage::BlockRef;
+use storage::{BlockRef, SideChainOrigin};
 use config::Config;
 use primitives::hash::H256;
 use util::init_db;
@@ -22,6 +22,39 @@ pub fn rollback(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 		.hash;
 	let genesis_hash = *cfg.network.genesis_block().hash();
 
+	// side chain block => switch to its chain, leaving decanonized blocks in the database
+	if cfg.db.block_number(&required_block_hash).is_none() {
+		let mut canonized_route = Vec::new();
+		let mut hash = required_block_hash;
+		let ancestor = loop {
+			match cfg.db.block_number(&hash) {
+				Some(number) => break number,
+				None => {
+					let parent_hash = cfg.db.block_header(hash.clone().into())
+						.ok_or(format!("Parent of block {:?} is unknown", block_ref))?
+						.raw.previous_header_hash;
+					canonized_route.push(hash);
+					hash = parent_hash;
+				},
+			}
+		};
+
+		let best_block = cfg.db.best_block();
+		let origin = SideChainOrigin {
+			ancestor: ancestor,
+			block_number: ancestor + canonized_route.len() as u32,
+			canonized_route: canonized_route.into_iter().rev().collect(),
+			decanonized_route: (ancestor + 1..best_block.number + 1)
+				.filter_map(|number| cfg.db.block_hash(number))
+				.collect(),
+		};
+		let fork = cfg.db.fork(origin).map_err(|e| format!("{:?}", e))?;
+		cfg.db.switch_to_fork(fork).map_err(|e| format!("{:?}", e))?;
+
+		info!("Switched to side chain block {:?}", block_ref);
+		return Ok(());
+	}
+
 	let mut best_block_hash = cfg.db.best_block().hash;
 	debug_assert!(best_block_hash != H256::default()); // genesis inserted in init_db
 
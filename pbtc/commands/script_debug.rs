@@ -0,0 +1,87 @@
+use clap::ArgMatches;
+use primitives::bytes::Bytes;
+use primitives::hash::H256;
+use script::{Error as ScriptError, Script, ScriptStage, ScriptTracer, Stack, TraceStep};
+use verification::{BackwardsCompatibleChainVerifier, TransactionError};
+use config::Config;
+use util::init_db;
+
+/// Prints interpreter state before every opcode
+struct PrintingTracer;
+
+fn format_stack(stack: &Stack<Bytes>) -> String {
+	stack.iter().map(|item| format!("{:?}", item)).collect::<Vec<_>>().join(" ")
+}
+
+impl ScriptTracer for PrintingTracer {
+	fn script_started(&mut self, stage: ScriptStage, script: &Script) {
+		println!("{:?}: {:?}", stage, script.to_bytes());
+	}
+
+	fn step(&mut self, step: &TraceStep) {
+		let data = step.data.map(|data| format!(" 0x{:?}", Bytes::from(data))).unwrap_or_default();
+		let skipped = match step.executing {
+			true => "",
+			false => " (skipped)",
+		};
+		println!("  {:>4} {:?}{}{}", step.pc, step.opcode, data, skipped);
+		println!("       stack: [{}]", format_stack(step.stack));
+		if !step.altstack.is_empty() {
+			println!("       altstack: [{}]", format_stack(step.altstack));
+		}
+		if !step.exec_stack.is_empty() {
+			println!("       execstack: {:?}", step.exec_stack);
+		}
+	}
+
+	fn script_finished(&mut self, stack: &Stack<Bytes>, result: &Result<bool, ScriptError>) {
+		println!("  final stack: [{}]", format_stack(stack));
+		if let Err(ref err) = *result {
+			println!("  error: {}", err);
+		}
+	}
+}
+
+pub fn script_debug(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	init_db(&cfg)?;
+
+	let hash = matches.value_of("TXID").expect("TXID is required in cli.yml; qed");
+	let hash: H256 = hash.parse().map_err(|e| format!("Invalid transaction hash: {}", e))?;
+	let hash = hash.reversed();
+	let input_index: usize = matches.value_of("INPUT").expect("INPUT is required in cli.yml; qed")
+		.parse().map_err(|e| format!("Invalid input index: {}", e))?;
+
+	let transaction = cfg.db.transaction(&hash)
+		.ok_or_else(|| format!("Transaction {} is unknown", hash.reversed()))?;
+	if transaction.raw.is_coinbase() {
+		return Err("Coinbase transaction inputs have no scripts to verify".into());
+	}
+
+	// replay the input with rules, active at the block where transaction has been included
+	let height = cfg.db.transaction_meta(&hash)
+		.ok_or_else(|| format!("Transaction {} is not in the canon chain", hash.reversed()))?
+		.height();
+	let time = cfg.db.block_header(height.into())
+		.expect("transaction meta is only stored for canon chain blocks; qed")
+		.raw.time;
+
+	let verifier = BackwardsCompatibleChainVerifier::new(cfg.db.clone(), cfg.consensus.clone());
+	let result = verifier.trace_transaction_input(
+		cfg.db.as_block_header_provider(),
+		cfg.db.as_transaction_output_provider(),
+		height,
+		time,
+		&transaction,
+		input_index,
+		&mut PrintingTracer,
+	);
+
+	match result {
+		Ok(()) => {
+			println!("Input {} of transaction {} is valid", input_index, hash.reversed());
+			Ok(())
+		},
+		Err(TransactionError::Signature(_, err)) => Err(format!("Input {} is invalid: {}", input_index, err)),
+		Err(err) => Err(format!("Input {} can't be verified: {:?}", input_index, err)),
+	}
+}
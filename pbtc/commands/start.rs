@@ -5,9 +5,10 @@ use std::sync::mpsc::{channel, Sender, Receiver};
 use std::sync::atomic::{AtomicBool, Ordering};
 use sync::{create_sync_peers, create_local_sync_node, create_sync_connection_factory, SyncListener};
 use primitives::hash::H256;
+use ethcore_rpc::v1::EventsNotifier;
 use util::{init_db, node_table_path};
-use {config, p2p, PROTOCOL_VERSION, PROTOCOL_MINIMUM};
-use super::super::rpc;
+use {config, logs, p2p, PROTOCOL_VERSION, PROTOCOL_MINIMUM};
+use super::super::{metrics_server, rpc};
 
 enum BlockNotifierTask {
 	NewBlock(H256),
@@ -81,7 +82,7 @@ impl Drop for BlockNotifier {
 	}
 }
 
-pub fn start(cfg: config::Config) -> Result<(), String> {
+pub fn start(cfg: config::Config, log_handle: logs::LogHandle) -> Result<(), String> {
 	let mut el = p2p::event_loop();
 
 	init_db(&cfg)?;
@@ -96,21 +97,29 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 			protocol_version: PROTOCOL_VERSION,
 			protocol_minimum: PROTOCOL_MINIMUM,
 			magic: cfg.consensus.magic(),
+			max_block_size: cfg.consensus.fork.max_serialized_block_size(),
 			local_address: SocketAddr::new(cfg.host, cfg.port),
 			services: cfg.services,
 			user_agent: cfg.user_agent,
 			start_height: 0,
 			relay: true,
+			proxy: cfg.proxy,
+			onion_proxy: cfg.onion_proxy,
+			v2_transport: cfg.v2_transport,
+			package_relay: cfg.package_relay,
 		},
-		peers: cfg.connect.map_or_else(|| vec![], |x| vec![x]),
+		peers: cfg.connect,
+		onion_peers: cfg.connect_onion,
 		seeds: cfg.seednodes,
 		node_table_path: nodes_path,
-		preferable_services: cfg.services,
+		// v2 transport falls back to v1, so it must not restrict peers selection
+		preferable_services: cfg.services.with_p2p_v2(false),
 		internet_protocol: cfg.internet_protocol,
+		tor_control: cfg.tor_control,
 	};
 
 	let sync_peers = create_sync_peers();
-	let local_sync_node = create_local_sync_node(cfg.consensus, cfg.db.clone(), sync_peers.clone(), cfg.verification_params);
+	let local_sync_node = create_local_sync_node(cfg.consensus.clone(), cfg.db.clone(), sync_peers.clone(), cfg.verification_params);
 	let sync_connection_factory = create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
 	if let Some(block_notify_command) = cfg.block_notify_command {
@@ -118,14 +127,29 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 	}
 
 	let p2p = p2p::P2P::new(p2p_cfg, sync_connection_factory, el.handle()).map_err(|x| x.to_string())?;
+	let metrics_deps = metrics_server::Dependencies {
+		storage: cfg.db.clone(),
+		local_sync_node: local_sync_node.clone(),
+		p2p_context: p2p.context().clone(),
+	};
+	let _metrics_server = metrics_server::new_http(cfg.metrics_config, metrics_deps)?;
+
+	// events are sent to subscribers of the WebSocket and IPC servers
+	let events = EventsNotifier::new(cfg.db.clone(), el.remote());
+	local_sync_node.install_sync_listener(Box::new(events.clone()));
+	local_sync_node.install_memory_pool_listener(Box::new(events.clone()));
+
 	let rpc_deps = rpc::Dependencies {
 		network: cfg.network,
+		consensus: cfg.consensus,
 		storage: cfg.db,
 		local_sync_node: local_sync_node,
 		p2p_context: p2p.context().clone(),
 		remote: el.remote(),
+		log_handle: log_handle,
+		events: events,
 	};
-	let _rpc_server = rpc::new_http(cfg.rpc_config, rpc_deps)?;
+	let _rpc_servers = rpc::new_servers(cfg.rpc_config, cfg.ws_config, cfg.ipc_config, rpc_deps)?;
 
 	p2p.run().map_err(|_| "Failed to start p2p module")?;
 	el.run(p2p::forever()).unwrap();
@@ -0,0 +1,59 @@
+use clap::ArgMatches;
+use verification::{BackwardsCompatibleChainVerifier, VerificationLevel};
+use config::Config;
+use util::init_db;
+
+/// Number of best blocks checked by default.
+const DEFAULT_DEPTH: u32 = 6;
+/// Default check level: indexes consistency, followed by full blocks verification.
+const DEFAULT_LEVEL: u32 = 2;
+
+pub fn verifydb(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
+	init_db(&cfg)?;
+
+	let level = match matches.value_of("level") {
+		Some(s) => s.parse().map_err(|_| "Invalid level - should be a number from 0 to 2".to_owned())?,
+		None => DEFAULT_LEVEL,
+	};
+	let verification_level = match level {
+		0 => None,
+		1 => Some(VerificationLevel::Header),
+		2 => Some(VerificationLevel::Full),
+		_ => return Err("Invalid level - should be a number from 0 to 2".into()),
+	};
+	let depth = match matches.value_of("depth") {
+		Some(s) => s.parse().map_err(|_| "Invalid depth - should be a number of blocks".to_owned())?,
+		None => DEFAULT_DEPTH,
+	};
+	let repair = matches.is_present("repair");
+
+	info!("Checking consistency of {} best blocks", depth);
+	let inconsistencies = cfg.db.check_integrity(depth, repair).map_err(|e| format!("{}", e))?;
+	for inconsistency in &inconsistencies {
+		match (repair, inconsistency.is_repairable()) {
+			(true, true) => warn!("{}. Repaired", inconsistency),
+			_ => warn!("{}", inconsistency),
+		}
+	}
+
+	let unrepaired = inconsistencies.iter()
+		.filter(|inconsistency| !repair || !inconsistency.is_repairable())
+		.count();
+	if unrepaired != 0 {
+		let hint = match inconsistencies.iter().all(|inconsistency| inconsistency.is_repairable()) {
+			true => "run with --repair to fix them",
+			false => "use `pbtc rollback` to a block before the first of them",
+		};
+		return Err(format!("Found {} database inconsistencies, {}", unrepaired, hint));
+	}
+
+	if let Some(verification_level) = verification_level {
+		info!("Verifying {} best blocks", depth);
+		let verifier = BackwardsCompatibleChainVerifier::new(cfg.db.clone(), cfg.consensus);
+		verifier.verify_canon_blocks(verification_level, depth)
+			.map_err(|(hash, err)| format!("Block {} is invalid: {:?}. Use `pbtc rollback` to a block before it", hash.to_reversed_str(), err))?;
+	}
+
+	info!("No database inconsistencies found");
+	Ok(())
+}
@@ -1,35 +1,51 @@
 use std::net;
 use clap;
+use logs;
 use storage;
 use message::Services;
 use network::{Network, ConsensusParams, ConsensusFork, BitcoinCashConsensusParams};
-use p2p::InternetProtocol;
+use p2p::{InternetProtocol, Proxy, ProxyCredentials, OnionPeer, TorControlConfig};
 use seednodes::{mainnet_seednodes, testnet_seednodes, bitcoin_cash_seednodes, bitcoin_cash_testnet_seednodes};
-use rpc_apis::ApiSet;
 use {USER_AGENT, REGTEST_USER_AGENT};
 use primitives::hash::H256;
-use rpc::HttpConfiguration as RpcHttpConfig;
-use verification::VerificationLevel;
+use rpc::{HttpConfiguration as RpcHttpConfig, WsConfiguration as RpcWsConfig, IpcConfiguration as RpcIpcConfig};
+use metrics_server::MetricsConfiguration;
+use verification::{VerificationLevel, Policy};
+use chain::constants::SATOSHIS_IN_COIN;
 use sync::VerificationParameters;
-use util::open_db;
+use util::{open_db, cookie_path, ipc_path, DatabaseBackend};
 
 pub struct Config {
 	pub network: Network,
 	pub consensus: ConsensusParams,
 	pub services: Services,
 	pub port: u16,
-	pub connect: Option<net::SocketAddr>,
+	pub connect: Vec<net::SocketAddr>,
+	pub connect_onion: Vec<OnionPeer>,
+	pub proxy: Option<Proxy>,
+	pub onion_proxy: Option<Proxy>,
+	pub tor_control: Option<TorControlConfig>,
+	pub v2_transport: bool,
+	pub package_relay: bool,
 	pub host: net::IpAddr,
 	pub seednodes: Vec<String>,
 	pub quiet: bool,
+	pub log_format: LogFormat,
+	pub log_file: bool,
+	pub log_modules: Vec<String>,
+	pub log_file_size: u64,
 	pub inbound_connections: u32,
 	pub outbound_connections: u32,
 	pub p2p_threads: usize,
+	pub db_backend: DatabaseBackend,
 	pub db_cache: usize,
 	pub data_dir: Option<String>,
 	pub user_agent: String,
 	pub internet_protocol: InternetProtocol,
 	pub rpc_config: RpcHttpConfig,
+	pub ws_config: RpcWsConfig,
+	pub ipc_config: RpcIpcConfig,
+	pub metrics_config: MetricsConfiguration,
 	pub block_notify_command: Option<String>,
 	pub verification_params: VerificationParameters,
 	pub db: storage::SharedStore,
@@ -37,6 +53,13 @@ pub struct Config {
 
 pub const DEFAULT_DB_CACHE: usize = 512;
 
+/// Format of log messages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+	Text,
+	Json,
+}
+
 pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 	let db_cache = match matches.value_of("db-cache") {
 		Some(s) => s.parse().map_err(|_| "Invalid cache size - should be number in MB".to_owned())?,
@@ -48,9 +71,30 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => None,
 	};
 
-	let db = open_db(&data_dir, db_cache);
+	let db_backend = match matches.value_of("db-backend") {
+		Some("rocksdb") | None => DatabaseBackend::RocksDb,
+		Some("sled") => DatabaseBackend::Sled,
+		Some(_) => return Err("Invalid database backend - should be rocksdb or sled".into()),
+	};
+
+	let block_filter_index = matches.is_present("blockfilterindex");
+	let db = open_db(&data_dir, db_backend, db_cache, block_filter_index);
 
 	let quiet = matches.is_present("quiet");
+	let log_format = match matches.value_of("log-format") {
+		Some("text") | None => LogFormat::Text,
+		Some("json") => LogFormat::Json,
+		Some(_) => return Err("Invalid log format - should be text or json".into()),
+	};
+	let log_file = matches.is_present("log-file");
+	let log_modules = match matches.value_of("log-modules") {
+		Some(s) => s.split(',').map(str::trim).filter(|module| !module.is_empty()).map(Into::into).collect(),
+		None => Vec::new(),
+	};
+	let log_file_size = match matches.value_of("log-file-size") {
+		Some(s) => s.parse::<u64>().map_err(|_| "Invalid log file size - should be number in MB".to_owned())? * 1024 * 1024,
+		None => logs::DEFAULT_MAX_LOG_FILE_SIZE,
+	};
 	let network = match (matches.is_present("testnet"), matches.is_present("regtest")) {
 		(true, false) => Network::Testnet,
 		(false, true) => Network::Regtest,
@@ -59,12 +103,26 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 	};
 
 	let consensus_fork = parse_consensus_fork(network, &db, &matches)?;
-	let consensus = ConsensusParams::new(network, consensus_fork);
+	let mut consensus = ConsensusParams::new(network, consensus_fork);
+	match matches.value_of("assumevalid") {
+		Some(s) if s == "0" => consensus.assume_valid = H256::default(),
+		Some(s) => {
+			let assume_valid: H256 = s.parse().map_err(|_| "Invalid assumevalid".to_owned())?;
+			consensus.assume_valid = assume_valid.reversed();
+		},
+		None => (),
+	}
 
-	let (in_connections, out_connections) = match network {
+	let (mut in_connections, mut out_connections) = match network {
 		Network::Testnet | Network::Mainnet | Network::Other(_) => (10, 10),
 		Network::Regtest | Network::Unitest => (1, 0),
 	};
+	if let Some(s) = matches.value_of("inbound-connections") {
+		in_connections = s.parse().map_err(|_| "Invalid inbound-connections - should be number".to_owned())?;
+	}
+	if let Some(s) = matches.value_of("outbound-connections") {
+		out_connections = s.parse().map_err(|_| "Invalid outbound-connections - should be number".to_owned())?;
+	}
 
 	let p2p_threads = match network {
 		Network::Testnet | Network::Mainnet | Network::Other(_) => 4,
@@ -86,18 +144,22 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => network.port(),
 	};
 
-	let connect = match matches.value_of("connect") {
-		Some(s) => Some(match s.parse::<net::SocketAddr>() {
-			Err(_) => s.parse::<net::IpAddr>()
-				.map(|ip| net::SocketAddr::new(ip, network.port()))
-				.map_err(|_| "Invalid connect".to_owned()),
-			Ok(a) => Ok(a),
-		}?),
-		None => None,
-	};
+	let mut connect = Vec::new();
+	let mut connect_onion = Vec::new();
+	for s in matches.values_of("connect").into_iter().flat_map(|values| values) {
+		match OnionPeer::parse(s, network.port()) {
+			Some(onion_peer) => connect_onion.push(onion_peer),
+			None => connect.push(match s.parse::<net::SocketAddr>() {
+				Err(_) => s.parse::<net::IpAddr>()
+					.map(|ip| net::SocketAddr::new(ip, network.port()))
+					.map_err(|_| "Invalid connect".to_owned()),
+				Ok(a) => Ok(a),
+			}?),
+		}
+	}
 
-	let seednodes: Vec<String> = match matches.value_of("seednode") {
-		Some(s) => vec![s.parse().map_err(|_| "Invalid seednode".to_owned())?],
+	let seednodes: Vec<String> = match matches.values_of("seednode") {
+		Some(values) => values.map(Into::into).collect(),
 		None => match (network, &consensus.fork) {
 			(Network::Mainnet, &ConsensusFork::BitcoinCash(_)) => bitcoin_cash_seednodes().into_iter().map(Into::into).collect(),
 			(Network::Testnet, &ConsensusFork::BitcoinCash(_)) => bitcoin_cash_testnet_seednodes().into_iter().map(Into::into).collect(),
@@ -120,14 +182,33 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		}
 	};
 
-	let rpc_config = parse_rpc_config(network, matches)?;
+	let (proxy, onion_proxy) = parse_proxy_config(matches)?;
+	if !connect_onion.is_empty() && onion_proxy.is_none() && proxy.is_none() {
+		return Err("Connecting to onion address requires --proxy or --onion".into());
+	}
+
+	let tor_control = match matches.value_of("tor-control") {
+		Some(s) => Some(TorControlConfig {
+			address: s.parse().map_err(|_| "Invalid tor-control".to_owned())?,
+			password: matches.value_of("tor-password").map(Into::into),
+		}),
+		None => None,
+	};
+
+	let v2_transport = !matches.is_present("no-v2transport");
+	let package_relay = !matches.is_present("no-packagerelay");
+
+	let rpc_config = parse_rpc_config(network, &data_dir, matches)?;
+	let ws_config = parse_ws_config(matches)?;
+	let ipc_config = parse_ipc_config(&data_dir, matches);
+	let metrics_config = parse_metrics_config(matches)?;
 
 	let block_notify_command = match matches.value_of("blocknotify") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid blocknotify commmand".to_owned())?),
 		None => None,
 	};
 
-	let services = Services::default().with_network(true);
+	let services = Services::default().with_network(true).with_p2p_v2(v2_transport).with_compact_filters(block_filter_index);
 	let services = match &consensus.fork {
 		&ConsensusFork::BitcoinCash(_) => services.with_bitcoin_cash(true),
 		&ConsensusFork::BitcoinCore => services.with_witness(true),
@@ -149,27 +230,50 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		_ => network.default_verification_edge(),
 	};
 
+	let script_threads = match matches.value_of("par") {
+		Some(s) => s.parse().map_err(|_| "Invalid number of script verification threads".to_owned())?,
+		None => 0,
+	};
+
+	let policy = parse_policy(network, matches)?;
+
 	let config = Config {
 		quiet: quiet,
+		log_format: log_format,
+		log_file: log_file,
+		log_modules: log_modules,
+		log_file_size: log_file_size,
 		network: network,
 		consensus: consensus,
 		services: services,
 		port: port,
 		connect: connect,
+		connect_onion: connect_onion,
+		proxy: proxy,
+		onion_proxy: onion_proxy,
+		tor_control: tor_control,
+		v2_transport: v2_transport,
+		package_relay: package_relay,
 		host: host,
 		seednodes: seednodes,
 		inbound_connections: in_connections,
 		outbound_connections: out_connections,
 		p2p_threads: p2p_threads,
+		db_backend: db_backend,
 		db_cache: db_cache,
 		data_dir: data_dir,
 		user_agent: user_agent,
 		internet_protocol: only_net,
 		rpc_config: rpc_config,
+		ws_config: ws_config,
+		ipc_config: ipc_config,
+		metrics_config: metrics_config,
 		block_notify_command: block_notify_command,
 		verification_params: VerificationParameters {
 			verification_level: verification_level,
 			verification_edge: verification_edge,
+			script_threads: script_threads,
+			policy: policy,
 		},
 		db: db,
 	};
@@ -203,15 +307,45 @@ fn parse_consensus_fork(network: Network, db: &storage::SharedStore, matches: &c
 	};
 }
 
-fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcHttpConfig, String> {
+fn parse_proxy_config(matches: &clap::ArgMatches) -> Result<(Option<Proxy>, Option<Proxy>), String> {
+	let credentials = match matches.value_of("proxy-auth") {
+		Some(s) => match s.find(':') {
+			Some(pos) => Some(ProxyCredentials {
+				username: s[..pos].into(),
+				password: s[pos + 1..].into(),
+			}),
+			None => return Err("Invalid proxy-auth - should be USER:PASSWORD".into()),
+		},
+		None => None,
+	};
+	let randomize_credentials = matches.is_present("proxy-randomize");
+
+	let parse_proxy = |s: &str, error: &str| -> Result<Proxy, String> {
+		let mut proxy = Proxy::new(s.parse().map_err(|_| error.to_owned())?);
+		proxy.credentials = credentials.clone();
+		proxy.randomize_credentials = randomize_credentials;
+		Ok(proxy)
+	};
+
+	let proxy = match matches.value_of("proxy") {
+		Some(s) => Some(parse_proxy(s, "Invalid proxy")?),
+		None => None,
+	};
+	let onion_proxy = match matches.value_of("onion") {
+		Some(s) => Some(parse_proxy(s, "Invalid onion proxy")?),
+		None => None,
+	};
+
+	Ok((proxy, onion_proxy))
+}
+
+fn parse_rpc_config(network: Network, data_dir: &Option<String>, matches: &clap::ArgMatches) -> Result<RpcHttpConfig, String> {
+	// APIs and authentication settings are also used by the WebSocket and IPC servers
 	let mut config = RpcHttpConfig::with_port(network.rpc_port());
 	config.enabled = !matches.is_present("no-jsonrpc");
-	if !config.enabled {
-		return Ok(config);
-	}
 
 	if let Some(apis) = matches.value_of("jsonrpc-apis") {
-		config.apis = ApiSet::List(vec![apis.parse().map_err(|_| "Invalid APIs".to_owned())?].into_iter().collect());
+		config.apis = apis.parse().map_err(|_| "Invalid APIs".to_owned())?;
 	}
 	if let Some(port) = matches.value_of("jsonrpc-port") {
 		config.port = port.parse().map_err(|_| "Invalid JSON RPC port".to_owned())?;
@@ -225,6 +359,106 @@ fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcH
 	if let Some(hosts) = matches.value_of("jsonrpc-hosts") {
 		config.hosts = Some(vec![hosts.parse().map_err(|_| "Invalid JSON RPC hosts".to_owned())?]);
 	}
+	config.auth = !matches.is_present("no-jsonrpc-auth");
+	if let Some(users) = matches.value_of("jsonrpc-auth") {
+		config.auth_users = users.split(',').map(str::trim).filter(|user| !user.is_empty()).map(Into::into).collect();
+	}
+	if let Some(whitelist) = matches.value_of("jsonrpc-whitelist") {
+		for entry in whitelist.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+			let mut parts = entry.splitn(2, ':');
+			let user = parts.next().expect("splitn always yields at least one item; qed");
+			let apis = parts.next().ok_or_else(|| format!("Invalid JSON RPC whitelist entry {} - should be USER:APIS", entry))?;
+			config.whitelist.insert(user.to_owned(), apis.parse()?);
+		}
+	}
+	config.cookie_path = cookie_path(data_dir);
 
 	Ok(config)
 }
+
+fn parse_ws_config(matches: &clap::ArgMatches) -> Result<RpcWsConfig, String> {
+	let mut config = RpcWsConfig::default();
+	config.enabled = matches.is_present("ws");
+	if !config.enabled {
+		return Ok(config);
+	}
+
+	if let Some(port) = matches.value_of("ws-port") {
+		config.port = port.parse().map_err(|_| "Invalid WebSocket port".to_owned())?;
+	}
+	if let Some(interface) = matches.value_of("ws-interface") {
+		config.interface = interface.to_owned();
+	}
+	if let Some(origins) = matches.value_of("ws-origins") {
+		config.origins = Some(origins.split(',').map(str::trim).filter(|origin| !origin.is_empty()).map(Into::into).collect());
+	}
+	if let Some(hosts) = matches.value_of("ws-hosts") {
+		config.hosts = Some(hosts.split(',').map(str::trim).filter(|host| !host.is_empty()).map(Into::into).collect());
+	}
+
+	Ok(config)
+}
+
+fn parse_ipc_config(data_dir: &Option<String>, matches: &clap::ArgMatches) -> RpcIpcConfig {
+	RpcIpcConfig {
+		enabled: matches.is_present("ipc"),
+		path: matches.value_of("ipc-path").map_or_else(|| ipc_path(data_dir), Into::into),
+	}
+}
+
+fn parse_metrics_config(matches: &clap::ArgMatches) -> Result<MetricsConfiguration, String> {
+	let mut config = MetricsConfiguration::default();
+	config.enabled = matches.is_present("metrics");
+	if !config.enabled {
+		return Ok(config);
+	}
+
+	if let Some(port) = matches.value_of("metrics-port") {
+		config.port = port.parse().map_err(|_| "Invalid metrics port".to_owned())?;
+	}
+	if let Some(interface) = matches.value_of("metrics-interface") {
+		config.interface = interface.to_owned();
+	}
+
+	Ok(config)
+}
+
+fn parse_policy(network: Network, matches: &clap::ArgMatches) -> Result<Policy, String> {
+	let mut policy = Policy::default();
+	match network {
+		Network::Mainnet | Network::Other(_) => (),
+		// during regtests, peer is providing us with zero fee transactions => we shouldn't ignore these
+		Network::Regtest | Network::Unitest => {
+			policy.accept_non_std_txn = true;
+			policy.min_relay_fee = 0;
+		},
+		Network::Testnet => policy.accept_non_std_txn = true,
+	}
+
+	policy.accept_non_std_txn = match matches.value_of("acceptnonstdtxn") {
+		Some("0") => false,
+		Some("1") => true,
+		Some(_) => return Err("Invalid acceptnonstdtxn value, expected 0 or 1".to_owned()),
+		None => policy.accept_non_std_txn,
+	};
+	if let Some(rate) = matches.value_of("minrelaytxfee") {
+		policy.min_relay_fee = parse_fee_rate(rate).ok_or_else(|| "Invalid minrelaytxfee".to_owned())?;
+	}
+	if let Some(rate) = matches.value_of("dustrelayfee") {
+		policy.dust_relay_fee = parse_fee_rate(rate).ok_or_else(|| "Invalid dustrelayfee".to_owned())?;
+	}
+	policy.data_carrier = !matches.is_present("no-datacarrier");
+	if let Some(size) = matches.value_of("datacarriersize") {
+		policy.max_data_carrier_bytes = size.parse().map_err(|_| "Invalid datacarriersize".to_owned())?;
+	}
+
+	Ok(policy)
+}
+
+/// Parses fee rate in BTC/kvB, returning satoshis per kvB
+fn parse_fee_rate(rate: &str) -> Option<u64> {
+	match rate.parse::<f64>() {
+		Ok(rate) if rate >= 0f64 && rate.is_finite() => Some((rate * SATOSHIS_IN_COIN as f64).round() as u64),
+		_ => None,
+	}
+}
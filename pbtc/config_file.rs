@@ -0,0 +1,289 @@
+use std::{env, fs, io};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use app_dirs::{app_root, AppDataType};
+use clap;
+use yaml_rust::Yaml;
+use APP_INFO;
+
+pub const CONFIG_FILE_NAME: &'static str = "pbtc.conf";
+
+/// Network sections of the config file
+const SECTIONS: [&'static str; 3] = ["main", "test", "regtest"];
+
+/// Groups of mutually exclusive options. Setting any option of the group on the command
+/// line overrides all options of this group in the config file.
+const EXCLUSIVE_OPTIONS: [&'static [&'static str]; 2] = [&["testnet", "regtest"], &["btc", "bch"]];
+
+/// Parsed `pbtc.conf` file. Every line is either `key=value` option, or `[section]` header.
+/// Keys are the long names of the command line options. Options of the `[main]`, `[test]`
+/// and `[regtest]` sections are only applied to the corresponding network. Repeated keys
+/// keep all their values, in order. Lines starting with `#` are comments.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigFile {
+	global: BTreeMap<String, Vec<String>>,
+	sections: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl ConfigFile {
+	pub fn parse(contents: &str) -> Result<Self, String> {
+		let mut result = ConfigFile::default();
+		let mut section = None;
+		for (index, line) in contents.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			if line.starts_with('[') && line.ends_with(']') {
+				let name = line[1..line.len() - 1].trim();
+				if !SECTIONS.contains(&name) {
+					return Err(format!("Unknown section [{}] at line {}, expected one of [main], [test], [regtest]", name, index + 1));
+				}
+				section = Some(name.to_owned());
+				continue;
+			}
+
+			let mut parts = line.splitn(2, '=');
+			let key = parts.next().expect("splitn always yields at least one item; qed").trim();
+			let value = match parts.next() {
+				Some(value) if !key.is_empty() => value.trim(),
+				_ => return Err(format!("Invalid option at line {}, expected key=value", index + 1)),
+			};
+
+			let options = match section {
+				Some(ref section) => result.sections.entry(section.clone()).or_insert_with(BTreeMap::new),
+				None => &mut result.global,
+			};
+			options.entry(key.to_owned()).or_insert_with(Vec::new).push(value.to_owned());
+		}
+
+		Ok(result)
+	}
+
+	/// Reads config file. Returns None if the file does not exist.
+	pub fn load(path: &Path) -> Result<Option<Self>, String> {
+		match fs::read_to_string(path) {
+			Ok(contents) => ConfigFile::parse(&contents)
+				.map(Some)
+				.map_err(|err| format!("Invalid config file {}: {}", path.display(), err)),
+			Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(err) => Err(format!("Failed to read config file {}: {}", path.display(), err)),
+		}
+	}
+
+	/// Returns options, applied to the network of given section. Section options override global ones.
+	pub fn options(&self, section: &str) -> BTreeMap<String, Vec<String>> {
+		let mut options = self.global.clone();
+		if let Some(section_options) = self.sections.get(section) {
+			options.extend(section_options.clone());
+		}
+		options
+	}
+}
+
+/// Default path of the config file
+pub fn default_path(data_dir: Option<&str>) -> PathBuf {
+	match data_dir {
+		Some(data_dir) => Path::new(data_dir).join(CONFIG_FILE_NAME),
+		None => app_root(AppDataType::UserData, &APP_INFO).expect("Failed to get app dir").join(CONFIG_FILE_NAME),
+	}
+}
+
+/// Reads config file and parses command line arguments again with config file options
+/// added. Options, given on the command line, take precedence over the config file ones.
+pub fn apply<'a>(yaml: &'a Yaml, matches: clap::ArgMatches<'a>) -> Result<clap::ArgMatches<'a>, String> {
+	apply_with_args(yaml, matches, env::args_os())
+}
+
+fn apply_with_args<'a, I>(yaml: &'a Yaml, matches: clap::ArgMatches<'a>, args: I) -> Result<clap::ArgMatches<'a>, String> where I: IntoIterator<Item = OsString> {
+	let path = match matches.value_of("conf") {
+		Some(path) => PathBuf::from(path),
+		None => default_path(matches.value_of("data-dir")),
+	};
+
+	let config_file = match ConfigFile::load(&path)? {
+		Some(config_file) => config_file,
+		None if matches.is_present("conf") => return Err(format!("Config file {} does not exist", path.display())),
+		None => return Ok(matches),
+	};
+
+	let is_set = |flag: &str| -> Result<bool, String> {
+		match is_overridden(&matches, flag) {
+			true => Ok(matches.is_present(flag)),
+			false => match config_file.global.get(flag).and_then(|values| values.last()) {
+				Some(value) => parse_flag(flag, value),
+				None => Ok(false),
+			},
+		}
+	};
+	let section = match (is_set("testnet")?, is_set("regtest")?) {
+		(_, true) => "regtest",
+		(true, false) => "test",
+		(false, false) => "main",
+	};
+
+	let known_args = known_args(yaml);
+	let mut config_args = Vec::new();
+	for (key, values) in config_file.options(section) {
+		let takes_value = match known_args.get(&key) {
+			Some(_) if key == "conf" => return Err(format!("Option conf can not be set in the config file {}", path.display())),
+			Some(takes_value) => *takes_value,
+			None => return Err(format!("Unknown option {} in the config file {}", key, path.display())),
+		};
+
+		// command line options override config file
+		if is_overridden(&matches, &key) {
+			continue;
+		}
+
+		for value in values {
+			match takes_value {
+				true => config_args.push(format!("--{}={}", key, value)),
+				false => if parse_flag(&key, &value)? {
+					config_args.push(format!("--{}", key));
+					break;
+				},
+			}
+		}
+	}
+
+	if config_args.is_empty() {
+		return Ok(matches);
+	}
+
+	// config file options are inserted before command line arguments, so that they are never
+	// treated as subcommand arguments
+	let mut args = args.into_iter();
+	let args = args.next().into_iter()
+		.chain(config_args.into_iter().map(OsString::from))
+		.chain(args)
+		.collect::<Vec<_>>();
+	clap::App::from_yaml(yaml).get_matches_from_safe(args).map_err(|err| err.message)
+}
+
+/// Returns true if the option, or any option, exclusive with it, is set on the command line
+fn is_overridden(matches: &clap::ArgMatches, key: &str) -> bool {
+	let group = EXCLUSIVE_OPTIONS.iter()
+		.find(|group| group.contains(&key))
+		.map(|group| group.to_vec())
+		.unwrap_or_else(|| vec![key]);
+	group.into_iter().any(|key| matches.occurrences_of(key) != 0)
+}
+
+/// Returns long names of the arguments and whether they take value
+fn known_args(yaml: &Yaml) -> BTreeMap<String, bool> {
+	let mut result = BTreeMap::new();
+	for arg in yaml["args"].as_vec().into_iter().flat_map(|args| args.iter()) {
+		for (_, settings) in arg.as_hash().into_iter().flat_map(|arg| arg.iter()) {
+			if let Some(long) = settings["long"].as_str() {
+				result.insert(long.to_owned(), settings["takes_value"].as_bool().unwrap_or(false));
+			}
+		}
+	}
+	result
+}
+
+fn parse_flag(key: &str, value: &str) -> Result<bool, String> {
+	match value {
+		"1" | "true" => Ok(true),
+		"0" | "false" => Ok(false),
+		_ => Err(format!("Invalid value of {} in the config file - should be 1 or 0", key)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs};
+	use std::collections::BTreeMap;
+	use std::ffi::OsString;
+	use std::path::PathBuf;
+	use clap;
+	use super::{ConfigFile, apply_with_args};
+
+	/// Writes config file and returns command line arguments, reading it
+	fn args_with_config(name: &str, contents: &str, args: &[&str]) -> Vec<OsString> {
+		let path: PathBuf = env::temp_dir().join(format!("pbtc-{}.conf", name));
+		fs::write(&path, contents).unwrap();
+		let conf = format!("--conf={}", path.display());
+		Some("pbtc").into_iter().chain(Some(conf.as_str())).chain(args.iter().cloned()).map(OsString::from).collect()
+	}
+
+	fn apply(name: &str, contents: &str, args: &[&str], check: &dyn Fn(&clap::ArgMatches)) {
+		let yaml = load_yaml!("cli.yml");
+		let args = args_with_config(name, contents, args);
+		let matches = clap::App::from_yaml(yaml).get_matches_from_safe(args.clone()).unwrap();
+		let matches = apply_with_args(yaml, matches, args).unwrap();
+		check(&matches);
+	}
+
+	fn values(values: &[&str]) -> Vec<String> {
+		values.iter().map(|value| value.to_string()).collect()
+	}
+
+	#[test]
+	fn config_file_keeps_repeated_values() {
+		let config_file = ConfigFile::parse("connect=127.0.0.1:8333\nconnect=127.0.0.2:8333\nport=8333").unwrap();
+		let mut expected = BTreeMap::new();
+		expected.insert("connect".to_owned(), values(&["127.0.0.1:8333", "127.0.0.2:8333"]));
+		expected.insert("port".to_owned(), values(&["8333"]));
+		assert_eq!(config_file.options("main"), expected);
+	}
+
+	#[test]
+	fn config_file_section_overrides_global_values() {
+		let config_file = ConfigFile::parse("seednode=a\nseednode=b\nport=1\n[test]\nseednode=c\n[main]\nport=2").unwrap();
+		assert_eq!(config_file.options("test")["seednode"], values(&["c"]));
+		assert_eq!(config_file.options("test")["port"], values(&["1"]));
+		assert_eq!(config_file.options("main")["seednode"], values(&["a", "b"]));
+		assert_eq!(config_file.options("main")["port"], values(&["2"]));
+		assert_eq!(config_file.options("regtest")["port"], values(&["1"]));
+	}
+
+	#[test]
+	fn config_file_strips_only_line_comments() {
+		let config_file = ConfigFile::parse("# comment\n  # indented comment\njsonrpc-auth=user:pass#word\n").unwrap();
+		assert_eq!(config_file.options("main").len(), 1);
+		assert_eq!(config_file.options("main")["jsonrpc-auth"], values(&["user:pass#word"]));
+	}
+
+	#[test]
+	fn config_file_rejects_invalid_lines() {
+		assert_eq!(ConfigFile::parse("port").unwrap_err(), "Invalid option at line 1, expected key=value");
+		assert_eq!(ConfigFile::parse("\n=8333").unwrap_err(), "Invalid option at line 2, expected key=value");
+		assert_eq!(ConfigFile::parse("[unknown]").unwrap_err(), "Unknown section [unknown] at line 1, expected one of [main], [test], [regtest]");
+	}
+
+	#[test]
+	fn apply_adds_config_file_options() {
+		apply("apply-adds", "port=1\nseednode=a\nseednode=b\n[test]\nport=2", &[], &|matches| {
+			assert!(!matches.is_present("testnet"));
+			assert_eq!(matches.value_of("port"), Some("1"));
+			assert_eq!(matches.values_of("seednode").unwrap().collect::<Vec<_>>(), vec!["a", "b"]);
+		});
+		apply("apply-section", "testnet=1\nport=1\n[test]\nport=2", &[], &|matches| {
+			assert!(matches.is_present("testnet"));
+			assert_eq!(matches.value_of("port"), Some("2"));
+		});
+	}
+
+	#[test]
+	fn apply_command_line_overrides_config_file() {
+		apply("apply-overrides", "port=1\nseednode=a", &["--port=3"], &|matches| {
+			assert_eq!(matches.value_of("port"), Some("3"));
+			assert_eq!(matches.value_of("seednode"), Some("a"));
+		});
+	}
+
+	#[test]
+	fn apply_command_line_overrides_config_file_network() {
+		apply("apply-network", "testnet=1\nbtc=1\n[regtest]\nport=4", &["--regtest", "--bch"], &|matches| {
+			assert!(!matches.is_present("testnet"));
+			assert!(matches.is_present("regtest"));
+			assert!(!matches.is_present("btc"));
+			assert!(matches.is_present("bch"));
+			assert_eq!(matches.value_of("port"), Some("4"));
+		});
+	}
+}
@@ -4,9 +4,10 @@
 extern crate clap;
 #[macro_use]
 extern crate log;
-extern crate env_logger;
 extern crate app_dirs;
 extern crate libc;
+extern crate rayon;
+extern crate yaml_rust;
 
 extern crate storage;
 extern crate db;
@@ -20,11 +21,14 @@ extern crate p2p;
 extern crate sync;
 extern crate import;
 extern crate rpc as ethcore_rpc;
+extern crate metrics;
 extern crate primitives;
 extern crate verification;
 
 mod commands;
 mod config;
+mod config_file;
+mod metrics_server;
 mod seednodes;
 mod util;
 mod rpc;
@@ -51,21 +55,18 @@ fn main() {
 fn run() -> Result<(), String> {
 	let yaml = load_yaml!("cli.yml");
 	let matches = clap::App::from_yaml(yaml).get_matches();
+	let matches = config_file::apply(yaml, matches)?;
 	let cfg = config::parse(&matches)?;
 
-	if !cfg.quiet {
-		if cfg!(windows) {
-			logs::init(LOG_INFO, logs::DateLogFormatter);
-		} else {
-			logs::init(LOG_INFO, logs::DateAndColorLogFormatter);
-		}
-	} else {
-		env_logger::init();
-	}
+	let log_handle = util::init_logs(&cfg)?;
 
 	match matches.subcommand() {
 		("import", Some(import_matches)) => commands::import(cfg, import_matches),
+		("export", Some(export_matches)) => commands::export(cfg, export_matches),
 		("rollback", Some(rollback_matches)) => commands::rollback(cfg, rollback_matches),
-		_ => commands::start(cfg),
+		("migrate", Some(_)) => commands::migrate(cfg),
+		("verifydb", Some(verifydb_matches)) => commands::verifydb(cfg, verifydb_matches),
+		("script-debug", Some(script_debug_matches)) => commands::script_debug(cfg, script_debug_matches),
+		_ => commands::start(cfg, log_handle),
 	}
 }
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use metrics::{self, MetricsSource, MetricsWriter, MetricType};
+use p2p;
+use storage;
+use sync;
+
+pub const DEFAULT_METRICS_PORT: u16 = 9332;
+
+pub struct Dependencies {
+	pub storage: storage::SharedStore,
+	pub local_sync_node: sync::LocalNodeRef,
+	pub p2p_context: Arc<p2p::Context>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MetricsConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+}
+
+impl Default for MetricsConfiguration {
+	fn default() -> Self {
+		MetricsConfiguration {
+			enabled: false,
+			interface: "127.0.0.1".into(),
+			port: DEFAULT_METRICS_PORT,
+		}
+	}
+}
+
+pub fn new_http(conf: MetricsConfiguration, deps: Dependencies) -> Result<Option<metrics::Server>, String> {
+	if !conf.enabled {
+		return Ok(None);
+	}
+
+	let url = format!("{}:{}", conf.interface, conf.port);
+	let addr = url.parse().map_err(|_| format!("Invalid metrics listen host/port given: {}", url))?;
+	let server = metrics::start_http(&addr, NodeMetrics { deps: deps })
+		.map_err(|err| format!("Failed to start metrics server at {}: {}", url, err))?;
+	info!("Metrics are served at http://{}/metrics", server.address());
+	Ok(Some(server))
+}
+
+struct NodeMetrics {
+	deps: Dependencies,
+}
+
+impl MetricsSource for NodeMetrics {
+	fn write_metrics(&self, writer: &mut MetricsWriter) {
+		let information = self.deps.local_sync_node.information();
+		let best_block_height = self.deps.storage.best_block().number;
+		writer.gauge("pbtc_best_block_height", "Height of the best stored block.", best_block_height);
+		writer.gauge("pbtc_best_header_height", "Height of the best known header.",
+			information.chain.stored - 1 + information.chain.headers.best);
+
+		let (inbound, outbound) = self.deps.p2p_context.connections_count();
+		writer.metric("pbtc_peers", "Number of connected peers.", MetricType::Gauge);
+		writer.sample("pbtc_peers", &[("direction", "inbound")], inbound);
+		writer.sample("pbtc_peers", &[("direction", "outbound")], outbound);
+
+		let network_stats = self.deps.p2p_context.network_stats();
+		let mut sent = network_stats.sent.into_iter().map(|(command, stats)| (command.to_string(), stats)).collect::<Vec<_>>();
+		let mut received = network_stats.received.into_iter().map(|(command, stats)| (command.to_string(), stats)).collect::<Vec<_>>();
+		sent.sort_by(|a, b| a.0.cmp(&b.0));
+		received.sort_by(|a, b| a.0.cmp(&b.0));
+
+		writer.metric("pbtc_p2p_sent_messages_total", "Number of messages sent to peers.", MetricType::Counter);
+		for &(ref command, ref stats) in &sent {
+			writer.sample("pbtc_p2p_sent_messages_total", &[("command", command)], stats.messages);
+		}
+		writer.metric("pbtc_p2p_sent_bytes_total", "Number of bytes sent to peers.", MetricType::Counter);
+		for &(ref command, ref stats) in &sent {
+			writer.sample("pbtc_p2p_sent_bytes_total", &[("command", command)], stats.bytes);
+		}
+		writer.metric("pbtc_p2p_received_messages_total", "Number of messages received from peers.", MetricType::Counter);
+		for &(ref command, ref stats) in &received {
+			writer.sample("pbtc_p2p_received_messages_total", &[("command", command)], stats.messages);
+		}
+		writer.metric("pbtc_p2p_received_bytes_total", "Number of bytes received from peers.", MetricType::Counter);
+		for &(ref command, ref stats) in &received {
+			writer.sample("pbtc_p2p_received_bytes_total", &[("command", command)], stats.bytes);
+		}
+
+		let mempool = &information.chain.transactions;
+		writer.gauge("pbtc_mempool_transactions", "Number of transactions in the memory pool.", mempool.transactions_count);
+		writer.gauge("pbtc_mempool_bytes", "Size of transactions in the memory pool.", mempool.transactions_size_in_bytes);
+		writer.gauge("pbtc_mempool_fees_satoshis", "Total fee of transactions in the memory pool.", mempool.transactions_fee);
+
+		writer.gauge("pbtc_orphan_blocks", "Number of blocks in the orphan pool.", information.orphaned_blocks);
+		writer.gauge("pbtc_orphan_transactions", "Number of transactions in the orphan pool.", information.orphaned_transactions);
+
+		let verification_stats = self.deps.local_sync_node.verification_stats();
+		writer.gauge("pbtc_verification_queue", "Number of blocks and transactions waiting for verification.", verification_stats.queue_len());
+		writer.histogram("pbtc_block_verification_seconds", "Time spent verifying blocks.", &verification_stats.block_verification_time());
+	}
+}
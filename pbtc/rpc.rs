@@ -1,19 +1,30 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use rpc_apis::{self, ApiSet};
-use ethcore_rpc::{Server, start_http, MetaIoHandler, Compatibility, Remote};
-use network::Network;
+use ethcore_rpc::{Server, WsServer, IpcServer, start_http, start_ws, start_ipc, MetaIoHandler, Metadata, MethodsWhitelist,
+	RpcAuth, CookieFile, Remote, COOKIE_USER};
+use ethcore_rpc::v1::EventsNotifier;
+use network::{Network, ConsensusParams};
 use std::io;
 use sync;
 use storage;
 use p2p;
+use logs;
 
+pub const DEFAULT_WS_PORT: u16 = 9333;
+
+#[derive(Clone)]
 pub struct Dependencies {
 	pub network: Network,
+	pub consensus: ConsensusParams,
 	pub local_sync_node: sync::LocalNodeRef,
 	pub storage: storage::SharedStore,
 	pub p2p_context: Arc<p2p::Context>,
 	pub remote: Remote,
+	pub log_handle: logs::LogHandle,
+	pub events: EventsNotifier,
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,6 +35,39 @@ pub struct HttpConfiguration {
 	pub apis: ApiSet,
 	pub cors: Option<Vec<String>>,
 	pub hosts: Option<Vec<String>>,
+	/// Require HTTP Basic authentication
+	pub auth: bool,
+	/// `USER:SALT$HASH` entries of the users
+	pub auth_users: Vec<String>,
+	/// APIs, available to the users. Users, which are not listed, may use all APIs.
+	pub whitelist: HashMap<String, ApiSet>,
+	/// Path of the cookie file
+	pub cookie_path: PathBuf,
+}
+
+/// WebSocket server configuration. APIs and authentication settings are shared with the HTTP server.
+#[derive(Debug, PartialEq)]
+pub struct WsConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+	pub origins: Option<Vec<String>>,
+	pub hosts: Option<Vec<String>>,
+}
+
+/// IPC server configuration. APIs are shared with the HTTP server.
+#[derive(Debug, PartialEq)]
+pub struct IpcConfiguration {
+	pub enabled: bool,
+	pub path: String,
+}
+
+/// JSON-RPC servers. Cookie file is removed after the servers are stopped.
+pub struct Servers {
+	_http: Option<Server>,
+	_ws: Option<WsServer>,
+	_ipc: Option<IpcServer>,
+	_cookie: Option<CookieFile>,
 }
 
 impl HttpConfiguration {
@@ -35,29 +79,104 @@ impl HttpConfiguration {
 			apis: ApiSet::default(),
 			cors: None,
 			hosts: Some(Vec::new()),
+			auth: true,
+			auth_users: Vec::new(),
+			whitelist: HashMap::new(),
+			cookie_path: PathBuf::new(),
 		}
 	}
 }
 
-pub fn new_http(conf: HttpConfiguration, deps: Dependencies) -> Result<Option<Server>, String> {
-	if !conf.enabled {
-		return Ok(None);
+impl Default for WsConfiguration {
+	fn default() -> Self {
+		WsConfiguration {
+			enabled: false,
+			interface: "127.0.0.1".into(),
+			port: DEFAULT_WS_PORT,
+			origins: None,
+			hosts: Some(Vec::new()),
+		}
 	}
+}
+
+pub fn new_servers(http: HttpConfiguration, ws: WsConfiguration, ipc: IpcConfiguration, deps: Dependencies) -> Result<Servers, String> {
+	let cookie = match http.auth && (http.enabled || ws.enabled) {
+		true => Some(CookieFile::create(&http.cookie_path)
+			.map_err(|err| format!("Failed to create JSON-RPC cookie file {}: {}", http.cookie_path.display(), err))?),
+		false => None,
+	};
+
+	let http_server = match http.enabled {
+		true => {
+			let url = format!("{}:{}", http.interface, http.port);
+			let addr = url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
+			let auth = rpc_auth(&http, cookie.as_ref())?;
+			Some(setup_http_rpc_server(&addr, http.cors.clone(), http.hosts.clone(), &http.apis, &http.whitelist, auth, deps.clone())?)
+		},
+		false => None,
+	};
+
+	let ws_server = match ws.enabled {
+		true => {
+			let url = format!("{}:{}", ws.interface, ws.port);
+			let addr = url.parse().map_err(|_| format!("Invalid WebSocket listen host/port given: {}", url))?;
+			// user name is not known to the WebSocket requests => users with the APIs whitelist are only allowed to use HTTP
+			let auth = rpc_auth(&http, cookie.as_ref())?.map(|mut auth| {
+				for user in http.whitelist.keys() {
+					auth.remove_user(user);
+				}
+				auth
+			});
+			let handler = rpc_apis::setup_rpc(&http.apis, &HashMap::new(), deps.clone());
+			Some(start_ws(&addr, ws.origins, ws.hosts, handler, auth)
+				.map_err(|err| format!("Failed to start WebSocket server at {}: {:?}", url, err))?)
+		},
+		false => None,
+	};
+
+	let ipc_server = match ipc.enabled {
+		true => {
+			let handler = rpc_apis::setup_rpc(&http.apis, &HashMap::new(), deps);
+			Some(start_ipc(&ipc.path, handler)
+				.map_err(|err| format!("Failed to start IPC server at {}: {}", ipc.path, err))?)
+		},
+		false => None,
+	};
 
-	let url = format!("{}:{}", conf.interface, conf.port);
-	let addr = url.parse().map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
-	Ok(Some(setup_http_rpc_server(&addr, conf.cors, conf.hosts, conf.apis, deps)?))
+	Ok(Servers {
+		_http: http_server,
+		_ws: ws_server,
+		_ipc: ipc_server,
+		_cookie: cookie,
+	})
+}
+
+/// Users, allowed to access the servers. None if authentication is disabled.
+fn rpc_auth(conf: &HttpConfiguration, cookie: Option<&CookieFile>) -> Result<Option<RpcAuth>, String> {
+	let cookie = match cookie {
+		Some(cookie) => cookie,
+		None => return Ok(None),
+	};
+
+	let mut auth = RpcAuth::default();
+	for user in &conf.auth_users {
+		auth.add_user(user)?;
+	}
+	auth.add_password(COOKIE_USER, cookie.password());
+	Ok(Some(auth))
 }
 
 pub fn setup_http_rpc_server(
 	url: &SocketAddr,
 	cors_domains: Option<Vec<String>>,
 	allowed_hosts: Option<Vec<String>>,
-	apis: ApiSet,
+	apis: &ApiSet,
+	whitelist: &HashMap<String, ApiSet>,
+	auth: Option<RpcAuth>,
 	deps: Dependencies,
 ) -> Result<Server, String> {
-	let server = setup_rpc_server(apis, deps);
-	let start_result = start_http(url, cors_domains, allowed_hosts, server);
+	let server = setup_rpc_server(apis, whitelist, deps);
+	let start_result = start_http(url, cors_domains, allowed_hosts, server, auth);
 	match start_result {
 		Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
 			Err(format!("RPC address {} is already in use, make sure that another instance of a Bitcoin node is not running or change the address using the --jsonrpc-port and --jsonrpc-interface options.", url))
@@ -67,6 +186,6 @@ pub fn setup_http_rpc_server(
 	}
 }
 
-fn setup_rpc_server(apis: ApiSet, deps: Dependencies) -> MetaIoHandler<()> {
-	rpc_apis::setup_rpc(MetaIoHandler::with_compatibility(Compatibility::Both), apis, deps)
+fn setup_rpc_server(apis: &ApiSet, whitelist: &HashMap<String, ApiSet>, deps: Dependencies) -> MetaIoHandler<Metadata, MethodsWhitelist> {
+	rpc_apis::setup_rpc(apis, whitelist, deps)
 }
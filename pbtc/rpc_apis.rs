@@ -1,7 +1,8 @@
 use std::str::FromStr;
 use std::collections::HashSet;
+use std::collections::HashMap;
 use rpc::Dependencies;
-use ethcore_rpc::MetaIoHandler;
+use ethcore_rpc::{MetaIoHandler, Compatibility, Metadata, MethodsWhitelist, RemoteProcedure};
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Api {
@@ -13,6 +14,12 @@ pub enum Api {
 	BlockChain,
 	/// Network
 	Network,
+	/// Node control
+	Control,
+	/// Best chain and memory pool subscriptions
+	Events,
+	/// Utility methods
+	Util,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -22,7 +29,7 @@ pub enum ApiSet {
 
 impl Default for ApiSet {
 	fn default() -> Self {
-		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network].into_iter().collect())
+		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network, Api::Control, Api::Events, Api::Util].into_iter().collect())
 	}
 }
 
@@ -35,11 +42,28 @@ impl FromStr for Api {
 			"miner" => Ok(Api::Miner),
 			"blockchain" => Ok(Api::BlockChain),
 			"network" => Ok(Api::Network),
+			"control" => Ok(Api::Control),
+			"events" => Ok(Api::Events),
+			"util" => Ok(Api::Util),
 			api => Err(format!("Unknown api: {}", api)),
 		}
 	}
 }
 
+impl FromStr for ApiSet {
+	type Err = String;
+
+	/// Parses comma-delimited list of API names
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.split(',')
+			.map(str::trim)
+			.filter(|api| !api.is_empty())
+			.map(str::parse)
+			.collect::<Result<_, _>>()
+			.map(ApiSet::List)
+	}
+}
+
 impl ApiSet {
 	pub fn list_apis(&self) -> HashSet<Api> {
 		match *self {
@@ -48,16 +72,36 @@ impl ApiSet {
 	}
 }
 
-pub fn setup_rpc(mut handler: MetaIoHandler<()>, apis: ApiSet, deps: Dependencies) -> MetaIoHandler<()> {
+/// Creates handler of the RPC methods. Users of the whitelist may only call methods of their APIs.
+pub fn setup_rpc(apis: &ApiSet, whitelist: &HashMap<String, ApiSet>, deps: Dependencies) -> MetaIoHandler<Metadata, MethodsWhitelist> {
 	use ethcore_rpc::v1::*;
 
+	let mut methods: Vec<(Api, HashMap<String, RemoteProcedure<Metadata>>)> = Vec::new();
 	for api in apis.list_apis() {
-		match api {
-			Api::Raw => handler.extend_with(RawClient::new(RawClientCore::new(deps.network,deps.local_sync_node.clone(), deps.storage.clone())).to_delegate()),
-			Api::Miner => handler.extend_with(MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone())).to_delegate()),
-			Api::BlockChain => handler.extend_with(BlockChainClient::new(BlockChainClientCore::new(deps.network, deps.storage.clone())).to_delegate()),
-			Api::Network => handler.extend_with(NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate()),
-		}
+		let api_methods = match api {
+			Api::Raw => RawClient::new(RawClientCore::new(deps.consensus.clone(), deps.local_sync_node.clone(), deps.storage.clone())).to_delegate().into(),
+			Api::Miner => MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone())).to_delegate().into(),
+			Api::BlockChain => BlockChainClient::new(BlockChainClientCore::new(deps.network, deps.storage.clone()).with_local_sync_node(deps.local_sync_node.clone())).to_delegate().into(),
+			Api::Network => NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate().into(),
+			Api::Control => ControlClient::new(ControlClientCore::new(deps.log_handle.clone())).to_delegate().into(),
+			Api::Events => EventsClient::new(&deps.events).to_delegate().into(),
+			Api::Util => UtilClient::new(UtilClientCore::new(deps.network)).to_delegate().into(),
+		};
+		methods.push((api, api_methods));
+	}
+
+	let mut methods_whitelist = MethodsWhitelist::default();
+	for (user, user_apis) in whitelist {
+		let user_apis = user_apis.list_apis();
+		let user_methods = methods.iter()
+			.filter(|&&(ref api, _)| user_apis.contains(api))
+			.flat_map(|&(_, ref api_methods)| api_methods.keys().cloned());
+		methods_whitelist.allow(user, user_methods);
+	}
+
+	let mut handler = MetaIoHandler::new(Compatibility::Both, methods_whitelist);
+	for (_, api_methods) in methods {
+		handler.extend_with(api_methods);
 	}
 
 	handler
@@ -1,17 +1,45 @@
 use std::sync::Arc;
+use std::cmp::Ordering;
 use std::path::PathBuf;
 use std::fs::create_dir_all;
-use app_dirs::{app_dir, AppDataType};
-use {storage, APP_INFO};
+use app_dirs::{app_dir, app_root, AppDataType};
+use {storage, APP_INFO, LOG_INFO};
+use ethcore_rpc::COOKIE_FILE_NAME;
 use db;
-use config::Config;
+use logs;
+use config::{Config, LogFormat};
 
-pub fn open_db(data_dir: &Option<String>, db_cache: usize) -> storage::SharedStore {
+const IPC_FILE_NAME: &'static str = "pbtc.ipc";
+
+/// Key-value storage engine used by the blockchain database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatabaseBackend {
+	RocksDb,
+	Sled,
+}
+
+impl DatabaseBackend {
+	/// Name of the data directory subfolder. Backends have incompatible formats, so they never share it.
+	fn sub_dir(&self) -> &'static str {
+		match *self {
+			DatabaseBackend::RocksDb => "db",
+			DatabaseBackend::Sled => "db-sled",
+		}
+	}
+}
+
+pub fn open_db(data_dir: &Option<String>, db_backend: DatabaseBackend, db_cache: usize, block_filter_index: bool) -> storage::SharedStore {
+	let sub_dir = db_backend.sub_dir();
 	let db_path = match *data_dir {
-		Some(ref data_dir) => custom_path(&data_dir, "db"),
-		None => app_dir(AppDataType::UserData, &APP_INFO, "db").expect("Failed to get app dir"),
+		Some(ref data_dir) => custom_path(&data_dir, sub_dir),
+		None => app_dir(AppDataType::UserData, &APP_INFO, sub_dir).expect("Failed to get app dir"),
 	};
-	Arc::new(db::BlockChainDatabase::open_at_path(db_path, db_cache).expect("Failed to open database"))
+	match db_backend {
+		DatabaseBackend::RocksDb => Arc::new(db::BlockChainDatabase::open_at_path(db_path, db_cache).expect("Failed to open database")
+			.with_block_filter_index(block_filter_index).expect("Failed to build block filter index")),
+		DatabaseBackend::Sled => Arc::new(db::BlockChainDatabase::open_sled_at_path(db_path, db_cache).expect("Failed to open database")
+			.with_block_filter_index(block_filter_index).expect("Failed to build block filter index")),
+	}
 }
 
 pub fn node_table_path(cfg: &Config) -> PathBuf {
@@ -23,7 +51,68 @@ pub fn node_table_path(cfg: &Config) -> PathBuf {
 	node_table
 }
 
+pub fn logs_path(cfg: &Config) -> PathBuf {
+	match cfg.data_dir {
+		Some(ref data_dir) => custom_path(&data_dir, "logs"),
+		None => app_dir(AppDataType::UserData, &APP_INFO, "logs").expect("Failed to get app dir"),
+	}
+}
+
+pub fn cookie_path(data_dir: &Option<String>) -> PathBuf {
+	match *data_dir {
+		Some(ref data_dir) => PathBuf::from(data_dir).join(COOKIE_FILE_NAME),
+		None => app_root(AppDataType::UserData, &APP_INFO).expect("Failed to get app dir").join(COOKIE_FILE_NAME),
+	}
+}
+
+/// Default path of the IPC socket. Named pipe is used on Windows.
+pub fn ipc_path(data_dir: &Option<String>) -> String {
+	if cfg!(windows) {
+		return format!(r"\\.\pipe\{}", IPC_FILE_NAME);
+	}
+
+	let path = match *data_dir {
+		Some(ref data_dir) => PathBuf::from(data_dir).join(IPC_FILE_NAME),
+		None => app_root(AppDataType::UserData, &APP_INFO).expect("Failed to get app dir").join(IPC_FILE_NAME),
+	};
+	path.to_string_lossy().into_owned()
+}
+
+pub fn init_logs(cfg: &Config) -> Result<logs::LogHandle, String> {
+	// in quiet mode only filters from RUST_LOG are used
+	let filters = match cfg.quiet {
+		true => "",
+		false => LOG_INFO,
+	};
+
+	let mut builder = logs::Builder::new(filters).max_file_size(cfg.log_file_size);
+	builder = match cfg.log_format {
+		LogFormat::Json => builder.console(logs::JsonLogFormatter).file_formatter(logs::JsonLogFormatter),
+		LogFormat::Text if cfg!(windows) => builder.console(logs::DateLogFormatter),
+		LogFormat::Text => builder.console(logs::DateAndColorLogFormatter),
+	};
+
+	if cfg.log_file || !cfg.log_modules.is_empty() {
+		let logs_path = logs_path(cfg);
+		if cfg.log_file {
+			builder = builder.file(logs_path.join("pbtc.log"));
+		}
+		for module in &cfg.log_modules {
+			builder = builder.module_file(module, logs_path.join(format!("{}.log", module.replace("::", "-"))));
+		}
+	}
+
+	builder.init()
+}
+
 pub fn init_db(cfg: &Config) -> Result<(), String> {
+	let version = cfg.db.format_version().map_err(|e| format!("{}", e))?;
+	match version.cmp(&db::DATABASE_VERSION) {
+		Ordering::Less => return Err(format!("Database format version {} is outdated, run `pbtc migrate` to upgrade it to version {}", version, db::DATABASE_VERSION)),
+		Ordering::Greater => return Err(format!("Database format version {} is not supported by this version of pbtc, expected version {}", version, db::DATABASE_VERSION)),
+		Ordering::Equal => (),
+	}
+
 	// insert genesis block if db is empty
 	let genesis_block = cfg.network.genesis_block();
 	match cfg.db.block_hash(0) {
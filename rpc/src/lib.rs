@@ -1,5 +1,8 @@
+#[macro_use]
 extern crate log;
+extern crate rand;
 extern crate rustc_hex as hex;
+extern crate base64;
 extern crate serde;
 extern crate serde_json;
 #[macro_use]
@@ -8,12 +11,17 @@ extern crate jsonrpc_core;
 #[macro_use]
 extern crate jsonrpc_macros;
 extern crate jsonrpc_http_server;
+extern crate jsonrpc_ws_server;
+extern crate jsonrpc_ipc_server;
+extern crate jsonrpc_pubsub;
+extern crate parking_lot;
 extern crate tokio_core;
 extern crate sync;
 extern crate chain;
 extern crate serialization as ser;
 extern crate primitives;
 extern crate p2p;
+extern crate message;
 extern crate network;
 extern crate storage;
 extern crate db;
@@ -21,12 +29,18 @@ extern crate miner;
 extern crate verification;
 extern crate script as global_script;
 extern crate keys;
+extern crate logs;
+extern crate bitcrypto;
 
 pub mod v1;
 pub mod rpc_server;
+pub mod rpc_auth;
 
-pub use jsonrpc_core::{MetaIoHandler, Compatibility, Error};
+pub use jsonrpc_core::{MetaIoHandler, Compatibility, Error, RemoteProcedure};
 pub use jsonrpc_http_server::tokio_core::reactor::{Remote};
 
 pub use jsonrpc_http_server::Server;
-pub use rpc_server::start_http;
+pub use jsonrpc_ws_server::Server as WsServer;
+pub use jsonrpc_ipc_server::Server as IpcServer;
+pub use rpc_server::{start_http, start_ws, start_ipc};
+pub use rpc_auth::{Metadata, RpcAuth, CookieFile, MethodsWhitelist, COOKIE_USER, COOKIE_FILE_NAME};
@@ -0,0 +1,381 @@
+use std::{fs, io};
+use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use rand::Rng;
+use rand::os::OsRng;
+use bitcrypto::hmac_sha256;
+use primitives::hash::H256;
+use jsonrpc_core::{self, Call, Failure, FutureResponse, Middleware, Output, Request, Response};
+use jsonrpc_core::futures::{future, Future};
+use jsonrpc_http_server::{self, RequestMiddleware, RequestMiddlewareAction, MetaExtractor};
+use jsonrpc_http_server::hyper::{header, server, Method, StatusCode};
+use jsonrpc_pubsub::{PubSubMetadata, Session};
+use jsonrpc_ws_server::ws;
+use v1::helpers::errors;
+
+/// Name of the user, authenticated with the cookie file
+pub const COOKIE_USER: &'static str = "__cookie__";
+/// Name of the cookie file in the data directory
+pub const COOKIE_FILE_NAME: &'static str = ".cookie";
+
+/// RPC request metadata
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+	/// Name of the authenticated user. None if authentication is disabled.
+	pub user: Option<String>,
+	/// Subscriptions session. None if the transport does not support subscriptions.
+	pub session: Option<Arc<Session>>,
+}
+
+impl jsonrpc_core::Metadata for Metadata {}
+
+impl PubSubMetadata for Metadata {
+	fn session(&self) -> Option<Arc<Session>> {
+		self.session.clone()
+	}
+}
+
+/// Credentials of the RPC users. Passwords are stored as `HMAC-SHA256(salt, password)`.
+#[derive(Debug, Default)]
+pub struct RpcAuth {
+	users: HashMap<String, (String, H256)>,
+}
+
+impl RpcAuth {
+	/// Adds user from the `USER:SALT$HASH` entry, where HASH is hex-encoded `HMAC-SHA256(SALT, PASSWORD)`
+	pub fn add_user(&mut self, entry: &str) -> Result<(), String> {
+		let mut parts = entry.splitn(2, ':');
+		let user = parts.next().expect("splitn always yields at least one item; qed");
+		let mut parts = parts.next().ok_or_else(|| format!("Invalid auth entry {}, expected USER:SALT$HASH", entry))?.splitn(2, '$');
+		let salt = parts.next().expect("splitn always yields at least one item; qed");
+		let hash = parts.next().and_then(|hash| hash.parse().ok())
+			.ok_or_else(|| format!("Invalid auth entry {}, expected USER:SALT$HASH", entry))?;
+		if user.is_empty() || user == COOKIE_USER {
+			return Err(format!("Invalid auth entry {}, invalid user name", entry));
+		}
+
+		self.users.insert(user.to_owned(), (salt.to_owned(), hash));
+		Ok(())
+	}
+
+	/// Adds user with given password
+	pub fn add_password(&mut self, user: &str, password: &str) {
+		let salt = random_hash().map(|salt| salt.to_string()).unwrap_or_default();
+		let hash = hmac_sha256(salt.as_bytes(), password.as_bytes());
+		self.users.insert(user.to_owned(), (salt, hash));
+	}
+
+	/// Removes user
+	pub fn remove_user(&mut self, user: &str) {
+		self.users.remove(user);
+	}
+
+	/// Returns true if password of the user is correct
+	pub fn authenticate(&self, user: &str, password: &str) -> bool {
+		match self.users.get(user) {
+			Some(&(ref salt, ref hash)) => constant_time_eq(&*hmac_sha256(salt.as_bytes(), password.as_bytes()), &**hash),
+			None => false,
+		}
+	}
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn random_hash() -> Result<H256, io::Error> {
+	let mut rng = OsRng::new().map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+	let mut result = H256::default();
+	rng.fill_bytes(&mut *result);
+	Ok(result)
+}
+
+/// Cookie file with random password of the `__cookie__` user. The file is removed, when dropped.
+pub struct CookieFile {
+	path: PathBuf,
+	password: String,
+}
+
+impl CookieFile {
+	/// Generates new password and writes `__cookie__:PASSWORD` to the file
+	pub fn create<P>(path: P) -> Result<Self, io::Error> where P: AsRef<Path> {
+		let password = random_hash()?.to_string();
+		let mut file = open_cookie_file(path.as_ref())?;
+		write!(file, "{}:{}", COOKIE_USER, password)?;
+		Ok(CookieFile {
+			path: path.as_ref().to_owned(),
+			password: password,
+		})
+	}
+
+	pub fn password(&self) -> &str {
+		&self.password
+	}
+}
+
+/// Opens the cookie file for writing, making it readable by the owner only
+#[cfg(unix)]
+fn open_cookie_file(path: &Path) -> Result<fs::File, io::Error> {
+	use std::os::unix::fs::OpenOptionsExt;
+
+	fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)
+}
+
+#[cfg(not(unix))]
+fn open_cookie_file(path: &Path) -> Result<fs::File, io::Error> {
+	fs::OpenOptions::new().write(true).create(true).truncate(true).open(path)
+}
+
+impl Drop for CookieFile {
+	fn drop(&mut self) {
+		let _ = fs::remove_file(&self.path);
+	}
+}
+
+fn basic_credentials(request: &server::Request) -> Option<(String, String)> {
+	request.headers().get::<header::Authorization<header::Basic>>()
+		.map(|auth| (auth.username.clone(), auth.password.clone().unwrap_or_default()))
+}
+
+/// Rejects HTTP requests without valid credentials with `401 Unauthorized` response
+pub struct AuthMiddleware {
+	auth: RpcAuth,
+}
+
+impl AuthMiddleware {
+	pub fn new(auth: RpcAuth) -> Self {
+		AuthMiddleware {
+			auth: auth,
+		}
+	}
+}
+
+impl RequestMiddleware for AuthMiddleware {
+	fn on_request(&self, request: server::Request) -> RequestMiddlewareAction {
+		// CORS preflight requests are never authenticated
+		if *request.method() == Method::Options {
+			return request.into();
+		}
+
+		match basic_credentials(&request) {
+			Some((ref user, ref password)) if self.auth.authenticate(user, password) => request.into(),
+			Some((ref user, _)) => {
+				warn!(target: "rpc", "Rejected JSON-RPC request with invalid password of user {}", user);
+				unauthorized().into()
+			},
+			None => unauthorized().into(),
+		}
+	}
+}
+
+fn unauthorized() -> server::Response {
+	let mut response: server::Response = jsonrpc_http_server::Response {
+		code: StatusCode::Unauthorized,
+		content_type: header::ContentType::plaintext(),
+		content: "Valid user name and password are required.\n".to_owned(),
+	}.into();
+	response.headers_mut().set_raw("WWW-Authenticate", "Basic realm=\"jsonrpc\"");
+	response
+}
+
+/// Fills metadata with the name of the authenticated user
+pub struct AuthMetaExtractor;
+
+impl MetaExtractor<Metadata> for AuthMetaExtractor {
+	fn read_metadata(&self, request: &server::Request) -> Metadata {
+		Metadata {
+			user: basic_credentials(request).map(|(user, _)| user),
+			session: None,
+		}
+	}
+}
+
+/// Rejects WebSocket handshakes without valid HTTP Basic credentials with `401 Unauthorized` response.
+/// User name is not available to the WebSocket requests, so users with the methods whitelist
+/// should not be added here.
+pub struct WsAuthMiddleware {
+	auth: RpcAuth,
+}
+
+impl WsAuthMiddleware {
+	pub fn new(auth: RpcAuth) -> Self {
+		WsAuthMiddleware {
+			auth: auth,
+		}
+	}
+
+	pub fn process(&self, request: &ws::Request) -> Option<ws::Response> {
+		let credentials = request.header("authorization")
+			.and_then(|value| ::std::str::from_utf8(value).ok())
+			.and_then(|value| match value.starts_with("Basic ") {
+				true => value["Basic ".len()..].parse::<header::Basic>().ok(),
+				false => None,
+			});
+
+		match credentials {
+			Some(ref credentials) if self.auth.authenticate(&credentials.username, credentials.password.as_ref().map_or("", String::as_str)) => None,
+			Some(ref credentials) => {
+				warn!(target: "rpc", "Rejected WebSocket connection with invalid password of user {}", credentials.username);
+				Some(ws_unauthorized())
+			},
+			None => Some(ws_unauthorized()),
+		}
+	}
+}
+
+fn ws_unauthorized() -> ws::Response {
+	let mut response = ws::Response::new(401, "Unauthorized", b"Valid user name and password are required.\n".to_vec());
+	response.headers_mut().push(("WWW-Authenticate".to_owned(), b"Basic realm=\"jsonrpc\"".to_vec()));
+	response
+}
+
+/// Restricts methods, available to the users. Users without the whitelist may call all methods.
+#[derive(Debug, Default)]
+pub struct MethodsWhitelist {
+	users: HashMap<String, HashSet<String>>,
+}
+
+impl MethodsWhitelist {
+	/// Allows user to call given methods only
+	pub fn allow<I>(&mut self, user: &str, methods: I) where I: IntoIterator<Item=String> {
+		self.users.entry(user.to_owned()).or_insert_with(HashSet::new).extend(methods);
+	}
+
+	fn is_denied(&self, user: Option<&str>, call: &Call) -> bool {
+		let methods = match user.and_then(|user| self.users.get(user)) {
+			Some(methods) => methods,
+			None => return false,
+		};
+
+		match *call {
+			Call::MethodCall(ref call) => !methods.contains(&call.method),
+			Call::Notification(ref notification) => !methods.contains(&notification.method),
+			Call::Invalid(_) => false,
+		}
+	}
+}
+
+fn denied_output(call: &Call) -> Option<Output> {
+	match *call {
+		Call::MethodCall(ref call) => Some(Output::Failure(Failure {
+			jsonrpc: call.jsonrpc,
+			error: errors::method_not_allowed(&call.method),
+			id: call.id.clone(),
+		})),
+		Call::Notification(_) | Call::Invalid(_) => None,
+	}
+}
+
+impl Middleware<Metadata> for MethodsWhitelist {
+	type Future = FutureResponse;
+
+	fn on_request<F, X>(&self, request: Request, meta: Metadata, next: F) -> Self::Future where
+		F: FnOnce(Request, Metadata) -> X + Send,
+		X: Future<Item=Option<Response>, Error=()> + Send + 'static,
+	{
+		let (denied, allowed): (Vec<_>, Vec<_>) = {
+			let user = meta.user.as_ref().map(String::as_str);
+			match request {
+				Request::Single(ref call) if !self.is_denied(user, call) => return Box::new(next(request, meta)),
+				Request::Single(call) => return Box::new(future::ok(denied_output(&call).map(Response::Single))),
+				Request::Batch(calls) => calls.into_iter().partition(|call| self.is_denied(user, call)),
+			}
+		};
+
+		// denied calls of the batch are answered with errors, other calls are processed as usual
+		let denied = denied.iter().filter_map(denied_output).collect::<Vec<_>>();
+		let response = match allowed.is_empty() {
+			true => Box::new(future::ok(None)) as FutureResponse,
+			false => Box::new(next(Request::Batch(allowed), meta)),
+		};
+
+		Box::new(response.map(move |response| {
+			let mut outputs = match response {
+				Some(Response::Batch(outputs)) => outputs,
+				Some(Response::Single(output)) => vec![output],
+				None => Vec::new(),
+			};
+			outputs.extend(denied);
+			match outputs.is_empty() {
+				true => None,
+				false => Some(Response::Batch(outputs)),
+			}
+		}))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate tempdir;
+
+	use std::fs;
+	use jsonrpc_core::{MetaIoHandler, Compatibility, Value};
+	use bitcrypto::hmac_sha256;
+	use self::tempdir::TempDir;
+	use super::{RpcAuth, CookieFile, Metadata, MethodsWhitelist, COOKIE_FILE_NAME};
+
+	#[test]
+	fn rpc_auth_users() {
+		let hash = hmac_sha256(b"salt", b"password");
+		let mut auth = RpcAuth::default();
+		auth.add_user(&format!("alice:salt${}", hash)).unwrap();
+		auth.add_password("bob", "secret");
+		assert!(auth.authenticate("alice", "password"));
+		assert!(!auth.authenticate("alice", "secret"));
+		assert!(auth.authenticate("bob", "secret"));
+		assert!(!auth.authenticate("carol", "password"));
+		auth.remove_user("bob");
+		assert!(!auth.authenticate("bob", "secret"));
+
+		assert!(auth.add_user("alice").is_err());
+		assert!(auth.add_user("alice:salt").is_err());
+		assert!(auth.add_user("alice:salt$00").is_err());
+		assert!(auth.add_user(&format!("__cookie__:salt${}", hash)).is_err());
+	}
+
+	#[test]
+	fn cookie_file_is_removed_on_drop() {
+		let dir = TempDir::new("rpc").unwrap();
+		let path = dir.path().join(COOKIE_FILE_NAME);
+		let cookie = CookieFile::create(&path).unwrap();
+		assert_eq!(fs::read_to_string(&path).unwrap(), format!("__cookie__:{}", cookie.password()));
+		assert_eq!(cookie.password().len(), 64);
+		drop(cookie);
+		assert!(!path.exists());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn cookie_file_is_readable_by_owner_only() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let dir = TempDir::new("rpc").unwrap();
+		let path = dir.path().join(COOKIE_FILE_NAME);
+		let _cookie = CookieFile::create(&path).unwrap();
+		assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+	}
+
+	#[test]
+	fn methods_whitelist() {
+		let mut whitelist = MethodsWhitelist::default();
+		whitelist.allow("alice", vec!["getblockcount".to_owned()]);
+		let mut handler = MetaIoHandler::new(Compatibility::Both, whitelist);
+		handler.add_method("getblockcount", |_| Ok(Value::from(1)));
+		handler.add_method("stop", |_| Ok(Value::Null));
+
+		let alice = Metadata { user: Some("alice".into()), session: None };
+		let bob = Metadata { user: Some("bob".into()), session: None };
+
+		let request = r#"{"jsonrpc": "2.0", "method": "getblockcount", "params": [], "id": 1}"#;
+		assert_eq!(handler.handle_request_sync(request, alice.clone()), Some(r#"{"jsonrpc":"2.0","result":1,"id":1}"#.to_owned()));
+
+		let request = r#"{"jsonrpc": "2.0", "method": "stop", "params": [], "id": 1}"#;
+		assert_eq!(handler.handle_request_sync(request, alice.clone()), Some(r#"{"jsonrpc":"2.0","error":{"code":-32010,"message":"Method is not allowed","data":"stop"},"id":1}"#.to_owned()));
+		assert_eq!(handler.handle_request_sync(request, bob), Some(r#"{"jsonrpc":"2.0","result":null,"id":1}"#.to_owned()));
+
+		let request = r#"[{"jsonrpc": "2.0", "method": "stop", "params": [], "id": 1}, {"jsonrpc": "2.0", "method": "getblockcount", "params": [], "id": 2}]"#;
+		assert_eq!(handler.handle_request_sync(request, alice), Some(r#"[{"jsonrpc":"2.0","result":1,"id":2},{"jsonrpc":"2.0","error":{"code":-32010,"message":"Method is not allowed","data":"stop"},"id":1}]"#.to_owned()));
+	}
+}
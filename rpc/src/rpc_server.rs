@@ -1,15 +1,22 @@
 // TODO: panic handler
 use std::io;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use jsonrpc_core;
 use jsonrpc_http_server::{self, ServerBuilder, Server, Host};
+use jsonrpc_ws_server::{self, ws};
+use jsonrpc_ipc_server;
+use jsonrpc_pubsub::Session;
+use rpc_auth::{Metadata, RpcAuth, AuthMiddleware, AuthMetaExtractor, WsAuthMiddleware};
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
-pub fn start_http<M: jsonrpc_core::Metadata>(
+/// If `auth` is given, requests without valid HTTP Basic credentials are rejected.
+pub fn start_http<S: jsonrpc_core::Middleware<Metadata>>(
 	addr: &SocketAddr,
 	cors_domains: Option<Vec<String>>,
 	allowed_hosts: Option<Vec<String>>,
-	handler: jsonrpc_core::MetaIoHandler<M>,
+	handler: jsonrpc_core::MetaIoHandler<Metadata, S>,
+	auth: Option<RpcAuth>,
 	) -> Result<Server, io::Error> {
 
 	let cors_domains = cors_domains.map(|domains| {
@@ -22,8 +29,107 @@ pub fn start_http<M: jsonrpc_core::Metadata>(
 			.collect()
 	});
 
-	ServerBuilder::new(handler)
+	let builder = ServerBuilder::new(handler)
 		.cors(cors_domains.into())
-		.allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into())
-		.start_http(addr)
+		.allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(Host::from).collect()).into());
+
+	let builder = match auth {
+		Some(auth) => builder
+			.request_middleware(AuthMiddleware::new(auth))
+			.meta_extractor(AuthMetaExtractor),
+		None => builder,
+	};
+
+	builder.start_http(addr)
+}
+
+/// Start WebSocket server asynchronously and returns result with `Server` handle on success or an error.
+/// If `auth` is given, connections without valid HTTP Basic credentials are rejected.
+pub fn start_ws<S: jsonrpc_core::Middleware<Metadata>>(
+	addr: &SocketAddr,
+	allowed_origins: Option<Vec<String>>,
+	allowed_hosts: Option<Vec<String>>,
+	handler: jsonrpc_core::MetaIoHandler<Metadata, S>,
+	auth: Option<RpcAuth>,
+	) -> Result<jsonrpc_ws_server::Server, jsonrpc_ws_server::Error> {
+
+	let builder = jsonrpc_ws_server::ServerBuilder::new(handler)
+		.session_meta_extractor(|context: &jsonrpc_ws_server::RequestContext| Metadata {
+			user: None,
+			session: Some(Arc::new(Session::new(context.sender()))),
+		})
+		.allowed_origins(allowed_origins.map(|origins| origins.into_iter().map(jsonrpc_ws_server::Origin::from).collect()).into())
+		.allowed_hosts(allowed_hosts.map(|hosts| hosts.into_iter().map(jsonrpc_ws_server::Host::from).collect()).into());
+
+	let builder = match auth {
+		Some(auth) => {
+			let middleware = WsAuthMiddleware::new(auth);
+			builder.request_middleware(move |request: &ws::Request| middleware.process(request))
+		},
+		None => builder,
+	};
+
+	builder.start(addr)
+}
+
+/// Start IPC server on the Unix socket (named pipe on Windows) asynchronously and returns result with
+/// `Server` handle on success or an error. Access to the socket is only restricted by the file permissions.
+pub fn start_ipc<S: jsonrpc_core::Middleware<Metadata>>(
+	path: &str,
+	handler: jsonrpc_core::MetaIoHandler<Metadata, S>,
+	) -> Result<jsonrpc_ipc_server::Server, io::Error> {
+
+	jsonrpc_ipc_server::ServerBuilder::with_meta_extractor(handler, |context: &jsonrpc_ipc_server::RequestContext| Metadata {
+		user: None,
+		session: Some(Arc::new(Session::new(context.sender.clone()))),
+	}).start(path)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+	use std::net::TcpStream;
+	use jsonrpc_core::{MetaIoHandler, Compatibility, Value};
+	use rpc_auth::{RpcAuth, MethodsWhitelist};
+	use super::start_http;
+
+	fn http_post(server: &::jsonrpc_http_server::Server, credentials: &str, body: &str) -> String {
+		let mut stream = TcpStream::connect(server.address()).unwrap();
+		write!(stream, "POST / HTTP/1.1\r\nHost: {}\r\nAuthorization: Basic {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			server.address(), credentials, body.len(), body).unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	}
+
+	#[test]
+	fn http_server_handles_batch_requests() {
+		let mut whitelist = MethodsWhitelist::default();
+		whitelist.allow("bob", vec!["getblockcount".to_owned()]);
+		let mut handler = MetaIoHandler::new(Compatibility::Both, whitelist);
+		handler.add_method("getblockcount", |_| Ok(Value::from(1)));
+		handler.add_method("getbestblockhash", |_| Ok(Value::from("00")));
+		let mut auth = RpcAuth::default();
+		auth.add_password("alice", "secret");
+		auth.add_password("bob", "secret");
+		let server = start_http(&"127.0.0.1:0".parse().unwrap(), None, None, handler, Some(auth)).unwrap();
+
+		let request = r#"[{"jsonrpc": "2.0", "method": "getblockcount", "params": [], "id": 1}, {"jsonrpc": "2.0", "method": "getbestblockhash", "params": [], "id": 2}]"#;
+
+		// alice:secret
+		let response = http_post(&server, "YWxpY2U6c2VjcmV0", request);
+		assert!(response.starts_with("HTTP/1.1 200"));
+		assert!(response.contains(r#"[{"jsonrpc":"2.0","result":1,"id":1},{"jsonrpc":"2.0","result":"00","id":2}]"#));
+
+		// bob:secret
+		let response = http_post(&server, "Ym9iOnNlY3JldA==", request);
+		assert!(response.starts_with("HTTP/1.1 200"));
+		assert!(response.contains(r#"[{"jsonrpc":"2.0","result":1,"id":1},{"jsonrpc":"2.0","error":{"code":-32010,"message":"Method is not allowed","data":"getbestblockhash"},"id":2}]"#));
+
+		// alice:wrong
+		let response = http_post(&server, "YWxpY2U6d3Jvbmc=", request);
+		assert!(response.starts_with("HTTP/1.1 401"));
+
+		server.close();
+	}
 }
@@ -3,7 +3,10 @@
 mod codes {
 	// NOTE [ToDr] Codes from [-32099, -32000]
 	pub const UNKNOWN: i64 = -32000;
+	pub const METHOD_NOT_ALLOWED: i64 = -32010;
 	pub const EXECUTION_ERROR: i64 = -32015;
+	pub const TRANSACTION_REJECTED: i64 = -32094;
+	pub const BLOCK_FILTER_NOT_FOUND: i64 = -32095;
 	pub const TRANSACTION_NOT_FOUND: i64 = -32096;
 	pub const TRANSACTION_OUTPUT_NOT_FOUND: i64 = -32097;
 	pub const TRANSACTION_OF_SIDE_BRANCH: i64 = -32098;
@@ -14,6 +17,7 @@ mod codes {
 
 use std::fmt;
 use jsonrpc_core::{Error, ErrorCode, Value};
+use serde_json::Map;
 
 macro_rules! rpc_unimplemented {
 	() => (Err(::v1::helpers::errors::unimplemented(None)))
@@ -35,6 +39,14 @@ pub fn invalid_params<T: fmt::Debug>(param: &str, details: T) -> Error {
 	}
 }
 
+pub fn method_not_allowed(method: &str) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::METHOD_NOT_ALLOWED),
+		message: "Method is not allowed".into(),
+		data: Some(Value::String(method.into())),
+	}
+}
+
 pub fn execution<T: fmt::Debug>(data: T) -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::EXECUTION_ERROR),
@@ -43,6 +55,17 @@ pub fn execution<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn transaction_rejected(code: u8, reason: &str) -> Error {
+	let mut data = Map::new();
+	data.insert("code".into(), code.into());
+	data.insert("reason".into(), reason.into());
+	Error {
+		code: ErrorCode::ServerError(codes::TRANSACTION_REJECTED),
+		message: format!("Transaction is rejected: {}", reason),
+		data: Some(Value::Object(data)),
+	}
+}
+
 pub fn block_not_found<T: fmt::Debug>(data: T) -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::BLOCK_NOT_FOUND),
@@ -59,6 +82,14 @@ pub fn block_at_height_not_found<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn block_filter_not_found<T: fmt::Debug>(data: T) -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::BLOCK_FILTER_NOT_FOUND),
+		message: "Block filter is not found. Is block filter index enabled?".into(),
+		data: Some(Value::String(format!("{:?}", data))),
+	}
+}
+
 pub fn transaction_not_found<T: fmt::Debug>(data: T) -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::TRANSACTION_NOT_FOUND),
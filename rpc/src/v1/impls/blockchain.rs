@@ -1,21 +1,38 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 use v1::traits::BlockChain;
 use v1::types::{GetBlockResponse, VerboseBlock, RawBlock};
 use v1::types::{GetTxOutResponse, TransactionOutputScript};
 use v1::types::GetTxOutSetInfoResponse;
+use v1::types::GetBlockFilterResponse;
+use v1::types::{ScanTxOutSetAction, ScanObject, ScanTxOutSetResponse, ScanTxOutSetResult, ScanTxOutSetUnspent,
+	ScanTxOutSetStatus, DescriptorRange};
 use v1::types::H256;
 use v1::types::U256;
 use keys::{self, Address};
 use v1::helpers::errors::{block_not_found, block_at_height_not_found, transaction_not_found,
-	transaction_output_not_found, transaction_of_side_branch};
+	transaction_output_not_found, transaction_of_side_branch, block_filter_not_found, invalid_params, execution};
+use super::util::check_descriptor_range;
 use jsonrpc_macros::Trailing;
-use jsonrpc_core::Error;
+use jsonrpc_core::{Error, BoxFuture};
+use jsonrpc_core::futures::{future, Future};
+use jsonrpc_core::futures::sync::oneshot;
 use storage;
-use global_script::Script;
+use global_script::{Script, Descriptor};
 use chain::OutPoint;
 use verification;
 use ser::serialize;
 use network::Network;
+use primitives::bytes::Bytes as GlobalBytes;
 use primitives::hash::H256 as GlobalH256;
+use sync;
+
+/// Number of child indices of the ranged descriptor, scanned by scantxoutset if range is not specified
+pub const DEFAULT_SCAN_RANGE: u32 = 1000;
+/// Number of transactions, scanned by scantxoutset while the storage is locked
+const TX_OUT_SET_SCAN_CHUNK: usize = 10_000;
 
 pub struct BlockChainClient<T: BlockChainClientCoreApi> {
 	core: T,
@@ -29,11 +46,136 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock>;
 	fn verbose_block(&self, hash: GlobalH256) -> Option<VerboseBlock>;
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error>;
+	fn block_filter(&self, hash: GlobalH256) -> Option<GetBlockFilterResponse>;
+	fn invalidate_block(&self, hash: GlobalH256) -> Result<(), String>;
+	fn reconsider_block(&self, hash: GlobalH256) -> Result<(), String>;
+	fn precious_block(&self, hash: GlobalH256) -> Result<(), String>;
+	fn scan_tx_out_set(&self, scripts: HashMap<GlobalBytes, String>) -> BoxFuture<ScanTxOutSetResult>;
+	fn abort_tx_out_set_scan(&self) -> bool;
+	fn tx_out_set_scan_progress(&self) -> Option<f64>;
 }
 
 pub struct BlockChainClientCore {
 	network: Network,
 	storage: storage::SharedStore,
+	local_sync_node: Option<sync::LocalNodeRef>,
+	tx_out_set_scan: Arc<TxOutSetScan>,
+}
+
+/// State of the unspent transaction outputs set scan
+#[derive(Default)]
+struct TxOutSetScan {
+	/// True while scan is running
+	in_progress: AtomicBool,
+	/// Set to interrupt the running scan
+	abort: AtomicBool,
+	/// Scan progress in percents
+	progress: AtomicUsize,
+}
+
+/// Marks scan as finished when dropped, even if scanning thread has panicked
+struct TxOutSetScanGuard(Arc<TxOutSetScan>);
+
+impl Drop for TxOutSetScanGuard {
+	fn drop(&mut self) {
+		self.0.in_progress.store(false, Ordering::SeqCst);
+	}
+}
+
+/// Walks the unspent outputs set, collecting outputs with given scripts.
+/// Blocks are canonized in between of scanned chunks, so outputs of blocks, connected after the scan has
+/// started, may be partially included in the result. The best block at the start of the scan is returned.
+fn scan_tx_out_set(storage: &storage::SharedStore, scripts: &HashMap<GlobalBytes, String>, scan: &TxOutSetScan) -> Result<ScanTxOutSetResult, Error> {
+	let mut success = true;
+	let mut txouts = 0;
+	let mut unspents = Vec::new();
+	let mut total_amount = 0u64;
+	let mut best_block = None;
+	let mut last_visited = None;
+	loop {
+		let mut visited = 0;
+		let mut next_visited = None;
+		let chunk_best_block = storage.for_each_transaction_meta(last_visited.as_ref(), &mut |hash, meta| {
+			if scan.abort.load(Ordering::SeqCst) {
+				success = false;
+				return false;
+			}
+
+			visited += 1;
+			next_visited = Some(hash.clone());
+
+			// metas are visited in the order of transaction hashes
+			scan.progress.store((hash[0] as usize * 0x100 + hash[1] as usize) * 100 / 0x10000, Ordering::SeqCst);
+
+			if meta.is_fully_spent() {
+				return visited < TX_OUT_SET_SCAN_CHUNK;
+			}
+
+			let transaction = storage.transaction(hash).expect("transaction meta is stored along with transaction; qed");
+			for (index, output) in transaction.raw.outputs.iter().enumerate() {
+				if meta.is_spent(index) != Some(false) {
+					continue;
+				}
+
+				txouts += 1;
+				if let Some(descriptor) = scripts.get(&output.script_pubkey) {
+					total_amount += output.value;
+					unspents.push(ScanTxOutSetUnspent {
+						txid: hash.clone().into(),
+						vout: index as u32,
+						script_pubkey: output.script_pubkey.clone().into(),
+						desc: descriptor.clone(),
+						amount: 0.00000001f64 * (output.value as f64),
+						height: meta.height(),
+					});
+				}
+			}
+
+			visited < TX_OUT_SET_SCAN_CHUNK
+		}).map_err(|err| execution(err))?;
+
+		if best_block.is_none() {
+			best_block = Some(chunk_best_block);
+		}
+		if !success || visited < TX_OUT_SET_SCAN_CHUNK {
+			break;
+		}
+		last_visited = next_visited;
+	}
+
+	let best_block = best_block.expect("at least one chunk is scanned; qed");
+	unspents.sort_by(|a, b| (a.height, &a.txid, a.vout).cmp(&(b.height, &b.txid, b.vout)));
+	Ok(ScanTxOutSetResult {
+		success: success,
+		txouts: txouts,
+		height: best_block.number,
+		bestblock: best_block.hash.into(),
+		unspents: unspents,
+		total_amount: 0.00000001f64 * (total_amount as f64),
+	})
+}
+
+/// Expands scan objects into scripts, mapped to the canonical form of the descriptor they are derived from
+fn scan_scripts(scan_objects: Vec<ScanObject>) -> Result<HashMap<GlobalBytes, String>, Error> {
+	let mut scripts = HashMap::new();
+	for scan_object in scan_objects {
+		let descriptor = Descriptor::parse(&scan_object.descriptor, false)
+			.map_err(|e| invalid_params("scanobjects", e.to_string()))?;
+		let range = match (descriptor.is_range(), scan_object.range) {
+			(true, Some(range)) => range,
+			(true, None) => DescriptorRange { begin: 0, end: DEFAULT_SCAN_RANGE - 1 },
+			(false, _) => DescriptorRange { begin: 0, end: 0 },
+		};
+		check_descriptor_range(&range)?;
+
+		let canonical = format!("{}#{}", descriptor, descriptor.checksum());
+		for index in range.begin..range.end + 1 {
+			let script = descriptor.script_pubkey(index).map_err(|e| execution(e.to_string()))?;
+			scripts.insert(script.to_bytes(), canonical.clone());
+		}
+	}
+
+	Ok(scripts)
 }
 
 impl BlockChainClientCore {
@@ -42,8 +184,20 @@ impl BlockChainClientCore {
 		BlockChainClientCore {
 			network: network,
 			storage: storage,
+			local_sync_node: None,
+			tx_out_set_scan: Default::default(),
 		}
 	}
+
+	/// Enables methods, which are switching the best chain
+	pub fn with_local_sync_node(mut self, local_sync_node: sync::LocalNodeRef) -> Self {
+		self.local_sync_node = Some(local_sync_node);
+		self
+	}
+
+	fn local_sync_node(&self) -> Result<&sync::LocalNodeRef, String> {
+		self.local_sync_node.as_ref().ok_or_else(|| "Synchronization is not running".to_owned())
+	}
 }
 
 impl BlockChainClientCoreApi for BlockChainClientCore {
@@ -165,6 +319,71 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 			coinbase: transaction.raw.is_coinbase(),
 		})
 	}
+
+	fn block_filter(&self, hash: GlobalH256) -> Option<GetBlockFilterResponse> {
+		let filter = self.storage.block_filter(&hash)?;
+		let header = self.storage.block_filter_header(&hash)?;
+		Some(GetBlockFilterResponse {
+			filter: filter.filter.into(),
+			header: header.into(),
+		})
+	}
+
+	fn invalidate_block(&self, hash: GlobalH256) -> Result<(), String> {
+		self.local_sync_node()?.invalidate_block(&hash)
+	}
+
+	fn reconsider_block(&self, hash: GlobalH256) -> Result<(), String> {
+		self.local_sync_node()?.reconsider_block(&hash)
+	}
+
+	fn precious_block(&self, hash: GlobalH256) -> Result<(), String> {
+		self.local_sync_node()?.precious_block(&hash)
+	}
+
+	fn scan_tx_out_set(&self, scripts: HashMap<GlobalBytes, String>) -> BoxFuture<ScanTxOutSetResult> {
+		if self.tx_out_set_scan.in_progress.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+			return Box::new(future::err(execution("Scan already in progress, use action \"abort\" or \"status\"")));
+		}
+
+		self.tx_out_set_scan.abort.store(false, Ordering::SeqCst);
+		self.tx_out_set_scan.progress.store(0, Ordering::SeqCst);
+
+		// scan is running in the separate thread, so that other requests are served meanwhile
+		let (sender, receiver) = oneshot::channel();
+		let guard = TxOutSetScanGuard(self.tx_out_set_scan.clone());
+		let storage = self.storage.clone();
+		let spawned = thread::Builder::new()
+			.name("Scan UTXO set".into())
+			.spawn(move || {
+				let result = scan_tx_out_set(&storage, &scripts, &guard.0);
+				drop(guard);
+				let _ = sender.send(result);
+			});
+
+		match spawned {
+			Ok(_) => Box::new(receiver.map_err(|_| execution("Scan has been interrupted")).and_then(|result| result)),
+			Err(err) => Box::new(future::err(execution(err))),
+		}
+	}
+
+	fn abort_tx_out_set_scan(&self) -> bool {
+		match self.tx_out_set_scan.in_progress.load(Ordering::SeqCst) {
+			true => {
+				self.tx_out_set_scan.abort.store(true, Ordering::SeqCst);
+				true
+			},
+			false => false,
+		}
+	}
+
+	fn tx_out_set_scan_progress(&self) -> Option<f64> {
+		if !self.tx_out_set_scan.in_progress.load(Ordering::SeqCst) {
+			return None;
+		}
+
+		Some(self.tx_out_set_scan.progress.load(Ordering::SeqCst) as f64)
+	}
 }
 
 impl<T> BlockChainClient<T> where T: BlockChainClientCoreApi {
@@ -228,6 +447,64 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
 		rpc_unimplemented!()
 	}
+
+	fn scan_tx_out_set(&self, action: ScanTxOutSetAction, scan_objects: Trailing<Vec<ScanObject>>) -> BoxFuture<ScanTxOutSetResponse> {
+		match action {
+			ScanTxOutSetAction::Start => {
+				let scripts = match scan_objects.into() {
+					Some(scan_objects) => scan_scripts(scan_objects),
+					None => Err(invalid_params("scanobjects", "Scan objects are required for the start action")),
+				};
+
+				match scripts {
+					Ok(scripts) => Box::new(self.core.scan_tx_out_set(scripts)
+						.map(|mut result| {
+							result.bestblock = result.bestblock.reversed();
+							for unspent in &mut result.unspents {
+								unspent.txid = unspent.txid.reversed();
+							}
+							ScanTxOutSetResponse::Result(result)
+						})),
+					Err(err) => Box::new(future::err(err)),
+				}
+			},
+			ScanTxOutSetAction::Abort => Box::new(future::ok(ScanTxOutSetResponse::Abort(self.core.abort_tx_out_set_scan()))),
+			ScanTxOutSetAction::Status => Box::new(future::ok(ScanTxOutSetResponse::Status(self.core.tx_out_set_scan_progress()
+				.map(|progress| ScanTxOutSetStatus {
+					progress: progress,
+				})))),
+		}
+	}
+
+	fn block_filter(&self, hash: H256, filter_type: Trailing<String>) -> Result<GetBlockFilterResponse, Error> {
+		let filter_type = filter_type.unwrap_or_else(|| "basic".to_owned());
+		if filter_type != "basic" {
+			return Err(invalid_params("filtertype", filter_type));
+		}
+
+		let global_hash: GlobalH256 = hash.clone().into();
+		self.core.block_filter(global_hash.reversed())
+			.map(|mut response| {
+				response.header = response.header.reversed();
+				response
+			})
+			.ok_or(block_filter_not_found(hash))
+	}
+
+	fn invalidate_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.invalidate_block(global_hash.reversed()).map_err(execution)
+	}
+
+	fn reconsider_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.reconsider_block(global_hash.reversed()).map_err(execution)
+	}
+
+	fn precious_block(&self, hash: H256) -> Result<(), Error> {
+		let global_hash: GlobalH256 = hash.into();
+		self.core.precious_block(global_hash.reversed()).map_err(execution)
+	}
 }
 
 #[cfg(test)]
@@ -320,6 +597,51 @@ pub mod tests {
 				coinbase: false,
 			})
 		}
+
+		fn block_filter(&self, _hash: GlobalH256) -> Option<GetBlockFilterResponse> {
+			Some(GetBlockFilterResponse {
+				filter: Bytes::from("017fa880"),
+				header: H256::from(0x56),
+			})
+		}
+
+		fn invalidate_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Ok(())
+		}
+
+		fn reconsider_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Ok(())
+		}
+
+		fn precious_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Ok(())
+		}
+
+		fn scan_tx_out_set(&self, _scripts: HashMap<GlobalBytes, String>) -> BoxFuture<ScanTxOutSetResult> {
+			Box::new(future::ok(ScanTxOutSetResult {
+				success: true,
+				txouts: 777,
+				height: 1,
+				bestblock: H256::from(0x56),
+				unspents: vec![ScanTxOutSetUnspent {
+					txid: H256::from(0x57),
+					vout: 0,
+					script_pubkey: Bytes::new(vec![1, 2, 3, 4]),
+					desc: "raw(01020304)#rhemleug".into(),
+					amount: 50.0,
+					height: 0,
+				}],
+				total_amount: 50.0,
+			}))
+		}
+
+		fn abort_tx_out_set_scan(&self) -> bool {
+			true
+		}
+
+		fn tx_out_set_scan_progress(&self) -> Option<f64> {
+			Some(50.0)
+		}
 	}
 
 	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
@@ -350,6 +672,34 @@ pub mod tests {
 		fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
 			Err(block_not_found(prev_out.hash))
 		}
+
+		fn block_filter(&self, _hash: GlobalH256) -> Option<GetBlockFilterResponse> {
+			None
+		}
+
+		fn invalidate_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Err("Block not found".to_owned())
+		}
+
+		fn reconsider_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Err("Block not found".to_owned())
+		}
+
+		fn precious_block(&self, _hash: GlobalH256) -> Result<(), String> {
+			Err("Block not found".to_owned())
+		}
+
+		fn scan_tx_out_set(&self, _scripts: HashMap<GlobalBytes, String>) -> BoxFuture<ScanTxOutSetResult> {
+			Box::new(future::err(execution("Scan already in progress, use action \"abort\" or \"status\"")))
+		}
+
+		fn abort_tx_out_set_scan(&self) -> bool {
+			false
+		}
+
+		fn tx_out_set_scan_progress(&self) -> Option<f64> {
+			None
+		}
 	}
 
 	#[test]
@@ -645,4 +995,202 @@ pub mod tests {
 
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"},"id":1}"#);
 	}
+
+	#[test]
+	fn block_filter_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"filter":"017fa880","header":"0000000000000000000000000000000000000000000000000000000000000056"},"id":1}"#);
+	}
+
+	#[test]
+	fn block_filter_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockfilter",
+				"params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "basic"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32095,"message":"Block filter is not found. Is block filter index enabled?","data":"000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"},"id":1}"#);
+	}
+
+	#[test]
+	fn invalidate_block_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "invalidateblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+	}
+
+	#[test]
+	fn reconsider_block_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "reconsiderblock",
+				"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Block not found\""},"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start", ["raw(01020304)"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bestblock":"0000000000000000000000000000000000000000000000000000000000000056","height":1,"success":true,"total_amount":50.0,"txouts":777,"unspents":[{"amount":50.0,"desc":"raw(01020304)#rhemleug","height":0,"scriptPubKey":"01020304","txid":"0000000000000000000000000000000000000000000000000000000000000057","vout":0}]},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["status"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"progress":50.0},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["abort"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start", ["raw(01020304)"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Scan already in progress, use action \\\"abort\\\" or \\\"status\\\"\""},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["start"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: scanobjects","data":"\"Scan objects are required for the start action\""},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["status"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "scantxoutset",
+				"params": ["abort"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn scan_tx_out_set_contents() {
+		let genesis = test_data::genesis();
+		let genesis_coinbase = genesis.transactions()[0].hash();
+		let genesis_script = genesis.transactions()[0].outputs[0].script_pubkey.clone();
+		let block1 = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(10).script_pubkey_bytes(genesis_script.clone()).build()
+				.build()
+			.transaction()
+				.input().hash(genesis_coinbase.clone()).build()
+				.output().value(20).script_pubkey_bytes(genesis_script.clone()).build()
+				.output().value(30).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let block1_hash = block1.hash();
+		let spending_transaction = block1.transactions()[1].hash();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), block1.into()]));
+		let core = BlockChainClientCore::new(Network::Mainnet, storage);
+
+		let mut scripts = HashMap::new();
+		scripts.insert(genesis_script.clone(), "raw".to_owned());
+		let result = core.scan_tx_out_set(scripts).wait().unwrap();
+		assert_eq!(result.success, true);
+		assert_eq!(result.height, 1);
+		assert_eq!(result.bestblock, block1_hash.into());
+		// spent genesis coinbase output is not included
+		assert_eq!(result.txouts, 3);
+		assert_eq!(result.unspents.len(), 2);
+		assert!(result.unspents.contains(&ScanTxOutSetUnspent {
+			txid: spending_transaction.into(),
+			vout: 0,
+			script_pubkey: genesis_script.into(),
+			desc: "raw".to_owned(),
+			amount: 0.0000002,
+			height: 1,
+		}));
+		assert_eq!(result.total_amount, 0.0000003);
+
+		// scan is finished
+		assert_eq!(core.tx_out_set_scan_progress(), None);
+		assert_eq!(core.abort_tx_out_set_scan(), false);
+	}
 }
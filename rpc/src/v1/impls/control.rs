@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use v1::traits::Control;
+use v1::helpers::errors::invalid_params;
+use jsonrpc_core::Error;
+use jsonrpc_macros::Trailing;
+use log::LevelFilter;
+use logs;
+
+pub struct ControlClient<T: ControlClientCoreApi> {
+	core: T,
+}
+
+pub trait ControlClientCoreApi: Send + Sync + 'static {
+	fn log_filters(&self) -> logs::Filters;
+	fn set_log_filters(&self, filters: logs::Filters);
+}
+
+pub struct ControlClientCore {
+	log_handle: logs::LogHandle,
+}
+
+impl ControlClientCore {
+	pub fn new(log_handle: logs::LogHandle) -> Self {
+		ControlClientCore {
+			log_handle: log_handle,
+		}
+	}
+}
+
+impl ControlClientCoreApi for ControlClientCore {
+	fn log_filters(&self) -> logs::Filters {
+		self.log_handle.filters()
+	}
+
+	fn set_log_filters(&self, filters: logs::Filters) {
+		self.log_handle.set_filters(filters)
+	}
+}
+
+impl<T> ControlClient<T> where T: ControlClientCoreApi {
+	pub fn new(core: T) -> Self {
+		ControlClient {
+			core: core,
+		}
+	}
+}
+
+impl<T> Control for ControlClient<T> where T: ControlClientCoreApi {
+	fn logging(&self, include: Vec<String>, exclude: Trailing<Vec<String>>) -> Result<BTreeMap<String, String>, Error> {
+		let exclude = exclude.unwrap_or_default();
+		if include.iter().chain(exclude.iter()).any(|target| target.is_empty() || target.contains(',') || target.contains('=')) {
+			return Err(invalid_params("target", "Invalid log target"));
+		}
+
+		let mut filters = self.core.log_filters();
+		if !include.is_empty() || !exclude.is_empty() {
+			for target in &include {
+				filters.set(target, LevelFilter::Debug);
+			}
+			for target in &exclude {
+				filters.remove(target);
+			}
+			self.core.set_log_filters(filters.clone());
+		}
+
+		Ok(filters.targets().iter()
+			.map(|(target, level)| (target.clone(), level.to_string().to_lowercase()))
+			.collect())
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use std::sync::Mutex;
+	use jsonrpc_core::IoHandler;
+	use v1::traits::Control;
+	use logs;
+	use super::*;
+
+	struct SuccessControlClientCore {
+		filters: Mutex<logs::Filters>,
+	}
+
+	impl Default for SuccessControlClientCore {
+		fn default() -> Self {
+			SuccessControlClientCore {
+				filters: Mutex::new(logs::Filters::parse("sync=info").unwrap()),
+			}
+		}
+	}
+
+	impl ControlClientCoreApi for SuccessControlClientCore {
+		fn log_filters(&self) -> logs::Filters {
+			self.filters.lock().unwrap().clone()
+		}
+
+		fn set_log_filters(&self, filters: logs::Filters) {
+			*self.filters.lock().unwrap() = filters;
+		}
+	}
+
+	#[test]
+	fn logging_success() {
+		let client = ControlClient::new(SuccessControlClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "logging",
+				"params": [[]],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"sync":"info"},"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "logging",
+				"params": [["p2p", "db"], ["sync"]],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"db":"debug","p2p":"debug"},"id":1}"#);
+	}
+
+	#[test]
+	fn logging_invalid_target() {
+		let client = ControlClient::new(SuccessControlClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "logging",
+				"params": [["sync=trace"]],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: target","data":"\"Invalid log target\""},"id":1}"#);
+	}
+}
@@ -0,0 +1,323 @@
+use std::mem;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use jsonrpc_core::Error;
+use jsonrpc_core::futures::Future;
+use jsonrpc_macros::pubsub::{Subscriber, Sink};
+use jsonrpc_pubsub::SubscriptionId;
+use tokio_core::reactor::Remote;
+use v1::traits::Events;
+use v1::types::{BlockNotification, MemoryPoolEvent, MemoryPoolNotification};
+use primitives::hash::H256 as GlobalH256;
+use miner::MemoryPoolListener;
+use sync::SyncListener;
+use storage;
+use rpc_auth::Metadata;
+
+type Sinks<T> = RwLock<HashMap<SubscriptionId, Sink<T>>>;
+
+/// Selects sinks of single subscription type
+type SinksSelector<T> = fn(&Subscriptions) -> &Sinks<T>;
+
+/// Active subscriptions of the events API
+#[derive(Default)]
+pub struct Subscriptions {
+	next_id: AtomicUsize,
+	blocks: Sinks<BlockNotification>,
+	mempool: Sinks<MemoryPoolNotification>,
+}
+
+impl Subscriptions {
+	/// Adds subscription, which is removed when the session is closed
+	fn subscribe<T>(subscriptions: &Arc<Subscriptions>, meta: Metadata, subscriber: Subscriber<T>, sinks: SinksSelector<T>) where T: 'static {
+		let id = SubscriptionId::Number(subscriptions.next_id.fetch_add(1, Ordering::SeqCst) as u64 + 1);
+		// error means that the session is already closed
+		let sink = match subscriber.assign_id(id.clone()) {
+			Ok(sink) => sink,
+			Err(_) => return,
+		};
+
+		sinks(subscriptions).write().insert(id.clone(), sink);
+		if let Some(session) = meta.session {
+			let subscriptions = Arc::downgrade(subscriptions);
+			session.on_drop(Box::new(move || Subscriptions::remove(&subscriptions, sinks, &id)));
+		}
+	}
+
+	fn unsubscribe<T>(&self, sinks: &Sinks<T>, id: SubscriptionId) -> bool {
+		sinks.write().remove(&id).is_some()
+	}
+
+	fn remove<T>(subscriptions: &Weak<Subscriptions>, sinks: SinksSelector<T>, id: &SubscriptionId) {
+		if let Some(subscriptions) = subscriptions.upgrade() {
+			sinks(&subscriptions).write().remove(id);
+		}
+	}
+}
+
+pub struct EventsClient {
+	subscriptions: Arc<Subscriptions>,
+}
+
+impl EventsClient {
+	pub fn new(notifier: &EventsNotifier) -> Self {
+		EventsClient {
+			subscriptions: notifier.subscriptions.clone(),
+		}
+	}
+}
+
+impl Events for EventsClient {
+	type Metadata = Metadata;
+
+	fn subscribe_blocks(&self, meta: Self::Metadata, subscriber: Subscriber<BlockNotification>) {
+		Subscriptions::subscribe(&self.subscriptions, meta, subscriber, |subscriptions| &subscriptions.blocks)
+	}
+
+	fn unsubscribe_blocks(&self, id: SubscriptionId) -> Result<bool, Error> {
+		Ok(self.subscriptions.unsubscribe(&self.subscriptions.blocks, id))
+	}
+
+	fn subscribe_mempool(&self, meta: Self::Metadata, subscriber: Subscriber<MemoryPoolNotification>) {
+		Subscriptions::subscribe(&self.subscriptions, meta, subscriber, |subscriptions| &subscriptions.mempool)
+	}
+
+	fn unsubscribe_mempool(&self, id: SubscriptionId) -> Result<bool, Error> {
+		Ok(self.subscriptions.unsubscribe(&self.subscriptions.mempool, id))
+	}
+}
+
+/// Sends best chain and memory pool events to the subscribers. Should be installed as both
+/// synchronization and memory pool listener.
+#[derive(Clone)]
+pub struct EventsNotifier {
+	subscriptions: Arc<Subscriptions>,
+	storage: storage::SharedStore,
+	remote: Remote,
+	/// Blocks, decanonized since the last best block notification
+	decanonized_blocks: Arc<Mutex<Vec<GlobalH256>>>,
+}
+
+impl EventsNotifier {
+	pub fn new(storage: storage::SharedStore, remote: Remote) -> Self {
+		EventsNotifier {
+			subscriptions: Default::default(),
+			storage: storage,
+			remote: remote,
+			decanonized_blocks: Default::default(),
+		}
+	}
+
+	/// Notifications are sent from the event loop, so listener callbacks never block.
+	/// Sinks, which transport is closed, are removed.
+	fn notify<T, F>(&self, sinks: SinksSelector<T>, notification: F) where
+		T: Serialize + 'static,
+		F: Fn() -> T,
+	{
+		for (id, sink) in sinks(&self.subscriptions).read().iter() {
+			let subscriptions = Arc::downgrade(&self.subscriptions);
+			let id = id.clone();
+			let future = sink.notify(Ok(notification()))
+				.map(|_| ())
+				.map_err(move |_| Subscriptions::remove(&subscriptions, sinks, &id));
+			self.remote.spawn(move |_| future);
+		}
+	}
+
+	fn notify_mempool(&self, event: MemoryPoolEvent, hash: &GlobalH256) {
+		self.notify(|subscriptions| &subscriptions.mempool, || MemoryPoolNotification {
+			event: event,
+			txid: hash.reversed().into(),
+		});
+	}
+}
+
+impl SyncListener for EventsNotifier {
+	fn synchronization_state_switched(&self, _is_synchronizing: bool) {
+	}
+
+	fn best_storage_block_inserted(&self, block_hash: &GlobalH256) {
+		let disconnected = mem::replace(&mut *self.decanonized_blocks.lock(), Vec::new());
+		if self.subscriptions.blocks.read().is_empty() {
+			return;
+		}
+
+		let height = match self.storage.block_number(block_hash) {
+			Some(height) => height,
+			None => return,
+		};
+		self.notify(|subscriptions| &subscriptions.blocks, || BlockNotification {
+			hash: block_hash.reversed().into(),
+			height: height,
+			disconnected: disconnected.iter().map(|hash| hash.reversed().into()).collect(),
+		});
+	}
+
+	fn best_storage_blocks_decanonized(&self, blocks_hashes: &[GlobalH256]) {
+		self.decanonized_blocks.lock().extend(blocks_hashes.iter().cloned());
+	}
+}
+
+impl MemoryPoolListener for EventsNotifier {
+	fn transaction_added(&self, hash: &GlobalH256) {
+		self.notify_mempool(MemoryPoolEvent::Added, hash);
+	}
+
+	fn transaction_removed(&self, hash: &GlobalH256) {
+		self.notify_mempool(MemoryPoolEvent::Removed, hash);
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use jsonrpc_core::{MetaIoHandler, Compatibility, NoopMiddleware};
+	use jsonrpc_core::futures::Stream;
+	use jsonrpc_core::futures::sync::mpsc;
+	use jsonrpc_pubsub::Session;
+	use tokio_core::reactor::Core;
+	use db::BlockChainDatabase;
+	use miner::MemoryPoolListener;
+	use sync::SyncListener;
+	use v1::traits::Events;
+	use rpc_auth::Metadata;
+	use super::*;
+
+	fn metadata() -> (Metadata, mpsc::Receiver<String>) {
+		let (sender, receiver) = mpsc::channel(8);
+		let metadata = Metadata {
+			user: None,
+			session: Some(Arc::new(Session::new(sender))),
+		};
+		(metadata, receiver)
+	}
+
+	fn handler(notifier: &EventsNotifier) -> MetaIoHandler<Metadata, NoopMiddleware> {
+		let mut handler = MetaIoHandler::new(Compatibility::Both, NoopMiddleware::default());
+		handler.extend_with(EventsClient::new(notifier).to_delegate());
+		handler
+	}
+
+	#[test]
+	fn subscriptions_are_not_available_without_session() {
+		let core = Core::new().unwrap();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let notifier = EventsNotifier::new(storage, core.remote());
+		let handler = handler(&notifier);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "blocks_subscribe",
+				"params": [],
+				"id": 1
+			}"#), Metadata::default()).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32090,"message":"Subscriptions are not available on this transport."},"id":1}"#);
+	}
+
+	#[test]
+	fn blocks_subscription() {
+		let mut core = Core::new().unwrap();
+		let genesis = test_data::genesis();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]));
+		let notifier = EventsNotifier::new(storage, core.remote());
+		let handler = handler(&notifier);
+		let (metadata, receiver) = metadata();
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "blocks_subscribe",
+				"params": [],
+				"id": 1
+			}"#), metadata.clone()).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+
+		notifier.best_storage_blocks_decanonized(&[GlobalH256::from(1)]);
+		notifier.best_storage_block_inserted(&genesis.hash());
+		let (notification, receiver) = core.run(receiver.into_future()).ok().unwrap();
+		assert_eq!(notification, Some(format!(r#"{{"jsonrpc":"2.0","method":"blocks","params":{{"result":{{"disconnected":["{}"],"hash":"{}","height":0}},"subscription":1}}}}"#,
+			GlobalH256::from(1).to_reversed_str(), genesis.hash().to_reversed_str())));
+
+		// disconnected blocks are only reported once
+		notifier.best_storage_block_inserted(&genesis.hash());
+		let (notification, _) = core.run(receiver.into_future()).ok().unwrap();
+		assert_eq!(notification, Some(format!(r#"{{"jsonrpc":"2.0","method":"blocks","params":{{"result":{{"disconnected":[],"hash":"{}","height":0}},"subscription":1}}}}"#,
+			genesis.hash().to_reversed_str())));
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "blocks_unsubscribe",
+				"params": [1],
+				"id": 1
+			}"#), metadata).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+
+	#[test]
+	fn mempool_subscription_batch_request() {
+		let mut core = Core::new().unwrap();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let notifier = EventsNotifier::new(storage, core.remote());
+		let handler = handler(&notifier);
+		let (metadata, receiver) = metadata();
+
+		let sample = handler.handle_request_sync(&(r#"
+			[{
+				"jsonrpc": "2.0",
+				"method": "blocks_subscribe",
+				"params": [],
+				"id": 1
+			}, {
+				"jsonrpc": "2.0",
+				"method": "mempool_subscribe",
+				"params": [],
+				"id": 2
+			}]"#), metadata.clone()).unwrap();
+		assert_eq!(&sample, r#"[{"jsonrpc":"2.0","result":1,"id":1},{"jsonrpc":"2.0","result":2,"id":2}]"#);
+
+		notifier.transaction_added(&GlobalH256::from(1));
+		notifier.transaction_removed(&GlobalH256::from(1));
+		let notifications = core.run(receiver.take(2).collect()).unwrap();
+		assert_eq!(notifications, vec![
+			format!(r#"{{"jsonrpc":"2.0","method":"mempool","params":{{"result":{{"txid":"{0}","type":"added"}},"subscription":2}}}}"#, GlobalH256::from(1).to_reversed_str()),
+			format!(r#"{{"jsonrpc":"2.0","method":"mempool","params":{{"result":{{"txid":"{0}","type":"removed"}},"subscription":2}}}}"#, GlobalH256::from(1).to_reversed_str()),
+		]);
+
+		// subscriptions are removed, when session is closed
+		drop(metadata);
+		notifier.transaction_added(&GlobalH256::from(2));
+		assert_eq!(notifier.subscriptions.mempool.read().len(), 0);
+	}
+
+	#[test]
+	fn subscription_is_removed_when_transport_is_closed() {
+		let mut core = Core::new().unwrap();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let notifier = EventsNotifier::new(storage, core.remote());
+		let handler = handler(&notifier);
+		let (metadata, receiver) = metadata();
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "mempool_subscribe",
+				"params": [],
+				"id": 1
+			}"#), metadata.clone()).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+
+		// session is still alive, but notifications can not be delivered anymore
+		drop(receiver);
+		notifier.transaction_added(&GlobalH256::from(1));
+		core.turn(Some(::std::time::Duration::from_millis(100)));
+		assert_eq!(notifier.subscriptions.mempool.read().len(), 0);
+	}
+}
@@ -2,8 +2,14 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod control;
+mod events;
+mod util;
 
 pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
 pub use self::miner::{MinerClient, MinerClientCore};
 pub use self::raw::{RawClient, RawClientCore};
 pub use self::network::{NetworkClient, NetworkClientCore};
+pub use self::control::{ControlClient, ControlClientCore};
+pub use self::events::{EventsClient, EventsNotifier};
+pub use self::util::{UtilClient, UtilClientCore};
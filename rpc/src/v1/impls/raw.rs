@@ -1,40 +1,50 @@
 use jsonrpc_core::Error;
 use jsonrpc_macros::Trailing;
+use hex::FromHex;
 use ser::{Reader, serialize, deserialize, Serializable, SERIALIZE_TRANSACTION_WITNESS};
 use v1::traits::Raw;
-use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse, SignedTransactionInput, TransactionInputScript, SignedTransactionOutput, TransactionOutputScript};
-use v1::types::H256;
-use v1::helpers::errors::{execution, invalid_params, transaction_not_found, transaction_of_side_branch};
-use global_script::Script;
+use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse, SignedTransactionInput, TransactionInputScript, SignedTransactionOutput, TransactionOutputScript, ScriptTrace};
+use v1::types::{H256, Bytes, TestMempoolAcceptResult, TestMempoolAcceptFees, SubmitPackageResult, SubmitPackageTransactionResult,
+	SubmitPackageFees};
+use v1::helpers::errors::{execution, invalid_params, transaction_not_found, transaction_of_side_branch, transaction_rejected};
+use global_script::{Script, ScriptWitness, VerificationFlags, SignatureVersion, NoopSignatureChecker, verify_script_traced};
 use chain::{Transaction as GlobalTransaction, IndexedTransaction as GlobalIndexedTransaction};
-use network::{ConsensusFork, Network};
+use network::{ConsensusParams, ConsensusFork, Network};
+use verification::TransactionError;
 use primitives::bytes::Bytes as GlobalBytes;
 use primitives::hash::H256 as GlobalH256;
-use sync;
+use sync::{self, TransactionAcceptance, TransactionRejection, PackageAcceptance};
 use storage;
 use keys::Address;
 
+/// Default max fee rate of `testmempoolaccept` transactions (in BTC/kvB)
+pub const DEFAULT_MAX_RAW_TX_FEE_RATE: f64 = 0.10;
+
 pub struct RawClient<T: RawClientCoreApi> {
 	core: T,
 }
 
 pub trait RawClientCoreApi: Send + Sync + 'static {
-	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, String>;
+	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, TransactionRejection>;
+	fn test_accept_transaction(&self, transaction: GlobalTransaction) -> TransactionAcceptance;
+	fn accept_package(&self, package: Vec<GlobalTransaction>) -> PackageAcceptance;
 	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, String>;
 	fn get_raw_transaction(&self, hash: GlobalH256, verbose: bool) -> Result<GetRawTransactionResponse, Error>;
 	fn transaction_to_verbose_transaction(&self, transaction: GlobalIndexedTransaction) -> Transaction;
+	fn debug_script(&self, script_sig: Script, script_pubkey: Script, script_witness: ScriptWitness) -> ScriptTrace;
+	fn trace_transaction(&self, transaction: GlobalIndexedTransaction) -> Vec<ScriptTrace>;
 }
 
 pub struct RawClientCore {
-	network: Network,
+	consensus: ConsensusParams,
 	local_sync_node: sync::LocalNodeRef,
 	storage: storage::SharedStore,
 }
 
 impl RawClientCore {
-	pub fn new(network: Network, local_sync_node: sync::LocalNodeRef, storage: storage::SharedStore) -> Self {
+	pub fn new(consensus: ConsensusParams, local_sync_node: sync::LocalNodeRef, storage: storage::SharedStore) -> Self {
 		RawClientCore {
-			network,
+			consensus,
 			local_sync_node,
 			storage,
 		}
@@ -96,13 +106,49 @@ impl RawClientCore {
 
 		Ok(transaction)
 	}
+
+	/// Flags and signature version of all activated consensus rules
+	fn latest_verification_flags(&self) -> (VerificationFlags, SignatureVersion) {
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_locktime(true)
+			.verify_checksequence(true)
+			.verify_dersig(true);
+		match self.consensus.fork {
+			ConsensusFork::BitcoinCore => (flags
+				.verify_witness(true)
+				.verify_nulldummy(true), SignatureVersion::Base),
+			ConsensusFork::BitcoinCash(_) => (flags
+				.verify_strictenc(true)
+				.verify_concat(true)
+				.verify_split(true)
+				.verify_and(true)
+				.verify_or(true)
+				.verify_xor(true)
+				.verify_div(true)
+				.verify_mod(true)
+				.verify_bin2num(true)
+				.verify_num2bin(true)
+				.verify_checkdatasig(true)
+				.verify_sigpushonly(true)
+				.verify_cleanstack(true), SignatureVersion::ForkId),
+		}
+	}
 }
 
 impl RawClientCoreApi for RawClientCore {
-	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, String> {
+	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, TransactionRejection> {
 		self.local_sync_node.accept_transaction(GlobalIndexedTransaction::from_raw(transaction))
 	}
 
+	fn test_accept_transaction(&self, transaction: GlobalTransaction) -> TransactionAcceptance {
+		self.local_sync_node.test_accept_transaction(&GlobalIndexedTransaction::from_raw(transaction))
+	}
+
+	fn accept_package(&self, package: Vec<GlobalTransaction>) -> PackageAcceptance {
+		self.local_sync_node.accept_package(package.into_iter().map(GlobalIndexedTransaction::from_raw).collect())
+	}
+
 	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<GlobalTransaction, String> {
 		RawClientCore::do_create_raw_transaction(inputs, outputs, lock_time)
 	}
@@ -196,7 +242,7 @@ impl RawClientCoreApi for RawClientCore {
 						addresses: script_addresses.into_iter().map(|address| Address {
 							hash: address.hash,
 							kind: address.kind,
-							network: match self.network {
+							network: match self.consensus.network {
 								Network::Mainnet => keys::Network::Mainnet,
 								_ => keys::Network::Testnet,
 							},
@@ -225,6 +271,28 @@ impl RawClientCoreApi for RawClientCore {
 			blocktime: None,
 		}
 	}
+
+	fn debug_script(&self, script_sig: Script, script_pubkey: Script, script_witness: ScriptWitness) -> ScriptTrace {
+		let (flags, signature_version) = self.latest_verification_flags();
+		let mut trace = ScriptTrace::default();
+		let result = verify_script_traced(&script_sig, &script_pubkey, &script_witness, &flags, &NoopSignatureChecker, signature_version, &mut trace);
+		trace.finish(result.map_err(|e| e.to_string()));
+		trace
+	}
+
+	fn trace_transaction(&self, transaction: GlobalIndexedTransaction) -> Vec<ScriptTrace> {
+		(0..transaction.raw.inputs.len())
+			.map(|index| {
+				let mut trace = ScriptTrace::default();
+				let result = self.local_sync_node.trace_transaction_input(&transaction, index, &mut trace);
+				trace.finish(result.map_err(|e| match e {
+					TransactionError::Signature(_, e) => e.to_string(),
+					e => format!("{:?}", e),
+				}));
+				trace
+			})
+			.collect()
+	}
 }
 
 impl<T> RawClient<T> where T: RawClientCoreApi {
@@ -241,7 +309,94 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 		let transaction = deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("tx", e))?;
 		self.core.accept_transaction(transaction)
 			.map(|h| h.reversed().into())
-			.map_err(|e| execution(e))
+			.map_err(|rejection| transaction_rejected(rejection.code.into(), &rejection.reason))
+	}
+
+	fn test_mempool_accept(&self, raw_transactions: Vec<RawTransaction>, max_fee_rate: Trailing<f64>) -> Result<Vec<TestMempoolAcceptResult>, Error> {
+		let max_fee_rate = max_fee_rate.unwrap_or(DEFAULT_MAX_RAW_TX_FEE_RATE);
+		if max_fee_rate < 0f64 {
+			return Err(invalid_params("maxfeerate", "Fee rate must be non-negative"));
+		}
+		// satoshis per kvB
+		let max_fee_rate = (max_fee_rate * chain::constants::SATOSHIS_IN_COIN as f64) as u64;
+
+		let transactions = raw_transactions.into_iter()
+			.map(|raw_transaction| {
+				let raw_transaction_data: Vec<u8> = raw_transaction.into();
+				deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("rawtxs", e))
+			})
+			.collect::<Result<Vec<GlobalTransaction>, Error>>()?;
+
+		Ok(transactions.into_iter()
+			.map(|transaction| {
+				let acceptance = self.core.test_accept_transaction(transaction);
+				let max_fee = max_fee_rate * acceptance.vsize as u64 / 1000;
+				let (reject_code, reject_reason) = match acceptance.result {
+					Err(rejection) => (Some(rejection.code.into()), Some(rejection.reason)),
+					Ok(_) => match acceptance.fee {
+						Some(fee) if max_fee_rate != 0 && fee > max_fee => (None, Some("max-fee-exceeded".to_owned())),
+						_ => (None, None),
+					},
+				};
+
+				TestMempoolAcceptResult {
+					txid: acceptance.hash.reversed().into(),
+					wtxid: acceptance.witness_hash.reversed().into(),
+					allowed: reject_reason.is_none(),
+					vsize: acceptance.vsize,
+					fees: acceptance.fee.map(|fee| TestMempoolAcceptFees {
+						base: fee as f64 / chain::constants::SATOSHIS_IN_COIN as f64,
+					}),
+					reject_code: reject_code,
+					reject_reason: reject_reason,
+				}
+			})
+			.collect())
+	}
+
+	fn submit_package(&self, raw_transactions: Vec<RawTransaction>) -> Result<SubmitPackageResult, Error> {
+		let package = raw_transactions.into_iter()
+			.map(|raw_transaction| {
+				let raw_transaction_data: Vec<u8> = raw_transaction.into();
+				deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("package", e))
+			})
+			.collect::<Result<Vec<GlobalTransaction>, Error>>()?;
+		if package.is_empty() {
+			return Err(invalid_params("package", "Package must contain at least one transaction"));
+		}
+
+		let acceptance = self.core.accept_package(package);
+
+		// fee rate of transactions, which have been added to the memory pool, in BTC/kvB
+		let (package_fee, package_vsize) = acceptance.transactions.iter()
+			.filter_map(|transaction| transaction.fee.map(|fee| (fee, transaction.vsize)))
+			.fold((0u64, 0usize), |(total_fee, total_vsize), (fee, vsize)| (total_fee + fee, total_vsize + vsize));
+		let effective_fee_rate = match package_vsize {
+			0 => 0f64,
+			_ => package_fee as f64 * 1000f64 / package_vsize as f64 / chain::constants::SATOSHIS_IN_COIN as f64,
+		};
+
+		Ok(SubmitPackageResult {
+			package_msg: match acceptance.result {
+				Ok(_) => "success".to_owned(),
+				Err(rejection) => rejection.reason,
+			},
+			tx_results: acceptance.transactions.into_iter()
+				.map(|transaction| SubmitPackageTransactionResult {
+					txid: transaction.hash.reversed().into(),
+					wtxid: transaction.witness_hash.reversed().into(),
+					vsize: transaction.vsize,
+					fees: match transaction.result {
+						Ok(_) => transaction.fee.map(|fee| SubmitPackageFees {
+							base: fee as f64 / chain::constants::SATOSHIS_IN_COIN as f64,
+							effective_fee_rate: effective_fee_rate,
+						}),
+						Err(_) => None,
+					},
+					error: transaction.result.err().map(|rejection| rejection.reason),
+				})
+				.collect(),
+		})
 	}
 
 	fn create_raw_transaction(&self, inputs: Vec<TransactionInput>, outputs: TransactionOutputs, lock_time: Trailing<u32>) -> Result<RawTransaction, Error> {
@@ -267,6 +422,22 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 		let global_hash: GlobalH256 = hash.clone().into();
 		self.core.get_raw_transaction(global_hash.reversed(), verbose.unwrap_or_default())
 	}
+
+	fn debug_script(&self, script_sig: String, script_pubkey: Bytes, script_witness: Trailing<Vec<Bytes>>) -> Result<ScriptTrace, Error> {
+		// unlike other scripts, input script of witness program is empty
+		let script_sig: Vec<u8> = script_sig.from_hex().map_err(|e| invalid_params("script_sig", e))?;
+		let script_witness = script_witness.unwrap_or_default()
+			.into_iter()
+			.map(|item| item.to_vec().into())
+			.collect();
+		Ok(self.core.debug_script(script_sig.into(), script_pubkey.to_vec().into(), script_witness))
+	}
+
+	fn trace_transaction(&self, raw_transaction: RawTransaction) -> Result<Vec<ScriptTrace>, Error> {
+		let raw_transaction_data: Vec<u8> = raw_transaction.into();
+		let transaction: GlobalTransaction = deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("tx", e))?;
+		Ok(self.core.trace_transaction(transaction.into()))
+	}
 }
 
 #[cfg(test)]
@@ -278,6 +449,7 @@ pub mod tests {
 	use v1::traits::Raw;
 	use v1::types::{Bytes, TransactionInput, TransactionOutputs, Transaction, SignedTransactionInput, TransactionInputScript, ScriptType};
 	use keys::Address;
+	use message::types::reject::RejectCode;
 	use super::*;
 
 	#[derive(Default)]
@@ -287,10 +459,30 @@ pub mod tests {
 	struct ErrorRawClientCore;
 
 	impl RawClientCoreApi for SuccessRawClientCore {
-		fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, String> {
+		fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, TransactionRejection> {
 			Ok(transaction.hash())
 		}
 
+		fn test_accept_transaction(&self, transaction: GlobalTransaction) -> TransactionAcceptance {
+			let transaction: GlobalIndexedTransaction = transaction.into();
+			TransactionAcceptance {
+				fee: Some(10_000),
+				..TransactionAcceptance::new(&transaction)
+			}
+		}
+
+		fn accept_package(&self, package: Vec<GlobalTransaction>) -> PackageAcceptance {
+			PackageAcceptance {
+				transactions: package.into_iter()
+					.map(|transaction| TransactionAcceptance {
+						fee: Some(0),
+						..TransactionAcceptance::new(&transaction.into())
+					})
+					.collect(),
+				result: Ok(()),
+			}
+		}
+
 		fn create_raw_transaction(&self, _inputs: Vec<TransactionInput>, _outputs: TransactionOutputs, _lock_time: Trailing<u32>) -> Result<GlobalTransaction, String> {
 			Ok("0100000001ad9d38823d95f31dc6c0cb0724c11a3cf5a466ca4147254a10cd94aade6eb5b3230000006b483045022100b7683165c3ecd57b0c44bf6a0fb258dc08c328458321c8fadc2b9348d4e66bd502204fd164c58d1a949a4d39bb380f8f05c9f6b3e9417f06bf72e5c068428ca3578601210391c35ac5ee7cf82c5015229dcff89507f83f9b8c952b8fecfa469066c1cb44ccffffffff0170f30500000000001976a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac00000000".into())
 		}
@@ -402,11 +594,43 @@ pub mod tests {
 				blocktime: None,
 			}
 		}
+
+		fn debug_script(&self, _script_sig: Script, _script_pubkey: Script, script_witness: ScriptWitness) -> ScriptTrace {
+			ScriptTrace {
+				scripts: vec![],
+				valid: script_witness.is_empty(),
+				error: None,
+			}
+		}
+
+		fn trace_transaction(&self, transaction: GlobalIndexedTransaction) -> Vec<ScriptTrace> {
+			transaction.raw.inputs.iter().map(|_| ScriptTrace {
+				scripts: vec![],
+				valid: true,
+				error: None,
+			}).collect()
+		}
 	}
 
 	impl RawClientCoreApi for ErrorRawClientCore {
-		fn accept_transaction(&self, _transaction: GlobalTransaction) -> Result<GlobalH256, String> {
-			Err("error".to_owned())
+		fn accept_transaction(&self, _transaction: GlobalTransaction) -> Result<GlobalH256, TransactionRejection> {
+			Err(TransactionRejection::new(RejectCode::Invalid, "bad-txns-inputs-missingorspent".to_owned()))
+		}
+
+		fn test_accept_transaction(&self, transaction: GlobalTransaction) -> TransactionAcceptance {
+			let transaction: GlobalIndexedTransaction = transaction.into();
+			TransactionAcceptance {
+				result: Err(TransactionRejection::new(RejectCode::Invalid, "bad-txns-inputs-missingorspent".to_owned())),
+				..TransactionAcceptance::new(&transaction)
+			}
+		}
+
+		fn accept_package(&self, package: Vec<GlobalTransaction>) -> PackageAcceptance {
+			let package: Vec<GlobalIndexedTransaction> = package.into_iter().map(Into::into).collect();
+			PackageAcceptance {
+				result: Err(TransactionRejection::new(RejectCode::InsuficientFee, "min relay fee not met, 0 < 60".to_owned())),
+				..PackageAcceptance::new(&package)
+			}
 		}
 
 		fn create_raw_transaction(&self, _inputs: Vec<TransactionInput>, _outputs: TransactionOutputs, _lock_time: Trailing<u32>) -> Result<GlobalTransaction, String> {
@@ -472,6 +696,22 @@ pub mod tests {
 				blocktime: None,
 			}
 		}
+
+		fn debug_script(&self, _script_sig: Script, _script_pubkey: Script, _script_witness: ScriptWitness) -> ScriptTrace {
+			ScriptTrace {
+				scripts: vec![],
+				valid: false,
+				error: Some("Script evaluated to false".into()),
+			}
+		}
+
+		fn trace_transaction(&self, transaction: GlobalIndexedTransaction) -> Vec<ScriptTrace> {
+			transaction.raw.inputs.iter().map(|_| ScriptTrace {
+				scripts: vec![],
+				valid: false,
+				error: Some("UnknownReference".into()),
+			}).collect()
+		}
 	}
 
 	#[test]
@@ -509,7 +749,91 @@ pub mod tests {
 			}"#)
 		).unwrap();
 
-		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32094,"message":"Transaction is rejected: bad-txns-inputs-missingorspent","data":{"code":16,"reason":"bad-txns-inputs-missingorspent"}},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn testmempoolaccept_allowed() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"allowed":true,"fees":{"base":0.0001},"reject-code":null,"reject-reason":null,"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"}],"id":1}"#, &sample);
+
+		// 10000 satoshis fee of 60 vbytes transaction exceeds 0.0001 BTC/kvB
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"], 0.0001],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"allowed":false,"fees":{"base":0.0001},"reject-code":null,"reject-reason":"max-fee-exceeded","txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn testmempoolaccept_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"allowed":false,"fees":null,"reject-code":16,"reject-reason":"bad-txns-inputs-missingorspent","txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn submitpackage_accepted() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"package_msg":"success","tx-results":[{"error":null,"fees":{"base":0.0,"effective-feerate":0.0},"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"}]},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn submitpackage_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "submitpackage",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"package_msg":"min relay fee not met, 0 < 60","tx-results":[{"error":"package-not-validated","fees":null,"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","vsize":60,"wtxid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"}]},"id":1}"#, &sample);
 	}
 
 	#[test]
@@ -637,4 +961,98 @@ pub mod tests {
 
 		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params: invalid hex."},"id":1}"#, &sample);
 	}
+
+	#[test]
+	fn debugscript_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "debugscript",
+				"params": ["0102", "5287"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"scripts":[],"valid":true},"id":1}"#, &sample);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "debugscript",
+				"params": ["", "0014a4b4ca48de0b3fffc15404a1acdc8dbaae226955", ["01"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"scripts":[],"valid":false},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn debugscript_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "debugscript",
+				"params": ["00", "87"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":{"error":"Script evaluated to false","scripts":[],"valid":false},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn tracetransaction_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "tracetransaction",
+				"params": ["0100000001273d7b971b6788f911038f917dfa9ba85980b018a80b2e8caa4fca85475afdaf010000008b48304502205eb82fbb78f3467269c64ebb48c66567b11b1ebfa9cf4dd793d1482e46d3851c022100d18e2091becaea279f6f896825e7ca669ee0607b30007ca88b43d1de91359ba9014104a208236447f5c93972a739105abb8292613eef741cab36a1b98fa4fcc2989add0e5dc6cda9127a2bf0b18357210ba0119ad700e1fa495143262720067f4fbf83ffffffff02003b5808000000001976a9147793078b2ebc6ab7b7fd213789912f1deb03a97088ac404b4c00000000001976a914ffc2838f7aeed00857dbbfc70d9830c6968aca5688ac00000000"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"scripts":[],"valid":true}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn tracetransaction_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "tracetransaction",
+				"params": ["0100000001273d7b971b6788f911038f917dfa9ba85980b018a80b2e8caa4fca85475afdaf010000008b48304502205eb82fbb78f3467269c64ebb48c66567b11b1ebfa9cf4dd793d1482e46d3851c022100d18e2091becaea279f6f896825e7ca669ee0607b30007ca88b43d1de91359ba9014104a208236447f5c93972a739105abb8292613eef741cab36a1b98fa4fcc2989add0e5dc6cda9127a2bf0b18357210ba0119ad700e1fa495143262720067f4fbf83ffffffff02003b5808000000001976a9147793078b2ebc6ab7b7fd213789912f1deb03a97088ac404b4c00000000001976a914ffc2838f7aeed00857dbbfc70d9830c6968aca5688ac00000000"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"error":"UnknownReference","scripts":[],"valid":false}],"id":1}"#, &sample);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "tracetransaction",
+				"params": ["0100"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert!(sample.contains(r#""code":-32602"#));
+	}
 }
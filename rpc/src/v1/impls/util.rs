@@ -0,0 +1,312 @@
+use jsonrpc_core::Error;
+use jsonrpc_macros::Trailing;
+use v1::traits::Util;
+use v1::types::{GetDescriptorInfoResponse, DescriptorRange};
+use v1::helpers::errors::{execution, invalid_params};
+use global_script::{Descriptor, DescriptorAddress, descriptor_checksum, bip322_sign_p2wpkh, bip322_verify_simple};
+use network::Network;
+use base64;
+use keys;
+
+/// Maximal number of addresses, derived by single deriveaddresses call
+pub const MAX_DERIVED_ADDRESSES: u32 = 1_000_000;
+
+/// Checks that range of child indices of the ranged descriptor is valid
+pub fn check_descriptor_range(range: &DescriptorRange) -> Result<(), Error> {
+	if range.begin > range.end {
+		return Err(invalid_params("range", "Range specified as [begin,end] must not have begin after end"));
+	}
+	if range.end >= keys::HARDENED_INDEX {
+		return Err(invalid_params("range", "End of range is too high"));
+	}
+	if range.end - range.begin >= MAX_DERIVED_ADDRESSES {
+		return Err(invalid_params("range", "Range is too large"));
+	}
+
+	Ok(())
+}
+
+pub struct UtilClient<T: UtilClientCoreApi> {
+	core: T,
+}
+
+pub trait UtilClientCoreApi: Send + Sync + 'static {
+	fn network(&self) -> keys::Network;
+}
+
+pub struct UtilClientCore {
+	network: Network,
+}
+
+impl UtilClientCore {
+	pub fn new(network: Network) -> Self {
+		UtilClientCore {
+			network: network,
+		}
+	}
+}
+
+impl UtilClientCoreApi for UtilClientCore {
+	fn network(&self) -> keys::Network {
+		match self.network {
+			Network::Mainnet => keys::Network::Mainnet,
+			Network::Regtest => keys::Network::Regtest,
+			_ => keys::Network::Testnet,
+		}
+	}
+}
+
+impl<T> UtilClient<T> where T: UtilClientCoreApi {
+	pub fn new(core: T) -> Self {
+		UtilClient {
+			core: core,
+		}
+	}
+}
+
+impl<T> Util for UtilClient<T> where T: UtilClientCoreApi {
+	fn get_descriptor_info(&self, descriptor: String) -> Result<GetDescriptorInfoResponse, Error> {
+		let parsed = Descriptor::parse(&descriptor, false)
+			.map_err(|e| invalid_params("descriptor", e.to_string()))?;
+		let input = descriptor.split('#').next().expect("split always yields at least one item; qed");
+
+		Ok(GetDescriptorInfoResponse {
+			descriptor: format!("{}#{}", parsed, parsed.checksum()),
+			checksum: descriptor_checksum(input).expect("descriptor is parsed, so it has only valid characters; qed"),
+			is_range: parsed.is_range(),
+			is_solvable: parsed.is_solvable(),
+			has_private_keys: false,
+		})
+	}
+
+	fn derive_addresses(&self, descriptor: String, range: Trailing<DescriptorRange>) -> Result<Vec<String>, Error> {
+		let descriptor = Descriptor::parse(&descriptor, true)
+			.map_err(|e| invalid_params("descriptor", e.to_string()))?;
+
+		let range = match (descriptor.is_range(), range.into()) {
+			(true, Some(range)) => range,
+			(true, None) => return Err(invalid_params("range", "Range must be specified for a ranged descriptor")),
+			(false, None) => DescriptorRange { begin: 0, end: 0 },
+			(false, Some(_)) => return Err(invalid_params("range", "Range should not be specified for an un-ranged descriptor")),
+		};
+
+		check_descriptor_range(&range)?;
+
+		let network = self.core.network();
+		(range.begin..range.end + 1)
+			.map(|index| match descriptor.address(index, network) {
+				Ok(Some(address)) => Ok(address.to_string()),
+				Ok(None) => Err(execution("Descriptor does not have a corresponding address")),
+				Err(err) => Err(execution(err.to_string())),
+			})
+			.collect()
+	}
+
+	fn sign_message_with_priv_key(&self, private: String, message: String, address_type: Trailing<String>) -> Result<String, Error> {
+		let private: keys::Private = private.parse()
+			.map_err(|_| invalid_params("privkey", "Invalid private key"))?;
+		if private.network != self.core.network().base58_network() {
+			return Err(invalid_params("privkey", "Invalid private key"));
+		}
+
+		let address_type: Option<String> = address_type.into();
+		let signature = match address_type.as_ref().map(AsRef::as_ref).unwrap_or("legacy") {
+			"legacy" => keys::sign_message(&private, message.as_bytes()).map(|signature| signature.to_vec()),
+			"bech32" => bip322_sign_p2wpkh(&private, message.as_bytes()).map(Into::into),
+			_ => return Err(invalid_params("address_type", "Address type must be either 'legacy' or 'bech32'")),
+		}.map_err(|_| execution("Sign failed"))?;
+
+		Ok(base64::encode(&signature))
+	}
+
+	fn verify_message(&self, address: String, signature: String, message: String) -> Result<bool, Error> {
+		let address: DescriptorAddress = match address.parse() {
+			Ok(address) => address,
+			Err(_) => return Err(invalid_params("address", "Invalid address")),
+		};
+		if !address.is_valid_for(self.core.network()) {
+			return Err(invalid_params("address", "Invalid address"));
+		}
+
+		let signature = base64::decode(&signature)
+			.map_err(|_| invalid_params("signature", "Malformed base64 encoding"))?;
+
+		match address {
+			DescriptorAddress::Legacy(ref address) if address.kind == keys::Type::P2PKH => {
+				if signature.len() != 65 {
+					return Ok(false);
+				}
+
+				let signature: keys::CompactSignature = keys::hash::H520::from(&signature[..]).into();
+				Ok(keys::recover_message(message.as_bytes(), &signature)
+					.map(|public| public.address_hash() == address.hash)
+					.unwrap_or(false))
+			},
+			DescriptorAddress::Legacy(_) => Err(invalid_params("address", "Address does not refer to key")),
+			DescriptorAddress::Segwit(_) => Ok(bip322_verify_simple(&address.script(), message.as_bytes(), &signature).is_ok()),
+		}
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use serde_json;
+	use jsonrpc_core::IoHandler;
+	use v1::traits::Util;
+	use keys;
+	use super::*;
+
+	// BIP32 test vector 1, chain m/0H
+	const XPUB: &'static str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+
+	#[derive(Default)]
+	struct MainnetUtilClientCore;
+
+	impl UtilClientCoreApi for MainnetUtilClientCore {
+		fn network(&self) -> keys::Network {
+			keys::Network::Mainnet
+		}
+	}
+
+	#[derive(Default)]
+	struct TestnetUtilClientCore;
+
+	impl UtilClientCoreApi for TestnetUtilClientCore {
+		fn network(&self) -> keys::Network {
+			keys::Network::Testnet
+		}
+	}
+
+	fn handler<T: UtilClientCoreApi>(core: T) -> IoHandler {
+		let client = UtilClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+		handler
+	}
+
+	fn request(method: &str, params: &str) -> String {
+		request_with_core(MainnetUtilClientCore::default(), method, params)
+	}
+
+	fn request_with_core<T: UtilClientCoreApi>(core: T, method: &str, params: &str) -> String {
+		handler(core).handle_request_sync(&format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params)).unwrap()
+	}
+
+	#[test]
+	fn get_descriptor_info_success() {
+		let sample = request("getdescriptorinfo", r#"["raw(deadbeef)"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"checksum":"89f8spxm","descriptor":"raw(deadbeef)#89f8spxm","hasprivatekeys":false,"isrange":false,"issolvable":false},"id":1}"#);
+
+		// descriptor is returned in canonical form, checksum is computed for the input
+		let descriptor = format!("pkh([d34db33f/44'/0'/0']{}/1/*)", XPUB);
+		let canonical = format!("pkh([d34db33f/44h/0h/0h]{}/1/*)", XPUB);
+		let sample = request("getdescriptorinfo", &format!(r#"["{}"]"#, descriptor));
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":{{"checksum":"{}","descriptor":"{}#{}","hasprivatekeys":false,"isrange":true,"issolvable":true}},"id":1}}"#,
+			descriptor_checksum(&descriptor).unwrap(), canonical, descriptor_checksum(&canonical).unwrap()));
+	}
+
+	#[test]
+	fn get_descriptor_info_invalid() {
+		let sample = request("getdescriptorinfo", r#"["pkh(deadbeef)"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: descriptor","data":"\"Key 'deadbeef' is not valid\""},"id":1}"#);
+	}
+
+	#[test]
+	fn derive_addresses_success() {
+		let sample = request("deriveaddresses", r#"["addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)#uyjndxcw"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":["bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"],"id":1}"#);
+
+		let descriptor = format!("wpkh({}/1/*)", XPUB);
+		let descriptor = format!("{}#{}", descriptor, descriptor_checksum(&descriptor).unwrap());
+		let sample = request("deriveaddresses", &format!(r#"["{}", [2, 4]]"#, descriptor));
+		let expected: Vec<String> = (2..5)
+			.map(|index| Descriptor::parse(&descriptor, true).unwrap().address(index, keys::Network::Mainnet).unwrap().unwrap().to_string())
+			.collect();
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":["{}"],"id":1}}"#, expected.join(r#"",""#)));
+	}
+
+	#[test]
+	fn derive_addresses_invalid() {
+		// checksum is required
+		let sample = request("deriveaddresses", r#"["addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: descriptor","data":"\"Missing checksum\""},"id":1}"#);
+
+		// range is only allowed for ranged descriptors
+		let sample = request("deriveaddresses", r#"["addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)#uyjndxcw", 2]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: range","data":"\"Range should not be specified for an un-ranged descriptor\""},"id":1}"#);
+
+		// pk() has no address
+		let descriptor = "pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)";
+		let sample = request("deriveaddresses", &format!(r#"["{}#{}"]"#, descriptor, descriptor_checksum(descriptor).unwrap()));
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Descriptor does not have a corresponding address\""},"id":1}"#);
+
+		// address of other network
+		let sample = request_with_core(TestnetUtilClientCore::default(), "deriveaddresses", r#"["addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)#uyjndxcw"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Address 'bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4' is not valid\""},"id":1}"#);
+	}
+
+	#[test]
+	fn util_client_core_network() {
+		assert_eq!(UtilClientCore::new(Network::Mainnet).network(), keys::Network::Mainnet);
+		assert_eq!(UtilClientCore::new(Network::Testnet).network(), keys::Network::Testnet);
+		assert_eq!(UtilClientCore::new(Network::Regtest).network(), keys::Network::Regtest);
+	}
+
+	#[test]
+	fn sign_message_with_priv_key_legacy() {
+		// Bitcoin Core signmessagewithprivkey test vector
+		let sample = request_with_core(TestnetUtilClientCore::default(), "verifymessage", r#"["mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB", "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=", "This is just a test message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+
+		let sample = request_with_core(TestnetUtilClientCore::default(), "verifymessage", r#"["mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB", "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=", "This is just another message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+		let sample = request_with_core(TestnetUtilClientCore::default(), "signmessagewithprivkey", r#"["cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N", "This is just a test message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=","id":1}"#);
+	}
+
+	#[test]
+	fn sign_message_with_priv_key_bech32() {
+		let sample = request("signmessagewithprivkey", r#"["L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k", "Hello World", "bech32"]"#);
+		let signature: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		let signature = signature["result"].as_str().unwrap();
+
+		let sample = request("verifymessage", &format!(r#"["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "{}", "Hello World"]"#, signature));
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+	}
+
+	#[test]
+	fn verify_message_bip322() {
+		// BIP322 test vectors
+		let sample = request("verifymessage", r#"["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", ""]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+
+		let sample = request("verifymessage", r#"["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", "Hello World"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+
+		// signature of the empty message
+		let sample = request("verifymessage", r#"["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", "Hello World"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn sign_and_verify_message_invalid() {
+		let sample = request("signmessagewithprivkey", r#"["cUeKHd5orzT3mz8P9pxyREHfsWtVfgsfDjiZZBcjUBAaGk1BTj7N", "message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: privkey","data":"\"Invalid private key\""},"id":1}"#);
+
+		let sample = request("signmessagewithprivkey", r#"["L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k", "message", "p2sh-segwit"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: address_type","data":"\"Address type must be either 'legacy' or 'bech32'\""},"id":1}"#);
+
+		// segwit address of the uncompressed key is not defined
+		let sample = request("signmessagewithprivkey", r#"["5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ", "message", "bech32"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Sign failed\""},"id":1}"#);
+
+		let sample = request("verifymessage", r#"["mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB", "", "message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: address","data":"\"Invalid address\""},"id":1}"#);
+
+		let sample = request("verifymessage", r#"["3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy", "", "message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: address","data":"\"Address does not refer to key\""},"id":1}"#);
+
+		let sample = request("verifymessage", r#"["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "not base64!", "message"]"#);
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: signature","data":"\"Malformed base64 encoding\""},"id":1}"#);
+	}
+}
@@ -8,7 +8,13 @@ pub use self::traits::Raw;
 pub use self::traits::Miner;
 pub use self::traits::BlockChain;
 pub use self::traits::Network;
+pub use self::traits::Control;
+pub use self::traits::Events;
+pub use self::traits::Util;
 pub use self::impls::{RawClient, RawClientCore};
 pub use self::impls::{MinerClient, MinerClientCore};
 pub use self::impls::{BlockChainClient, BlockChainClientCore};
 pub use self::impls::{NetworkClient, NetworkClientCore};
+pub use self::impls::{ControlClient, ControlClientCore};
+pub use self::impls::{EventsClient, EventsNotifier};
+pub use self::impls::{UtilClient, UtilClientCore};
@@ -1,10 +1,12 @@
 use jsonrpc_macros::Trailing;
-use jsonrpc_core::Error;
+use jsonrpc_core::{Error, BoxFuture};
 
 use v1::types::H256;
 use v1::types::GetBlockResponse;
 use v1::types::GetTxOutResponse;
 use v1::types::GetTxOutSetInfoResponse;
+use v1::types::GetBlockFilterResponse;
+use v1::types::{ScanTxOutSetAction, ScanObject, ScanTxOutSetResponse};
 
 
 build_rpc_trait! {
@@ -38,5 +40,27 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxoutsetinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "gettxoutsetinfo")]
 		fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error>;
+		/// Scan the unspent transaction output set for outputs, matching given output script descriptors.
+		/// Ranged descriptors are scanned for child indices [0, 999], unless range is specified.
+		/// Use "status" action to get progress of the running scan and "abort" action to stop it.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "scantxoutset", "params": ["start", ["addr(1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa)", {"desc": "wpkh(xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw/0/*)", "range": 100}]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "scantxoutset")]
+		fn scan_tx_out_set(&self, ScanTxOutSetAction, Trailing<Vec<ScanObject>>) -> BoxFuture<ScanTxOutSetResponse>;
+		/// Get compact filter of given block.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockfilter", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f", "basic"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getblockfilter")]
+		fn block_filter(&self, H256, Trailing<String>) -> Result<GetBlockFilterResponse, Error>;
+		/// Permanently mark block as invalid and switch to the best chain without it.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "invalidateblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "invalidateblock")]
+		fn invalidate_block(&self, H256) -> Result<(), Error>;
+		/// Remove invalid mark from the block, its ancestors and descendants and switch to the best chain.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "reconsiderblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "reconsiderblock")]
+		fn reconsider_block(&self, H256) -> Result<(), Error>;
+		/// Treat block as if it was received before other blocks with the same height.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "preciousblock", "params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "preciousblock")]
+		fn precious_block(&self, H256) -> Result<(), Error>;
 	}
 }
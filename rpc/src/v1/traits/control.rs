@@ -0,0 +1,16 @@
+use std::collections::BTreeMap;
+use jsonrpc_core::Error;
+use jsonrpc_macros::Trailing;
+
+build_rpc_trait! {
+	/// Parity-bitcoin node control interface
+	pub trait Control {
+		/// Enables debug logging of targets from the first list and resets logging of targets from the second list
+		/// to the default level. Returns levels of all explicitly filtered targets.
+		/// Current levels are returned when both lists are empty.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "logging", "params": [[]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "logging", "params": [["p2p", "sync"], ["db"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "logging")]
+		fn logging(&self, Vec<String>, Trailing<Vec<String>>) -> Result<BTreeMap<String, String>, Error>;
+	}
+}
@@ -0,0 +1,37 @@
+use jsonrpc_core::Error;
+use jsonrpc_macros::pubsub::Subscriber;
+use jsonrpc_pubsub::SubscriptionId;
+use v1::types::{BlockNotification, MemoryPoolNotification};
+
+build_rpc_trait! {
+	/// Parity-bitcoin best chain and memory pool events.
+	/// Subscriptions are only available on WebSocket and IPC transports.
+	pub trait Events {
+		type Metadata;
+
+		#[pubsub(name = "blocks")] {
+			/// Subscribes to new best block notifications. Notification of the chain reorganization
+			/// also contains hashes of the disconnected blocks.
+			/// @wscat-example: {"jsonrpc": "2.0", "method": "blocks_subscribe", "params": [], "id":1 }
+			#[rpc(name = "blocks_subscribe")]
+			fn subscribe_blocks(&self, Self::Metadata, Subscriber<BlockNotification>);
+
+			/// Unsubscribes from new best block notifications.
+			/// @wscat-example: {"jsonrpc": "2.0", "method": "blocks_unsubscribe", "params": [1], "id":1 }
+			#[rpc(name = "blocks_unsubscribe")]
+			fn unsubscribe_blocks(&self, SubscriptionId) -> Result<bool, Error>;
+		}
+
+		#[pubsub(name = "mempool")] {
+			/// Subscribes to memory pool transactions additions and removals.
+			/// @wscat-example: {"jsonrpc": "2.0", "method": "mempool_subscribe", "params": [], "id":1 }
+			#[rpc(name = "mempool_subscribe")]
+			fn subscribe_mempool(&self, Self::Metadata, Subscriber<MemoryPoolNotification>);
+
+			/// Unsubscribes from memory pool notifications.
+			/// @wscat-example: {"jsonrpc": "2.0", "method": "mempool_unsubscribe", "params": [1], "id":1 }
+			#[rpc(name = "mempool_unsubscribe")]
+			fn unsubscribe_mempool(&self, SubscriptionId) -> Result<bool, Error>;
+		}
+	}
+}
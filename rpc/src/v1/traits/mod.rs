@@ -2,8 +2,14 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod control;
+mod events;
+mod util;
 
 pub use self::blockchain::BlockChain;
 pub use self::miner::Miner;
 pub use self::raw::Raw;
 pub use self::network::Network;
+pub use self::control::Control;
+pub use self::events::Events;
+pub use self::util::Util;
@@ -1,12 +1,15 @@
 use jsonrpc_macros::Trailing;
 use jsonrpc_core::Error;
 
-use v1::types::H256;
+use v1::types::{H256, Bytes};
 use v1::types::RawTransaction;
 use v1::types::Transaction;
 use v1::types::TransactionInput;
 use v1::types::TransactionOutputs;
 use v1::types::GetRawTransactionResponse;
+use v1::types::ScriptTrace;
+use v1::types::TestMempoolAcceptResult;
+use v1::types::SubmitPackageResult;
 
 build_rpc_trait! {
 	/// Parity-bitcoin raw data interface.
@@ -15,6 +18,16 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "sendrawtransaction", "params": ["01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "sendrawtransaction")]
 		fn send_raw_transaction(&self, RawTransaction) -> Result<H256, Error>;
+		/// Checks if transactions would be accepted to the memory pool, without accepting them. Transactions are checked independently.
+		/// Transaction is rejected if its fee rate exceeds the max fee rate (in BTC/kvB, 0.10 by default, 0 for no limit).
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "testmempoolaccept", "params": [["0100000001273d7b971b6788f911038f917dfa9ba85980b018a80b2e8caa4fca85475afdaf010000008b48304502205eb82fbb78f3467269c64ebb48c66567b11b1ebfa9cf4dd793d1482e46d3851c022100d18e2091becaea279f6f896825e7ca669ee0607b30007ca88b43d1de91359ba9014104a208236447f5c93972a739105abb8292613eef741cab36a1b98fa4fcc2989add0e5dc6cda9127a2bf0b18357210ba0119ad700e1fa495143262720067f4fbf83ffffffff02003b5808000000001976a9147793078b2ebc6ab7b7fd213789912f1deb03a97088ac404b4c00000000001976a914ffc2838f7aeed00857dbbfc70d9830c6968aca5688ac00000000"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "testmempoolaccept")]
+		fn test_mempool_accept(&self, Vec<RawTransaction>, Trailing<f64>) -> Result<Vec<TestMempoolAcceptResult>, Error>;
+		/// Adds package of the child transaction and its unconfirmed parents to the memory pool && relays it to the peers.
+		/// Parents must precede the child. Package fee rate is checked as a whole, so the child could pay for its parents.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitpackage", "params": [["0100000001273d7b971b6788f911038f917dfa9ba85980b018a80b2e8caa4fca85475afdaf010000008b48304502205eb82fbb78f3467269c64ebb48c66567b11b1ebfa9cf4dd793d1482e46d3851c022100d18e2091becaea279f6f896825e7ca669ee0607b30007ca88b43d1de91359ba9014104a208236447f5c93972a739105abb8292613eef741cab36a1b98fa4fcc2989add0e5dc6cda9127a2bf0b18357210ba0119ad700e1fa495143262720067f4fbf83ffffffff02003b5808000000001976a9147793078b2ebc6ab7b7fd213789912f1deb03a97088ac404b4c00000000001976a914ffc2838f7aeed00857dbbfc70d9830c6968aca5688ac00000000"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "submitpackage")]
+		fn submit_package(&self, Vec<RawTransaction>) -> Result<SubmitPackageResult, Error>;
 		/// Create a transaction spending the given inputs and creating new outputs.
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "createrawtransaction", "params": [[{"txid":"4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b","vout":0}],{"1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa":0.01}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "createrawtransaction")]
@@ -27,5 +40,15 @@ build_rpc_trait! {
 		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrawtransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 		#[rpc(name = "getrawtransaction")]
 		fn get_raw_transaction(&self, H256, Trailing<bool>) -> Result<GetRawTransactionResponse, Error>;
+		/// Evaluate input script, output script and optional witness with the latest consensus rules,
+		/// returning the interpreter state before every opcode. Input script may be empty. Signature checks always fail.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "debugscript", "params": ["0102", "5287"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "debugscript")]
+		fn debug_script(&self, String, Bytes, Trailing<Vec<Bytes>>) -> Result<ScriptTrace, Error>;
+		/// Replay scripts of every transaction input against outputs from the storage and the memory pool,
+		/// returning the interpreter state before every opcode.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "tracetransaction", "params": ["0100000001273d7b971b6788f911038f917dfa9ba85980b018a80b2e8caa4fca85475afdaf010000008b48304502205eb82fbb78f3467269c64ebb48c66567b11b1ebfa9cf4dd793d1482e46d3851c022100d18e2091becaea279f6f896825e7ca669ee0607b30007ca88b43d1de91359ba9014104a208236447f5c93972a739105abb8292613eef741cab36a1b98fa4fcc2989add0e5dc6cda9127a2bf0b18357210ba0119ad700e1fa495143262720067f4fbf83ffffffff02003b5808000000001976a9147793078b2ebc6ab7b7fd213789912f1deb03a97088ac404b4c00000000001976a914ffc2838f7aeed00857dbbfc70d9830c6968aca5688ac00000000"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "tracetransaction")]
+		fn trace_transaction(&self, RawTransaction) -> Result<Vec<ScriptTrace>, Error>;
 	}
 }
@@ -0,0 +1,30 @@
+use jsonrpc_core::Error;
+use jsonrpc_macros::Trailing;
+
+use v1::types::{GetDescriptorInfoResponse, DescriptorRange};
+
+build_rpc_trait! {
+	/// Parity-bitcoin utility interface.
+	pub trait Util {
+		/// Analyses output script descriptor, returning it in canonical form with checksum.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdescriptorinfo", "params": ["wpkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "getdescriptorinfo")]
+		fn get_descriptor_info(&self, String) -> Result<GetDescriptorInfoResponse, Error>;
+		/// Derives addresses of the output script descriptor. Descriptor checksum is required.
+		/// Ranged descriptors require either end index or [begin, end] range of child indices.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "deriveaddresses", "params": ["wpkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)#wg9vgf99"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "deriveaddresses")]
+		fn derive_addresses(&self, String, Trailing<DescriptorRange>) -> Result<Vec<String>, Error>;
+		/// Signs the message with the private key, returning base64-encoded signature.
+		/// Address type is either "legacy" (default) for the P2PKH address signature,
+		/// or "bech32" for the BIP322 signature of the P2WPKH address of the key.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "signmessagewithprivkey", "params": ["L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k", "Hello World"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "signmessagewithprivkey")]
+		fn sign_message_with_priv_key(&self, String, String, Trailing<String>) -> Result<String, Error>;
+		/// Verifies the signed message. P2PKH addresses are verified with the legacy signature,
+		/// segwit addresses are verified with the BIP322 simple signature.
+		/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "verifymessage", "params": ["bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l", "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=", "Hello World"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+		#[rpc(name = "verifymessage")]
+		fn verify_message(&self, String, String, String) -> Result<bool, Error>;
+	}
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer};
+
+/// getdescriptorinfo response
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GetDescriptorInfoResponse {
+	/// Descriptor in canonical form, with checksum
+	pub descriptor: String,
+	/// Checksum of the input descriptor
+	pub checksum: String,
+	/// Whether descriptor is ranged
+	#[serde(rename = "isrange")]
+	pub is_range: bool,
+	/// Whether descriptor is solvable
+	#[serde(rename = "issolvable")]
+	pub is_solvable: bool,
+	/// Whether descriptor has at least one private key
+	#[serde(rename = "hasprivatekeys")]
+	pub has_private_keys: bool,
+}
+
+/// Range of child indices of the ranged descriptor: either `end` or `[begin, end]`, both inclusive
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DescriptorRange {
+	pub begin: u32,
+	pub end: u32,
+}
+
+impl<'a> Deserialize<'a> for DescriptorRange {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::{Visitor, SeqAccess, Error};
+
+		struct DescriptorRangeVisitor;
+
+		impl<'b> Visitor<'b> for DescriptorRangeVisitor {
+			type Value = DescriptorRange;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("an end index or [begin, end] pair")
+			}
+
+			fn visit_u64<E>(self, value: u64) -> Result<DescriptorRange, E> where E: Error {
+				if value > u32::max_value() as u64 {
+					return Err(E::custom("range end is too large"));
+				}
+
+				Ok(DescriptorRange {
+					begin: 0,
+					end: value as u32,
+				})
+			}
+
+			fn visit_seq<V>(self, mut visitor: V) -> Result<DescriptorRange, V::Error> where V: SeqAccess<'b> {
+				let begin = visitor.next_element()?.ok_or_else(|| V::Error::invalid_length(0, &self))?;
+				let end = visitor.next_element()?.ok_or_else(|| V::Error::invalid_length(1, &self))?;
+				if visitor.next_element::<u32>()?.is_some() {
+					return Err(V::Error::invalid_length(3, &self));
+				}
+
+				Ok(DescriptorRange {
+					begin: begin,
+					end: end,
+				})
+			}
+		}
+
+		deserializer.deserialize_any(DescriptorRangeVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::*;
+
+	#[test]
+	fn descriptor_range_deserialize() {
+		assert_eq!(serde_json::from_str::<DescriptorRange>("5").unwrap(), DescriptorRange { begin: 0, end: 5 });
+		assert_eq!(serde_json::from_str::<DescriptorRange>("[2, 5]").unwrap(), DescriptorRange { begin: 2, end: 5 });
+		assert!(serde_json::from_str::<DescriptorRange>("[2]").is_err());
+		assert!(serde_json::from_str::<DescriptorRange>("[1, 2, 3]").is_err());
+		assert!(serde_json::from_str::<DescriptorRange>("-1").is_err());
+	}
+
+	#[test]
+	fn get_descriptor_info_response_serialize() {
+		let response = GetDescriptorInfoResponse {
+			descriptor: "raw(deadbeef)#89f8spxm".into(),
+			checksum: "89f8spxm".into(),
+			is_range: false,
+			is_solvable: false,
+			has_private_keys: false,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"descriptor":"raw(deadbeef)#89f8spxm","checksum":"89f8spxm","isrange":false,"issolvable":false,"hasprivatekeys":false}"#);
+	}
+}
@@ -0,0 +1,59 @@
+use super::hash::H256;
+
+/// Notification of the `blocks` subscription
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct BlockNotification {
+	/// Hash of the new best block
+	pub hash: H256,
+	/// Height of the new best block
+	pub height: u32,
+	/// Hashes of blocks, disconnected from the best chain by reorganization
+	pub disconnected: Vec<H256>,
+}
+
+/// Memory pool event type
+#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+pub enum MemoryPoolEvent {
+	/// Transaction is inserted to the memory pool
+	#[serde(rename = "added")]
+	Added,
+	/// Transaction is removed from the memory pool (mined, replaced, evicted or reverified after reorganization)
+	#[serde(rename = "removed")]
+	Removed,
+}
+
+/// Notification of the `mempool` subscription
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct MemoryPoolNotification {
+	/// Event type
+	#[serde(rename = "type")]
+	pub event: MemoryPoolEvent,
+	/// Transaction hash
+	pub txid: H256,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::{BlockNotification, MemoryPoolEvent, MemoryPoolNotification};
+
+	#[test]
+	fn block_notification_serialize() {
+		let notification = BlockNotification {
+			hash: H256::from(1),
+			height: 10,
+			disconnected: vec![H256::from(2)],
+		};
+		assert_eq!(serde_json::to_string(&notification).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","height":10,"disconnected":["0200000000000000000000000000000000000000000000000000000000000000"]}"#);
+	}
+
+	#[test]
+	fn memory_pool_notification_serialize() {
+		let notification = MemoryPoolNotification {
+			event: MemoryPoolEvent::Removed,
+			txid: H256::from(1),
+		};
+		assert_eq!(serde_json::to_string(&notification).unwrap(), r#"{"type":"removed","txid":"0100000000000000000000000000000000000000000000000000000000000000"}"#);
+	}
+}
@@ -0,0 +1,28 @@
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// getblockfilter response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockFilterResponse {
+	/// Serialized filter
+	pub filter: Bytes,
+	/// Filter header
+	pub header: H256,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::bytes::Bytes;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn block_filter_response_serialize() {
+		let response = GetBlockFilterResponse {
+			filter: Bytes::new(vec![1, 2, 3, 4]),
+			header: H256::from(0x56),
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"filter":"01020304","header":"5600000000000000000000000000000000000000000000000000000000000000"}"#);
+	}
+}
@@ -3,11 +3,18 @@ mod block;
 mod block_template;
 mod block_template_request;
 mod bytes;
+mod descriptor;
+mod events;
+mod get_block_filter_response;
 mod get_block_response;
 mod get_tx_out_response;
 mod get_tx_out_set_info_response;
 mod hash;
+mod scan_tx_out_set;
 mod script;
+mod script_trace;
+mod submit_package;
+mod test_mempool_accept;
 mod transaction;
 mod uint;
 mod nodes;
@@ -16,11 +23,19 @@ pub use self::block::RawBlock;
 pub use self::block_template::{BlockTemplate, BlockTemplateTransaction};
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
 pub use self::bytes::Bytes;
+pub use self::descriptor::{GetDescriptorInfoResponse, DescriptorRange};
+pub use self::events::{BlockNotification, MemoryPoolEvent, MemoryPoolNotification};
+pub use self::get_block_filter_response::GetBlockFilterResponse;
 pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
 pub use self::get_tx_out_response::GetTxOutResponse;
 pub use self::get_tx_out_set_info_response::GetTxOutSetInfoResponse;
 pub use self::hash::{H160, H256};
+pub use self::scan_tx_out_set::{ScanTxOutSetAction, ScanObject, ScanTxOutSetUnspent, ScanTxOutSetResult,
+	ScanTxOutSetStatus, ScanTxOutSetResponse};
 pub use self::script::ScriptType;
+pub use self::script_trace::{ScriptTrace, ScriptExecution, ScriptStep, ScriptStage};
+pub use self::submit_package::{SubmitPackageResult, SubmitPackageTransactionResult, SubmitPackageFees};
+pub use self::test_mempool_accept::{TestMempoolAcceptResult, TestMempoolAcceptFees};
 pub use self::transaction::{RawTransaction, Transaction, TransactionInput, TransactionOutput,
 	TransactionOutputWithAddress, TransactionOutputWithScriptData, TransactionInputScript,
 	TransactionOutputScript, SignedTransactionInput, GetRawTransactionResponse,
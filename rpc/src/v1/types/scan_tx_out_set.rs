@@ -0,0 +1,215 @@
+use std::fmt;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Unexpected;
+use super::bytes::Bytes;
+use super::descriptor::DescriptorRange;
+use super::hash::H256;
+
+/// scantxoutset action
+#[derive(Debug, PartialEq)]
+pub enum ScanTxOutSetAction {
+	/// Start the scan
+	Start,
+	/// Abort the running scan
+	Abort,
+	/// Get progress of the running scan
+	Status,
+}
+
+impl<'a> Deserialize<'a> for ScanTxOutSetAction {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::Visitor;
+
+		struct DummyVisitor;
+
+		impl<'b> Visitor<'b> for DummyVisitor {
+			type Value = ScanTxOutSetAction;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a scan action string")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<ScanTxOutSetAction, E> where E: ::serde::de::Error {
+				match value {
+					"start" => Ok(ScanTxOutSetAction::Start),
+					"abort" => Ok(ScanTxOutSetAction::Abort),
+					"status" => Ok(ScanTxOutSetAction::Status),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+		}
+
+		deserializer.deserialize_identifier(DummyVisitor)
+	}
+}
+
+/// Scan object: either descriptor string or `{"desc": descriptor, "range": range}` object
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScanObject {
+	/// Output script descriptor
+	pub descriptor: String,
+	/// Range of child indices of the ranged descriptor
+	pub range: Option<DescriptorRange>,
+}
+
+impl<'a> Deserialize<'a> for ScanObject {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::{Visitor, MapAccess, Error};
+
+		struct ScanObjectVisitor;
+
+		impl<'b> Visitor<'b> for ScanObjectVisitor {
+			type Value = ScanObject;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a descriptor string or {\"desc\": descriptor, \"range\": range} object")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<ScanObject, E> where E: Error {
+				Ok(ScanObject {
+					descriptor: value.to_owned(),
+					range: None,
+				})
+			}
+
+			fn visit_map<V>(self, mut visitor: V) -> Result<ScanObject, V::Error> where V: MapAccess<'b> {
+				let mut descriptor = None;
+				let mut range = None;
+				while let Some(key) = visitor.next_key::<String>()? {
+					match key.as_ref() {
+						"desc" => descriptor = Some(visitor.next_value()?),
+						"range" => range = Some(visitor.next_value()?),
+						_ => return Err(V::Error::unknown_field(&key, &["desc", "range"])),
+					}
+				}
+
+				Ok(ScanObject {
+					descriptor: descriptor.ok_or_else(|| V::Error::missing_field("desc"))?,
+					range: range,
+				})
+			}
+		}
+
+		deserializer.deserialize_any(ScanObjectVisitor)
+	}
+}
+
+/// Unspent output, found by scantxoutset
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetUnspent {
+	/// Hash of the transaction
+	pub txid: H256,
+	/// Index of the output
+	pub vout: u32,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script_pubkey: Bytes,
+	/// Descriptor, which has matched the output script
+	pub desc: String,
+	/// Output value in BTC
+	pub amount: f64,
+	/// Height of the block, containing the transaction
+	pub height: u32,
+}
+
+/// Result of the completed (or aborted) scan
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetResult {
+	/// False if scan has been aborted
+	pub success: bool,
+	/// Number of unspent outputs scanned
+	pub txouts: u64,
+	/// Height of the best block at the scan start
+	pub height: u32,
+	/// Hash of the best block at the scan start
+	pub bestblock: H256,
+	/// Unspent outputs, matching the scan objects
+	pub unspents: Vec<ScanTxOutSetUnspent>,
+	/// Total value of all found unspent outputs in BTC
+	pub total_amount: f64,
+}
+
+/// Progress of the running scan
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScanTxOutSetStatus {
+	/// Approximate percent of the UTXO set, which has been scanned
+	pub progress: f64,
+}
+
+/// Response to scantxoutset RPC request
+#[derive(Debug, PartialEq)]
+pub enum ScanTxOutSetResponse {
+	/// Response to start action
+	Result(ScanTxOutSetResult),
+	/// Response to status action. None if there's no scan in progress
+	Status(Option<ScanTxOutSetStatus>),
+	/// Response to abort action. False if there's no scan in progress
+	Abort(bool),
+}
+
+impl Serialize for ScanTxOutSetResponse {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			ScanTxOutSetResponse::Result(ref result) => result.serialize(serializer),
+			ScanTxOutSetResponse::Status(ref status) => status.serialize(serializer),
+			ScanTxOutSetResponse::Abort(aborted) => aborted.serialize(serializer),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::descriptor::DescriptorRange;
+	use super::*;
+
+	#[test]
+	fn scan_tx_out_set_action_deserialize() {
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""start""#).unwrap(), ScanTxOutSetAction::Start);
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""abort""#).unwrap(), ScanTxOutSetAction::Abort);
+		assert_eq!(serde_json::from_str::<ScanTxOutSetAction>(r#""status""#).unwrap(), ScanTxOutSetAction::Status);
+		assert!(serde_json::from_str::<ScanTxOutSetAction>(r#""stop""#).is_err());
+	}
+
+	#[test]
+	fn scan_object_deserialize() {
+		assert_eq!(serde_json::from_str::<ScanObject>(r#""raw(deadbeef)""#).unwrap(), ScanObject {
+			descriptor: "raw(deadbeef)".into(),
+			range: None,
+		});
+		assert_eq!(serde_json::from_str::<ScanObject>(r#"{"desc":"raw(deadbeef)"}"#).unwrap(), ScanObject {
+			descriptor: "raw(deadbeef)".into(),
+			range: None,
+		});
+		assert_eq!(serde_json::from_str::<ScanObject>(r#"{"range":[1,5],"desc":"raw(deadbeef)"}"#).unwrap(), ScanObject {
+			descriptor: "raw(deadbeef)".into(),
+			range: Some(DescriptorRange { begin: 1, end: 5 }),
+		});
+		assert!(serde_json::from_str::<ScanObject>(r#"{"range":5}"#).is_err());
+		assert!(serde_json::from_str::<ScanObject>(r#"{"desc":"raw(deadbeef)","label":"x"}"#).is_err());
+	}
+
+	#[test]
+	fn scan_tx_out_set_response_serialize() {
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResponse::Abort(false)).unwrap(), "false");
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResponse::Status(None)).unwrap(), "null");
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResponse::Status(Some(ScanTxOutSetStatus {
+			progress: 50.0,
+		}))).unwrap(), r#"{"progress":50.0}"#);
+		assert_eq!(serde_json::to_string(&ScanTxOutSetResponse::Result(ScanTxOutSetResult {
+			success: true,
+			txouts: 1,
+			height: 0,
+			bestblock: H256::from(0x56),
+			unspents: vec![ScanTxOutSetUnspent {
+				txid: H256::from(0x57),
+				vout: 0,
+				script_pubkey: Bytes::new(vec![1, 2, 3, 4]),
+				desc: "raw(01020304)#rhemleug".into(),
+				amount: 50.0,
+				height: 0,
+			}],
+			total_amount: 50.0,
+		})).unwrap(), r#"{"success":true,"txouts":1,"height":0,"bestblock":"5600000000000000000000000000000000000000000000000000000000000000","unspents":[{"txid":"5700000000000000000000000000000000000000000000000000000000000000","vout":0,"scriptPubKey":"01020304","desc":"raw(01020304)#rhemleug","amount":50.0,"height":0}],"total_amount":50.0}"#);
+	}
+}
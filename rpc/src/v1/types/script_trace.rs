@@ -0,0 +1,164 @@
+use global_script::{Script, ScriptTracer, ScriptStage as GlobalScriptStage, TraceStep, Stack, Error as ScriptError};
+use primitives::bytes::Bytes as GlobalBytes;
+use super::bytes::Bytes;
+
+/// Script, evaluated when input is verified
+#[derive(Debug, Serialize, PartialEq, Clone, Copy)]
+pub enum ScriptStage {
+	/// Input script
+	#[serde(rename = "scriptSig")]
+	ScriptSig,
+	/// Script of the spent output
+	#[serde(rename = "scriptPubKey")]
+	ScriptPubKey,
+	/// Serialized script of the P2SH output
+	#[serde(rename = "redeemScript")]
+	RedeemScript,
+	/// Script of the witness program
+	#[serde(rename = "witnessScript")]
+	WitnessScript,
+}
+
+/// Interpreter state before execution of the opcode
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScriptStep {
+	/// Position of the opcode in the script
+	pub pc: usize,
+	/// Opcode name
+	pub opcode: String,
+	/// Data, pushed by the opcode
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data: Option<Bytes>,
+	/// False if opcode is in the not executed branch of the conditional
+	pub executed: bool,
+	/// Main stack, top item is the last one
+	pub stack: Vec<Bytes>,
+	/// Alt stack, top item is the last one
+	pub altstack: Vec<Bytes>,
+	/// Values of the entered conditionals
+	pub execstack: Vec<bool>,
+}
+
+/// Evaluation of the single script
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ScriptExecution {
+	/// Script stage
+	pub stage: ScriptStage,
+	/// Script hex
+	pub hex: Bytes,
+	/// Script opcodes
+	pub asm: String,
+	/// Executed opcodes
+	pub steps: Vec<ScriptStep>,
+	/// Main stack after evaluation
+	pub stack: Vec<Bytes>,
+	/// Evaluation error
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+/// debugscript && tracetransaction response
+#[derive(Debug, Serialize, PartialEq, Default)]
+pub struct ScriptTrace {
+	/// Evaluated scripts
+	pub scripts: Vec<ScriptExecution>,
+	/// Is input valid?
+	pub valid: bool,
+	/// Verification error
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl ScriptTrace {
+	/// Sets verification result
+	pub fn finish(&mut self, result: Result<(), String>) {
+		self.valid = result.is_ok();
+		self.error = result.err();
+	}
+}
+
+impl From<GlobalScriptStage> for ScriptStage {
+	fn from(stage: GlobalScriptStage) -> Self {
+		match stage {
+			GlobalScriptStage::ScriptSig => ScriptStage::ScriptSig,
+			GlobalScriptStage::ScriptPubKey => ScriptStage::ScriptPubKey,
+			GlobalScriptStage::RedeemScript => ScriptStage::RedeemScript,
+			GlobalScriptStage::WitnessScript => ScriptStage::WitnessScript,
+		}
+	}
+}
+
+fn stack_items(stack: &Stack<GlobalBytes>) -> Vec<Bytes> {
+	stack.iter().cloned().map(Into::into).collect()
+}
+
+impl ScriptTracer for ScriptTrace {
+	fn script_started(&mut self, stage: GlobalScriptStage, script: &Script) {
+		self.scripts.push(ScriptExecution {
+			stage: stage.into(),
+			hex: script.to_bytes().into(),
+			asm: format!("{}", script),
+			steps: Vec::new(),
+			stack: Vec::new(),
+			error: None,
+		});
+	}
+
+	fn step(&mut self, step: &TraceStep) {
+		if let Some(execution) = self.scripts.last_mut() {
+			execution.steps.push(ScriptStep {
+				pc: step.pc,
+				opcode: format!("{:?}", step.opcode),
+				data: step.data.map(|data| data.to_vec().into()),
+				executed: step.executing,
+				stack: stack_items(step.stack),
+				altstack: stack_items(step.altstack),
+				execstack: step.exec_stack.to_vec(),
+			});
+		}
+	}
+
+	fn script_finished(&mut self, stack: &Stack<GlobalBytes>, result: &Result<bool, ScriptError>) {
+		if let Some(execution) = self.scripts.last_mut() {
+			execution.stack = stack_items(stack);
+			execution.error = result.as_ref().err().map(|e| e.to_string());
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use global_script::{Builder, Opcode, NoopSignatureChecker, ScriptWitness, SignatureVersion, VerificationFlags, verify_script_traced};
+	use super::*;
+
+	#[test]
+	fn script_trace_serialize() {
+		let script_pubkey = Builder::default()
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_EQUAL)
+			.into_script();
+		let script_sig = Builder::default()
+			.push_data(&[2])
+			.into_script();
+
+		let mut trace = ScriptTrace::default();
+		let result = verify_script_traced(&script_sig, &script_pubkey, &ScriptWitness::default(), &VerificationFlags::default(),
+			&NoopSignatureChecker, SignatureVersion::Base, &mut trace);
+		trace.finish(result.map_err(|e| e.to_string()));
+		assert_eq!(serde_json::to_string(&trace).unwrap(), r#"{"scripts":[{"stage":"scriptSig","hex":"0102","asm":"OP_PUSHBYTES_1 0x02\n","steps":[{"pc":0,"opcode":"OP_PUSHBYTES_1","data":"02","executed":true,"stack":[],"altstack":[],"execstack":[]}],"stack":["02"]},{"stage":"scriptPubKey","hex":"5287","asm":"OP_2\nOP_EQUAL\n","steps":[{"pc":0,"opcode":"OP_2","executed":true,"stack":["02"],"altstack":[],"execstack":[]},{"pc":1,"opcode":"OP_EQUAL","executed":true,"stack":["02","02"],"altstack":[],"execstack":[]}],"stack":["01"]}],"valid":true}"#);
+	}
+
+	#[test]
+	fn script_trace_failure_serialize() {
+		let script_pubkey = Builder::default()
+			.push_opcode(Opcode::OP_DROP)
+			.into_script();
+
+		let mut trace = ScriptTrace::default();
+		let result = verify_script_traced(&Builder::default().into_script(), &script_pubkey, &ScriptWitness::default(), &VerificationFlags::default(),
+			&NoopSignatureChecker, SignatureVersion::Base, &mut trace);
+		trace.finish(result.map_err(|e| e.to_string()));
+		assert_eq!(serde_json::to_string(&trace).unwrap(), r#"{"scripts":[{"stage":"scriptSig","hex":"","asm":"","steps":[],"stack":[]},{"stage":"scriptPubKey","hex":"75","asm":"OP_DROP\n","steps":[{"pc":0,"opcode":"OP_DROP","executed":true,"stack":[],"altstack":[],"execstack":[]}],"stack":[],"error":"Invalid stack operation"}],"valid":false,"error":"Invalid stack operation"}"#);
+	}
+}
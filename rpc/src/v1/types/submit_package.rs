@@ -0,0 +1,60 @@
+use super::hash::H256;
+
+/// Fees of the package transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmitPackageFees {
+	/// Transaction fee in BTC
+	pub base: f64,
+	/// Fee rate of the whole package in BTC/kvB
+	#[serde(rename = "effective-feerate")]
+	pub effective_fee_rate: f64,
+}
+
+/// submitpackage result of single transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmitPackageTransactionResult {
+	/// Transaction hash
+	pub txid: H256,
+	/// Transaction witness hash
+	pub wtxid: H256,
+	/// Transaction virtual size
+	pub vsize: usize,
+	/// Transaction fees, if transaction has been accepted to the memory pool
+	pub fees: Option<SubmitPackageFees>,
+	/// Rejection reason, if transaction is rejected
+	pub error: Option<String>,
+}
+
+/// submitpackage result
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SubmitPackageResult {
+	/// Package acceptance message: `success` or rejection reason
+	pub package_msg: String,
+	/// Results of package transactions
+	#[serde(rename = "tx-results")]
+	pub tx_results: Vec<SubmitPackageTransactionResult>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn submit_package_result_serialize() {
+		let result = SubmitPackageResult {
+			package_msg: "success".into(),
+			tx_results: vec![
+				SubmitPackageTransactionResult {
+					txid: H256::from(1),
+					wtxid: H256::from(2),
+					vsize: 110,
+					fees: Some(SubmitPackageFees { base: 0.0, effective_fee_rate: 0.0005 }),
+					error: None,
+				},
+			],
+		};
+		assert_eq!(serde_json::to_string(&result).unwrap(), r#"{"package_msg":"success","tx-results":[{"txid":"0100000000000000000000000000000000000000000000000000000000000000","wtxid":"0200000000000000000000000000000000000000000000000000000000000000","vsize":110,"fees":{"base":0.0,"effective-feerate":0.0005},"error":null}]}"#);
+	}
+}
@@ -0,0 +1,50 @@
+use super::hash::H256;
+
+/// Fees of the transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TestMempoolAcceptFees {
+	/// Transaction fee in BTC
+	pub base: f64,
+}
+
+/// testmempoolaccept result of single transaction
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TestMempoolAcceptResult {
+	/// Transaction hash
+	pub txid: H256,
+	/// Transaction witness hash
+	pub wtxid: H256,
+	/// Would transaction be accepted to the memory pool?
+	pub allowed: bool,
+	/// Transaction virtual size
+	pub vsize: usize,
+	/// Transaction fees, if inputs are known
+	pub fees: Option<TestMempoolAcceptFees>,
+	/// Reject code of the `reject` message, if transaction is rejected
+	#[serde(rename = "reject-code")]
+	pub reject_code: Option<u8>,
+	/// Rejection reason, if transaction is rejected
+	#[serde(rename = "reject-reason")]
+	pub reject_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn test_mempool_accept_result_serialize() {
+		let result = TestMempoolAcceptResult {
+			txid: H256::from(1),
+			wtxid: H256::from(2),
+			allowed: false,
+			vsize: 110,
+			fees: Some(TestMempoolAcceptFees { base: 0.0001 }),
+			reject_code: Some(0x42),
+			reject_reason: Some("min relay fee not met, 10000 < 11000".into()),
+		};
+		assert_eq!(serde_json::to_string(&result).unwrap(), r#"{"txid":"0100000000000000000000000000000000000000000000000000000000000000","wtxid":"0200000000000000000000000000000000000000000000000000000000000000","allowed":false,"vsize":110,"fees":{"base":0.0001},"reject-code":66,"reject-reason":"min relay fee not met, 10000 < 11000"}"#);
+	}
+}
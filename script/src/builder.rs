@@ -1,8 +1,9 @@
 //! Script builder
 
 use bytes::Bytes;
+use hash::H256;
 use {Opcode, Script, Num};
-use keys::AddressHash;
+use keys::{AddressHash, Public};
 
 /// Script builder
 #[derive(Default)]
@@ -31,6 +32,41 @@ impl Builder {
 			.into_script()
 	}
 
+	/// Builds p2pk script pubkey
+	pub fn build_p2pk(public: &Public) -> Script {
+		Builder::default()
+			.push_bytes(public)
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.into_script()
+	}
+
+	/// Builds p2wpkh script pubkey
+	pub fn build_p2wpkh(address: &AddressHash) -> Script {
+		Builder::build_witness_program(0, &**address)
+	}
+
+	/// Builds p2wsh script pubkey
+	pub fn build_p2wsh(hash: &H256) -> Script {
+		Builder::build_witness_program(0, &**hash)
+	}
+
+	/// Builds witness program script pubkey of given version
+	pub fn build_witness_program(version: u8, program: &[u8]) -> Script {
+		Builder::default()
+			.push_small_num(version as usize)
+			.push_bytes(program)
+			.into_script()
+	}
+
+	/// Builds bare multisig script, requiring `threshold` signatures of given keys
+	pub fn build_multisig(threshold: usize, keys: &[Public]) -> Script {
+		keys.iter()
+			.fold(Builder::default().push_small_num(threshold), |builder, key| builder.push_bytes(key))
+			.push_small_num(keys.len())
+			.push_opcode(Opcode::OP_CHECKMULTISIG)
+			.into_script()
+	}
+
 	/// Builds op_return script
 	pub fn build_nulldata(bytes: &[u8]) -> Script {
 		Builder::default()
@@ -60,6 +96,19 @@ impl Builder {
 		self.push_data(&num.to_bytes())
 	}
 
+	/// Appends num push operation to the end of script, using `OP_0`..`OP_16` opcodes for small numbers
+	pub fn push_small_num(self, num: usize) -> Self {
+		match num {
+			0 => self.push_opcode(Opcode::OP_0),
+			1..=16 => {
+				let opcode = Opcode::from_u8(Opcode::OP_1 as u8 + num as u8 - 1)
+					.expect("value is within [OP_1; OP_16] interval; qed");
+				self.push_opcode(opcode)
+			},
+			_ => self.push_num(num.into()),
+		}
+	}
+
 	/// Appends bytes push operation to the end od script
 	pub fn push_bytes(mut self, bytes: &[u8]) -> Self {
 		let len = bytes.len();
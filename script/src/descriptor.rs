@@ -0,0 +1,724 @@
+//! Output script descriptors (BIP380-386)
+//!
+//! Descriptors are human-readable expressions of the output scripts, eg:
+//! `pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)` or
+//! `wsh(sortedmulti(2,xpub.../0/*,xpub.../0/*))`. Descriptors with `*` derivation step are ranged:
+//! they describe an output script for every child index of the extended keys.
+
+use std::fmt;
+use std::str::FromStr;
+use bytes::Bytes;
+use hash::H32;
+use crypto::{dhash160, sha256};
+use keys::{self, Address, ExtendedPublic, Private, Public, SegwitAddress, HARDENED_INDEX};
+use {Builder, Script};
+
+/// Maximal number of keys in multisig descriptor
+pub const MAX_MULTISIG_KEYS: usize = 20;
+/// Maximal number of keys in multisig descriptor, which is not wrapped into sh() or wsh()
+pub const MAX_BARE_MULTISIG_KEYS: usize = 3;
+/// Maximal size of the P2SH redeem script
+const MAX_REDEEM_SCRIPT_SIZE: usize = 520;
+
+const INPUT_CHARSET: &'static str = "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &'static [u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Descriptor parsing or derivation error
+#[derive(Debug, PartialEq)]
+pub enum DescriptorError {
+	/// Descriptor is malformed
+	Syntax(String),
+	/// Checksum is required, but missing
+	MissingChecksum,
+	/// Provided checksum doesn't match computed one
+	InvalidChecksum(String, String),
+	/// Key expression is invalid
+	InvalidKey(String),
+	/// Private keys are not supported
+	PrivateKey,
+	/// Hardened step follows extended public key
+	HardenedDerivation,
+	/// Uncompressed key is used in the witness script
+	UncompressedKey,
+	/// Function is not allowed at this position
+	InvalidContext(&'static str),
+	/// Invalid multisig threshold or number of keys
+	InvalidMultisig(String),
+	/// P2SH redeem script is too large
+	ScriptTooLarge(usize),
+	/// Address is invalid
+	InvalidAddress(String),
+	/// Child key can't be derived at this index
+	Derivation(u32),
+}
+
+impl fmt::Display for DescriptorError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DescriptorError::Syntax(ref msg) => msg.fmt(f),
+			DescriptorError::MissingChecksum => "Missing checksum".fmt(f),
+			DescriptorError::InvalidChecksum(ref provided, ref computed) =>
+				write!(f, "Provided checksum '{}' does not match computed checksum '{}'", provided, computed),
+			DescriptorError::InvalidKey(ref key) => write!(f, "Key '{}' is not valid", key),
+			DescriptorError::PrivateKey => "Private keys are not supported".fmt(f),
+			DescriptorError::HardenedDerivation => "Hardened derivation requires private key".fmt(f),
+			DescriptorError::UncompressedKey => "Uncompressed keys are not allowed in witness scripts".fmt(f),
+			DescriptorError::InvalidContext(name) => write!(f, "Cannot use {}() at this position", name),
+			DescriptorError::InvalidMultisig(ref msg) => msg.fmt(f),
+			DescriptorError::ScriptTooLarge(size) =>
+				write!(f, "P2SH script is too large, {} bytes is larger than {} bytes", size, MAX_REDEEM_SCRIPT_SIZE),
+			DescriptorError::InvalidAddress(ref address) => write!(f, "Address '{}' is not valid", address),
+			DescriptorError::Derivation(index) => write!(f, "Cannot derive key at index {}", index),
+		}
+	}
+}
+
+/// Fingerprint of the master key and derivation path from it to the key
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeyOrigin {
+	/// Master key fingerprint.
+	pub fingerprint: H32,
+	/// Derivation path, hardened steps are >= HARDENED_INDEX.
+	pub path: Vec<u32>,
+}
+
+/// Key expression
+#[derive(Debug, PartialEq, Clone)]
+pub enum DescriptorKey {
+	/// Hex-encoded public key
+	Single {
+		origin: Option<KeyOrigin>,
+		public: Public,
+	},
+	/// Extended public key with non-hardened derivation path, optionally ending with `*`
+	Extended {
+		origin: Option<KeyOrigin>,
+		key: ExtendedPublic,
+		path: Vec<u32>,
+		wildcard: bool,
+	},
+}
+
+impl DescriptorKey {
+	/// Returns true if key has `*` derivation step
+	pub fn is_range(&self) -> bool {
+		match *self {
+			DescriptorKey::Single { .. } => false,
+			DescriptorKey::Extended { wildcard, .. } => wildcard,
+		}
+	}
+
+	/// Returns true if key is compressed. Keys, derived from extended keys, are always compressed
+	pub fn is_compressed(&self) -> bool {
+		match *self {
+			DescriptorKey::Single { public: Public::Normal(_), .. } => false,
+			_ => true,
+		}
+	}
+
+	/// Returns public key. `index` is used as the last derivation step of ranged keys
+	pub fn public(&self, index: u32) -> Result<Public, DescriptorError> {
+		match *self {
+			DescriptorKey::Single { ref public, .. } => Ok(public.clone()),
+			DescriptorKey::Extended { ref key, ref path, wildcard, .. } => {
+				let key = key.derive_path(path).map_err(|_| DescriptorError::Derivation(index))?;
+				match wildcard {
+					true => key.derive(index).map(|key| key.public).map_err(|_| DescriptorError::Derivation(index)),
+					false => Ok(key.public),
+				}
+			},
+		}
+	}
+
+	fn serialized_len(&self) -> usize {
+		match self.is_compressed() {
+			true => 33,
+			false => 65,
+		}
+	}
+}
+
+/// Address, used in the `addr()` descriptor
+#[derive(Debug, PartialEq, Clone)]
+pub enum DescriptorAddress {
+	/// Base58-encoded P2PKH or P2SH address
+	Legacy(Address),
+	/// Bech32-encoded witness program address
+	Segwit(SegwitAddress),
+}
+
+impl DescriptorAddress {
+	/// Returns address of the output script, if script has one
+	pub fn from_script(script: &Script, network: keys::Network) -> Option<Self> {
+		if script.is_pay_to_public_key_hash() {
+			return Some(DescriptorAddress::Legacy(Address {
+				kind: keys::Type::P2PKH,
+				network: network,
+				hash: script[3..23].into(),
+			}));
+		}
+
+		if script.is_pay_to_script_hash() {
+			return Some(DescriptorAddress::Legacy(Address {
+				kind: keys::Type::P2SH,
+				network: network,
+				hash: script[2..22].into(),
+			}));
+		}
+
+		script.parse_witness_program().map(|(version, program)| DescriptorAddress::Segwit(SegwitAddress {
+			network: network,
+			version: version,
+			program: program.into(),
+		}))
+	}
+
+	/// Returns true if address belongs to given network
+	pub fn is_valid_for(&self, network: keys::Network) -> bool {
+		match *self {
+			DescriptorAddress::Legacy(ref address) => address.network == network.base58_network(),
+			DescriptorAddress::Segwit(ref address) => address.network == network,
+		}
+	}
+
+	/// Returns output script, paying to this address
+	pub fn script(&self) -> Script {
+		match *self {
+			DescriptorAddress::Legacy(ref address) => match address.kind {
+				keys::Type::P2PKH => Builder::build_p2pkh(&address.hash),
+				keys::Type::P2SH => Builder::build_p2sh(&address.hash),
+			},
+			DescriptorAddress::Segwit(ref address) => Builder::build_witness_program(address.version, &address.program),
+		}
+	}
+}
+
+impl fmt::Display for DescriptorAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DescriptorAddress::Legacy(ref address) => address.fmt(f),
+			DescriptorAddress::Segwit(ref address) => address.fmt(f),
+		}
+	}
+}
+
+impl FromStr for DescriptorAddress {
+	type Err = DescriptorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.parse().map(DescriptorAddress::Legacy)
+			.or_else(|_| s.parse().map(DescriptorAddress::Segwit))
+			.map_err(|_| DescriptorError::InvalidAddress(s.into()))
+	}
+}
+
+/// Output script descriptor
+#[derive(Debug, PartialEq, Clone)]
+pub enum Descriptor {
+	/// `pk(KEY)`: P2PK output
+	Pk(DescriptorKey),
+	/// `pkh(KEY)`: P2PKH output
+	Pkh(DescriptorKey),
+	/// `wpkh(KEY)`: P2WPKH output
+	Wpkh(DescriptorKey),
+	/// `sh(SCRIPT)`: P2SH output, paying to inner script
+	Sh(Box<Descriptor>),
+	/// `wsh(SCRIPT)`: P2WSH output, paying to inner script
+	Wsh(Box<Descriptor>),
+	/// `multi(k,KEY_1,...,KEY_n)`: k-of-n multisig script
+	Multi(usize, Vec<DescriptorKey>),
+	/// `sortedmulti(k,KEY_1,...,KEY_n)`: k-of-n multisig script with lexicographically sorted keys
+	SortedMulti(usize, Vec<DescriptorKey>),
+	/// `addr(ADDR)`: output, paying to address
+	Addr(DescriptorAddress),
+	/// `raw(HEX)`: output script
+	Raw(Bytes),
+}
+
+/// Position of the descriptor
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Context {
+	/// Top level descriptor
+	Top,
+	/// Inside sh()
+	P2SH,
+	/// Inside wpkh()
+	P2WPKH,
+	/// Inside wsh()
+	P2WSH,
+}
+
+impl Descriptor {
+	/// Parses descriptor, optionally followed by `#checksum`
+	pub fn parse(descriptor: &str, require_checksum: bool) -> Result<Self, DescriptorError> {
+		let (descriptor, checksum) = match descriptor.find('#') {
+			Some(position) => (&descriptor[..position], Some(&descriptor[position + 1..])),
+			None => (descriptor, None),
+		};
+
+		let computed = descriptor_checksum(descriptor)
+			.ok_or_else(|| DescriptorError::Syntax("Invalid characters in descriptor".into()))?;
+		match checksum {
+			Some(checksum) if checksum.len() != 8 =>
+				return Err(DescriptorError::Syntax(format!("Expected 8 character checksum, not {} characters", checksum.len()))),
+			Some(checksum) if checksum != computed =>
+				return Err(DescriptorError::InvalidChecksum(checksum.into(), computed)),
+			None if require_checksum => return Err(DescriptorError::MissingChecksum),
+			_ => (),
+		}
+
+		parse_descriptor(descriptor, Context::Top)
+	}
+
+	/// Returns true if descriptor describes range of output scripts
+	pub fn is_range(&self) -> bool {
+		match *self {
+			Descriptor::Pk(ref key) | Descriptor::Pkh(ref key) | Descriptor::Wpkh(ref key) => key.is_range(),
+			Descriptor::Sh(ref inner) | Descriptor::Wsh(ref inner) => inner.is_range(),
+			Descriptor::Multi(_, ref keys) | Descriptor::SortedMulti(_, ref keys) => keys.iter().any(DescriptorKey::is_range),
+			Descriptor::Addr(_) | Descriptor::Raw(_) => false,
+		}
+	}
+
+	/// Returns true if descriptor contains all information, required to spend the output (except private keys)
+	pub fn is_solvable(&self) -> bool {
+		match *self {
+			Descriptor::Sh(ref inner) | Descriptor::Wsh(ref inner) => inner.is_solvable(),
+			Descriptor::Addr(_) | Descriptor::Raw(_) => false,
+			_ => true,
+		}
+	}
+
+	/// Returns output script. `index` is used as the last derivation step of ranged keys
+	pub fn script_pubkey(&self, index: u32) -> Result<Script, DescriptorError> {
+		let script = match *self {
+			Descriptor::Pk(ref key) => Builder::build_p2pk(&key.public(index)?),
+			Descriptor::Pkh(ref key) => Builder::build_p2pkh(&key.public(index)?.address_hash()),
+			Descriptor::Wpkh(ref key) => Builder::build_p2wpkh(&key.public(index)?.address_hash()),
+			Descriptor::Sh(ref inner) => Builder::build_p2sh(&dhash160(&inner.script_pubkey(index)?)),
+			Descriptor::Wsh(ref inner) => Builder::build_p2wsh(&sha256(&inner.script_pubkey(index)?)),
+			Descriptor::Multi(threshold, ref keys) => {
+				let keys = keys.iter().map(|key| key.public(index)).collect::<Result<Vec<_>, _>>()?;
+				Builder::build_multisig(threshold, &keys)
+			},
+			Descriptor::SortedMulti(threshold, ref keys) => {
+				let mut keys = keys.iter().map(|key| key.public(index)).collect::<Result<Vec<_>, _>>()?;
+				keys.sort_by(|a, b| (**a).cmp(&**b));
+				Builder::build_multisig(threshold, &keys)
+			},
+			Descriptor::Addr(ref address) => address.script(),
+			Descriptor::Raw(ref script) => script.clone().into(),
+		};
+
+		Ok(script)
+	}
+
+	/// Returns address of the output script. Some descriptors (pk(), bare multisig) have no address
+	pub fn address(&self, index: u32, network: keys::Network) -> Result<Option<DescriptorAddress>, DescriptorError> {
+		if let Descriptor::Addr(ref address) = *self {
+			if !address.is_valid_for(network) {
+				return Err(DescriptorError::InvalidAddress(address.to_string()));
+			}
+		}
+
+		self.script_pubkey(index).map(|script| DescriptorAddress::from_script(&script, network))
+	}
+
+	/// Returns descriptor checksum
+	pub fn checksum(&self) -> String {
+		descriptor_checksum(&self.to_string()).expect("descriptor is always displayed using valid characters; qed")
+	}
+}
+
+impl fmt::Display for Descriptor {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Descriptor::Pk(ref key) => write!(f, "pk({})", key),
+			Descriptor::Pkh(ref key) => write!(f, "pkh({})", key),
+			Descriptor::Wpkh(ref key) => write!(f, "wpkh({})", key),
+			Descriptor::Sh(ref inner) => write!(f, "sh({})", inner),
+			Descriptor::Wsh(ref inner) => write!(f, "wsh({})", inner),
+			Descriptor::Multi(threshold, ref keys) => write!(f, "multi({}{})", threshold, format_keys(keys)),
+			Descriptor::SortedMulti(threshold, ref keys) => write!(f, "sortedmulti({}{})", threshold, format_keys(keys)),
+			Descriptor::Addr(ref address) => write!(f, "addr({})", address),
+			Descriptor::Raw(ref script) => write!(f, "raw({:?})", script),
+		}
+	}
+}
+
+impl FromStr for Descriptor {
+	type Err = DescriptorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Descriptor::parse(s, false)
+	}
+}
+
+impl fmt::Display for KeyOrigin {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.fingerprint.fmt(f)?;
+		format_path(f, &self.path)
+	}
+}
+
+impl fmt::Display for DescriptorKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DescriptorKey::Single { ref origin, ref public } => {
+				if let Some(ref origin) = *origin {
+					write!(f, "[{}]", origin)?;
+				}
+				public.fmt(f)
+			},
+			DescriptorKey::Extended { ref origin, ref key, ref path, wildcard } => {
+				if let Some(ref origin) = *origin {
+					write!(f, "[{}]", origin)?;
+				}
+				key.fmt(f)?;
+				format_path(f, path)?;
+				match wildcard {
+					true => "/*".fmt(f),
+					false => Ok(()),
+				}
+			},
+		}
+	}
+}
+
+fn format_path(f: &mut fmt::Formatter, path: &[u32]) -> fmt::Result {
+	for step in path {
+		match *step >= HARDENED_INDEX {
+			true => write!(f, "/{}h", step - HARDENED_INDEX)?,
+			false => write!(f, "/{}", step)?,
+		}
+	}
+	Ok(())
+}
+
+fn format_keys(keys: &[DescriptorKey]) -> String {
+	keys.iter().map(|key| format!(",{}", key)).collect()
+}
+
+/// Computes descriptor checksum. Returns None if descriptor contains invalid characters
+pub fn descriptor_checksum(descriptor: &str) -> Option<String> {
+	fn polymod(c: u64, value: u64) -> u64 {
+		let top = c >> 35;
+		let mut c = (c & 0x7ffffffff) << 5 ^ value;
+		if top & 1 != 0 { c ^= 0xf5dee51989; }
+		if top & 2 != 0 { c ^= 0xa9fdca3312; }
+		if top & 4 != 0 { c ^= 0x1bab10e32d; }
+		if top & 8 != 0 { c ^= 0x3706b1677a; }
+		if top & 16 != 0 { c ^= 0x644d626ffd; }
+		c
+	}
+
+	let mut c = 1u64;
+	let mut class = 0u64;
+	let mut class_count = 0;
+	for ch in descriptor.chars() {
+		let position = INPUT_CHARSET.find(ch)? as u64;
+		// symbol position within its group of 32 characters
+		c = polymod(c, position & 31);
+		// group of the symbol, every 3 groups are combined into single symbol
+		class = class * 3 + (position >> 5);
+		class_count += 1;
+		if class_count == 3 {
+			c = polymod(c, class);
+			class = 0;
+			class_count = 0;
+		}
+	}
+
+	if class_count > 0 {
+		c = polymod(c, class);
+	}
+	for _ in 0..8 {
+		c = polymod(c, 0);
+	}
+	c ^= 1;
+
+	Some((0..8).map(|i| CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize] as char).collect())
+}
+
+fn parse_descriptor(s: &str, context: Context) -> Result<Descriptor, DescriptorError> {
+	let (name, args) = match (s.find('('), s.ends_with(')')) {
+		(Some(open), true) => (&s[..open], &s[open + 1..s.len() - 1]),
+		_ => return Err(DescriptorError::Syntax(format!("'{}' is not a valid descriptor function", s))),
+	};
+
+	match name {
+		"pk" => parse_key(args, context).map(Descriptor::Pk),
+		"pkh" => parse_key(args, context).map(Descriptor::Pkh),
+		"wpkh" => match context {
+			Context::Top | Context::P2SH => parse_key(args, Context::P2WPKH).map(Descriptor::Wpkh),
+			_ => Err(DescriptorError::InvalidContext("wpkh")),
+		},
+		"sh" => match context {
+			Context::Top => {
+				let inner = parse_descriptor(args, Context::P2SH)?;
+				match inner {
+					Descriptor::Multi(_, ref keys) | Descriptor::SortedMulti(_, ref keys) => {
+						let size = multisig_script_size(keys);
+						if size > MAX_REDEEM_SCRIPT_SIZE {
+							return Err(DescriptorError::ScriptTooLarge(size));
+						}
+					},
+					_ => (),
+				}
+				Ok(Descriptor::Sh(Box::new(inner)))
+			},
+			_ => Err(DescriptorError::InvalidContext("sh")),
+		},
+		"wsh" => match context {
+			Context::Top | Context::P2SH => parse_descriptor(args, Context::P2WSH).map(Box::new).map(Descriptor::Wsh),
+			_ => Err(DescriptorError::InvalidContext("wsh")),
+		},
+		"multi" => parse_multisig(args, context).map(|(threshold, keys)| Descriptor::Multi(threshold, keys)),
+		"sortedmulti" => parse_multisig(args, context).map(|(threshold, keys)| Descriptor::SortedMulti(threshold, keys)),
+		"addr" => match context {
+			Context::Top => args.parse().map(Descriptor::Addr),
+			_ => Err(DescriptorError::InvalidContext("addr")),
+		},
+		"raw" => match context {
+			Context::Top => args.parse().map(Descriptor::Raw)
+				.map_err(|_| DescriptorError::Syntax(format!("Raw script '{}' is not hex", args))),
+			_ => Err(DescriptorError::InvalidContext("raw")),
+		},
+		_ => Err(DescriptorError::Syntax(format!("'{}' is not a valid descriptor function", name))),
+	}
+}
+
+fn parse_multisig(args: &str, context: Context) -> Result<(usize, Vec<DescriptorKey>), DescriptorError> {
+	let mut args = args.split(',');
+	let threshold = args.next().expect("split always yields at least one item; qed");
+	let threshold: usize = threshold.parse()
+		.map_err(|_| DescriptorError::InvalidMultisig(format!("Multi threshold '{}' is not valid", threshold)))?;
+	let keys = args.map(|key| parse_key(key, context)).collect::<Result<Vec<_>, _>>()?;
+
+	if keys.is_empty() || keys.len() > MAX_MULTISIG_KEYS {
+		return Err(DescriptorError::InvalidMultisig(format!("Cannot have {} keys in multisig; must have between 1 and {} keys, inclusive", keys.len(), MAX_MULTISIG_KEYS)));
+	}
+	if threshold < 1 || threshold > keys.len() {
+		return Err(DescriptorError::InvalidMultisig(format!("Multisig threshold cannot be {}, must be at least 1 and at most {}", threshold, keys.len())));
+	}
+	if context == Context::Top && keys.len() > MAX_BARE_MULTISIG_KEYS {
+		return Err(DescriptorError::InvalidMultisig(format!("Cannot have {} keys in bare multisig; only at most {} keys", keys.len(), MAX_BARE_MULTISIG_KEYS)));
+	}
+
+	Ok((threshold, keys))
+}
+
+fn multisig_script_size(keys: &[DescriptorKey]) -> usize {
+	// threshold, number of keys and OP_CHECKMULTISIG opcodes + pushes of all keys
+	3 + keys.iter().map(|key| 1 + key.serialized_len()).sum::<usize>()
+}
+
+fn parse_key(s: &str, context: Context) -> Result<DescriptorKey, DescriptorError> {
+	let (origin, s) = match s.starts_with('[') {
+		true => {
+			let close = s.find(']').ok_or_else(|| DescriptorError::Syntax(format!("Key origin start '[' character without matching ']' in '{}'", s)))?;
+			(Some(parse_origin(&s[1..close])?), &s[close + 1..])
+		},
+		false => (None, s),
+	};
+
+	let mut steps = s.split('/');
+	let key = steps.next().expect("split always yields at least one item; qed");
+	let steps: Vec<&str> = steps.collect();
+
+	if let Ok(data) = key.parse::<Bytes>() {
+		let public = match data.len() {
+			33 | 65 => Public::from_slice(&data).expect("data length is checked above; qed"),
+			_ => return Err(DescriptorError::InvalidKey(key.into())),
+		};
+		if !public.is_valid() {
+			return Err(DescriptorError::InvalidKey(key.into()));
+		}
+		if !steps.is_empty() {
+			return Err(DescriptorError::Syntax(format!("Key '{}' can't be followed by derivation path", key)));
+		}
+
+		let key = DescriptorKey::Single {
+			origin: origin,
+			public: public,
+		};
+		return match (context, key.is_compressed()) {
+			(Context::P2WPKH, false) | (Context::P2WSH, false) => Err(DescriptorError::UncompressedKey),
+			_ => Ok(key),
+		};
+	}
+
+	if let Ok(extended) = key.parse::<ExtendedPublic>() {
+		let (path, wildcard) = match steps.last() {
+			Some(&"*") => (&steps[..steps.len() - 1], true),
+			Some(&"*'") | Some(&"*h") | Some(&"*H") => return Err(DescriptorError::HardenedDerivation),
+			_ => (&steps[..], false),
+		};
+
+		let path = path.iter().map(|step| parse_path_step(step)).collect::<Result<Vec<_>, _>>()?;
+		if path.iter().any(|step| *step >= HARDENED_INDEX) {
+			return Err(DescriptorError::HardenedDerivation);
+		}
+
+		return Ok(DescriptorKey::Extended {
+			origin: origin,
+			key: extended,
+			path: path,
+			wildcard: wildcard,
+		});
+	}
+
+	if key.parse::<Private>().is_ok() || key.starts_with("xprv") || key.starts_with("tprv") {
+		return Err(DescriptorError::PrivateKey);
+	}
+
+	Err(DescriptorError::InvalidKey(key.into()))
+}
+
+fn parse_origin(s: &str) -> Result<KeyOrigin, DescriptorError> {
+	let mut steps = s.split('/');
+	let fingerprint = steps.next().expect("split always yields at least one item; qed");
+	let fingerprint = match fingerprint.len() {
+		8 => fingerprint.parse().ok(),
+		_ => None,
+	}.ok_or_else(|| DescriptorError::Syntax(format!("Fingerprint '{}' is not hex", fingerprint)))?;
+
+	Ok(KeyOrigin {
+		fingerprint: fingerprint,
+		path: steps.map(parse_path_step).collect::<Result<_, _>>()?,
+	})
+}
+
+fn parse_path_step(step: &str) -> Result<u32, DescriptorError> {
+	let (index, hardened) = match step.chars().last() {
+		Some('\'') | Some('h') | Some('H') => (&step[..step.len() - 1], true),
+		_ => (step, false),
+	};
+
+	let index = match !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) {
+		true => index.parse::<u32>().ok().and_then(|index| if index < HARDENED_INDEX { Some(index) } else { None }),
+		false => None,
+	}.ok_or_else(|| DescriptorError::Syntax(format!("Key path value '{}' is not a valid uint31", step)))?;
+
+	match hardened {
+		true => Ok(index + HARDENED_INDEX),
+		false => Ok(index),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use keys::{self, ExtendedPublic, HARDENED_INDEX};
+	use super::{Descriptor, DescriptorAddress, DescriptorError, DescriptorKey, descriptor_checksum};
+
+	// BIP32 test vector 1, chain m/0H
+	const XPUB: &'static str = "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw";
+
+	fn script_hex(descriptor: &str, index: u32) -> String {
+		let descriptor: Descriptor = descriptor.parse().unwrap();
+		format!("{:?}", descriptor.script_pubkey(index).unwrap().to_bytes())
+	}
+
+	#[test]
+	fn test_descriptor_checksum() {
+		assert_eq!(descriptor_checksum("raw(deadbeef)"), Some("89f8spxm".into()));
+		assert_eq!(descriptor_checksum("raw(deadbeef)\u{1}"), None);
+		assert!(Descriptor::parse("raw(deadbeef)#89f8spxm", true).is_ok());
+		assert_eq!(Descriptor::parse("raw(deadbeef)", true), Err(DescriptorError::MissingChecksum));
+		assert_eq!(Descriptor::parse("raw(deadbeef)#89f8spxn", true), Err(DescriptorError::InvalidChecksum("89f8spxn".into(), "89f8spxm".into())));
+		assert!(Descriptor::parse("raw(deadbeef)#89f8spx", false).is_err());
+	}
+
+	#[test]
+	fn test_single_key_descriptors() {
+		assert_eq!(script_hex("pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)", 0),
+			"210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ac");
+		assert_eq!(script_hex("pkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)", 0),
+			"76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac");
+		assert_eq!(script_hex("wpkh(02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5)", 0),
+			"001406afd46bcdfd22ef94ac122aa11f241244a37ecc");
+		assert_eq!(script_hex("sh(wpkh(03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556))", 0),
+			"a914cc6ffbc0bf31af759451068f90ba7a0272b6b33287");
+	}
+
+	#[test]
+	fn test_multisig_descriptors() {
+		assert_eq!(script_hex("multi(1,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4,025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc)", 0),
+			"5121022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe421025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc52ae");
+		// keys are sorted
+		assert_eq!(script_hex("sortedmulti(1,025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4)", 0),
+			script_hex("multi(1,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4,025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc)", 0));
+
+		assert!("multi(0,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4)".parse::<Descriptor>().is_err());
+		assert!("multi(2,022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4)".parse::<Descriptor>().is_err());
+		let four_keys = format!("multi(1{})", ",022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4".repeat(4));
+		assert!(four_keys.parse::<Descriptor>().is_err());
+		assert!(format!("wsh({})", four_keys).parse::<Descriptor>().is_ok());
+		let sixteen_keys = format!("sh(multi(1{}))", ",022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4".repeat(16));
+		assert_eq!(sixteen_keys.parse::<Descriptor>(), Err(DescriptorError::ScriptTooLarge(547)));
+	}
+
+	#[test]
+	fn test_extended_key_descriptors() {
+		let descriptor: Descriptor = format!("pkh([d34db33f/44'/0h/0H]{}/1/*)", XPUB).parse().unwrap();
+		assert!(descriptor.is_range());
+		assert_eq!(descriptor.to_string(), format!("pkh([d34db33f/44h/0h/0h]{}/1/*)", XPUB));
+
+		let expected = ExtendedPublic::from(XPUB).derive(1).unwrap().derive(5).unwrap().public;
+		match descriptor {
+			Descriptor::Pkh(ref key @ DescriptorKey::Extended { .. }) => assert_eq!(key.public(5).unwrap(), expected),
+			_ => panic!("pkh() with extended key is expected"),
+		}
+		assert_eq!(descriptor.address(5, keys::Network::Mainnet).unwrap().unwrap().to_string(),
+			keys::Address { kind: keys::Type::P2PKH, network: keys::Network::Mainnet, hash: expected.address_hash() }.to_string());
+
+		assert_eq!(format!("pkh({}/1h/*)", XPUB).parse::<Descriptor>(), Err(DescriptorError::HardenedDerivation));
+		assert_eq!(format!("pkh({}/1/*')", XPUB).parse::<Descriptor>(), Err(DescriptorError::HardenedDerivation));
+		assert_eq!(format!("pkh({}/{})", XPUB, HARDENED_INDEX).parse::<Descriptor>(),
+			Err(DescriptorError::Syntax(format!("Key path value '{}' is not a valid uint31", HARDENED_INDEX))));
+	}
+
+	#[test]
+	fn test_descriptor_context() {
+		assert_eq!("wsh(wpkh(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9))".parse::<Descriptor>(),
+			Err(DescriptorError::InvalidContext("wpkh")));
+		assert_eq!("sh(sh(pk(02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9)))".parse::<Descriptor>(),
+			Err(DescriptorError::InvalidContext("sh")));
+		assert_eq!("sh(raw(deadbeef))".parse::<Descriptor>(), Err(DescriptorError::InvalidContext("raw")));
+		assert_eq!("wpkh(04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235)".parse::<Descriptor>(),
+			Err(DescriptorError::UncompressedKey));
+		assert!("pkh(04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235)".parse::<Descriptor>().is_ok());
+		assert_eq!("pkh(5KSCKP8NUyBZPCCQusxRwgmz9sfvJQEgbGukmmHepWw5Bzp95mu)".parse::<Descriptor>(), Err(DescriptorError::PrivateKey));
+	}
+
+	#[test]
+	fn test_addr_and_raw_descriptors() {
+		let descriptor: Descriptor = "addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)".parse().unwrap();
+		assert!(!descriptor.is_solvable());
+		assert_eq!(format!("{:?}", descriptor.script_pubkey(0).unwrap().to_bytes()), "0014751e76e8199196d454941c45d1b3a323f1433bd6");
+		assert_eq!(descriptor.address(0, keys::Network::Mainnet).unwrap().unwrap().to_string(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+
+		let descriptor: Descriptor = "raw(76a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac)".parse().unwrap();
+		let expected = keys::Address { kind: keys::Type::P2PKH, network: keys::Network::Mainnet, hash: "06afd46bcdfd22ef94ac122aa11f241244a37ecc".into() };
+		assert_eq!(descriptor.address(0, keys::Network::Mainnet), Ok(Some(DescriptorAddress::Legacy(expected))));
+
+		let descriptor: Descriptor = "pk(0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798)".parse().unwrap();
+		assert_eq!(descriptor.address(0, keys::Network::Mainnet), Ok(None));
+	}
+
+	#[test]
+	fn test_addr_descriptor_network() {
+		let descriptor: Descriptor = "addr(bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4)".parse().unwrap();
+		assert_eq!(descriptor.address(0, keys::Network::Testnet),
+			Err(DescriptorError::InvalidAddress("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".into())));
+
+		// regtest shares base58 prefixes with testnet
+		let descriptor: Descriptor = "addr(mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB)".parse().unwrap();
+		assert_eq!(descriptor.address(0, keys::Network::Regtest).unwrap().unwrap().to_string(), "mpLQjfK79b7CCV4VMJWEWAj5Mpx8Up5zxB");
+		assert!(descriptor.address(0, keys::Network::Mainnet).is_err());
+
+		// but not bech32 prefix
+		let descriptor: Descriptor = "addr(tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7)".parse().unwrap();
+		assert!(descriptor.address(0, keys::Network::Regtest).is_err());
+	}
+}
@@ -1,7 +1,7 @@
 //! Script interpreter verification flags
 
 /// Script interpreter verification flags
-#[derive(Default, Debug, PartialEq)]
+#[derive(Default, Debug, PartialEq, Eq, Hash, Clone)]
 pub struct VerificationFlags {
 	pub none: bool,
 
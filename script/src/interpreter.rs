@@ -5,6 +5,7 @@ use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
 use crypto::{sha1, sha256, dhash160, dhash256, ripemd160};
 use sign::{SignatureVersion, Sighash};
 use script::MAX_SCRIPT_ELEMENT_SIZE;
+use trace::{ScriptTracer, ScriptStage, TraceStep, NoopTracer};
 use {
 	script, Builder, Script, ScriptWitness, Num, VerificationFlags, Opcode, Error, SignatureChecker, Stack
 };
@@ -265,6 +266,19 @@ pub fn verify_script(
 	flags: &VerificationFlags,
 	checker: &dyn SignatureChecker,
 	version: SignatureVersion,
+) -> Result<(), Error> {
+	verify_script_traced(script_sig, script_pubkey, witness, flags, checker, version, &mut NoopTracer)
+}
+
+/// Verifies script signature and pubkey, reporting every evaluated opcode to the tracer
+pub fn verify_script_traced<T: ScriptTracer + ?Sized>(
+	script_sig: &Script,
+	script_pubkey: &Script,
+	witness: &ScriptWitness,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	tracer: &mut T,
 ) -> Result<(), Error> {
 	if flags.verify_sigpushonly && !script_sig.is_push_only() {
 		return Err(Error::SignaturePushOnly);
@@ -274,13 +288,13 @@ pub fn verify_script(
 	let mut stack_copy = Stack::new();
 	let mut had_witness = false;
 
-	eval_script(&mut stack, script_sig, flags, checker, version)?;
+	eval_stage(ScriptStage::ScriptSig, &mut stack, script_sig, flags, checker, version, tracer)?;
 
 	if flags.verify_p2sh {
 		stack_copy = stack.clone();
 	}
 
-	let res = eval_script(&mut stack, script_pubkey, flags, checker, version)?;
+	let res = eval_stage(ScriptStage::ScriptPubKey, &mut stack, script_pubkey, flags, checker, version, tracer)?;
 	if !res {
 		return Err(Error::EvalFalse);
 	}
@@ -295,7 +309,7 @@ pub fn verify_script(
 
 			had_witness = true;
 			verify_cleanstack = false;
-			if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+			if !verify_witness_program(witness, witness_version, witness_program, flags, checker, tracer)? {
 				return Err(Error::EvalFalse);
 			}
 		}
@@ -316,7 +330,7 @@ pub fn verify_script(
 
 		let pubkey2: Script = stack.pop()?.into();
 
-		let res = eval_script(&mut stack, &pubkey2, flags, checker, version)?;
+		let res = eval_stage(ScriptStage::RedeemScript, &mut stack, &pubkey2, flags, checker, version, tracer)?;
 		if !res {
 			return Err(Error::EvalFalse);
 		}
@@ -329,7 +343,7 @@ pub fn verify_script(
 
 				had_witness = true;
 				verify_cleanstack = false;
-				if !verify_witness_program(witness, witness_version, witness_program, flags, checker)? {
+				if !verify_witness_program(witness, witness_version, witness_program, flags, checker, tracer)? {
 					return Err(Error::EvalFalse);
 				}
 			}
@@ -361,12 +375,13 @@ pub fn verify_script(
 	Ok(())
 }
 
-fn verify_witness_program(
+fn verify_witness_program<T: ScriptTracer + ?Sized>(
 	witness: &ScriptWitness,
 	witness_version: u8,
 	witness_program: &[u8],
 	flags: &VerificationFlags,
 	checker: &dyn SignatureChecker,
+	tracer: &mut T,
 ) -> Result<bool, Error> {
 	if witness_version != 0 {
 		if flags.verify_discourage_upgradable_witness_program {
@@ -416,7 +431,7 @@ fn verify_witness_program(
 		return Err(Error::PushSize);
 	}
 
-	if !eval_script(&mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0)? {
+	if !eval_stage(ScriptStage::WitnessScript, &mut stack, &script_pubkey, flags, checker, SignatureVersion::WitnessV0, tracer)? {
 		return Ok(false);
 	}
 
@@ -428,14 +443,42 @@ fn verify_witness_program(
 	Ok(success)
 }
 
+/// Evaluates one of the scripts of `verify_script`, notifying tracer about the script start and finish
+fn eval_stage<T: ScriptTracer + ?Sized>(
+	stage: ScriptStage,
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	tracer: &mut T,
+) -> Result<bool, Error> {
+	tracer.script_started(stage, script);
+	let result = eval_script_traced(stack, script, flags, checker, version, tracer);
+	tracer.script_finished(stack, &result);
+	result
+}
+
 /// Evaluautes the script
-#[cfg_attr(feature="cargo-clippy", allow(match_same_arms))]
 pub fn eval_script(
 	stack: &mut Stack<Bytes>,
 	script: &Script,
 	flags: &VerificationFlags,
 	checker: &dyn SignatureChecker,
 	version: SignatureVersion
+) -> Result<bool, Error> {
+	eval_script_traced(stack, script, flags, checker, version, &mut NoopTracer)
+}
+
+/// Evaluates the script, calling the tracer before every opcode
+#[cfg_attr(feature="cargo-clippy", allow(match_same_arms))]
+pub fn eval_script_traced<T: ScriptTracer + ?Sized>(
+	stack: &mut Stack<Bytes>,
+	script: &Script,
+	flags: &VerificationFlags,
+	checker: &dyn SignatureChecker,
+	version: SignatureVersion,
+	tracer: &mut T,
 ) -> Result<bool, Error> {
 	if script.len() > script::MAX_SCRIPT_SIZE {
 		return Err(Error::ScriptSize);
@@ -459,6 +502,16 @@ pub fn eval_script(
 		};
 		let opcode = instruction.opcode;
 
+		tracer.step(&TraceStep {
+			pc: pc,
+			opcode: opcode,
+			data: instruction.data,
+			executing: executing,
+			stack: stack,
+			altstack: &altstack,
+			exec_stack: &exec_stack,
+		});
+
 		if let Some(data) = instruction.data {
 			if data.len() > script::MAX_SCRIPT_ELEMENT_SIZE {
 				return Err(Error::PushSize);
@@ -2399,7 +2452,7 @@ mod tests {
 			lock_time: 0,
 		};
 
-		let checker = TransactionSignatureChecker {
+		let checker: TransactionSignatureChecker = TransactionSignatureChecker {
 			input_index: 0,
 			input_amount: amount,
 			signer: tx2.into(),
@@ -2829,7 +2882,7 @@ mod tests {
 	}
 
 	fn run_witness_test_tx_test(script_pubkey: Script, tx: &Transaction, flags: &VerificationFlags, amount: u64, index: usize) -> Result<(), Error> {
-		let checker = TransactionSignatureChecker {
+		let checker: TransactionSignatureChecker = TransactionSignatureChecker {
 			input_index: index,
 			input_amount: amount,
 			signer: tx.clone().into(),
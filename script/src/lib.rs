@@ -6,26 +6,34 @@ extern crate primitives;
 extern crate serialization as ser;
 
 mod builder;
+mod descriptor;
 mod error;
 mod flags;
 mod interpreter;
+mod miniscript;
 mod num;
 mod opcode;
 mod script;
 mod sign;
+mod signed_message;
 mod stack;
+mod trace;
 mod verify;
 
 pub use primitives::{bytes, hash};
 
 pub use self::builder::Builder;
+pub use self::descriptor::{Descriptor, DescriptorKey, DescriptorAddress, DescriptorError, KeyOrigin, descriptor_checksum};
 pub use self::error::Error;
 pub use self::flags::VerificationFlags;
-pub use self::interpreter::{eval_script, verify_script};
+pub use self::interpreter::{eval_script, eval_script_traced, verify_script, verify_script_traced};
+pub use self::miniscript::{Miniscript, MiniscriptError, Satisfier, Type as MiniscriptType, BaseType as MiniscriptBaseType};
 pub use self::opcode::Opcode;
 pub use self::num::Num;
 pub use self::script::{Script, ScriptType, ScriptAddress, ScriptWitness, is_witness_commitment_script};
 pub use self::sign::{TransactionInputSigner, UnsignedTransactionInput, SignatureVersion};
+pub use self::signed_message::{bip322_message_hash, bip322_to_spend, bip322_to_sign, bip322_sign_p2wpkh, bip322_verify_simple};
 pub use self::stack::Stack;
+pub use self::trace::{ScriptTracer, ScriptStage, TraceStep, NoopTracer};
 pub use self::verify::{SignatureChecker, NoopSignatureChecker, TransactionSignatureChecker};
 
@@ -0,0 +1,1454 @@
+//! Miniscript for P2WSH scripts
+//!
+//! Miniscript is a structured representation of the subset of scripts, eg:
+//! `or_d(pk(KEY_A),and_v(v:pk(KEY_B),older(1008)))`. Every fragment has a type, which guarantees
+//! that the composition of fragments is a correct script. Satisfactions of every fragment are
+//! known, so the witness could be built from available signatures, hash preimages and timelocks.
+//!
+//! See http://bitcoin.sipa.be/miniscript/ for the fragments, types and satisfactions reference.
+
+use std::fmt;
+use std::str::FromStr;
+use bytes::Bytes;
+use hash::{H160, H256};
+use chain::constants::{
+	SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_TYPE_FLAG, SEQUENCE_LOCKTIME_MASK, LOCKTIME_THRESHOLD
+};
+use crypto::{sha256, dhash256, ripemd160, dhash160};
+use keys::Public;
+use {Builder, Opcode, Script, ScriptWitness};
+
+/// Maximal size of the standard P2WSH witness script
+pub const MAX_WITNESS_SCRIPT_SIZE: usize = 3600;
+/// Maximal number of keys in multi() fragment
+pub const MAX_MULTI_KEYS: usize = 20;
+
+/// Miniscript parsing or type checking error
+#[derive(Debug, PartialEq)]
+pub enum MiniscriptError {
+	/// Expression is malformed
+	Syntax(String),
+	/// Key expression is invalid
+	InvalidKey(String),
+	/// Uncompressed key is used in the witness script
+	UncompressedKey,
+	/// Hash is not a hex string of expected length
+	InvalidHash(String),
+	/// Timelock is zero or too large
+	InvalidTimelock(String),
+	/// Invalid threshold or number of subexpressions
+	InvalidThreshold(String),
+	/// Subexpression has type, which is not allowed at this position
+	Type(String),
+	/// Witness script is too large
+	ScriptTooLarge(usize),
+	/// Expression is valid, but is not safe to use: it is malleable, could be satisfied
+	/// without signatures, mixes timelocks or reuses keys
+	NotSane(String),
+}
+
+impl fmt::Display for MiniscriptError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MiniscriptError::Syntax(ref msg) => msg.fmt(f),
+			MiniscriptError::InvalidKey(ref key) => write!(f, "Key '{}' is not valid", key),
+			MiniscriptError::UncompressedKey => "Uncompressed keys are not allowed in witness scripts".fmt(f),
+			MiniscriptError::InvalidHash(ref hash) => write!(f, "Hash '{}' is not valid", hash),
+			MiniscriptError::InvalidTimelock(ref timelock) => write!(f, "Timelock '{}' is not valid", timelock),
+			MiniscriptError::InvalidThreshold(ref msg) => msg.fmt(f),
+			MiniscriptError::Type(ref msg) => msg.fmt(f),
+			MiniscriptError::ScriptTooLarge(size) =>
+				write!(f, "Witness script is too large, {} bytes is larger than {} bytes", size, MAX_WITNESS_SCRIPT_SIZE),
+			MiniscriptError::NotSane(ref msg) => msg.fmt(f),
+		}
+	}
+}
+
+/// Basic type of the fragment
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BaseType {
+	/// Base: consumes its inputs, pushes nonzero on satisfaction and zero on dissatisfaction
+	B,
+	/// Verify: consumes its inputs, pushes nothing, can't be dissatisfied
+	V,
+	/// Key: consumes its inputs, pushes public key for the signature check
+	K,
+	/// Wrapped: takes its inputs from under the top stack element, works like B
+	W,
+}
+
+/// Type of the fragment: basic type and type properties
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Type {
+	/// Basic type
+	pub base: BaseType,
+	/// `z`: always consumes exactly 0 stack elements
+	pub zero_arg: bool,
+	/// `o`: always consumes exactly 1 stack element
+	pub one_arg: bool,
+	/// `n`: top input stack element is never zero on satisfaction
+	pub nonzero: bool,
+	/// `d`: has dissatisfaction, which doesn't require signatures
+	pub dissatisfiable: bool,
+	/// `u`: pushes exactly 1 on satisfaction
+	pub unit: bool,
+	/// `e`: has unique unconditional dissatisfaction, all conditional dissatisfactions require signatures
+	pub expressive: bool,
+	/// `f`: dissatisfactions (if any) always require signatures
+	pub forced: bool,
+	/// `s`: satisfactions always require signatures
+	pub safe: bool,
+	/// `m`: non-malleable satisfaction always exists
+	pub nonmalleable: bool,
+	/// `g`: contains relative time timelock
+	pub relative_time: bool,
+	/// `h`: contains relative height timelock
+	pub relative_height: bool,
+	/// `i`: contains absolute time timelock
+	pub absolute_time: bool,
+	/// `j`: contains absolute height timelock
+	pub absolute_height: bool,
+	/// `k`: no satisfaction requires both height and time timelocks of the same kind
+	pub no_timelock_mix: bool,
+}
+
+impl Type {
+	fn new(base: BaseType, properties: &str) -> Self {
+		Type {
+			base: base,
+			zero_arg: properties.contains('z'),
+			one_arg: properties.contains('o'),
+			nonzero: properties.contains('n'),
+			dissatisfiable: properties.contains('d'),
+			unit: properties.contains('u'),
+			expressive: properties.contains('e'),
+			forced: properties.contains('f'),
+			safe: properties.contains('s'),
+			nonmalleable: properties.contains('m'),
+			relative_time: properties.contains('g'),
+			relative_height: properties.contains('h'),
+			absolute_time: properties.contains('i'),
+			absolute_height: properties.contains('j'),
+			no_timelock_mix: properties.contains('k'),
+		}
+	}
+
+	/// Type with timelock properties of all subexpressions. `k` is lost if any of subexpressions
+	/// mixes timelocks or if `mixed` is set (subexpressions are satisfied together and mix timelocks).
+	fn timelocks(subs: &[&Type], mixed: bool) -> Self {
+		Type {
+			relative_time: subs.iter().any(|ty| ty.relative_time),
+			relative_height: subs.iter().any(|ty| ty.relative_height),
+			absolute_time: subs.iter().any(|ty| ty.absolute_time),
+			absolute_height: subs.iter().any(|ty| ty.absolute_height),
+			no_timelock_mix: !mixed && subs.iter().all(|ty| ty.no_timelock_mix),
+			..Type::new(BaseType::B, "")
+		}
+	}
+
+	/// Returns true if satisfying both fragments requires height and time timelocks of the same kind
+	fn mixes_timelocks(&self, other: &Type) -> bool {
+		(self.relative_time && other.relative_height) || (self.relative_height && other.relative_time) ||
+			(self.absolute_time && other.absolute_height) || (self.absolute_height && other.absolute_time)
+	}
+}
+
+impl fmt::Display for Type {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?}", self.base)?;
+		let properties = [
+			(self.zero_arg, 'z'), (self.one_arg, 'o'), (self.nonzero, 'n'), (self.dissatisfiable, 'd'), (self.unit, 'u'),
+			(self.expressive, 'e'), (self.forced, 'f'), (self.safe, 's'), (self.nonmalleable, 'm'),
+			(self.relative_time, 'g'), (self.relative_height, 'h'), (self.absolute_time, 'i'), (self.absolute_height, 'j'),
+			(self.no_timelock_mix, 'k'),
+		];
+		for &(set, property) in properties.iter() {
+			if set {
+				write!(f, "{}", property)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Miniscript fragment
+#[derive(Debug, PartialEq, Clone)]
+pub enum Miniscript {
+	/// `0`
+	False,
+	/// `1`
+	True,
+	/// `pk_k(KEY)`: `<KEY>`
+	PkK(Public),
+	/// `pk_h(KEY)`: `DUP HASH160 <HASH160(KEY)> EQUALVERIFY`
+	PkH(Public),
+	/// `older(n)`: `<n> CHECKSEQUENCEVERIFY`
+	Older(u32),
+	/// `after(n)`: `<n> CHECKLOCKTIMEVERIFY`
+	After(u32),
+	/// `sha256(h)`: `SIZE <32> EQUALVERIFY SHA256 <h> EQUAL`
+	Sha256(H256),
+	/// `hash256(h)`: `SIZE <32> EQUALVERIFY HASH256 <h> EQUAL`
+	Hash256(H256),
+	/// `ripemd160(h)`: `SIZE <32> EQUALVERIFY RIPEMD160 <h> EQUAL`
+	Ripemd160(H160),
+	/// `hash160(h)`: `SIZE <32> EQUALVERIFY HASH160 <h> EQUAL`
+	Hash160(H160),
+	/// `andor(X,Y,Z)`: `[X] NOTIF [Z] ELSE [Y] ENDIF`
+	AndOr(Box<Miniscript>, Box<Miniscript>, Box<Miniscript>),
+	/// `and_v(X,Y)`: `[X] [Y]`
+	AndV(Box<Miniscript>, Box<Miniscript>),
+	/// `and_b(X,Y)`: `[X] [Y] BOOLAND`
+	AndB(Box<Miniscript>, Box<Miniscript>),
+	/// `or_b(X,Z)`: `[X] [Z] BOOLOR`
+	OrB(Box<Miniscript>, Box<Miniscript>),
+	/// `or_c(X,Z)`: `[X] NOTIF [Z] ENDIF`
+	OrC(Box<Miniscript>, Box<Miniscript>),
+	/// `or_d(X,Z)`: `[X] IFDUP NOTIF [Z] ENDIF`
+	OrD(Box<Miniscript>, Box<Miniscript>),
+	/// `or_i(X,Z)`: `IF [X] ELSE [Z] ENDIF`
+	OrI(Box<Miniscript>, Box<Miniscript>),
+	/// `thresh(k,X_1,...,X_n)`: `[X_1] [X_2] ADD ... [X_n] ADD <k> EQUAL`
+	Thresh(usize, Vec<Miniscript>),
+	/// `multi(k,KEY_1,...,KEY_n)`: `<k> <KEY_1> ... <KEY_n> <n> CHECKMULTISIG`
+	Multi(usize, Vec<Public>),
+	/// `a:X`: `TOALTSTACK [X] FROMALTSTACK`
+	Alt(Box<Miniscript>),
+	/// `s:X`: `SWAP [X]`
+	Swap(Box<Miniscript>),
+	/// `c:X`: `[X] CHECKSIG`
+	Check(Box<Miniscript>),
+	/// `d:X`: `DUP IF [X] ENDIF`
+	DupIf(Box<Miniscript>),
+	/// `v:X`: `[X] VERIFY`, or VERIFY version of the last opcode of X
+	Verify(Box<Miniscript>),
+	/// `j:X`: `SIZE 0NOTEQUAL IF [X] ENDIF`
+	NonZero(Box<Miniscript>),
+	/// `n:X`: `[X] 0NOTEQUAL`
+	ZeroNotEqual(Box<Miniscript>),
+}
+
+/// Signatures, hash preimages and timelocks, available to satisfy the miniscript
+#[derive(Debug, Default, Clone)]
+pub struct Satisfier {
+	signatures: Vec<(Public, Bytes)>,
+	preimages: Vec<Bytes>,
+	lock_time: u32,
+	sequence: u32,
+}
+
+impl Satisfier {
+	/// Adds signature of the key, including the sighash type byte
+	pub fn signature(mut self, public: Public, signature: Bytes) -> Self {
+		self.signatures.push((public, signature));
+		self
+	}
+
+	/// Adds 32-byte preimage of the hash
+	pub fn preimage(mut self, preimage: Bytes) -> Self {
+		self.preimages.push(preimage);
+		self
+	}
+
+	/// Sets lock time of the spending transaction
+	pub fn lock_time(mut self, lock_time: u32) -> Self {
+		self.lock_time = lock_time;
+		self
+	}
+
+	/// Sets sequence of the spending input
+	pub fn sequence(mut self, sequence: u32) -> Self {
+		self.sequence = sequence;
+		self
+	}
+
+	fn find_signature(&self, public: &Public) -> Option<Bytes> {
+		self.signatures.iter()
+			.find(|&&(ref key, _)| key == public)
+			.map(|&(_, ref signature)| signature.clone())
+	}
+
+	fn find_preimage<F>(&self, matches: F) -> Option<Bytes> where F: Fn(&[u8]) -> bool {
+		self.preimages.iter()
+			.find(|preimage| preimage.len() == 32 && matches(preimage))
+			.cloned()
+	}
+
+	fn check_older(&self, n: u32) -> bool {
+		// same rules as in CHECKSEQUENCEVERIFY
+		if self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+			return false;
+		}
+
+		let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+		let (sequence, n) = (self.sequence & mask, n & mask);
+		(sequence < SEQUENCE_LOCKTIME_TYPE_FLAG) == (n < SEQUENCE_LOCKTIME_TYPE_FLAG) && n <= sequence
+	}
+
+	fn check_after(&self, n: u32) -> bool {
+		// same rules as in CHECKLOCKTIMEVERIFY
+		(self.lock_time < LOCKTIME_THRESHOLD) == (n < LOCKTIME_THRESHOLD) && n <= self.lock_time
+	}
+}
+
+/// Satisfaction and dissatisfaction of the fragment. None if it's not available
+struct Satisfactions {
+	sat: Option<ScriptWitness>,
+	dissat: Option<ScriptWitness>,
+}
+
+impl Satisfactions {
+	fn new(sat: Option<ScriptWitness>, dissat: Option<ScriptWitness>) -> Self {
+		Satisfactions {
+			sat: sat,
+			dissat: dissat,
+		}
+	}
+}
+
+/// Concatenates witnesses. Elements of `bottom` are placed deeper on the stack
+fn concat(bottom: &Option<ScriptWitness>, top: &Option<ScriptWitness>) -> Option<ScriptWitness> {
+	match (bottom.as_ref(), top.as_ref()) {
+		(Some(bottom), Some(top)) => Some(bottom.iter().chain(top.iter()).cloned().collect()),
+		_ => None,
+	}
+}
+
+fn witness_size(witness: &ScriptWitness) -> usize {
+	witness.iter().map(|item| 1 + item.len()).sum()
+}
+
+/// Returns the smallest of available witnesses
+fn cheapest(witnesses: Vec<Option<ScriptWitness>>) -> Option<ScriptWitness> {
+	witnesses.into_iter()
+		.filter_map(|witness| witness)
+		.min_by_key(witness_size)
+}
+
+impl Miniscript {
+	/// Parses and type checks miniscript. Top level expression must have `B` type and must be sane:
+	/// every satisfaction requires a signature, non-malleable satisfaction exists, height and time
+	/// timelocks are never mixed and keys are not reused
+	pub fn parse(s: &str) -> Result<Self, MiniscriptError> {
+		let (miniscript, ty) = Miniscript::parse_with_type(s)?;
+		if !ty.safe {
+			return Err(MiniscriptError::NotSane("Miniscript could be satisfied without a signature".into()));
+		}
+		if !ty.nonmalleable {
+			return Err(MiniscriptError::NotSane("Miniscript has no non-malleable satisfaction".into()));
+		}
+		if !ty.no_timelock_mix {
+			return Err(MiniscriptError::NotSane("Miniscript mixes height and time timelocks".into()));
+		}
+
+		let mut keys = miniscript.keys();
+		let keys_len = keys.len();
+		keys.sort_by(|a, b| a[..].cmp(&b[..]));
+		keys.dedup();
+		if keys.len() != keys_len {
+			return Err(MiniscriptError::NotSane("Miniscript contains duplicate keys".into()));
+		}
+
+		Ok(miniscript)
+	}
+
+	/// Parses and type checks miniscript, which is not required to be sane. Top level expression must have `B` type
+	pub fn parse_insane(s: &str) -> Result<Self, MiniscriptError> {
+		Miniscript::parse_with_type(s).map(|(miniscript, _)| miniscript)
+	}
+
+	fn parse_with_type(s: &str) -> Result<(Self, Type), MiniscriptError> {
+		let miniscript = parse_fragment(s)?;
+		let ty = miniscript.ty()?;
+		if ty.base != BaseType::B {
+			return Err(MiniscriptError::Type(format!("Top level expression must be B, not {}", ty)));
+		}
+
+		let size = miniscript.script().len();
+		if size > MAX_WITNESS_SCRIPT_SIZE {
+			return Err(MiniscriptError::ScriptTooLarge(size));
+		}
+
+		Ok((miniscript, ty))
+	}
+
+	/// Returns all keys of the fragment and its subexpressions
+	pub fn keys(&self) -> Vec<&Public> {
+		match *self {
+			Miniscript::False | Miniscript::True | Miniscript::Older(_) | Miniscript::After(_) |
+			Miniscript::Sha256(_) | Miniscript::Hash256(_) | Miniscript::Ripemd160(_) | Miniscript::Hash160(_) => Vec::new(),
+			Miniscript::PkK(ref key) | Miniscript::PkH(ref key) => vec![key],
+			Miniscript::Multi(_, ref keys) => keys.iter().collect(),
+			Miniscript::AndOr(ref x, ref y, ref z) => x.keys().into_iter().chain(y.keys()).chain(z.keys()).collect(),
+			Miniscript::AndV(ref x, ref y) | Miniscript::AndB(ref x, ref y) | Miniscript::OrB(ref x, ref y) |
+			Miniscript::OrC(ref x, ref y) | Miniscript::OrD(ref x, ref y) | Miniscript::OrI(ref x, ref y) =>
+				x.keys().into_iter().chain(y.keys()).collect(),
+			Miniscript::Thresh(_, ref subs) => subs.iter().flat_map(|sub| sub.keys()).collect(),
+			Miniscript::Alt(ref x) | Miniscript::Swap(ref x) | Miniscript::Check(ref x) | Miniscript::DupIf(ref x) |
+			Miniscript::Verify(ref x) | Miniscript::NonZero(ref x) | Miniscript::ZeroNotEqual(ref x) => x.keys(),
+		}
+	}
+
+	/// Computes type of the fragment, checking types of all subexpressions
+	pub fn ty(&self) -> Result<Type, MiniscriptError> {
+		use self::BaseType::*;
+
+		let ty = match *self {
+			Miniscript::False => Type::new(B, "zudemsk"),
+			Miniscript::True => Type::new(B, "zufmk"),
+			Miniscript::PkK(_) => Type::new(K, "ondusemk"),
+			Miniscript::PkH(_) => Type::new(K, "ndusemk"),
+			Miniscript::Older(n) => match n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+				true => Type::new(B, "zfmgk"),
+				false => Type::new(B, "zfmhk"),
+			},
+			Miniscript::After(n) => match n >= LOCKTIME_THRESHOLD {
+				true => Type::new(B, "zfmik"),
+				false => Type::new(B, "zfmjk"),
+			},
+			Miniscript::Sha256(_) | Miniscript::Hash256(_) | Miniscript::Ripemd160(_) | Miniscript::Hash160(_) =>
+				Type::new(B, "ondumk"),
+			Miniscript::AndOr(ref x, ref y, ref z) => {
+				let (x, y, z) = (x.ty()?, y.ty()?, z.ty()?);
+				require(x.base == B && x.dissatisfiable && x.unit, "andor", "X must be Bdu")?;
+				require(y.base == z.base && y.base != W, "andor", "Y and Z must be both B, K or V")?;
+				Type {
+					base: y.base,
+					zero_arg: x.zero_arg && y.zero_arg && z.zero_arg,
+					one_arg: (x.zero_arg && y.one_arg && z.one_arg) || (x.one_arg && y.zero_arg && z.zero_arg),
+					nonzero: false,
+					dissatisfiable: z.dissatisfiable,
+					unit: y.unit && z.unit,
+					expressive: z.expressive && (x.safe || y.forced),
+					forced: z.forced && (x.safe || y.forced),
+					safe: z.safe && (x.safe || y.safe),
+					nonmalleable: x.nonmalleable && y.nonmalleable && z.nonmalleable && x.expressive && (x.safe || y.safe || z.safe),
+					..Type::timelocks(&[&x, &y, &z], x.mixes_timelocks(&y))
+				}
+			},
+			Miniscript::AndV(ref x, ref y) => {
+				let (x, y) = (x.ty()?, y.ty()?);
+				require(x.base == V, "and_v", "X must be V")?;
+				require(y.base != W, "and_v", "Y must be B, K or V")?;
+				Type {
+					base: y.base,
+					zero_arg: x.zero_arg && y.zero_arg,
+					one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+					nonzero: x.nonzero || (x.zero_arg && y.nonzero),
+					dissatisfiable: false,
+					unit: y.unit,
+					expressive: false,
+					forced: y.forced || x.safe,
+					safe: x.safe || y.safe,
+					nonmalleable: x.nonmalleable && y.nonmalleable,
+					..Type::timelocks(&[&x, &y], x.mixes_timelocks(&y))
+				}
+			},
+			Miniscript::AndB(ref x, ref y) => {
+				let (x, y) = (x.ty()?, y.ty()?);
+				require(x.base == B, "and_b", "X must be B")?;
+				require(y.base == W, "and_b", "Y must be W")?;
+				Type {
+					base: B,
+					zero_arg: x.zero_arg && y.zero_arg,
+					one_arg: (x.zero_arg && y.one_arg) || (x.one_arg && y.zero_arg),
+					nonzero: x.nonzero || (x.zero_arg && y.nonzero),
+					dissatisfiable: x.dissatisfiable && y.dissatisfiable,
+					unit: true,
+					expressive: x.expressive && y.expressive && x.safe && y.safe,
+					forced: (x.forced && y.forced) || (x.safe && x.forced) || (y.safe && y.forced),
+					safe: x.safe || y.safe,
+					nonmalleable: x.nonmalleable && y.nonmalleable,
+					..Type::timelocks(&[&x, &y], x.mixes_timelocks(&y))
+				}
+			},
+			Miniscript::OrB(ref x, ref z) => {
+				let (x, z) = (x.ty()?, z.ty()?);
+				require(x.base == B && x.dissatisfiable, "or_b", "X must be Bd")?;
+				require(z.base == W && z.dissatisfiable, "or_b", "Z must be Wd")?;
+				Type {
+					base: B,
+					zero_arg: x.zero_arg && z.zero_arg,
+					one_arg: (x.zero_arg && z.one_arg) || (x.one_arg && z.zero_arg),
+					nonzero: false,
+					dissatisfiable: true,
+					unit: true,
+					expressive: x.expressive && z.expressive,
+					forced: false,
+					safe: x.safe && z.safe,
+					nonmalleable: x.nonmalleable && z.nonmalleable && x.expressive && z.expressive && (x.safe || z.safe),
+					..Type::timelocks(&[&x, &z], false)
+				}
+			},
+			Miniscript::OrC(ref x, ref z) => {
+				let (x, z) = (x.ty()?, z.ty()?);
+				require(x.base == B && x.dissatisfiable && x.unit, "or_c", "X must be Bdu")?;
+				require(z.base == V, "or_c", "Z must be V")?;
+				Type {
+					base: V,
+					zero_arg: x.zero_arg && z.zero_arg,
+					one_arg: x.one_arg && z.zero_arg,
+					nonzero: false,
+					dissatisfiable: false,
+					unit: false,
+					expressive: false,
+					forced: true,
+					safe: x.safe && z.safe,
+					nonmalleable: x.nonmalleable && z.nonmalleable && x.expressive && (x.safe || z.safe),
+					..Type::timelocks(&[&x, &z], false)
+				}
+			},
+			Miniscript::OrD(ref x, ref z) => {
+				let (x, z) = (x.ty()?, z.ty()?);
+				require(x.base == B && x.dissatisfiable && x.unit, "or_d", "X must be Bdu")?;
+				require(z.base == B, "or_d", "Z must be B")?;
+				Type {
+					base: B,
+					zero_arg: x.zero_arg && z.zero_arg,
+					one_arg: x.one_arg && z.zero_arg,
+					nonzero: false,
+					dissatisfiable: z.dissatisfiable,
+					unit: z.unit,
+					expressive: z.expressive,
+					forced: z.forced,
+					safe: x.safe && z.safe,
+					nonmalleable: x.nonmalleable && z.nonmalleable && x.expressive && (x.safe || z.safe),
+					..Type::timelocks(&[&x, &z], false)
+				}
+			},
+			Miniscript::OrI(ref x, ref z) => {
+				let (x, z) = (x.ty()?, z.ty()?);
+				require(x.base == z.base && x.base != W, "or_i", "X and Z must be both B, K or V")?;
+				Type {
+					base: x.base,
+					zero_arg: false,
+					one_arg: x.zero_arg && z.zero_arg,
+					nonzero: false,
+					dissatisfiable: x.dissatisfiable || z.dissatisfiable,
+					unit: x.unit && z.unit,
+					expressive: (x.expressive && z.forced) || (x.forced && z.expressive),
+					forced: x.forced && z.forced,
+					safe: x.safe && z.safe,
+					nonmalleable: x.nonmalleable && z.nonmalleable && (x.safe || z.safe),
+					..Type::timelocks(&[&x, &z], false)
+				}
+			},
+			Miniscript::Thresh(k, ref subs) => {
+				let mut zero_args = 0;
+				let mut one_args = 0;
+				let mut safe_args = 0;
+				let mut all_expressive = true;
+				let mut all_nonmalleable = true;
+				// timelocks of different subexpressions are mixed only if they could be satisfied together
+				let mut timelocks = Type::timelocks(&[], false);
+				for (index, sub) in subs.iter().enumerate() {
+					let sub = sub.ty()?;
+					match index {
+						0 => require(sub.base == B && sub.dissatisfiable && sub.unit, "thresh", "X_1 must be Bdu")?,
+						_ => require(sub.base == W && sub.dissatisfiable && sub.unit, "thresh", "X_2 ... X_n must be Wdu")?,
+					}
+					if sub.zero_arg {
+						zero_args += 1;
+					} else if sub.one_arg {
+						one_args += 1;
+					}
+					if sub.safe {
+						safe_args += 1;
+					}
+					all_expressive = all_expressive && sub.expressive;
+					all_nonmalleable = all_nonmalleable && sub.nonmalleable;
+					timelocks = Type::timelocks(&[&timelocks, &sub], k > 1 && timelocks.mixes_timelocks(&sub));
+				}
+
+				Type {
+					base: B,
+					zero_arg: zero_args == subs.len(),
+					one_arg: zero_args + 1 == subs.len() && one_args == 1,
+					nonzero: false,
+					dissatisfiable: true,
+					unit: true,
+					expressive: all_expressive && safe_args == subs.len(),
+					forced: false,
+					safe: safe_args + k > subs.len(),
+					nonmalleable: all_expressive && all_nonmalleable && safe_args + k >= subs.len(),
+					..timelocks
+				}
+			},
+			Miniscript::Multi(..) => Type::new(B, "ndusemk"),
+			Miniscript::Alt(ref x) => {
+				let x = x.ty()?;
+				require(x.base == B, "a:", "X must be B")?;
+				Type {
+					base: W,
+					zero_arg: false,
+					one_arg: false,
+					nonzero: false,
+					dissatisfiable: x.dissatisfiable,
+					unit: x.unit,
+					expressive: x.expressive,
+					forced: x.forced,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::Swap(ref x) => {
+				let x = x.ty()?;
+				require(x.base == B && x.one_arg, "s:", "X must be Bo")?;
+				Type {
+					base: W,
+					zero_arg: false,
+					one_arg: false,
+					nonzero: false,
+					dissatisfiable: x.dissatisfiable,
+					unit: x.unit,
+					expressive: x.expressive,
+					forced: x.forced,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::Check(ref x) => {
+				let x = x.ty()?;
+				require(x.base == K, "c:", "X must be K")?;
+				Type {
+					base: B,
+					zero_arg: false,
+					one_arg: x.one_arg,
+					nonzero: x.nonzero,
+					dissatisfiable: x.dissatisfiable,
+					unit: true,
+					expressive: x.expressive,
+					forced: x.forced,
+					safe: true,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::DupIf(ref x) => {
+				let x = x.ty()?;
+				require(x.base == V && x.zero_arg, "d:", "X must be Vz")?;
+				// IF argument is not required to be minimal in P2WSH, so the result is not unit
+				Type {
+					base: B,
+					zero_arg: false,
+					one_arg: true,
+					nonzero: true,
+					dissatisfiable: true,
+					unit: false,
+					expressive: x.forced,
+					forced: false,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::Verify(ref x) => {
+				let x = x.ty()?;
+				require(x.base == B, "v:", "X must be B")?;
+				Type {
+					base: V,
+					zero_arg: x.zero_arg,
+					one_arg: x.one_arg,
+					nonzero: x.nonzero,
+					dissatisfiable: false,
+					unit: false,
+					expressive: false,
+					forced: true,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::NonZero(ref x) => {
+				let x = x.ty()?;
+				require(x.base == B && x.nonzero, "j:", "X must be Bn")?;
+				Type {
+					base: B,
+					zero_arg: false,
+					one_arg: x.one_arg,
+					nonzero: true,
+					dissatisfiable: true,
+					unit: x.unit,
+					expressive: x.forced,
+					forced: false,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+			Miniscript::ZeroNotEqual(ref x) => {
+				let x = x.ty()?;
+				require(x.base == B, "n:", "X must be B")?;
+				Type {
+					base: B,
+					zero_arg: x.zero_arg,
+					one_arg: x.one_arg,
+					nonzero: x.nonzero,
+					dissatisfiable: x.dissatisfiable,
+					unit: true,
+					expressive: x.expressive,
+					forced: x.forced,
+					safe: x.safe,
+					nonmalleable: x.nonmalleable,
+					..Type::timelocks(&[&x], false)
+				}
+			},
+		};
+
+		Ok(ty)
+	}
+
+	/// Returns witness script
+	pub fn script(&self) -> Script {
+		self.encode(Builder::default(), false).into_script()
+	}
+
+	/// Returns witness stack items, satisfying the script, or None if available signatures,
+	/// preimages and timelocks are not enough. Witness script should be appended to the items
+	/// to build the P2WSH input witness. The smallest of possible satisfactions is chosen.
+	pub fn satisfy(&self, satisfier: &Satisfier) -> Option<ScriptWitness> {
+		self.satisfactions(satisfier).sat
+	}
+
+	/// Returns true if the last opcode of the fragment has VERIFY version
+	fn has_verify_form(&self) -> bool {
+		match *self {
+			Miniscript::Check(_) | Miniscript::Multi(..) | Miniscript::Thresh(..) |
+			Miniscript::Sha256(_) | Miniscript::Hash256(_) | Miniscript::Ripemd160(_) | Miniscript::Hash160(_) => true,
+			Miniscript::Swap(ref x) | Miniscript::AndV(_, ref x) => x.has_verify_form(),
+			_ => false,
+		}
+	}
+
+	/// Appends fragment to the script. If `verify` is set, the last opcode is replaced with its VERIFY version
+	fn encode(&self, builder: Builder, verify: bool) -> Builder {
+		let (equal, checksig, checkmultisig) = match verify {
+			true => (Opcode::OP_EQUALVERIFY, Opcode::OP_CHECKSIGVERIFY, Opcode::OP_CHECKMULTISIGVERIFY),
+			false => (Opcode::OP_EQUAL, Opcode::OP_CHECKSIG, Opcode::OP_CHECKMULTISIG),
+		};
+
+		match *self {
+			Miniscript::False => builder.push_opcode(Opcode::OP_0),
+			Miniscript::True => builder.push_opcode(Opcode::OP_1),
+			Miniscript::PkK(ref public) => builder.push_data(public),
+			Miniscript::PkH(ref public) => builder
+				.push_opcode(Opcode::OP_DUP)
+				.push_opcode(Opcode::OP_HASH160)
+				.push_data(&*public.address_hash())
+				.push_opcode(Opcode::OP_EQUALVERIFY),
+			Miniscript::Older(n) => builder
+				.push_small_num(n as usize)
+				.push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY),
+			Miniscript::After(n) => builder
+				.push_small_num(n as usize)
+				.push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY),
+			Miniscript::Sha256(ref hash) => encode_hash(builder, Opcode::OP_SHA256, &**hash).push_opcode(equal),
+			Miniscript::Hash256(ref hash) => encode_hash(builder, Opcode::OP_HASH256, &**hash).push_opcode(equal),
+			Miniscript::Ripemd160(ref hash) => encode_hash(builder, Opcode::OP_RIPEMD160, &**hash).push_opcode(equal),
+			Miniscript::Hash160(ref hash) => encode_hash(builder, Opcode::OP_HASH160, &**hash).push_opcode(equal),
+			Miniscript::AndOr(ref x, ref y, ref z) => {
+				let builder = x.encode(builder, false).push_opcode(Opcode::OP_NOTIF);
+				let builder = z.encode(builder, false).push_opcode(Opcode::OP_ELSE);
+				y.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::AndV(ref x, ref y) => y.encode(x.encode(builder, false), verify),
+			Miniscript::AndB(ref x, ref y) => y.encode(x.encode(builder, false), false).push_opcode(Opcode::OP_BOOLAND),
+			Miniscript::OrB(ref x, ref z) => z.encode(x.encode(builder, false), false).push_opcode(Opcode::OP_BOOLOR),
+			Miniscript::OrC(ref x, ref z) => {
+				let builder = x.encode(builder, false).push_opcode(Opcode::OP_NOTIF);
+				z.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::OrD(ref x, ref z) => {
+				let builder = x.encode(builder, false)
+					.push_opcode(Opcode::OP_IFDUP)
+					.push_opcode(Opcode::OP_NOTIF);
+				z.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::OrI(ref x, ref z) => {
+				let builder = x.encode(builder.push_opcode(Opcode::OP_IF), false).push_opcode(Opcode::OP_ELSE);
+				z.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::Thresh(k, ref subs) => {
+				let mut builder = builder;
+				for (index, sub) in subs.iter().enumerate() {
+					builder = sub.encode(builder, false);
+					if index != 0 {
+						builder = builder.push_opcode(Opcode::OP_ADD);
+					}
+				}
+				builder.push_small_num(k).push_opcode(equal)
+			},
+			Miniscript::Multi(k, ref keys) => keys.iter()
+				.fold(builder.push_small_num(k), |builder, key| builder.push_data(key))
+				.push_small_num(keys.len())
+				.push_opcode(checkmultisig),
+			Miniscript::Alt(ref x) => x.encode(builder.push_opcode(Opcode::OP_TOALTSTACK), false)
+				.push_opcode(Opcode::OP_FROMALTSTACK),
+			Miniscript::Swap(ref x) => x.encode(builder.push_opcode(Opcode::OP_SWAP), verify),
+			Miniscript::Check(ref x) => x.encode(builder, false).push_opcode(checksig),
+			Miniscript::DupIf(ref x) => {
+				let builder = builder
+					.push_opcode(Opcode::OP_DUP)
+					.push_opcode(Opcode::OP_IF);
+				x.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::Verify(ref x) => match x.has_verify_form() {
+				true => x.encode(builder, true),
+				false => x.encode(builder, false).push_opcode(Opcode::OP_VERIFY),
+			},
+			Miniscript::NonZero(ref x) => {
+				let builder = builder
+					.push_opcode(Opcode::OP_SIZE)
+					.push_opcode(Opcode::OP_0NOTEQUAL)
+					.push_opcode(Opcode::OP_IF);
+				x.encode(builder, false).push_opcode(Opcode::OP_ENDIF)
+			},
+			Miniscript::ZeroNotEqual(ref x) => x.encode(builder, false).push_opcode(Opcode::OP_0NOTEQUAL),
+		}
+	}
+
+	fn satisfactions(&self, satisfier: &Satisfier) -> Satisfactions {
+		let empty = || Some(vec![Bytes::new()]);
+		let one = || Some(vec![Bytes::from(vec![1u8])]);
+
+		match *self {
+			Miniscript::False => Satisfactions::new(None, Some(vec![])),
+			Miniscript::True => Satisfactions::new(Some(vec![]), None),
+			Miniscript::PkK(ref public) => Satisfactions::new(
+				satisfier.find_signature(public).map(|signature| vec![signature]),
+				empty(),
+			),
+			Miniscript::PkH(ref public) => {
+				let key = Bytes::from(public.to_vec());
+				Satisfactions::new(
+					satisfier.find_signature(public).map(|signature| vec![signature, key.clone()]),
+					Some(vec![Bytes::new(), key]),
+				)
+			},
+			Miniscript::Older(n) => Satisfactions::new(match satisfier.check_older(n) {
+				true => Some(vec![]),
+				false => None,
+			}, None),
+			Miniscript::After(n) => Satisfactions::new(match satisfier.check_after(n) {
+				true => Some(vec![]),
+				false => None,
+			}, None),
+			Miniscript::Sha256(ref hash) => hash_satisfactions(satisfier.find_preimage(|preimage| sha256(preimage) == *hash)),
+			Miniscript::Hash256(ref hash) => hash_satisfactions(satisfier.find_preimage(|preimage| dhash256(preimage) == *hash)),
+			Miniscript::Ripemd160(ref hash) => hash_satisfactions(satisfier.find_preimage(|preimage| ripemd160(preimage) == *hash)),
+			Miniscript::Hash160(ref hash) => hash_satisfactions(satisfier.find_preimage(|preimage| dhash160(preimage) == *hash)),
+			Miniscript::AndOr(ref x, ref y, ref z) => {
+				let (x, y, z) = (x.satisfactions(satisfier), y.satisfactions(satisfier), z.satisfactions(satisfier));
+				Satisfactions::new(
+					cheapest(vec![concat(&y.sat, &x.sat), concat(&z.sat, &x.dissat)]),
+					concat(&z.dissat, &x.dissat),
+				)
+			},
+			Miniscript::AndV(ref x, ref y) => {
+				let (x, y) = (x.satisfactions(satisfier), y.satisfactions(satisfier));
+				Satisfactions::new(concat(&y.sat, &x.sat), concat(&y.dissat, &x.sat))
+			},
+			Miniscript::AndB(ref x, ref y) => {
+				let (x, y) = (x.satisfactions(satisfier), y.satisfactions(satisfier));
+				Satisfactions::new(
+					concat(&y.sat, &x.sat),
+					cheapest(vec![concat(&y.dissat, &x.dissat), concat(&y.sat, &x.dissat), concat(&y.dissat, &x.sat)]),
+				)
+			},
+			Miniscript::OrB(ref x, ref z) => {
+				let (x, z) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+				Satisfactions::new(
+					cheapest(vec![concat(&z.dissat, &x.sat), concat(&z.sat, &x.dissat), concat(&z.sat, &x.sat)]),
+					concat(&z.dissat, &x.dissat),
+				)
+			},
+			Miniscript::OrC(ref x, ref z) => {
+				let (x, z) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+				Satisfactions::new(cheapest(vec![x.sat, concat(&z.sat, &x.dissat)]), None)
+			},
+			Miniscript::OrD(ref x, ref z) => {
+				let (x, z) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+				Satisfactions::new(
+					cheapest(vec![x.sat.clone(), concat(&z.sat, &x.dissat)]),
+					concat(&z.dissat, &x.dissat),
+				)
+			},
+			Miniscript::OrI(ref x, ref z) => {
+				let (x, z) = (x.satisfactions(satisfier), z.satisfactions(satisfier));
+				Satisfactions::new(
+					cheapest(vec![concat(&x.sat, &one()), concat(&z.sat, &empty())]),
+					cheapest(vec![concat(&x.dissat, &one()), concat(&z.dissat, &empty())]),
+				)
+			},
+			Miniscript::Thresh(k, ref subs) => {
+				let subs: Vec<_> = subs.iter().map(|sub| sub.satisfactions(satisfier)).collect();
+				// satisfy k subexpressions with the smallest size increase, dissatisfy others
+				let mut candidates: Vec<(usize, isize)> = subs.iter().enumerate()
+					.filter_map(|(index, sub)| match (sub.sat.as_ref(), sub.dissat.as_ref()) {
+						(Some(sat), Some(dissat)) => Some((index, witness_size(sat) as isize - witness_size(dissat) as isize)),
+						_ => None,
+					})
+					.collect();
+				candidates.sort_by_key(|&(_, cost)| cost);
+				let satisfied: Vec<usize> = candidates.into_iter().take(k).map(|(index, _)| index).collect();
+
+				// the first subexpression is evaluated first, so its witness is on top of the stack
+				let sat = match satisfied.len() == k {
+					true => subs.iter().enumerate().rev().fold(Some(vec![]), |witness, (index, sub)| match satisfied.contains(&index) {
+						true => concat(&witness, &sub.sat),
+						false => concat(&witness, &sub.dissat),
+					}),
+					false => None,
+				};
+				let dissat = subs.iter().rev().fold(Some(vec![]), |witness, sub| concat(&witness, &sub.dissat));
+				Satisfactions::new(sat, dissat)
+			},
+			Miniscript::Multi(k, ref keys) => {
+				let signatures: Vec<Bytes> = keys.iter()
+					.filter_map(|key| satisfier.find_signature(key))
+					.take(k)
+					.collect();
+				// extra element is consumed by CHECKMULTISIG because of the off-by-one bug
+				let sat = match signatures.len() == k {
+					true => Some(Some(Bytes::new()).into_iter().chain(signatures).collect()),
+					false => None,
+				};
+				Satisfactions::new(sat, Some(vec![Bytes::new(); k + 1]))
+			},
+			Miniscript::Alt(ref x) | Miniscript::Swap(ref x) | Miniscript::Check(ref x) | Miniscript::ZeroNotEqual(ref x) =>
+				x.satisfactions(satisfier),
+			Miniscript::DupIf(ref x) => Satisfactions::new(concat(&x.satisfactions(satisfier).sat, &one()), empty()),
+			Miniscript::Verify(ref x) => Satisfactions::new(x.satisfactions(satisfier).sat, None),
+			Miniscript::NonZero(ref x) => Satisfactions::new(x.satisfactions(satisfier).sat, empty()),
+		}
+	}
+
+	/// Returns wrapper letter, if fragment is a wrapper, which is displayed as `letter:X`
+	fn wrapper(&self) -> Option<(char, &Miniscript)> {
+		match *self {
+			Miniscript::Alt(ref x) => Some(('a', x)),
+			Miniscript::Swap(ref x) => Some(('s', x)),
+			Miniscript::Check(ref x) => match **x {
+				// displayed as pk() and pkh()
+				Miniscript::PkK(_) | Miniscript::PkH(_) => None,
+				_ => Some(('c', x)),
+			},
+			Miniscript::DupIf(ref x) => Some(('d', x)),
+			Miniscript::Verify(ref x) => Some(('v', x)),
+			Miniscript::NonZero(ref x) => Some(('j', x)),
+			Miniscript::ZeroNotEqual(ref x) => Some(('n', x)),
+			Miniscript::AndV(ref x, ref y) if **y == Miniscript::True => Some(('t', x)),
+			Miniscript::OrI(ref x, ref z) if **x == Miniscript::False => Some(('l', z)),
+			Miniscript::OrI(ref x, ref z) if **z == Miniscript::False => Some(('u', x)),
+			_ => None,
+		}
+	}
+}
+
+fn require(condition: bool, fragment: &str, msg: &str) -> Result<(), MiniscriptError> {
+	match condition {
+		true => Ok(()),
+		false => Err(MiniscriptError::Type(format!("{}: {}", fragment, msg))),
+	}
+}
+
+fn encode_hash(builder: Builder, opcode: Opcode, hash: &[u8]) -> Builder {
+	builder
+		.push_opcode(Opcode::OP_SIZE)
+		.push_small_num(32)
+		.push_opcode(Opcode::OP_EQUALVERIFY)
+		.push_opcode(opcode)
+		.push_data(hash)
+}
+
+fn hash_satisfactions(preimage: Option<Bytes>) -> Satisfactions {
+	// any 32-byte value, which is not a preimage, dissatisfies the hash check
+	Satisfactions::new(preimage.map(|preimage| vec![preimage]), Some(vec![Bytes::from(vec![0u8; 32])]))
+}
+
+impl fmt::Display for Miniscript {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if let Some(_) = self.wrapper() {
+			let mut inner = self;
+			while let Some((letter, x)) = inner.wrapper() {
+				write!(f, "{}", letter)?;
+				inner = x;
+			}
+			return write!(f, ":{}", inner);
+		}
+
+		match *self {
+			Miniscript::False => "0".fmt(f),
+			Miniscript::True => "1".fmt(f),
+			Miniscript::PkK(ref public) => write!(f, "pk_k({})", public),
+			Miniscript::PkH(ref public) => write!(f, "pk_h({})", public),
+			Miniscript::Older(n) => write!(f, "older({})", n),
+			Miniscript::After(n) => write!(f, "after({})", n),
+			Miniscript::Sha256(ref hash) => write!(f, "sha256({})", hash),
+			Miniscript::Hash256(ref hash) => write!(f, "hash256({})", hash),
+			Miniscript::Ripemd160(ref hash) => write!(f, "ripemd160({})", hash),
+			Miniscript::Hash160(ref hash) => write!(f, "hash160({})", hash),
+			Miniscript::AndOr(ref x, ref y, ref z) => match **z {
+				Miniscript::False => write!(f, "and_n({},{})", x, y),
+				_ => write!(f, "andor({},{},{})", x, y, z),
+			},
+			Miniscript::AndV(ref x, ref y) => write!(f, "and_v({},{})", x, y),
+			Miniscript::AndB(ref x, ref y) => write!(f, "and_b({},{})", x, y),
+			Miniscript::OrB(ref x, ref z) => write!(f, "or_b({},{})", x, z),
+			Miniscript::OrC(ref x, ref z) => write!(f, "or_c({},{})", x, z),
+			Miniscript::OrD(ref x, ref z) => write!(f, "or_d({},{})", x, z),
+			Miniscript::OrI(ref x, ref z) => write!(f, "or_i({},{})", x, z),
+			Miniscript::Thresh(k, ref subs) => {
+				write!(f, "thresh({}", k)?;
+				for sub in subs {
+					write!(f, ",{}", sub)?;
+				}
+				")".fmt(f)
+			},
+			Miniscript::Multi(k, ref keys) => {
+				write!(f, "multi({}", k)?;
+				for key in keys {
+					write!(f, ",{}", key)?;
+				}
+				")".fmt(f)
+			},
+			Miniscript::Check(ref x) => match **x {
+				Miniscript::PkK(ref public) => write!(f, "pk({})", public),
+				Miniscript::PkH(ref public) => write!(f, "pkh({})", public),
+				_ => unreachable!("other c: fragments are displayed as wrappers; qed"),
+			},
+			_ => unreachable!("wrappers are displayed above; qed"),
+		}
+	}
+}
+
+impl FromStr for Miniscript {
+	type Err = MiniscriptError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Miniscript::parse(s)
+	}
+}
+
+fn parse_fragment(s: &str) -> Result<Miniscript, MiniscriptError> {
+	let open = s.find('(');
+	// wrappers are only allowed before the fragment name
+	if let Some(colon) = s.find(':') {
+		if open.map_or(true, |open| colon < open) {
+			let inner = parse_fragment(&s[colon + 1..])?;
+			return s[..colon].chars().rev().try_fold(inner, |inner, wrapper| apply_wrapper(wrapper, inner));
+		}
+	}
+
+	let (name, args) = match (open, s.ends_with(')')) {
+		(Some(open), true) => (&s[..open], split_args(&s[open + 1..s.len() - 1])?),
+		(None, _) => (s, Vec::new()),
+		_ => return Err(MiniscriptError::Syntax(format!("'{}' is not a valid miniscript fragment", s))),
+	};
+
+	let fragment = match (name, args.len()) {
+		("0", 0) => Miniscript::False,
+		("1", 0) => Miniscript::True,
+		("pk_k", 1) => Miniscript::PkK(parse_key(args[0])?),
+		("pk_h", 1) => Miniscript::PkH(parse_key(args[0])?),
+		("pk", 1) => Miniscript::Check(Box::new(Miniscript::PkK(parse_key(args[0])?))),
+		("pkh", 1) => Miniscript::Check(Box::new(Miniscript::PkH(parse_key(args[0])?))),
+		("older", 1) => Miniscript::Older(parse_timelock(args[0])?),
+		("after", 1) => Miniscript::After(parse_timelock(args[0])?),
+		("sha256", 1) => Miniscript::Sha256(parse_hash(args[0])?),
+		("hash256", 1) => Miniscript::Hash256(parse_hash(args[0])?),
+		("ripemd160", 1) => Miniscript::Ripemd160(parse_hash(args[0])?),
+		("hash160", 1) => Miniscript::Hash160(parse_hash(args[0])?),
+		("andor", 3) => Miniscript::AndOr(parse_boxed(args[0])?, parse_boxed(args[1])?, parse_boxed(args[2])?),
+		("and_n", 2) => Miniscript::AndOr(parse_boxed(args[0])?, parse_boxed(args[1])?, Box::new(Miniscript::False)),
+		("and_v", 2) => Miniscript::AndV(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("and_b", 2) => Miniscript::AndB(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("or_b", 2) => Miniscript::OrB(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("or_c", 2) => Miniscript::OrC(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("or_d", 2) => Miniscript::OrD(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("or_i", 2) => Miniscript::OrI(parse_boxed(args[0])?, parse_boxed(args[1])?),
+		("thresh", n) if n > 1 => {
+			let subs = args[1..].iter().map(|arg| parse_fragment(arg)).collect::<Result<Vec<_>, _>>()?;
+			Miniscript::Thresh(parse_threshold(args[0], subs.len())?, subs)
+		},
+		("multi", n) if n > 1 => {
+			let keys = args[1..].iter().map(|arg| parse_key(arg)).collect::<Result<Vec<_>, _>>()?;
+			if keys.len() > MAX_MULTI_KEYS {
+				return Err(MiniscriptError::InvalidThreshold(format!("Cannot have {} keys in multi; must have at most {} keys", keys.len(), MAX_MULTI_KEYS)));
+			}
+			Miniscript::Multi(parse_threshold(args[0], keys.len())?, keys)
+		},
+		_ => return Err(MiniscriptError::Syntax(format!("'{}' is not a valid miniscript fragment", s))),
+	};
+
+	Ok(fragment)
+}
+
+fn parse_boxed(s: &str) -> Result<Box<Miniscript>, MiniscriptError> {
+	parse_fragment(s).map(Box::new)
+}
+
+fn apply_wrapper(wrapper: char, inner: Miniscript) -> Result<Miniscript, MiniscriptError> {
+	let inner = Box::new(inner);
+	let wrapped = match wrapper {
+		'a' => Miniscript::Alt(inner),
+		's' => Miniscript::Swap(inner),
+		'c' => Miniscript::Check(inner),
+		'd' => Miniscript::DupIf(inner),
+		'v' => Miniscript::Verify(inner),
+		'j' => Miniscript::NonZero(inner),
+		'n' => Miniscript::ZeroNotEqual(inner),
+		't' => Miniscript::AndV(inner, Box::new(Miniscript::True)),
+		'l' => Miniscript::OrI(Box::new(Miniscript::False), inner),
+		'u' => Miniscript::OrI(inner, Box::new(Miniscript::False)),
+		_ => return Err(MiniscriptError::Syntax(format!("'{}' is not a valid wrapper", wrapper))),
+	};
+
+	Ok(wrapped)
+}
+
+/// Splits comma-separated arguments, ignoring commas of the nested fragments
+fn split_args(s: &str) -> Result<Vec<&str>, MiniscriptError> {
+	let mut args = Vec::new();
+	let mut depth = 0;
+	let mut start = 0;
+	for (position, ch) in s.char_indices() {
+		match ch {
+			'(' => depth += 1,
+			')' if depth == 0 => return Err(MiniscriptError::Syntax(format!("Unbalanced parentheses in '{}'", s))),
+			')' => depth -= 1,
+			',' if depth == 0 => {
+				args.push(&s[start..position]);
+				start = position + 1;
+			},
+			_ => (),
+		}
+	}
+
+	if depth != 0 {
+		return Err(MiniscriptError::Syntax(format!("Unbalanced parentheses in '{}'", s)));
+	}
+
+	args.push(&s[start..]);
+	Ok(args)
+}
+
+fn parse_key(s: &str) -> Result<Public, MiniscriptError> {
+	let data: Bytes = s.parse().map_err(|_| MiniscriptError::InvalidKey(s.into()))?;
+	let public = match data.len() {
+		33 => Public::from_slice(&data).expect("data length is checked above; qed"),
+		65 => return Err(MiniscriptError::UncompressedKey),
+		_ => return Err(MiniscriptError::InvalidKey(s.into())),
+	};
+
+	match public.is_valid() {
+		true => Ok(public),
+		false => Err(MiniscriptError::InvalidKey(s.into())),
+	}
+}
+
+fn parse_hash<T: FromStr>(s: &str) -> Result<T, MiniscriptError> {
+	s.parse().map_err(|_| MiniscriptError::InvalidHash(s.into()))
+}
+
+fn parse_timelock(s: &str) -> Result<u32, MiniscriptError> {
+	match s.parse::<u32>() {
+		Ok(n) if n >= 1 && n < SEQUENCE_LOCKTIME_DISABLE_FLAG => Ok(n),
+		_ => Err(MiniscriptError::InvalidTimelock(s.into())),
+	}
+}
+
+fn parse_threshold(s: &str, n: usize) -> Result<usize, MiniscriptError> {
+	match s.parse::<usize>() {
+		Ok(k) if k >= 1 && k <= n => Ok(k),
+		_ => Err(MiniscriptError::InvalidThreshold(format!("Threshold cannot be {}, must be at least 1 and at most {}", s, n))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+	use crypto::sha256;
+	use keys::{KeyPair, Private, Network};
+	use sign::{SignatureVersion, TransactionInputSigner};
+	use verify::TransactionSignatureChecker;
+	use {verify_script, Builder, Script, VerificationFlags, Error};
+	use super::*;
+
+	const INPUT_AMOUNT: u64 = 100_000;
+	const SIGHASH_ALL: u32 = 1;
+
+	fn key_pair(secret: u8) -> KeyPair {
+		KeyPair::from_private(Private { network: Network::Mainnet, secret: secret.into(), compressed: true }).unwrap()
+	}
+
+	fn public(secret: u8) -> Public {
+		key_pair(secret).public().clone()
+	}
+
+	fn spending_transaction(version: i32, lock_time: u32, sequence: u32) -> Transaction {
+		Transaction {
+			version: version,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint { hash: 1u8.into(), index: 0 },
+				script_sig: Bytes::new(),
+				sequence: sequence,
+				script_witness: vec![],
+			}],
+			outputs: vec![TransactionOutput {
+				value: INPUT_AMOUNT - 1000,
+				script_pubkey: Builder::build_p2wpkh(&public(1).address_hash()).to_bytes(),
+			}],
+			lock_time: lock_time,
+		}
+	}
+
+	fn sign(transaction: &Transaction, script: &Script, secret: u8) -> Bytes {
+		let signer: TransactionInputSigner = transaction.clone().into();
+		let hash = signer.signature_hash(0, INPUT_AMOUNT, script, SignatureVersion::WitnessV0, SIGHASH_ALL);
+		let mut signature: Vec<u8> = key_pair(secret).private().sign(&hash).unwrap().into();
+		signature.push(SIGHASH_ALL as u8);
+		signature.into()
+	}
+
+	/// Spends P2WSH output with satisfied miniscript, using the interpreter
+	fn verify_satisfaction(miniscript: &Miniscript, transaction: &Transaction, satisfier: &Satisfier) -> Result<(), Error> {
+		let script = miniscript.script();
+		let mut witness = miniscript.satisfy(satisfier).expect("satisfaction is available");
+		witness.push(script.to_bytes());
+
+		let script_pubkey = Builder::build_p2wsh(&sha256(&script));
+		let checker = TransactionSignatureChecker {
+			signer: TransactionInputSigner::from(transaction.clone()),
+			input_index: 0,
+			input_amount: INPUT_AMOUNT,
+		};
+		let flags = VerificationFlags::default()
+			.verify_p2sh(true)
+			.verify_witness(true)
+			.verify_locktime(true)
+			.verify_checksequence(true)
+			.verify_dersig(true)
+			.verify_strictenc(true)
+			.verify_nulldummy(true)
+			.verify_cleanstack(true);
+		verify_script(&Script::new(Bytes::new()), &script_pubkey, &witness, &flags, &checker, SignatureVersion::Base)
+	}
+
+	#[test]
+	fn test_miniscript_parse_and_display() {
+		let miniscript = format!("or_d(pk({}),and_v(v:pkh({}),older(1008)))", public(1), public(2));
+		assert_eq!(Miniscript::parse(&miniscript).unwrap().to_string(), miniscript);
+
+		// sugar is displayed back
+		let miniscript = format!("and_n(pk({}),l:after(500000))", public(1));
+		let parsed = Miniscript::parse(&miniscript).unwrap();
+		assert_eq!(parsed, Miniscript::AndOr(
+			Box::new(Miniscript::Check(Box::new(Miniscript::PkK(public(1))))),
+			Box::new(Miniscript::OrI(Box::new(Miniscript::False), Box::new(Miniscript::After(500000)))),
+			Box::new(Miniscript::False),
+		));
+		assert_eq!(parsed.to_string(), miniscript);
+
+		let miniscript = format!("thresh(2,pk({}),s:pk({}),sln:older(144))", public(1), public(2));
+		assert_eq!(Miniscript::parse(&miniscript).unwrap().to_string(), miniscript);
+	}
+
+	#[test]
+	fn test_miniscript_types() {
+		let ty = |s: &str| Miniscript::parse_insane(s).map(|miniscript| miniscript.ty().unwrap().to_string());
+		assert_eq!(ty(&format!("pk({})", public(1))), Ok("Bonduesmk".to_owned()));
+		assert_eq!(ty(&format!("pkh({})", public(1))), Ok("Bnduesmk".to_owned()));
+		assert_eq!(ty("older(1)"), Ok("Bzfmhk".to_owned()));
+		assert_eq!(ty(&format!("multi(1,{},{})", public(1), public(2))), Ok("Bnduesmk".to_owned()));
+		assert_eq!(ty(&format!("or_d(pk({}),older(1))", public(1))), Ok("Bofmhk".to_owned()));
+		assert_eq!(ty(&format!("and_v(v:pk({}),pk({}))", public(1), public(2))), Ok("Bnufsmk".to_owned()));
+
+		assert_eq!(Miniscript::parse(&format!("and_v(pk({}),pk({}))", public(1), public(2))),
+			Err(MiniscriptError::Type("and_v: X must be V".into())));
+		assert_eq!(Miniscript::parse(&format!("or_b(pk({}),pk({}))", public(1), public(2))),
+			Err(MiniscriptError::Type("or_b: Z must be Wd".into())));
+		assert_eq!(Miniscript::parse(&format!("v:pk({})", public(1))),
+			Err(MiniscriptError::Type("Top level expression must be B, not Vonfsmk".into())));
+		assert_eq!(Miniscript::parse(&format!("pk_k({})", public(1))),
+			Err(MiniscriptError::Type("Top level expression must be B, not Konduesmk".into())));
+	}
+
+	#[test]
+	fn test_miniscript_invalid() {
+		assert_eq!(Miniscript::parse("older(0)"), Err(MiniscriptError::InvalidTimelock("0".into())));
+		assert_eq!(Miniscript::parse("after(2147483648)"), Err(MiniscriptError::InvalidTimelock("2147483648".into())));
+		assert_eq!(Miniscript::parse("sha256(deadbeef)"), Err(MiniscriptError::InvalidHash("deadbeef".into())));
+		assert_eq!(Miniscript::parse("pk(deadbeef)"), Err(MiniscriptError::InvalidKey("deadbeef".into())));
+		assert_eq!(Miniscript::parse(&format!("multi(3,{},{})", public(1), public(2))),
+			Err(MiniscriptError::InvalidThreshold("Threshold cannot be 3, must be at least 1 and at most 2".into())));
+		assert_eq!(Miniscript::parse("x:older(1)"), Err(MiniscriptError::Syntax("'x' is not a valid wrapper".into())));
+		assert_eq!(Miniscript::parse("and_v(v:older(1),older(2)"), Err(MiniscriptError::Syntax("Unbalanced parentheses in 'v:older(1),older(2'".into())));
+		assert_eq!(Miniscript::parse("or_i(1)"), Err(MiniscriptError::Syntax("'or_i(1)' is not a valid miniscript fragment".into())));
+
+		assert_eq!(Miniscript::parse(&format!("thresh(3,pk({}),s:pk({}))", public(1), public(2))),
+			Err(MiniscriptError::InvalidThreshold("Threshold cannot be 3, must be at least 1 and at most 2".into())));
+
+		let uncompressed = KeyPair::from_private(Private { network: Network::Mainnet, secret: 1u8.into(), compressed: false }).unwrap();
+		assert_eq!(Miniscript::parse(&format!("pk({})", uncompressed.public())), Err(MiniscriptError::UncompressedKey));
+	}
+
+	#[test]
+	fn test_miniscript_not_sane() {
+		let not_sane = |msg: &str| Err(MiniscriptError::NotSane(msg.into()));
+
+		// timelock alone, or as alternative to the signature
+		assert_eq!(Miniscript::parse("older(144)"), not_sane("Miniscript could be satisfied without a signature"));
+		assert_eq!(Miniscript::parse(&format!("or_d(pk({}),older(144))", public(1))),
+			not_sane("Miniscript could be satisfied without a signature"));
+		assert!(Miniscript::parse_insane("older(144)").is_ok());
+
+		// third party could replace j:multi dissatisfaction
+		assert_eq!(Miniscript::parse(&format!("or_b(j:multi(1,{},{}),a:pk({}))", public(1), public(2), public(3))),
+			not_sane("Miniscript has no non-malleable satisfaction"));
+
+		// both height and time timelocks are required to satisfy
+		assert_eq!(Miniscript::parse(&format!("and_v(v:pk({}),and_v(v:after(100),after(500000001)))", public(1))),
+			not_sane("Miniscript mixes height and time timelocks"));
+		assert_eq!(Miniscript::parse(&format!("and_v(v:pk({}),and_v(v:older(100),older(4194305)))", public(1))),
+			not_sane("Miniscript mixes height and time timelocks"));
+		assert_eq!(Miniscript::parse(&format!(
+			"thresh(3,pk({}),s:pk({}),sln:after(100),sln:after(500000001))", public(1), public(2)
+		)), not_sane("Miniscript mixes height and time timelocks"));
+		// timelocks of different kinds are fine, when they are alternatives
+		assert!(Miniscript::parse(&format!(
+			"or_i(and_v(v:pk({}),after(100)),and_v(v:pk({}),after(500000001)))", public(1), public(2)
+		)).is_ok());
+		assert!(Miniscript::parse(&format!("and_v(v:pk({}),and_v(v:older(100),after(500000001)))", public(1))).is_ok());
+
+		assert_eq!(Miniscript::parse(&format!("or_b(pk({}),a:pk({}))", public(1), public(1))),
+			not_sane("Miniscript contains duplicate keys"));
+	}
+
+	#[test]
+	fn test_miniscript_script() {
+		let miniscript = Miniscript::parse(&format!("or_d(pk({}),and_v(v:pkh({}),older(144)))", public(1), public(2))).unwrap();
+		let expected = Builder::default()
+			.push_data(&public(1))
+			.push_opcode(Opcode::OP_CHECKSIG)
+			.push_opcode(Opcode::OP_IFDUP)
+			.push_opcode(Opcode::OP_NOTIF)
+			.push_opcode(Opcode::OP_DUP)
+			.push_opcode(Opcode::OP_HASH160)
+			.push_data(&*public(2).address_hash())
+			.push_opcode(Opcode::OP_EQUALVERIFY)
+			.push_opcode(Opcode::OP_CHECKSIGVERIFY)
+			.push_num(144.into())
+			.push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY)
+			.push_opcode(Opcode::OP_ENDIF)
+			.into_script();
+		assert_eq!(miniscript.script(), expected);
+
+		// multi() is the same as the standard multisig script
+		let miniscript = Miniscript::parse(&format!("multi(2,{},{},{})", public(1), public(2), public(3))).unwrap();
+		assert_eq!(miniscript.script(), Builder::build_multisig(2, &[public(1), public(2), public(3)]));
+	}
+
+	#[test]
+	fn test_miniscript_satisfy_timelocked_recovery() {
+		// primary key, or recovery key after 144 blocks
+		let miniscript = Miniscript::parse(&format!("or_d(pk({}),and_v(v:pk({}),older(144)))", public(1), public(2))).unwrap();
+		let script = miniscript.script();
+
+		let transaction = spending_transaction(2, 0, 0xffffffff);
+		let satisfier = Satisfier::default().signature(public(1), sign(&transaction, &script, 1));
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+
+		// recovery key can't be used before timelock expires
+		let transaction = spending_transaction(2, 0, 143);
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.sequence(143);
+		assert_eq!(miniscript.satisfy(&satisfier), None);
+
+		let transaction = spending_transaction(2, 0, 144);
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.sequence(144);
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+	}
+
+	#[test]
+	fn test_miniscript_satisfy_decaying_multisig() {
+		// 2-of-3, decaying to 1-of-3 after the lock time
+		let miniscript = Miniscript::parse(&format!(
+			"thresh(2,pk({}),s:pk({}),s:pk({}),sln:after(500000))", public(1), public(2), public(3)
+		)).unwrap();
+		let script = miniscript.script();
+
+		let transaction = spending_transaction(2, 0, 0xffffffff);
+		let satisfier = Satisfier::default()
+			.signature(public(1), sign(&transaction, &script, 1))
+			.signature(public(3), sign(&transaction, &script, 3));
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+
+		let transaction = spending_transaction(2, 499999, 0xfffffffe);
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.lock_time(499999);
+		assert_eq!(miniscript.satisfy(&satisfier), None);
+
+		let transaction = spending_transaction(2, 500000, 0xfffffffe);
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.lock_time(500000);
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+	}
+
+	#[test]
+	fn test_miniscript_satisfy_hashlock() {
+		let preimage = Bytes::from(vec![0x42u8; 32]);
+		let miniscript = Miniscript::parse(&format!(
+			"andor(pk({}),sha256({}),and_v(v:pkh({}),hash160({})))",
+			public(1), sha256(&preimage), public(2), dhash160(&preimage)
+		)).unwrap();
+		let script = miniscript.script();
+		let transaction = spending_transaction(1, 0, 0xffffffff);
+
+		// signature without preimage is not enough
+		let satisfier = Satisfier::default().signature(public(1), sign(&transaction, &script, 1));
+		assert_eq!(miniscript.satisfy(&satisfier), None);
+
+		let satisfier = satisfier.preimage(preimage.clone());
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.preimage(preimage);
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+	}
+
+	#[test]
+	fn test_miniscript_satisfy_wrappers() {
+		let miniscript = format!("or_b(j:multi(1,{},{}),a:and_b(pk({}),sdv:older(10)))", public(1), public(2), public(3));
+		assert_eq!(Miniscript::parse(&miniscript), Err(MiniscriptError::NotSane("Miniscript has no non-malleable satisfaction".into())));
+		let miniscript = Miniscript::parse_insane(&miniscript).unwrap();
+		let script = miniscript.script();
+
+		let transaction = spending_transaction(2, 0, 10);
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2))
+			.signature(public(3), sign(&transaction, &script, 3))
+			.sequence(10);
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+
+		let satisfier = Satisfier::default()
+			.signature(public(3), sign(&transaction, &script, 3))
+			.sequence(10);
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+
+		let miniscript = Miniscript::parse(&format!("or_i(and_v(v:pk({}),older(10)),c:pk_h({}))", public(1), public(2))).unwrap();
+		let script = miniscript.script();
+		let satisfier = Satisfier::default()
+			.signature(public(2), sign(&transaction, &script, 2));
+		assert_eq!(verify_satisfaction(&miniscript, &transaction, &satisfier), Ok(()));
+	}
+}
@@ -0,0 +1,196 @@
+//! BIP322 generic signed messages
+//!
+//! Message is committed to by the virtual `to_spend` transaction, which output has the
+//! script pubkey of the signer address. Signature is the witness of the virtual `to_sign`
+//! transaction, spending this output. The spend is verified by the script interpreter, so
+//! the signature works for any segwit address, including P2WSH.
+//!
+//! Only the 'simple' signature format (serialized witness stack) is supported.
+
+use bytes::Bytes;
+use hash::H256;
+use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+use crypto::tagged_hash;
+use keys::{self, Private, KeyPair};
+use ser::{serialize_list, Reader};
+use {Builder, Opcode, Script, ScriptWitness, Error, VerificationFlags, SignatureVersion, TransactionInputSigner, TransactionSignatureChecker, verify_script};
+
+/// Tag of the message hash
+pub const BIP322_TAG: &'static [u8] = b"BIP0322-signed-message";
+/// SIGHASH_ALL signature hash type
+const SIGHASH_ALL: u32 = 1;
+
+/// Returns tagged hash of the message
+pub fn bip322_message_hash(message: &[u8]) -> H256 {
+	tagged_hash(BIP322_TAG, message)
+}
+
+/// Builds virtual transaction, which output is spent by the message signature
+pub fn bip322_to_spend(script_pubkey: &Script, message: &[u8]) -> Transaction {
+	let script_sig = Builder::default()
+		.push_opcode(Opcode::OP_0)
+		.push_data(&*bip322_message_hash(message))
+		.into_script();
+
+	Transaction {
+		version: 0,
+		inputs: vec![TransactionInput {
+			previous_output: OutPoint {
+				hash: H256::default(),
+				index: 0xffffffff,
+			},
+			script_sig: script_sig.to_bytes(),
+			sequence: 0,
+			script_witness: vec![],
+		}],
+		outputs: vec![TransactionOutput {
+			value: 0,
+			script_pubkey: script_pubkey.to_bytes(),
+		}],
+		lock_time: 0,
+	}
+}
+
+/// Builds virtual transaction, spending the `to_spend` transaction output with given witness
+pub fn bip322_to_sign(to_spend: &Transaction, witness: ScriptWitness) -> Transaction {
+	Transaction {
+		version: 0,
+		inputs: vec![TransactionInput {
+			previous_output: OutPoint {
+				hash: to_spend.hash(),
+				index: 0,
+			},
+			script_sig: Bytes::new(),
+			sequence: 0,
+			script_witness: witness,
+		}],
+		outputs: vec![TransactionOutput {
+			value: 0,
+			script_pubkey: Builder::default().push_opcode(Opcode::OP_RETURN).into_bytes(),
+		}],
+		lock_time: 0,
+	}
+}
+
+/// Signs the message for the P2WPKH address of the key, returning the serialized witness.
+/// Segwit addresses are defined for the compressed keys only.
+pub fn bip322_sign_p2wpkh(private: &Private, message: &[u8]) -> Result<Bytes, keys::Error> {
+	if !private.compressed {
+		return Err(keys::Error::InvalidPrivate);
+	}
+
+	let keypair = KeyPair::from_private(Private {
+		network: private.network,
+		secret: private.secret.clone(),
+		compressed: true,
+	})?;
+	let address_hash = keypair.public().address_hash();
+	let to_spend = bip322_to_spend(&Builder::build_p2wpkh(&address_hash), message);
+	let signer: TransactionInputSigner = bip322_to_sign(&to_spend, vec![]).into();
+	let script_code = Builder::build_p2pkh(&address_hash);
+	let hash = signer.signature_hash(0, 0, &script_code, SignatureVersion::WitnessV0, SIGHASH_ALL);
+
+	let mut signature: Vec<u8> = keypair.private().sign(&hash)?.into();
+	signature.push(SIGHASH_ALL as u8);
+	let witness: ScriptWitness = vec![signature.into(), keypair.public().to_vec().into()];
+	Ok(serialize_list(&witness))
+}
+
+/// Verifies the 'simple' signature (serialized witness stack) of the message.
+/// Script pubkey must be a witness program, since the signature has no script sig.
+pub fn bip322_verify_simple(script_pubkey: &Script, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+	if script_pubkey.parse_witness_program().is_none() {
+		return Err(Error::WitnessProgramMismatch);
+	}
+
+	let mut reader = Reader::new(signature);
+	let witness: ScriptWitness = reader.read_list().map_err(|_| Error::WitnessMalleated)?;
+	if !reader.is_finished() {
+		return Err(Error::WitnessMalleated);
+	}
+
+	let to_spend = bip322_to_spend(script_pubkey, message);
+	let to_sign = bip322_to_sign(&to_spend, witness.clone());
+	let checker = TransactionSignatureChecker {
+		signer: TransactionInputSigner::from(to_sign),
+		input_index: 0,
+		input_amount: 0,
+	};
+	let flags = VerificationFlags::default()
+		.verify_p2sh(true)
+		.verify_strictenc(true)
+		.verify_dersig(true)
+		.verify_nulldummy(true)
+		.verify_cleanstack(true)
+		.verify_locktime(true)
+		.verify_checksequence(true)
+		.verify_witness(true)
+		.verify_discourage_upgradable_witness_program(true);
+	verify_script(&Script::new(Bytes::new()), script_pubkey, &witness, &flags, &checker, SignatureVersion::Base)
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::Transaction;
+	use keys::{Private, KeyPair};
+	use crypto::sha256;
+	use {Builder, Script, Error};
+	use super::*;
+
+	// BIP322 test vectors
+	const PRIVATE: &'static str = "L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k";
+
+	fn script_pubkey() -> Script {
+		let keypair = KeyPair::from_private(PRIVATE.into()).unwrap();
+		Builder::build_p2wpkh(&keypair.public().address_hash())
+	}
+
+	#[test]
+	fn test_bip322_message_hash() {
+		assert_eq!(bip322_message_hash(b""), "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1".into());
+		assert_eq!(bip322_message_hash(b"Hello World"), "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a".into());
+	}
+
+	#[test]
+	fn test_bip322_transactions() {
+		let to_spend = bip322_to_spend(&script_pubkey(), b"");
+		assert_eq!(to_spend.hash().reversed(), "c5680aa69bb8d860bf82d4e9cd3504b55dde018de765a91bb566283c545a99a7".into());
+		let to_sign: Transaction = bip322_to_sign(&to_spend, vec![]);
+		assert_eq!(to_sign.hash().reversed(), "1e9654e951a5ba44c8604c4de6c67fd78a27e81dcadcfe1edf638ba3aaebaed6".into());
+
+		let to_spend = bip322_to_spend(&script_pubkey(), b"Hello World");
+		assert_eq!(to_spend.hash().reversed(), "b79d196740ad5217771c1098fc4a4b51e0535c32236c71f1ea4d61a2d603352b".into());
+		let to_sign: Transaction = bip322_to_sign(&to_spend, vec![]);
+		assert_eq!(to_sign.hash().reversed(), "88737ae86f2077145f93cc4b153ae9a1cb8d56afa511988c149c5c8c9d93bddf".into());
+	}
+
+	#[test]
+	fn test_bip322_sign_and_verify_p2wpkh() {
+		let private: Private = PRIVATE.into();
+		let signature = bip322_sign_p2wpkh(&private, b"Hello World").unwrap();
+		assert_eq!(bip322_verify_simple(&script_pubkey(), b"Hello World", &signature), Ok(()));
+		assert_eq!(bip322_verify_simple(&script_pubkey(), b"Hello World!", &signature), Err(Error::EvalFalse));
+		assert_eq!(bip322_verify_simple(&script_pubkey(), b"Hello World", &signature[1..]), Err(Error::WitnessMalleated));
+
+		// legacy script pubkey can't be verified with the simple signature
+		let keypair = KeyPair::from_private(private).unwrap();
+		let p2pkh = Builder::build_p2pkh(&keypair.public().address_hash());
+		assert_eq!(bip322_verify_simple(&p2pkh, b"Hello World", &signature), Err(Error::WitnessProgramMismatch));
+	}
+
+	#[test]
+	fn test_bip322_sign_p2wpkh_uncompressed_key() {
+		let private: Private = "5HueCGU8rMjxEXxiPuD5BDku4MkFqeZyd4dZ1jvhTVqvbTLvyTJ".into();
+		assert!(!private.compressed);
+		assert_eq!(bip322_sign_p2wpkh(&private, b"Hello World"), Err(keys::Error::InvalidPrivate));
+	}
+
+	#[test]
+	fn test_bip322_verify_p2wsh() {
+		// anyone-can-spend P2WSH, which is satisfied by the witness script only
+		let witness_script = Builder::default().push_opcode(Opcode::OP_1).into_bytes();
+		let script_pubkey = Builder::build_p2wsh(&sha256(&witness_script));
+		let witness: ScriptWitness = vec![witness_script];
+		assert_eq!(bip322_verify_simple(&script_pubkey, b"", &serialize_list(&witness)), Ok(()));
+	}
+}
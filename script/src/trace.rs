@@ -0,0 +1,146 @@
+//! Script execution tracing
+
+use bytes::Bytes;
+use {Error, Opcode, Script, Stack};
+
+/// Script, evaluated by `verify_script`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScriptStage {
+	/// Input script
+	ScriptSig,
+	/// Script of the spent output
+	ScriptPubKey,
+	/// Serialized script of the P2SH output
+	RedeemScript,
+	/// Script of the witness program
+	WitnessScript,
+}
+
+/// Interpreter state before execution of the opcode
+#[derive(Debug)]
+pub struct TraceStep<'a> {
+	/// Position of the opcode in the script
+	pub pc: usize,
+	/// Opcode to execute
+	pub opcode: Opcode,
+	/// Data, pushed by the opcode
+	pub data: Option<&'a [u8]>,
+	/// False if opcode is in the not executed branch of the conditional
+	pub executing: bool,
+	/// Main stack
+	pub stack: &'a Stack<Bytes>,
+	/// Alt stack
+	pub altstack: &'a Stack<Bytes>,
+	/// Values of the entered conditionals
+	pub exec_stack: &'a [bool],
+}
+
+/// Receives state of the interpreter
+pub trait ScriptTracer {
+	/// Called by `verify_script` before evaluation of every script
+	fn script_started(&mut self, _stage: ScriptStage, _script: &Script) {
+	}
+
+	/// Called before every opcode of the script, including opcodes of not executed branches
+	fn step(&mut self, step: &TraceStep);
+
+	/// Called by `verify_script` after evaluation of every script
+	fn script_finished(&mut self, _stack: &Stack<Bytes>, _result: &Result<bool, Error>) {
+	}
+}
+
+/// Tracer, which ignores all events
+pub struct NoopTracer;
+
+impl ScriptTracer for NoopTracer {
+	#[inline]
+	fn step(&mut self, _step: &TraceStep) {
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use crypto::dhash160;
+	use {Builder, Error, NoopSignatureChecker, Opcode, Script, ScriptWitness, SignatureVersion, Stack, VerificationFlags,
+		eval_script_traced, verify_script_traced};
+	use super::{ScriptTracer, ScriptStage, TraceStep};
+
+	#[derive(Default)]
+	struct DummyTracer {
+		scripts: Vec<(ScriptStage, Option<bool>)>,
+		steps: Vec<(usize, Opcode, bool, usize, Vec<bool>)>,
+	}
+
+	impl ScriptTracer for DummyTracer {
+		fn script_started(&mut self, stage: ScriptStage, _script: &Script) {
+			self.scripts.push((stage, None));
+		}
+
+		fn step(&mut self, step: &TraceStep) {
+			self.steps.push((step.pc, step.opcode, step.executing, step.stack.len(), step.exec_stack.to_vec()));
+		}
+
+		fn script_finished(&mut self, _stack: &Stack<Bytes>, result: &Result<bool, Error>) {
+			self.scripts.last_mut().unwrap().1 = result.as_ref().ok().cloned();
+		}
+	}
+
+	#[test]
+	fn test_eval_script_traced() {
+		let script = Builder::default()
+			.push_opcode(Opcode::OP_1)
+			.push_opcode(Opcode::OP_IF)
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_ELSE)
+			.push_opcode(Opcode::OP_3)
+			.push_opcode(Opcode::OP_ENDIF)
+			.into_script();
+		let mut stack = Stack::new();
+		let mut tracer = DummyTracer::default();
+		let result = eval_script_traced(&mut stack, &script, &VerificationFlags::default(), &NoopSignatureChecker, SignatureVersion::Base, &mut tracer);
+		assert_eq!(result, Ok(true));
+		assert_eq!(tracer.steps, vec![
+			(0, Opcode::OP_1, true, 0, vec![]),
+			(1, Opcode::OP_IF, true, 1, vec![]),
+			(2, Opcode::OP_2, true, 0, vec![true]),
+			(3, Opcode::OP_ELSE, true, 1, vec![true]),
+			(4, Opcode::OP_3, false, 1, vec![false]),
+			(5, Opcode::OP_ENDIF, false, 1, vec![false]),
+		]);
+		// scripts are only reported by verify_script_traced
+		assert!(tracer.scripts.is_empty());
+	}
+
+	#[test]
+	fn test_verify_script_traced_p2sh() {
+		let redeem_script = Builder::default()
+			.push_opcode(Opcode::OP_2)
+			.push_opcode(Opcode::OP_EQUAL)
+			.into_script();
+		let script_pubkey = Builder::build_p2sh(&dhash160(&redeem_script));
+		let flags = VerificationFlags::default().verify_p2sh(true);
+
+		let valid_script_sig = Builder::default()
+			.push_opcode(Opcode::OP_2)
+			.push_data(&redeem_script)
+			.into_script();
+		let mut tracer = DummyTracer::default();
+		assert_eq!(verify_script_traced(&valid_script_sig, &script_pubkey, &ScriptWitness::default(), &flags, &NoopSignatureChecker, SignatureVersion::Base, &mut tracer), Ok(()));
+		assert_eq!(tracer.scripts, vec![
+			(ScriptStage::ScriptSig, Some(true)),
+			(ScriptStage::ScriptPubKey, Some(true)),
+			(ScriptStage::RedeemScript, Some(true)),
+		]);
+		assert_eq!(tracer.steps.len(), 2 + 3 + 2);
+
+		let invalid_script_sig = Builder::default()
+			.push_opcode(Opcode::OP_3)
+			.push_data(&redeem_script)
+			.into_script();
+		let mut tracer = DummyTracer::default();
+		assert_eq!(verify_script_traced(&invalid_script_sig, &script_pubkey, &ScriptWitness::default(), &flags, &NoopSignatureChecker, SignatureVersion::Base, &mut tracer), Err(Error::EvalFalse));
+		assert_eq!(tracer.scripts.last(), Some(&(ScriptStage::RedeemScript, Some(false))));
+		assert_eq!(tracer.steps.last(), Some(&(1, Opcode::OP_EQUAL, true, 2, vec![])));
+	}
+}
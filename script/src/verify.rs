@@ -1,3 +1,4 @@
+use std::borrow::Borrow;
 use keys::{Public, Signature, Message};
 use chain::constants::{
 	SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG,
@@ -49,14 +50,16 @@ impl SignatureChecker for NoopSignatureChecker {
 	}
 }
 
+/// Checks signatures of the transaction input. Signer is either owned or borrowed,
+/// so that checkers of all transaction inputs could share the same signer.
 #[derive(Debug)]
-pub struct TransactionSignatureChecker {
-	pub signer: TransactionInputSigner,
+pub struct TransactionSignatureChecker<S = TransactionInputSigner> {
+	pub signer: S,
 	pub input_index: usize,
 	pub input_amount: u64,
 }
 
-impl SignatureChecker for TransactionSignatureChecker {
+impl<S> SignatureChecker for TransactionSignatureChecker<S> where S: Borrow<TransactionInputSigner> {
 	fn verify_signature(
 		&self,
 		signature: &Signature,
@@ -74,7 +77,7 @@ impl SignatureChecker for TransactionSignatureChecker {
 		sighashtype: u32,
 		version: SignatureVersion
 	) -> bool {
-		let hash = self.signer.signature_hash(self.input_index, self.input_amount, script_code, version, sighashtype);
+		let hash = self.signer.borrow().signature_hash(self.input_index, self.input_amount, script_code, version, sighashtype);
 		self.verify_signature(signature, public, &hash)
 	}
 
@@ -88,15 +91,15 @@ impl SignatureChecker for TransactionSignatureChecker {
 		// the nLockTime in the transaction.
 		let lock_time_u32: u32 = lock_time.into();
 		if !(
-			(self.signer.lock_time < LOCKTIME_THRESHOLD && lock_time_u32 < LOCKTIME_THRESHOLD) ||
-			(self.signer.lock_time >= LOCKTIME_THRESHOLD && lock_time_u32 >= LOCKTIME_THRESHOLD)
+			(self.signer.borrow().lock_time < LOCKTIME_THRESHOLD && lock_time_u32 < LOCKTIME_THRESHOLD) ||
+			(self.signer.borrow().lock_time >= LOCKTIME_THRESHOLD && lock_time_u32 >= LOCKTIME_THRESHOLD)
 		) {
 			return false;
 		}
 
 		// Now that we know we're comparing apples-to-apples, the
 		// comparison is a simple numeric one.
-		if i64::from(lock_time) > self.signer.lock_time as i64 {
+		if i64::from(lock_time) > self.signer.borrow().lock_time as i64 {
 			return false;
 		}
 
@@ -110,17 +113,17 @@ impl SignatureChecker for TransactionSignatureChecker {
 		// prevent this condition. Alternatively we could test all
 		// inputs, but testing just this input minimizes the data
 		// required to prove correct CHECKLOCKTIMEVERIFY execution.
-		SEQUENCE_FINAL != self.signer.inputs[self.input_index].sequence
+		SEQUENCE_FINAL != self.signer.borrow().inputs[self.input_index].sequence
 	}
 
 	fn check_sequence(&self, sequence: Num) -> bool {
 		// Relative lock times are supported by comparing the passed
 		// in operand to the sequence number of the input.
-		let to_sequence: i64 = self.signer.inputs[self.input_index].sequence as i64;
+		let to_sequence: i64 = self.signer.borrow().inputs[self.input_index].sequence as i64;
 
 		// Fail if the transaction's version number is not set high
 		// enough to trigger BIP 68 rules.
-		if (self.signer.version as u32) < 2 {
+		if (self.signer.borrow().version as u32) < 2 {
 			return false;
 		}
 
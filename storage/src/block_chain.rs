@@ -26,6 +26,9 @@ pub trait BlockChain {
 
 	/// Checks block origin
 	fn block_origin(&self, header: &IndexedBlockHeader) -> Result<BlockOrigin, Error>;
+
+	/// Returns hashes of stored blocks, which have no stored children
+	fn chain_tips(&self) -> Vec<H256>;
 }
 
 pub trait Forkable {
@@ -0,0 +1,13 @@
+use hash::H256;
+use chain::BlockFilter;
+
+pub trait BlockFilterProvider {
+	/// true if basic block filters are maintained for connected blocks
+	fn is_block_filter_index_enabled(&self) -> bool;
+
+	/// resolves basic block filter by block hash
+	fn block_filter(&self, hash: &H256) -> Option<BlockFilter>;
+
+	/// resolves basic block filter header by block hash
+	fn block_filter_header(&self, hash: &H256) -> Option<H256>;
+}
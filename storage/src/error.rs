@@ -15,6 +15,9 @@ pub enum Error {
 	/// Ancient fork
 	#[display(fmt = "Fork is too long to proceed")]
 	AncientFork,
+	/// Database format version is not supported
+	#[display(fmt = "Database format version {} is not supported, expected version {}", _0, _1)]
+	IncompatibleVersion(u32, u32),
 }
 
 impl From<Error> for String {
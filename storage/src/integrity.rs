@@ -0,0 +1,70 @@
+use std::fmt;
+use hash::H256;
+use Error;
+
+/// Inconsistency between database columns found by integrity check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+	/// Canon chain has no block with given number
+	MissingBlockHash(u32),
+	/// Canon block is not indexed by its number
+	BlockNumberMismatch(u32, H256),
+	/// Canon block header is missing
+	MissingBlockHeader(u32, H256),
+	/// Canon block is not a child of previous canon block
+	ParentMismatch(u32, H256),
+	/// List of canon block transactions is missing
+	MissingBlockTransactions(u32, H256),
+	/// Transaction of canon block is missing
+	MissingTransaction(u32, H256),
+	/// Meta of transaction from canon block with given number does not match the block
+	TransactionMetaMismatch(u32, H256),
+	/// Meta of transaction spent by canon block with given number is missing
+	MissingSpentTransactionMeta(u32, H256),
+	/// Output spent by canon block with given number is not marked as spent
+	SpentOutputNotMarked(u32, H256, u32),
+}
+
+impl Inconsistency {
+	/// Returns true if inconsistency can be fixed using data that is still in the database.
+	pub fn is_repairable(&self) -> bool {
+		match *self {
+			Inconsistency::BlockNumberMismatch(..) |
+			Inconsistency::TransactionMetaMismatch(..) |
+			Inconsistency::SpentOutputNotMarked(..) => true,
+			_ => false,
+		}
+	}
+}
+
+impl fmt::Display for Inconsistency {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Inconsistency::MissingBlockHash(number) =>
+				write!(f, "Canon block #{} is missing", number),
+			Inconsistency::BlockNumberMismatch(number, ref hash) =>
+				write!(f, "Canon block #{} {} has wrong number index", number, hash.to_reversed_str()),
+			Inconsistency::MissingBlockHeader(number, ref hash) =>
+				write!(f, "Header of canon block #{} {} is missing", number, hash.to_reversed_str()),
+			Inconsistency::ParentMismatch(number, ref hash) =>
+				write!(f, "Canon block #{} {} does not follow previous canon block", number, hash.to_reversed_str()),
+			Inconsistency::MissingBlockTransactions(number, ref hash) =>
+				write!(f, "Transactions list of canon block #{} {} is missing", number, hash.to_reversed_str()),
+			Inconsistency::MissingTransaction(number, ref hash) =>
+				write!(f, "Transaction {} of canon block #{} is missing", hash.to_reversed_str(), number),
+			Inconsistency::TransactionMetaMismatch(number, ref hash) =>
+				write!(f, "Meta of transaction {} of canon block #{} is wrong", hash.to_reversed_str(), number),
+			Inconsistency::MissingSpentTransactionMeta(number, ref hash) =>
+				write!(f, "Meta of transaction {} spent in canon block #{} is missing", hash.to_reversed_str(), number),
+			Inconsistency::SpentOutputNotMarked(number, ref hash, index) =>
+				write!(f, "Output {}:{} spent in canon block #{} is not marked as spent", hash.to_reversed_str(), index, number),
+		}
+	}
+}
+
+/// Database integrity checking interface
+pub trait IntegrityStore {
+	/// Checks that indexes of `depth` best canon blocks are consistent with each other and with block contents.
+	/// Repairable inconsistencies are fixed when `repair` is set. All found inconsistencies are returned.
+	fn check_integrity(&self, depth: u32, repair: bool) -> Result<Vec<Inconsistency>, Error>;
+}
@@ -13,6 +13,7 @@ extern crate network;
 mod best_block;
 mod block_ancestors;
 mod block_chain;
+mod block_filter_provider;
 mod block_impls;
 mod block_iterator;
 mod block_origin;
@@ -20,6 +21,7 @@ mod block_provider;
 mod block_ref;
 mod duplex_store;
 mod error;
+mod integrity;
 mod store;
 mod transaction_meta;
 mod transaction_provider;
@@ -29,13 +31,15 @@ pub use primitives::{hash, bytes};
 pub use best_block::BestBlock;
 pub use block_ancestors::BlockAncestors;
 pub use block_chain::{BlockChain, ForkChain, Forkable};
+pub use block_filter_provider::BlockFilterProvider;
 pub use block_iterator::BlockIterator;
 pub use block_origin::{BlockOrigin, SideChainOrigin};
 pub use block_provider::{BlockHeaderProvider, BlockProvider};
 pub use block_ref::BlockRef;
 pub use duplex_store::{DuplexTransactionOutputProvider, NoopStore, transaction_index_for_output_check};
 pub use error::Error;
-pub use store::{AsSubstore, Store, SharedStore, CanonStore, ConfigStore};
+pub use integrity::{Inconsistency, IntegrityStore};
+pub use store::{AsSubstore, Store, SharedStore, CanonStore, ConfigStore, MigrationProgress};
 pub use transaction_meta::TransactionMeta;
 pub use transaction_provider::{
 	TransactionProvider, TransactionOutputProvider, TransactionMetaProvider, CachedTransactionOutputProvider,
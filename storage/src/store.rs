@@ -1,11 +1,13 @@
 use std::sync::Arc;
+use bytes::Bytes;
+use hash::H256;
 use chain::IndexedBlockHeader;
 use {
 	BestBlock, BlockProvider, BlockHeaderProvider, TransactionProvider, TransactionMetaProvider,
-	TransactionOutputProvider, BlockChain, Forkable, Error,
+	TransactionOutputProvider, BlockChain, Forkable, Error, BlockFilterProvider, IntegrityStore,
 };
 
-pub trait CanonStore: Store + Forkable + ConfigStore {
+pub trait CanonStore: Store + Forkable + ConfigStore + IntegrityStore {
 	fn as_store(&self) -> &dyn Store;
 }
 
@@ -16,6 +18,35 @@ pub trait ConfigStore {
 
 	/// set consensus_fork this database is configured for
 	fn set_consensus_fork(&self, consensus_fork: &str) -> Result<(), Error>;
+
+	/// get format version of the database
+	fn format_version(&self) -> Result<u32, Error>;
+
+	/// upgrade database to the latest format version, resuming interrupted upgrade if any
+	fn migrate(&self, progress: &mut dyn FnMut(MigrationProgress)) -> Result<(), Error>;
+
+	/// get opaque position of the last blocks import, used to resume interrupted import
+	fn import_position(&self) -> Result<Option<Bytes>, Error>;
+
+	/// set opaque position of the blocks import
+	fn set_import_position(&self, position: &[u8]) -> Result<(), Error>;
+
+	/// get hashes of blocks, manually marked as invalid
+	fn invalidated_blocks(&self) -> Result<Vec<H256>, Error>;
+
+	/// set hashes of blocks, manually marked as invalid
+	fn set_invalidated_blocks(&self, hashes: &[H256]) -> Result<(), Error>;
+}
+
+/// Progress of the database format upgrade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MigrationProgress {
+	/// Format version the database is upgraded to
+	pub version: u32,
+	/// Number of blocks already upgraded
+	pub processed: u32,
+	/// Total number of blocks to upgrade
+	pub total: u32,
 }
 
 /// Blockchain storage interface
@@ -31,7 +62,7 @@ pub trait Store: AsSubstore {
 }
 
 /// Allows casting Arc<Store> to reference to any substore type
-pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider {
+pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider + BlockFilterProvider {
 	fn as_block_provider(&self) -> &dyn BlockProvider;
 
 	fn as_block_header_provider(&self) -> &dyn BlockHeaderProvider;
@@ -41,9 +72,11 @@ pub trait AsSubstore: BlockChain + BlockProvider + TransactionProvider + Transac
 	fn as_transaction_output_provider(&self) -> &dyn TransactionOutputProvider;
 
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider;
+
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider;
 }
 
-impl<T> AsSubstore for T where T: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider {
+impl<T> AsSubstore for T where T: BlockChain + BlockProvider + TransactionProvider + TransactionMetaProvider + TransactionOutputProvider + BlockFilterProvider {
 	fn as_block_provider(&self) -> &dyn BlockProvider {
 		&*self
 	}
@@ -63,6 +96,10 @@ impl<T> AsSubstore for T where T: BlockChain + BlockProvider + TransactionProvid
 	fn as_transaction_meta_provider(&self) -> &dyn TransactionMetaProvider {
 		&*self
 	}
+
+	fn as_block_filter_provider(&self) -> &dyn BlockFilterProvider {
+		&*self
+	}
 }
 
 pub type SharedStore = Arc<dyn CanonStore + Send + Sync>;
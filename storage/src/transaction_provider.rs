@@ -3,7 +3,7 @@ use parking_lot::RwLock;
 use hash::H256;
 use bytes::Bytes;
 use chain::{IndexedTransaction, OutPoint, TransactionOutput};
-use {TransactionMeta};
+use {TransactionMeta, BestBlock, Error};
 
 /// Should be used to obtain all transactions from canon chain and forks.
 pub trait TransactionProvider {
@@ -33,6 +33,11 @@ pub trait TransactionMetaProvider: Send + Sync {
 	/// Returns None if transactin with given hash does not exist
 	/// Otherwise returns transaction meta object
 	fn transaction_meta(&self, hash: &H256) -> Option<TransactionMeta>;
+
+	/// Visits metas of canon chain transactions in the order of transaction hashes, starting after given hash,
+	/// until visitor returns false. The best block can't change while metas are visited, so it is returned along.
+	/// Blocks are not canonized while visiting, so long scans should be split into multiple calls.
+	fn for_each_transaction_meta(&self, after: Option<&H256>, visitor: &mut dyn FnMut(&H256, &TransactionMeta) -> bool) -> Result<BestBlock, Error>;
 }
 
 /// Transaction output provider that caches all read outputs.
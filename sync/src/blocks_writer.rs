@@ -8,9 +8,9 @@ use primitives::hash::H256;
 use super::Error;
 use synchronization_chain::Chain;
 use synchronization_verifier::{Verifier, SyncVerifier, VerificationTask,
-	VerificationSink, BlockVerificationSink, TransactionVerificationSink};
+	VerificationSink, BlockVerificationSink, TransactionVerificationSink, PackageVerificationSink};
 use types::StorageRef;
-use utils::OrphanBlocksPool;
+use utils::{OrphanBlocksPool, TransactionRejection, PackageAcceptance};
 use VerificationParameters;
 
 /// Maximum number of orphaned in-memory blocks
@@ -127,6 +127,10 @@ impl BlockVerificationSink for BlocksWriterSink {
 	fn on_block_verification_error(&self, err: &str, _hash: &H256) {
 		self.data.lock().err = Some(Error::Verification(err.into()));
 	}
+
+	fn is_assumed_valid_block(&self, hash: &H256) -> bool {
+		self.data.lock().chain.is_assumed_valid_block(hash)
+	}
 }
 
 impl TransactionVerificationSink for BlocksWriterSink {
@@ -134,11 +138,21 @@ impl TransactionVerificationSink for BlocksWriterSink {
 		unreachable!("not intended to verify transactions")
 	}
 
-	fn on_transaction_verification_error(&self, _err: &str, _hash: &H256) {
+	fn on_transaction_verification_error(&self, _rejection: &TransactionRejection, _hash: &H256) {
 		unreachable!("not intended to verify transactions")
 	}
 }
 
+impl PackageVerificationSink for BlocksWriterSink {
+	fn on_package_verification_success(&self, _package: Vec<chain::IndexedTransaction>, _acceptance: PackageAcceptance) -> Option<Vec<VerificationTask>> {
+		unreachable!("not intended to verify packages")
+	}
+
+	fn on_package_verification_error(&self, _acceptance: PackageAcceptance, _hash: &H256) {
+		// packages are always rejected while importing blocks && nobody is waiting for the result
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
@@ -146,7 +160,7 @@ mod tests {
 	use std::sync::Arc;
 	use db::{BlockChainDatabase};
 	use network::{ConsensusParams, ConsensusFork, Network};
-	use verification::VerificationLevel;
+	use verification::{VerificationLevel, Policy};
 	use super::super::Error;
 	use super::{BlocksWriter, MAX_ORPHANED_BLOCKS};
 	use VerificationParameters;
@@ -155,6 +169,8 @@ mod tests {
 		VerificationParameters {
 			verification_level: VerificationLevel::Full,
 			verification_edge: 0u8.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		}
 	}
 
@@ -221,6 +237,8 @@ mod tests {
 		let mut blocks_target = BlocksWriter::new(db.clone(), ConsensusParams::new(Network::Testnet, ConsensusFork::BitcoinCore), VerificationParameters {
 			verification_level: VerificationLevel::NoVerification,
 			verification_edge: 0u8.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
 		assert_eq!(blocks_target.append_block(b1.into()), Ok(()));
 		assert_eq!(blocks_target.append_block(b2.into()), Ok(()));
@@ -35,7 +35,10 @@ impl InboundSyncConnection for InboundConnection {
 		self.node.sync_state()
 	}
 
-	fn start_sync_session(&self, peer_name: String, version: types::Version) {
+	fn start_sync_session(&self, peer_name: String, version: types::Version, package_relay: bool) {
+		if package_relay {
+			self.peers.enable_package_relay(self.peer_index);
+		}
 		self.node.on_connect(self.peer_index, peer_name, version);
 	}
 
@@ -171,6 +174,64 @@ impl InboundSyncConnection for InboundConnection {
 	fn on_notfound(&self, message: types::NotFound) {
 		self.node.on_notfound(self.peer_index, message);
 	}
+
+	fn on_get_cfilters(&self, message: types::GetCFilters) {
+		self.node.on_get_cfilters(self.peer_index, message);
+	}
+
+	fn on_get_cfheaders(&self, message: types::GetCFHeaders) {
+		self.node.on_get_cfheaders(self.peer_index, message);
+	}
+
+	fn on_get_cfcheckpt(&self, message: types::GetCFCheckpt) {
+		self.node.on_get_cfcheckpt(self.peer_index, message);
+	}
+
+	fn on_ancpkginfo(&self, message: types::AncPkgInfo) {
+		// if package is empty - just ignore this message
+		if message.hashes.is_empty() {
+			return;
+		}
+		// if package is too big => possible DOS
+		if message.hashes.len() > types::ANCPKGINFO_MAX_HASHES_LEN {
+			self.peers.dos(self.peer_index, &format!("'ancpkginfo' message contains {} entries", message.hashes.len()));
+			return;
+		}
+
+		self.node.on_ancpkginfo(self.peer_index, message);
+	}
+
+	fn on_getpkgtxns(&self, message: types::GetPkgTxns) {
+		// if request is empty - just ignore this message
+		if message.hashes.is_empty() {
+			return;
+		}
+		// if request is too big => possible DOS
+		if message.hashes.len() > types::GETPKGTXNS_MAX_HASHES_LEN {
+			self.peers.dos(self.peer_index, &format!("'getpkgtxns' message contains {} entries", message.hashes.len()));
+			return;
+		}
+
+		self.node.on_getpkgtxns(self.peer_index, message);
+	}
+
+	fn on_pkgtxns(&self, message: types::PkgTxns) {
+		// if package is empty - just ignore this message
+		if message.transactions.is_empty() {
+			return;
+		}
+		// if package is too big => possible DOS
+		if message.transactions.len() > types::PKGTXNS_MAX_TRANSACTIONS_LEN {
+			self.peers.dos(self.peer_index, &format!("'pkgtxns' message contains {} transactions", message.transactions.len()));
+			return;
+		}
+
+		let transactions: Vec<_> = message.transactions.into_iter().map(IndexedTransaction::from_raw).collect();
+		for tx in &transactions {
+			self.peers.hash_known_as(self.peer_index, tx.hash.clone(), KnownHashType::Transaction);
+		}
+		self.node.on_pkgtxns(self.peer_index, transactions);
+	}
 }
 
 #[cfg(test)]
@@ -217,7 +278,14 @@ pub mod tests {
 		fn send_get_block_txn(&self, _message: &types::GetBlockTxn) { *self.messages.lock().entry("getblocktxn".to_owned()).or_insert(0) += 1; }
 		fn send_block_txn(&self, _message: &types::BlockTxn) { *self.messages.lock().entry("blocktxn".to_owned()).or_insert(0) += 1; }
 		fn send_notfound(&self, _message: &types::NotFound) { *self.messages.lock().entry("notfound".to_owned()).or_insert(0) += 1; }
+		fn send_cfilter(&self, _message: &types::CFilter) { *self.messages.lock().entry("cfilter".to_owned()).or_insert(0) += 1; }
+		fn send_cfheaders(&self, _message: &types::CFHeaders) { *self.messages.lock().entry("cfheaders".to_owned()).or_insert(0) += 1; }
+		fn send_cfcheckpt(&self, _message: &types::CFCheckpt) { *self.messages.lock().entry("cfcheckpt".to_owned()).or_insert(0) += 1; }
+		fn send_ancpkginfo(&self, _message: &types::AncPkgInfo) { *self.messages.lock().entry("ancpkginfo".to_owned()).or_insert(0) += 1; }
+		fn send_getpkgtxns(&self, _message: &types::GetPkgTxns) { *self.messages.lock().entry("getpkgtxns".to_owned()).or_insert(0) += 1; }
+		fn send_pkgtxns(&self, _message: &types::PkgTxns) { *self.messages.lock().entry("pkgtxns".to_owned()).or_insert(0) += 1; }
 		fn ignored(&self, _id: RequestId) {}
 		fn close(&self) {}
+		fn connect_extra_outbound(&self) { *self.messages.lock().entry("extra_outbound".to_owned()).or_insert(0) += 1; }
 	}
 }
@@ -33,7 +33,7 @@ impl InboundConnectionFactory {
 impl LocalSyncNode for InboundConnectionFactory {
 	fn create_sync_session(&self, _best_block_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
 		let peer_index = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
-		trace!(target: "sync", "Creating new sync session with peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Creating new sync session with peer#{}", peer_index);
 		// remember outbound connection
 		self.peers.insert(peer_index, services, outbound_connection);
 		// create new inbound connection
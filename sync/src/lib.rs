@@ -20,6 +20,7 @@ extern crate script;
 extern crate serialization as ser;
 extern crate rand;
 extern crate network;
+extern crate metrics;
 
 mod blocks_writer;
 mod inbound_connection;
@@ -39,6 +40,10 @@ mod utils;
 
 pub use types::LocalNodeRef;
 pub use types::PeersRef;
+pub use types::VerificationStatsRef;
+pub use synchronization_client_core::Information;
+pub use synchronization_verifier::VerificationStats;
+pub use utils::{TransactionAcceptance, TransactionRejection, PackageAcceptance};
 
 use std::sync::Arc;
 use parking_lot::RwLock;
@@ -66,6 +71,10 @@ pub struct VerificationParameters {
 	/// Blocks verification edge: all blocks before this are validated using verification_level.
 	/// All blocks after this (inclusive) are validated using VerificationLevel::Full level.
 	pub verification_edge: H256,
+	/// Number of threads, verifying scripts of the block transactions (0 => one thread per CPU core).
+	pub script_threads: usize,
+	/// Relay policy of the memory pool transactions.
+	pub policy: verification::Policy,
 }
 
 /// Synchronization events listener
@@ -74,6 +83,8 @@ pub trait SyncListener: Send + 'static {
 	fn synchronization_state_switched(&self, is_synchronizing: bool);
 	/// Called when new best storage block is inserted
 	fn best_storage_block_inserted(&self, block_hash: &H256);
+	/// Called when blocks are removed from the best storage chain, before the new best block is reported
+	fn best_storage_blocks_decanonized(&self, _blocks_hashes: &[H256]) {}
 }
 
 /// Create blocks writer.
@@ -119,7 +130,8 @@ pub fn create_local_sync_node(consensus: ConsensusParams, db: storage::SharedSto
 		peers.require_peer_services(Services::default().with_witness(true));
 	}
 
-	let chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone()));
+	let chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone())
+		.with_script_threads(verification_params.script_threads));
 	let sync_executor = SyncExecutor::new(peers.clone());
 	let sync_server = Arc::new(ServerImpl::new(peers.clone(), db.clone(), memory_pool.clone(), sync_executor.clone()));
 	let sync_client_core = SynchronizationClientCore::new(sync_client_config, sync_state.clone(), peers.clone(), sync_executor.clone(), sync_chain, chain_verifier.clone());
@@ -4,17 +4,22 @@ use time;
 use futures::{lazy, finished};
 use chain::{IndexedTransaction, IndexedBlock, IndexedBlockHeader};
 use message::types;
-use miner::BlockAssembler;
+use message::types::reject::RejectCode;
+use miner::{BlockAssembler, MemoryPoolListener};
 use network::ConsensusParams;
 use synchronization_client::{Client};
+use synchronization_client_core::Information;
 use synchronization_server::{Server, ServerTask};
-use synchronization_verifier::{TransactionVerificationSink};
+use synchronization_verifier::{TransactionVerificationSink, PackageVerificationSink, VerificationTask};
 use primitives::hash::H256;
 use miner::BlockTemplate;
-use verification::median_timestamp_inclusive;
+use verification::{median_timestamp_inclusive, BackwardsCompatibleChainVerifier as ChainVerifier, TransactionError};
+use script::ScriptTracer;
+use storage::DuplexTransactionOutputProvider;
 use synchronization_peers::{TransactionAnnouncementType, BlockAnnouncementType};
 use types::{PeerIndex, RequestId, StorageRef, MemoryPoolRef, PeersRef,
-	ClientRef, ServerRef, SynchronizationStateRef, SyncListenerRef};
+	ClientRef, ServerRef, SynchronizationStateRef, SyncListenerRef, VerificationStatsRef};
+use utils::{TransactionAcceptance, TransactionRejection, PackageAcceptance};
 
 /// Local synchronization node
 pub struct LocalNode<U: Server, V: Client> {
@@ -41,7 +46,18 @@ struct TransactionAcceptSink {
 
 #[derive(Default)]
 struct TransactionAcceptSinkData {
-	result: Mutex<Option<Result<H256, String>>>,
+	result: Mutex<Option<Result<H256, TransactionRejection>>>,
+	waiter: Condvar,
+}
+
+/// Package accept verification sink
+struct PackageAcceptSink {
+	data: Arc<PackageAcceptSinkData>,
+}
+
+#[derive(Default)]
+struct PackageAcceptSinkData {
+	result: Mutex<Option<PackageAcceptance>>,
 	waiter: Condvar,
 }
 
@@ -68,7 +84,7 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 
 	/// When new peer connects to the node
 	pub fn on_connect(&self, peer_index: PeerIndex, peer_name: String, version: types::Version) {
-		trace!(target: "sync", "Starting new sync session with peer#{}: {}", peer_index, peer_name);
+		trace!(target: "sync", peer = peer_index; "Starting new sync session with peer#{}: {}", peer_index, peer_name);
 
 		// light clients may not want transactions broadcasting until filter for connection is set
 		if !version.relay_transactions() {
@@ -81,7 +97,7 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 
 	/// When peer disconnects
 	pub fn on_disconnect(&self, peer_index: PeerIndex) {
-		trace!(target: "sync", "Stopping sync session with peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Stopping sync session with peer#{}", peer_index);
 
 		// stop synchronization session with peer
 		self.client.on_disconnect(peer_index);
@@ -89,49 +105,49 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 
 	/// When inventory message is received
 	pub fn on_inventory(&self, peer_index: PeerIndex, message: types::Inv) {
-		trace!(target: "sync", "Got `inventory` message from peer#{}. Inventory len: {}", peer_index, message.inventory.len());
+		trace!(target: "sync", peer = peer_index; "Got `inventory` message from peer#{}. Inventory len: {}", peer_index, message.inventory.len());
 		self.client.on_inventory(peer_index, message);
 	}
 
 	/// When headers message is received
 	pub fn on_headers(&self, peer_index: PeerIndex, headers: Vec<IndexedBlockHeader>) {
-		trace!(target: "sync", "Got `headers` message from peer#{}. Headers len: {}", peer_index, headers.len());
+		trace!(target: "sync", peer = peer_index; "Got `headers` message from peer#{}. Headers len: {}", peer_index, headers.len());
 		self.client.on_headers(peer_index, headers);
 	}
 
 	/// When transaction is received
 	pub fn on_transaction(&self, peer_index: PeerIndex, tx: IndexedTransaction) {
-		trace!(target: "sync", "Got `transaction` message from peer#{}. Tx hash: {}", peer_index, tx.hash.to_reversed_str());
+		trace!(target: "sync", peer = peer_index; "Got `transaction` message from peer#{}. Tx hash: {}", peer_index, tx.hash.to_reversed_str());
 		self.client.on_transaction(peer_index, tx);
 	}
 
 	/// When block is received
 	pub fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
-		trace!(target: "sync", "Got `block` message from peer#{}. Block hash: {}", peer_index, block.header.hash.to_reversed_str());
+		trace!(target: "sync", peer = peer_index, block:% = block.header.hash.reversed(); "Got `block` message from peer#{}. Block hash: {}", peer_index, block.header.hash.to_reversed_str());
 		self.client.on_block(peer_index, block);
 	}
 
 	/// When notfound is received
 	pub fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound) {
-		trace!(target: "sync", "Got `notfound` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `notfound` message from peer#{}", peer_index);
 		self.client.on_notfound(peer_index, message);
 	}
 
 	/// When peer is requesting for items
 	pub fn on_getdata(&self, peer_index: PeerIndex, message: types::GetData) {
-		trace!(target: "sync", "Got `getdata` message from peer#{}. Inventory len: {}", peer_index, message.inventory.len());
+		trace!(target: "sync", peer = peer_index; "Got `getdata` message from peer#{}. Inventory len: {}", peer_index, message.inventory.len());
 		self.server.execute(ServerTask::GetData(peer_index, message));
 	}
 
 	/// When peer is requesting for known blocks hashes
 	pub fn on_getblocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
-		trace!(target: "sync", "Got `getblocks` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `getblocks` message from peer#{}", peer_index);
 		self.server.execute(ServerTask::GetBlocks(peer_index, message));
 	}
 
 	/// When peer is requesting for known blocks headers
 	pub fn on_getheaders(&self, peer_index: PeerIndex, message: types::GetHeaders, id: RequestId) {
-		trace!(target: "sync", "Got `getheaders` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `getheaders` message from peer#{}", peer_index);
 
 		// simulating bitcoind for passing tests: if we are in nearly-saturated state
 		// and peer, which has just provided a new blocks to us, is asking for headers
@@ -147,54 +163,72 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 
 	/// When peer is requesting for memory pool contents
 	pub fn on_mempool(&self, peer_index: PeerIndex, _message: types::MemPool) {
-		trace!(target: "sync", "Got `mempool` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `mempool` message from peer#{}", peer_index);
 		self.server.execute(ServerTask::Mempool(peer_index));
 	}
 
 	/// When peer asks us from specific transactions from specific block
 	pub fn on_get_block_txn(&self, peer_index: PeerIndex, message: types::GetBlockTxn) {
 		if self.state.synchronizing() {
-			trace!(target: "sync", "Ignored `getblocktxn` message from peer#{}", peer_index);
+			trace!(target: "sync", peer = peer_index; "Ignored `getblocktxn` message from peer#{}", peer_index);
 			return;
 		}
 
-		trace!(target: "sync", "Got `getblocktxn` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `getblocktxn` message from peer#{}", peer_index);
 		self.server.execute(ServerTask::GetBlockTxn(peer_index, message));
 	}
 
+	/// When peer is requesting for compact block filters
+	pub fn on_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		trace!(target: "sync", peer = peer_index; "Got `getcfilters` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFilters(peer_index, message));
+	}
+
+	/// When peer is requesting for compact block filter headers
+	pub fn on_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		trace!(target: "sync", peer = peer_index; "Got `getcfheaders` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFHeaders(peer_index, message));
+	}
+
+	/// When peer is requesting for compact block filter checkpoints
+	pub fn on_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		trace!(target: "sync", peer = peer_index; "Got `getcfcheckpt` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetCFCheckpt(peer_index, message));
+	}
+
 	/// When peer sets bloom filter for connection
 	pub fn on_filterload(&self, peer_index: PeerIndex, message: types::FilterLoad) {
-		trace!(target: "sync", "Got `filterload` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `filterload` message from peer#{}", peer_index);
 		self.peers.set_bloom_filter(peer_index, message);
 	}
 
 	/// When peer updates bloom filter for connection
 	pub fn on_filteradd(&self, peer_index: PeerIndex, message: types::FilterAdd) {
-		trace!(target: "sync", "Got `filteradd` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `filteradd` message from peer#{}", peer_index);
 		self.peers.update_bloom_filter(peer_index, message);
 	}
 
 	/// When peer removes bloom filter from connection
 	pub fn on_filterclear(&self, peer_index: PeerIndex, _message: types::FilterClear) {
-		trace!(target: "sync", "Got `filterclear` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `filterclear` message from peer#{}", peer_index);
 		self.peers.clear_bloom_filter(peer_index);
 	}
 
 	/// When peer sets up a minimum fee rate filter for connection
 	pub fn on_feefilter(&self, peer_index: PeerIndex, message: types::FeeFilter) {
-		trace!(target: "sync", "Got `feefilter` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `feefilter` message from peer#{}", peer_index);
 		self.peers.set_fee_filter(peer_index, message);
 	}
 
 	/// When peer asks us to announce new blocks using headers message
 	pub fn on_sendheaders(&self, peer_index: PeerIndex, _message: types::SendHeaders) {
-		trace!(target: "sync", "Got `sendheaders` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `sendheaders` message from peer#{}", peer_index);
 		self.peers.set_block_announcement_type(peer_index, BlockAnnouncementType::SendHeaders);
 	}
 
 	/// When peer asks us to announce new blocks using cpmctblock message
 	pub fn on_send_compact(&self, peer_index: PeerIndex, message: types::SendCompact) {
-		trace!(target: "sync", "Got `sendcmpct` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `sendcmpct` message from peer#{}", peer_index);
 
 		// The second integer SHALL be interpreted as a little-endian version number. Nodes sending a sendcmpct message MUST currently set this value to 1.
 		// TODO: version 2 supports segregated witness transactions
@@ -215,37 +249,155 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 
 	/// When peer sents us a merkle block
 	pub fn on_merkleblock(&self, peer_index: PeerIndex, _message: types::MerkleBlock) {
-		trace!(target: "sync", "Got `merkleblock` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `merkleblock` message from peer#{}", peer_index);
 		// we never setup filter on connections => misbehaving
 		self.peers.misbehaving(peer_index, "Got unrequested 'merkleblock' message");
 	}
 
 	/// When peer sents us a compact block
 	pub fn on_compact_block(&self, peer_index: PeerIndex, _message: types::CompactBlock) {
-		trace!(target: "sync", "Got `cmpctblock` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `cmpctblock` message from peer#{}", peer_index);
 		// we never ask compact block from peers => misbehaving
 		self.peers.misbehaving(peer_index, "Got unrequested 'cmpctblock' message");
 	}
 
 	/// When peer sents us specific transactions for specific block
 	pub fn on_block_txn(&self, peer_index: PeerIndex, _message: types::BlockTxn) {
-		trace!(target: "sync", "Got `blocktxn` message from peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Got `blocktxn` message from peer#{}", peer_index);
 		// we never ask for this => misbehaving
 		self.peers.misbehaving(peer_index, "Got unrequested 'blocktxn' message");
 	}
 
+	/// When peer sents us unconfirmed ancestors of the transaction
+	pub fn on_ancpkginfo(&self, peer_index: PeerIndex, message: types::AncPkgInfo) {
+		if !self.is_package_relay_message_allowed(peer_index, "ancpkginfo") {
+			return;
+		}
+
+		trace!(target: "sync", peer = peer_index; "Got `ancpkginfo` message from peer#{}. Package len: {}", peer_index, message.hashes.len());
+		self.client.on_ancpkginfo(peer_index, message);
+	}
+
+	/// When peer asks us for package transactions
+	pub fn on_getpkgtxns(&self, peer_index: PeerIndex, message: types::GetPkgTxns) {
+		if !self.is_package_relay_message_allowed(peer_index, "getpkgtxns") {
+			return;
+		}
+
+		trace!(target: "sync", peer = peer_index; "Got `getpkgtxns` message from peer#{}. Package len: {}", peer_index, message.hashes.len());
+		self.server.execute(ServerTask::GetPkgTxns(peer_index, message));
+	}
+
+	/// When peer sents us package transactions
+	pub fn on_pkgtxns(&self, peer_index: PeerIndex, transactions: Vec<IndexedTransaction>) {
+		if !self.is_package_relay_message_allowed(peer_index, "pkgtxns") {
+			return;
+		}
+
+		trace!(target: "sync", peer = peer_index; "Got `pkgtxns` message from peer#{}. Package len: {}", peer_index, transactions.len());
+		self.client.on_pkgtxns(peer_index, transactions);
+	}
+
 	/// Verify and then schedule new transaction
-	pub fn accept_transaction(&self, transaction: IndexedTransaction) -> Result<H256, String> {
+	pub fn accept_transaction(&self, transaction: IndexedTransaction) -> Result<H256, TransactionRejection> {
+		if let Some(rejection) = self.duplicate_transaction_rejection(&transaction.hash) {
+			return Err(rejection);
+		}
+
+		// transaction is verified once, in the verification thread => rejection reason is passed back by the sink
 		let sink_data = Arc::new(TransactionAcceptSinkData::default());
 		let sink = TransactionAcceptSink::new(sink_data.clone()).boxed();
-		{
-			if let Err(err) = self.client.accept_transaction(transaction, sink) {
-				return Err(err.into());
-			}
+		self.client.accept_transaction(transaction, sink)?;
+		sink_data.wait()
+	}
+
+	/// Check if transaction could be accepted to the memory pool, without accepting it
+	pub fn test_accept_transaction(&self, transaction: &IndexedTransaction) -> TransactionAcceptance {
+		match self.duplicate_transaction_rejection(&transaction.hash) {
+			Some(rejection) => TransactionAcceptance {
+				result: Err(rejection),
+				..TransactionAcceptance::new(transaction)
+			},
+			None => self.client.test_accept_transaction(transaction),
+		}
+	}
+
+	/// Rejects transaction, which is already in the memory pool or in the storage
+	fn duplicate_transaction_rejection(&self, hash: &H256) -> Option<TransactionRejection> {
+		let reason = if self.memory_pool.read().contains(hash) {
+			"txn-already-in-mempool"
+		} else if self.storage.transaction_meta(hash).is_some() {
+			"txn-already-known"
+		} else {
+			return None;
+		};
+
+		Some(TransactionRejection::new(RejectCode::Duplicate, reason.to_owned()))
+	}
+
+	/// Verify package of the child transaction with its unconfirmed parents and insert it to the memory pool
+	pub fn accept_package(&self, package: Vec<IndexedTransaction>) -> PackageAcceptance {
+		if self.state.synchronizing() {
+			return PackageAcceptance {
+				result: Err(TransactionRejection::new(RejectCode::Invalid, "Cannot accept package as node is not yet fully synchronized".to_owned())),
+				..PackageAcceptance::new(&package)
+			};
+		}
+
+		// package is verified in the verification thread => acceptance is passed back by the sink
+		let sink_data = Arc::new(PackageAcceptSinkData::default());
+		let sink = PackageAcceptSink::new(sink_data.clone()).boxed();
+		let rejected_acceptance = PackageAcceptance::new(&package);
+		if let Err(rejection) = self.client.accept_package(package, sink) {
+			return PackageAcceptance {
+				result: Err(rejection),
+				..rejected_acceptance
+			};
 		}
 		sink_data.wait()
 	}
 
+	/// Package relay messages are only allowed after negotiation && when we are not synchronizing
+	fn is_package_relay_message_allowed(&self, peer_index: PeerIndex, command: &str) -> bool {
+		if !self.peers.is_package_relay_enabled(peer_index) {
+			self.peers.misbehaving(peer_index, &format!("Got '{}' message without package relay negotiated", command));
+			return false;
+		}
+
+		if self.state.synchronizing() {
+			trace!(target: "sync", peer = peer_index; "Ignored `{}` message from peer#{}", command, peer_index);
+			return false;
+		}
+
+		true
+	}
+
+	/// Replay scripts of the transaction input with flags of the next block, reporting every evaluated opcode to the tracer.
+	/// Spent outputs are searched for in both storage && memory pool, so both confirmed and unconfirmed transactions could be traced.
+	pub fn trace_transaction_input(&self, transaction: &IndexedTransaction, input_index: usize, tracer: &mut dyn ScriptTracer) -> Result<(), TransactionError> {
+		let memory_pool = self.memory_pool.read();
+		let tx_output_provider = DuplexTransactionOutputProvider::new(self.storage.as_transaction_output_provider(), &*memory_pool);
+		let verifier = ChainVerifier::new(self.storage.clone(), self.consensus.clone());
+		let height = self.storage.best_block().number + 1;
+		let time = time::get_time().sec as u32;
+		verifier.trace_transaction_input(self.storage.as_block_header_provider(), &tx_output_provider, height, time, transaction, input_index, tracer)
+	}
+
+	/// Mark block as invalid and switch to the best chain without invalidated blocks
+	pub fn invalidate_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.invalidate_block(hash)
+	}
+
+	/// Remove invalid mark from the block and switch to the best chain without invalidated blocks
+	pub fn reconsider_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.reconsider_block(hash)
+	}
+
+	/// Prefer chain with given block over other chains of the same length
+	pub fn precious_block(&self, hash: &H256) -> Result<(), String> {
+		self.client.precious_block(hash)
+	}
+
 	/// Get block template for mining
 	pub fn get_block_template(&self) -> BlockTemplate {
 		let previous_block_height = self.storage.best_block().number;
@@ -265,6 +417,21 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 	pub fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.client.install_sync_listener(listener);
 	}
+
+	/// Install memory pool events listener
+	pub fn install_memory_pool_listener(&self, listener: Box<dyn MemoryPoolListener>) {
+		self.memory_pool.write().install_listener(listener);
+	}
+
+	/// Get information on current synchronization state
+	pub fn information(&self) -> Information {
+		self.client.information()
+	}
+
+	/// Get verification statistics
+	pub fn verification_stats(&self) -> VerificationStatsRef {
+		self.client.verification_stats()
+	}
 }
 
 impl TransactionAcceptSink {
@@ -280,7 +447,7 @@ impl TransactionAcceptSink {
 }
 
 impl TransactionAcceptSinkData {
-	pub fn wait(&self) -> Result<H256, String> {
+	pub fn wait(&self) -> Result<H256, TransactionRejection> {
 		let mut lock = self.result.lock();
 		if lock.is_some() {
 			return lock.take().expect("checked line above");
@@ -297,8 +464,45 @@ impl TransactionVerificationSink for TransactionAcceptSink {
 		self.data.waiter.notify_all();
 	}
 
-	fn on_transaction_verification_error(&self, err: &str, _hash: &H256) {
-		*self.data.result.lock() = Some(Err(err.to_owned()));
+	fn on_transaction_verification_error(&self, rejection: &TransactionRejection, _hash: &H256) {
+		*self.data.result.lock() = Some(Err(rejection.clone()));
+		self.data.waiter.notify_all();
+	}
+}
+
+impl PackageAcceptSink {
+	pub fn new(data: Arc<PackageAcceptSinkData>) -> Self {
+		PackageAcceptSink {
+			data: data,
+		}
+	}
+
+	pub fn boxed(self) -> Box<Self> {
+		Box::new(self)
+	}
+}
+
+impl PackageAcceptSinkData {
+	pub fn wait(&self) -> PackageAcceptance {
+		let mut lock = self.result.lock();
+		if lock.is_some() {
+			return lock.take().expect("checked line above");
+		}
+
+		self.waiter.wait(&mut lock);
+		lock.take().expect("waiter.wait returns only when result is set; lock.take() takes result from waiter.result; qed")
+	}
+}
+
+impl PackageVerificationSink for PackageAcceptSink {
+	fn on_package_verification_success(&self, _package: Vec<IndexedTransaction>, acceptance: PackageAcceptance) -> Option<Vec<VerificationTask>> {
+		*self.data.result.lock() = Some(acceptance);
+		self.data.waiter.notify_all();
+		None
+	}
+
+	fn on_package_verification_error(&self, acceptance: PackageAcceptance, _hash: &H256) {
+		*self.data.result.lock() = Some(acceptance);
 		self.data.waiter.notify_all();
 	}
 }
@@ -315,6 +519,7 @@ pub mod tests {
 	use synchronization_client_core::{Config, SynchronizationClientCore, CoreVerificationSink};
 	use synchronization_chain::Chain;
 	use message::types;
+	use message::types::reject::RejectCode;
 	use message::common::{InventoryVector, InventoryType};
 	use network::{ConsensusParams, ConsensusFork, Network};
 	use chain::Transaction;
@@ -330,6 +535,7 @@ pub mod tests {
 	use synchronization_peers::PeersImpl;
 	use utils::SynchronizationState;
 	use types::SynchronizationStateRef;
+	use utils::TransactionRejection;
 
 	pub fn default_filterload() -> types::FilterLoad {
 		types::FilterLoad {
@@ -356,12 +562,15 @@ pub mod tests {
 		let server = Arc::new(DummyServer::new());
 		let config = Config { close_connection_on_bad_block: true };
 		let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Network::Mainnet, ConsensusFork::BitcoinCore)));
-		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain, chain_verifier);
+		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain, chain_verifier.clone());
 		let mut verifier = match verifier {
 			Some(verifier) => verifier,
 			None => DummyVerifier::default(),
 		};
 		verifier.set_sink(Arc::new(CoreVerificationSink::new(client_core.clone())));
+		verifier.set_storage(storage.clone());
+		verifier.set_memory_pool(memory_pool.clone());
+		verifier.set_verifier(chain_verifier);
 		let client = SynchronizationClient::new(sync_state.clone(), client_core, verifier);
 		let local_node = LocalNode::new(ConsensusParams::new(Network::Mainnet, ConsensusFork::BitcoinCore), storage, memory_pool, sync_peers, sync_state, client, server.clone());
 		(executor, server, local_node)
@@ -405,6 +614,26 @@ pub mod tests {
 		assert_eq!(executor.take_tasks(), vec![Task::RelayNewTransaction(transaction.into(), 83333333)]);
 	}
 
+	#[test]
+	fn local_node_tests_transaction_acceptance() {
+		let (executor, _, local_node) = create_local_node(None);
+		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default());
+		executor.take_tasks();
+
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(1).add_input(&genesis.transactions[0], 0).into();
+
+		// dry run doesn't affect memory pool
+		assert!(local_node.test_accept_transaction(&transaction.clone().into()).is_allowed());
+		assert!(local_node.test_accept_transaction(&transaction.clone().into()).is_allowed());
+		assert_eq!(executor.take_tasks(), vec![]);
+
+		// transactions from memory pool are rejected as duplicates
+		local_node.accept_transaction(transaction.clone().into()).unwrap();
+		let acceptance = local_node.test_accept_transaction(&transaction.into());
+		assert_eq!(acceptance.result, Err(TransactionRejection::new(RejectCode::Duplicate, "txn-already-in-mempool".to_owned())));
+	}
+
 	#[test]
 	fn local_node_discards_local_transaction() {
 		let genesis = test_data::genesis();
@@ -421,7 +650,28 @@ pub mod tests {
 		executor.take_tasks();
 
 		let result = local_node.accept_transaction(transaction.into());
-		assert_eq!(result, Err("simulated".to_owned()));
+		assert_eq!(result, Err(TransactionRejection::new(RejectCode::Invalid, "simulated".to_owned())));
+
+		assert_eq!(executor.take_tasks(), vec![]);
+	}
+
+	#[test]
+	fn local_node_rejects_local_transaction_with_verification_reason() {
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(1).add_input(&genesis.transactions[0], 0).into();
+		let transaction_hash = transaction.hash();
+
+		// transaction is verified by the verification thread only
+		let mut verifier = DummyVerifier::default();
+		verifier.actual_check_when_verifying(transaction_hash.clone());
+
+		let (executor, _, local_node) = create_local_node(Some(verifier));
+
+		let peer_index1 = 0; local_node.on_connect(peer_index1, "test".into(), types::Version::default());
+		executor.take_tasks();
+
+		let result = local_node.accept_transaction(transaction.into());
+		assert_eq!(result, Err(TransactionRejection::new(RejectCode::Invalid, "bad-txns-premature-spend-of-coinbase".to_owned())));
 
 		assert_eq!(executor.take_tasks(), vec![]);
 	}
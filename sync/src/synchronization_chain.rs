@@ -1,12 +1,14 @@
 use std::collections::{VecDeque, HashSet};
-use std::fmt;
+use std::{cmp, fmt};
 use linked_hash_map::LinkedHashMap;
 use chain::{IndexedBlockHeader, IndexedBlock, IndexedTransaction, OutPoint, TransactionOutput};
 use storage;
-use miner::{MemoryPoolOrderingStrategy, MemoryPoolInformation, FeeCalculator};
+use miner::{MemoryPoolInformation, FeeCalculator};
 use network::ConsensusParams;
+use primitives::bigint::{Uint, U256};
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
+use verification::block_work;
 use utils::{BestHeadersChain, BestHeadersChainInformation, HashQueueChain, HashPosition};
 use types::{BlockHeight, StorageRef, MemoryPoolRef};
 
@@ -24,6 +26,8 @@ const NUMBER_OF_QUEUES: usize = 3;
 pub struct BlockInsertionResult {
 	/// Hashes of blocks, which were canonized during this insertion procedure. Order matters
 	pub canonized_blocks_hashes: Vec<H256>,
+	/// Hashes of blocks, which were decanonized during this insertion procedure
+	pub decanonized_blocks_hashes: Vec<H256>,
 	/// Transaction to 'reverify'. Order matters
 	pub transactions_to_reverify: Vec<IndexedTransaction>,
 }
@@ -32,6 +36,7 @@ impl fmt::Debug for BlockInsertionResult {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_struct("BlockInsertionResult")
 			.field("canonized_blocks_hashes", &self.canonized_blocks_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
+			.field("decanonized_blocks_hashes", &self.decanonized_blocks_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
 			.field("transactions_to_reverify", &self.transactions_to_reverify)
 			.finish()
 	}
@@ -42,6 +47,7 @@ impl BlockInsertionResult {
 	pub fn with_canonized_blocks(canonized_blocks_hashes: Vec<H256>) -> Self {
 		BlockInsertionResult {
 			canonized_blocks_hashes: canonized_blocks_hashes,
+			decanonized_blocks_hashes: Vec::new(),
 			transactions_to_reverify: Vec::new(),
 		}
 	}
@@ -115,9 +121,20 @@ pub struct Chain {
 	memory_pool: MemoryPoolRef,
 	/// Blocks that have been marked as dead-ends
 	dead_end_blocks: HashSet<H256>,
+	/// Blocks that have been manually marked as invalid. Their descendants are never canonized
+	invalidated_blocks: HashSet<H256>,
 	/// Is SegWit is possible on this chain? SegWit inventory types are used when block/tx-es are
 	/// requested and this flag is true.
 	is_segwit_possible: bool,
+	/// Scripts of this block ancestors are not verified.
+	assume_valid: H256,
+	/// Minimal work of the chain, containing `assume_valid` block.
+	minimum_chain_work: U256,
+	/// Has the chain, containing `assume_valid` block, enough work? None if block is not yet known.
+	assume_valid_has_enough_work: Option<bool>,
+	/// Number of best storage block at startup and work of the chain up to (including) this block.
+	/// Only computed when `assume_valid` block is not in the storage at startup.
+	startup_chain_work: Option<(BlockHeight, U256)>,
 }
 
 impl BlockState {
@@ -149,6 +166,28 @@ impl Chain {
 		let best_storage_block = storage.best_block();
 		let best_storage_block_hash = best_storage_block.hash.clone();
 		let is_segwit_possible = consensus.is_segwit_possible();
+		let assume_valid = consensus.assume_valid.clone();
+		let minimum_chain_work = consensus.minimum_chain_work;
+		let invalidated_blocks = storage.invalidated_blocks()
+			.expect("invalidated blocks are read from storage")
+			.into_iter()
+			.collect();
+
+		// chain work is computed here, before the chain is shared => sync isn't blocked while we're
+		// summing work of (possibly) hundreds of thousands of stored headers
+		let (assume_valid_has_enough_work, startup_chain_work) = match assume_valid.is_zero() {
+			true => (None, None),
+			false => match storage.block_number(&assume_valid) {
+				Some(assume_valid_number) => {
+					let chain_work = chain_work(&*storage, 0, assume_valid_number);
+					(Some(chain_work >= minimum_chain_work), None)
+				},
+				None => (None, Some((best_storage_block.number, chain_work(&*storage, 0, best_storage_block.number)))),
+			},
+		};
+		if assume_valid_has_enough_work == Some(false) {
+			warn!(target: "sync", block:% = assume_valid.reversed(); "Assume-valid block {} is on the chain with too little work. Verifying all scripts", assume_valid.to_reversed_str());
+		}
 
 		Chain {
 			genesis_block_hash: genesis_block_hash,
@@ -159,7 +198,12 @@ impl Chain {
 			verifying_transactions: LinkedHashMap::new(),
 			memory_pool: memory_pool,
 			dead_end_blocks: HashSet::new(),
+			invalidated_blocks: invalidated_blocks,
 			is_segwit_possible,
+			assume_valid: assume_valid,
+			minimum_chain_work: minimum_chain_work,
+			assume_valid_has_enough_work: assume_valid_has_enough_work,
+			startup_chain_work: startup_chain_work,
 		}
 	}
 
@@ -259,8 +303,43 @@ impl Chain {
 		if number <= self.best_storage_block.number {
 			self.storage.block_header(storage::BlockRef::Number(number))
 		} else {
-			self.headers_chain.at(number - self.best_storage_block.number)
+			self.headers_chain.at(number - self.best_storage_block.number - 1)
+		}
+	}
+
+	/// Is block an ancestor of assume-valid block (or this block itself) on the chain with enough work?
+	/// Scripts of such blocks are not verified.
+	pub fn is_assumed_valid_block(&mut self, hash: &H256) -> bool {
+		if self.assume_valid.is_zero() {
+			return false;
 		}
+
+		let assume_valid_number = match self.block_number(&self.assume_valid) {
+			Some(assume_valid_number) => assume_valid_number,
+			None => return false,
+		};
+
+		// chain work only depends on assume-valid block => compute once. Work of blocks that were
+		// stored at startup is already known, so only blocks that were added later are summed here
+		let has_enough_work = match self.assume_valid_has_enough_work {
+			Some(has_enough_work) => has_enough_work,
+			None => {
+				let (startup_number, startup_chain_work) = self.startup_chain_work
+					.expect("startup_chain_work is computed when assume_valid block is not in the storage at startup; qed");
+				let chain_work = match assume_valid_number > startup_number {
+					true => startup_chain_work + chain_work(&*self, startup_number + 1, assume_valid_number),
+					false => chain_work(&*self, 0, assume_valid_number),
+				};
+				let has_enough_work = chain_work >= self.minimum_chain_work;
+				if !has_enough_work {
+					warn!(target: "sync", block:% = self.assume_valid.reversed(); "Assume-valid block {} is on the chain with too little work. Verifying all scripts", self.assume_valid.to_reversed_str());
+				}
+				self.assume_valid_has_enough_work = Some(has_enough_work);
+				has_enough_work
+			},
+		};
+
+		has_enough_work && self.block_number(hash).map(|number| number <= assume_valid_number).unwrap_or(false)
 	}
 
 	/// Get block header by hash
@@ -346,7 +425,12 @@ impl Chain {
 	/// Insert new best block to storage
 	pub fn insert_best_block(&mut self, block: IndexedBlock) -> Result<BlockInsertionResult, storage::Error> {
 		assert_eq!(Some(self.storage.best_block().hash), self.storage.block_hash(self.storage.best_block().number));
-		let block_origin = self.storage.block_origin(&block.header)?;
+		let block_origin = match self.storage.block_origin(&block.header)? {
+			// never switch to the branch with manually invalidated blocks
+			storage::BlockOrigin::SideChainBecomingCanonChain(ref origin) if self.contains_invalidated_block(&origin.canonized_route) =>
+				storage::BlockOrigin::SideChain(origin.clone()),
+			block_origin => block_origin,
+		};
 		trace!(target: "sync", "insert_best_block {:?} origin: {:?}", block.hash().reversed(), block_origin);
 		match block_origin {
 			storage::BlockOrigin::KnownBlock => {
@@ -383,6 +467,7 @@ impl Chain {
 
 				Ok(BlockInsertionResult {
 					canonized_blocks_hashes: vec![*block.hash()],
+					decanonized_blocks_hashes: Vec::new(),
 					transactions_to_reverify: Vec::new(),
 				})
 			},
@@ -416,6 +501,7 @@ impl Chain {
 				}
 
 				// reverify all transactions from old main branch' blocks
+				let decanonized_blocks_hashes = origin.decanonized_route.clone();
 				let old_main_blocks_transactions = origin.decanonized_route.into_iter()
 					.flat_map(|block_hash| self.storage.block_transactions(block_hash.into()))
 					.collect::<Vec<_>>();
@@ -424,9 +510,8 @@ impl Chain {
 					   old_main_blocks_transactions.iter().map(|tx| tx.hash.reversed()).collect::<Vec<H256>>());
 
 				// reverify memory pool transactions, sorted by timestamp
-				let memory_pool_transactions_count = memory_pool.information().transactions_count;
 				let memory_pool_transactions: Vec<IndexedTransaction> = memory_pool
-					.remove_n_with_strategy(memory_pool_transactions_count, MemoryPoolOrderingStrategy::ByTimestamp)
+					.remove_all_for_reverification()
 					.into_iter()
 					.map(|t| t.into())
 					.collect();
@@ -442,6 +527,7 @@ impl Chain {
 
 				let result = BlockInsertionResult {
 					canonized_blocks_hashes: canonized_blocks_hashes,
+					decanonized_blocks_hashes: decanonized_blocks_hashes,
 					// order matters: db transactions, then ordered mempool transactions, then ordered verifying transactions
 					transactions_to_reverify: old_main_blocks_transactions.into_iter()
 						.chain(memory_pool_transactions.into_iter())
@@ -469,6 +555,197 @@ impl Chain {
 		}
 	}
 
+	/// Is block manually marked as invalid?
+	pub fn is_invalidated_block(&self, hash: &H256) -> bool {
+		self.invalidated_blocks.contains(hash)
+	}
+
+	/// Is block, or any of its ancestors, manually marked as invalid?
+	pub fn is_invalidated_branch(&self, hash: &H256) -> bool {
+		if self.invalidated_blocks.is_empty() {
+			return false;
+		}
+
+		let mut hash = *hash;
+		loop {
+			if self.invalidated_blocks.contains(&hash) {
+				return true;
+			}
+
+			// canon chain never contains invalidated blocks, and headers are only scheduled on top of it
+			if self.storage.block_number(&hash).is_some() || self.hash_chain.contains_in(&hash).is_some() {
+				return false;
+			}
+
+			match self.storage.block_header(storage::BlockRef::Hash(hash)) {
+				Some(header) => hash = header.raw.previous_header_hash,
+				None => return false,
+			}
+		}
+	}
+
+	/// Marks stored block as invalid and switches to the best chain without invalidated blocks
+	pub fn invalidate_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, storage::Error> {
+		if self.invalidated_blocks.insert(*hash) {
+			self.save_invalidated_blocks()?;
+		}
+		self.switch_to_best_valid_chain(None)
+	}
+
+	/// Removes invalid mark from the block, its ancestors and descendants and switches to the best chain
+	/// without invalidated blocks
+	pub fn reconsider_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, storage::Error> {
+		// routes from tips must reach the canon block itself to find all its descendants
+		let max_ancestor = match self.storage.block_number(hash) {
+			Some(number) => number.saturating_sub(1),
+			None => self.best_storage_block.number,
+		};
+
+		let mut reconsidered = vec![*hash];
+		for tip in self.storage.chain_tips() {
+			let route = self.side_chain_route(tip, max_ancestor);
+			if route.0.contains(hash) {
+				reconsidered.extend(route.0);
+			}
+		}
+
+		let invalidated_blocks_len = self.invalidated_blocks.len();
+		for hash in &reconsidered {
+			self.invalidated_blocks.remove(hash);
+		}
+		if self.invalidated_blocks.len() != invalidated_blocks_len {
+			self.save_invalidated_blocks()?;
+		}
+		self.switch_to_best_valid_chain(None)
+	}
+
+	/// Switches to the chain with given block if it is as long as the current best chain
+	pub fn precious_block(&mut self, hash: &H256) -> Result<BlockInsertionResult, storage::Error> {
+		self.switch_to_best_valid_chain(Some(hash))
+	}
+
+	fn save_invalidated_blocks(&self) -> Result<(), storage::Error> {
+		let invalidated_blocks = self.invalidated_blocks.iter().cloned().collect::<Vec<_>>();
+		self.storage.set_invalidated_blocks(&invalidated_blocks)
+	}
+
+	fn contains_invalidated_block(&self, route: &[H256]) -> bool {
+		route.iter().any(|hash| self.invalidated_blocks.contains(hash))
+	}
+
+	/// Returns blocks from given block back to the first canon block with number not greater than
+	/// `max_ancestor`, and the number of that canon block
+	fn side_chain_route(&self, mut hash: H256, max_ancestor: BlockHeight) -> (Vec<H256>, Option<BlockHeight>) {
+		let mut route = Vec::new();
+		loop {
+			match self.storage.block_number(&hash) {
+				Some(number) if number <= max_ancestor => return (route, Some(number)),
+				_ => match self.storage.block_header(storage::BlockRef::Hash(hash)) {
+					Some(header) => {
+						route.push(hash);
+						hash = header.raw.previous_header_hash;
+					},
+					None => return (route, None),
+				},
+			}
+		}
+	}
+
+	/// Switches storage to the longest stored chain without invalidated blocks. Of equally long chains,
+	/// the one with `preferred` block is selected, then the current best chain.
+	fn switch_to_best_valid_chain(&mut self, preferred: Option<&H256>) -> Result<BlockInsertionResult, storage::Error> {
+		// canon blocks, starting from the first invalidated canon block, must be decanonized
+		let best_block = self.storage.best_block();
+		let valid_canon_number = self.invalidated_blocks.iter()
+			.filter_map(|hash| self.storage.block_number(hash))
+			.map(|number| number.saturating_sub(1))
+			.min()
+			.map_or(best_block.number, |number| cmp::min(number, best_block.number));
+		let is_preferred_canon = |number: BlockHeight| preferred
+			.and_then(|hash| self.storage.block_number(hash))
+			.map_or(false, |preferred_number| preferred_number <= number);
+
+		let mut best_origin = storage::SideChainOrigin {
+			ancestor: valid_canon_number,
+			canonized_route: Vec::new(),
+			decanonized_route: Vec::new(),
+			block_number: valid_canon_number,
+		};
+		let mut best_is_preferred = is_preferred_canon(valid_canon_number);
+
+		for tip in self.storage.chain_tips() {
+			let (route, ancestor) = match self.side_chain_route(tip, valid_canon_number) {
+				(_, None) => continue,
+				(ref route, _) if self.contains_invalidated_block(route) => continue,
+				(route, Some(ancestor)) => (route, ancestor),
+			};
+
+			let block_number = ancestor + route.len() as BlockHeight;
+			let is_preferred = preferred.map_or(false, |hash| route.contains(hash)) || is_preferred_canon(ancestor);
+			if block_number > best_origin.block_number || (block_number == best_origin.block_number && is_preferred && !best_is_preferred) {
+				best_origin = storage::SideChainOrigin {
+					ancestor: ancestor,
+					canonized_route: route.into_iter().rev().collect(),
+					decanonized_route: Vec::new(),
+					block_number: block_number,
+				};
+				best_is_preferred = is_preferred;
+			}
+		}
+
+		best_origin.decanonized_route = (best_origin.ancestor + 1..best_block.number + 1)
+			.filter_map(|number| self.storage.block_hash(number))
+			.collect();
+		if best_origin.canonized_route.is_empty() && best_origin.decanonized_route.is_empty() {
+			return Ok(BlockInsertionResult::default());
+		}
+
+		info!(target: "sync", "Switching to the chain with best block #{}, {} blocks are decanonized",
+			best_origin.block_number, best_origin.decanonized_route.len());
+
+		let fork = self.storage.fork(best_origin.clone())?;
+		self.storage.switch_to_fork(fork)?;
+		self.best_storage_block = self.storage.best_block();
+
+		// headers and requested blocks are built on top of the previous best block
+		self.hash_chain.remove_all_at(SCHEDULED_QUEUE);
+		self.hash_chain.remove_all_at(REQUESTED_QUEUE);
+		self.headers_chain = BestHeadersChain::new(self.best_storage_block.hash.clone());
+
+		// transactions of the new best chain are accepted
+		let mut memory_pool = self.memory_pool.write();
+		for block_hash in &best_origin.canonized_route {
+			for transaction_hash in self.storage.block_transaction_hashes(block_hash.clone().into()) {
+				memory_pool.remove_by_hash(&transaction_hash);
+				self.verifying_transactions.remove(&transaction_hash);
+			}
+		}
+
+		// reverify transactions of decanonized blocks, then memory pool and verifying transactions
+		let decanonized_transactions = best_origin.decanonized_route.iter()
+			.flat_map(|block_hash| self.storage.block_transactions(block_hash.clone().into()))
+			.filter(|transaction| !transaction.raw.is_coinbase())
+			.collect::<Vec<_>>();
+		let memory_pool_transactions = memory_pool
+			.remove_all_for_reverification()
+			.into_iter()
+			.map(|t| t.into());
+		let verifying_transactions = self.verifying_transactions
+			.iter()
+			.map(|(_, t)| t.clone())
+			.collect::<Vec<_>>();
+		self.verifying_transactions.clear();
+
+		Ok(BlockInsertionResult {
+			canonized_blocks_hashes: best_origin.canonized_route,
+			decanonized_blocks_hashes: best_origin.decanonized_route,
+			transactions_to_reverify: decanonized_transactions.into_iter()
+				.chain(memory_pool_transactions)
+				.chain(verifying_transactions.into_iter())
+				.collect(),
+		})
+	}
+
 	/// Forget in-memory block
 	pub fn forget_block(&mut self, hash: &H256) -> HashPosition {
 		self.headers_chain.remove(hash);
@@ -571,6 +848,7 @@ impl Chain {
 	/// Remove verifying transaction + all dependent transactions currently verifying
 	pub fn forget_verifying_transaction_with_children(&mut self, hash: &H256) {
 		self.forget_verifying_transaction(hash);
+		self.forget_reverified_transaction(hash);
 
 		let mut queue: VecDeque<H256> = VecDeque::new();
 		queue.push_back(*hash);
@@ -583,11 +861,18 @@ impl Chain {
 
 			for h in &remove {
 				self.verifying_transactions.remove(h);
+				self.forget_reverified_transaction(h);
 			}
 			queue.extend(remove);
 		}
 	}
 
+	/// Forget memory pool transaction, which has been removed for reverification, but is not
+	/// returned back to the memory pool
+	pub fn forget_reverified_transaction(&mut self, hash: &H256) {
+		self.memory_pool.write().forget_reverified_transaction(hash);
+	}
+
 	/// Get transaction by hash (if it's in memory pool or verifying)
 	pub fn transaction_by_hash(&self, hash: &H256) -> Option<IndexedTransaction> {
 		self.verifying_transactions.get(hash).cloned()
@@ -609,6 +894,21 @@ impl Chain {
 		memory_pool.insert_verified(transaction, &FeeCalculator(self.storage.as_transaction_output_provider()));
 	}
 
+	/// Insert package to memory pool. Package fee has been checked as a whole.
+	pub fn insert_verified_package(&mut self, package: Vec<IndexedTransaction>) {
+		let mut memory_pool = self.memory_pool.write();
+		// package transactions, which are already in the memory pool, are left untouched
+		let package: Vec<_> = package.into_iter()
+			.filter(|transaction| !memory_pool.contains(&transaction.hash))
+			.collect();
+		for transaction in &package {
+			for input in &transaction.raw.inputs {
+				memory_pool.remove_by_prevout(&input.previous_output);
+			}
+		}
+		memory_pool.insert_verified_package(package, &FeeCalculator(self.storage.as_transaction_output_provider()));
+	}
+
 	/// Calculate block locator hashes for hash queue
 	fn block_locator_hashes_for_queue(&self, hashes: &mut Vec<H256>) -> (BlockHeight, BlockHeight) {
 		let queue_len = self.hash_chain.len();
@@ -693,6 +993,13 @@ impl storage::BlockHeaderProvider for Chain {
 	}
 }
 
+/// Compute work of the chain blocks in [from; to] range
+fn chain_work<T: storage::BlockHeaderProvider + ?Sized>(headers: &T, from: BlockHeight, to: BlockHeight) -> U256 {
+	(from..to + 1)
+		.filter_map(|number| headers.block_header(storage::BlockRef::Number(number)))
+		.fold(U256::zero(), |chain_work, header| chain_work + block_work(header.raw.bits))
+}
+
 impl fmt::Debug for Information {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "[sch:{} -> req:{} -> vfy:{} -> stored: {}]", self.scheduled, self.requested, self.verifying, self.stored)
@@ -738,7 +1045,9 @@ mod tests {
 	use db::BlockChainDatabase;
 	use miner::MemoryPool;
 	use network::{Network, ConsensusParams, ConsensusFork};
+	use primitives::bigint::U256;
 	use primitives::hash::H256;
+	use storage::ConfigStore;
 	use super::{Chain, BlockState, TransactionState, BlockInsertionResult};
 	use utils::HashPosition;
 
@@ -1035,6 +1344,96 @@ mod tests {
 		assert!(result.transactions_to_reverify.iter().any(|ref tx| &tx.hash == &tx2_hash));
 	}
 
+	#[test]
+	fn chain_invalidate_and_reconsider_block() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().nonce(1).parent(b0.hash()).build().build();
+		let b2 = test_data::block_builder().header().parent(b1.hash()).build().build();
+		let b3 = test_data::block_builder().header().parent(b2.hash()).build().build();
+		let c1 = test_data::block_builder().header().nonce(2).parent(b0.hash()).build().build();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
+		let mut chain = Chain::new(db.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore), Arc::new(RwLock::new(MemoryPool::new())));
+		chain.insert_best_block(b1.clone().into()).expect("no error");
+		chain.insert_best_block(b2.clone().into()).expect("no error");
+		chain.insert_best_block(c1.clone().into()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, b2.hash());
+
+		// switch to the only valid chain
+		let result = chain.invalidate_block(&b1.hash()).expect("no error");
+		assert_eq!(result.canonized_blocks_hashes, vec![c1.hash()]);
+		assert_eq!(result.decanonized_blocks_hashes, vec![b1.hash(), b2.hash()]);
+		assert_eq!(chain.best_storage_block().hash, c1.hash());
+		assert!(chain.is_invalidated_block(&b1.hash()));
+		assert_eq!(db.invalidated_blocks(), Ok(vec![b1.hash()]));
+
+		// longer chain with invalidated block is not canonized
+		chain.insert_best_block(b3.clone().into()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, c1.hash());
+		assert!(!chain.is_invalidated_block(&b3.hash()));
+		assert!(chain.is_invalidated_branch(&b3.hash()));
+		assert!(!chain.is_invalidated_branch(&c1.hash()));
+
+		// invalid mark is persistent
+		let mut chain = Chain::new(db.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore), Arc::new(RwLock::new(MemoryPool::new())));
+		assert!(chain.is_invalidated_block(&b1.hash()));
+
+		let result = chain.reconsider_block(&b2.hash()).expect("no error");
+		assert_eq!(result.canonized_blocks_hashes, vec![b1.hash(), b2.hash(), b3.hash()]);
+		assert_eq!(result.decanonized_blocks_hashes, vec![c1.hash()]);
+		assert_eq!(chain.best_storage_block().hash, b3.hash());
+		assert!(!chain.is_invalidated_block(&b1.hash()));
+		assert_eq!(db.invalidated_blocks(), Ok(vec![]));
+	}
+
+	#[test]
+	fn chain_reconsider_canon_block_clears_invalidated_descendants() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().nonce(1).parent(b0.hash()).build().build();
+		let b2 = test_data::block_builder().header().nonce(1).parent(b1.hash()).build().build();
+		let c2 = test_data::block_builder().header().nonce(2).parent(b1.hash()).build().build();
+		let c3 = test_data::block_builder().header().parent(c2.hash()).build().build();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
+		let mut chain = Chain::new(db.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore), Arc::new(RwLock::new(MemoryPool::new())));
+		chain.insert_best_block(b1.clone().into()).expect("no error");
+		chain.insert_best_block(b2.clone().into()).expect("no error");
+		chain.insert_best_block(c2.clone().into()).expect("no error");
+		chain.invalidate_block(&c2.hash()).expect("no error");
+		chain.insert_best_block(c3.clone().into()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, b2.hash());
+
+		// reconsidering canon ancestor clears invalid mark of its side chain descendants
+		let result = chain.reconsider_block(&b1.hash()).expect("no error");
+		assert_eq!(result.canonized_blocks_hashes, vec![c2.hash(), c3.hash()]);
+		assert_eq!(result.decanonized_blocks_hashes, vec![b2.hash()]);
+		assert_eq!(chain.best_storage_block().hash, c3.hash());
+		assert!(!chain.is_invalidated_block(&c2.hash()));
+		assert_eq!(db.invalidated_blocks(), Ok(vec![]));
+	}
+
+	#[test]
+	fn chain_precious_block() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().nonce(1).parent(b0.hash()).build().build();
+		let c1 = test_data::block_builder().header().nonce(2).parent(b0.hash()).build().build();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
+		let mut chain = Chain::new(db, ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore), Arc::new(RwLock::new(MemoryPool::new())));
+		chain.insert_best_block(b1.clone().into()).expect("no error");
+		chain.insert_best_block(c1.clone().into()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, b1.hash());
+
+		chain.precious_block(&c1.hash()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, c1.hash());
+
+		chain.precious_block(&c1.hash()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, c1.hash());
+
+		chain.precious_block(&b1.hash()).expect("no error");
+		assert_eq!(chain.best_storage_block().hash, b1.hash());
+	}
+
 	#[test]
 	fn fork_chain_block_transaction_is_removed_from_on_block_insert() {
 		let genesis = test_data::genesis();
@@ -1093,6 +1492,7 @@ mod tests {
 			.collect();
 		assert_eq!(transactions_to_reverify_hashes, vec![tx1_hash, tx2_hash]);
 		assert_eq!(insert_result.canonized_blocks_hashes, vec![b3.hash(), b4.hash(), b5.hash()]);
+		assert_eq!(insert_result.decanonized_blocks_hashes, vec![b1.hash(), b2.hash()]);
 		assert_eq!(chain.information().transactions.transactions_count, 0); // tx3, tx4, tx5 are added to the database
 	}
 
@@ -1137,4 +1537,72 @@ mod tests {
 		chain.insert_verified_transaction(data_chain.at(2).into());
 		assert_eq!(chain.information().transactions.transactions_count, 2); // tx was replaced
 	}
+
+	#[test]
+	fn chain_block_header_by_number() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+		let headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+		let mut chain = Chain::new(db, ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore), Arc::new(RwLock::new(MemoryPool::new())));
+		chain.schedule_blocks_headers(headers.clone());
+
+		// first header follows the best storage block
+		assert_eq!(chain.block_header_by_number(0).map(|h| h.hash), Some(test_data::genesis().hash()));
+		assert_eq!(chain.block_header_by_number(1).map(|h| h.hash), Some(headers[0].hash.clone()));
+		assert_eq!(chain.block_header_by_number(3).map(|h| h.hash), Some(headers[2].hash.clone()));
+		assert_eq!(chain.block_header_by_number(4), None);
+		for (number, header) in headers.iter().enumerate() {
+			assert_eq!(chain.block_number(&header.hash), Some(number as u32 + 1));
+		}
+	}
+
+	#[test]
+	fn chain_assumed_valid_blocks() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+		let headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+
+		let mut consensus = ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore);
+		consensus.assume_valid = headers[1].hash.clone();
+		let mut chain = Chain::new(db.clone(), consensus.clone(), Arc::new(RwLock::new(MemoryPool::new())));
+		// assume-valid block is unknown yet
+		assert!(!chain.is_assumed_valid_block(&headers[0].hash));
+
+		chain.schedule_blocks_headers(headers.clone());
+		assert!(chain.is_assumed_valid_block(&test_data::genesis().hash()));
+		assert!(chain.is_assumed_valid_block(&headers[0].hash));
+		assert!(chain.is_assumed_valid_block(&headers[1].hash));
+		assert!(!chain.is_assumed_valid_block(&headers[2].hash));
+		assert!(!chain.is_assumed_valid_block(&H256::from(1)));
+
+		// not enough work on the chain
+		consensus.minimum_chain_work = U256::from(::std::u64::MAX);
+		let mut chain = Chain::new(db, consensus, Arc::new(RwLock::new(MemoryPool::new())));
+		chain.schedule_blocks_headers(headers.clone());
+		assert!(!chain.is_assumed_valid_block(&headers[0].hash));
+	}
+
+	#[test]
+	fn chain_assumed_valid_blocks_in_storage_at_startup() {
+		let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+		let hashes: Vec<H256> = blocks.iter().map(|b| b.hash()).collect();
+		let mut storage_blocks = vec![test_data::genesis().into()];
+		storage_blocks.extend(blocks.into_iter().map(Into::into));
+		let db = Arc::new(BlockChainDatabase::init_test_chain(storage_blocks));
+
+		let mut consensus = ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore);
+		consensus.assume_valid = hashes[1].clone();
+		let mut chain = Chain::new(db.clone(), consensus.clone(), Arc::new(RwLock::new(MemoryPool::new())));
+		// chain work has been computed at startup
+		assert_eq!(chain.assume_valid_has_enough_work, Some(true));
+		assert!(chain.is_assumed_valid_block(&hashes[0]));
+		assert!(chain.is_assumed_valid_block(&hashes[1]));
+		assert!(!chain.is_assumed_valid_block(&hashes[2]));
+
+		// not enough work on the chain
+		consensus.minimum_chain_work = U256::from(::std::u64::MAX);
+		let mut chain = Chain::new(db, consensus, Arc::new(RwLock::new(MemoryPool::new())));
+		assert_eq!(chain.assume_valid_has_enough_work, Some(false));
+		assert!(!chain.is_assumed_valid_block(&hashes[0]));
+	}
 }
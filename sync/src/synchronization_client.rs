@@ -1,11 +1,14 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 use parking_lot::Mutex;
 use chain::{IndexedTransaction, IndexedBlock, IndexedBlockHeader};
 use message::types;
+use primitives::hash::H256;
 use synchronization_executor::TaskExecutor;
-use synchronization_verifier::{Verifier, TransactionVerificationSink};
-use synchronization_client_core::{ClientCore, SynchronizationClientCore};
-use types::{PeerIndex, ClientCoreRef, SynchronizationStateRef, EmptyBoxFuture, SyncListenerRef};
+use synchronization_verifier::{Verifier, TransactionVerificationSink, PackageVerificationSink};
+use synchronization_client_core::{ClientCore, SynchronizationClientCore, Information};
+use types::{PeerIndex, ClientCoreRef, SynchronizationStateRef, EmptyBoxFuture, SyncListenerRef, VerificationStatsRef};
+use utils::{TransactionAcceptance, TransactionRejection};
 
 #[cfg_attr(feature="cargo-clippy", allow(doc_markdown))]
 ///! TODO: update with headers-first corrections
@@ -128,9 +131,18 @@ pub trait Client : Send + Sync + 'static {
 	fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock);
 	fn on_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction);
 	fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_ancpkginfo(&self, peer_index: PeerIndex, message: types::AncPkgInfo);
+	fn on_pkgtxns(&self, peer_index: PeerIndex, transactions: Vec<IndexedTransaction>);
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture);
-	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String>;
+	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), TransactionRejection>;
+	fn test_accept_transaction(&self, transaction: &IndexedTransaction) -> TransactionAcceptance;
+	fn accept_package(&self, package: Vec<IndexedTransaction>, sink: Box<dyn PackageVerificationSink>) -> Result<(), TransactionRejection>;
+	fn invalidate_block(&self, hash: &H256) -> Result<(), String>;
+	fn reconsider_block(&self, hash: &H256) -> Result<(), String>;
+	fn precious_block(&self, hash: &H256) -> Result<(), String>;
 	fn install_sync_listener(&self, listener: SyncListenerRef);
+	fn information(&self) -> Information;
+	fn verification_stats(&self) -> VerificationStatsRef;
 }
 
 /// Synchronization client facade
@@ -210,23 +222,75 @@ impl<T, U> Client for SynchronizationClient<T, U> where T: TaskExecutor, U: Veri
 		self.core.lock().on_notfound(peer_index, message);
 	}
 
+	fn on_ancpkginfo(&self, peer_index: PeerIndex, message: types::AncPkgInfo) {
+		self.core.lock().on_ancpkginfo(peer_index, message);
+	}
+
+	fn on_pkgtxns(&self, peer_index: PeerIndex, transactions: Vec<IndexedTransaction>) {
+		// package is only verified if we have asked for it
+		let _verification_lock = self.verification_lock.lock();
+		let package = self.core.lock().on_pkgtxns(peer_index, transactions);
+		if let Some(package) = package {
+			let next_block_height = self.shared_state.best_storage_block_height() + 1;
+			self.verifier.verify_package(next_block_height, package);
+		}
+	}
+
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture) {
 		self.core.lock().after_peer_nearly_blocks_verified(peer_index, future);
 	}
 
-	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), String> {
-		let mut transactions_to_verify = self.core.lock().accept_transaction(transaction, sink)?;
+	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<(), TransactionRejection> {
+		let transactions_to_verify = self.core.lock().accept_transaction(transaction, sink)?;
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
+	fn test_accept_transaction(&self, transaction: &IndexedTransaction) -> TransactionAcceptance {
+		let next_block_height = self.shared_state.best_storage_block_height() + 1;
+		self.verifier.test_transaction(next_block_height, transaction)
+	}
 
+	fn accept_package(&self, package: Vec<IndexedTransaction>, sink: Box<dyn PackageVerificationSink>) -> Result<(), TransactionRejection> {
+		let _verification_lock = self.verification_lock.lock();
+		self.core.lock().accept_package(&package, sink)?;
 		let next_block_height = self.shared_state.best_storage_block_height() + 1;
-		while let Some(tx) = transactions_to_verify.pop_front() {
-			self.verifier.verify_transaction(next_block_height, tx);
-		}
+		self.verifier.verify_package(next_block_height, package);
+		Ok(())
+	}
+
+	fn invalidate_block(&self, hash: &H256) -> Result<(), String> {
+		let _verification_lock = self.verification_lock.lock();
+		let transactions_to_verify = self.core.lock().invalidate_block(hash)?;
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
+	fn reconsider_block(&self, hash: &H256) -> Result<(), String> {
+		let _verification_lock = self.verification_lock.lock();
+		let transactions_to_verify = self.core.lock().reconsider_block(hash)?;
+		self.verify_transactions(transactions_to_verify);
+		Ok(())
+	}
+
+	fn precious_block(&self, hash: &H256) -> Result<(), String> {
+		let _verification_lock = self.verification_lock.lock();
+		let transactions_to_verify = self.core.lock().precious_block(hash)?;
+		self.verify_transactions(transactions_to_verify);
 		Ok(())
 	}
 
 	fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.core.lock().install_sync_listener(listener);
 	}
+
+	fn information(&self) -> Information {
+		self.core.lock().information()
+	}
+
+	fn verification_stats(&self) -> VerificationStatsRef {
+		self.verifier.stats()
+	}
 }
 
 impl<T, U> SynchronizationClient<T, U> where T: TaskExecutor, U: Verifier {
@@ -239,4 +303,12 @@ impl<T, U> SynchronizationClient<T, U> where T: TaskExecutor, U: Verifier {
 			verifier: verifier,
 		})
 	}
+
+	/// Schedules verification of transactions for inclusion into the next block
+	fn verify_transactions(&self, mut transactions: VecDeque<IndexedTransaction>) {
+		let next_block_height = self.shared_state.best_storage_block_height() + 1;
+		while let Some(tx) = transactions.pop_front() {
+			self.verifier.verify_transaction(next_block_height, tx);
+		}
+	}
 }
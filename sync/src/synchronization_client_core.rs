@@ -7,6 +7,7 @@ use parking_lot::Mutex;
 use time::precise_time_s;
 use chain::{IndexedBlockHeader, IndexedTransaction, IndexedBlock};
 use message::types;
+use message::types::reject::RejectCode;
 use message::common::{InventoryType, InventoryVector};
 use miner::transaction_fee_rate;
 use primitives::hash::H256;
@@ -15,18 +16,25 @@ use synchronization_chain::{Chain, BlockState, TransactionState, BlockInsertionR
 use synchronization_executor::{Task, TaskExecutor};
 use synchronization_manager::ManagementWorker;
 use synchronization_peers_tasks::PeersTasks;
-use synchronization_verifier::{VerificationSink, BlockVerificationSink, TransactionVerificationSink, VerificationTask};
+use synchronization_verifier::{VerificationSink, BlockVerificationSink, TransactionVerificationSink, PackageVerificationSink, VerificationTask};
 use types::{BlockHeight, ClientCoreRef, PeersRef, PeerIndex, SynchronizationStateRef, EmptyBoxFuture, SyncListenerRef};
-use utils::{AverageSpeedMeter, MessageBlockHeadersProvider, OrphanBlocksPool, OrphanTransactionsPool, HashPosition};
-#[cfg(test)] use synchronization_peers_tasks::{Information as PeersTasksInformation};
-#[cfg(test)] use synchronization_chain::{Information as ChainInformation};
+use utils::{AverageSpeedMeter, MessageBlockHeadersProvider, OrphanBlocksPool, OrphanTransactionsPool, HashPosition,
+	TransactionRejection, PackageAcceptance};
+use synchronization_peers_tasks::{Information as PeersTasksInformation};
+use synchronization_chain::{Information as ChainInformation};
 
 /// Approximate maximal number of blocks hashes in scheduled queue.
 const MAX_SCHEDULED_HASHES: BlockHeight = 4 * 1024;
-/// Approximate maximal number of blocks hashes in requested queue.
-const MAX_REQUESTED_BLOCKS: BlockHeight = 256;
-/// Approximate maximal number of blocks in verifying queue.
-const MAX_VERIFYING_BLOCKS: BlockHeight = 256;
+/// Maximal number of blocks in requested + verifying queues (blocks download window).
+const BLOCK_DOWNLOAD_WINDOW: BlockHeight = 1024;
+/// Time the lowest block of the full download window may stay requested before its peer is treated as stalling.
+const BLOCK_STALLING_TIMEOUT_S: f64 = 2_f64;
+/// Maximal stalling timeout. Timeout is doubled after every stall, so that we do not disconnect all peers when our own connection is slow.
+const BLOCK_STALLING_TIMEOUT_MAX_S: f64 = 64_f64;
+/// Stalling timeout is multiplied by this factor after every received requested block, until it reaches its default value.
+const BLOCK_STALLING_TIMEOUT_DECAY: f64 = 0.85_f64;
+/// Time since last best block update before the tip is considered stale.
+const STALE_TIP_TIMEOUT_S: f64 = 30_f64 * 60_f64;
 /// Minimum number of blocks to request from peer
 const MIN_BLOCKS_IN_REQUEST: BlockHeight = 32;
 /// Maximum number of blocks to request from peer
@@ -47,7 +55,6 @@ const MAX_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 4;
 const MIN_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 8;
 
 /// Information on current synchronization state.
-#[cfg(test)]
 #[derive(Debug)]
 pub struct Information {
 	/// Current synchronization state.
@@ -71,8 +78,14 @@ pub trait ClientCore {
 	fn on_block(&mut self, peer_index: PeerIndex, block: IndexedBlock) -> Option<VecDeque<IndexedBlock>>;
 	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>>;
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_ancpkginfo(&mut self, peer_index: PeerIndex, message: types::AncPkgInfo);
+	fn on_pkgtxns(&mut self, peer_index: PeerIndex, transactions: Vec<IndexedTransaction>) -> Option<Vec<IndexedTransaction>>;
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: EmptyBoxFuture);
-	fn accept_transaction(&mut self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn accept_transaction(&mut self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, TransactionRejection>;
+	fn accept_package(&mut self, package: &[IndexedTransaction], sink: Box<dyn PackageVerificationSink>) -> Result<(), TransactionRejection>;
+	fn invalidate_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn reconsider_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
+	fn precious_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String>;
 	fn install_sync_listener(&mut self, listener: SyncListenerRef);
 	fn execute_synchronization_tasks(&mut self, forced_blocks_requests: Option<Vec<H256>>, final_blocks_requests: Option<Vec<H256>>);
 	fn try_switch_to_saturated_state(&mut self) -> bool;
@@ -117,16 +130,31 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	verifying_transactions_sinks: HashMap<H256, Box<dyn TransactionVerificationSink>>,
 	/// Hashes of items we do not want to relay after verification is completed
 	do_not_relay: HashSet<H256>,
+	/// Packages of orphaned transactions, requested from peers
+	requested_packages: HashMap<PeerIndex, Vec<H256>>,
+	/// Verifying packages by peer. Key is the hash of the package child transaction
+	verifying_packages_by_peer: HashMap<H256, PeerIndex>,
+	/// Verifying packages futures. Key is the hash of the package child transaction
+	verifying_packages_sinks: HashMap<H256, Box<dyn PackageVerificationSink>>,
 	/// Block processing speed meter
 	block_speed_meter: AverageSpeedMeter,
 	/// Block synchronization speed meter
 	sync_speed_meter: AverageSpeedMeter,
 	/// Configuration
 	config: Config,
-	/// Synchronization events listener
-	listener: Option<SyncListenerRef>,
+	/// Synchronization events listeners
+	listeners: Vec<SyncListenerRef>,
 	/// Time of last duplicated blocks request.
 	last_dup_time: f64,
+	/// Peers, which have responded with full headers message => we have not yet received all their headers.
+	/// Blocks are not requested until headers are synchronized with all peers.
+	headers_syncing_peers: HashSet<PeerIndex>,
+	/// Lowest requested block of the full download window && time when it has been noticed.
+	stalling_block: Option<(H256, f64)>,
+	/// Current stalling timeout.
+	block_stalling_timeout: f64,
+	/// Time of last best storage block update.
+	last_tip_update_time: f64,
 }
 
 /// Verification sink for synchronization client core
@@ -150,10 +178,8 @@ pub enum State {
 pub struct BlocksRequestLimits {
 	/// Approximate maximal number of blocks hashes in scheduled queue.
 	pub max_scheduled_hashes: BlockHeight,
-	/// Approximate maximal number of blocks hashes in requested queue.
-	pub max_requested_blocks: BlockHeight,
-	/// Approximate maximal number of blocks in verifying queue.
-	pub max_verifying_blocks: BlockHeight,
+	/// Maximal number of blocks in requested + verifying queues.
+	pub block_download_window: BlockHeight,
 	/// Minimum number of blocks to request from peer
 	pub min_blocks_in_request: BlockHeight,
 	/// Maximum number of blocks to request from peer
@@ -214,6 +240,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// sync tasks from this peers must be executed by other peers
 		let peer_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
 		self.peers_tasks.disconnect(peer_index);
+		self.requested_packages.remove(&peer_index);
+		self.headers_syncing_peers.remove(&peer_index);
+		// forget orphans, received from this peer, so that they are not occupying other peers quotas
+		self.orphaned_transactions_pool.remove_peer_transactions(peer_index);
+		for removed_block_hash in self.orphaned_blocks_pool.remove_peer_blocks(peer_index) {
+			self.chain.forget_block(&removed_block_hash);
+		}
 		self.execute_synchronization_tasks(Some(peer_tasks), None);
 	}
 
@@ -238,8 +271,9 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 						_ => false,
 					},
 					// we never ask for merkle blocks && we never ask for compact blocks
+					// && packages info is never announced
 					InventoryType::MessageCompactBlock | InventoryType::MessageFilteredBlock
-						| InventoryType::MessageWitnessFilteredBlock
+						| InventoryType::MessageWitnessFilteredBlock | InventoryType::MessageAncestorPackageInfo
 						 => false,
 					// unknown inventory type
 					InventoryType::Error => {
@@ -270,7 +304,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 
 		// if everything is known => ignore this message
 		if unknown_inventory.is_empty() {
-			trace!(target: "sync", "Ignoring inventory message from peer#{} as all items are known", peer_index);
+			trace!(target: "sync", peer = peer_index; "Ignoring inventory message from peer#{} as all items are known", peer_index);
 			return;
 		}
 
@@ -322,9 +356,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 						.expect("last header has UnknownState; we are searching for first unknown header; qed"),
 					// else all headers are known
 					_ => {
-						trace!(target: "sync", "Ignoring {} known headers from peer#{}", headers.len(), peer_index);
+						trace!(target: "sync", peer = peer_index; "Ignoring {} known headers from peer#{}", headers.len(), peer_index);
 						// but this peer is still useful for synchronization
 						self.peers_tasks.useful_peer(peer_index);
+						// && it has no new headers for us => maybe we could start requesting blocks
+						if self.headers_syncing_peers.remove(&peer_index) && self.are_headers_synced() {
+							self.execute_synchronization_tasks(None, None);
+						}
 						return;
 					},
 				}
@@ -333,6 +371,10 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 
 		// validate blocks headers before scheduling
 		let last_known_hash = if first_unknown_index > 0 { headers[first_unknown_index - 1].hash.clone() } else { header0.raw.previous_header_hash.clone() };
+		if self.chain.is_invalidated_branch(&last_known_hash) {
+			trace!(target: "sync", peer = peer_index, block:% = last_known_hash.reversed(); "Ignoring {} headers from peer#{} after invalidated block {}", headers.len(), peer_index, last_known_hash.to_reversed_str());
+			return;
+		}
 		if self.config.close_connection_on_bad_block && self.chain.block_state(&last_known_hash) == BlockState::DeadEnd {
 			self.peers.misbehaving(peer_index, &format!("Provided after dead-end block {}", last_known_hash.to_reversed_str()));
 			return;
@@ -343,13 +385,20 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			BlocksHeadersVerificationResult::Success => {
 				// report progress
 				let num_new_headers = num_headers - first_unknown_index;
-				trace!(target: "sync", "New {} headers from peer#{}. First {:?}, last: {:?}",
+				trace!(target: "sync", peer = peer_index; "New {} headers from peer#{}. First {:?}, last: {:?}",
 					num_new_headers,
 					peer_index,
 					headers[first_unknown_index].hash.to_reversed_str(),
 					headers[num_headers - 1].hash.to_reversed_str()
 				);
 
+				// peer has responded with full headers message => there are more headers to ask for
+				// blocks are only requested after all headers are received && verified
+				match num_headers < types::HEADERS_MAX_HEADERS_LEN {
+					true => self.headers_syncing_peers.remove(&peer_index),
+					false => self.headers_syncing_peers.insert(peer_index),
+				};
+
 				// prepare new headers array
 				let new_headers = headers.split_off(first_unknown_index);
 				self.chain.schedule_blocks_headers(new_headers);
@@ -376,9 +425,20 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// update peers to select next tasks
 		self.peers_tasks.on_block_received(peer_index, &block.header.hash);
 
+		// blocks of the manually invalidated branch are not synchronized
+		if self.chain.is_invalidated_block(&block.header.hash) || self.chain.is_invalidated_branch(&block.header.raw.previous_header_hash) {
+			trace!(target: "sync", peer = peer_index, block:% = block.header.hash.reversed(); "Ignoring invalidated block {} from peer#{}", block.header.hash.to_reversed_str(), peer_index);
+			self.chain.forget_block(&block.header.hash);
+			return None;
+		}
+
 		// prepare list of blocks to verify + make all required changes to the chain
 		let mut result: Option<VecDeque<IndexedBlock>> = None;
 		let block_state = self.chain.block_state(&block.header.hash);
+		// requested block is received => stalling timeout returns to its default value
+		if block_state == BlockState::Requested && self.block_stalling_timeout > BLOCK_STALLING_TIMEOUT_S {
+			self.block_stalling_timeout = (self.block_stalling_timeout * BLOCK_STALLING_TIMEOUT_DECAY).max(BLOCK_STALLING_TIMEOUT_S);
+		}
 		match block_state {
 			BlockState::Verifying | BlockState::Stored => {
 				// remember peer as useful
@@ -391,7 +451,7 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 						self.peers.misbehaving(peer_index, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
 						return None;
 					}
-					warn!(target: "sync", "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
+					warn!(target: "sync", peer = peer_index, block:% = block.header.hash.reversed(); "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
 				}
 
 				// check parent block state
@@ -403,13 +463,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 								self.peers.misbehaving(peer_index, &format!("Provided dead-end block {}", block.header.hash.to_reversed_str()));
 								return None;
 							}
-							warn!(target: "sync", "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
+							warn!(target: "sync", peer = peer_index, block:% = block.header.hash.reversed(); "Peer#{} has provided dead-end block {}", peer_index, block.header.hash.to_reversed_str());
 						}
 
 						if self.state.is_synchronizing() {
 							// when synchronizing, we tend to receive all blocks in-order
 							trace!(
-								target: "sync",
+								target: "sync", peer = peer_index, block:% = block.header.hash.reversed();
 								"Ignoring block {} from peer#{}, because its parent is unknown and we are synchronizing",
 								block.header.hash.to_reversed_str(),
 								peer_index
@@ -428,7 +488,13 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 							self.chain.forget_block_leave_header(&block.header.hash);
 							// remember this block as unknown
 							if !self.orphaned_blocks_pool.contains_unknown_block(&block.header.hash) {
-								self.orphaned_blocks_pool.insert_unknown_block(block);
+								let evicted_blocks_hashes = self.orphaned_blocks_pool.insert_unknown_block(block, peer_index);
+								if !evicted_blocks_hashes.is_empty() {
+									trace!(target: "sync", peer = peer_index; "Evicted {} unknown blocks after receiving block from peer#{}", evicted_blocks_hashes.len(), peer_index);
+									for evicted_block_hash in evicted_blocks_hashes {
+										self.chain.forget_block(&evicted_block_hash);
+									}
+								}
 							}
 						}
 					},
@@ -520,6 +586,65 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// When peer has responded with unconfirmed ancestors of orphaned transaction
+	fn on_ancpkginfo(&mut self, peer_index: PeerIndex, message: types::AncPkgInfo) {
+		// we only ask for ancestors of orphaned transactions
+		let child_hash = match message.hashes.last() {
+			Some(child_hash) if self.orphaned_transactions_pool.contains(child_hash) => child_hash.clone(),
+			_ => return,
+		};
+
+		// ask for ancestors, which are unknown to us
+		let unknown_hashes: Vec<_> = message.hashes.iter()
+			.filter(|hash| **hash != child_hash)
+			.filter(|hash| self.chain.transaction_state(hash) == TransactionState::Unknown
+				&& !self.orphaned_transactions_pool.contains(hash))
+			.cloned()
+			.collect();
+		if unknown_hashes.is_empty() {
+			return;
+		}
+
+		trace!(target: "sync", peer = peer_index; "Asking peer#{} for {} ancestors of orphaned transaction {}", peer_index, unknown_hashes.len(), child_hash.to_reversed_str());
+		self.requested_packages.insert(peer_index, message.hashes);
+		self.executor.execute(Task::GetPkgTxns(peer_index, types::GetPkgTxns::with_hashes(unknown_hashes)));
+	}
+
+	/// When peer has responded with requested package transactions.
+	/// Returns the whole package for verification, if every its transaction is now known.
+	fn on_pkgtxns(&mut self, peer_index: PeerIndex, transactions: Vec<IndexedTransaction>) -> Option<Vec<IndexedTransaction>> {
+		let package_hashes = match self.requested_packages.remove(&peer_index) {
+			Some(package_hashes) => package_hashes,
+			None => {
+				trace!(target: "sync", peer = peer_index; "Ignoring unrequested package from peer#{}", peer_index);
+				return None;
+			},
+		};
+
+		// package is built from received transactions and from orphaned transactions
+		// ancestors, which are already in the memory pool, are omitted
+		let mut package = Vec::with_capacity(package_hashes.len());
+		for hash in package_hashes {
+			if let Some(transaction) = transactions.iter().find(|transaction| transaction.hash == hash) {
+				package.push(transaction.clone());
+			} else if let Some(orphan) = self.orphaned_transactions_pool.transactions().get(&hash) {
+				package.push(orphan.transaction.clone());
+			} else if self.chain.transaction_state(&hash) != TransactionState::InMemory {
+				trace!(target: "sync", peer = peer_index; "Ignoring incomplete package from peer#{}", peer_index);
+				return None;
+			}
+		}
+
+		let child_hash = package.last().expect("package always contains orphaned child; qed").hash.clone();
+		if self.is_verifying_package(&child_hash) {
+			trace!(target: "sync", peer = peer_index; "Ignoring package from peer#{}, which is already verifying", peer_index);
+			return None;
+		}
+
+		self.verifying_packages_by_peer.insert(child_hash, peer_index);
+		Some(package)
+	}
+
 	/// Execute after last block from this peer in NearlySaturated state is verified.
 	/// If there are no verifying blocks from this peer or we are not in the NearlySaturated state => execute immediately.
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: EmptyBoxFuture) {
@@ -538,11 +663,11 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
-	fn accept_transaction(&mut self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, String> {
+	fn accept_transaction(&mut self, transaction: IndexedTransaction, sink: Box<dyn TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, TransactionRejection> {
 		let hash = transaction.hash;
 		match self.try_append_transaction(transaction, true) {
-			Err(AppendTransactionError::Orphan(_)) => Err("Cannot append transaction as its inputs are unknown".to_owned()),
-			Err(AppendTransactionError::Synchronizing) => Err("Cannot append transaction as node is not yet fully synchronized".to_owned()),
+			Err(AppendTransactionError::Orphan(_)) => Err(TransactionRejection::new(RejectCode::Invalid, "bad-txns-inputs-missingorspent".to_owned())),
+			Err(AppendTransactionError::Synchronizing) => Err(TransactionRejection::new(RejectCode::Invalid, "Cannot append transaction as node is not yet fully synchronized".to_owned())),
 			Ok(transactions) => {
 				self.verifying_transactions_sinks.insert(hash, sink);
 				Ok(transactions)
@@ -550,10 +675,50 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	fn accept_package(&mut self, package: &[IndexedTransaction], sink: Box<dyn PackageVerificationSink>) -> Result<(), TransactionRejection> {
+		let child_hash = match package.last() {
+			Some(child) => child.hash.clone(),
+			None => return Err(TransactionRejection::new(RejectCode::Invalid, "package-empty".to_owned())),
+		};
+		if self.is_verifying_package(&child_hash) {
+			return Err(TransactionRejection::new(RejectCode::Duplicate, "package-already-verifying".to_owned()));
+		}
+
+		self.verifying_packages_sinks.insert(child_hash, sink);
+		Ok(())
+	}
+
+	fn invalidate_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		match self.chain.storage().block_number(hash) {
+			Some(0) => return Err("Genesis block cannot be invalidated".to_owned()),
+			_ if !self.chain.storage().contains_block(hash.clone().into()) => return Err("Block not found".to_owned()),
+			_ => (),
+		}
+
+		let insert_result = self.chain.invalidate_block(hash)?;
+		Ok(self.on_best_chain_switched(insert_result))
+	}
+
+	fn reconsider_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		if !self.chain.storage().contains_block(hash.clone().into()) {
+			return Err("Block not found".to_owned());
+		}
+
+		let insert_result = self.chain.reconsider_block(hash)?;
+		Ok(self.on_best_chain_switched(insert_result))
+	}
+
+	fn precious_block(&mut self, hash: &H256) -> Result<VecDeque<IndexedTransaction>, String> {
+		if !self.chain.storage().contains_block(hash.clone().into()) {
+			return Err("Block not found".to_owned());
+		}
+
+		let insert_result = self.chain.precious_block(hash)?;
+		Ok(self.on_best_chain_switched(insert_result))
+	}
+
 	fn install_sync_listener(&mut self, listener: SyncListenerRef) {
-		// currently single, single-setup listener is supported
-		assert!(self.listener.is_none());
-		self.listener = Some(listener);
+		self.listeners.push(listener);
 	}
 
 	/// Schedule new synchronization tasks, if any.
@@ -570,6 +735,15 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			limits.max_blocks_in_request = 16;
 		}
 
+		// if some peer is stalling the download window => its blocks are requested from other peers
+		let forced_blocks_requests = match (forced_blocks_requests, self.reset_stalling_peer_tasks()) {
+			(Some(mut forced_blocks_requests), Some(stalled_blocks_requests)) => {
+				forced_blocks_requests.extend(stalled_blocks_requests);
+				Some(forced_blocks_requests)
+			},
+			(forced_blocks_requests, stalled_blocks_requests) => forced_blocks_requests.or(stalled_blocks_requests),
+		};
+
 		// if some blocks requests are forced => we should ask peers even if there are no idle peers
 		let verifying_hashes_len = self.chain.length_of_blocks_state(BlockState::Verifying);
 		if let Some(forced_blocks_requests) = forced_blocks_requests {
@@ -600,8 +774,9 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 			// check if we can query some blocks headers
 			let headers_idle_peers: Vec<_> = self.peers_tasks.idle_peers_for_headers().iter().cloned().collect();
 			if !headers_idle_peers.is_empty() {
+				// while headers are not synchronized => ask for headers regardless of scheduled queue size
 				let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-				if scheduled_hashes_len < MAX_SCHEDULED_HASHES {
+				if !self.are_headers_synced() || scheduled_hashes_len < limits.max_scheduled_hashes {
 					for header_peer in &headers_idle_peers {
 						self.peers_tasks.on_headers_requested(*header_peer);
 					}
@@ -682,12 +857,14 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				}
 
 				// check if we can move some blocks from scheduled to requested queue
-				{
-					// TODO: only request minimal number of blocks, if other urgent blocks are requested
+				// headers-first: do not request blocks until the whole headers chain is known
+				if self.are_headers_synced() {
+					// blocks are requested in the moving window, which starts at the first block that is not yet verified
 					let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-					if requested_hashes_len + verifying_hashes_len < MAX_REQUESTED_BLOCKS + MAX_VERIFYING_BLOCKS && scheduled_hashes_len != 0 {
+					let window_hashes_len = requested_hashes_len + verifying_hashes_len;
+					if window_hashes_len < limits.block_download_window && scheduled_hashes_len != 0 {
 						let chunk_size = min(limits.max_blocks_in_request, max(scheduled_hashes_len / blocks_idle_peers_len, limits.min_blocks_in_request));
-						let hashes_to_request_len = chunk_size * blocks_idle_peers_len;
+						let hashes_to_request_len = min(chunk_size * blocks_idle_peers_len, limits.block_download_window - window_hashes_len);
 						let hashes_to_request = self.chain.request_blocks_hashes(hashes_to_request_len);
 						match blocks_requests {
 							Some(ref mut blocks_requests) => blocks_requests.extend(hashes_to_request),
@@ -745,6 +922,11 @@ impl<T> BlockVerificationSink for CoreVerificationSink<T> where T: TaskExecutor
 	fn on_block_verification_error(&self, err: &str, hash: &H256) {
 		self.core.lock().on_block_verification_error(err, hash)
 	}
+
+	/// Check if block scripts verification could be skipped
+	fn is_assumed_valid_block(&self, hash: &H256) -> bool {
+		self.core.lock().chain().is_assumed_valid_block(hash)
+	}
 }
 
 impl<T> TransactionVerificationSink for CoreVerificationSink<T> where T: TaskExecutor {
@@ -754,8 +936,20 @@ impl<T> TransactionVerificationSink for CoreVerificationSink<T> where T: TaskExe
 	}
 
 	/// Process failed transaction verification
-	fn on_transaction_verification_error(&self, err: &str, hash: &H256) {
-		self.core.lock().on_transaction_verification_error(err, hash)
+	fn on_transaction_verification_error(&self, rejection: &TransactionRejection, hash: &H256) {
+		self.core.lock().on_transaction_verification_error(rejection, hash)
+	}
+}
+
+impl<T> PackageVerificationSink for CoreVerificationSink<T> where T: TaskExecutor {
+	/// Process successful package verification
+	fn on_package_verification_success(&self, package: Vec<IndexedTransaction>, acceptance: PackageAcceptance) -> Option<Vec<VerificationTask>> {
+		self.core.lock().on_package_verification_success(package, acceptance)
+	}
+
+	/// Process failed package verification
+	fn on_package_verification_error(&self, acceptance: PackageAcceptance, hash: &H256) {
+		self.core.lock().on_package_verification_error(acceptance, hash)
 	}
 }
 
@@ -779,11 +973,18 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				verifying_blocks_futures: HashMap::new(),
 				verifying_transactions_sinks: HashMap::new(),
 				do_not_relay: HashSet::new(),
+				requested_packages: HashMap::new(),
+				verifying_packages_by_peer: HashMap::new(),
+				verifying_packages_sinks: HashMap::new(),
 				block_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
 				sync_speed_meter: AverageSpeedMeter::with_inspect_items(BLOCKS_SPEED_BLOCKS_TO_INSPECT),
 				config: config,
-				listener: None,
+				listeners: Vec::new(),
 				last_dup_time: 0f64,
+				headers_syncing_peers: HashSet::new(),
+				stalling_block: None,
+				block_stalling_timeout: BLOCK_STALLING_TIMEOUT_S,
+				last_tip_update_time: precise_time_s(),
 			}
 		));
 
@@ -797,7 +998,6 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 	}
 
 	/// Get information on current synchronization state.
-	#[cfg(test)]
 	pub fn information(&self) -> Information {
 		Information {
 			state: self.state,
@@ -875,6 +1075,75 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// Ask for additional outbound connection if best block has not been updated for a long time
+	pub fn check_stale_tip(&mut self) {
+		if self.state.is_synchronizing() {
+			return;
+		}
+
+		let now = precise_time_s();
+		if now - self.last_tip_update_time < STALE_TIP_TIMEOUT_S {
+			return;
+		}
+
+		// any connection could be used to reach the p2p layer
+		if let Some(connection) = self.peers.enumerate().into_iter().filter_map(|peer_index| self.peers.connection(peer_index)).next() {
+			warn!(target: "sync", "Best block has not been updated for {:.0} seconds. Trying extra outbound connection", now - self.last_tip_update_time);
+			connection.connect_extra_outbound();
+		}
+		self.last_tip_update_time = now;
+	}
+
+	/// Have we received all headers from all peers?
+	fn are_headers_synced(&self) -> bool {
+		self.headers_syncing_peers.is_empty()
+	}
+
+	/// Reset tasks of the peer, which is stalling the full blocks download window.
+	/// Returns blocks that must be requested from other peers.
+	fn reset_stalling_peer_tasks(&mut self) -> Option<Vec<H256>> {
+		let window_hashes_len = self.chain.length_of_blocks_state(BlockState::Requested)
+			+ self.chain.length_of_blocks_state(BlockState::Verifying);
+		// window is not full || there's no one to ask instead => no stalling
+		if !self.are_headers_synced()
+			|| window_hashes_len < BLOCK_DOWNLOAD_WINDOW
+			|| self.chain.length_of_blocks_state(BlockState::Scheduled) == 0
+			|| self.peers_tasks.idle_peers_for_blocks().is_empty() {
+			self.stalling_block = None;
+			return None;
+		}
+
+		// the whole window is waiting for the lowest requested block
+		let lowest_block = self.chain.best_n_of_blocks_state(BlockState::Requested, 1).pop();
+		let (lowest_block, peer_index) = match lowest_block.and_then(|h| self.peers_tasks.blocks_request_peer(&h).map(|p| (h, p))) {
+			Some(lowest_block_and_peer) => lowest_block_and_peer,
+			None => {
+				self.stalling_block = None;
+				return None;
+			},
+		};
+
+		let now = precise_time_s();
+		let is_stalling = match self.stalling_block {
+			Some((ref hash, since)) if *hash == lowest_block => now - since >= self.block_stalling_timeout,
+			_ => {
+				self.stalling_block = Some((lowest_block, now));
+				return None;
+			},
+		};
+		if !is_stalling {
+			return None;
+		}
+
+		self.stalling_block = None;
+		// if we keep seeing stalls, then it could be our own connection that is slow => back off
+		self.block_stalling_timeout = (self.block_stalling_timeout * 2_f64).min(BLOCK_STALLING_TIMEOUT_MAX_S);
+		let peer_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
+		self.peers_tasks.unuseful_peer(peer_index);
+		self.peers.misbehaving(peer_index, &format!("Stalling blocks download at block {}", lowest_block.to_reversed_str()));
+		Some(peer_tasks)
+	}
+
 	/// Verify and select unknown headers for scheduling
 	fn verify_headers(&mut self, peer_index: PeerIndex, last_known_hash: H256, headers: &[IndexedBlockHeader]) -> BlocksHeadersVerificationResult {
 		// validate blocks headers before scheduling
@@ -898,7 +1167,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 					return BlocksHeadersVerificationResult::Skip;
 				},
 				block_state => {
-					trace!(target: "sync", "Ignoring {} headers from peer#{} - known ({:?}) header {} at the {}/{} ({}...{})",
+					trace!(target: "sync", peer = peer_index; "Ignoring {} headers from peer#{} - known ({:?}) header {} at the {}/{} ({}...{})",
 						headers.len(), peer_index, block_state, header.hash.to_reversed_str(), header_index, headers.len(),
 						headers[0].hash.to_reversed_str(), headers[headers.len() - 1].hash.to_reversed_str());
 					self.peers_tasks.useful_peer(peer_index);
@@ -926,10 +1195,22 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 	}
 
 	/// Process new peer transaction
-	fn process_peer_transaction(&mut self, _peer_index: Option<PeerIndex>, transaction: IndexedTransaction, relay: bool) -> Option<VecDeque<IndexedTransaction>> {
+	fn process_peer_transaction(&mut self, peer_index: Option<PeerIndex>, transaction: IndexedTransaction, relay: bool) -> Option<VecDeque<IndexedTransaction>> {
 		match self.try_append_transaction(transaction.clone(), relay) {
 			Err(AppendTransactionError::Orphan(unknown_parents)) => {
-				self.orphaned_transactions_pool.insert(transaction, unknown_parents);
+				// peer could provide us with all unconfirmed ancestors of the orphan
+				// => ask for them, so that the orphan could be accepted as a package
+				if let Some(peer_index) = peer_index {
+					if self.peers.is_package_relay_enabled(peer_index) {
+						let inventory = vec![InventoryVector::ancestor_package_info(transaction.hash.clone())];
+						self.executor.execute(Task::GetData(peer_index, types::GetData::with_inventory(inventory)));
+					}
+				}
+
+				let evicted_transactions = self.orphaned_transactions_pool.insert(transaction, unknown_parents, peer_index);
+				if !evicted_transactions.is_empty() {
+					trace!(target: "sync", "Evicted {} orphaned transactions from orphans pool", evicted_transactions.len());
+				}
 				None
 			},
 			Err(AppendTransactionError::Synchronizing) => None,
@@ -1011,7 +1292,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(true);
 		}
 
@@ -1025,7 +1306,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1039,7 +1320,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			return;
 		}
 
-		if let Some(ref listener) = self.listener {
+		for listener in &self.listeners {
 			listener.synchronization_state_switched(false);
 		}
 
@@ -1095,14 +1376,13 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			Ok(insert_result) => {
 				// update shared state
 				self.shared_state.update_best_storage_block_height(self.chain.best_storage_block().number);
-
-				// notify listener
-				if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
-					if let Some(ref listener) = self.listener {
-						listener.best_storage_block_inserted(best_block_hash);
-					}
+				if !insert_result.canonized_blocks_hashes.is_empty() {
+					self.last_tip_update_time = precise_time_s();
 				}
 
+				// notify listeners
+				self.notify_best_storage_block_changed(&insert_result);
+
 				// awake threads, waiting for this block insertion
 				self.awake_waiting_threads(&block_hash);
 
@@ -1122,11 +1402,15 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				let mut verification_tasks: Vec<VerificationTask> = Vec::with_capacity(insert_result.transactions_to_reverify.len());
 				let next_block_height = self.chain.best_block().number + 1;
 				for tx in insert_result.transactions_to_reverify {
+					let tx_hash = tx.hash.clone();
 					// do not relay resurrected transactions again
-					if let Some(tx_orphans) = self.process_peer_transaction(None, tx.into(), false) {
-						let tx_tasks = tx_orphans.into_iter().map(|tx| VerificationTask::VerifyTransaction(next_block_height, tx));
-						verification_tasks.extend(tx_tasks);
-					};
+					match self.process_peer_transaction(None, tx.into(), false) {
+						Some(tx_orphans) => {
+							let tx_tasks = tx_orphans.into_iter().map(|tx| VerificationTask::VerifyTransaction(next_block_height, tx));
+							verification_tasks.extend(tx_tasks);
+						},
+						None => self.chain.forget_reverified_transaction(&tx_hash),
+					}
 				}
 				Some(verification_tasks)
 			},
@@ -1137,8 +1421,58 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// Notify listeners about decanonized blocks and the new best storage block
+	fn notify_best_storage_block_changed(&self, insert_result: &BlockInsertionResult) {
+		if !insert_result.decanonized_blocks_hashes.is_empty() {
+			for listener in &self.listeners {
+				listener.best_storage_blocks_decanonized(&insert_result.decanonized_blocks_hashes);
+			}
+		}
+
+		// when blocks are only decanonized, the best block is the common ancestor
+		let best_block_hash = match insert_result.canonized_blocks_hashes.last() {
+			Some(best_block_hash) => best_block_hash.clone(),
+			None if !insert_result.decanonized_blocks_hashes.is_empty() => self.chain.best_storage_block().hash,
+			None => return,
+		};
+		for listener in &self.listeners {
+			listener.best_storage_block_inserted(&best_block_hash);
+		}
+	}
+
+	/// Called when best storage chain is switched by the user request
+	fn on_best_chain_switched(&mut self, insert_result: BlockInsertionResult) -> VecDeque<IndexedTransaction> {
+		// update shared state
+		self.shared_state.update_best_storage_block_height(self.chain.best_storage_block().number);
+		if !insert_result.canonized_blocks_hashes.is_empty() {
+			self.last_tip_update_time = precise_time_s();
+		}
+
+		// notify listeners
+		self.notify_best_storage_block_changed(&insert_result);
+
+		// requested blocks are forgotten by the chain => ask all peers for headers of the new best chain
+		let block_locator_hashes = self.chain.block_locator_hashes();
+		let peers: Vec<_> = self.peers_tasks.all_peers().iter().cloned().collect();
+		for peer in peers {
+			self.peers_tasks.reset_blocks_tasks(peer);
+			self.executor.execute(Task::GetHeaders(peer, types::GetHeaders::with_block_locator_hashes(block_locator_hashes.clone())));
+		}
+
+		// reverify transactions of decanonized blocks and memory pool
+		let mut transactions_to_verify = VecDeque::new();
+		for tx in insert_result.transactions_to_reverify {
+			let tx_hash = tx.hash.clone();
+			match self.process_peer_transaction(None, tx, false) {
+				Some(tx_orphans) => transactions_to_verify.extend(tx_orphans),
+				None => self.chain.forget_reverified_transaction(&tx_hash),
+			}
+		}
+		transactions_to_verify
+	}
+
 	fn on_block_verification_error(&mut self, err: &str, hash: &H256) {
-		warn!(target: "sync", "Block {:?} verification failed with error {:?}", hash.to_reversed_str(), err);
+		warn!(target: "sync", block:% = hash.reversed(); "Block {:?} verification failed with error {:?}", hash.to_reversed_str(), err);
 
 		// remove flags
 		self.do_not_relay.remove(hash);
@@ -1148,7 +1482,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			if self.config.close_connection_on_bad_block {
 				self.peers.dos(*peer_index, &format!("Provided wrong block {}", hash.to_reversed_str()))
 			} else {
-				warn!(target: "sync", "Peer#{} has provided wrong block {:?}", peer_index, hash.to_reversed_str());
+				warn!(target: "sync", peer = peer_index, block:% = hash.reversed(); "Peer#{} has provided wrong block {:?}", peer_index, hash.to_reversed_str());
 			}
 		}
 
@@ -1194,8 +1528,8 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
-	fn on_transaction_verification_error(&mut self, err: &str, hash: &H256) {
-		warn!(target: "sync", "Transaction {} verification failed with error {:?}", hash.to_reversed_str(), err);
+	fn on_transaction_verification_error(&mut self, rejection: &TransactionRejection, hash: &H256) {
+		warn!(target: "sync", "Transaction {} verification failed with error {:?}", hash.to_reversed_str(), rejection.reason);
 
 		// remove flags
 		self.do_not_relay.remove(hash);
@@ -1205,10 +1539,76 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 
 		// call verification future, if any
 		if let Some(future_sink) = self.verifying_transactions_sinks.remove(hash) {
-			future_sink.on_transaction_verification_error(err, hash);
+			future_sink.on_transaction_verification_error(rejection, hash);
 		}
 	}
 
+	/// Insert package, which has been verified as a whole, to the memory pool.
+	/// Returns verification tasks for orphaned transactions, which are now ready for verification.
+	fn on_package_verification_success(&mut self, package: Vec<IndexedTransaction>, acceptance: PackageAcceptance) -> Option<Vec<VerificationTask>> {
+		let child_hash = package.last().expect("packages are never empty; qed").hash.clone();
+		self.verifying_packages_by_peer.remove(&child_hash);
+		let package_hashes: Vec<H256> = package.iter().map(|transaction| transaction.hash.clone()).collect();
+
+		// package transactions could be orphaned or could be verified individually
+		let orphans = self.orphaned_transactions_pool.remove_transactions(&package_hashes);
+		for hash in &package_hashes {
+			self.chain.forget_verifying_transaction(hash);
+		}
+
+		let new_transactions: Vec<_> = package.iter()
+			.filter(|transaction| self.chain.transaction_state(&transaction.hash) != TransactionState::InMemory)
+			.cloned()
+			.collect();
+		self.chain.insert_verified_package(new_transactions.clone());
+
+		// relay package transactions to peers
+		for transaction in new_transactions {
+			let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
+			self.executor.execute(Task::RelayNewTransaction(transaction, transaction_fee_rate));
+		}
+
+		// call verification future, if any
+		if let Some(future_sink) = self.verifying_packages_sinks.remove(&child_hash) {
+			future_sink.on_package_verification_success(package, acceptance);
+		}
+
+		// orphans, which have been waiting for package transactions, could be verified now
+		let next_block_height = self.chain.best_block().number + 1;
+		let tasks: Vec<_> = orphans.into_iter()
+			.filter(|transaction| !package_hashes.contains(&transaction.hash))
+			.map(|transaction| {
+				self.chain.verify_transaction(transaction.clone());
+				VerificationTask::VerifyTransaction(next_block_height, transaction)
+			})
+			.collect();
+		Some(tasks)
+	}
+
+	fn on_package_verification_error(&mut self, acceptance: PackageAcceptance, hash: &H256) {
+		let rejection = acceptance.result.as_ref().err().cloned()
+			.unwrap_or_else(|| TransactionRejection::new(RejectCode::Invalid, "package-rejected".to_owned()));
+		warn!(target: "sync", "Package of transaction {} verification failed with error {:?}", hash.to_reversed_str(), rejection.reason);
+
+		// peer, which has provided us with invalid package, is misbehaving
+		// => policy rejections are not punished, since they depend on our own settings
+		if let Some(peer_index) = self.verifying_packages_by_peer.remove(hash) {
+			if rejection.code == RejectCode::Invalid {
+				self.peers.misbehaving(peer_index, &format!("Provided invalid package of transaction {}: {}", hash.to_reversed_str(), rejection.reason));
+			}
+		}
+
+		// call verification future, if any
+		if let Some(future_sink) = self.verifying_packages_sinks.remove(hash) {
+			future_sink.on_package_verification_error(acceptance, hash);
+		}
+	}
+
+	/// Is package of given child transaction currently verifying?
+	fn is_verifying_package(&self, hash: &H256) -> bool {
+		self.verifying_packages_by_peer.contains_key(hash) || self.verifying_packages_sinks.contains_key(hash)
+	}
+
 	/// Execute futures, which were waiting for this block verification
 	fn awake_waiting_threads(&mut self, hash: &H256) {
 		// find a peer, which has supplied us with this block
@@ -1242,8 +1642,7 @@ impl Default for BlocksRequestLimits {
 	fn default() -> Self {
 		BlocksRequestLimits {
 			max_scheduled_hashes: MAX_SCHEDULED_HASHES,
-			max_requested_blocks: MAX_REQUESTED_BLOCKS,
-			max_verifying_blocks: MAX_VERIFYING_BLOCKS,
+			block_download_window: BLOCK_DOWNLOAD_WINDOW,
 			min_blocks_in_request: MIN_BLOCKS_IN_REQUEST,
 			max_blocks_in_request: MAX_BLOCKS_IN_REQUEST,
 		}
@@ -1256,7 +1655,7 @@ pub mod tests {
 
 	use std::sync::Arc;
 	use parking_lot::{Mutex, RwLock};
-	use chain::{Block, Transaction};
+	use chain::{Block, Transaction, IndexedBlock, IndexedBlockHeader};
 	use db::BlockChainDatabase;
 	use message::common::InventoryVector;
 	use message::{Services, types};
@@ -1265,21 +1664,23 @@ pub mod tests {
 	use primitives::hash::H256;
 	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 	use inbound_connection::tests::DummyOutboundSyncConnection;
-	use synchronization_chain::Chain;
+	use synchronization_chain::{Chain, BlockState};
 	use synchronization_client::{SynchronizationClient, Client};
 	use synchronization_peers::PeersImpl;
 	use synchronization_executor::Task;
 	use synchronization_executor::tests::DummyTaskExecutor;
 	use synchronization_verifier::tests::DummyVerifier;
-	use utils::SynchronizationState;
+	use ser::Serializable;
+	use utils::{SynchronizationState, OrphanBlocksPool, OrphanTransactionsPool};
 	use types::{PeerIndex, StorageRef, SynchronizationStateRef, ClientCoreRef};
-	use super::{Config, SynchronizationClientCore, ClientCore, CoreVerificationSink};
+	use super::{Config, SynchronizationClientCore, ClientCore, CoreVerificationSink, BLOCK_DOWNLOAD_WINDOW, BLOCK_STALLING_TIMEOUT_S, STALE_TIP_TIMEOUT_S};
 	use super::super::SyncListener;
 
 	#[derive(Default)]
 	struct DummySyncListenerData {
 		pub is_synchronizing: bool,
 		pub best_blocks: Vec<H256>,
+		pub decanonized_blocks: Vec<H256>,
 	}
 
 	struct DummySyncListener {
@@ -1302,6 +1703,10 @@ pub mod tests {
 		fn best_storage_block_inserted(&self, block_hash: &H256) {
 			self.data.lock().best_blocks.push(block_hash.clone());
 		}
+
+		fn best_storage_blocks_decanonized(&self, blocks_hashes: &[H256]) {
+			self.data.lock().decanonized_blocks.extend(blocks_hashes.iter().cloned());
+		}
 	}
 
 	fn create_sync(storage: Option<StorageRef>, verifier: Option<DummyVerifier>) -> (Arc<DummyTaskExecutor>, ClientCoreRef<SynchronizationClientCore<DummyTaskExecutor>>, Arc<SynchronizationClient<DummyTaskExecutor, DummyVerifier>>) {
@@ -1968,6 +2373,89 @@ pub mod tests {
 		assert_eq!(core.lock().information().orphaned_transactions, 1);
 	}
 
+	#[test]
+	fn flooding_peer_does_not_push_out_orphaned_transactions_of_other_peers() {
+		let (_, core, sync) = create_sync(None, None);
+
+		let honest_transaction: Transaction = test_data::TransactionBuilder::with_default_input(0).into();
+		let honest_transaction_hash = honest_transaction.hash();
+		let transaction_size = honest_transaction.serialized_size();
+		*core.lock().orphaned_transactions_pool() = OrphanTransactionsPool::with_limits(10 * transaction_size, 4 * transaction_size);
+
+		// honest peer sends single orphan
+		sync.on_transaction(2, honest_transaction.into());
+
+		// flooding peer sends a lot of orphans
+		for index in 1..100 {
+			sync.on_transaction(1, test_data::TransactionBuilder::with_default_input(index).into());
+		}
+
+		{
+			let mut core = core.lock();
+			let pool = core.orphaned_transactions_pool();
+			assert!(pool.contains(&honest_transaction_hash));
+			assert!(pool.peer_size(1) <= 4 * transaction_size);
+			assert!(pool.size() <= 10 * transaction_size);
+		}
+
+		// orphans of flooding peer are removed when it disconnects
+		sync.on_disconnect(1);
+		{
+			let mut core = core.lock();
+			let pool = core.orphaned_transactions_pool();
+			assert_eq!(pool.len(), 1);
+			assert_eq!(pool.peer_size(1), 0);
+			assert!(pool.contains(&honest_transaction_hash));
+		}
+	}
+
+	#[test]
+	fn flooding_peer_does_not_push_out_unknown_blocks_of_other_peers() {
+		let (_, core, sync) = create_sync(None, None);
+
+		let unknown_block = |index: u32| test_data::block_builder().header().parent(H256::from((index + 1) as u8)).nonce(index).build().build();
+		let block_size = IndexedBlock::from(unknown_block(0)).size_with_witness();
+		*core.lock().orphaned_blocks_pool() = OrphanBlocksPool::with_limits(10 * block_size, 4 * block_size);
+
+		// honest peer sends single unknown block
+		let honest_block = unknown_block(0);
+		let honest_block_hash = honest_block.hash();
+		sync.on_block(2, honest_block.into());
+
+		// flooding peer sends a lot of unknown blocks
+		let flooding_blocks: Vec<Block> = (1..100).map(unknown_block).collect();
+		for block in &flooding_blocks {
+			sync.on_block(1, block.clone().into());
+		}
+
+		{
+			let mut core = core.lock();
+			{
+				let pool = core.orphaned_blocks_pool();
+				assert!(pool.contains_unknown_block(&honest_block_hash));
+				assert!(pool.peer_unknown_blocks_size(1) <= 4 * block_size);
+				assert!(pool.unknown_blocks_size() <= 10 * block_size);
+			}
+			// evicted blocks are also forgotten by the chain
+			let evicted_blocks: Vec<_> = flooding_blocks.iter()
+				.map(|block| block.hash())
+				.filter(|hash| !core.orphaned_blocks_pool().contains_unknown_block(hash))
+				.collect();
+			assert!(!evicted_blocks.is_empty());
+			assert!(evicted_blocks.iter().all(|hash| core.chain().block_state(hash) == BlockState::Unknown));
+		}
+
+		// unknown blocks of flooding peer are removed when it disconnects
+		sync.on_disconnect(1);
+		{
+			let mut core = core.lock();
+			let pool = core.orphaned_blocks_pool();
+			assert_eq!(pool.unknown_blocks().len(), 1);
+			assert_eq!(pool.peer_unknown_blocks_size(1), 0);
+			assert!(pool.contains_unknown_block(&honest_block_hash));
+		}
+	}
+
 	#[test]
 	fn orphaned_transaction_is_verified_when_input_is_received() {
 		let input_tx = test_data::genesis().transactions[0].clone();
@@ -1986,6 +2474,61 @@ pub mod tests {
 		assert_eq!(core.lock().information().orphaned_transactions, 0);
 	}
 
+	#[test]
+	fn orphaned_transaction_is_accepted_with_package_from_peer() {
+		let input_tx = test_data::genesis().transactions[0].clone();
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).store(chain)	// t0
+			.set_input(&chain.at(0), 0).set_output(20).store(chain);							// t0 -> t1
+
+		let (executor, core, sync) = create_sync(None, None);
+		core.lock().peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		core.lock().peers.enable_package_relay(1);
+
+		// orphan is received => ask for its ancestors
+		sync.on_transaction(1, chain.at(1).into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+		assert_eq!(executor.take_tasks(), vec![Task::GetData(1, types::GetData::with_inventory(vec![
+			InventoryVector::ancestor_package_info(chain.at(1).hash()),
+		]))]);
+
+		// ancestors are received => ask for unknown ancestors
+		sync.on_ancpkginfo(1, types::AncPkgInfo::with_hashes(vec![chain.at(0).hash(), chain.at(1).hash()]));
+		assert_eq!(executor.take_tasks(), vec![Task::GetPkgTxns(1, types::GetPkgTxns::with_hashes(vec![chain.at(0).hash()]))]);
+
+		// package transactions are received => package is accepted
+		sync.on_pkgtxns(1, vec![chain.at(0).into()]);
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+		assert_eq!(core.lock().information().orphaned_transactions, 0);
+
+		// unrequested package is ignored
+		sync.on_pkgtxns(1, vec![chain.at(0).into()]);
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+	}
+
+	#[test]
+	fn peer_is_disconnected_when_requested_package_is_invalid() {
+		let input_tx = test_data::genesis().transactions[0].clone();
+		let chain = &mut test_data::ChainBuilder::new();
+		test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).store(chain)	// t0
+			.set_input(&chain.at(0), 0).set_output(20).store(chain);							// t0 -> t1
+
+		let mut verifier = DummyVerifier::default();
+		verifier.error_when_verifying(chain.at(0).hash(), "simulated");
+		let (_, core, sync) = create_sync(None, Some(verifier));
+		core.lock().peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		core.lock().peers.enable_package_relay(1);
+
+		sync.on_transaction(1, chain.at(1).into());
+		sync.on_ancpkginfo(1, types::AncPkgInfo::with_hashes(vec![chain.at(0).hash(), chain.at(1).hash()]));
+		assert!(core.lock().peers.enumerate().contains(&1));
+
+		// package is rejected => peer is disconnected
+		sync.on_pkgtxns(1, vec![chain.at(0).into()]);
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+		assert!(!core.lock().peers.enumerate().contains(&1));
+	}
+
 	#[test]
 	// https://github.com/ethcore/parity-bitcoin/issues/121
 	fn when_previous_block_verification_failed_fork_is_not_requested() {
@@ -2406,5 +2949,135 @@ pub mod tests {
 		sync.on_block(0, test_data::block_h2().into());
 		assert_eq!(data.lock().is_synchronizing, false);
 		assert_eq!(data.lock().best_blocks.len(), 3);
+		assert_eq!(data.lock().decanonized_blocks.len(), 0);
+
+		// invalidate block 2 => blocks 2 and 3 are decanonized, block 1 is the new best block
+		sync.invalidate_block(&test_data::block_h2().hash()).unwrap();
+		assert_eq!(data.lock().decanonized_blocks, vec![test_data::block_h2().hash(), test_data::block_h3().hash()]);
+		assert_eq!(data.lock().best_blocks.last(), Some(&test_data::block_h1().hash()));
+	}
+
+	#[test]
+	fn descendants_of_invalidated_block_are_ignored() {
+		let (_, core, sync) = create_sync(None, None);
+		for block in vec![test_data::block_h1(), test_data::block_h2(), test_data::block_h3()] {
+			sync.on_headers(0, vec![block.block_header.clone().into()]);
+			sync.on_block(0, block.into());
+		}
+		assert_eq!(core.lock().information().chain.stored, 4);
+
+		// blocks 2 and 3 are moved to the side chain
+		sync.invalidate_block(&test_data::block_h2().hash()).unwrap();
+		assert_eq!(core.lock().information().chain.stored, 2);
+
+		// child of block 3 descends from the invalidated block
+		let b4 = test_data::block_builder().header().parent(test_data::block_h3().hash()).build().build();
+		sync.on_headers(0, vec![b4.block_header.clone().into()]);
+		assert_eq!(core.lock().information().chain.scheduled, 0);
+		sync.on_block(0, b4.clone().into());
+		assert!(!core.lock().chain.storage().contains_block(b4.hash().into()));
+	}
+
+	#[test]
+	fn blocks_are_not_requested_until_headers_are_synchronized() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let blocks = test_data::build_n_empty_blocks_from_genesis(types::HEADERS_MAX_HEADERS_LEN as u32 + 1, 0);
+		let mut headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+		let last_header = headers.split_off(types::HEADERS_MAX_HEADERS_LEN);
+
+		// full headers message => only more headers are requested
+		sync.on_headers(1, headers);
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks.len(), 1);
+		match tasks[0] {
+			Task::GetHeaders(1, _) => (),
+			_ => panic!("unexpected task: {:?}", tasks[0]),
+		}
+		assert_eq!(core.lock().information().chain.scheduled, types::HEADERS_MAX_HEADERS_LEN as u32);
+		assert_eq!(core.lock().information().chain.requested, 0);
+
+		// last headers message => blocks are requested
+		sync.on_headers(1, last_header);
+		assert!(executor.take_tasks().iter().any(|t| match *t { Task::GetData(1, _) => true, _ => false }));
+		let chain_information = core.lock().information().chain;
+		assert!(chain_information.requested != 0);
+		assert_eq!(chain_information.scheduled + chain_information.requested, types::HEADERS_MAX_HEADERS_LEN as u32 + 1);
+	}
+
+	#[test]
+	fn headers_are_not_synchronized_while_other_peer_sends_full_headers() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let blocks = test_data::build_n_empty_blocks_from_genesis(types::HEADERS_MAX_HEADERS_LEN as u32 + 1, 0);
+		let mut headers: Vec<IndexedBlockHeader> = blocks.into_iter().map(|b| b.block_header.into()).collect();
+		let last_header = headers.split_off(types::HEADERS_MAX_HEADERS_LEN);
+
+		// peer#1 responds with full headers message, peer#2 responds with short message
+		sync.on_headers(1, headers);
+		sync.on_headers(2, last_header.clone());
+		// => we still wait for headers from peer#1
+		assert!(!executor.take_tasks().iter().any(|t| match *t { Task::GetData(_, _) => true, _ => false }));
+		assert_eq!(core.lock().information().chain.requested, 0);
+
+		// peer#1 has no more headers => blocks are requested
+		sync.on_headers(1, last_header);
+		assert!(executor.take_tasks().iter().any(|t| match *t { Task::GetData(_, _) => true, _ => false }));
+		assert!(core.lock().information().chain.requested != 0);
+	}
+
+	#[test]
+	fn stalling_peer_blocks_are_reassigned() {
+		let (_, core, sync) = create_sync(None, None);
+
+		// fill the whole download window
+		for peer_index in 1..9 {
+			core.lock().peers_tasks.useful_peer(peer_index);
+		}
+		let blocks = test_data::build_n_empty_blocks_from_genesis(BLOCK_DOWNLOAD_WINDOW + 100, 0);
+		sync.on_headers(1, blocks.iter().map(|b| b.block_header.clone().into()).collect());
+		assert_eq!(core.lock().information().chain.requested, BLOCK_DOWNLOAD_WINDOW);
+		let lowest_block = blocks[0].hash();
+		let stalling_peer = core.lock().peers_tasks.blocks_request_peer(&lowest_block).unwrap();
+
+		// new idle peer connects => stalling timer is started
+		core.lock().peers_tasks.useful_peer(9);
+		core.lock().execute_synchronization_tasks(None, None);
+		assert_eq!(core.lock().peers_tasks.blocks_request_peer(&lowest_block), Some(stalling_peer));
+		assert_eq!(core.lock().stalling_block.as_ref().map(|s| s.0.clone()), Some(lowest_block.clone()));
+
+		// timeout => stalling peer tasks are reassigned
+		core.lock().stalling_block.as_mut().unwrap().1 -= BLOCK_STALLING_TIMEOUT_S;
+		core.lock().execute_synchronization_tasks(None, None);
+		let new_peer = core.lock().peers_tasks.blocks_request_peer(&lowest_block).unwrap();
+		assert!(new_peer != stalling_peer);
+		assert!(core.lock().peers_tasks.get_blocks_tasks(stalling_peer).is_none());
+		assert_eq!(core.lock().information().peers_tasks.unuseful, 1);
+
+		// stalling timeout is increased after stall
+		assert_eq!(core.lock().block_stalling_timeout, 2_f64 * BLOCK_STALLING_TIMEOUT_S);
+
+		// and starts to decrease when requested blocks are received
+		sync.on_block(new_peer, blocks[0].clone().into());
+		let block_stalling_timeout = core.lock().block_stalling_timeout;
+		assert!(block_stalling_timeout < 2_f64 * BLOCK_STALLING_TIMEOUT_S && block_stalling_timeout >= BLOCK_STALLING_TIMEOUT_S);
+	}
+
+	#[test]
+	fn extra_outbound_connection_is_requested_when_tip_is_stale() {
+		let (_, core, _) = create_sync(None, None);
+		let connection = DummyOutboundSyncConnection::new();
+		core.lock().peers.insert(0, Services::default(), connection.clone());
+
+		core.lock().check_stale_tip();
+		assert_eq!(connection.messages.lock().get("extra_outbound"), None);
+
+		core.lock().last_tip_update_time -= STALE_TIP_TIMEOUT_S;
+		core.lock().check_stale_tip();
+		assert_eq!(connection.messages.lock().get("extra_outbound"), Some(&1));
+
+		// timer is reset after request
+		core.lock().check_stale_tip();
+		assert_eq!(connection.messages.lock().get("extra_outbound"), Some(&1));
 	}
 }
@@ -39,6 +39,18 @@ pub enum Task {
 	BlockTxn(PeerIndex, types::BlockTxn),
 	/// Send notfound
 	NotFound(PeerIndex, types::NotFound),
+	/// Send compact block filter
+	CFilter(PeerIndex, types::CFilter),
+	/// Send compact block filter headers
+	CFHeaders(PeerIndex, types::CFHeaders),
+	/// Send compact block filter checkpoints
+	CFCheckpt(PeerIndex, types::CFCheckpt),
+	/// Send ancestor package information
+	AncPkgInfo(PeerIndex, types::AncPkgInfo),
+	/// Request package transactions
+	GetPkgTxns(PeerIndex, types::GetPkgTxns),
+	/// Send package transactions
+	PkgTxns(PeerIndex, types::PkgTxns),
 	/// Send inventory
 	Inventory(PeerIndex, types::Inv),
 	/// Send headers
@@ -64,14 +76,14 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_ignore(&self, peer_index: PeerIndex, request_id: RequestId) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Ignoring request {} from peer#{}", request_id, peer_index);
+			trace!(target: "sync", peer = peer_index; "Ignoring request {} from peer#{}", request_id, peer_index);
 			connection.ignored(request_id);
 		}
 	}
 
 	fn execute_getdata(&self, peer_index: PeerIndex, getdata: types::GetData) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Querying {} unknown items from peer#{}", getdata.inventory.len(), peer_index);
+			trace!(target: "sync", peer = peer_index; "Querying {} unknown items from peer#{}", getdata.inventory.len(), peer_index);
 			connection.send_getdata(&getdata);
 		}
 	}
@@ -79,7 +91,7 @@ impl LocalSynchronizationTaskExecutor {
 	fn execute_getheaders(&self, peer_index: PeerIndex, getheaders: types::GetHeaders) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			if !getheaders.block_locator_hashes.is_empty() {
-				trace!(target: "sync", "Querying headers starting with {} unknown items from peer#{}", getheaders.block_locator_hashes[0].to_reversed_str(), peer_index);
+				trace!(target: "sync", peer = peer_index; "Querying headers starting with {} unknown items from peer#{}", getheaders.block_locator_hashes[0].to_reversed_str(), peer_index);
 			}
 			connection.send_getheaders(&getheaders);
 		}
@@ -87,7 +99,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_memorypool(&self, peer_index: PeerIndex) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Querying memory pool contents from peer#{}", peer_index);
+			trace!(target: "sync", peer = peer_index; "Querying memory pool contents from peer#{}", peer_index);
 			let mempool = types::MemPool;
 			connection.send_mempool(&mempool);
 		}
@@ -95,7 +107,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index, block:% = block.hash().reversed(); "Sending block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, block.hash().clone(), KnownHashType::Block);
 			let block = types::Block {
 				block: block.to_raw_block(),
@@ -106,7 +118,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_merkleblock(&self, peer_index: PeerIndex, hash: H256, block: types::MerkleBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending merkle block {} to peer#{}", hash.to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index, block:% = hash.reversed(); "Sending merkle block {} to peer#{}", hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, hash, KnownHashType::Block);
 			connection.send_merkleblock(&block);
 		}
@@ -114,7 +126,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_compact_block(&self, peer_index: PeerIndex, hash: H256, block: types::CompactBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending compact block {} to peer#{}", hash.to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index, block:% = hash.reversed(); "Sending compact block {} to peer#{}", hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, hash, KnownHashType::CompactBlock);
 			connection.send_compact_block(&block);
 		}
@@ -122,7 +134,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_witness_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending witness block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index, block:% = block.hash().reversed(); "Sending witness block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, block.hash().clone(), KnownHashType::Block);
 			let block = types::Block {
 				block: block.to_raw_block(),
@@ -133,7 +145,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index; "Sending transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
 			let transaction = types::Tx {
 				transaction: transaction.raw,
@@ -144,7 +156,7 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_witness_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending witness transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
+			trace!(target: "sync", peer = peer_index; "Sending witness transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
 			let transaction = types::Tx {
 				transaction: transaction.raw,
@@ -155,28 +167,73 @@ impl LocalSynchronizationTaskExecutor {
 
 	fn execute_block_txn(&self, peer_index: PeerIndex, blocktxn: types::BlockTxn) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending blocktxn with {} transactions to peer#{}", blocktxn.request.transactions.len(), peer_index);
+			trace!(target: "sync", peer = peer_index; "Sending blocktxn with {} transactions to peer#{}", blocktxn.request.transactions.len(), peer_index);
 			connection.send_block_txn(&blocktxn);
 		}
 	}
 
 	fn execute_notfound(&self, peer_index: PeerIndex, notfound: types::NotFound) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending notfound to peer#{} with {} items", peer_index, notfound.inventory.len());
+			trace!(target: "sync", peer = peer_index; "Sending notfound to peer#{} with {} items", peer_index, notfound.inventory.len());
 			connection.send_notfound(&notfound);
 		}
 	}
 
+	fn execute_cfilter(&self, peer_index: PeerIndex, cfilter: types::CFilter) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index, block:% = cfilter.block_hash.reversed(); "Sending cfilter for block {} to peer#{}", cfilter.block_hash.to_reversed_str(), peer_index);
+			connection.send_cfilter(&cfilter);
+		}
+	}
+
+	fn execute_cfheaders(&self, peer_index: PeerIndex, cfheaders: types::CFHeaders) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index; "Sending cfheaders to peer#{} with {} items", peer_index, cfheaders.filter_hashes.len());
+			connection.send_cfheaders(&cfheaders);
+		}
+	}
+
+	fn execute_cfcheckpt(&self, peer_index: PeerIndex, cfcheckpt: types::CFCheckpt) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index; "Sending cfcheckpt to peer#{} with {} items", peer_index, cfcheckpt.filter_headers.len());
+			connection.send_cfcheckpt(&cfcheckpt);
+		}
+	}
+
+	fn execute_ancpkginfo(&self, peer_index: PeerIndex, ancpkginfo: types::AncPkgInfo) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index; "Sending ancpkginfo to peer#{} with {} items", peer_index, ancpkginfo.hashes.len());
+			connection.send_ancpkginfo(&ancpkginfo);
+		}
+	}
+
+	fn execute_getpkgtxns(&self, peer_index: PeerIndex, getpkgtxns: types::GetPkgTxns) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index; "Querying {} package transactions from peer#{}", getpkgtxns.hashes.len(), peer_index);
+			connection.send_getpkgtxns(&getpkgtxns);
+		}
+	}
+
+	fn execute_pkgtxns(&self, peer_index: PeerIndex, pkgtxns: types::PkgTxns) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", peer = peer_index; "Sending pkgtxns with {} transactions to peer#{}", pkgtxns.transactions.len(), peer_index);
+			for transaction in &pkgtxns.transactions {
+				self.peers.hash_known_as(peer_index, transaction.hash(), KnownHashType::Transaction);
+			}
+			connection.send_pkgtxns(&pkgtxns);
+		}
+	}
+
 	fn execute_inventory(&self, peer_index: PeerIndex, inventory: types::Inv) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending inventory to peer#{} with {} items", peer_index, inventory.inventory.len());
+			trace!(target: "sync", peer = peer_index; "Sending inventory to peer#{} with {} items", peer_index, inventory.inventory.len());
 			connection.send_inventory(&inventory);
 		}
 	}
 
 	fn execute_headers(&self, peer_index: PeerIndex, headers: types::Headers, request_id: Option<RequestId>) {
 		if let Some(connection) = self.peers.connection(peer_index) {
-			trace!(target: "sync", "Sending headers to peer#{} with {} items", peer_index, headers.headers.len());
+			trace!(target: "sync", peer = peer_index; "Sending headers to peer#{} with {} items", peer_index, headers.headers.len());
 			match request_id {
 				Some(request_id) => connection.respond_headers(&headers, request_id),
 				None => connection.send_headers(&headers),
@@ -232,6 +289,12 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::WitnessTransaction(peer_index, transaction) => self.execute_witness_transaction(peer_index, transaction),
 			Task::BlockTxn(peer_index, blocktxn) => self.execute_block_txn(peer_index, blocktxn),
 			Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
+			Task::CFilter(peer_index, cfilter) => self.execute_cfilter(peer_index, cfilter),
+			Task::CFHeaders(peer_index, cfheaders) => self.execute_cfheaders(peer_index, cfheaders),
+			Task::CFCheckpt(peer_index, cfcheckpt) => self.execute_cfcheckpt(peer_index, cfcheckpt),
+			Task::AncPkgInfo(peer_index, ancpkginfo) => self.execute_ancpkginfo(peer_index, ancpkginfo),
+			Task::GetPkgTxns(peer_index, getpkgtxns) => self.execute_getpkgtxns(peer_index, getpkgtxns),
+			Task::PkgTxns(peer_index, pkgtxns) => self.execute_pkgtxns(peer_index, pkgtxns),
 			Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
 			Task::Headers(peer_index, headers, request_id) => self.execute_headers(peer_index, headers, request_id),
 			Task::RelayNewBlock(block) => self.execute_relay_block(block),
@@ -3,6 +3,7 @@ use std::sync::{Arc, Weak};
 use std::thread;
 use std::time::Duration;
 use parking_lot::{Mutex, Condvar};
+use rand::{seq, thread_rng};
 use time::precise_time_s;
 use primitives::hash::H256;
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
@@ -96,6 +97,9 @@ impl ManagementWorker {
 				manage_synchronization_peers_headers(&peers_config, core.peers(), core.peers_tasks());
 				manage_orphaned_transactions(&orphan_config, core.orphaned_transactions_pool());
 			} else {
+				// ask for new peers if the best block is not updated for a long time
+				core.check_stale_tip();
+
 				// only remove orphaned blocks when not in synchronization state
 				if let Some(orphans_to_remove) = manage_unknown_orphaned_blocks(&unknown_config, core.orphaned_blocks_pool()) {
 					for orphan_to_remove in orphans_to_remove {
@@ -194,7 +198,7 @@ pub fn manage_synchronization_peers_blocks(config: &ManagePeersConfig, peers: Pe
 		}
 
 		// decrease score && move to the idle queue
-		warn!(target: "sync", "Failed to get requested block from peer#{} in {:.2} seconds.", worst_peer_index, time_diff);
+		warn!(target: "sync", peer = worst_peer_index; "Failed to get requested block from peer#{} in {:.2} seconds.", worst_peer_index, time_diff);
 		let failed_blocks = peers_tasks.reset_blocks_tasks(worst_peer_index);
 
 		// mark blocks as failed
@@ -204,7 +208,7 @@ pub fn manage_synchronization_peers_blocks(config: &ManagePeersConfig, peers: Pe
 
 		// if peer failed many times => forget it
 		if peers_tasks.on_peer_block_failure(worst_peer_index) {
-			warn!(target: "sync", "Too many failures for peer#{}. Excluding from synchronization.", worst_peer_index);
+			warn!(target: "sync", peer = worst_peer_index; "Too many failures for peer#{}. Excluding from synchronization.", worst_peer_index);
 			peers_tasks.unuseful_peer(worst_peer_index);
 			peers.misbehaving(worst_peer_index, &format!("Too many failures."));
 		}
@@ -234,7 +238,7 @@ pub fn manage_synchronization_peers_headers(config: &ManagePeersConfig, peers: P
 
 		// if peer failed many times => forget it
 		if peers_tasks.on_peer_headers_failure(worst_peer_index) {
-			warn!(target: "sync", "Too many header failures for peer#{}. Excluding from synchronization.", worst_peer_index);
+			warn!(target: "sync", peer = worst_peer_index; "Too many header failures for peer#{}. Excluding from synchronization.", worst_peer_index);
 			peers.misbehaving(worst_peer_index, &format!("Too many header failures."));
 		}
 	}
@@ -244,25 +248,30 @@ pub fn manage_synchronization_peers_headers(config: &ManagePeersConfig, peers: P
 pub fn manage_unknown_orphaned_blocks(config: &ManageUnknownBlocksConfig, orphaned_blocks_pool: &mut OrphanBlocksPool) -> Option<Vec<H256>> {
 	let unknown_to_remove = {
 		let unknown_blocks = orphaned_blocks_pool.unknown_blocks();
-		let mut unknown_to_remove: HashSet<H256> = HashSet::new();
-		let mut remove_num = if unknown_blocks.len() > config.max_number { unknown_blocks.len() - config.max_number } else { 0 };
 		let now = precise_time_s();
-		for (hash, time) in unknown_blocks {
-			// remove oldest blocks if there are more unknown blocks that we can hold in memory
-			if remove_num > 0 {
-				unknown_to_remove.insert(hash.clone());
-				remove_num -= 1;
-				continue;
-			}
 
-			// check if block is unknown for too long
-			let time_diff = now - time;
+		// remove blocks that are unknown for too long
+		let mut unknown_to_remove: HashSet<H256> = HashSet::new();
+		for (hash, unknown_block) in unknown_blocks {
+			let time_diff = now - unknown_block.insertion_time;
 			if time_diff <= config.removal_time_ms as f64 / 1000f64 {
 				break;
 			}
 			unknown_to_remove.insert(hash.clone());
 		}
 
+		// remove random blocks if there are more unknown blocks that we can hold in memory
+		// (random, so that peer can not push out blocks of other peers by sending a lot of blocks)
+		let remaining_len = unknown_blocks.len() - unknown_to_remove.len();
+		if remaining_len > config.max_number {
+			let remaining_blocks = unknown_blocks.keys().skip(unknown_to_remove.len()).cloned();
+			let random_blocks = match seq::sample_iter(&mut thread_rng(), remaining_blocks, remaining_len - config.max_number) {
+				Ok(random_blocks) => random_blocks,
+				Err(random_blocks) => random_blocks,
+			};
+			unknown_to_remove.extend(random_blocks);
+		}
+
 		unknown_to_remove
 	};
 
@@ -276,18 +285,11 @@ pub fn manage_unknown_orphaned_blocks(config: &ManageUnknownBlocksConfig, orphan
 pub fn manage_orphaned_transactions(config: &ManageOrphanTransactionsConfig, orphaned_transactions_pool: &mut OrphanTransactionsPool) -> Option<Vec<H256>> {
 	let orphans_to_remove = {
 		let unknown_transactions = orphaned_transactions_pool.transactions();
-		let mut orphans_to_remove: Vec<H256> = Vec::new();
-		let mut remove_num = if unknown_transactions.len() > config.max_number { unknown_transactions.len() - config.max_number } else { 0 };
 		let now = precise_time_s();
-		for (hash, orphan_tx) in unknown_transactions {
-			// remove oldest transactions if there are more unknown transactions that we can hold in memory
-			if remove_num > 0 {
-				orphans_to_remove.push(hash.clone());
-				remove_num -= 1;
-				continue;
-			}
 
-			// check if transaction is unknown for too long
+		// remove transactions that are unknown for too long
+		let mut orphans_to_remove: Vec<H256> = Vec::new();
+		for (hash, orphan_tx) in unknown_transactions {
 			let time_diff = now - orphan_tx.insertion_time;
 			if time_diff <= config.removal_time_ms as f64 / 1000f64 {
 				break;
@@ -295,6 +297,18 @@ pub fn manage_orphaned_transactions(config: &ManageOrphanTransactionsConfig, orp
 			orphans_to_remove.push(hash.clone());
 		}
 
+		// remove random transactions if there are more unknown transactions that we can hold in memory
+		// (random, so that peer can not push out transactions of other peers by sending a lot of orphans)
+		let remaining_len = unknown_transactions.len() - orphans_to_remove.len();
+		if remaining_len > config.max_number {
+			let remaining_transactions = unknown_transactions.keys().skip(orphans_to_remove.len()).cloned();
+			let random_transactions = match seq::sample_iter(&mut thread_rng(), remaining_transactions, remaining_len - config.max_number) {
+				Ok(random_transactions) => random_transactions,
+				Err(random_transactions) => random_transactions,
+			};
+			orphans_to_remove.extend(random_transactions);
+		}
+
 		orphans_to_remove
 	};
 
@@ -355,7 +369,7 @@ mod tests {
 		let config = ManageUnknownBlocksConfig { removal_time_ms: 1000, max_number: 100 };
 		let mut pool = OrphanBlocksPool::new();
 		let block = test_data::genesis();
-		pool.insert_unknown_block(block.into());
+		pool.insert_unknown_block(block.into(), 0);
 		assert_eq!(manage_unknown_orphaned_blocks(&config, &mut pool), None);
 		assert_eq!(pool.len(), 1);
 	}
@@ -368,7 +382,7 @@ mod tests {
 		let mut pool = OrphanBlocksPool::new();
 		let block = test_data::genesis();
 		let block_hash = block.hash();
-		pool.insert_unknown_block(block.into());
+		pool.insert_unknown_block(block.into(), 0);
 		sleep(Duration::from_millis(1));
 
 		assert_eq!(manage_unknown_orphaned_blocks(&config, &mut pool), Some(vec![block_hash]));
@@ -382,9 +396,13 @@ mod tests {
 		let block1 = test_data::genesis();
 		let block1_hash = block1.hash();
 		let block2 = test_data::block_h2();
-		pool.insert_unknown_block(block1.into());
-		pool.insert_unknown_block(block2.into());
-		assert_eq!(manage_unknown_orphaned_blocks(&config, &mut pool), Some(vec![block1_hash]));
+		let block2_hash = block2.hash();
+		pool.insert_unknown_block(block1.into(), 0);
+		pool.insert_unknown_block(block2.into(), 0);
+		let removed = manage_unknown_orphaned_blocks(&config, &mut pool).unwrap();
+		assert_eq!(removed.len(), 1);
+		assert!(removed[0] == block1_hash || removed[0] == block2_hash);
+		assert!(!pool.contains_unknown_block(&removed[0]));
 		assert_eq!(pool.len(), 1);
 	}
 
@@ -394,7 +412,7 @@ mod tests {
 		let mut pool = OrphanTransactionsPool::new();
 		let transaction = test_data::block_h170().transactions[1].clone();
 		let unknown_inputs: HashSet<H256> = transaction.inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
-		pool.insert(transaction.into(), unknown_inputs);
+		pool.insert(transaction.into(), unknown_inputs, None);
 		assert_eq!(manage_orphaned_transactions(&config, &mut pool), None);
 		assert_eq!(pool.len(), 1);
 	}
@@ -408,7 +426,7 @@ mod tests {
 		let transaction = test_data::block_h170().transactions[1].clone();
 		let unknown_inputs: HashSet<H256> = transaction.inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
 		let transaction_hash = transaction.hash();
-		pool.insert(transaction.into(), unknown_inputs);
+		pool.insert(transaction.into(), unknown_inputs, None);
 		sleep(Duration::from_millis(1));
 
 		assert_eq!(manage_orphaned_transactions(&config, &mut pool), Some(vec![transaction_hash]));
@@ -424,9 +442,13 @@ mod tests {
 		let transaction1_hash = transaction1.hash();
 		let transaction2 = test_data::block_h182().transactions[1].clone();
 		let unknown_inputs2: HashSet<H256> = transaction2.inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
-		pool.insert(transaction1.into(), unknown_inputs1);
-		pool.insert(transaction2.into(), unknown_inputs2);
-		assert_eq!(manage_orphaned_transactions(&config, &mut pool), Some(vec![transaction1_hash]));
+		let transaction2_hash = transaction2.hash();
+		pool.insert(transaction1.into(), unknown_inputs1, None);
+		pool.insert(transaction2.into(), unknown_inputs2, None);
+		let removed = manage_orphaned_transactions(&config, &mut pool).unwrap();
+		assert_eq!(removed.len(), 1);
+		assert!(removed[0] == transaction1_hash || removed[0] == transaction2_hash);
+		assert!(!pool.contains(&removed[0]));
 		assert_eq!(pool.len(), 1);
 	}
 }
@@ -92,6 +92,10 @@ pub trait PeersOptions {
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType);
 	/// Set up new transaction announcement type for the connection
 	fn set_transaction_announcement_type(&self, peer_index: PeerIndex, announcement_type: TransactionAnnouncementType);
+	/// Enable package relay for the connection
+	fn enable_package_relay(&self, peer_index: PeerIndex);
+	/// Is package relay negotiated with the peer?
+	fn is_package_relay_enabled(&self, peer_index: PeerIndex) -> bool;
 }
 
 /// Single connected peer data
@@ -106,6 +110,8 @@ struct Peer {
 	pub block_announcement_type: BlockAnnouncementType,
 	/// Transaction announcement type
 	pub transaction_announcement_type: TransactionAnnouncementType,
+	/// Is package relay negotiated?
+	pub package_relay: bool,
 }
 
 /// Default implementation of connectd peers container
@@ -124,6 +130,7 @@ impl Peer {
 			filter: ConnectionFilter::default(),
 			block_announcement_type: BlockAnnouncementType::SendInventory,
 			transaction_announcement_type: TransactionAnnouncementType::SendInventory,
+			package_relay: false,
 		}
 	}
 }
@@ -137,7 +144,7 @@ impl Peers for PeersImpl {
 			let peer = peers.remove(&peer_index).expect("iterating peers keys; qed"); 
 			let expected_services: u64 = services.into();
 			let actual_services: u64 = peer.services.into();
-			warn!(target: "sync", "Disconnecting from peer#{} because of insufficient services. Expected {:x}, actual: {:x}", peer_index, expected_services, actual_services);
+			warn!(target: "sync", peer = peer_index; "Disconnecting from peer#{} because of insufficient services. Expected {:x}, actual: {:x}", peer_index, expected_services, actual_services);
 			peer.connection.close();
 		}
 	}
@@ -153,26 +160,26 @@ impl PeersContainer for PeersImpl {
 	}
 
 	fn insert(&self, peer_index: PeerIndex, services: Services, connection: OutboundSyncConnectionRef) {
-		trace!(target: "sync", "Connected to peer#{}", peer_index);
+		trace!(target: "sync", peer = peer_index; "Connected to peer#{}", peer_index);
 		assert!(self.peers.write().insert(peer_index, Peer::new(services, connection)).is_none());
 	}
 
 	fn remove(&self, peer_index: PeerIndex) {
 		if self.peers.write().remove(&peer_index).is_some() {
-			trace!(target: "sync", "Disconnected from peer#{}", peer_index);
+			trace!(target: "sync", peer = peer_index; "Disconnected from peer#{}", peer_index);
 		}
 	}
 
 	fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
-			warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
+			warn!(target: "sync", peer = peer_index; "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
 			peer.connection.close();
 		}
 	}
 
 	fn dos(&self, peer_index: PeerIndex, reason: &str) {
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
-			warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
+			warn!(target: "sync", peer = peer_index; "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
 			peer.connection.close();
 		}
 	}
@@ -265,4 +272,17 @@ impl PeersOptions for PeersImpl {
 			peer.transaction_announcement_type = announcement_type;
 		}
 	}
+
+	fn enable_package_relay(&self, peer_index: PeerIndex) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			peer.package_relay = true;
+		}
+	}
+
+	fn is_package_relay_enabled(&self, peer_index: PeerIndex) -> bool {
+		self.peers.read()
+			.get(&peer_index)
+			.map(|peer| peer.package_relay)
+			.unwrap_or_default()
+	}
 }
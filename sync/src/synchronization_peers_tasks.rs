@@ -149,6 +149,13 @@ impl PeersTasks {
 			.map(|br| &br.blocks)
 	}
 
+	/// Get peer, which has been asked for the block
+	pub fn blocks_request_peer(&self, hash: &H256) -> Option<PeerIndex> {
+		self.blocks_requests.iter()
+			.find(|&(_, br)| br.blocks.contains(hash))
+			.map(|(peer_index, _)| *peer_index)
+	}
+
 	/// Get peer statistics
 	pub fn get_peer_stats(&self, peer_index: PeerIndex) -> Option<&PeerStats> {
 		self.stats.get(&peer_index)
@@ -625,4 +632,15 @@ mod tests {
 		assert_eq!(peers_for_blocks[0], 2);
 		assert_eq!(peers_for_blocks[1], 1);
 	}
+
+	#[test]
+	fn peer_blocks_request_peer() {
+		let mut peers = PeersTasks::default();
+		peers.on_blocks_requested(1, &vec![H256::from(1), H256::from(2)]);
+		peers.on_blocks_requested(2, &vec![H256::from(3)]);
+		assert_eq!(peers.blocks_request_peer(&H256::from(2)), Some(1));
+		assert_eq!(peers.blocks_request_peer(&H256::from(3)), Some(2));
+		peers.on_block_received(2, &H256::from(3));
+		assert_eq!(peers.blocks_request_peer(&H256::from(3)), None);
+	}
 }
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use parking_lot::{Mutex, Condvar};
-use chain::IndexedTransaction;
+use chain::{IndexedTransaction, BASIC_FILTER_TYPE};
 use message::{types, common};
 use primitives::hash::H256;
 use synchronization_executor::{Task, TaskExecutor};
@@ -26,6 +26,14 @@ pub enum ServerTask {
 	Mempool(PeerIndex),
 	/// Serve 'getblocktxn' request
 	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Serve 'getcfilters' request
+	GetCFilters(PeerIndex, types::GetCFilters),
+	/// Serve 'getcfheaders' request
+	GetCFHeaders(PeerIndex, types::GetCFHeaders),
+	/// Serve 'getcfcheckpt' request
+	GetCFCheckpt(PeerIndex, types::GetCFCheckpt),
+	/// Serve 'getpkgtxns' request
+	GetPkgTxns(PeerIndex, types::GetPkgTxns),
 }
 
 /// Synchronization server
@@ -81,7 +89,11 @@ impl ServerTask {
 				| ServerTask::GetBlocks(peer_index, _)
 				| ServerTask::GetHeaders(peer_index, _, _)
 				| ServerTask::Mempool(peer_index)
-				| ServerTask::GetBlockTxn(peer_index, _) => peer_index,
+				| ServerTask::GetBlockTxn(peer_index, _)
+				| ServerTask::GetCFilters(peer_index, _)
+				| ServerTask::GetCFHeaders(peer_index, _)
+				| ServerTask::GetCFCheckpt(peer_index, _)
+				| ServerTask::GetPkgTxns(peer_index, _) => peer_index,
 		}
 	}
 }
@@ -235,6 +247,10 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			ServerTask::GetHeaders(peer_index, message, request_id) => self.serve_get_headers(peer_index, message, request_id),
 			ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
 			ServerTask::GetBlockTxn(peer_index, message) => self.serve_get_block_txn(peer_index, message),
+			ServerTask::GetCFilters(peer_index, message) => self.serve_get_cfilters(peer_index, message),
+			ServerTask::GetCFHeaders(peer_index, message) => self.serve_get_cfheaders(peer_index, message),
+			ServerTask::GetCFCheckpt(peer_index, message) => self.serve_get_cfcheckpt(peer_index, message),
+			ServerTask::GetPkgTxns(peer_index, message) => self.serve_get_pkg_txns(peer_index, message),
 		}
 
 		None
@@ -254,7 +270,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		let next_item = match message.inventory.pop() {
 			None => {
 				if !notfound.inventory.is_empty() {
-					trace!(target: "sync", "'getdata' from peer#{} container contains {} unknown items", peer_index, notfound.inventory.len());
+					trace!(target: "sync", peer = peer_index; "'getdata' from peer#{} container contains {} unknown items", peer_index, notfound.inventory.len());
 					self.executor.execute(Task::NotFound(peer_index, notfound));
 				}
 				return None;
@@ -266,7 +282,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			common::InventoryType::MessageTx => {
 				// only transaction from memory pool can be requested
 				if let Some(transaction) = self.memory_pool.read().read_by_hash(&next_item.hash) {
-					trace!(target: "sync", "'getblocks' response to peer#{} is ready with tx {}", peer_index, next_item.hash.to_reversed_str());
+					trace!(target: "sync", peer = peer_index; "'getblocks' response to peer#{} is ready with tx {}", peer_index, next_item.hash.to_reversed_str());
 					let transaction = IndexedTransaction::new(next_item.hash, transaction.clone());
 					self.executor.execute(Task::Transaction(peer_index, transaction));
 				} else {
@@ -276,7 +292,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			common::InventoryType::MessageWitnessTx => {
 				// only transaction from memory pool can be requested
 				if let Some(transaction) = self.memory_pool.read().read_by_hash(&next_item.hash) {
-					trace!(target: "sync", "'getblocks' response to peer#{} is ready with witness-tx {}", peer_index, next_item.hash.to_reversed_str());
+					trace!(target: "sync", peer = peer_index; "'getblocks' response to peer#{} is ready with witness-tx {}", peer_index, next_item.hash.to_reversed_str());
 					let transaction = IndexedTransaction::new(next_item.hash, transaction.clone());
 					self.executor.execute(Task::WitnessTransaction(peer_index, transaction));
 				} else {
@@ -285,7 +301,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			},
 			common::InventoryType::MessageBlock => {
 				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
-					trace!(target: "sync", "'getblocks' response to peer#{} is ready with block {}", peer_index, next_item.hash.to_reversed_str());
+					trace!(target: "sync", peer = peer_index, block:% = next_item.hash.reversed(); "'getblocks' response to peer#{} is ready with block {}", peer_index, next_item.hash.to_reversed_str());
 					self.executor.execute(Task::Block(peer_index, block));
 				} else {
 					notfound.inventory.push(next_item);
@@ -296,12 +312,12 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 					let message_artefacts = self.peers.build_merkle_block(peer_index, &block);
 					if let Some(message_artefacts) = message_artefacts {
 						// send merkleblock first
-						trace!(target: "sync", "'getblocks' response to peer#{} is ready with merkleblock {}", peer_index, next_item.hash.to_reversed_str());
+						trace!(target: "sync", peer = peer_index, block:% = next_item.hash.reversed(); "'getblocks' response to peer#{} is ready with merkleblock {}", peer_index, next_item.hash.to_reversed_str());
 						self.executor.execute(Task::MerkleBlock(peer_index, *block.hash(), message_artefacts.merkleblock));
 
 						// also send all matched transactions
 						for matched_transaction in message_artefacts.matching_transactions {
-							trace!(target: "sync", "'getblocks' response to peer#{} is ready with merkletx {}", peer_index, matched_transaction.hash.to_reversed_str());
+							trace!(target: "sync", peer = peer_index; "'getblocks' response to peer#{} is ready with merkletx {}", peer_index, matched_transaction.hash.to_reversed_str());
 							self.executor.execute(Task::Transaction(peer_index, matched_transaction));
 						}
 					} else {
@@ -315,7 +331,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
 					let message = self.peers.build_compact_block(peer_index, &block);
 					if let Some(message) = message {
-						trace!(target: "sync", "'getblocks' response to peer#{} is ready with compactblock {}", peer_index, next_item.hash.to_reversed_str());
+						trace!(target: "sync", peer = peer_index, block:% = next_item.hash.reversed(); "'getblocks' response to peer#{} is ready with compactblock {}", peer_index, next_item.hash.to_reversed_str());
 						self.executor.execute(Task::CompactBlock(peer_index, *block.hash(), message));
 					}
 				} else {
@@ -324,12 +340,24 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			},
 			common::InventoryType::MessageWitnessBlock => {
 				if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
-					trace!(target: "sync", "'getblocks' response to peer#{} is ready with witness-block {}", peer_index, next_item.hash.to_reversed_str());
+					trace!(target: "sync", peer = peer_index, block:% = next_item.hash.reversed(); "'getblocks' response to peer#{} is ready with witness-block {}", peer_index, next_item.hash.to_reversed_str());
 					self.executor.execute(Task::WitnessBlock(peer_index, block.into()));
 				} else {
 					notfound.inventory.push(next_item);
 				}
 			},
+			common::InventoryType::MessageAncestorPackageInfo => {
+				// only ancestors of memory pool transaction can be requested
+				let ancestors = self.memory_pool.read().get_ancestors_ids(&next_item.hash);
+				match ancestors {
+					Some(mut hashes) if hashes.len() < types::ANCPKGINFO_MAX_HASHES_LEN => {
+						trace!(target: "sync", peer = peer_index; "'getdata' response to peer#{} is ready with {} ancestors of tx {}", peer_index, hashes.len(), next_item.hash.to_reversed_str());
+						hashes.push(next_item.hash);
+						self.executor.execute(Task::AncPkgInfo(peer_index, types::AncPkgInfo::with_hashes(hashes)));
+					},
+					_ => notfound.inventory.push(next_item),
+				}
+			},
 			common::InventoryType::Error | common::InventoryType::MessageWitnessFilteredBlock => (),
 		}
 
@@ -347,10 +375,10 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 				.collect();
 			// empty inventory messages are invalid according to regtests, while empty headers messages are valid
 			if !inventory.is_empty() {
-				trace!(target: "sync", "'getblocks' response to peer#{} is ready with {} hashes", peer_index, inventory.len());
+				trace!(target: "sync", peer = peer_index; "'getblocks' response to peer#{} is ready with {} hashes", peer_index, inventory.len());
 				self.executor.execute(Task::Inventory(peer_index, types::Inv::with_inventory(inventory)));
 			} else {
-				trace!(target: "sync", "'getblocks' request from peer#{} is ignored as there are no new blocks for peer", peer_index);
+				trace!(target: "sync", peer = peer_index; "'getblocks' request from peer#{} is ignored as there are no new blocks for peer", peer_index);
 			}
 		} else {
 			self.peers.misbehaving(peer_index, "Got 'getblocks' message without known blocks");
@@ -371,7 +399,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 				.map(|h| h.raw)
 				.collect();
 			// empty inventory messages are invalid according to regtests, while empty headers messages are valid
-			trace!(target: "sync", "'getheaders' response to peer#{} is ready with {} headers", peer_index, headers.len());
+			trace!(target: "sync", peer = peer_index; "'getheaders' response to peer#{} is ready with {} headers", peer_index, headers.len());
 			self.executor.execute(Task::Headers(peer_index, types::Headers::with_headers(headers), Some(request_id)));
 		} else {
 			self.peers.misbehaving(peer_index, "Got 'headers' message without known blocks");
@@ -387,10 +415,33 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			.collect();
 		// empty inventory messages are invalid according to regtests, while empty headers messages are valid
 		if !inventory.is_empty() {
-			trace!(target: "sync", "'mempool' response to peer#{} is ready with {} transactions", peer_index, inventory.len());
+			trace!(target: "sync", peer = peer_index; "'mempool' response to peer#{} is ready with {} transactions", peer_index, inventory.len());
 			self.executor.execute(Task::Inventory(peer_index, types::Inv::with_inventory(inventory)));
 		} else {
-			trace!(target: "sync", "'mempool' request from peer#{} is ignored as pool is empty", peer_index);
+			trace!(target: "sync", peer = peer_index; "'mempool' request from peer#{} is ignored as pool is empty", peer_index);
+		}
+	}
+
+	fn serve_get_pkg_txns(&self, peer_index: PeerIndex, message: types::GetPkgTxns) {
+		// only transactions from memory pool can be requested
+		// => respond with notfound if any of transactions is missing
+		let memory_pool = self.memory_pool.read();
+		let transactions: Option<Vec<_>> = message.hashes.iter()
+			.map(|hash| memory_pool.read_by_hash(hash).cloned())
+			.collect();
+		match transactions {
+			Some(transactions) => {
+				trace!(target: "sync", peer = peer_index; "'getpkgtxns' response to peer#{} is ready with {} transactions", peer_index, transactions.len());
+				self.executor.execute(Task::PkgTxns(peer_index, types::PkgTxns::with_transactions(transactions)));
+			},
+			None => {
+				trace!(target: "sync", peer = peer_index; "'getpkgtxns' request from peer#{} contains unknown transactions", peer_index);
+				let inventory = message.hashes.into_iter()
+					.filter(|hash| !memory_pool.contains(hash))
+					.map(common::InventoryVector::tx)
+					.collect();
+				self.executor.execute(Task::NotFound(peer_index, types::NotFound::with_inventory(inventory)));
+			},
 		}
 	}
 
@@ -432,12 +483,12 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 				// => this is either some db error, or db has been pruned
 				// => we can not skip transactions, according to protocol description
 				// => ignore
-				warn!(target: "sync", "'getblocktxn' request from peer#{} is ignored as we have failed to find transaction {} in storage", peer_index, block_transactions[transaction_index].to_reversed_str());
+				warn!(target: "sync", peer = peer_index; "'getblocktxn' request from peer#{} is ignored as we have failed to find transaction {} in storage", peer_index, block_transactions[transaction_index].to_reversed_str());
 				return;
 			}
 		}
 
-		trace!(target: "sync", "'getblocktxn' response to peer#{} is ready with {} transactions", peer_index, transactions.len());
+		trace!(target: "sync", peer = peer_index; "'getblocktxn' response to peer#{} is ready with {} transactions", peer_index, transactions.len());
 		self.executor.execute(Task::BlockTxn(peer_index, types::BlockTxn {
 			request: common::BlockTransactions {
 				blockhash: message.request.blockhash,
@@ -446,6 +497,155 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		}));
 	}
 
+	fn serve_get_cfilters(&self, peer_index: PeerIndex, message: types::GetCFilters) {
+		let stop_height = match self.locate_filters_range(peer_index, "getcfilters", message.filter_type, message.start_height, &message.stop_hash, types::GETCFILTERS_MAX_RESPONSE_FILTERS) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let mut filters = Vec::with_capacity((stop_height - message.start_height + 1) as usize);
+		for block_height in message.start_height..stop_height + 1 {
+			match self.storage.block_hash(block_height).and_then(|block_hash| self.storage.block_filter(&block_hash)) {
+				Some(filter) => filters.push(filter),
+				None => {
+					// filters index is disabled or has been reorganized while we were serving request
+					trace!(target: "sync", peer = peer_index; "'getcfilters' request from peer#{} is ignored as filter of block #{} is unknown", peer_index, block_height);
+					return;
+				},
+			}
+		}
+
+		trace!(target: "sync", peer = peer_index; "'getcfilters' response to peer#{} is ready with {} filters", peer_index, filters.len());
+		for filter in filters {
+			self.executor.execute(Task::CFilter(peer_index, types::CFilter {
+				filter_type: message.filter_type,
+				block_hash: filter.block_hash,
+				filter: filter.filter,
+			}));
+		}
+	}
+
+	fn serve_get_cfheaders(&self, peer_index: PeerIndex, message: types::GetCFHeaders) {
+		let stop_height = match self.locate_filters_range(peer_index, "getcfheaders", message.filter_type, message.start_height, &message.stop_hash, types::GETCFHEADERS_MAX_RESPONSE_HEADERS) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let previous_filter_header = if message.start_height == 0 {
+			Some(H256::default())
+		} else {
+			self.storage.block_hash(message.start_height - 1)
+				.and_then(|block_hash| self.storage.block_filter_header(&block_hash))
+		};
+		let previous_filter_header = match previous_filter_header {
+			Some(previous_filter_header) => previous_filter_header,
+			None => {
+				trace!(target: "sync", peer = peer_index; "'getcfheaders' request from peer#{} is ignored as filter header of block #{} is unknown", peer_index, message.start_height - 1);
+				return;
+			},
+		};
+
+		let mut filter_hashes = Vec::with_capacity((stop_height - message.start_height + 1) as usize);
+		for block_height in message.start_height..stop_height + 1 {
+			match self.storage.block_hash(block_height).and_then(|block_hash| self.storage.block_filter(&block_hash)) {
+				Some(filter) => filter_hashes.push(filter.hash()),
+				None => {
+					trace!(target: "sync", peer = peer_index; "'getcfheaders' request from peer#{} is ignored as filter of block #{} is unknown", peer_index, block_height);
+					return;
+				},
+			}
+		}
+
+		trace!(target: "sync", peer = peer_index; "'getcfheaders' response to peer#{} is ready with {} hashes", peer_index, filter_hashes.len());
+		self.executor.execute(Task::CFHeaders(peer_index, types::CFHeaders {
+			filter_type: message.filter_type,
+			stop_hash: message.stop_hash,
+			previous_filter_header: previous_filter_header,
+			filter_hashes: filter_hashes,
+		}));
+	}
+
+	fn serve_get_cfcheckpt(&self, peer_index: PeerIndex, message: types::GetCFCheckpt) {
+		if !self.check_filters_request(peer_index, "getcfcheckpt", message.filter_type) {
+			return;
+		}
+
+		let stop_height = match self.locate_filters_stop_block(peer_index, "getcfcheckpt", &message.stop_hash) {
+			Some(stop_height) => stop_height,
+			None => return,
+		};
+
+		let mut filter_headers = Vec::with_capacity((stop_height / types::CFCHECKPT_INTERVAL) as usize);
+		let mut block_height = types::CFCHECKPT_INTERVAL;
+		while block_height <= stop_height {
+			match self.storage.block_hash(block_height).and_then(|block_hash| self.storage.block_filter_header(&block_hash)) {
+				Some(filter_header) => filter_headers.push(filter_header),
+				None => {
+					trace!(target: "sync", peer = peer_index; "'getcfcheckpt' request from peer#{} is ignored as filter header of block #{} is unknown", peer_index, block_height);
+					return;
+				},
+			}
+			block_height += types::CFCHECKPT_INTERVAL;
+		}
+
+		trace!(target: "sync", peer = peer_index; "'getcfcheckpt' response to peer#{} is ready with {} headers", peer_index, filter_headers.len());
+		self.executor.execute(Task::CFCheckpt(peer_index, types::CFCheckpt {
+			filter_type: message.filter_type,
+			stop_hash: message.stop_hash,
+			filter_headers: filter_headers,
+		}));
+	}
+
+	/// Checks that filters are served and filter type is supported. Disconnects peer otherwise.
+	fn check_filters_request(&self, peer_index: PeerIndex, command: &str, filter_type: u8) -> bool {
+		if !self.storage.is_block_filter_index_enabled() {
+			self.peers.misbehaving(peer_index, &format!("Got '{}' message, but block filter index is disabled", command));
+			return false;
+		}
+
+		if filter_type != BASIC_FILTER_TYPE {
+			self.peers.misbehaving(peer_index, &format!("Got '{}' message with unsupported filter type {}", command, filter_type));
+			return false;
+		}
+
+		true
+	}
+
+	/// Returns height of the filters request stop block, if it is in the best chain.
+	fn locate_filters_stop_block(&self, peer_index: PeerIndex, command: &str, stop_hash: &H256) -> Option<BlockHeight> {
+		if let Some(stop_height) = self.storage.block_number(stop_hash) {
+			return Some(stop_height);
+		}
+
+		// stop block could be just reorganized out of the best chain => ignore request
+		if self.storage.block_header(stop_hash.clone().into()).is_some() {
+			trace!(target: "sync", peer = peer_index; "'{}' request from peer#{} is ignored as stop block {} is not in the best chain", command, peer_index, stop_hash.to_reversed_str());
+			return None;
+		}
+
+		self.peers.misbehaving(peer_index, &format!("Got '{}' message with unknown stop hash {}", command, stop_hash.to_reversed_str()));
+		None
+	}
+
+	/// Checks filters request and returns height of the stop block.
+	fn locate_filters_range(&self, peer_index: PeerIndex, command: &str, filter_type: u8, start_height: BlockHeight, stop_hash: &H256, max_len: u32) -> Option<BlockHeight> {
+		if !self.check_filters_request(peer_index, command, filter_type) {
+			return None;
+		}
+
+		let stop_height = match self.locate_filters_stop_block(peer_index, command, stop_hash) {
+			Some(stop_height) => stop_height,
+			None => return None,
+		};
+
+		if start_height > stop_height || stop_height - start_height >= max_len {
+			self.peers.misbehaving(peer_index, &format!("Got '{}' message with invalid range {}..{}", command, start_height, stop_height));
+			return None;
+		}
+
+		Some(stop_height)
+	}
+
 	fn locate_best_common_block(&self, hash_stop: &H256, locator: &[H256]) -> Option<BlockHeight> {
 		for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
 			if let Some(block_number) = self.storage.block_number(block_hash) {
@@ -482,6 +682,7 @@ pub mod tests {
 	use std::sync::Arc;
 	use parking_lot::{Mutex, RwLock};
 	use db::{BlockChainDatabase};
+	use storage::BlockFilterProvider;
 	use message::types;
 	use message::common::{self, Services, InventoryVector, InventoryType};
 	use primitives::hash::H256;
@@ -708,6 +909,96 @@ pub mod tests {
 		assert!(!peers.enumerate().contains(&0));
 	}
 
+	#[test]
+	fn server_get_cfheaders_responds_when_good_request() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]).with_block_filter_index(true).unwrap());
+		let executor = DummyTaskExecutor::new();
+		let server = ServerImpl::new(peers.clone(), storage.clone(), Arc::new(RwLock::new(MemoryPool::new())), executor.clone());
+
+		// when asking for filter headers
+		server.execute(ServerTask::GetCFHeaders(0, types::GetCFHeaders {
+			filter_type: 0,
+			start_height: 0,
+			stop_hash: test_data::genesis().hash(),
+		}));
+
+		// => responds with filter hashes
+		let filter_hash = storage.block_filter(&test_data::genesis().hash()).unwrap().hash();
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(tasks, vec![Task::CFHeaders(0, types::CFHeaders {
+			filter_type: 0,
+			stop_hash: test_data::genesis().hash(),
+			previous_filter_header: H256::default(),
+			filter_hashes: vec![filter_hash],
+		})]);
+	}
+
+	#[test]
+	fn server_get_cfilters_do_not_responds_when_bad_request() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]).with_block_filter_index(true).unwrap());
+		let executor = DummyTaskExecutor::new();
+		let server = ServerImpl::new(peers.clone(), storage, Arc::new(RwLock::new(MemoryPool::new())), executor.clone());
+
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+
+		// when asking for filters of unknown type
+		server.execute(ServerTask::GetCFilters(0, types::GetCFilters {
+			filter_type: 1,
+			start_height: 0,
+			stop_hash: test_data::genesis().hash(),
+		}));
+
+		// => no response and connection is closed
+		let tasks = DummyTaskExecutor::wait_tasks_for(executor, 100); // TODO: get rid of explicit timeout
+		assert_eq!(tasks, vec![]);
+		assert!(!peers.enumerate().contains(&0));
+	}
+
+	#[test]
+	fn server_get_cfcheckpt_disconnects_when_filter_index_is_disabled() {
+		let (_, _, executor, peers, server) = create_synchronization_server();
+
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+
+		// when asking for filter checkpoints while filters are not maintained
+		server.execute(ServerTask::GetCFCheckpt(0, types::GetCFCheckpt {
+			filter_type: 0,
+			stop_hash: test_data::genesis().hash(),
+		}));
+
+		// => no response and connection is closed
+		let tasks = DummyTaskExecutor::wait_tasks_for(executor, 100); // TODO: get rid of explicit timeout
+		assert_eq!(tasks, vec![]);
+		assert!(!peers.enumerate().contains(&0));
+	}
+
+	#[test]
+	fn server_get_cfheaders_ignores_side_chain_stop_hash() {
+		let peers = Arc::new(PeersImpl::default());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]).with_block_filter_index(true).unwrap());
+		let executor = DummyTaskExecutor::new();
+		let server = ServerImpl::new(peers.clone(), storage.clone(), Arc::new(RwLock::new(MemoryPool::new())), executor.clone());
+
+		// given block that is known, but is not in the best chain
+		let side_block = test_data::block_h1();
+		storage.insert(side_block.clone().into()).expect("Db write error");
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+
+		// when asking for filter headers up to this block
+		server.execute(ServerTask::GetCFHeaders(0, types::GetCFHeaders {
+			filter_type: 0,
+			start_height: 0,
+			stop_hash: side_block.hash(),
+		}));
+
+		// => no response, but connection is kept
+		let tasks = DummyTaskExecutor::wait_tasks_for(executor, 100); // TODO: get rid of explicit timeout
+		assert_eq!(tasks, vec![]);
+		assert!(peers.enumerate().contains(&0));
+	}
+
 	#[test]
 	fn server_getdata_responds_notfound_when_transaction_is_inaccessible() {
 		let (_, _, executor, _, server) = create_synchronization_server();
@@ -1,17 +1,19 @@
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::mpsc::{channel, Sender, Receiver};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread;
+use std::time::Instant;
 use parking_lot::Mutex;
-use time::get_time;
+use metrics::{Histogram, HistogramSnapshot};
 use chain::{IndexedBlock, IndexedTransaction};
 use network::ConsensusParams;
 use primitives::hash::H256;
 use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Verify as VerificationVerify,
 	Error as VerificationError, VerificationLevel};
-use types::{BlockHeight, StorageRef, MemoryPoolRef};
-use utils::MemoryPoolTransactionOutputProvider;
+use types::{BlockHeight, StorageRef, MemoryPoolRef, VerificationStatsRef};
+use message::types::reject::RejectCode;
+use utils::{TransactionAcceptance, TransactionRejection, PackageAcceptance, check_transaction_acceptance, check_package_acceptance};
 use VerificationParameters;
 
 /// Block verification events sink
@@ -20,6 +22,8 @@ pub trait BlockVerificationSink : Send + Sync + 'static {
 	fn on_block_verification_success(&self, block: IndexedBlock) -> Option<Vec<VerificationTask>>;
 	/// When block verification has failed.
 	fn on_block_verification_error(&self, err: &str, hash: &H256);
+	/// Is block an ancestor of assume-valid block? Scripts of such blocks are not verified.
+	fn is_assumed_valid_block(&self, hash: &H256) -> bool;
 }
 
 /// Transaction verification events sink
@@ -27,11 +31,19 @@ pub trait TransactionVerificationSink : Send + Sync + 'static {
 	/// When transaction verification has completed successfully.
 	fn on_transaction_verification_success(&self, transaction: IndexedTransaction);
 	/// When transaction verification has failed.
-	fn on_transaction_verification_error(&self, err: &str, hash: &H256);
+	fn on_transaction_verification_error(&self, rejection: &TransactionRejection, hash: &H256);
+}
+
+/// Package verification events sink
+pub trait PackageVerificationSink : Send + Sync + 'static {
+	/// When package verification has completed successfully.
+	fn on_package_verification_success(&self, package: Vec<IndexedTransaction>, acceptance: PackageAcceptance) -> Option<Vec<VerificationTask>>;
+	/// When package verification has failed. Hash is the hash of the package child transaction.
+	fn on_package_verification_error(&self, acceptance: PackageAcceptance, hash: &H256);
 }
 
 /// Verification events sink
-pub trait VerificationSink : BlockVerificationSink + TransactionVerificationSink {
+pub trait VerificationSink : BlockVerificationSink + TransactionVerificationSink + PackageVerificationSink {
 }
 
 /// Verification thread tasks
@@ -41,6 +53,8 @@ pub enum VerificationTask {
 	VerifyBlock(IndexedBlock),
 	/// Verify single transaction
 	VerifyTransaction(BlockHeight, IndexedTransaction),
+	/// Verify package of the child transaction with its unconfirmed parents
+	VerifyPackage(BlockHeight, Vec<IndexedTransaction>),
 	/// Stop verification thread
 	Stop,
 }
@@ -51,6 +65,21 @@ pub trait Verifier : Send + Sync + 'static {
 	fn verify_block(&self, block: IndexedBlock);
 	/// Verify transaction
 	fn verify_transaction(&self, height: BlockHeight, transaction: IndexedTransaction);
+	/// Check if transaction could be accepted to the memory pool, without accepting it
+	fn test_transaction(&self, height: BlockHeight, transaction: &IndexedTransaction) -> TransactionAcceptance;
+	/// Verify package
+	fn verify_package(&self, height: BlockHeight, package: Vec<IndexedTransaction>);
+	/// Get verification statistics
+	fn stats(&self) -> VerificationStatsRef;
+}
+
+/// Verification statistics
+#[derive(Debug, Default)]
+pub struct VerificationStats {
+	/// Number of tasks waiting in the verification queue.
+	queue_len: AtomicUsize,
+	/// Block verification time.
+	block_verification_time: Histogram,
 }
 
 /// Asynchronous synchronization verifier
@@ -59,6 +88,14 @@ pub struct AsyncVerifier {
 	verification_work_sender: Mutex<Sender<VerificationTask>>,
 	/// Verification thread.
 	verification_worker_thread: Option<thread::JoinHandle<()>>,
+	/// Storage reference.
+	storage: StorageRef,
+	/// Memory pool reference.
+	memory_pool: MemoryPoolRef,
+	/// Verifier, shared with verification thread.
+	verifier: Arc<ChainVerifierWrapper>,
+	/// Verification statistics.
+	stats: VerificationStatsRef,
 }
 
 /// Chain verifier wrapper to deal with verification parameters.
@@ -69,6 +106,20 @@ pub struct ChainVerifierWrapper {
 	verification_params: VerificationParameters,
 	/// Is verification edge passed.
 	pub enforce_full_verification: AtomicBool,
+	/// Verification statistics.
+	stats: VerificationStatsRef,
+}
+
+impl VerificationStats {
+	/// Number of tasks waiting in the verification queue
+	pub fn queue_len(&self) -> usize {
+		self.queue_len.load(Ordering::Relaxed)
+	}
+
+	/// Block verification time histogram
+	pub fn block_verification_time(&self) -> HistogramSnapshot {
+		self.block_verification_time.snapshot()
+	}
 }
 
 impl ChainVerifierWrapper {
@@ -79,11 +130,17 @@ impl ChainVerifierWrapper {
 			verifier: verifier,
 			verification_params: verification_params,
 			enforce_full_verification: enforce_full_verification,
+			stats: VerificationStatsRef::default(),
 		}
 	}
 
-	/// Verify block.
-	pub fn verify_block(&self, block: &IndexedBlock) -> Result<(), VerificationError> {
+	/// Get verification statistics
+	pub fn stats(&self) -> VerificationStatsRef {
+		self.stats.clone()
+	}
+
+	/// Verify block. Scripts of assumed valid blocks are not verified.
+	pub fn verify_block(&self, block: &IndexedBlock, is_assumed_valid: bool) -> Result<(), VerificationError> {
 		let enforce_full_verification = if block.hash() == &self.verification_params.verification_edge {
 			self.enforce_full_verification.store(true, Ordering::Relaxed);
 			true
@@ -95,8 +152,25 @@ impl ChainVerifierWrapper {
 		} else {
 			self.verification_params.verification_level
 		};
+		let verification_level = match verification_level {
+			VerificationLevel::Full if is_assumed_valid => VerificationLevel::Header,
+			verification_level => verification_level,
+		};
+
+		let start = Instant::now();
+		let result = self.verifier.verify(verification_level, block);
+		self.stats.block_verification_time.observe_duration(start.elapsed());
+		result
+	}
+
+	/// Check if transaction could be accepted to the memory pool at given height.
+	pub fn check_transaction(&self, storage: &StorageRef, memory_pool: &MemoryPoolRef, height: BlockHeight, transaction: &IndexedTransaction) -> TransactionAcceptance {
+		check_transaction_acceptance(storage, memory_pool, &self.verifier, &self.verification_params.policy, height, transaction)
+	}
 
-		self.verifier.verify(verification_level, block)
+	/// Check if package could be accepted to the memory pool at given height.
+	pub fn check_package(&self, storage: &StorageRef, memory_pool: &MemoryPoolRef, height: BlockHeight, package: &[IndexedTransaction]) -> PackageAcceptance {
+		check_package_acceptance(storage, memory_pool, &self.verifier, &self.verification_params.policy, height, package)
 	}
 }
 
@@ -114,21 +188,26 @@ impl AsyncVerifier {
 	/// Create new async verifier
 	pub fn new<T: VerificationSink>(verifier: Arc<ChainVerifier>, storage: StorageRef, memory_pool: MemoryPoolRef, sink: Arc<T>, verification_params: VerificationParameters) -> Self {
 		let (verification_work_sender, verification_work_receiver) = channel();
+		let verifier = Arc::new(ChainVerifierWrapper::new(verifier, &storage, verification_params));
+		let stats = verifier.stats();
+		let (worker_storage, worker_memory_pool, worker_verifier) = (storage.clone(), memory_pool.clone(), verifier.clone());
 		AsyncVerifier {
 			verification_work_sender: Mutex::new(verification_work_sender),
 			verification_worker_thread: Some(thread::Builder::new()
 				.name("Sync verification thread".to_string())
-				.spawn(move || {
-					let verifier = ChainVerifierWrapper::new(verifier, &storage, verification_params);
-					AsyncVerifier::verification_worker_proc(sink, storage, memory_pool, verifier, verification_work_receiver)
-				})
-				.expect("Error creating sync verification thread"))
+				.spawn(move || AsyncVerifier::verification_worker_proc(sink, worker_storage, worker_memory_pool, worker_verifier, verification_work_receiver))
+				.expect("Error creating sync verification thread")),
+			storage: storage,
+			memory_pool: memory_pool,
+			verifier: verifier,
+			stats: stats,
 		}
 	}
 
 	/// Thread procedure for handling verification tasks
-	fn verification_worker_proc<T: VerificationSink>(sink: Arc<T>, storage: StorageRef, memory_pool: MemoryPoolRef, verifier: ChainVerifierWrapper, work_receiver: Receiver<VerificationTask>) {
+	fn verification_worker_proc<T: VerificationSink>(sink: Arc<T>, storage: StorageRef, memory_pool: MemoryPoolRef, verifier: Arc<ChainVerifierWrapper>, work_receiver: Receiver<VerificationTask>) {
 		while let Ok(task) = work_receiver.recv() {
+			verifier.stats.queue_len.fetch_sub(1, Ordering::Relaxed);
 			if !AsyncVerifier::execute_single_task(&sink, &storage, &memory_pool, &verifier, task) {
 				break;
 			}
@@ -150,7 +229,7 @@ impl AsyncVerifier {
 			match task {
 				VerificationTask::VerifyBlock(block) => {
 					// verify block
-					match verifier.verify_block(&block) {
+					match verifier.verify_block(&block, sink.is_assumed_valid_block(block.hash())) {
 						Ok(_) => {
 							if let Some(tasks) = sink.on_block_verification_success(block) {
 								tasks_queue.extend(tasks);
@@ -162,20 +241,22 @@ impl AsyncVerifier {
 					}
 				},
 				VerificationTask::VerifyTransaction(height, transaction) => {
-					// output provider must check previous outputs in both storage && memory pool
-					match MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), memory_pool, &transaction.raw) {
-						Err(e) => {
-							sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash);
-							continue; // with new verification sub-task
+					match verifier.check_transaction(storage, memory_pool, height, &transaction).result {
+						Ok(_) => sink.on_transaction_verification_success(transaction),
+						Err(rejection) => sink.on_transaction_verification_error(&rejection, &transaction.hash),
+					}
+				},
+				VerificationTask::VerifyPackage(height, package) => {
+					let acceptance = verifier.check_package(storage, memory_pool, height, &package);
+					match acceptance.result {
+						Ok(_) => if let Some(tasks) = sink.on_package_verification_success(package, acceptance) {
+							tasks_queue.extend(tasks);
 						},
-						Ok(tx_output_provider) => {
-							let time: u32 = get_time().sec as u32;
-							match verifier.verifier.verify_mempool_transaction(storage.as_block_header_provider(), &tx_output_provider, height, time, &transaction) {
-								Ok(_) => sink.on_transaction_verification_success(transaction.into()),
-								Err(e) => sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash),
-							}
+						Err(_) => {
+							let hash = package.last().expect("packages are never empty; qed").hash.clone();
+							sink.on_package_verification_error(acceptance, &hash);
 						},
-					};
+					}
 				},
 				VerificationTask::Stop => return false,
 			}
@@ -192,6 +273,7 @@ impl Drop for AsyncVerifier {
 			{
 				let verification_work_sender = self.verification_work_sender.lock();
 				// ignore send error here <= destructing anyway
+				self.stats.queue_len.fetch_add(1, Ordering::Relaxed);
 				let _ = verification_work_sender.send(VerificationTask::Stop);
 			}
 			join_handle.join().expect("Clean shutdown.");
@@ -202,6 +284,7 @@ impl Drop for AsyncVerifier {
 impl Verifier for AsyncVerifier {
 	/// Verify block
 	fn verify_block(&self, block: IndexedBlock) {
+		self.stats.queue_len.fetch_add(1, Ordering::Relaxed);
 		self.verification_work_sender.lock()
 			.send(VerificationTask::VerifyBlock(block))
 			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
@@ -209,10 +292,29 @@ impl Verifier for AsyncVerifier {
 
 	/// Verify transaction
 	fn verify_transaction(&self, height: BlockHeight, transaction: IndexedTransaction) {
+		self.stats.queue_len.fetch_add(1, Ordering::Relaxed);
 		self.verification_work_sender.lock()
 			.send(VerificationTask::VerifyTransaction(height, transaction))
 			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
 	}
+
+	/// Check transaction acceptance in the caller thread
+	fn test_transaction(&self, height: BlockHeight, transaction: &IndexedTransaction) -> TransactionAcceptance {
+		self.verifier.check_transaction(&self.storage, &self.memory_pool, height, transaction)
+	}
+
+	/// Verify package
+	fn verify_package(&self, height: BlockHeight, package: Vec<IndexedTransaction>) {
+		self.stats.queue_len.fetch_add(1, Ordering::Relaxed);
+		self.verification_work_sender.lock()
+			.send(VerificationTask::VerifyPackage(height, package))
+			.expect("Verification thread have the same lifetime as `AsyncVerifier`");
+	}
+
+	/// Get verification statistics
+	fn stats(&self) -> VerificationStatsRef {
+		self.stats.clone()
+	}
 }
 
 /// Synchronous synchronization verifier
@@ -226,7 +328,8 @@ pub struct SyncVerifier<T: VerificationSink> {
 impl<T> SyncVerifier<T> where T: VerificationSink {
 	/// Create new sync verifier
 	pub fn new(consensus: ConsensusParams, storage: StorageRef, sink: Arc<T>, verification_params: VerificationParameters) -> Self {
-		let verifier = ChainVerifier::new(storage.clone(), consensus);
+		let verifier = ChainVerifier::new(storage.clone(), consensus)
+			.with_script_threads(verification_params.script_threads);
 		let verifier = ChainVerifierWrapper::new(Arc::new(verifier), &storage, verification_params);
 		SyncVerifier {
 			verifier: verifier,
@@ -238,7 +341,7 @@ impl<T> SyncVerifier<T> where T: VerificationSink {
 impl<T> Verifier for SyncVerifier<T> where T: VerificationSink {
 	/// Verify block
 	fn verify_block(&self, block: IndexedBlock) {
-		match self.verifier.verify_block(&block) {
+		match self.verifier.verify_block(&block, self.sink.is_assumed_valid_block(block.hash())) {
 			Ok(_) => {
 				// SyncVerifier is used for bulk blocks import only
 				// => there are no memory pool
@@ -253,6 +356,31 @@ impl<T> Verifier for SyncVerifier<T> where T: VerificationSink {
 	fn verify_transaction(&self, _height: BlockHeight, _transaction: IndexedTransaction) {
 		unimplemented!() // sync verifier is currently only used for blocks verification
 	}
+
+	/// Test transaction
+	fn test_transaction(&self, _height: BlockHeight, transaction: &IndexedTransaction) -> TransactionAcceptance {
+		// sync verifier is currently only used for blocks verification
+		TransactionAcceptance {
+			result: Err(TransactionRejection::new(RejectCode::Invalid, "Cannot verify transaction while importing blocks".to_owned())),
+			..TransactionAcceptance::new(transaction)
+		}
+	}
+
+	/// Test package
+	fn verify_package(&self, _height: BlockHeight, package: Vec<IndexedTransaction>) {
+		// sync verifier is currently only used for blocks verification
+		let acceptance = PackageAcceptance {
+			result: Err(TransactionRejection::new(RejectCode::Invalid, "Cannot verify package while importing blocks".to_owned())),
+			..PackageAcceptance::new(&package)
+		};
+		let hash = package.last().expect("packages are never empty; qed").hash.clone();
+		self.sink.on_package_verification_error(acceptance, &hash);
+	}
+
+	/// Get verification statistics
+	fn stats(&self) -> VerificationStatsRef {
+		self.verifier.stats()
+	}
 }
 
 #[cfg(test)]
@@ -264,13 +392,15 @@ pub mod tests {
 	use std::collections::{HashSet, HashMap};
 	use db::BlockChainDatabase;
 	use network::{Network, ConsensusParams, ConsensusFork};
-	use verification::{VerificationLevel, BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError, TransactionError};
+	use verification::{VerificationLevel, BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError, TransactionError, Policy};
 	use synchronization_client_core::CoreVerificationSink;
 	use synchronization_executor::tests::DummyTaskExecutor;
 	use primitives::hash::H256;
 	use chain::{IndexedBlock, IndexedTransaction};
-	use super::{Verifier, BlockVerificationSink, TransactionVerificationSink, AsyncVerifier, VerificationTask, ChainVerifierWrapper};
-	use types::{BlockHeight, StorageRef, MemoryPoolRef};
+	use message::types::reject::RejectCode;
+	use super::{Verifier, BlockVerificationSink, TransactionVerificationSink, PackageVerificationSink, AsyncVerifier, VerificationTask, ChainVerifierWrapper};
+	use types::{BlockHeight, StorageRef, MemoryPoolRef, VerificationStatsRef};
+	use utils::{TransactionAcceptance, TransactionRejection, PackageAcceptance};
 	use script::Error as ScriptError;
 	use VerificationParameters;
 
@@ -282,6 +412,7 @@ pub mod tests {
 		storage: Option<StorageRef>,
 		memory_pool: Option<MemoryPoolRef>,
 		verifier: Option<ChainVerifierWrapper>,
+		stats: VerificationStatsRef,
 	}
 
 	impl DummyVerifier {
@@ -301,6 +432,8 @@ pub mod tests {
 			self.verifier = Some(ChainVerifierWrapper::new(verifier, self.storage.as_ref().unwrap(), VerificationParameters {
 				verification_level: VerificationLevel::Full,
 				verification_edge: 0u8.into(),
+				script_threads: 0,
+				policy: Policy::permissive(),
 			}));
 		}
 
@@ -333,7 +466,7 @@ pub mod tests {
 		fn verify_transaction(&self, _height: BlockHeight, transaction: IndexedTransaction) {
 			match self.sink {
 				Some(ref sink) => match self.errors.get(&transaction.hash) {
-					Some(err) => sink.on_transaction_verification_error(&err, &transaction.hash),
+					Some(err) => sink.on_transaction_verification_error(&TransactionRejection::new(RejectCode::Invalid, err.clone()), &transaction.hash),
 					None => {
 						if self.actual_checks.contains(&transaction.hash) {
 							let next_block_height = self.storage.as_ref().unwrap().best_block().number + 1;
@@ -346,6 +479,61 @@ pub mod tests {
 				None => panic!("call set_sink"),
 			}
 		}
+
+		fn test_transaction(&self, height: BlockHeight, transaction: &IndexedTransaction) -> TransactionAcceptance {
+			match self.errors.get(&transaction.hash) {
+				Some(err) => TransactionAcceptance {
+					result: Err(TransactionRejection::new(RejectCode::Invalid, err.clone())),
+					..TransactionAcceptance::new(transaction)
+				},
+				None => match self.actual_checks.contains(&transaction.hash) {
+					true => self.verifier.as_ref().unwrap().check_transaction(self.storage.as_ref().unwrap(), self.memory_pool.as_ref().unwrap(), height, transaction),
+					false => TransactionAcceptance::new(transaction),
+				},
+			}
+		}
+
+		fn verify_package(&self, height: BlockHeight, package: Vec<IndexedTransaction>) {
+			let sink = match self.sink {
+				Some(ref sink) => sink,
+				None => panic!("call set_sink"),
+			};
+
+			let hash = package.last().unwrap().hash.clone();
+			let tasks = match package.iter().filter_map(|transaction| self.errors.get(&transaction.hash)).next() {
+				Some(err) => {
+					sink.on_package_verification_error(PackageAcceptance {
+						result: Err(TransactionRejection::new(RejectCode::Invalid, err.clone())),
+						..PackageAcceptance::new(&package)
+					}, &hash);
+					None
+				},
+				None => match package.iter().any(|transaction| self.actual_checks.contains(&transaction.hash)) {
+					true => {
+						AsyncVerifier::execute_single_task(sink, self.storage.as_ref().unwrap(), self.memory_pool.as_ref().unwrap(), self.verifier.as_ref().unwrap(), VerificationTask::VerifyPackage(height, package));
+						None
+					},
+					false => {
+						let acceptance = PackageAcceptance {
+							transactions: package.iter().map(TransactionAcceptance::new).collect(),
+							result: Ok(()),
+						};
+						sink.on_package_verification_success(package, acceptance)
+					},
+				},
+			};
+
+			// orphans, which have been waiting for the package, are verified now
+			for task in tasks.into_iter().flat_map(|tasks| tasks) {
+				if let VerificationTask::VerifyTransaction(height, transaction) = task {
+					self.verify_transaction(height, transaction);
+				}
+			}
+		}
+
+		fn stats(&self) -> VerificationStatsRef {
+			self.stats.clone()
+		}
 	}
 
 	#[test]
@@ -357,16 +545,20 @@ pub mod tests {
 		assert_eq!(ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::NoVerification,
 			verification_edge: test_data::genesis().hash(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		}).enforce_full_verification.load(Ordering::Relaxed), true);
 
 		// switching to full verification when block with given hash is coming
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::NoVerification,
 			verification_edge: test_data::block_h1().hash(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
 		assert_eq!(wrapper.enforce_full_verification.load(Ordering::Relaxed), false);
 		let block: IndexedBlock = test_data::block_h1().into();
-		let _ = wrapper.verify_block(&block);
+		let _ = wrapper.verify_block(&block, false);
 		assert_eq!(wrapper.enforce_full_verification.load(Ordering::Relaxed), true);
 	}
 
@@ -410,15 +602,22 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::Header,
 			verification_edge: 1.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_transaction_block), Ok(()));
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, false), Ok(()));
 
 		// Error when tx script is checked
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::Full,
 			verification_edge: 1.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_transaction_block), Err(VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::InvalidStackOperation))));
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, false), Err(VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::InvalidStackOperation))));
+
+		// Ok(()) when block is assumed valid
+		assert_eq!(wrapper.verify_block(&bad_transaction_block, true), Ok(()));
 	}
 
 	#[test]
@@ -431,14 +630,37 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::NoVerification,
 			verification_edge: 1.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_block), Ok(()));
+		assert_eq!(wrapper.verify_block(&bad_block, false), Ok(()));
 
 		// Error when everything is verified
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::Full,
 			verification_edge: 1.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
+		});
+		assert_eq!(wrapper.verify_block(&bad_block, false), Err(VerificationError::Empty));
+	}
+
+	#[test]
+	fn verifier_wrapper_collects_block_verification_time() {
+		let storage: StorageRef = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore)));
+		let bad_block: IndexedBlock = test_data::block_builder().header().build().build().into();
+
+		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
+			verification_level: VerificationLevel::Full,
+			verification_edge: 1.into(),
+			script_threads: 0,
+			policy: Policy::permissive(),
 		});
-		assert_eq!(wrapper.verify_block(&bad_block), Err(VerificationError::Empty));
+		assert_eq!(wrapper.stats().block_verification_time().count, 0);
+		assert!(wrapper.verify_block(&bad_block, false).is_err());
+		assert!(wrapper.verify_block(&bad_block, false).is_err());
+		assert_eq!(wrapper.stats().block_verification_time().count, 2);
+		assert_eq!(wrapper.stats().queue_len(), 0);
 	}
 }
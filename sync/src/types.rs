@@ -9,7 +9,7 @@ use synchronization_client::SynchronizationClient;
 use synchronization_executor::LocalSynchronizationTaskExecutor;
 use synchronization_peers::Peers;
 use synchronization_server::ServerImpl;
-use synchronization_verifier::AsyncVerifier;
+use synchronization_verifier::{AsyncVerifier, VerificationStats};
 use utils::SynchronizationState;
 
 pub use utils::BlockHeight;
@@ -35,6 +35,9 @@ pub type SynchronizationStateRef = Arc<SynchronizationState>;
 /// Reference to peers
 pub type PeersRef = Arc<dyn Peers>;
 
+/// Reference to verification statistics
+pub type VerificationStatsRef = Arc<VerificationStats>;
+
 /// Reference to synchronization tasks executor
 pub type ExecutorRef<T> = Arc<T>;
 
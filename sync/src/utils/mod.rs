@@ -12,6 +12,7 @@ mod orphan_blocks_pool;
 mod orphan_transactions_pool;
 mod partial_merkle_tree;
 mod synchronization_state;
+mod transaction_acceptance;
 
 pub use self::average_speed_meter::AverageSpeedMeter;
 pub use self::best_headers_chain::{BestHeadersChain, Information as BestHeadersChainInformation};
@@ -27,6 +28,8 @@ pub use self::orphan_blocks_pool::OrphanBlocksPool;
 pub use self::orphan_transactions_pool::{OrphanTransactionsPool, OrphanTransaction};
 pub use self::partial_merkle_tree::{PartialMerkleTree, build_partial_merkle_tree};
 pub use self::synchronization_state::SynchronizationState;
+pub use self::transaction_acceptance::{TransactionAcceptance, TransactionRejection, PackageAcceptance,
+	check_transaction_acceptance, check_package_acceptance};
 
 /// Block height type
 pub type BlockHeight = u32;
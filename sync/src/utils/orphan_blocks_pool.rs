@@ -1,26 +1,63 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::collections::hash_map::Entry;
 use linked_hash_map::LinkedHashMap;
+use rand::{seq, thread_rng};
 use time;
 use primitives::hash::H256;
 use chain::IndexedBlock;
+use types::PeerIndex;
+
+/// Default maximal total size of unknown blocks (in bytes)
+pub const DEFAULT_UNKNOWN_BLOCKS_MAX_SIZE: usize = 32 * 1024 * 1024;
+/// Default maximal total size of unknown blocks from single peer (in bytes)
+pub const DEFAULT_UNKNOWN_BLOCKS_MAX_PEER_SIZE: usize = 8 * 1024 * 1024;
 
 #[derive(Debug)]
 /// Storage for blocks, for which we have no parent yet.
 /// Blocks from this storage are either moved to verification queue, or removed at all.
+/// Total size of unknown blocks and size of unknown blocks from every peer are limited. When limit is
+/// exceeded, random unknown blocks are evicted, so that single peer can not push out blocks of other peers.
 pub struct OrphanBlocksPool {
 	/// Blocks from requested_hashes, but received out-of-order.
 	orphaned_blocks: HashMap<H256, HashMap<H256, IndexedBlock>>,
-	/// Blocks that we have received without requesting with receiving time.
-	unknown_blocks: LinkedHashMap<H256, f64>,
+	/// Blocks that we have received without requesting.
+	unknown_blocks: LinkedHashMap<H256, UnknownBlock>,
+	/// Unknown blocks by peer, which has sent them && their total size
+	unknown_blocks_by_peer: HashMap<PeerIndex, (HashSet<H256>, usize)>,
+	/// Total size of unknown blocks
+	unknown_blocks_size: usize,
+	/// Maximal total size of unknown blocks
+	max_size: usize,
+	/// Maximal total size of unknown blocks from single peer
+	max_peer_size: usize,
+}
+
+#[derive(Debug)]
+/// Unknown block representation.
+pub struct UnknownBlock {
+	/// Time when this block was inserted to the pool
+	pub insertion_time: f64,
+	/// Peer, which has sent us this block
+	pub peer_index: PeerIndex,
+	/// Serialized size of the block
+	pub size: usize,
 }
 
 impl OrphanBlocksPool {
-	/// Create new pool
+	/// Create new pool with default limits
 	pub fn new() -> Self {
+		OrphanBlocksPool::with_limits(DEFAULT_UNKNOWN_BLOCKS_MAX_SIZE, DEFAULT_UNKNOWN_BLOCKS_MAX_PEER_SIZE)
+	}
+
+	/// Create new pool with given limits of unknown blocks
+	pub fn with_limits(max_size: usize, max_peer_size: usize) -> Self {
 		OrphanBlocksPool {
 			orphaned_blocks: HashMap::new(),
 			unknown_blocks: LinkedHashMap::new(),
+			unknown_blocks_by_peer: HashMap::new(),
+			unknown_blocks_size: 0,
+			max_size: max_size,
+			max_peer_size: max_peer_size,
 		}
 	}
 
@@ -29,13 +66,23 @@ impl OrphanBlocksPool {
 		self.orphaned_blocks.len()
 	}
 
+	/// Get total size of unknown blocks in pool
+	pub fn unknown_blocks_size(&self) -> usize {
+		self.unknown_blocks_size
+	}
+
+	/// Get total size of unknown blocks from given peer
+	pub fn peer_unknown_blocks_size(&self, peer_index: PeerIndex) -> usize {
+		self.unknown_blocks_by_peer.get(&peer_index).map(|&(_, size)| size).unwrap_or(0)
+	}
+
 	/// Check if block with given hash is stored as unknown in this pool
 	pub fn contains_unknown_block(&self, hash: &H256) -> bool {
 		self.unknown_blocks.contains_key(hash)
 	}
 
 	/// Get unknown blocks in the insertion order
-	pub fn unknown_blocks(&self) -> &LinkedHashMap<H256, f64> {
+	pub fn unknown_blocks(&self) -> &LinkedHashMap<H256, UnknownBlock> {
 		&self.unknown_blocks
 	}
 
@@ -47,12 +94,31 @@ impl OrphanBlocksPool {
 			.insert(block.header.hash.clone(), block);
 	}
 
-	/// Insert unknown block, for which we know nothing about its parent block
-	pub fn insert_unknown_block(&mut self, block: IndexedBlock) {
-		let previous_value = self.unknown_blocks.insert(block.header.hash.clone(), time::precise_time_s());
-		assert_eq!(previous_value, None);
+	/// Insert unknown block, for which we know nothing about its parent block.
+	/// Returns hashes of blocks, evicted from the pool to fit the limits. This includes
+	/// inserted block if it is too large to be stored.
+	pub fn insert_unknown_block(&mut self, block: IndexedBlock, peer_index: PeerIndex) -> Vec<H256> {
+		let unknown_block = UnknownBlock {
+			insertion_time: time::precise_time_s(),
+			peer_index: peer_index,
+			size: block.size_with_witness(),
+		};
+		if unknown_block.size > self.max_peer_size || unknown_block.size > self.max_size {
+			return vec![block.header.hash];
+		}
+
+		{
+			let peer_blocks = self.unknown_blocks_by_peer.entry(peer_index).or_insert_with(|| (HashSet::new(), 0));
+			peer_blocks.0.insert(block.header.hash.clone());
+			peer_blocks.1 += unknown_block.size;
+		}
+		self.unknown_blocks_size += unknown_block.size;
+		let previous_value = self.unknown_blocks.insert(block.header.hash.clone(), unknown_block);
+		assert!(previous_value.is_none());
 
 		self.insert_orphaned_block(block);
+
+		self.evict(peer_index)
 	}
 
 	/// Remove all blocks, which are not-unknown
@@ -75,7 +141,7 @@ impl OrphanBlocksPool {
 			if let Entry::Occupied(entry) = self.orphaned_blocks.entry(parent_hash) {
 				let (_, orphaned) = entry.remove_entry();
 				for orphaned_hash in orphaned.keys() {
-					self.unknown_blocks.remove(orphaned_hash);
+					self.remove_unknown_block(orphaned_hash);
 				}
 				queue.extend(orphaned.keys().cloned());
 				removed.extend(orphaned.into_iter().map(|(_, b)| b));
@@ -96,7 +162,7 @@ impl OrphanBlocksPool {
 		});
 
 		for block in &removed {
-			self.unknown_blocks.remove(block);
+			self.remove_unknown_block(block);
 		}
 		// also delete all children
 		for hash in hashes.iter() {
@@ -105,6 +171,66 @@ impl OrphanBlocksPool {
 
 		removed
 	}
+
+	/// Remove all unknown blocks, received from given peer + all dependent blocks
+	pub fn remove_peer_blocks(&mut self, peer_index: PeerIndex) -> Vec<H256> {
+		let hashes: HashSet<H256> = match self.unknown_blocks_by_peer.get(&peer_index) {
+			Some(&(ref hashes, _)) => hashes.clone(),
+			None => return Vec::new(),
+		};
+
+		self.remove_blocks(&hashes)
+	}
+
+	/// Evict random unknown blocks (+ all dependent blocks) until pool fits the limits.
+	/// Blocks of the peer, which has exceeded its quota, are evicted first.
+	/// Then blocks of the peer, which uses the most of the pool, are evicted.
+	fn evict(&mut self, peer_index: PeerIndex) -> Vec<H256> {
+		let mut evicted: Vec<H256> = Vec::new();
+		while self.peer_unknown_blocks_size(peer_index) > self.max_peer_size {
+			let hash = self.random_unknown_block(peer_index).expect("peer size is non-zero; qed");
+			evicted.extend(self.remove_blocks(&vec![hash].into_iter().collect()));
+		}
+
+		while self.unknown_blocks_size > self.max_size {
+			let largest_peer_index = self.unknown_blocks_by_peer.iter()
+				.max_by_key(|&(_, &(_, size))| size)
+				.map(|(peer_index, _)| *peer_index)
+				.expect("pool size is non-zero; qed");
+			let hash = self.random_unknown_block(largest_peer_index).expect("peer size is non-zero; qed");
+			evicted.extend(self.remove_blocks(&vec![hash].into_iter().collect()));
+		}
+
+		evicted
+	}
+
+	/// Select random unknown block of given peer
+	fn random_unknown_block(&self, peer_index: PeerIndex) -> Option<H256> {
+		let hashes = &self.unknown_blocks_by_peer.get(&peer_index)?.0;
+		seq::sample_iter(&mut thread_rng(), hashes.iter(), 1).ok()
+			.and_then(|selected| selected.into_iter().next().cloned())
+	}
+
+	/// Forget that block is unknown
+	fn remove_unknown_block(&mut self, hash: &H256) {
+		let unknown_block = match self.unknown_blocks.remove(hash) {
+			Some(unknown_block) => unknown_block,
+			None => return,
+		};
+
+		let is_last_peer_block = match self.unknown_blocks_by_peer.get_mut(&unknown_block.peer_index) {
+			Some(peer_blocks) => {
+				peer_blocks.0.remove(hash);
+				peer_blocks.1 -= unknown_block.size;
+				peer_blocks.0.is_empty()
+			},
+			None => false,
+		};
+		if is_last_peer_block {
+			self.unknown_blocks_by_peer.remove(&unknown_block.peer_index);
+		}
+		self.unknown_blocks_size -= unknown_block.size;
+	}
 }
 
 #[cfg(test)]
@@ -112,9 +238,14 @@ mod tests {
 	extern crate test_data;
 
 	use std::collections::HashSet;
+	use chain::IndexedBlock;
 	use primitives::hash::H256;
 	use super::OrphanBlocksPool;
 
+	fn unknown_block(index: u32) -> IndexedBlock {
+		test_data::block_builder().header().parent(H256::from((index + 1) as u8)).nonce(index).build().build().into()
+	}
+
 	#[test]
 	fn orphan_block_pool_empty_on_start() {
 		let pool = OrphanBlocksPool::new();
@@ -140,7 +271,7 @@ mod tests {
 		let b1 = test_data::block_h1();
 		let b1_hash = b1.hash();
 
-		pool.insert_unknown_block(b1.into());
+		pool.insert_unknown_block(b1.into(), 0);
 
 		assert_eq!(pool.len(), 1);
 		assert!(pool.contains_unknown_block(&b1_hash));
@@ -156,7 +287,7 @@ mod tests {
 		let b2_hash = b2.hash();
 
 		pool.insert_orphaned_block(b1.into());
-		pool.insert_unknown_block(b2.into());
+		pool.insert_unknown_block(b2.into(), 0);
 
 		assert_eq!(pool.len(), 2);
 		assert!(!pool.contains_unknown_block(&b1_hash));
@@ -182,7 +313,7 @@ mod tests {
 		let b3_hash = b3.hash();
 
 		pool.insert_orphaned_block(b1.into());
-		pool.insert_unknown_block(b2.into());
+		pool.insert_unknown_block(b2.into(), 0);
 		pool.insert_orphaned_block(b3.into());
 
 		let removed = pool.remove_blocks_for_parent(&test_data::genesis().hash());
@@ -229,4 +360,73 @@ mod tests {
 
 		assert_eq!(pool.len(), 1);
 	}
+
+	#[test]
+	fn orphan_block_pool_evicts_unknown_blocks_of_peer_over_quota() {
+		let size = unknown_block(0).size_with_witness();
+
+		let mut pool = OrphanBlocksPool::with_limits(100 * size, 2 * size);
+		let b0 = unknown_block(0);
+		let b0_hash = b0.header.hash.clone();
+		assert!(pool.insert_unknown_block(b0, 2).is_empty());
+		for index in 1..10 {
+			pool.insert_unknown_block(unknown_block(index), 1);
+		}
+
+		assert_eq!(pool.unknown_blocks().len(), 3);
+		assert_eq!(pool.peer_unknown_blocks_size(1), 2 * size);
+		assert_eq!(pool.peer_unknown_blocks_size(2), size);
+		assert!(pool.contains_unknown_block(&b0_hash));
+	}
+
+	#[test]
+	fn orphan_block_pool_evicts_unknown_blocks_of_largest_peer() {
+		let size = unknown_block(0).size_with_witness();
+
+		let mut pool = OrphanBlocksPool::with_limits(4 * size, 3 * size);
+		let b0 = unknown_block(0);
+		let b0_hash = b0.header.hash.clone();
+		pool.insert_unknown_block(b0, 2);
+		for index in 1..4 {
+			assert!(pool.insert_unknown_block(unknown_block(index), 1).is_empty());
+		}
+
+		let evicted = pool.insert_unknown_block(unknown_block(4), 3);
+		assert_eq!(evicted.len(), 1);
+		assert_eq!(pool.unknown_blocks_size(), 4 * size);
+		assert_eq!(pool.peer_unknown_blocks_size(1), 2 * size);
+		assert!(pool.contains_unknown_block(&b0_hash));
+	}
+
+	#[test]
+	fn orphan_block_pool_rejects_too_large_block() {
+		let b0 = unknown_block(0);
+		let b0_hash = b0.header.hash.clone();
+		let mut pool = OrphanBlocksPool::with_limits(100, 10);
+
+		assert_eq!(pool.insert_unknown_block(b0, 1), vec![b0_hash.clone()]);
+		assert_eq!(pool.len(), 0);
+		assert!(!pool.contains_unknown_block(&b0_hash));
+	}
+
+	#[test]
+	fn orphan_block_pool_remove_peer_blocks() {
+		let mut pool = OrphanBlocksPool::new();
+		let b0 = unknown_block(0);
+		let b0_hash = b0.header.hash.clone();
+		let b1 = unknown_block(1);
+		let b1_hash = b1.header.hash.clone();
+		let b2 = test_data::block_builder().header().parent(b1_hash.clone()).build().build();
+		let b2_hash = b2.hash();
+		pool.insert_unknown_block(b0, 1);
+		pool.insert_unknown_block(b1, 2);
+		pool.insert_orphaned_block(b2.into());
+
+		let removed: HashSet<H256> = pool.remove_peer_blocks(2).into_iter().collect();
+		assert_eq!(removed, vec![b1_hash, b2_hash].into_iter().collect());
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.unknown_blocks_size(), pool.peer_unknown_blocks_size(1));
+		assert!(pool.contains_unknown_block(&b0_hash));
+		assert!(pool.remove_peer_blocks(2).is_empty());
+	}
 }
@@ -1,18 +1,37 @@
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::collections::hash_map::Entry;
 use linked_hash_map::LinkedHashMap;
+use rand::{seq, thread_rng};
 use time;
 use chain::IndexedTransaction;
 use primitives::hash::H256;
+use ser::{Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use types::PeerIndex;
+
+/// Default maximal total size of orphaned transactions (in bytes)
+pub const DEFAULT_ORPHAN_TRANSACTIONS_MAX_SIZE: usize = 16 * 1024 * 1024;
+/// Default maximal total size of orphaned transactions from single peer (in bytes)
+pub const DEFAULT_ORPHAN_TRANSACTIONS_MAX_PEER_SIZE: usize = 1024 * 1024;
 
 #[derive(Debug)]
 /// Storage for transactions, for which we have no parent transactions yet.
 /// Transactions from this storage are either moved to verification queue, or removed at all.
+/// Total size of the pool and size of transactions from every peer are limited. When limit is
+/// exceeded, random transactions are evicted, so that single peer can not push out transactions of other peers.
 pub struct OrphanTransactionsPool {
 	/// Orphan transactions by hash.
 	by_hash: LinkedHashMap<H256, OrphanTransaction>,
 	/// Orphan transactions by parent' transaction hash
 	by_parent: HashMap<H256, HashSet<H256>>,
+	/// Orphan transactions by peer, which has sent them && their total size
+	by_peer: HashMap<PeerIndex, (HashSet<H256>, usize)>,
+	/// Peers, which have sent orphan transactions, by transaction hash
+	peer_by_hash: HashMap<H256, PeerIndex>,
+	/// Total size of orphan transactions
+	size: usize,
+	/// Maximal total size of orphan transactions
+	max_size: usize,
+	/// Maximal total size of orphan transactions from single peer
+	max_peer_size: usize,
 }
 
 #[derive(Debug)]
@@ -24,23 +43,44 @@ pub struct OrphanTransaction {
 	pub transaction: IndexedTransaction,
 	/// Parent transactions, which are still unknown to us
 	pub unknown_parents: HashSet<H256>,
+	/// Serialized size of the transaction
+	pub size: usize,
 }
 
 impl OrphanTransactionsPool {
-	/// Create new pool
+	/// Create new pool with default limits
 	pub fn new() -> Self {
+		OrphanTransactionsPool::with_limits(DEFAULT_ORPHAN_TRANSACTIONS_MAX_SIZE, DEFAULT_ORPHAN_TRANSACTIONS_MAX_PEER_SIZE)
+	}
+
+	/// Create new pool with given limits
+	pub fn with_limits(max_size: usize, max_peer_size: usize) -> Self {
 		OrphanTransactionsPool {
 			by_hash: LinkedHashMap::new(),
 			by_parent: HashMap::new(),
+			by_peer: HashMap::new(),
+			peer_by_hash: HashMap::new(),
+			size: 0,
+			max_size: max_size,
+			max_peer_size: max_peer_size,
 		}
 	}
 
-	#[cfg(test)]
 	/// Get total number of transactions in pool
 	pub fn len(&self) -> usize {
 		self.by_hash.len()
 	}
 
+	/// Get total size of transactions in pool
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// Get total size of transactions from given peer
+	pub fn peer_size(&self, peer_index: PeerIndex) -> usize {
+		self.by_peer.get(&peer_index).map(|&(_, size)| size).unwrap_or(0)
+	}
+
 	/// Get unknown transactions in the insertion order
 	pub fn transactions(&self) -> &LinkedHashMap<H256, OrphanTransaction> {
 		&self.by_hash
@@ -51,19 +91,35 @@ impl OrphanTransactionsPool {
 		self.by_hash.contains_key(hash)
 	}
 
-	/// Insert orphan transaction
-	pub fn insert(&mut self, transaction: IndexedTransaction, unknown_parents: HashSet<H256>) {
+	/// Insert orphan transaction, received from given peer.
+	/// Returns hashes of transactions, evicted from the pool to fit the limits. This includes
+	/// inserted transaction if it is too large to be stored.
+	pub fn insert(&mut self, transaction: IndexedTransaction, unknown_parents: HashSet<H256>, peer_index: Option<PeerIndex>) -> Vec<H256> {
 		assert!(!self.by_hash.contains_key(&transaction.hash));
 		assert!(unknown_parents.iter().all(|h| transaction.raw.inputs.iter().any(|i| &i.previous_output.hash == h)));
 
-		for unknown_parent in &unknown_parents {
+		let orphan = OrphanTransaction::new(transaction, unknown_parents);
+		if orphan.size > self.max_peer_size || orphan.size > self.max_size {
+			return vec![orphan.transaction.hash];
+		}
+
+		for unknown_parent in &orphan.unknown_parents {
 			self.by_parent.entry(unknown_parent.clone())
 				.or_insert_with(HashSet::new)
-				.insert(transaction.hash.clone());
+				.insert(orphan.transaction.hash.clone());
 		}
+		if let Some(peer_index) = peer_index {
+			let peer_orphans = self.by_peer.entry(peer_index).or_insert_with(|| (HashSet::new(), 0));
+			peer_orphans.0.insert(orphan.transaction.hash.clone());
+			peer_orphans.1 += orphan.size;
+			self.peer_by_hash.insert(orphan.transaction.hash.clone(), peer_index);
+		}
+		self.size += orphan.size;
+
+		let hash = orphan.transaction.hash.clone();
+		self.by_hash.insert(hash, orphan);
 
-		let hash = transaction.hash.clone();
-		self.by_hash.insert(hash, OrphanTransaction::new(transaction, unknown_parents));
+		self.evict(peer_index)
 	}
 
 	/// Remove all transactions, depending on this parent
@@ -76,25 +132,23 @@ impl OrphanTransactionsPool {
 		let mut removed_orphans: Vec<IndexedTransaction> = Vec::new();
 		while let Some(hash) = removal_queue.pop_front() {
 			// remove direct children of hash
-			let mut removed_orphans_hashes: Vec<H256> = Vec::new();
-			if let Entry::Occupied(children_entry) = self.by_parent.entry(hash.clone()) {
-				for child in children_entry.get() {
-					let all_parents_are_known = match self.by_hash.get_mut(child) {
-						Some(child_entry) => child_entry.remove_known_parent(&hash),
-						None => false,
-					};
-
-					if all_parents_are_known {
-						removed_orphans_hashes.push(child.clone());
-						removed_orphans.push(self.by_hash.remove(child).expect("checked couple of lines above").transaction);
-					}
+			let children = match self.by_parent.remove(&hash) {
+				Some(children) => children,
+				None => continue,
+			};
+
+			for child in children {
+				let all_parents_are_known = match self.by_hash.get_mut(&child) {
+					Some(child_entry) => child_entry.remove_known_parent(&hash),
+					None => false,
+				};
+
+				if all_parents_are_known {
+					removed_orphans.push(self.remove(&child).expect("checked couple of lines above").transaction);
+					// then also remove grandchildren of hash & so on
+					removal_queue.push_back(child);
 				}
-
-				children_entry.remove_entry();
 			}
-
-			// then also remove grandchildren of hash & so on
-			removal_queue.extend(removed_orphans_hashes);
 		}
 
 		removed_orphans
@@ -104,22 +158,106 @@ impl OrphanTransactionsPool {
 	pub fn remove_transactions(&mut self, hashes: &[H256]) -> Vec<IndexedTransaction> {
 		let mut removed: Vec<IndexedTransaction> = Vec::new();
 		for hash in hashes {
-			if let Some(transaction) = self.by_hash.remove(hash) {
+			if let Some(transaction) = self.remove(hash) {
 				removed.push(transaction.transaction);
 			}
 			removed.extend(self.remove_transactions_for_parent(hash));
 		}
 		removed
 	}
+
+	/// Remove all transactions, received from given peer
+	pub fn remove_peer_transactions(&mut self, peer_index: PeerIndex) -> Vec<H256> {
+		let hashes: Vec<H256> = match self.by_peer.get(&peer_index) {
+			Some(&(ref hashes, _)) => hashes.iter().cloned().collect(),
+			None => return Vec::new(),
+		};
+
+		for hash in &hashes {
+			self.remove(hash);
+		}
+		hashes
+	}
+
+	/// Evict random transactions until pool fits the limits.
+	/// Transactions of the peer, which has exceeded its quota, are evicted first.
+	/// Then transactions of the peer, which uses the most of the pool, are evicted.
+	fn evict(&mut self, peer_index: Option<PeerIndex>) -> Vec<H256> {
+		let mut evicted: Vec<H256> = Vec::new();
+		if let Some(peer_index) = peer_index {
+			while self.peer_size(peer_index) > self.max_peer_size {
+				let hash = self.random_transaction(Some(peer_index)).expect("peer size is non-zero; qed");
+				self.remove(&hash);
+				evicted.push(hash);
+			}
+		}
+
+		while self.size > self.max_size {
+			let largest_peer_index = self.by_peer.iter()
+				.max_by_key(|&(_, &(_, size))| size)
+				.map(|(peer_index, _)| *peer_index);
+			let hash = self.random_transaction(largest_peer_index).expect("pool size is non-zero; qed");
+			self.remove(&hash);
+			evicted.push(hash);
+		}
+
+		evicted
+	}
+
+	/// Select random transaction of given peer or random transaction of the pool
+	fn random_transaction(&self, peer_index: Option<PeerIndex>) -> Option<H256> {
+		let mut rng = thread_rng();
+		let selected = match peer_index.and_then(|peer_index| self.by_peer.get(&peer_index)) {
+			Some(&(ref hashes, _)) => seq::sample_iter(&mut rng, hashes.iter(), 1),
+			None => seq::sample_iter(&mut rng, self.by_hash.keys(), 1),
+		};
+		selected.ok().and_then(|selected| selected.into_iter().next().cloned())
+	}
+
+	/// Remove single transaction from the pool, leaving its children orphaned
+	fn remove(&mut self, hash: &H256) -> Option<OrphanTransaction> {
+		let orphan = self.by_hash.remove(hash)?;
+
+		for unknown_parent in &orphan.unknown_parents {
+			let is_last_child = match self.by_parent.get_mut(unknown_parent) {
+				Some(children) => {
+					children.remove(hash);
+					children.is_empty()
+				},
+				None => false,
+			};
+			if is_last_child {
+				self.by_parent.remove(unknown_parent);
+			}
+		}
+		if let Some(peer_index) = self.peer_by_hash.remove(hash) {
+			let is_last_peer_orphan = match self.by_peer.get_mut(&peer_index) {
+				Some(peer_orphans) => {
+					peer_orphans.0.remove(hash);
+					peer_orphans.1 -= orphan.size;
+					peer_orphans.0.is_empty()
+				},
+				None => false,
+			};
+			if is_last_peer_orphan {
+				self.by_peer.remove(&peer_index);
+			}
+		}
+		self.size -= orphan.size;
+
+		Some(orphan)
+	}
 }
 
 impl OrphanTransaction {
 	/// Create new orphaned transaction
 	pub fn new(transaction: IndexedTransaction, unknown_parents: HashSet<H256>) -> Self {
+		let size = transaction.raw.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS);
 		OrphanTransaction {
 			insertion_time: time::precise_time_s(),
 			transaction: transaction,
 			unknown_parents: unknown_parents,
+			size: size,
 		}
 	}
 
@@ -136,9 +274,17 @@ mod tests {
 
 	use std::collections::HashSet;
 	use self::test_data::{TransactionBuilder, ChainBuilder};
+	use chain::IndexedTransaction;
 	use primitives::hash::H256;
+	use ser::Serializable;
 	use super::OrphanTransactionsPool;
 
+	fn orphan(index: u32) -> (IndexedTransaction, HashSet<H256>) {
+		let transaction: IndexedTransaction = TransactionBuilder::with_default_input(index).into();
+		let unknown_parents = transaction.raw.inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+		(transaction, unknown_parents)
+	}
+
 	#[test]
 	fn orphan_transaction_pool_empty_on_start() {
 		let pool = OrphanTransactionsPool::new();
@@ -158,9 +304,9 @@ mod tests {
 		let t5_unknown: HashSet<H256> = chain.at(4).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
 
 		let mut pool = OrphanTransactionsPool::new();
-		pool.insert(chain.at(1).into(), t2_unknown); // t2
-		pool.insert(chain.at(2).into(), t3_unknown); // t3
-		pool.insert(chain.at(4).into(), t5_unknown); // t5
+		pool.insert(chain.at(1).into(), t2_unknown, None); // t2
+		pool.insert(chain.at(2).into(), t3_unknown, None); // t3
+		pool.insert(chain.at(4).into(), t5_unknown, None); // t5
 		assert_eq!(pool.len(), 3);
 
 		let removed = pool.remove_transactions_for_parent(&chain.at(0).hash());
@@ -190,10 +336,10 @@ mod tests {
 		let t7_unknown: HashSet<H256> = chain.at(6).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
 
 		let mut pool = OrphanTransactionsPool::new();
-		pool.insert(chain.at(1).into(), t2_unknown); // t2
-		pool.insert(chain.at(2).into(), t3_unknown); // t3
-		pool.insert(chain.at(4).into(), t5_unknown); // t5
-		pool.insert(chain.at(6).into(), t7_unknown); // t7
+		pool.insert(chain.at(1).into(), t2_unknown, None); // t2
+		pool.insert(chain.at(2).into(), t3_unknown, None); // t3
+		pool.insert(chain.at(4).into(), t5_unknown, None); // t5
+		pool.insert(chain.at(6).into(), t7_unknown, None); // t7
 		assert_eq!(pool.len(), 4);
 
 		let removed = pool.remove_transactions(&vec![chain.at(1).hash(), chain.at(3).hash()]);
@@ -217,9 +363,86 @@ mod tests {
 		let t3_unknown: HashSet<H256> = chain.at(2).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
 
 		let mut pool = OrphanTransactionsPool::new();
-		pool.insert(chain.at(1).into(), t2_unknown); // t2
-		pool.insert(chain.at(2).into(), t3_unknown); // t3
+		pool.insert(chain.at(1).into(), t2_unknown, None); // t2
+		pool.insert(chain.at(2).into(), t3_unknown, None); // t3
 
 		pool.remove_transactions(&[chain.at(2).hash(), chain.at(1).hash()]);
 	}
+
+	#[test]
+	fn orphan_transaction_pool_evicts_transactions_of_peer_over_quota() {
+		let (transaction, _) = orphan(0);
+		let size = transaction.raw.serialized_size();
+
+		let mut pool = OrphanTransactionsPool::with_limits(100 * size, 2 * size);
+		let (t0, t0_unknown) = orphan(0);
+		pool.insert(t0.clone(), t0_unknown, Some(2));
+		for index in 1..10 {
+			let (transaction, unknown_parents) = orphan(index);
+			pool.insert(transaction, unknown_parents, Some(1));
+		}
+
+		assert_eq!(pool.len(), 3);
+		assert_eq!(pool.peer_size(1), 2 * size);
+		assert_eq!(pool.peer_size(2), size);
+		assert!(pool.contains(&t0.hash));
+	}
+
+	#[test]
+	fn orphan_transaction_pool_evicts_transactions_of_largest_peer() {
+		let (transaction, _) = orphan(0);
+		let size = transaction.raw.serialized_size();
+
+		let mut pool = OrphanTransactionsPool::with_limits(4 * size, 3 * size);
+		let (t0, t0_unknown) = orphan(0);
+		pool.insert(t0.clone(), t0_unknown, Some(2));
+		for index in 1..4 {
+			let (transaction, unknown_parents) = orphan(index);
+			assert!(pool.insert(transaction, unknown_parents, Some(1)).is_empty());
+		}
+
+		let (t4, t4_unknown) = orphan(4);
+		let evicted = pool.insert(t4.clone(), t4_unknown, Some(3));
+		assert_eq!(evicted.len(), 1);
+		assert!(evicted[0] != t0.hash && evicted[0] != t4.hash);
+		assert_eq!(pool.size(), 4 * size);
+		assert_eq!(pool.peer_size(1), 2 * size);
+		assert!(pool.contains(&t0.hash));
+		assert!(pool.contains(&t4.hash));
+	}
+
+	#[test]
+	fn orphan_transaction_pool_rejects_too_large_transaction() {
+		let (transaction, unknown_parents) = orphan(0);
+		let size = transaction.raw.serialized_size();
+
+		let mut pool = OrphanTransactionsPool::with_limits(100 * size, size - 1);
+		assert_eq!(pool.insert(transaction.clone(), unknown_parents, Some(1)), vec![transaction.hash]);
+		assert_eq!(pool.len(), 0);
+		assert_eq!(pool.size(), 0);
+	}
+
+	#[test]
+	fn orphan_transaction_pool_remove_peer_transactions() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(100).store(chain)			// t1
+			.into_input(0).add_output(200).store(chain)				// t1 -> t2
+			.into_input(0).add_output(300).store(chain);			// t1 -> t2 -> t3
+		let t2_unknown: HashSet<H256> = chain.at(1).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+		let t3_unknown: HashSet<H256> = chain.at(2).inputs.iter().map(|i| i.previous_output.hash.clone()).collect();
+
+		let mut pool = OrphanTransactionsPool::new();
+		pool.insert(chain.at(1).into(), t2_unknown, Some(1)); // t2
+		pool.insert(chain.at(2).into(), t3_unknown, Some(2)); // t3
+
+		assert_eq!(pool.remove_peer_transactions(1), vec![chain.at(1).hash()]);
+		assert_eq!(pool.len(), 1);
+		assert_eq!(pool.peer_size(1), 0);
+		assert_eq!(pool.size(), pool.peer_size(2));
+
+		// t3 is still waiting for t2
+		let removed: Vec<H256> = pool.remove_transactions_for_parent(&chain.at(1).hash()).into_iter().map(|tx| tx.hash).collect();
+		assert_eq!(removed, vec![chain.at(2).hash()]);
+		assert_eq!(pool.size(), 0);
+	}
 }
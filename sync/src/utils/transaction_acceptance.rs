@@ -0,0 +1,311 @@
+use std::fmt;
+use chain::{IndexedTransaction, Transaction, TransactionOutput, OutPoint};
+use message::types::reject::RejectCode;
+use miner::transaction_fee;
+use primitives::hash::H256;
+use storage::TransactionOutputProvider;
+use time::get_time;
+use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Policy, PolicyError, PackageError, TransactionError,
+	check_package, transaction_vsize};
+use types::{BlockHeight, StorageRef, MemoryPoolRef};
+use super::MemoryPoolTransactionOutputProvider;
+
+/// Reason of the transaction rejection, as it is reported to peers && RPC clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionRejection {
+	/// Reject code of the `reject` message
+	pub code: RejectCode,
+	/// Short rejection reason
+	pub reason: String,
+}
+
+/// Result of the memory pool acceptance check
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionAcceptance {
+	/// Transaction hash
+	pub hash: H256,
+	/// Transaction witness hash
+	pub witness_hash: H256,
+	/// Transaction virtual size
+	pub vsize: usize,
+	/// Transaction fee, if inputs are known
+	pub fee: Option<u64>,
+	/// Acceptance result
+	pub result: Result<(), TransactionRejection>,
+}
+
+/// Result of the memory pool acceptance check of the package
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageAcceptance {
+	/// Acceptance of every package transaction
+	pub transactions: Vec<TransactionAcceptance>,
+	/// Acceptance result of the whole package
+	pub result: Result<(), TransactionRejection>,
+}
+
+/// Transaction output provider, which also looks into outputs of preceding package transactions
+struct PackageTransactionOutputProvider<'a> {
+	/// Preceding package transactions
+	package: &'a [IndexedTransaction],
+	/// Storage && memory pool provider
+	memory_pool_provider: MemoryPoolTransactionOutputProvider,
+}
+
+impl TransactionRejection {
+	pub fn new(code: RejectCode, reason: String) -> Self {
+		TransactionRejection {
+			code: code,
+			reason: reason,
+		}
+	}
+}
+
+impl fmt::Display for TransactionRejection {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.reason)
+	}
+}
+
+impl From<TransactionError> for TransactionRejection {
+	fn from(error: TransactionError) -> Self {
+		let (code, reason) = match error {
+			TransactionError::Empty => (RejectCode::Invalid, "bad-txns-empty".to_owned()),
+			TransactionError::NullNonCoinbase => (RejectCode::Invalid, "bad-txns-prevout-null".to_owned()),
+			TransactionError::CoinbaseSignatureLength(_) => (RejectCode::Invalid, "bad-cb-length".to_owned()),
+			TransactionError::MaxSize => (RejectCode::Invalid, "bad-txns-oversize".to_owned()),
+			TransactionError::MinSize => (RejectCode::Invalid, "tx-size-small".to_owned()),
+			TransactionError::MaxSigops |
+			TransactionError::Sigops(_) |
+			TransactionError::SigopsP2SH(_) => (RejectCode::Invalid, "bad-txns-too-many-sigops".to_owned()),
+			TransactionError::MemoryPoolCoinbase |
+			TransactionError::MisplacedCoinbase => (RejectCode::Invalid, "coinbase".to_owned()),
+			TransactionError::Input(_) |
+			TransactionError::UnknownReference(_) => (RejectCode::Invalid, "bad-txns-inputs-missingorspent".to_owned()),
+			TransactionError::Maturity => (RejectCode::Invalid, "bad-txns-premature-spend-of-coinbase".to_owned()),
+			TransactionError::Signature(index, error) => (RejectCode::Invalid,
+				format!("mandatory-script-verify-flag-failed (input {}: {:?})", index, error)),
+			TransactionError::Overspend => (RejectCode::Invalid, "bad-txns-in-belowout".to_owned()),
+			TransactionError::SignatureMallformed(_) => (RejectCode::Invalid, "bad-txns-scriptsig-malformed".to_owned()),
+			TransactionError::UnspentTransactionWithTheSameHash => (RejectCode::Duplicate, "txn-already-known".to_owned()),
+			TransactionError::UsingSpentOutput(_, _) => (RejectCode::Duplicate, "txn-mempool-conflict".to_owned()),
+			TransactionError::ReturnReplayProtection => (RejectCode::Invalid, "bad-txns-replay-protected".to_owned()),
+			TransactionError::PrematureWitness => (RejectCode::Nonstandard, "no-witness-yet".to_owned()),
+		};
+		TransactionRejection::new(code, reason)
+	}
+}
+
+impl From<PolicyError> for TransactionRejection {
+	fn from(error: PolicyError) -> Self {
+		match error {
+			PolicyError::Dust(_) => TransactionRejection::new(RejectCode::Dust, error.reason().to_owned()),
+			PolicyError::MinRelayFee { fee, required } => TransactionRejection::new(RejectCode::InsuficientFee,
+				format!("{}, {} < {}", error.reason(), fee, required)),
+			_ => TransactionRejection::new(RejectCode::Nonstandard, error.reason().to_owned()),
+		}
+	}
+}
+
+impl From<PackageError> for TransactionRejection {
+	fn from(error: PackageError) -> Self {
+		TransactionRejection::new(RejectCode::Nonstandard, error.reason().to_owned())
+	}
+}
+
+impl TransactionAcceptance {
+	/// Acceptance of the transaction, which has passed all checks, but the fee is unknown
+	pub fn new(transaction: &IndexedTransaction) -> Self {
+		TransactionAcceptance {
+			hash: transaction.hash.clone(),
+			witness_hash: transaction.raw.witness_hash(),
+			vsize: transaction_vsize(&transaction.raw),
+			fee: None,
+			result: Ok(()),
+		}
+	}
+
+	/// Returns true if transaction could be accepted to the memory pool
+	pub fn is_allowed(&self) -> bool {
+		self.result.is_ok()
+	}
+}
+
+impl PackageAcceptance {
+	/// Acceptance of the package, which transactions are not yet checked
+	pub fn new(package: &[IndexedTransaction]) -> Self {
+		PackageAcceptance {
+			transactions: package.iter()
+				.map(|transaction| TransactionAcceptance {
+					result: Err(TransactionRejection::new(RejectCode::Invalid, "package-not-validated".to_owned())),
+					..TransactionAcceptance::new(transaction)
+				})
+				.collect(),
+			result: Ok(()),
+		}
+	}
+
+	/// Returns true if package could be accepted to the memory pool
+	pub fn is_allowed(&self) -> bool {
+		self.result.is_ok()
+	}
+}
+
+impl<'a> TransactionOutputProvider for PackageTransactionOutputProvider<'a> {
+	fn transaction_output(&self, prevout: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
+		self.package.iter()
+			.find(|transaction| transaction.hash == prevout.hash)
+			.and_then(|transaction| transaction.raw.outputs.get(prevout.index as usize).cloned())
+			.or_else(|| self.memory_pool_provider.transaction_output(prevout, transaction_index))
+	}
+
+	fn is_spent(&self, prevout: &OutPoint) -> bool {
+		// package transactions are not spending the same outputs, it is checked in `check_package`
+		self.memory_pool_provider.is_spent(prevout)
+	}
+}
+
+/// Checks if transaction could be accepted to the memory pool at given height.
+/// Relay policy is checked first, so that cheap checks fail before scripts are verified.
+pub fn check_transaction_acceptance(
+	storage: &StorageRef,
+	memory_pool: &MemoryPoolRef,
+	verifier: &ChainVerifier,
+	policy: &Policy,
+	height: BlockHeight,
+	transaction: &IndexedTransaction,
+) -> TransactionAcceptance {
+	let mut acceptance = TransactionAcceptance::new(transaction);
+
+	if let Err(error) = policy.check_transaction(&transaction.raw) {
+		acceptance.result = Err(error.into());
+		return acceptance;
+	}
+
+	// output provider must check previous outputs in both storage && memory pool
+	let tx_output_provider = match MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), memory_pool, &transaction.raw) {
+		Ok(tx_output_provider) => tx_output_provider,
+		Err(error) => {
+			acceptance.result = Err(error.into());
+			return acceptance;
+		},
+	};
+
+	if let Err(rejection) = check_transaction_inputs(storage, verifier, policy, height, transaction, &tx_output_provider) {
+		acceptance.result = Err(rejection);
+		return acceptance;
+	}
+
+	let fee = transaction_fee(&tx_output_provider, &transaction.raw);
+	acceptance.fee = Some(fee);
+	acceptance.result = policy.check_fee(fee, acceptance.vsize).map_err(Into::into);
+	acceptance
+}
+
+/// Checks if package could be accepted to the memory pool at given height.
+/// Every transaction is checked separately, except for the fee, which is checked for the whole package.
+/// Transactions, which are already in the memory pool, are skipped.
+pub fn check_package_acceptance(
+	storage: &StorageRef,
+	memory_pool: &MemoryPoolRef,
+	verifier: &ChainVerifier,
+	policy: &Policy,
+	height: BlockHeight,
+	package: &[IndexedTransaction],
+) -> PackageAcceptance {
+	let mut acceptance = PackageAcceptance::new(package);
+
+	let raw_package: Vec<Transaction> = package.iter().map(|transaction| transaction.raw.clone()).collect();
+	if let Err(error) = check_package(&raw_package) {
+		acceptance.result = Err(error.into());
+		return acceptance;
+	}
+
+	let (mut package_fee, mut package_vsize) = (0, 0);
+	for (index, transaction) in package.iter().enumerate() {
+		if memory_pool.read().contains(&transaction.hash) {
+			acceptance.transactions[index].result = Ok(());
+			continue;
+		}
+
+		match check_package_transaction(storage, memory_pool, verifier, policy, height, &package[..index], transaction) {
+			Ok(fee) => {
+				acceptance.transactions[index].fee = Some(fee);
+				acceptance.transactions[index].result = Ok(());
+				package_fee += fee;
+				package_vsize += acceptance.transactions[index].vsize;
+			},
+			Err(rejection) => {
+				acceptance.transactions[index].result = Err(rejection.clone());
+				acceptance.result = Err(rejection);
+				return acceptance;
+			},
+		}
+	}
+
+	// low fee parent could be accepted if its child pays enough for both
+	if let Err(error) = policy.check_fee(package_fee, package_vsize) {
+		let rejection: TransactionRejection = error.into();
+		for transaction_acceptance in acceptance.transactions.iter_mut().filter(|acceptance| acceptance.fee.is_some()) {
+			transaction_acceptance.result = Err(rejection.clone());
+		}
+		acceptance.result = Err(rejection);
+	}
+
+	acceptance
+}
+
+/// Checks package transaction, spending outputs of preceding package transactions. Returns transaction fee.
+fn check_package_transaction(
+	storage: &StorageRef,
+	memory_pool: &MemoryPoolRef,
+	verifier: &ChainVerifier,
+	policy: &Policy,
+	height: BlockHeight,
+	preceding_transactions: &[IndexedTransaction],
+	transaction: &IndexedTransaction,
+) -> Result<u64, TransactionRejection> {
+	policy.check_transaction(&transaction.raw)?;
+
+	let tx_output_provider = PackageTransactionOutputProvider {
+		package: preceding_transactions,
+		memory_pool_provider: MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), memory_pool, &transaction.raw)?,
+	};
+	check_transaction_inputs(storage, verifier, policy, height, transaction, &tx_output_provider)?;
+
+	Ok(transaction_fee(&tx_output_provider, &transaction.raw))
+}
+
+/// Verifies transaction, spending given outputs, and checks standardness of the spent outputs.
+fn check_transaction_inputs<T>(
+	storage: &StorageRef,
+	verifier: &ChainVerifier,
+	policy: &Policy,
+	height: BlockHeight,
+	transaction: &IndexedTransaction,
+	tx_output_provider: &T,
+) -> Result<(), TransactionRejection> where T: TransactionOutputProvider {
+	let time: u32 = get_time().sec as u32;
+	verifier.verify_mempool_transaction(storage.as_block_header_provider(), tx_output_provider, height, time, transaction)?;
+	policy.check_inputs(&transaction.raw, tx_output_provider)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use message::types::reject::RejectCode;
+	use verification::{PolicyError, TransactionError};
+	use super::TransactionRejection;
+
+	#[test]
+	fn transaction_rejection_reasons() {
+		assert_eq!(TransactionRejection::from(TransactionError::Input(0)),
+			TransactionRejection::new(RejectCode::Invalid, "bad-txns-inputs-missingorspent".into()));
+		assert_eq!(TransactionRejection::from(TransactionError::UsingSpentOutput(Default::default(), 0)),
+			TransactionRejection::new(RejectCode::Duplicate, "txn-mempool-conflict".into()));
+		assert_eq!(TransactionRejection::from(PolicyError::Dust(1)),
+			TransactionRejection::new(RejectCode::Dust, "dust".into()));
+		assert_eq!(TransactionRejection::from(PolicyError::MinRelayFee { fee: 100, required: 226 }),
+			TransactionRejection::new(RejectCode::InsuficientFee, "min relay fee not met, 100 < 226".into()));
+		assert_eq!(TransactionRejection::from(PolicyError::ScriptPubKey(0)).to_string(), "scriptpubkey");
+	}
+}
@@ -1,3 +1,4 @@
+use rayon::ThreadPool;
 use rayon::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator};
 use storage::{
 	DuplexTransactionOutputProvider, TransactionOutputProvider, TransactionMetaProvider,
@@ -10,12 +11,15 @@ use accept_block::BlockAcceptor;
 use accept_header::HeaderAcceptor;
 use accept_transaction::TransactionAcceptor;
 use deployments::BlockDeployments;
+use cache::VerificationCache;
 use VerificationLevel;
 
 pub struct ChainAcceptor<'a> {
 	pub block: BlockAcceptor<'a>,
 	pub header: HeaderAcceptor<'a>,
 	pub transactions: Vec<TransactionAcceptor<'a>>,
+	/// Pool, verifying scripts of the block transactions. Global pool is used if None.
+	script_pool: Option<&'a ThreadPool>,
 }
 
 impl<'a> ChainAcceptor<'a> {
@@ -29,6 +33,8 @@ impl<'a> ChainAcceptor<'a> {
 		height: u32,
 		median_time_past: u32,
 		deployments: &'a BlockDeployments,
+		cache: &'a VerificationCache,
+		script_pool: Option<&'a ThreadPool>,
 	) -> Self {
 		trace!(target: "verification", "Block verification {}", block.hash().to_reversed_str());
 		let output_store = DuplexTransactionOutputProvider::new(tx_out_provider, block.raw());
@@ -59,8 +65,10 @@ impl<'a> ChainAcceptor<'a> {
 						median_time_past,
 						tx_index,
 						deployments,
+						cache,
 				))
 				.collect(),
+			script_pool: script_pool,
 		}
 	}
 
@@ -68,7 +76,11 @@ impl<'a> ChainAcceptor<'a> {
 		self.block.check()?;
 		self.header.check()?;
 		self.check_transactions()?;
-		Ok(())
+		let transactions = &self.transactions;
+		match self.script_pool {
+			Some(pool) => pool.install(|| check_scripts(transactions)),
+			None => check_scripts(transactions),
+		}
 	}
 
 	fn check_transactions(&self) -> Result<(), Error> {
@@ -78,3 +90,25 @@ impl<'a> ChainAcceptor<'a> {
 			.reduce(|| Ok(()), |acc, check| acc.and(check))
 	}
 }
+
+/// Verifies scripts of all inputs concurrently, stopping at the first failure. Error of the first
+/// failing input of the first failing transaction is returned, whatever the number of threads is.
+fn check_scripts(transactions: &[TransactionAcceptor]) -> Result<(), Error> {
+	let signers: Vec<_> = transactions.par_iter()
+		.map(|tx| tx.eval.signer())
+		.collect();
+	let inputs: Vec<_> = signers.iter()
+		.enumerate()
+		.filter_map(|(tx_index, signer)| signer.as_ref().map(|signer| (tx_index, signer.inputs.len())))
+		.flat_map(|(tx_index, inputs_len)| (0..inputs_len).map(move |input_index| (tx_index, input_index)))
+		.collect();
+
+	inputs.par_iter()
+		.map(|&(tx_index, input_index)| {
+			let signer = signers[tx_index].as_ref().expect("inputs are only collected for transactions with signers; qed");
+			transactions[tx_index].eval.check_input(signer, input_index)
+				.map_err(|err| Error::Transaction(tx_index, err))
+		})
+		.find_first(Result::is_err)
+		.unwrap_or(Ok(()))
+}
@@ -4,8 +4,10 @@ use ser::Serializable;
 use storage::{TransactionMetaProvider, TransactionOutputProvider, DuplexTransactionOutputProvider,
 	transaction_index_for_output_check};
 use network::{ConsensusParams, ConsensusFork};
-use script::{Script, verify_script, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, SignatureVersion};
+use script::{Script, verify_script_traced, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, SignatureVersion,
+	ScriptTracer, NoopTracer};
 use deployments::BlockDeployments;
+use cache::{VerificationCache, CachingSignatureChecker};
 use script::Builder;
 use sigops::transaction_sigops;
 use canon::CanonTransaction;
@@ -41,6 +43,7 @@ impl<'a> TransactionAcceptor<'a> {
 		median_time_past: u32,
 		transaction_index: usize,
 		deployments: &'a BlockDeployments<'a>,
+		cache: &'a VerificationCache,
 	) -> Self {
 		trace!(target: "verification", "Tx verification {}", transaction.hash.to_reversed_str());
 		let tx_ordering = consensus.fork.transaction_ordering(median_time_past);
@@ -54,10 +57,12 @@ impl<'a> TransactionAcceptor<'a> {
 			overspent: TransactionOverspent::new(transaction, output_store),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
 			return_replay_protection: TransactionReturnReplayProtection::new(transaction, consensus, height),
-			eval: TransactionEval::new(transaction, output_store, consensus, verification_level, height, time, median_time_past, deployments),
+			eval: TransactionEval::new(transaction, output_store, consensus, verification_level, height, time, median_time_past, deployments, cache, false),
 		}
 	}
 
+	/// Checks everything, except transaction scripts. Scripts of all block transactions
+	/// are verified concurrently by `ChainAcceptor`, when all other checks have passed.
 	pub fn check(&self) -> Result<(), TransactionError> {
 		self.size.check()?;
 		self.premature_witness.check()?;
@@ -67,7 +72,6 @@ impl<'a> TransactionAcceptor<'a> {
 		self.overspent.check()?;
 		self.double_spent.check()?;
 		self.return_replay_protection.check()?;
-		self.eval.check()?;
 		Ok(())
 	}
 }
@@ -95,6 +99,7 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 		time: u32,
 		median_time_past: u32,
 		deployments: &'a BlockDeployments<'a>,
+		cache: &'a VerificationCache,
 	) -> Self {
 		trace!(target: "verification", "Mempool-Tx verification {}", transaction.hash.to_reversed_str());
 		let transaction_index = 0;
@@ -107,7 +112,7 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 			sigops: TransactionSigops::new(transaction, output_store, consensus, max_block_sigops, time),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
 			return_replay_protection: TransactionReturnReplayProtection::new(transaction, consensus, height),
-			eval: TransactionEval::new(transaction, output_store, consensus, VerificationLevel::Full, height, time, median_time_past, deployments),
+			eval: TransactionEval::new(transaction, output_store, consensus, VerificationLevel::Full, height, time, median_time_past, deployments, cache, true),
 		}
 	}
 
@@ -311,6 +316,9 @@ pub struct TransactionEval<'a> {
 	verify_sigpushonly: bool,
 	verify_cleanstack: bool,
 	signature_version: SignatureVersion,
+	cache: &'a VerificationCache,
+	/// Remember successfully verified signatures and scripts? Only memory pool transactions are cached.
+	cache_results: bool,
 }
 
 impl<'a> TransactionEval<'a> {
@@ -323,6 +331,8 @@ impl<'a> TransactionEval<'a> {
 		time: u32,
 		median_timestamp: u32,
 		deployments: &'a BlockDeployments,
+		cache: &'a VerificationCache,
+		cache_results: bool,
 	) -> Self {
 		let verify_p2sh = time >= params.bip16_time;
 		let verify_strictenc = match params.fork {
@@ -366,64 +376,109 @@ impl<'a> TransactionEval<'a> {
 			verify_sigpushonly: verify_sigpushonly,
 			verify_cleanstack: verify_cleanstack,
 			signature_version: signature_version,
+			cache: cache,
+			cache_results: cache_results,
 		}
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
+		let signer = match self.signer() {
+			Some(signer) => signer,
+			None => return Ok(()),
+		};
+
+		for index in 0..signer.inputs.len() {
+			self.check_input(&signer, index)?;
+		}
+
+		if self.cache_results {
+			self.cache.scripts.insert(self.transaction.raw.witness_hash(), self.flags());
+		}
+
+		Ok(())
+	}
+
+	/// Returns signer, shared by all transaction inputs, or None if transaction scripts are not checked
+	pub fn signer(&self) -> Option<TransactionInputSigner> {
 		if self.verification_level == VerificationLevel::Header
 			|| self.verification_level == VerificationLevel::NoVerification {
-			return Ok(());
+			return None;
 		}
 
 		if self.transaction.raw.is_coinbase() {
-			return Ok(());
+			return None;
+		}
+
+		// scripts of the memory pool transaction are not verified again, when block is verified
+		if self.cache.scripts.contains(&self.transaction.raw.witness_hash(), &self.flags()) {
+			return None;
+		}
+
+		Some(self.transaction.raw.clone().into())
+	}
+
+	/// Verifies script of the transaction input
+	pub fn check_input(&self, signer: &TransactionInputSigner, index: usize) -> Result<(), TransactionError> {
+		self.verify_input(signer, &self.flags(), index, self.cache_results, &mut NoopTracer)
+	}
+
+	/// Verifies script of the transaction input, reporting every evaluated opcode to the tracer
+	pub fn trace_input(&self, index: usize, tracer: &mut dyn ScriptTracer) -> Result<(), TransactionError> {
+		if index >= self.transaction.raw.inputs.len() {
+			return Err(TransactionError::Input(index));
 		}
 
 		let signer: TransactionInputSigner = self.transaction.raw.clone().into();
+		self.verify_input(&signer, &self.flags(), index, false, tracer)
+	}
 
-		let mut checker = TransactionSignatureChecker {
+	fn verify_input<T: ScriptTracer + ?Sized>(
+		&self,
+		signer: &TransactionInputSigner,
+		flags: &VerificationFlags,
+		index: usize,
+		cache_results: bool,
+		tracer: &mut T,
+	) -> Result<(), TransactionError> {
+		let input = &self.transaction.raw.inputs[index];
+		let output = self.store.transaction_output(&input.previous_output, usize::max_value())
+			.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
+
+		let checker = CachingSignatureChecker::new(TransactionSignatureChecker {
 			signer: signer,
-			input_index: 0,
-			input_amount: 0,
-		};
+			input_index: index,
+			input_amount: output.value,
+		}, &self.cache.signatures, cache_results);
 
-		for (index, input) in self.transaction.raw.inputs.iter().enumerate() {
-			let output = self.store.transaction_output(&input.previous_output, usize::max_value())
-				.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
-
-			checker.input_index = index;
-			checker.input_amount = output.value;
-
-			let script_witness = &input.script_witness;
-			let input: Script = input.script_sig.clone().into();
-			let output: Script = output.script_pubkey.into();
-
-			let flags = VerificationFlags::default()
-				.verify_p2sh(self.verify_p2sh)
-				.verify_strictenc(self.verify_strictenc)
-				.verify_locktime(self.verify_locktime)
-				.verify_checksequence(self.verify_checksequence)
-				.verify_dersig(self.verify_dersig)
-				.verify_nulldummy(self.verify_nulldummy)
-				.verify_witness(self.verify_witness)
-				.verify_concat(self.verify_monolith_opcodes)
-				.verify_split(self.verify_monolith_opcodes)
-				.verify_and(self.verify_monolith_opcodes)
-				.verify_or(self.verify_monolith_opcodes)
-				.verify_xor(self.verify_monolith_opcodes)
-				.verify_div(self.verify_monolith_opcodes)
-				.verify_mod(self.verify_monolith_opcodes)
-				.verify_bin2num(self.verify_monolith_opcodes)
-				.verify_num2bin(self.verify_monolith_opcodes)
-				.verify_checkdatasig(self.verify_magnetic_anomaly_opcodes)
-				.verify_sigpushonly(self.verify_sigpushonly)
-				.verify_cleanstack(self.verify_cleanstack);
-
-			verify_script(&input, &output, &script_witness, &flags, &checker, self.signature_version)
-				.map_err(|e| TransactionError::Signature(index, e))?;
-		}
+		let script_witness = &input.script_witness;
+		let input: Script = input.script_sig.clone().into();
+		let output: Script = output.script_pubkey.into();
 
-		Ok(())
+		verify_script_traced(&input, &output, &script_witness, flags, &checker, self.signature_version, tracer)
+			.map_err(|e| TransactionError::Signature(index, e))
+	}
+
+	fn flags(&self) -> VerificationFlags {
+		VerificationFlags::default()
+			.verify_p2sh(self.verify_p2sh)
+			.verify_strictenc(self.verify_strictenc)
+			.verify_locktime(self.verify_locktime)
+			.verify_checksequence(self.verify_checksequence)
+			.verify_dersig(self.verify_dersig)
+			.verify_nulldummy(self.verify_nulldummy)
+			.verify_witness(self.verify_witness)
+			.verify_concat(self.verify_monolith_opcodes)
+			.verify_split(self.verify_monolith_opcodes)
+			.verify_and(self.verify_monolith_opcodes)
+			.verify_or(self.verify_monolith_opcodes)
+			.verify_xor(self.verify_monolith_opcodes)
+			.verify_div(self.verify_monolith_opcodes)
+			.verify_mod(self.verify_monolith_opcodes)
+			.verify_bin2num(self.verify_monolith_opcodes)
+			.verify_num2bin(self.verify_monolith_opcodes)
+			.verify_checkdatasig(self.verify_magnetic_anomaly_opcodes)
+			.verify_sigpushonly(self.verify_sigpushonly)
+			.verify_cleanstack(self.verify_cleanstack)
 	}
 }
 
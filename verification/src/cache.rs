@@ -0,0 +1,206 @@
+//! Caches of the successful script verification results.
+//!
+//! Transactions are verified twice: when they're accepted to the memory pool and when
+//! block, containing these transactions, is verified. Results of the first verification
+//! are cached, so that block verification could skip the most expensive checks.
+
+use lru_cache::LruCache;
+use parking_lot::Mutex;
+use rand::random;
+use keys::{Public, Signature, Message};
+use hash::H256;
+use crypto::{DHash256, Digest};
+use script::{Script, Num, SignatureChecker, SignatureVersion, TransactionSignatureChecker, TransactionInputSigner, VerificationFlags};
+
+/// Default number of cached valid signatures
+pub const DEFAULT_SIGNATURE_CACHE_SIZE: usize = 100_000;
+/// Default number of cached transactions with valid scripts
+pub const DEFAULT_SCRIPT_CACHE_SIZE: usize = 20_000;
+
+/// Bounded cache of valid (message, public key, signature) triples.
+///
+/// Entries are keyed by the salted hash of the triple, so the attacker is unable to
+/// predict keys of cached entries.
+pub struct SignatureCache {
+	salt: H256,
+	entries: Mutex<LruCache<H256, ()>>,
+}
+
+impl SignatureCache {
+	pub fn new(capacity: usize) -> Self {
+		SignatureCache {
+			salt: random::<[u8; 32]>().into(),
+			entries: Mutex::new(LruCache::new(capacity)),
+		}
+	}
+
+	/// Returns true if signature has been verified before
+	pub fn contains(&self, signature: &Signature, public: &Public, message: &Message) -> bool {
+		let key = self.key(signature, public, message);
+		self.entries.lock().contains_key(&key)
+	}
+
+	/// Remembers valid signature
+	pub fn insert(&self, signature: &Signature, public: &Public, message: &Message) {
+		let key = self.key(signature, public, message);
+		self.entries.lock().insert(key, ());
+	}
+
+	fn key(&self, signature: &Signature, public: &Public, message: &Message) -> H256 {
+		let mut hasher = DHash256::new();
+		hasher.input(&*self.salt);
+		hasher.input(&**message);
+		hasher.input(&**public);
+		hasher.input(&**signature);
+		hasher.finish()
+	}
+}
+
+/// Bounded cache of transactions, which scripts were valid under given verification flags.
+pub struct ScriptCache {
+	entries: Mutex<LruCache<(H256, VerificationFlags), ()>>,
+}
+
+impl ScriptCache {
+	pub fn new(capacity: usize) -> Self {
+		ScriptCache {
+			entries: Mutex::new(LruCache::new(capacity)),
+		}
+	}
+
+	/// Returns true if all scripts of the transaction with given witness hash were valid under given flags
+	pub fn contains(&self, witness_hash: &H256, flags: &VerificationFlags) -> bool {
+		self.entries.lock().contains_key(&(witness_hash.clone(), flags.clone()))
+	}
+
+	/// Remembers transaction with valid scripts
+	pub fn insert(&self, witness_hash: H256, flags: VerificationFlags) {
+		self.entries.lock().insert((witness_hash, flags), ());
+	}
+}
+
+/// Caches, shared by memory pool and block verification
+pub struct VerificationCache {
+	pub signatures: SignatureCache,
+	pub scripts: ScriptCache,
+}
+
+impl VerificationCache {
+	pub fn new(signature_cache_size: usize, script_cache_size: usize) -> Self {
+		VerificationCache {
+			signatures: SignatureCache::new(signature_cache_size),
+			scripts: ScriptCache::new(script_cache_size),
+		}
+	}
+}
+
+impl Default for VerificationCache {
+	fn default() -> Self {
+		VerificationCache::new(DEFAULT_SIGNATURE_CACHE_SIZE, DEFAULT_SCRIPT_CACHE_SIZE)
+	}
+}
+
+/// Transaction signature checker, which skips verification of cached signatures
+pub struct CachingSignatureChecker<'a> {
+	checker: TransactionSignatureChecker<&'a TransactionInputSigner>,
+	cache: &'a SignatureCache,
+	/// Cache valid signatures? Only memory pool stores signatures, block verification only reads them.
+	store: bool,
+}
+
+impl<'a> CachingSignatureChecker<'a> {
+	pub fn new(checker: TransactionSignatureChecker<&'a TransactionInputSigner>, cache: &'a SignatureCache, store: bool) -> Self {
+		CachingSignatureChecker {
+			checker: checker,
+			cache: cache,
+			store: store,
+		}
+	}
+}
+
+impl<'a> SignatureChecker for CachingSignatureChecker<'a> {
+	fn verify_signature(&self, signature: &Signature, public: &Public, hash: &Message) -> bool {
+		if self.cache.contains(signature, public, hash) {
+			return true;
+		}
+
+		let is_valid = self.checker.verify_signature(signature, public, hash);
+		if is_valid && self.store {
+			self.cache.insert(signature, public, hash);
+		}
+		is_valid
+	}
+
+	fn check_signature(
+		&self,
+		signature: &Signature,
+		public: &Public,
+		script_code: &Script,
+		sighashtype: u32,
+		version: SignatureVersion
+	) -> bool {
+		let hash = self.checker.signer.signature_hash(self.checker.input_index, self.checker.input_amount, script_code, version, sighashtype);
+		self.verify_signature(signature, public, &hash)
+	}
+
+	fn check_lock_time(&self, lock_time: Num) -> bool {
+		self.checker.check_lock_time(lock_time)
+	}
+
+	fn check_sequence(&self, sequence: Num) -> bool {
+		self.checker.check_sequence(sequence)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use keys::{KeyPair, Private, Network};
+	use hash::H256;
+	use script::VerificationFlags;
+	use super::{SignatureCache, ScriptCache};
+
+	#[test]
+	fn signature_cache_works() {
+		let key_pair = KeyPair::from_private(Private {
+			network: Network::Testnet,
+			secret: "88d3dad1b9bc0e2cbcf0f5ae5e75f2c3e06d9e9fc7d72ba8b08ef7e16ebc4fdb".into(),
+			compressed: true,
+		}).unwrap();
+		let message: H256 = 1.into();
+		let signature = key_pair.private().sign(&message).unwrap();
+
+		let cache = SignatureCache::new(1);
+		assert!(!cache.contains(&signature, key_pair.public(), &message));
+		cache.insert(&signature, key_pair.public(), &message);
+		assert!(cache.contains(&signature, key_pair.public(), &message));
+		assert!(!cache.contains(&signature, key_pair.public(), &2.into()));
+
+		// the oldest entry is evicted when capacity is reached
+		cache.insert(&signature, key_pair.public(), &2.into());
+		assert!(!cache.contains(&signature, key_pair.public(), &message));
+	}
+
+	#[test]
+	fn signature_cache_is_salted() {
+		let key_pair = KeyPair::from_private(Private {
+			network: Network::Testnet,
+			secret: "88d3dad1b9bc0e2cbcf0f5ae5e75f2c3e06d9e9fc7d72ba8b08ef7e16ebc4fdb".into(),
+			compressed: true,
+		}).unwrap();
+		let message: H256 = 1.into();
+		let signature = key_pair.private().sign(&message).unwrap();
+
+		let cache1 = SignatureCache::new(1);
+		let cache2 = SignatureCache::new(1);
+		assert!(cache1.key(&signature, key_pair.public(), &message) != cache2.key(&signature, key_pair.public(), &message));
+	}
+
+	#[test]
+	fn script_cache_is_keyed_by_flags() {
+		let cache = ScriptCache::new(10);
+		cache.insert(1.into(), VerificationFlags::default().verify_p2sh(true));
+		assert!(cache.contains(&1.into(), &VerificationFlags::default().verify_p2sh(true)));
+		assert!(!cache.contains(&1.into(), &VerificationFlags::default()));
+		assert!(!cache.contains(&2.into(), &VerificationFlags::default().verify_p2sh(true)));
+	}
+}
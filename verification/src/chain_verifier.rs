@@ -1,10 +1,14 @@
 //! Bitcoin chain verifier
 
+use std::cmp;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use hash::H256;
 use chain::{IndexedBlock, IndexedBlockHeader, BlockHeader, IndexedTransaction};
 use storage::{SharedStore, TransactionOutputProvider, BlockHeaderProvider, BlockOrigin,
-	DuplexTransactionOutputProvider, NoopStore, CachedTransactionOutputProvider};
+	DuplexTransactionOutputProvider, NoopStore, CachedTransactionOutputProvider, SideChainOrigin,
+	Error as DBError};
 use network::ConsensusParams;
+use script::ScriptTracer;
 use error::{Error, TransactionError};
 use canon::{CanonBlock, CanonTransaction};
 use verify_chain::ChainVerifier;
@@ -13,6 +17,7 @@ use verify_transaction::MemoryPoolTransactionVerifier;
 use accept_chain::ChainAcceptor;
 use accept_transaction::MemoryPoolTransactionAcceptor;
 use deployments::{Deployments, BlockDeployments};
+use cache::VerificationCache;
 use timestamp::median_timestamp_inclusive;
 use {Verify, VerificationLevel};
 
@@ -20,6 +25,8 @@ pub struct BackwardsCompatibleChainVerifier {
 	store: SharedStore,
 	consensus: ConsensusParams,
 	deployments: Deployments,
+	cache: VerificationCache,
+	script_pool: Option<ThreadPool>,
 }
 
 impl BackwardsCompatibleChainVerifier {
@@ -28,9 +35,25 @@ impl BackwardsCompatibleChainVerifier {
 			store: store,
 			consensus: consensus,
 			deployments: Deployments::new(),
+			cache: VerificationCache::default(),
+			script_pool: None,
 		}
 	}
 
+	/// Verify scripts of the block transactions using dedicated pool of given number of threads.
+	/// Scripts are verified by the global pool (one thread per CPU core) if number of threads is 0.
+	pub fn with_script_threads(mut self, threads: usize) -> Self {
+		self.script_pool = match threads {
+			0 => None,
+			threads => Some(ThreadPoolBuilder::new()
+				.num_threads(threads)
+				.thread_name(|index| format!("Script verification thread #{}", index))
+				.build()
+				.expect("Error creating script verification threads")),
+		};
+		self
+	}
+
 	fn verify_block(&self, verification_level: VerificationLevel, block: &IndexedBlock) -> Result<(), Error> {
 		if verification_level == VerificationLevel::NoVerification {
 			return Ok(());
@@ -73,6 +96,8 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					median_time_past,
 					&deployments,
+					&self.cache,
+					self.script_pool.as_ref(),
 				);
 				chain_acceptor.check()?;
 			},
@@ -93,6 +118,8 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					median_time_past,
 					&deployments,
+					&self.cache,
+					self.script_pool.as_ref(),
 				);
 				chain_acceptor.check()?;
 			},
@@ -113,6 +140,8 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					median_time_past,
 					&deployments,
+					&self.cache,
+					self.script_pool.as_ref(),
 				);
 				chain_acceptor.check()?;
 			},
@@ -122,6 +151,65 @@ impl BackwardsCompatibleChainVerifier {
 		Ok(())
 	}
 
+	/// Re-verifies `depth` best canon blocks.
+	///
+	/// Blocks are decanonized on top of a fork and then verified and canonized again one by one,
+	/// so the database itself is not modified. Returns the first invalid block with its error.
+	pub fn verify_canon_blocks(&self, verification_level: VerificationLevel, depth: u32) -> Result<(), (H256, Error)> {
+		// genesis block is never verified
+		let best_block = self.store.best_block();
+		let depth = cmp::min(depth, best_block.number);
+		if depth == 0 {
+			return Ok(());
+		}
+
+		let first_block_number = best_block.number - depth + 1;
+		let mut blocks = Vec::with_capacity(depth as usize);
+		for block_number in first_block_number..best_block.number + 1 {
+			let block = self.store.block(block_number.into())
+				.ok_or_else(|| (H256::default(), Error::Database(DBError::DatabaseError(format!("Canon block #{} is missing", block_number)))))?;
+			blocks.push(block);
+		}
+
+		let origin = SideChainOrigin {
+			ancestor: first_block_number - 1,
+			canonized_route: Vec::new(),
+			decanonized_route: blocks.iter().map(|block| block.hash().clone()).collect(),
+			block_number: first_block_number,
+		};
+		let fork = self.store.fork(origin).map_err(|err| (blocks[0].hash().clone(), err.into()))?;
+
+		let current_time = ::time::get_time().sec as u32;
+		for (block, block_number) in blocks.iter().zip(first_block_number..) {
+			let chain_verifier = ChainVerifier::new(block, self.consensus.network, current_time);
+			chain_verifier.check().map_err(|err| (block.hash().clone(), err))?;
+
+			let median_time_past = median_timestamp_inclusive(block.header.raw.previous_header_hash.clone(), fork.store().as_block_header_provider());
+			let tx_out_provider = CachedTransactionOutputProvider::new(fork.store().as_transaction_output_provider());
+			let tx_meta_provider = fork.store().as_transaction_meta_provider();
+			let header_provider = fork.store().as_block_header_provider();
+			let deployments = BlockDeployments::new(&self.deployments, block_number, header_provider, &self.consensus);
+			let chain_acceptor = ChainAcceptor::new(
+				&tx_out_provider,
+				tx_meta_provider,
+				header_provider,
+				&self.consensus,
+				verification_level,
+				CanonBlock::new(block),
+				block_number,
+				median_time_past,
+				&deployments,
+				&self.cache,
+				self.script_pool.as_ref(),
+			);
+			chain_acceptor.check().map_err(|err| (block.hash().clone(), err))?;
+
+			fork.store().canonize(block.hash()).map_err(|err| (block.hash().clone(), err.into()))?;
+		}
+
+		Ok(())
+	}
+
 	pub fn verify_block_header(
 		&self,
 		_block_header_provider: &dyn BlockHeaderProvider,
@@ -153,11 +241,7 @@ impl BackwardsCompatibleChainVerifier {
 		// now let's do full verification
 		let noop = NoopStore;
 		let output_store = DuplexTransactionOutputProvider::new(prevout_provider, &noop);
-		let previous_block_number = height.checked_sub(1)
-			.expect("height is the height of future block of new tx; genesis block can't be in the future; qed");
-		let previous_block_header = block_header_provider.block_header(previous_block_number.into())
-			.expect("blocks up to height should be in db; qed");
-		let median_time_past = median_timestamp_inclusive(previous_block_header.hash, block_header_provider);
+		let median_time_past = self.median_time_past(block_header_provider, height);
 		let tx_acceptor = MemoryPoolTransactionAcceptor::new(
 			self.store.as_transaction_meta_provider(),
 			output_store,
@@ -167,9 +251,49 @@ impl BackwardsCompatibleChainVerifier {
 			time,
 			median_time_past,
 			&deployments,
+			&self.cache,
 		);
 		tx_acceptor.check()
 	}
+
+	/// Replays scripts of the transaction input with flags of the block at given height,
+	/// reporting every evaluated opcode to the tracer.
+	pub fn trace_transaction_input(
+		&self,
+		block_header_provider: &dyn BlockHeaderProvider,
+		prevout_provider: &dyn TransactionOutputProvider,
+		height: u32,
+		time: u32,
+		transaction: &IndexedTransaction,
+		input_index: usize,
+		tracer: &mut dyn ScriptTracer,
+	) -> Result<(), TransactionError> {
+		let deployments = BlockDeployments::new(&self.deployments, height, block_header_provider, &self.consensus);
+		let canon_tx = CanonTransaction::new(&transaction);
+		let noop = NoopStore;
+		let output_store = DuplexTransactionOutputProvider::new(prevout_provider, &noop);
+		let median_time_past = self.median_time_past(block_header_provider, height);
+		let tx_acceptor = MemoryPoolTransactionAcceptor::new(
+			self.store.as_transaction_meta_provider(),
+			output_store,
+			&self.consensus,
+			canon_tx,
+			height,
+			time,
+			median_time_past,
+			&deployments,
+			&self.cache,
+		);
+		tx_acceptor.eval.trace_input(input_index, tracer)
+	}
+
+	fn median_time_past(&self, block_header_provider: &dyn BlockHeaderProvider, height: u32) -> u32 {
+		let previous_block_number = height.checked_sub(1)
+			.expect("height is the height of future block of new tx; genesis block can't be in the future; qed");
+		let previous_block_header = block_header_provider.block_header(previous_block_number.into())
+			.expect("blocks up to height should be in db; qed");
+		median_timestamp_inclusive(previous_block_header.hash, block_header_provider)
+	}
 }
 
 impl Verify for BackwardsCompatibleChainVerifier {
@@ -262,6 +386,96 @@ mod tests {
 		assert_eq!(expected, verifier.verify(VerificationLevel::Full, &block.into()));
 	}
 
+	#[test]
+	fn verify_canon_blocks() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+			test_data::block_h0().into(),
+			test_data::block_h1().into(),
+			test_data::block_h2().into(),
+		]));
+		let verifier = ChainVerifier::new(storage.clone(), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore));
+		assert_eq!(verifier.verify_canon_blocks(VerificationLevel::Full, 10), Ok(()));
+		// database is not modified
+		assert_eq!(storage.best_block().number, 2);
+		assert_eq!(storage.best_block().hash, test_data::block_h2().hash());
+	}
+
+	#[test]
+	fn verify_canon_blocks_finds_invalid_block() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.merkled_header().build()
+			.build();
+		let genesis_coinbase = genesis.transactions()[0].hash();
+		let block = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.input().hash(genesis_coinbase).build()
+				.output().value(2).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+
+		// immature coinbase spend is written bypassing verification
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.into(), block.clone().into()]);
+		let verifier = ChainVerifier::new(Arc::new(storage), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore));
+
+		let expected = Err((block.hash(), Error::Transaction(1, TransactionError::Maturity)));
+		assert_eq!(expected, verifier.verify_canon_blocks(VerificationLevel::Full, 1));
+	}
+
+	#[test]
+	fn first_failing_input_is_reported() {
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.output().value(10).script_pubkey("51").build()
+				.output().value(10).script_pubkey("00").build()
+				.output().value(10).script_pubkey("51").build()
+				.output().value(10).script_pubkey("00").build()
+				.build()
+			.merkled_header().build()
+			.build();
+
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]);
+		let reference_tx = genesis.transactions()[1].hash();
+
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(2).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx.clone()).index(0).build()
+				.input().hash(reference_tx.clone()).index(1).build()
+				.output().value(10).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx.clone()).index(2).build()
+				.input().hash(reference_tx).index(3).build()
+				.output().value(10).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build()
+			.into();
+
+		let expected = Err(Error::Transaction(1, TransactionError::Signature(1, script::Error::EvalFalse)));
+		let verifier = ChainVerifier::new(Arc::new(storage), ConsensusParams::new(Network::Unitest, ConsensusFork::BitcoinCore))
+			.with_script_threads(4);
+		for _ in 0..10 {
+			assert_eq!(expected, verifier.verify(VerificationLevel::Full, &block));
+		}
+	}
+
 	#[test]
 	fn non_coinbase_happy() {
 		let genesis = test_data::block_builder()
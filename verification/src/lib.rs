@@ -58,6 +58,8 @@ extern crate lazy_static;
 extern crate log;
 extern crate parking_lot;
 extern crate rayon;
+extern crate rand;
+extern crate lru_cache;
 
 extern crate storage;
 extern crate chain;
@@ -65,14 +67,17 @@ extern crate network;
 extern crate primitives;
 extern crate serialization as ser;
 extern crate script;
+extern crate keys;
 extern crate bitcrypto as crypto;
 #[cfg(test)]
 extern crate db;
 
 pub mod constants;
+mod cache;
 mod canon;
 mod deployments;
 mod error;
+mod policy;
 mod sigops;
 mod timestamp;
 mod work;
@@ -95,6 +100,7 @@ mod chain_verifier;
 
 pub use primitives::{bigint, hash, compact};
 
+pub use cache::{VerificationCache, SignatureCache, ScriptCache, DEFAULT_SIGNATURE_CACHE_SIZE, DEFAULT_SCRIPT_CACHE_SIZE};
 pub use canon::{CanonBlock, CanonHeader, CanonTransaction};
 pub use accept_block::BlockAcceptor;
 pub use accept_chain::ChainAcceptor;
@@ -108,9 +114,10 @@ pub use verify_transaction::{TransactionVerifier, MemoryPoolTransactionVerifier}
 
 pub use chain_verifier::BackwardsCompatibleChainVerifier;
 pub use error::{Error, TransactionError};
+pub use policy::{Policy, PolicyError, PackageError, check_package, transaction_weight, transaction_vsize, MAX_PACKAGE_COUNT};
 pub use sigops::transaction_sigops;
 pub use timestamp::{median_timestamp, median_timestamp_inclusive};
-pub use work::{work_required, is_valid_proof_of_work, is_valid_proof_of_work_hash, block_reward_satoshi};
+pub use work::{work_required, is_valid_proof_of_work, is_valid_proof_of_work_hash, block_reward_satoshi, block_work};
 pub use deployments::Deployments;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -0,0 +1,552 @@
+//! Transaction relay policy
+//!
+//! Standardness rules are not part of consensus. Transaction that breaks them is still
+//! valid if it is mined, but it is neither accepted to the memory pool, nor relayed.
+
+use std::collections::HashSet;
+use chain::{Transaction, TransactionOutput};
+use network::ConsensusFork;
+use ser::{Serializable, SERIALIZE_TRANSACTION_WITNESS};
+use storage::TransactionOutputProvider;
+use script::{Script, ScriptType};
+
+/// Default min fee rate (in satoshis per kvB) of relayed transactions
+pub const DEFAULT_MIN_RELAY_TX_FEE: u64 = 1_000;
+/// Default fee rate (in satoshis per kvB), used to compute dust threshold
+pub const DUST_RELAY_TX_FEE: u64 = 3_000;
+/// Default max size of the relayed null data script (80 bytes of data + 3 bytes of opcodes)
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+/// Max weight of the standard transaction
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+/// Max version of the standard transaction
+pub const MAX_STANDARD_VERSION: i32 = 2;
+/// Min size of the standard transaction, serialized without witness
+pub const MIN_STANDARD_TX_NONWITNESS_SIZE: usize = 65;
+/// Max size of the standard script sig (large enough for 15-of-15 P2SH multisig)
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1_650;
+/// Max number of sigops in the standard P2SH redeem script
+pub const MAX_P2SH_SIGOPS: usize = 15;
+/// Max number of keys in the standard bare multisig script
+pub const MAX_STANDARD_BARE_MULTISIG_KEYS: u8 = 3;
+/// Max size of the standard P2WSH witness script
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3_600;
+/// Max number of the standard P2WSH witness stack items (witness script excluded)
+pub const MAX_STANDARD_P2WSH_STACK_ITEMS: usize = 100;
+/// Max size of the standard P2WSH witness stack item (witness script excluded)
+pub const MAX_STANDARD_P2WSH_STACK_ITEM_SIZE: usize = 80;
+/// Max number of transactions in the package
+pub const MAX_PACKAGE_COUNT: usize = 25;
+/// Max total weight of the package transactions
+pub const MAX_PACKAGE_WEIGHT: usize = 404_000;
+/// First byte of the taproot annex
+const ANNEX_TAG: u8 = 0x50;
+
+#[derive(Debug, PartialEq)]
+/// Transaction relay policy violations
+pub enum PolicyError {
+	/// Transaction version is not standard
+	Version,
+	/// Transaction weight exceeds `MAX_STANDARD_TX_WEIGHT`
+	TxSize,
+	/// Transaction is smaller than `MIN_STANDARD_TX_NONWITNESS_SIZE`
+	TxSizeSmall,
+	/// Script sig of given input exceeds `MAX_STANDARD_SCRIPTSIG_SIZE`
+	ScriptSigSize(usize),
+	/// Script sig of given input contains non-push opcodes
+	ScriptSigNotPushOnly(usize),
+	/// Script pubkey of given output is not standard
+	ScriptPubKey(usize),
+	/// Value of given output is below the dust threshold
+	Dust(usize),
+	/// Transaction has more than one null data output
+	MultiOpReturn,
+	/// Given input spends non-standard output
+	NonStandardInputs(usize),
+	/// Witness of given input is not standard
+	NonStandardWitness(usize),
+	/// Transaction fee is below the min relay fee
+	MinRelayFee { fee: u64, required: u64 },
+}
+
+impl PolicyError {
+	/// Short reason of the rejection, as it is reported to peers
+	pub fn reason(&self) -> &'static str {
+		match *self {
+			PolicyError::Version => "version",
+			PolicyError::TxSize => "tx-size",
+			PolicyError::TxSizeSmall => "tx-size-small",
+			PolicyError::ScriptSigSize(_) => "scriptsig-size",
+			PolicyError::ScriptSigNotPushOnly(_) => "scriptsig-not-pushonly",
+			PolicyError::ScriptPubKey(_) => "scriptpubkey",
+			PolicyError::Dust(_) => "dust",
+			PolicyError::MultiOpReturn => "multi-op-return",
+			PolicyError::NonStandardInputs(_) => "bad-txns-nonstandard-inputs",
+			PolicyError::NonStandardWitness(_) => "bad-witness-nonstandard",
+			PolicyError::MinRelayFee { .. } => "min relay fee not met",
+		}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+/// Package relay policy violations
+pub enum PackageError {
+	/// Package has more than `MAX_PACKAGE_COUNT` transactions
+	TooManyTransactions,
+	/// Package weight exceeds `MAX_PACKAGE_WEIGHT`
+	TooLarge,
+	/// Package contains the same transaction twice
+	Duplicates,
+	/// Transaction spends output of the later package transaction
+	NotSorted,
+	/// Package transactions spend the same output
+	Conflict,
+	/// Package is not a child transaction with its parents
+	NotChildWithParents,
+}
+
+impl PackageError {
+	/// Short reason of the rejection, as it is reported to peers
+	pub fn reason(&self) -> &'static str {
+		match *self {
+			PackageError::TooManyTransactions => "package-too-many-transactions",
+			PackageError::TooLarge => "package-too-large",
+			PackageError::Duplicates => "package-contains-duplicates",
+			PackageError::NotSorted => "package-not-sorted",
+			PackageError::Conflict => "conflict-in-package",
+			PackageError::NotChildWithParents => "package-not-child-with-parents",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Transaction relay policy
+pub struct Policy {
+	/// Accept non-standard transactions. Fee is still checked.
+	pub accept_non_std_txn: bool,
+	/// Min fee rate (in satoshis per kvB) of relayed transactions
+	pub min_relay_fee: u64,
+	/// Fee rate (in satoshis per kvB), used to compute dust threshold
+	pub dust_relay_fee: u64,
+	/// Relay transactions with null data outputs
+	pub data_carrier: bool,
+	/// Max size of the relayed null data script
+	pub max_data_carrier_bytes: usize,
+}
+
+impl Default for Policy {
+	fn default() -> Self {
+		Policy {
+			accept_non_std_txn: false,
+			min_relay_fee: DEFAULT_MIN_RELAY_TX_FEE,
+			dust_relay_fee: DUST_RELAY_TX_FEE,
+			data_carrier: true,
+			max_data_carrier_bytes: MAX_OP_RETURN_RELAY,
+		}
+	}
+}
+
+impl Policy {
+	/// Policy that accepts any valid transaction
+	pub fn permissive() -> Self {
+		Policy {
+			accept_non_std_txn: true,
+			min_relay_fee: 0,
+			..Default::default()
+		}
+	}
+
+	/// Checks transaction standardness without looking at spent outputs
+	pub fn check_transaction(&self, transaction: &Transaction) -> Result<(), PolicyError> {
+		if self.accept_non_std_txn {
+			return Ok(());
+		}
+
+		if transaction.version < 1 || transaction.version > MAX_STANDARD_VERSION {
+			return Err(PolicyError::Version);
+		}
+
+		if transaction_weight(transaction) > MAX_STANDARD_TX_WEIGHT {
+			return Err(PolicyError::TxSize);
+		}
+
+		if transaction.serialized_size() < MIN_STANDARD_TX_NONWITNESS_SIZE {
+			return Err(PolicyError::TxSizeSmall);
+		}
+
+		for (index, input) in transaction.inputs.iter().enumerate() {
+			if input.script_sig.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
+				return Err(PolicyError::ScriptSigSize(index));
+			}
+
+			if !Script::new(input.script_sig.clone()).is_push_only() {
+				return Err(PolicyError::ScriptSigNotPushOnly(index));
+			}
+		}
+
+		let mut null_data_outputs = 0;
+		for (index, output) in transaction.outputs.iter().enumerate() {
+			let script_pubkey = Script::new(output.script_pubkey.clone());
+			match script_pubkey.script_type() {
+				ScriptType::NullData => {
+					if !self.data_carrier || script_pubkey.len() > self.max_data_carrier_bytes {
+						return Err(PolicyError::ScriptPubKey(index));
+					}
+
+					null_data_outputs += 1;
+					continue;
+				},
+				ScriptType::Multisig => {
+					let required = script_pubkey.num_signatures_required();
+					let keys = multisig_keys(&script_pubkey);
+					if required < 1 || keys > MAX_STANDARD_BARE_MULTISIG_KEYS {
+						return Err(PolicyError::ScriptPubKey(index));
+					}
+				},
+				ScriptType::NonStandard if script_pubkey.parse_witness_program().is_none() => {
+					return Err(PolicyError::ScriptPubKey(index));
+				},
+				_ => (),
+			}
+
+			if self.is_dust(output) {
+				return Err(PolicyError::Dust(index));
+			}
+		}
+
+		if null_data_outputs > 1 {
+			return Err(PolicyError::MultiOpReturn);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that transaction spends standard outputs and has standard witnesses
+	pub fn check_inputs(&self, transaction: &Transaction, store: &dyn TransactionOutputProvider) -> Result<(), PolicyError> {
+		if self.accept_non_std_txn || transaction.is_coinbase() {
+			return Ok(());
+		}
+
+		for (index, input) in transaction.inputs.iter().enumerate() {
+			// missing outputs are reported by the consensus checks
+			let previous_output = match store.transaction_output(&input.previous_output, usize::max_value()) {
+				Some(output) => output,
+				None => continue,
+			};
+
+			let script_sig = Script::new(input.script_sig.clone());
+			let mut script_pubkey = Script::new(previous_output.script_pubkey);
+			match script_pubkey.script_type() {
+				ScriptType::NonStandard => match script_pubkey.parse_witness_program() {
+					Some((0, program)) if program.len() == 20 || program.len() == 32 => (),
+					Some((1, program)) if program.len() == 32 => (),
+					_ => return Err(PolicyError::NonStandardInputs(index)),
+				},
+				ScriptType::ScriptHash => {
+					let redeem_script = match redeem_script(&script_sig) {
+						Some(redeem_script) => redeem_script,
+						None => return Err(PolicyError::NonStandardInputs(index)),
+					};
+					if redeem_script.sigops_count(false, true) > MAX_P2SH_SIGOPS {
+						return Err(PolicyError::NonStandardInputs(index));
+					}
+
+					script_pubkey = redeem_script;
+				},
+				_ => (),
+			}
+
+			if input.script_witness.is_empty() {
+				continue;
+			}
+
+			let witness = &input.script_witness;
+			match script_pubkey.parse_witness_program() {
+				Some((0, program)) if program.len() == 32 => {
+					let (witness_script, stack) = witness.split_last().expect("witness is not empty; qed");
+					if witness_script.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE ||
+						stack.len() > MAX_STANDARD_P2WSH_STACK_ITEMS ||
+						stack.iter().any(|item| item.len() > MAX_STANDARD_P2WSH_STACK_ITEM_SIZE) {
+						return Err(PolicyError::NonStandardWitness(index));
+					}
+				},
+				Some((1, program)) if program.len() == 32 => {
+					let has_annex = witness.len() >= 2 && witness[witness.len() - 1].first() == Some(&ANNEX_TAG);
+					if has_annex {
+						return Err(PolicyError::NonStandardWitness(index));
+					}
+				},
+				Some(_) => (),
+				None => return Err(PolicyError::NonStandardWitness(index)),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Checks that transaction pays at least the min relay fee
+	pub fn check_fee(&self, fee: u64, vsize: usize) -> Result<(), PolicyError> {
+		let required = self.min_relay_fee * vsize as u64 / 1000;
+		match fee < required {
+			true => Err(PolicyError::MinRelayFee { fee: fee, required: required }),
+			false => Ok(()),
+		}
+	}
+
+	/// Returns min value of the output, which costs less to spend than its value
+	pub fn dust_threshold(&self, output: &TransactionOutput) -> u64 {
+		let script_pubkey = Script::new(output.script_pubkey.clone());
+		if script_pubkey.is_null_data_script() {
+			return 0;
+		}
+
+		// outpoint + script sig length + sequence, plus the size of the spending script sig or witness
+		let spend_size = match script_pubkey.parse_witness_program() {
+			Some(_) => 32 + 4 + 1 + 107 / ConsensusFork::witness_scale_factor() + 4,
+			None => 32 + 4 + 1 + 107 + 4,
+		};
+		let size = output.serialized_size() + spend_size;
+		self.dust_relay_fee * size as u64 / 1000
+	}
+
+	/// Returns true if value of the output is below the dust threshold
+	pub fn is_dust(&self, output: &TransactionOutput) -> bool {
+		output.value < self.dust_threshold(output)
+	}
+}
+
+/// Returns transaction weight
+pub fn transaction_weight(transaction: &Transaction) -> usize {
+	let base_size = transaction.serialized_size();
+	let total_size = transaction.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS);
+	base_size * (ConsensusFork::witness_scale_factor() - 1) + total_size
+}
+
+/// Returns transaction virtual size
+pub fn transaction_vsize(transaction: &Transaction) -> usize {
+	let scale = ConsensusFork::witness_scale_factor();
+	(transaction_weight(transaction) + scale - 1) / scale
+}
+
+/// Checks package limits and topology. Package must be a child transaction, preceded by its parents.
+/// Parents are sorted, so that every transaction is placed after all package transactions it spends.
+pub fn check_package(package: &[Transaction]) -> Result<(), PackageError> {
+	if package.len() > MAX_PACKAGE_COUNT {
+		return Err(PackageError::TooManyTransactions);
+	}
+
+	if package.iter().map(transaction_weight).sum::<usize>() > MAX_PACKAGE_WEIGHT {
+		return Err(PackageError::TooLarge);
+	}
+
+	let hashes: Vec<_> = package.iter().map(Transaction::hash).collect();
+	if hashes.iter().collect::<HashSet<_>>().len() != hashes.len() {
+		return Err(PackageError::Duplicates);
+	}
+
+	let mut spent_outputs = HashSet::new();
+	for (index, transaction) in package.iter().enumerate() {
+		for input in &transaction.inputs {
+			if hashes[index..].contains(&input.previous_output.hash) {
+				return Err(PackageError::NotSorted);
+			}
+
+			if !spent_outputs.insert(&input.previous_output) {
+				return Err(PackageError::Conflict);
+			}
+		}
+	}
+
+	let (child, parents) = match package.split_last() {
+		Some(split) => split,
+		None => return Err(PackageError::NotChildWithParents),
+	};
+	let is_child_with_parents = hashes[..parents.len()].iter()
+		.all(|parent| child.inputs.iter().any(|input| &input.previous_output.hash == parent));
+	match is_child_with_parents {
+		true => Ok(()),
+		false => Err(PackageError::NotChildWithParents),
+	}
+}
+
+/// Returns number of keys in the multisig script
+fn multisig_keys(script: &Script) -> u8 {
+	match script.get_opcode(script.len() - 2) {
+		Ok(opcode) if opcode.is_within_op_n() => opcode.decode_op_n(),
+		_ => 0,
+	}
+}
+
+/// Returns redeem script, which is the last push of the push-only script sig
+fn redeem_script(script_sig: &Script) -> Option<Script> {
+	if !script_sig.is_push_only() {
+		return None;
+	}
+
+	match script_sig.iter().last() {
+		Some(Ok(instruction)) => instruction.data.map(|data| Script::new(data.to_vec().into())),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use chain::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+	use storage::TransactionOutputProvider;
+	use script::{Builder, Opcode};
+	use super::*;
+
+	struct Outputs(HashMap<OutPoint, TransactionOutput>);
+
+	impl TransactionOutputProvider for Outputs {
+		fn transaction_output(&self, outpoint: &OutPoint, _transaction_index: usize) -> Option<TransactionOutput> {
+			self.0.get(outpoint).cloned()
+		}
+
+		fn is_spent(&self, _outpoint: &OutPoint) -> bool {
+			false
+		}
+	}
+
+	fn p2pkh_output(value: u64) -> TransactionOutput {
+		TransactionOutput {
+			value: value,
+			script_pubkey: Builder::build_p2pkh(&[1u8; 20].into()).to_bytes(),
+		}
+	}
+
+	fn transaction(outputs: Vec<TransactionOutput>) -> Transaction {
+		Transaction {
+			version: 2,
+			inputs: vec![TransactionInput {
+				previous_output: OutPoint { hash: [1u8; 32].into(), index: 0 },
+				script_sig: Builder::default().push_data(&[0u8; 72]).push_data(&[2u8; 33]).into_bytes(),
+				sequence: 0xffffffff,
+				script_witness: vec![],
+			}],
+			outputs: outputs,
+			lock_time: 0,
+		}
+	}
+
+	#[test]
+	fn test_standard_transaction() {
+		let policy = Policy::default();
+		assert_eq!(policy.check_transaction(&transaction(vec![p2pkh_output(100_000)])), Ok(()));
+
+		let mut tx = transaction(vec![p2pkh_output(100_000)]);
+		tx.version = 3;
+		assert_eq!(policy.check_transaction(&tx), Err(PolicyError::Version));
+		assert_eq!(Policy::permissive().check_transaction(&tx), Ok(()));
+
+		let mut tx = transaction(vec![p2pkh_output(100_000)]);
+		tx.inputs[0].script_sig = Builder::default().push_opcode(Opcode::OP_DUP).push_data(&[0u8; 72]).into_bytes();
+		assert_eq!(policy.check_transaction(&tx), Err(PolicyError::ScriptSigNotPushOnly(0)));
+
+		let nonstandard = TransactionOutput {
+			value: 100_000,
+			script_pubkey: Builder::default().push_opcode(Opcode::OP_1).into_bytes(),
+		};
+		let tx = transaction(vec![p2pkh_output(100_000), nonstandard]);
+		assert_eq!(policy.check_transaction(&tx), Err(PolicyError::ScriptPubKey(1)));
+	}
+
+	#[test]
+	fn test_dust_threshold() {
+		let policy = Policy::default();
+		// 34 bytes of P2PKH output + 148 bytes of the spending input
+		assert_eq!(policy.dust_threshold(&p2pkh_output(0)), 546);
+		let p2wpkh = TransactionOutput {
+			value: 0,
+			script_pubkey: Builder::build_p2wpkh(&[1u8; 20].into()).to_bytes(),
+		};
+		assert_eq!(policy.dust_threshold(&p2wpkh), 294);
+
+		let tx = transaction(vec![p2pkh_output(545)]);
+		assert_eq!(policy.check_transaction(&tx), Err(PolicyError::Dust(0)));
+		let tx = transaction(vec![p2pkh_output(546)]);
+		assert_eq!(policy.check_transaction(&tx), Ok(()));
+	}
+
+	#[test]
+	fn test_data_carrier() {
+		let null_data = |len: usize| TransactionOutput {
+			value: 0,
+			script_pubkey: Builder::default().push_opcode(Opcode::OP_RETURN).push_data(&vec![0u8; len]).into_bytes(),
+		};
+
+		let policy = Policy::default();
+		assert_eq!(policy.check_transaction(&transaction(vec![p2pkh_output(100_000), null_data(80)])), Ok(()));
+		assert_eq!(policy.check_transaction(&transaction(vec![p2pkh_output(100_000), null_data(81)])), Err(PolicyError::ScriptPubKey(1)));
+		assert_eq!(policy.check_transaction(&transaction(vec![null_data(10), null_data(10)])), Err(PolicyError::MultiOpReturn));
+
+		let policy = Policy { data_carrier: false, ..Default::default() };
+		assert_eq!(policy.check_transaction(&transaction(vec![p2pkh_output(100_000), null_data(10)])), Err(PolicyError::ScriptPubKey(1)));
+		let policy = Policy { max_data_carrier_bytes: 200, ..Default::default() };
+		assert_eq!(policy.check_transaction(&transaction(vec![p2pkh_output(100_000), null_data(160)])), Ok(()));
+	}
+
+	#[test]
+	fn test_standard_inputs() {
+		let tx = transaction(vec![p2pkh_output(100_000)]);
+		let outpoint = tx.inputs[0].previous_output.clone();
+		let spent = |script_pubkey: Script| {
+			let mut outputs = HashMap::new();
+			outputs.insert(outpoint.clone(), TransactionOutput { value: 200_000, script_pubkey: script_pubkey.to_bytes() });
+			Outputs(outputs)
+		};
+
+		let policy = Policy::default();
+		assert_eq!(policy.check_inputs(&tx, &spent(Builder::build_p2pkh(&[1u8; 20].into()))), Ok(()));
+		assert_eq!(policy.check_inputs(&tx, &spent(Builder::default().push_opcode(Opcode::OP_1).into_script())),
+			Err(PolicyError::NonStandardInputs(0)));
+		// P2SH redeem script with too many sigops
+		assert_eq!(policy.check_inputs(&tx, &spent(Builder::build_p2sh(&[1u8; 20].into()))), Ok(()));
+		let mut p2sh_tx = tx.clone();
+		let redeem_script = (0..16).fold(Builder::default(), |builder, _| builder.push_opcode(Opcode::OP_CHECKSIG)).into_bytes();
+		p2sh_tx.inputs[0].script_sig = Builder::default().push_data(&redeem_script).into_bytes();
+		assert_eq!(policy.check_inputs(&p2sh_tx, &spent(Builder::build_p2sh(&[1u8; 20].into()))),
+			Err(PolicyError::NonStandardInputs(0)));
+		// witness is only standard for witness programs
+		let mut witness_tx = tx.clone();
+		witness_tx.inputs[0].script_witness = vec![vec![0u8; 81].into(), vec![0u8; 10].into()];
+		assert_eq!(policy.check_inputs(&witness_tx, &spent(Builder::build_p2pkh(&[1u8; 20].into()))),
+			Err(PolicyError::NonStandardWitness(0)));
+		assert_eq!(policy.check_inputs(&witness_tx, &spent(Builder::build_p2wsh(&[1u8; 32].into()))),
+			Err(PolicyError::NonStandardWitness(0)));
+	}
+
+	#[test]
+	fn test_min_relay_fee() {
+		let policy = Policy::default();
+		assert_eq!(policy.check_fee(226, 226), Ok(()));
+		assert_eq!(policy.check_fee(225, 226), Err(PolicyError::MinRelayFee { fee: 225, required: 226 }));
+		assert_eq!(Policy::permissive().check_fee(0, 226), Ok(()));
+	}
+
+	#[test]
+	fn test_package_topology() {
+		let spend = |parents: &[&Transaction]| Transaction {
+			inputs: parents.iter().map(|parent| TransactionInput {
+				previous_output: OutPoint { hash: parent.hash(), index: 0 },
+				..Default::default()
+			}).collect(),
+			..transaction(vec![p2pkh_output(100_000)])
+		};
+
+		let parent1 = transaction(vec![p2pkh_output(100_000)]);
+		let mut parent2 = transaction(vec![p2pkh_output(200_000)]);
+		parent2.inputs[0].previous_output.index = 1;
+		let child = spend(&[&parent1, &parent2]);
+		assert_eq!(check_package(&[parent1.clone(), parent2.clone(), child.clone()]), Ok(()));
+		assert_eq!(check_package(&[child.clone()]), Ok(()));
+		assert_eq!(check_package(&[]), Err(PackageError::NotChildWithParents));
+		assert_eq!(check_package(&[parent1.clone(), child.clone(), parent2.clone()]), Err(PackageError::NotSorted));
+		assert_eq!(check_package(&[parent1.clone(), parent1.clone(), child.clone()]), Err(PackageError::Duplicates));
+		assert_eq!(check_package(&[parent1.clone(), parent2.clone(), spend(&[&parent1])]), Err(PackageError::NotChildWithParents));
+		// both children are spending the same output
+		assert_eq!(check_package(&[parent1.clone(), parent2.clone(), spend(&[&parent1, &parent2]), spend(&[&parent1])]),
+			Err(PackageError::Conflict));
+		assert_eq!(check_package(&vec![parent1.clone(); MAX_PACKAGE_COUNT + 1]), Err(PackageError::TooManyTransactions));
+	}
+}
@@ -1,7 +1,7 @@
 use std::cmp;
 use primitives::compact::Compact;
 use primitives::hash::H256;
-use primitives::bigint::U256;
+use primitives::bigint::{Uint, U256};
 use chain::IndexedBlockHeader;
 use network::{Network, ConsensusParams, ConsensusFork};
 use storage::{BlockHeaderProvider, BlockRef};
@@ -48,6 +48,20 @@ pub fn is_valid_proof_of_work(max_work_bits: Compact, bits: Compact, hash: &H256
 	target <= maximum && value <= target
 }
 
+/// Returns amount of work, represented by block with given compact bits
+pub fn block_work(bits: Compact) -> U256 {
+	let target = match bits.to_u256() {
+		Ok(ref target) if !target.is_zero() => *target,
+		_ => return U256::zero(),
+	};
+
+	// We need to compute 2**256 / (bnTarget+1), but we can't represent 2**256
+	// as it's too large for a arith_uint256. However, as 2**256 is at least as
+	// large as bnTarget+1, it is equal to ((2**256 - bnTarget - 1) /
+	// (bnTarget+1)) + 1, or ~bnTarget / (nTarget+1) + 1.
+	(!target / (target + U256::one())) + U256::one()
+}
+
 /// Returns constrained number of seconds since last retarget
 pub fn retarget_timespan(retarget_timestamp: u32, last_timestamp: u32) -> u32 {
 	// subtract unsigned 32 bit numbers in signed 64 bit space in
@@ -150,7 +164,8 @@ mod tests {
 	use primitives::hash::H256;
 	use primitives::compact::Compact;
 	use network::Network;
-	use super::{is_valid_proof_of_work_hash, is_valid_proof_of_work, block_reward_satoshi};
+	use primitives::bigint::{Uint, U256};
+	use super::{is_valid_proof_of_work_hash, is_valid_proof_of_work, block_reward_satoshi, block_work};
 
 	fn is_valid_pow(max: Compact, bits: u32, hash: &'static str) -> bool {
 		is_valid_proof_of_work_hash(bits.into(), &H256::from_reversed_str(hash)) &&
@@ -181,4 +196,11 @@ mod tests {
 		assert_eq!(block_reward_satoshi(630000), 625000000);
 		assert_eq!(block_reward_satoshi(630001), 625000000);
 	}
+
+	#[test]
+	fn test_block_work() {
+		// genesis block
+		assert_eq!(block_work(0x1d00ffffu32.into()), U256::from(0x100010001u64));
+		assert_eq!(block_work(0u32.into()), U256::zero());
+	}
 }
@@ -1,11 +1,11 @@
 use primitives::compact::Compact;
 use primitives::hash::H256;
-use primitives::bigint::{Uint, U256};
+use primitives::bigint::U256;
 use chain::IndexedBlockHeader;
 use network::{Network, ConsensusParams, BitcoinCashConsensusParams};
 use storage::BlockHeaderProvider;
 use timestamp::median_timestamp_inclusive;
-use work::{is_retarget_height, work_required_testnet, work_required_retarget};
+use work::{is_retarget_height, work_required_testnet, work_required_retarget, block_work};
 
 use constants::{
 	DOUBLE_SPACING_SECONDS, TARGET_SPACING_SECONDS, RETARGETING_INTERVAL
@@ -82,12 +82,7 @@ fn work_required_bitcoin_cash_adjusted(parent_header: IndexedBlockHeader, time:
 
 	/// Get block proof.
 	fn block_proof(header: &IndexedBlockHeader) -> U256 {
-		let proof: U256 = header.raw.bits.into();
-		// We need to compute 2**256 / (bnTarget+1), but we can't represent 2**256
-		// as it's too large for a arith_uint256. However, as 2**256 is at least as
-		// large as bnTarget+1, it is equal to ((2**256 - bnTarget - 1) /
-		// (bnTarget+1)) + 1, or ~bnTarget / (nTarget+1) + 1.
-		(!proof / (proof + U256::one())) + U256::one()
+		block_work(header.raw.bits)
 	}
 
 	/// Compute chain work between two blocks. Last block work is included. First block work is excluded.